@@ -7,12 +7,12 @@ use crate::wgpu_context_provider::EguiWgpuContextProvider;
 use eframe::{egui, CreationContext};
 use egui::load::SizedTexture;
 use egui::StrokeKind;
-use gosub_engine::cookies::SqliteCookieStore;
+use gosub_engine::profile::Profile;
 use gosub_engine::render::backend::ExternalHandle;
 use gosub_engine::render::backends::vello::WgpuContextProvider;
 use gosub_engine::render::Viewport;
-use gosub_engine::storage::{InMemorySessionStore, SqliteLocalStore, StorageService};
-use gosub_engine::zone::{ZoneConfig, ZoneId};
+use gosub_engine::storage::StorageService;
+use gosub_engine::zone::{SearchProvider, UrlResolver, ZoneConfig, ZoneId};
 use gosub_engine::{EngineCommand, EngineEvent, GosubEngine};
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -28,12 +28,7 @@ const DEFAULT_WIDTH: i32 = 800;
 const DEFAULT_HEIGHT: i32 = 600;
 
 fn current_url_for_tab(eng: &GosubEngine, tab_id: gosub_engine::tab::TabId) -> Option<Url> {
-    eng.get_tab(tab_id)
-        .unwrap()
-        .lock()
-        .unwrap()
-        .current_url
-        .clone()
+    eng.tab_state(tab_id)?.url()
 }
 
 struct GosubApp {
@@ -53,12 +48,9 @@ struct GosubApp {
 
 impl GosubApp {
     fn new(cc: &CreationContext) -> Self {
-        // Setup persistent cookie store
-        let cookie_store = SqliteCookieStore::new(".gosub-gtk-cookie-store.db".parse().unwrap());
-        let storage = Arc::new(StorageService::new(
-            Arc::new(SqliteLocalStore::new(".gosub-gtk-local-storage.db").unwrap()),
-            Arc::new(InMemorySessionStore::new()),
-        ));
+        // Persistent cookies and local storage
+        let profile = Profile::open(".gosub-egui-profile").expect("cannot open profile");
+        let storage = profile.storage();
 
         // Set up the engine with a null backend for now. We will update this once we have an egui context
         // so we can initialize the vello renderer properly.
@@ -77,9 +69,12 @@ impl GosubApp {
             .borrow_mut()
             .zone_builder()
             .id(ZoneId::from(DEFAULT_MAIN_ZONE))
-            .storage(storage.clone())
-            .cookie_store(cookie_store.clone())
+            .profile(&profile)
             .config(config)
+            .url_resolver(UrlResolver::new().with_search_provider(
+                SearchProvider::new("DuckDuckGo", "https://duckduckgo.com/?q=%s")
+                    .expect("search provider"),
+            ))
             .create()
             .expect("zone creation failed");
 
@@ -115,25 +110,13 @@ impl GosubApp {
     }
 
     fn handle_navigation(&mut self) {
-        let composed_url = self.current_url_input.clone();
-
-        // Check if composed_url starts with a scheme like http:// or https://
-        let url_str =
-            if !composed_url.starts_with("http://") && !composed_url.starts_with("https://") {
-                format!("https://{}", composed_url)
-            } else {
-                composed_url
-            };
-
-        let Ok(url) = Url::parse(&url_str) else {
-            return;
-        };
-
+        // The zone resolves the input, adding a scheme or searching for it as needed
+        let input = self.current_url_input.clone();
         let tab_id = *self.active_tab.borrow();
         let _ = self
             .engine
             .borrow_mut()
-            .execute_command(tab_id, EngineCommand::Navigate(url));
+            .execute_command(tab_id, EngineCommand::NavigateInput(input));
         self.needs_redraw = true;
     }
 
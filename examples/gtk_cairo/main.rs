@@ -3,11 +3,10 @@ use crate::tiling::{
     close_leaf, collect_leaves, compute_layout, find_leaf_at, split_leaf_into_cols,
     split_leaf_into_rows, LayoutHandle, LayoutNode, Rect,
 };
-use gosub_engine::cookies::SqliteCookieStore;
+use gosub_engine::profile::Profile;
 use gosub_engine::render::backend::ExternalHandle;
 use gosub_engine::render::Viewport;
-use gosub_engine::storage::{InMemorySessionStore, SqliteLocalStore, StorageService};
-use gosub_engine::zone::ZoneId;
+use gosub_engine::zone::{SearchProvider, UrlResolver, ZoneId};
 use gosub_engine::{EngineCommand, EngineEvent, GosubEngine};
 use gtk4::glib::clone;
 use gtk4::prelude::*;
@@ -18,7 +17,6 @@ use gtk4::{
 };
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::sync::Arc;
 use url::Url;
 
 mod compositor;
@@ -27,12 +25,7 @@ mod tiling;
 const DEFAULT_MAIN_ZONE: &str = "95d9c701-5f1b-43ea-ba7e-bc509ee8aa54";
 
 fn current_url_for_tab(eng: &GosubEngine, tab_id: gosub_engine::tab::TabId) -> Option<Url> {
-    eng.get_tab(tab_id)
-        .unwrap()
-        .lock()
-        .unwrap()
-        .current_url
-        .clone()
+    eng.tab_state(tab_id)?.url()
 }
 
 fn main() {
@@ -40,12 +33,9 @@ fn main() {
         .application_id("io.gosub.engine")
         .build();
 
-    // Persistent cookie store
-    let cookie_store = SqliteCookieStore::new(".gosub-gtk-cookie-store.db".parse().unwrap());
-    let storage = Arc::new(StorageService::new(
-        Arc::new(SqliteLocalStore::new(".gosub-gtk-local-storage.db").unwrap()),
-        Arc::new(InMemorySessionStore::new()),
-    ));
+    // Persistent cookies and local storage
+    let profile = Profile::open(".gosub-gtk-profile").expect("cannot open profile");
+    let storage = profile.storage();
 
     app.connect_activate(move |app| {
         let backend = gosub_engine::render::backends::cairo::CairoBackend::new();
@@ -55,8 +45,10 @@ fn main() {
         // Let's create our default zone
         let zone_id = engine.borrow_mut().zone_builder()
             .id(ZoneId::from(DEFAULT_MAIN_ZONE))
-            .storage(storage.clone())
-            .cookie_store(cookie_store.clone())
+            .profile(&profile)
+            .url_resolver(UrlResolver::new().with_search_provider(
+                SearchProvider::new("DuckDuckGo", "https://duckduckgo.com/?q=%s").expect("search provider"),
+            ))
             .create().expect("zone creation failed");
 
         // Start with 1 tab
@@ -405,20 +397,9 @@ fn main() {
         let active_entry = active_tab.clone();
         let draw_entry = drawing_area.clone();
         address_entry.connect_activate(clone!(@strong eng_entry, @strong active_entry, @strong draw_entry => move |entry| {
-            let composed_url = entry.text();
-
-            // Check if composed_url starts with a scheme like http:// or https://
-            if !composed_url.starts_with("http://") && !composed_url.starts_with("https://") {
-                // If not, prepend https://
-                entry.set_text(&format!("https://{}", composed_url));
-            }
-
-            let Ok(url) = Url::parse(&entry.text()) else {
-                return;
-            };
-
+            // The zone resolves the input, adding a scheme or searching for it as needed
             let tab_id = *active_entry.borrow();
-            let _ = eng_entry.borrow_mut().execute_command(tab_id, EngineCommand::Navigate(url));
+            let _ = eng_entry.borrow_mut().execute_command(tab_id, EngineCommand::NavigateInput(entry.text().to_string()));
             draw_entry.queue_draw();
         }));
 
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let (text, issues) = gosub_engine::fuzzing::decode_document(data);
+    // Every issue points into the decoded text
+    let lines = text.matches('\n').count() as u32 + 1;
+    assert!(issues.iter().all(|issue| issue.line <= lines));
+});
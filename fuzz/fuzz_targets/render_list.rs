@@ -0,0 +1,12 @@
+#![no_main]
+
+use gosub_engine::render::RenderList;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(list) = RenderList::from_bytes(data) {
+        let bytes = list.to_bytes();
+        let decoded = RenderList::from_bytes(&bytes).expect("encoded lists decode");
+        assert_eq!(decoded.to_bytes(), bytes);
+    }
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(cookie) = gosub_engine::fuzzing::parse_set_cookie(data) {
+        assert!(cookie.path.is_some());
+    }
+});
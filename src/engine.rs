@@ -2,13 +2,34 @@
 //!
 //! Most users should start with [`GosubEngine`].
 
+mod blocking;
 mod context;
 mod engine;
+mod engine_builder;
 mod errors;
 mod event;
+mod gesture;
+mod har;
+mod history;
+mod notification;
+#[cfg(test)]
+pub(crate) mod test_support;
+mod watchdog;
 mod zone_builder;
 
+pub mod activity;
 pub mod cookies;
+pub mod diagnostics;
+pub mod forms;
+#[cfg(feature = "multiprocess")]
+pub mod ipc;
+pub mod logging;
+#[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+pub mod profile;
+#[cfg(feature = "serde_events")]
+pub mod recording;
+pub mod scheduler;
+pub mod spellcheck;
 pub mod tab;
 pub mod tick;
 pub mod zone;
@@ -16,7 +37,19 @@ pub mod storage;
 
 pub mod config;
 
+pub use blocking::{BlockingEngineHandle, TabInput};
 pub use context::BrowsingContext;
 pub use engine::GosubEngine;
+pub use engine_builder::EngineBuilder;
 pub use errors::EngineError;
-pub use event::{EngineCommand, EngineEvent, MouseButton};
+pub(crate) use event::coalesce_into;
+pub use event::{
+    DragPayload, DropEffect, EngineCommand, EngineEvent, KeyModifiers, MouseButton,
+    NavigationDisposition, ZoneCommand,
+};
+pub(crate) use notification::NotificationBus;
+pub use notification::{
+    ConsoleLevel, EngineNotification, NotificationCategories, NotificationSubscription,
+    SequencedNotification, SequencedSubscription,
+};
+pub use watchdog::TabKiller;
@@ -0,0 +1,149 @@
+//! A log of what the engine was asked to do recently.
+//!
+//! When a tab misbehaves in the field, the question is usually what happened right before.
+//! With [`EngineConfig::activity_log_size`](crate::EngineConfig::activity_log_size) set, the
+//! engine keeps the last few tabs it opened, [`EngineEvent`]s, [`EngineCommand`]s and
+//! [`ZoneCommand`]s in memory, overwriting the oldest ones. Embedders can attach
+//! [`GosubEngine::dump_recent_activity`](crate::GosubEngine::dump_recent_activity) to a bug
+//! report without having set up logging first.
+//!
+//! Unlike a recording (see `GosubEngine::start_recording`), the log is bounded, needs no extra
+//! feature and cannot be replayed.
+//!
+//! ```
+//! use gosub_engine::render::backends::null::NullBackend;
+//! use gosub_engine::render::Viewport;
+//! use gosub_engine::{EngineConfig, EngineEvent, GosubEngine};
+//!
+//! let config = EngineConfig::builder().activity_log_size(2).build().unwrap();
+//! let mut engine = GosubEngine::new(Some(config), Box::new(NullBackend::new().unwrap()));
+//! let zone_id = engine.zone_builder().create().unwrap();
+//! let tab_id = engine.open_tab_in_zone(zone_id, Viewport::new(0, 0, 800, 600)).unwrap();
+//! engine.handle_event(tab_id, EngineEvent::Scroll { dx: 0.0, dy: 120.0 }).unwrap();
+//! engine.handle_event(tab_id, EngineEvent::Scroll { dx: 0.0, dy: -40.0 }).unwrap();
+//!
+//! // Only the last two entries are kept
+//! for entry in engine.dump_recent_activity() {
+//!     println!("{entry}");
+//! }
+//! assert_eq!(engine.dump_recent_activity().len(), 2);
+//! ```
+use crate::render::Viewport;
+use crate::tab::TabId;
+use crate::zone::ZoneId;
+use crate::{EngineCommand, EngineEvent, ZoneCommand};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Something the engine was asked to do.
+#[derive(Debug, Clone)]
+pub enum Activity {
+    /// A tab was opened
+    OpenTab {
+        zone_id: ZoneId,
+        tab_id: TabId,
+        viewport: Viewport,
+    },
+    /// An event was sent to a tab
+    Event { tab_id: TabId, event: EngineEvent },
+    /// A command was sent to a tab
+    Command {
+        tab_id: TabId,
+        command: EngineCommand,
+    },
+    /// A command was sent to a zone
+    ZoneCommand {
+        zone_id: ZoneId,
+        command: ZoneCommand,
+    },
+}
+
+/// An [`Activity`] and when it happened.
+#[derive(Debug, Clone)]
+pub struct ActivityEntry {
+    /// Time since the engine was created
+    pub at: Duration,
+    /// What happened
+    pub activity: Activity,
+}
+
+impl fmt::Display for ActivityEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:>10.3}s] ", self.at.as_secs_f64())?;
+        match &self.activity {
+            Activity::OpenTab {
+                zone_id,
+                tab_id,
+                viewport,
+            } => write!(f, "open tab {tab_id:?} in zone {zone_id:?} ({viewport:?})"),
+            Activity::Event { tab_id, event } => write!(f, "event {tab_id:?}: {event:?}"),
+            Activity::Command { tab_id, command } => write!(f, "command {tab_id:?}: {command:?}"),
+            Activity::ZoneCommand { zone_id, command } => {
+                write!(f, "zone command {zone_id:?}: {command:?}")
+            }
+        }
+    }
+}
+
+/// The last `capacity` activities of an engine.
+pub(crate) struct ActivityLog {
+    started: Instant,
+    capacity: usize,
+    entries: VecDeque<ActivityEntry>,
+}
+
+impl ActivityLog {
+    /// Creates a log that keeps `capacity` entries, or `None` when `capacity` is 0.
+    pub(crate) fn new(capacity: usize) -> Option<Self> {
+        (capacity > 0).then(|| Self {
+            started: Instant::now(),
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        })
+    }
+
+    /// Adds an entry, dropping the oldest one when the log is full.
+    pub(crate) fn record(&mut self, activity: Activity) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ActivityEntry {
+            at: self.started.elapsed(),
+            activity,
+        });
+    }
+
+    /// Returns the entries, oldest first.
+    pub(crate) fn entries(&self) -> Vec<ActivityEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_oldest_entries_are_dropped() {
+        assert!(ActivityLog::new(0).is_none());
+
+        let mut log = ActivityLog::new(2).unwrap();
+        let tab_id = TabId::new();
+        for dy in [1.0, 2.0, 3.0] {
+            log.record(Activity::Event {
+                tab_id,
+                event: EngineEvent::Scroll { dx: 0.0, dy },
+            });
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(
+            entries[0].activity,
+            Activity::Event { event: EngineEvent::Scroll { dy, .. }, .. } if dy == 2.0
+        ));
+        assert!(entries[1]
+            .to_string()
+            .contains("Scroll { dx: 0.0, dy: 3.0 }"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Synchronous facade for embedders without an async runtime.
+//!
+//! [`GosubEngine`] owns its tokio runtime and never requires the caller to be inside one, but
+//! its API is broad. [`BlockingEngineHandle`] narrows it down to what a plain `fn main()` or a
+//! GUI toolkit loop needs: create zones and tabs, send input, tick, and drain notifications.
+//! Every call returns immediately; network loads continue on the engine's runtime and surface
+//! through [`BlockingEngineHandle::tick`] and [`BlockingEngineHandle::try_recv_event`].
+//!
+//! ```
+//! use gosub_engine::{BlockingEngineHandle, EngineCommand};
+//! use gosub_engine::render::backends::null::NullBackend;
+//! use gosub_engine::render::{DefaultCompositor, Viewport};
+//! use url::Url;
+//!
+//! let backend = NullBackend::new().expect("null renderer cannot be created (!?)");
+//! let mut handle = BlockingEngineHandle::new(None, Box::new(backend));
+//!
+//! let zone_id = handle.create_zone().unwrap();
+//! let tab_id = handle.create_tab(zone_id, Viewport::new(0, 0, 800, 600)).unwrap();
+//! handle
+//!     .send(tab_id, EngineCommand::Navigate(Url::parse("about:blank").unwrap()))
+//!     .unwrap();
+//!
+//! let compositor = &mut DefaultCompositor::new(|| {});
+//! handle.tick(compositor);
+//! while let Some(notification) = handle.try_recv_event() {
+//!     println!("{notification:?}");
+//! }
+//! ```
+use crate::engine::tab::TabId;
+use crate::engine::tick::TickResult;
+use crate::render::backend::{CompositorSink, RenderBackend};
+use crate::render::Viewport;
+use crate::zone::ZoneId;
+use crate::{
+    EngineCommand, EngineConfig, EngineError, EngineEvent, EngineNotification, GosubEngine,
+    NotificationSubscription,
+};
+use std::collections::BTreeMap;
+
+/// Input that can be sent to a tab: either a UI event or a command.
+#[derive(Debug, Clone)]
+pub enum TabInput {
+    /// A UI event such as a mouse move or key press
+    Event(EngineEvent),
+    /// A command such as a navigation
+    Command(EngineCommand),
+}
+
+impl From<EngineEvent> for TabInput {
+    fn from(event: EngineEvent) -> Self {
+        TabInput::Event(event)
+    }
+}
+
+impl From<EngineCommand> for TabInput {
+    fn from(command: EngineCommand) -> Self {
+        TabInput::Command(command)
+    }
+}
+
+/// Blocking handle around a [`GosubEngine`].
+pub struct BlockingEngineHandle {
+    engine: GosubEngine,
+    /// Notifications since the handle was created
+    events: NotificationSubscription,
+}
+
+impl BlockingEngineHandle {
+    /// Creates a new engine (and its runtime). If `config` is `None`, defaults are used.
+    pub fn new(config: Option<EngineConfig>, backend: Box<dyn RenderBackend>) -> Self {
+        let engine = GosubEngine::new(config, backend);
+        let events = engine.subscribe_notifications();
+        Self { engine, events }
+    }
+
+    /// Creates a zone with default settings. Use [`BlockingEngineHandle::engine_mut`] and
+    /// [`GosubEngine::zone_builder`] for anything else.
+    pub fn create_zone(&mut self) -> Result<ZoneId, EngineError> {
+        self.engine.zone_builder().create()
+    }
+
+    /// Opens a tab in `zone_id`.
+    pub fn create_tab(
+        &mut self,
+        zone_id: ZoneId,
+        viewport: Viewport,
+    ) -> Result<TabId, EngineError> {
+        self.engine.open_tab_in_zone(zone_id, viewport)
+    }
+
+    /// Sends an [`EngineEvent`] or [`EngineCommand`] to a tab.
+    pub fn send(&mut self, tab_id: TabId, input: impl Into<TabInput>) -> Result<(), EngineError> {
+        match input.into() {
+            TabInput::Event(event) => self.engine.handle_event(tab_id, event),
+            TabInput::Command(command) => self.engine.execute_command(tab_id, command),
+        }
+    }
+
+    /// Advances all zones and tabs once. See [`GosubEngine::tick`].
+    pub fn tick(&mut self, host: &mut impl CompositorSink) -> BTreeMap<TabId, TickResult> {
+        self.engine.tick(host)
+    }
+
+    /// Returns the next pending notification without waiting. Notifications are queued from
+    /// the moment the handle was created.
+    pub fn try_recv_event(&mut self) -> Option<EngineNotification> {
+        self.events.try_recv().ok()
+    }
+
+    /// Returns the wrapped engine.
+    pub fn engine(&self) -> &GosubEngine {
+        &self.engine
+    }
+
+    /// Returns the wrapped engine mutably.
+    pub fn engine_mut(&mut self) -> &mut GosubEngine {
+        &mut self.engine
+    }
+
+    /// Consumes the handle and returns the wrapped engine.
+    pub fn into_inner(self) -> GosubEngine {
+        self.engine
+    }
+}
@@ -50,13 +50,15 @@
 //!   - `default_zone_config`: Zone defaults if no config is supplied.
 //!
 //! - **Concurrency**
-//!   - `worker_threads`: Engine thread-pool size.
+//!   - `worker_threads`: Engine thread-pool size, also used for parsing documents.
 //!   - `io_concurrency`: Max concurrent network/disk tasks.
 //!   - `script_concurrency`: Max concurrent JS/WASM tasks.
+//!   - `tab_watchdog_timeout`: Time after which a busy tab is reported as unresponsive.
+//!   - `tab_watchdog_kill`: Whether tabs reported as unresponsive are also killed.
 //!
 //! - **Networking**
 //!   - `user_agent`: Default UA string.
-//!   - `connect_timeout`, `request_timeout`: Timeouts.
+//!   - `connect_timeout`, `request_timeout`: Timeouts; loads that run into one fail with `"timeout"`.
 //!   - `redirect_policy`: Redirect handling.
 //!   - `http2`: Enable HTTP/2.
 //!   - `max_connections_per_host`: Connection cap per host.
@@ -66,21 +68,28 @@
 //! - **Cache & storage**
 //!   - `disk_cache_dir`, `disk_cache_bytes`: On-disk cache.
 //!   - `memory_cache_bytes`: In-memory cache size.
+//!   - `tab_memory_limit_bytes`: Memory for tabs above which background tabs are discarded.
 //!   - `storage_root`: Root for per-zone storage (localStorage, IndexedDB…).
 //!   - `quota_per_zone_bytes`: Per-zone storage cap.
 //!   - `persist_cookies`: Save cookies to disk.
 //!   - `cookie_jar_partitioning`: [`CookiePartitioning`] policy.
+//!   - `first_party_sets`: [`FirstPartySets`] of related sites that share a partition.
 //!
 //! - **Security / privacy**
 //!   - `sandbox_mode`: [`SandboxMode`] for zones.
 //!   - `cors_enforcement`: Enforce CORS.
 //!   - `disable_networking`: Disable networking completely.
-//!   - `blocked_domains`, `allowlist_domains`: Domain filters.
+//!   - `blocked_domains`, `allowlist_domains`: Domain filters, added to the
+//!     [`ContentBlocker`](crate::net::ContentBlocker) of every zone.
+//!   - `automated`: Disclose that the engine is driven by automation, through the user agent
+//!     string and `navigator.webdriver`.
 //!
 //! - **Rendering**
 //!   - `gpu`: [`GpuOptions`] (MSAA, vsync, etc.).
-//!   - `target_fps`: Limit FPS, or `None` for uncapped.
+//!   - `target_fps`: Limit FPS, or `None` for uncapped. Also sets the frame budget of tabs.
+//!   - `frame_sharing`: [`FrameSharing`] of the tabs that have a frame to render at once.
 //!   - `pixel_snap`: Align to pixels for sharper text.
+//!   - `subpixel_text`, `text_hinting`: Glyph positioning and [`TextHinting`].
 //!
 //! - **Fonts**
 //!   - `font_search_paths`: Extra font directories.
@@ -97,6 +106,17 @@
 //!   - `log_level`: [`LogLevel`] verbosity.
 //!   - `metrics_enabled`: Collect metrics.
 //!   - `trace_enabled`: Collect tracing spans.
+//!   - `activity_log_size`: Recent events and commands kept for
+//!     [`GosubEngine::dump_recent_activity`](crate::GosubEngine::dump_recent_activity).
+//!
+//! # Changing the configuration at runtime
+//!
+//! The fields in [`RuntimeConfig`] can be changed on a running engine with
+//! [`GosubEngine::update_config`](crate::GosubEngine::update_config): `log_level`,
+//! `target_fps`, `frame_sharing`, `memory_cache_bytes`, `tab_memory_limit_bytes`,
+//! `blocked_domains`, `allowlist_domains`, `pixel_snap`, `subpixel_text` and `text_hinting`.
+//! All other fields are fixed once the engine is created, because the runtime, network
+//! clients or zones were already built from them.
 //!
 //! # Notes
 //!
@@ -107,7 +127,7 @@
 //!
 //! Builder validation may return [`EngineConfigError`] if values are
 //! nonsensical (e.g. `max_zones == 0`, invalid MSAA samples, zero
-//! timeouts).
+//! timeouts, a site in two first-party sets).
 //!
 //! # See also
 //!
@@ -115,6 +135,8 @@
 
 use std::{fmt, path::PathBuf, time::Duration};
 
+use crate::render::TextHinting;
+use crate::storage::FirstPartySets;
 use crate::zone::ZoneConfig; // adjust path if needed
 
 // ---------- Public types ----------
@@ -174,16 +196,72 @@ pub enum CookiePartitioning {
 pub struct GpuOptions {
     /// Whether to prefer low-power GPUs (e.g. integrated) on multi-GPU systems.
     pub prefer_low_power: bool,
-    /// Number of MSAA samples for anti-aliasing.
-    pub msaa_samples: u32,           // {1,2,4,8}
+    /// Number of MSAA samples for anti-aliasing; see [`GpuOptions::antialiasing`].
+    pub msaa_samples: u32,           // {1,2,4,8,16}
     /// Whether to enable vsync (may affect latency).
     pub vsync: bool,
     /// Whether to use an sRGB framebuffer (if supported).
     pub use_srgb_framebuffer: bool,
 }
 
-/// Log verbosity for the engine.
-#[derive(Debug, Clone, Copy)]
+impl GpuOptions {
+    /// Returns the anti-aliasing that `msaa_samples` asks for. A single sample means no
+    /// multisampling, so edges are smoothed by area coverage instead. Backends offer no fewer
+    /// than 8 samples, so 2 and 4 round up to 8.
+    pub fn antialiasing(&self) -> AntialiasingMode {
+        match self.msaa_samples {
+            0 | 1 => AntialiasingMode::Area,
+            2..=8 => AntialiasingMode::Msaa8,
+            _ => AntialiasingMode::Msaa16,
+        }
+    }
+}
+
+/// How a GPU backend smooths the edges of shapes and text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AntialiasingMode {
+    /// Coverage of each pixel computed from the area a shape covers. Fast, and supported by
+    /// every device, but overlapping edges can show seams.
+    #[default]
+    Area,
+    /// Multisampling with 8 samples per pixel
+    Msaa8,
+    /// Multisampling with 16 samples per pixel
+    Msaa16,
+}
+
+/// How many tabs may render in one [`GosubEngine::tick`](crate::GosubEngine::tick).
+///
+/// A window with many visible panes would otherwise render all of them in the same tick and
+/// saturate the GPU. With a limit, tabs that have a new frame beyond it keep their place and
+/// render in a later tick, and those that have waited longest go first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameSharing {
+    /// Every tab renders as soon as it has something new to show
+    #[default]
+    Unlimited,
+    /// At most this many tabs render per tick, taking turns
+    RoundRobin(usize),
+    /// At most this many tabs render per tick; the tab set with
+    /// [`GosubEngine::set_focused_tab`](crate::GosubEngine::set_focused_tab) goes first, the
+    /// others take turns. After three frames in a row that made other tabs wait, the focused
+    /// tab lets the one that waited longest go first once.
+    FocusFirst(usize),
+}
+
+impl FrameSharing {
+    /// Returns the number of tabs that may render per tick, or `None` when there is no limit.
+    pub fn frames_per_tick(&self) -> Option<usize> {
+        match self {
+            FrameSharing::Unlimited => None,
+            FrameSharing::RoundRobin(n) | FrameSharing::FocusFirst(n) => Some(*n),
+        }
+    }
+}
+
+/// Log verbosity for the engine, from the most to the least severe. Setting a level also
+/// keeps everything more severe, see the [`logging`](crate::logging) module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Error,
     Warn,
@@ -192,6 +270,18 @@ pub enum LogLevel {
     Trace,
 }
 
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::Trace => log::Level::Trace,
+        }
+    }
+}
+
 /// Overall engine configuration (engine-wide knobs).
 ///
 /// Use [`EngineConfig::default()`] for sensible defaults, or
@@ -205,19 +295,28 @@ pub struct EngineConfig {
 
     // --- threads / concurrency ---
     /// Number of worker threads for the engine's thread pool (default: num_cpus::get().max(2)).
+    /// Documents are parsed on a separate pool of at most this many threads.
     pub worker_threads: usize,
     /// Number of concurrent I/O tasks (e.g. network, disk).
     pub io_concurrency: usize,
     /// Number of concurrent script tasks (e.g. JS, WASM).
     pub script_concurrency: usize,
+    /// Time a tab may spend in a single tick or command before it is reported with
+    /// [`EngineNotification::TabUnresponsive`](crate::EngineNotification::TabUnresponsive).
+    /// `None` (the default) runs no watchdog.
+    pub tab_watchdog_timeout: Option<Duration>,
+    /// Whether the watchdog also kills the tabs it reports, as with
+    /// [`EngineCommand::ForceKill`](crate::EngineCommand::ForceKill). Off by default, so the
+    /// user agent can decide with [`GosubEngine::tab_killer`](crate::GosubEngine::tab_killer).
+    pub tab_watchdog_kill: bool,
 
     // --- networking / HTTP ---
 
     /// User agent string used for outgoing HTTP requests (default is Gosub-UA).
     pub user_agent: String,
-    /// Connection timeout duration.
+    /// Time a page load may take to connect to the server.
     pub connect_timeout: Duration,
-    /// Overall request timeout duration.
+    /// Time a page load may take as a whole, including reading the response.
     pub request_timeout: Duration,
     /// Redirect handling policy.
     pub redirect_policy: RedirectPolicy,
@@ -237,6 +336,9 @@ pub struct EngineConfig {
     pub disk_cache_bytes: u64,
     /// Maximum memory cache size in bytes.
     pub memory_cache_bytes: u64,
+    /// Estimated memory that tabs may use before the least recently used background tabs are
+    /// discarded. `None` never discards tabs.
+    pub tab_memory_limit_bytes: Option<u64>,
     /// Root directory for per-zone storage (IndexedDB, localStorage, etc).
     pub storage_root: PathBuf,
     /// Maximum storage quota per zone in bytes.
@@ -245,6 +347,8 @@ pub struct EngineConfig {
     pub persist_cookies: bool,
     /// Cookie partitioning mode.
     pub cookie_jar_partitioning: CookiePartitioning,
+    /// Related sites that share a storage partition and count as same-site for cookies.
+    pub first_party_sets: FirstPartySets,
 
     // --- security / privacy ---
     /// Sandboxing mode for zones (network, filesystem, etc).
@@ -253,18 +357,30 @@ pub struct EngineConfig {
     pub cors_enforcement: bool,
     /// Whether to disable all networking (for testing).
     pub disable_networking: bool,
-    /// List of blocked domains (exact match).
+    /// Domains whose requests are blocked in every zone, subdomains included.
     pub blocked_domains: Vec<String>,
-    /// List of allowlisted domains (exact match).
+    /// Domains (and their subdomains) exempted from `blocked_domains` and from the zones'
+    /// filter lists.
     pub allowlist_domains: Vec<String>,
+    /// Whether the engine identifies itself as automated: the user agent string of every zone
+    /// ends in a `Headless` token and pages see `navigator.webdriver` set. Test rigs turn this
+    /// on; browsers for people leave it off.
+    pub automated: bool,
 
     // --- rendering ---
     /// GPU Options (if applicable for the chosen backend)
     pub gpu: GpuOptions,
-    /// FPS target for rendering (None = uncapped).
+    /// FPS target for rendering (None = uncapped). Tabs defer non-critical work when a frame
+    /// takes longer than one frame at this rate, or at 60 FPS when uncapped.
     pub target_fps: Option<u16>,
+    /// How the tabs that have a frame to render share a tick.
+    pub frame_sharing: FrameSharing,
     /// Pixel snapping for sharper text (if supported by backend).
     pub pixel_snap: bool,
+    /// Place glyphs at fractional pixel positions (if supported by backend).
+    pub subpixel_text: bool,
+    /// How glyph outlines are fitted to the pixel grid (if supported by backend).
+    pub text_hinting: TextHinting,
 
     // --- fonts ---
     /// List of additional font search paths.
@@ -285,21 +401,59 @@ pub struct EngineConfig {
     pub max_script_cpu_ms_per_frame: u32,
 
     // --- telemetry / logging ---
-    /// Logging verbosity level.
+    /// Logging verbosity level of the engine's messages, see the [`logging`](crate::logging)
+    /// module.
     pub log_level: LogLevel,
     /// Whether to enable metrics
     pub metrics_enabled: bool,
     /// Whether to enable tracing
     pub trace_enabled: bool,
+    /// Number of recent events and commands the engine keeps for post-mortem debugging, see
+    /// the [`activity`](crate::activity) module. 0 (the default) keeps none.
+    pub activity_log_size: usize,
 }
 
-#[derive(Debug, Clone)]
+/// Restrictions applied to the content loaded in tabs.
+///
+/// | Mode       | Allowed URL schemes                                  | Script APIs |
+/// |------------|------------------------------------------------------|-------------|
+/// | `Off`      | any                                                  | enabled     |
+/// | `Balanced` | `http`, `https`, `file`, `data`, `about`, `blob`     | enabled     |
+/// | `Strict`   | `http`, `https`, `about`                             | disabled    |
+///
+/// A navigation or redirect to a scheme that is not allowed fails the load, and the engine
+/// emits an [`EngineNotification::Warning`](crate::EngineNotification::Warning) describing
+/// the violation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SandboxMode {
+    /// No restrictions.
     Off,
+    /// Blocks custom (non-web) URL schemes.
     Balanced,
+    /// Only `http`/`https` network access, no local files, no custom schemes and no script
+    /// APIs (such as storage) exposed to page content.
     Strict,
 }
 
+impl SandboxMode {
+    /// Returns true when content may be loaded from a URL with the given scheme.
+    pub fn allows_scheme(&self, scheme: &str) -> bool {
+        match self {
+            SandboxMode::Off => true,
+            SandboxMode::Balanced => matches!(
+                scheme,
+                "http" | "https" | "file" | "data" | "about" | "blob"
+            ),
+            SandboxMode::Strict => matches!(scheme, "http" | "https" | "about"),
+        }
+    }
+
+    /// Returns true when script APIs may be exposed to page content.
+    pub fn allows_scripting(&self) -> bool {
+        !matches!(self, SandboxMode::Strict)
+    }
+}
+
 impl Default for EngineConfig {
     fn default() -> Self {
         Self {
@@ -310,6 +464,8 @@ impl Default for EngineConfig {
             worker_threads: num_cpus::get().max(2),
             io_concurrency: 64,
             script_concurrency: 8,
+            tab_watchdog_timeout: None,
+            tab_watchdog_kill: false,
 
             connect_timeout: Duration::from_secs(10),
             request_timeout: Duration::from_secs(30),
@@ -328,16 +484,19 @@ impl Default for EngineConfig {
             disk_cache_dir: std::env::temp_dir().join("gosub-cache"),
             disk_cache_bytes: 512 * 1024 * 1024, // 512 MB
             memory_cache_bytes: 128 * 1024 * 1024,
+            tab_memory_limit_bytes: Some(1024 * 1024 * 1024), // 1 GB
             storage_root: std::env::temp_dir().join("gosub-storage"),
             quota_per_zone_bytes: 256 * 1024 * 1024,
             persist_cookies: true,
             cookie_jar_partitioning: CookiePartitioning::TopLevel,
+            first_party_sets: FirstPartySets::default(),
 
             sandbox_mode: SandboxMode::Balanced,
             cors_enforcement: true,
             disable_networking: false,
             blocked_domains: Vec::new(),
             allowlist_domains: Vec::new(),
+            automated: false,
 
             gpu: GpuOptions {
                 prefer_low_power: false,
@@ -346,7 +505,10 @@ impl Default for EngineConfig {
                 use_srgb_framebuffer: true,
             },
             target_fps: None,
+            frame_sharing: FrameSharing::Unlimited,
             pixel_snap: true,
+            subpixel_text: true,
+            text_hinting: TextHinting::Slight,
 
             font_search_paths: Vec::new(),
             fallback_fonts: vec!["Inter".into(), "Noto Sans".into()],
@@ -360,6 +522,7 @@ impl Default for EngineConfig {
             log_level: LogLevel::Info,
             metrics_enabled: false,
             trace_enabled: false,
+            activity_log_size: 0,
         }
     }
 }
@@ -369,6 +532,69 @@ impl EngineConfig {
     pub fn builder() -> EngineConfigBuilder {
         EngineConfigBuilder::default()
     }
+
+    /// Returns the fields that may change while the engine runs.
+    pub fn runtime(&self) -> RuntimeConfig {
+        RuntimeConfig {
+            log_level: self.log_level,
+            target_fps: self.target_fps,
+            frame_sharing: self.frame_sharing,
+            memory_cache_bytes: self.memory_cache_bytes,
+            tab_memory_limit_bytes: self.tab_memory_limit_bytes,
+            blocked_domains: self.blocked_domains.clone(),
+            allowlist_domains: self.allowlist_domains.clone(),
+            pixel_snap: self.pixel_snap,
+            subpixel_text: self.subpixel_text,
+            text_hinting: self.text_hinting,
+        }
+    }
+
+    /// Returns a copy of the configuration with the runtime fields replaced, validated.
+    pub(crate) fn with_runtime(
+        &self,
+        runtime: RuntimeConfig,
+    ) -> Result<EngineConfig, EngineConfigError> {
+        let mut config = self.clone();
+        config.log_level = runtime.log_level;
+        config.target_fps = runtime.target_fps;
+        config.frame_sharing = runtime.frame_sharing;
+        config.memory_cache_bytes = runtime.memory_cache_bytes;
+        config.tab_memory_limit_bytes = runtime.tab_memory_limit_bytes;
+        config.blocked_domains = runtime.blocked_domains;
+        config.allowlist_domains = runtime.allowlist_domains;
+        config.pixel_snap = runtime.pixel_snap;
+        config.subpixel_text = runtime.subpixel_text;
+        config.text_hinting = runtime.text_hinting;
+        validate(&config)?;
+        Ok(config)
+    }
+}
+
+/// The part of the [`EngineConfig`] that can be changed on a running engine, see
+/// [`GosubEngine::update_config`](crate::GosubEngine::update_config). The fields mean the
+/// same as in `EngineConfig`.
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    /// Logging verbosity level.
+    pub log_level: LogLevel,
+    /// FPS target for rendering (None = uncapped). Applies to the frame budget of all tabs.
+    pub target_fps: Option<u16>,
+    /// How the tabs that have a frame to render share a tick. Applies from the next tick.
+    pub frame_sharing: FrameSharing,
+    /// Maximum memory cache size in bytes. Shrinking it evicts cached responses right away.
+    pub memory_cache_bytes: u64,
+    /// Estimated memory that tabs may use before background tabs are discarded.
+    pub tab_memory_limit_bytes: Option<u64>,
+    /// Domains blocked in every zone. The zones' content blockers are rebuilt right away.
+    pub blocked_domains: Vec<String>,
+    /// Domains exempted from blocking. The zones' content blockers are rebuilt right away.
+    pub allowlist_domains: Vec<String>,
+    /// Pixel snapping for sharper text. Open tabs re-render.
+    pub pixel_snap: bool,
+    /// Place glyphs at fractional pixel positions. Open tabs re-render.
+    pub subpixel_text: bool,
+    /// How glyph outlines are fitted to the pixel grid. Open tabs re-render.
+    pub text_hinting: TextHinting,
 }
 
 // ---------- Builder ----------
@@ -397,6 +623,8 @@ impl EngineConfigBuilder {
     pub fn worker_threads(self, n: usize) -> Self { self.map(|c| c.worker_threads = n) }
     pub fn io_concurrency(self, n: usize) -> Self { self.map(|c| c.io_concurrency = n) }
     pub fn script_concurrency(self, n: usize) -> Self { self.map(|c| c.script_concurrency = n) }
+    pub fn tab_watchdog_timeout(self, d: Option<Duration>) -> Self { self.map(|c| c.tab_watchdog_timeout = d) }
+    pub fn tab_watchdog_kill(self, on: bool) -> Self { self.map(|c| c.tab_watchdog_kill = on) }
 
     pub fn connect_timeout(self, d: Duration) -> Self { self.map(|c| c.connect_timeout = d) }
     pub fn request_timeout(self, d: Duration) -> Self { self.map(|c| c.request_timeout = d) }
@@ -409,20 +637,26 @@ impl EngineConfigBuilder {
     pub fn disk_cache_dir<P: Into<PathBuf>>(self, p: P) -> Self { self.map(|c| c.disk_cache_dir = p.into()) }
     pub fn disk_cache_bytes(self, n: u64) -> Self { self.map(|c| c.disk_cache_bytes = n) }
     pub fn memory_cache_bytes(self, n: u64) -> Self { self.map(|c| c.memory_cache_bytes = n) }
+    pub fn tab_memory_limit_bytes(self, n: Option<u64>) -> Self { self.map(|c| c.tab_memory_limit_bytes = n) }
     pub fn storage_root<P: Into<PathBuf>>(self, p: P) -> Self { self.map(|c| c.storage_root = p.into()) }
     pub fn quota_per_zone_bytes(self, n: u64) -> Self { self.map(|c| c.quota_per_zone_bytes = n) }
     pub fn persist_cookies(self, on: bool) -> Self { self.map(|c| c.persist_cookies = on) }
     pub fn cookie_jar_partitioning(self, m: CookiePartitioning) -> Self { self.map(|c| c.cookie_jar_partitioning = m) }
+    pub fn first_party_sets(self, sets: FirstPartySets) -> Self { self.map(|c| c.first_party_sets = sets) }
 
     pub fn sandbox_mode(self, m: SandboxMode) -> Self { self.map(|c| c.sandbox_mode = m) }
     pub fn cors_enforcement(self, on: bool) -> Self { self.map(|c| c.cors_enforcement = on) }
     pub fn disable_networking(self, on: bool) -> Self { self.map(|c| c.disable_networking = on) }
     pub fn blocked_domains(self, list: Vec<String>) -> Self { self.map(|c| c.blocked_domains = list) }
     pub fn allowlist_domains(self, list: Vec<String>) -> Self { self.map(|c| c.allowlist_domains = list) }
+    pub fn automated(self, on: bool) -> Self { self.map(|c| c.automated = on) }
 
     pub fn gpu(self, opts: GpuOptions) -> Self { self.map(|c| c.gpu = opts) }
     pub fn target_fps(self, fps: Option<u16>) -> Self { self.map(|c| c.target_fps = fps) }
+    pub fn frame_sharing(self, sharing: FrameSharing) -> Self { self.map(|c| c.frame_sharing = sharing) }
     pub fn pixel_snap(self, on: bool) -> Self { self.map(|c| c.pixel_snap = on) }
+    pub fn subpixel_text(self, on: bool) -> Self { self.map(|c| c.subpixel_text = on) }
+    pub fn text_hinting(self, hinting: TextHinting) -> Self { self.map(|c| c.text_hinting = hinting) }
 
     pub fn font_search_paths(self, v: Vec<PathBuf>) -> Self { self.map(|c| c.font_search_paths = v) }
     pub fn fallback_fonts(self, v: Vec<String>) -> Self { self.map(|c| c.fallback_fonts = v) }
@@ -436,6 +670,7 @@ impl EngineConfigBuilder {
     pub fn log_level(self, lvl: LogLevel) -> Self { self.map(|c| c.log_level = lvl) }
     pub fn metrics_enabled(self, on: bool) -> Self { self.map(|c| c.metrics_enabled = on) }
     pub fn trace_enabled(self, on: bool) -> Self { self.map(|c| c.trace_enabled = on) }
+    pub fn activity_log_size(self, n: usize) -> Self { self.map(|c| c.activity_log_size = n) }
 
     /// Apply multiple mutations in one go.
     pub fn with(self, f: impl FnOnce(&mut EngineConfig)) -> Self { self.map(f) }
@@ -456,6 +691,8 @@ pub enum EngineConfigError {
     InvalidConnectionsPerHost(u32),
     InvalidTimeout(&'static str, Duration),
     InvalidMsaa(u32),
+    ZeroFramesPerTick,
+    InvalidFirstPartySets(String),
     NegativeBytes(&'static str), // (we still use u64, but keep for future signed fields)
 }
 
@@ -467,7 +704,9 @@ impl fmt::Display for EngineConfigError {
             ZeroThreads(who) => write!(f, "{who} must be at least 1"),
             InvalidConnectionsPerHost(n) => write!(f, "max_connections_per_host must be >= 1 (got {n})"),
             InvalidTimeout(name, d) => write!(f, "{name} must be > 0 (got {:?})", d),
-            InvalidMsaa(s) => write!(f, "msaa_samples must be one of {{1,2,4,8}} (got {s})"),
+            InvalidMsaa(s) => write!(f, "msaa_samples must be one of {{1,2,4,8,16}} (got {s})"),
+            ZeroFramesPerTick => write!(f, "frame_sharing must allow at least 1 frame per tick"),
+            InvalidFirstPartySets(why) => write!(f, "first_party_sets: {why}"),
             NegativeBytes(name) => write!(f, "{name} must be non-negative"),
         }
     }
@@ -489,10 +728,17 @@ fn validate(c: &EngineConfig) -> Result<(), EngineConfigError> {
     if c.request_timeout == Duration::from_millis(0) {
         return Err(EngineConfigError::InvalidTimeout("request_timeout", c.request_timeout));
     }
+    if c.tab_watchdog_timeout == Some(Duration::ZERO) {
+        return Err(EngineConfigError::InvalidTimeout("tab_watchdog_timeout", Duration::ZERO));
+    }
     match c.gpu.msaa_samples {
-        1 | 2 | 4 | 8 => {}
+        1 | 2 | 4 | 8 | 16 => {}
         other => return Err(EngineConfigError::InvalidMsaa(other)),
     }
+    if c.frame_sharing.frames_per_tick() == Some(0) {
+        return Err(EngineConfigError::ZeroFramesPerTick);
+    }
+    c.first_party_sets.validate().map_err(EngineConfigError::InvalidFirstPartySets)?;
     // (bytes are u64 already; if you later switch to i64, keep NegativeBytes)
     Ok(())
 }
@@ -1,11 +1,35 @@
-use crate::engine::storage::{StorageArea, StorageHandles};
-use crate::net::{fetch, Response};
-use crate::render::{Color, DisplayItem, RenderList, Viewport};
+use crate::config::SandboxMode;
+use crate::engine::cookies::{allowed_cookies, CookieJarHandle, CookiePolicyHandle, CookieRequest};
+use crate::engine::diagnostics::{decode_document, ParseIssue};
+use crate::engine::forms::{FormControlKind, FormOutcome, FormState, FormSubmission};
+use crate::engine::logging::engine_log;
+use crate::engine::spellcheck::SpellcheckHandle;
+use crate::engine::storage::{FirstPartySets, StorageArea, StorageHandles};
+use crate::engine::zone::{ExtensionId, PageColors, UserScript, UserStyle};
+use crate::engine::ConsoleLevel;
+use crate::net::{
+    check_sandbox, emulate, is_transient, ConnectionPool, MultipartForm, NetworkConditions,
+    RequestCookies, RequestTimeouts, Response, SandboxViolation, TimeoutKind,
+};
+use crate::render::{
+    Color, DisplayItem, FontSettings, RenderList, RenderListDiff, RenderRequest, TextOptions,
+    Viewport,
+};
+use reqwest::header::HeaderMap;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 use tokio::task::JoinHandle;
 use url::Url;
 
+/// Smallest page zoom factor.
+pub const MIN_ZOOM: f32 = 0.25;
+/// Largest page zoom factor.
+pub const MAX_ZOOM: f32 = 5.0;
+
 /// BrowsingContext dedicated to a specific tab
 ///
 /// A BrowsingContext is a single instance of the engine that deals with a specific tab. Each tab
@@ -18,25 +42,91 @@ pub struct BrowsingContext {
     current_url: Option<Url>,
     /// This should become the DOM document, but maybe we can leave the raw HTML here as well
     raw_html: String,
+    /// Form controls of the document and their current values
+    forms: FormState,
+    /// Spellchecker for editable form controls, provided by the zone
+    spellcheck: Option<SpellcheckHandle>,
+    /// Set when the owning tab is killed, so a parse in flight gives up early
+    cancel: Arc<AtomicBool>,
+    /// Page colors overridden by the zone's user styles
+    page_colors: PageColors,
+    /// User scripts that apply to the current document
+    user_scripts: Vec<UserScript>,
+    /// Messages from the user agent for extension content scripts in this document
+    extension_inbox: Vec<(ExtensionId, String)>,
+    /// Messages logged by the document that the tab has not picked up yet
+    console: Vec<ConsoleMessage>,
+    /// Recoverable problems found while parsing the current document
+    parse_issues: Vec<ParseIssue>,
     /// True when the tab has failed loading (mostly net issues)
     failed: bool,
+    /// Restrictions on what this context may load and expose to scripts
+    sandbox: SandboxMode,
+    /// Set when the last load was refused by the sandbox during a redirect
+    sandbox_violation: Option<SandboxViolation>,
+    /// Extra headers sent with every request
+    request_headers: HeaderMap,
+    /// Jar whose cookies are sent with the requests of this context
+    cookie_jar: Option<CookieJarHandle>,
+    /// Page that started the current navigation; `None` when the user started it
+    initiator: Option<Url>,
+    /// Related sites whose requests to each other send their `SameSite` cookies
+    first_party_sets: Arc<FirstPartySets>,
+    /// Decides which cookies of responses are stored; all of them when `None`
+    cookie_policy: Option<CookiePolicyHandle>,
+    /// Time limits for the requests of this context
+    request_timeouts: RequestTimeouts,
+    /// Network conditions the requests of this context are slowed down to
+    network_conditions: NetworkConditions,
+    /// Connections shared with the other tabs of the zone
+    connection_pool: Arc<ConnectionPool>,
+    /// Set when the last load failed because it ran into one of the timeouts
+    timed_out: Option<TimeoutKind>,
+    /// Set when the last load failed in a way that may go away on its own
+    transient_failure: bool,
+    /// Whether scripts may run in the current document, per zone config and site settings
+    javascript_enabled: bool,
+    /// Whether the engine discloses to pages that it is automated
+    webdriver: bool,
+    /// Whether the user agent does not show the tab
+    hidden: bool,
+    /// Language tag pages see, or `None` for the host's
+    locale: Option<String>,
+    /// IANA time zone pages see, or `None` for the host's
+    timezone: Option<String>,
+    /// Whether images are loaded in the current document, per zone config and site settings
+    images_enabled: bool,
 
     /// Tokio runtime for async operations
     runtime: Arc<Runtime>,
     /// Handle for loading the task (async)
     loading_task: Option<JoinHandle<Result<Response, reqwest::Error>>>,
+    /// Handle for parsing the loaded document on the blocking pool
+    parse_task: Option<JoinHandle<ParsedDocument>>,
+    /// Handle for fetching the icon of the current document
+    favicon_task: Option<JoinHandle<Result<Response, reqwest::Error>>>,
 
     /// Storage handles for local and session storage
     storage: Option<StorageHandles>,
 
     // Rendering commands to paint the tab onto a surface
     render_list: RenderList,
+    /// Render list that `render_list` replaced, to diff frames against
+    previous_render_list: RenderList,
     /// Render dirty flag, used to determine if the tab needs to be rendered
     render_dirty: bool,
     /// Viewport for the tab, used to determine what part of the page to render
     viewport: Viewport,
     /// Epoch of the scene, used to determine if the scene has changed
     scene_epoch: u64,
+    /// Page zoom factor (1.0 = 100%)
+    zoom: f32,
+    /// Device pixels per document pixel of the screen the tab is shown on
+    device_scale: f32,
+    /// How backends rasterize the text of this context
+    text_options: TextOptions,
+    /// Default font family and sizes, from the zone
+    fonts: FontSettings,
 
     /// DOM dirty flag, used to determine if the DOM has changed
     dom_dirty: bool,
@@ -53,14 +143,47 @@ impl BrowsingContext {
             // dirty: DirtyFlags::default(),
             current_url: None,
             raw_html: String::new(),
+            forms: FormState::default(),
+            spellcheck: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            page_colors: PageColors::default(),
+            user_scripts: Vec::new(),
+            extension_inbox: Vec::new(),
+            console: Vec::new(),
+            parse_issues: Vec::new(),
             runtime,
             loading_task: None,
+            parse_task: None,
+            favicon_task: None,
             failed: false,
+            sandbox: SandboxMode::Balanced,
+            sandbox_violation: None,
+            request_headers: HeaderMap::new(),
+            cookie_jar: None,
+            initiator: None,
+            first_party_sets: Arc::default(),
+            cookie_policy: None,
+            request_timeouts: RequestTimeouts::default(),
+            network_conditions: NetworkConditions::NONE,
+            connection_pool: Arc::new(ConnectionPool::new()),
+            timed_out: None,
+            transient_failure: false,
+            javascript_enabled: true,
+            webdriver: false,
+            hidden: false,
+            locale: None,
+            timezone: None,
+            images_enabled: true,
             storage: None, // Default no storage unless binding manually by a tab
             render_list: RenderList::new(),
+            previous_render_list: RenderList::new(),
             render_dirty: false,
             viewport: Viewport::default(),
             scene_epoch: 0,
+            zoom: 1.0,
+            device_scale: 1.0,
+            text_options: TextOptions::default(),
+            fonts: FontSettings::default(),
             dom_dirty: false,
             style_dirty: false,
             layout_dirty: false,
@@ -75,21 +198,311 @@ impl BrowsingContext {
         });
         // At this point, we would probably want to hook our storage handles into the javascript/lua runtime
     }
+    /// Local storage as exposed to page scripts. `None` when no storage is bound, or when the
+    /// document may not run scripts (see [`BrowsingContext::javascript_enabled`]).
     pub fn local_storage(&self) -> Option<Arc<dyn StorageArea>> {
+        if !self.javascript_enabled() {
+            return None;
+        }
         self.storage.as_ref().map(|s| s.local.clone())
     }
+    /// Session storage as exposed to page scripts. `None` when no storage is bound, or when the
+    /// document may not run scripts (see [`BrowsingContext::javascript_enabled`]).
     pub fn session_storage(&self) -> Option<Arc<dyn StorageArea>> {
+        if !self.javascript_enabled() {
+            return None;
+        }
         self.storage.as_ref().map(|s| s.session.clone())
     }
 
+    /// Sets the sandbox mode that applies to subsequent loads.
+    pub(crate) fn set_sandbox_mode(&mut self, mode: SandboxMode) {
+        self.sandbox = mode;
+    }
+
+    /// Returns the sandbox mode of this context.
+    pub fn sandbox_mode(&self) -> SandboxMode {
+        self.sandbox
+    }
+
+    /// Sets whether scripts may run and images are loaded in the current document.
+    pub(crate) fn set_content_settings(&mut self, javascript: bool, images: bool) {
+        self.javascript_enabled = javascript;
+        self.images_enabled = images;
+    }
+
+    /// Returns true when scripts may run in the current document: the sandbox allows
+    /// scripting, and neither the zone config nor the site settings turned JavaScript off.
+    pub fn javascript_enabled(&self) -> bool {
+        self.sandbox.allows_scripting() && self.javascript_enabled
+    }
+
+    /// Sets whether pages are told that the engine is automated.
+    pub(crate) fn set_webdriver(&mut self, webdriver: bool) {
+        self.webdriver = webdriver;
+    }
+
+    /// `navigator.webdriver` as exposed to page scripts: true when the engine is configured as
+    /// [`automated`](crate::EngineConfig::automated).
+    pub fn webdriver(&self) -> bool {
+        self.webdriver
+    }
+
+    /// Sets whether the document is hidden from the user.
+    pub(crate) fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// `document.visibilityState` as exposed to page scripts: `"hidden"` while the user agent
+    /// does not show the tab, see
+    /// [`EngineCommand::SetVisibility`](crate::EngineCommand::SetVisibility). A tab that is
+    /// only covered by other windows stays `"visible"`.
+    pub fn visibility_state(&self) -> &'static str {
+        if self.hidden {
+            "hidden"
+        } else {
+            "visible"
+        }
+    }
+
+    /// Sets the locale and time zone that documents loaded afterward see.
+    pub(crate) fn set_locale(&mut self, locale: Option<String>, timezone: Option<String>) {
+        self.locale = locale;
+        self.timezone = timezone;
+    }
+
+    /// `navigator.language` as exposed to page scripts, and the default locale of `Intl`.
+    /// `None` means the host's locale.
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Time zone that `Date` and `Intl` use for the page, as an IANA name. `None` means the
+    /// host's time zone.
+    pub fn timezone(&self) -> Option<&str> {
+        self.timezone.as_deref()
+    }
+
+    /// Returns true when images are loaded in the current document.
+    pub fn images_enabled(&self) -> bool {
+        self.images_enabled
+    }
+
+    /// Sets the extra headers sent with subsequent requests, such as `DNT`.
+    pub(crate) fn set_request_headers(&mut self, headers: HeaderMap) {
+        self.request_headers = headers;
+    }
+
+    /// Sets the jar whose cookies are sent with requests started afterward. With `None` no
+    /// cookies are sent.
+    pub(crate) fn set_cookie_jar(&mut self, jar: Option<CookieJarHandle>) {
+        self.cookie_jar = jar;
+    }
+
+    /// Sets the page that started the next navigation, or `None` when the user started it.
+    /// Cross-site navigations leave `SameSite` cookies behind.
+    pub(crate) fn set_initiator(&mut self, initiator: Option<Url>) {
+        self.initiator = initiator;
+    }
+
+    /// Sets the related sites that count as same-site for the cookies of subsequent requests.
+    pub(crate) fn set_first_party_sets(&mut self, sets: Arc<FirstPartySets>) {
+        self.first_party_sets = sets;
+    }
+
+    /// Sets the policy that decides which cookies of subsequent responses are stored.
+    pub(crate) fn set_cookie_policy(&mut self, policy: Option<CookiePolicyHandle>) {
+        self.cookie_policy = policy;
+    }
+
+    /// Returns the headers of the response to a navigation to `url`, without the cookies the
+    /// cookie policy refuses.
+    pub(crate) fn allowed_navigation_cookies<'a>(
+        &self,
+        url: &Url,
+        headers: &'a HeaderMap,
+    ) -> Cow<'a, HeaderMap> {
+        let Some(policy) = &self.cookie_policy else {
+            return Cow::Borrowed(headers);
+        };
+        let request = CookieRequest {
+            url,
+            top_level: Some(url),
+            third_party: false,
+        };
+        Cow::Owned(allowed_cookies(policy.as_ref(), &request, headers))
+    }
+
+    /// Returns the cookies of a request made by `initiator`, which the connection pool sends
+    /// and stores at every redirect. `None` when the context has no cookie jar.
+    fn request_cookies(&self, initiator: Option<Url>, navigation: bool) -> Option<RequestCookies> {
+        Some(RequestCookies {
+            jar: self.cookie_jar.clone()?,
+            initiator,
+            navigation,
+            related: self.first_party_sets.clone(),
+            policy: self.cookie_policy.clone(),
+        })
+    }
+
+    /// Returns the extra headers sent with every request of this context.
+    pub fn request_headers(&self) -> &HeaderMap {
+        &self.request_headers
+    }
+
+    /// Shares the kill flag of the owning tab, which the parse checks between its stages.
+    pub(crate) fn set_cancel_flag(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = cancel;
+    }
+
+    /// Sets the time limits of subsequent requests.
+    pub(crate) fn set_request_timeouts(&mut self, timeouts: RequestTimeouts) {
+        self.request_timeouts = timeouts;
+    }
+
+    /// Returns the time limits of the requests of this context.
+    pub fn request_timeouts(&self) -> RequestTimeouts {
+        self.request_timeouts
+    }
+
+    /// Sets the network conditions that subsequent requests are slowed down to.
+    pub(crate) fn set_network_conditions(&mut self, conditions: NetworkConditions) {
+        self.network_conditions = conditions;
+    }
+
+    /// Returns the network conditions the requests of this context are slowed down to.
+    pub fn network_conditions(&self) -> NetworkConditions {
+        self.network_conditions
+    }
+
+    /// Shares the connection pool of the zone with this context.
+    pub(crate) fn set_connection_pool(&mut self, pool: Arc<ConnectionPool>) {
+        self.connection_pool = pool;
+    }
+
+    /// Opens a connection to the origin of `url` in the background, so that a later request
+    /// to it starts faster. Does nothing for URLs the sandbox does not allow.
+    pub(crate) fn preconnect(&self, url: Url) -> Option<JoinHandle<()>> {
+        check_sandbox(&url, self.sandbox).ok()?;
+        let pool = self.connection_pool.clone();
+        let sandbox = self.sandbox;
+        let headers = self.request_headers.clone();
+        let timeouts = self.request_timeouts;
+        Some(self.runtime.spawn(async move {
+            pool.preconnect(url, sandbox, headers, timeouts).await;
+        }))
+    }
+
+    /// Resolves the host name of `url` in the background.
+    pub(crate) fn prefetch_dns(&self, url: Url) -> JoinHandle<()> {
+        let pool = self.connection_pool.clone();
+        self.runtime.spawn(async move {
+            pool.prefetch_dns(url).await;
+        })
+    }
+
     /// Starts a task that will load the actual url
-    pub fn start_loading(&mut self, url: Url) {
+    ///
+    /// # Errors
+    /// Returns a [`SandboxViolation`] (and marks the context as failed) when the sandbox does
+    /// not allow loading `url`. Nothing is fetched in that case.
+    pub fn start_loading(&mut self, url: Url) -> Result<(), SandboxViolation> {
+        self.start_request(url, None)
+    }
+
+    /// Starts loading the result of a form submission. GET submissions are plain loads, POST
+    /// submissions send the encoded form data as request body. Files of multipart forms are
+    /// streamed from disk while the request is sent.
+    ///
+    /// # Errors
+    /// Same as [`BrowsingContext::start_loading`].
+    pub fn start_submission(&mut self, submission: FormSubmission) -> Result<(), SandboxViolation> {
+        let upload = match (submission.body, submission.multipart) {
+            (Some(body), _) => Some(Upload::Form(body)),
+            (None, Some(form)) => Some(Upload::Multipart(form)),
+            (None, None) => None,
+        };
+        self.start_request(submission.url, upload)
+    }
+
+    /// Starts "loading" a response that is already complete, such as content generated by the
+    /// user agent. It is picked up by [`BrowsingContext::poll_loading`] like a fetched response,
+    /// without touching the network.
+    pub fn start_with_response(&mut self, response: Response) {
+        self.cancel_pipeline();
+        self.sandbox_violation = None;
+        let url = response.url.clone();
+        let handle = self
+            .runtime
+            .spawn(async move { Ok::<_, reqwest::Error>(response) });
+
+        self.loading_task = Some(handle);
+        self.failed = false;
+        self.current_url = Some(url);
+    }
+
+    fn start_request(&mut self, url: Url, body: Option<Upload>) -> Result<(), SandboxViolation> {
+        self.cancel_pipeline();
+        self.sandbox_violation = None;
+        self.timed_out = None;
+        self.transient_failure = false;
+        if let Err(violation) = check_sandbox(&url, self.sandbox) {
+            self.loading_task = None;
+            self.failed = true;
+            return Err(violation);
+        }
+
         let url_clone = url.clone();
-        let handle = self.runtime.spawn(async move { fetch(url_clone).await });
+        let sandbox = self.sandbox;
+        let headers = self.request_headers.clone();
+        let cookies = self.request_cookies(self.initiator.clone(), true);
+        let timeouts = self.request_timeouts;
+        let conditions = self.network_conditions;
+        let pool = self.connection_pool.clone();
+        let upload = match &body {
+            Some(Upload::Form(body)) => body.len(),
+            Some(Upload::Multipart(form)) => form.content_length().unwrap_or(0) as usize,
+            None => 0,
+        };
+        let handle = self.runtime.spawn(emulate(conditions, upload, async move {
+            match body {
+                Some(Upload::Form(body)) => {
+                    pool.post_form(url_clone, body, sandbox, headers, timeouts, cookies)
+                        .await
+                }
+                Some(Upload::Multipart(form)) => {
+                    pool.post_multipart(url_clone, form, sandbox, headers, timeouts, cookies)
+                        .await
+                }
+                None => {
+                    pool.get(url_clone, sandbox, headers, timeouts, cookies)
+                        .await
+                }
+            }
+        }));
 
         self.loading_task = Some(handle);
         self.failed = false;
         self.current_url = Some(url);
+        Ok(())
+    }
+
+    /// Returns (and clears) the sandbox violation that made the last load fail, if any.
+    ///
+    /// This covers redirects to disallowed URLs, which are only detected while loading.
+    pub fn take_sandbox_violation(&mut self) -> Option<SandboxViolation> {
+        self.sandbox_violation.take()
+    }
+
+    /// Returns (and clears) the timeout that made the last load fail, if any.
+    pub fn take_timeout(&mut self) -> Option<TimeoutKind> {
+        self.timed_out.take()
+    }
+
+    /// Returns (and clears) whether the last load failed in a way that may go away on its
+    /// own, such as a reset connection. See [`RetryPolicy`](crate::net::RetryPolicy).
+    pub fn take_transient_failure(&mut self) -> bool {
+        std::mem::take(&mut self.transient_failure)
     }
 
     /// Polls the loading to see if it is still running or not.
@@ -100,8 +513,16 @@ impl BrowsingContext {
             if let Some(join_result) = handle.now_or_never() {
                 self.loading_task = None;
                 return Some(match join_result {
-                    Ok(Ok(resp)) => Ok(resp),
-                    Ok(Err(e)) => Err(e.to_string()),
+                    Ok(Ok(resp)) => {
+                        self.current_url = Some(resp.url.clone());
+                        Ok(resp)
+                    }
+                    Ok(Err(e)) => {
+                        self.sandbox_violation = find_violation(&e);
+                        self.timed_out = TimeoutKind::of(&e);
+                        self.transient_failure = is_transient(&e);
+                        Err(e.to_string())
+                    }
                     Err(e) => Err(format!("Join error: {}", e)),
                 });
             }
@@ -110,20 +531,412 @@ impl BrowsingContext {
         None
     }
 
+    /// Starts fetching the icon at `url` for the current document, with the same sandbox,
+    /// headers and timeouts as page loads. Poll with [`BrowsingContext::poll_favicon`].
+    pub(crate) fn start_favicon_fetch(&mut self, url: Url) {
+        if let Some(task) = self.favicon_task.take() {
+            task.abort();
+        }
+        let sandbox = self.sandbox;
+        let headers = self.request_headers.clone();
+        let cookies = self.request_cookies(self.current_url.clone(), false);
+        let timeouts = self.request_timeouts;
+        let conditions = self.network_conditions;
+        let pool = self.connection_pool.clone();
+        self.favicon_task = Some(self.runtime.spawn(emulate(conditions, 0, async move {
+            pool.get(url, sandbox, headers, timeouts, cookies).await
+        })));
+    }
+
+    /// Returns the outcome of the icon fetch once it is done.
+    pub(crate) fn poll_favicon(&mut self) -> Option<Result<Response, String>> {
+        use futures::FutureExt;
+
+        let join_result = self.favicon_task.as_mut()?.now_or_never()?;
+        self.favicon_task = None;
+        Some(match join_result {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Join error: {}", e)),
+        })
+    }
+
+    /// Starts parsing a loaded document on the runtime's blocking pool, so that a large page
+    /// does not hold up the tab. Poll with [`BrowsingContext::poll_parsing`].
+    pub fn start_parsing(&mut self, body: Vec<u8>) {
+        if let Some(task) = self.parse_task.take() {
+            task.abort();
+        }
+        let spellcheck = self.spellcheck.clone();
+        let cancel = self.cancel.clone();
+        self.parse_task = Some(self.runtime.spawn_blocking(move || {
+            let killed = || cancel.load(Ordering::Relaxed);
+            let (html, issues) = decode_document(body);
+            if killed() {
+                return ParsedDocument::default();
+            }
+            let mut forms = FormState::from_html(&html);
+            if killed() {
+                return ParsedDocument::default();
+            }
+            forms.check_all_spelling(spellcheck.as_deref());
+            ParsedDocument {
+                html,
+                forms,
+                issues,
+            }
+        }));
+    }
+
+    /// Polls the parsing started by [`BrowsingContext::start_parsing`]. Returns true once the
+    /// parsed document has replaced the current one.
+    pub fn poll_parsing(&mut self) -> bool {
+        use futures::FutureExt;
+
+        let Some(handle) = &mut self.parse_task else {
+            return false;
+        };
+        let Some(join_result) = handle.now_or_never() else {
+            return false;
+        };
+        self.parse_task = None;
+
+        let document = join_result.unwrap_or_else(|e| {
+            engine_log!(Error, "Parsing the document failed: {e}");
+            ParsedDocument::default()
+        });
+        self.raw_html = document.html;
+        self.forms = document.forms;
+        self.console
+            .extend(document.issues.iter().map(|issue| ConsoleMessage {
+                level: ConsoleLevel::Warn,
+                text: issue.kind.to_string(),
+                line: Some(issue.line),
+            }));
+        self.parse_issues = document.issues;
+        self.dom_dirty = true;
+        self.style_dirty = true;
+        self.layout_dirty = true;
+        self.invalidate_render();
+        true
+    }
+
+    /// Stops the load and parse in flight, if any. Their results are dropped. Called whenever
+    /// a new load supersedes them.
+    pub(crate) fn cancel_pipeline(&mut self) {
+        if let Some(task) = self.loading_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.parse_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.favicon_task.take() {
+            task.abort();
+        }
+    }
+
+    /// Returns true when navigating to `url` only changes the fragment of the current
+    /// document, so it can be handled without fetching anything.
+    pub fn is_same_document(&self, url: &Url) -> bool {
+        let Some(current) = &self.current_url else {
+            return false;
+        };
+        url.fragment().is_some()
+            && current.as_str().split('#').next() == url.as_str().split('#').next()
+    }
+
+    /// Performs a same-document (fragment) navigation to `url`.
+    ///
+    /// Updates the current URL and returns the vertical scroll offset of the element the
+    /// fragment points at: `Some(0)` for an empty fragment or `#top`, `None` when there is no
+    /// such element (in which case the page should not scroll).
+    pub fn navigate_to_fragment(&mut self, url: Url) -> Option<i32> {
+        let fragment = url.fragment().unwrap_or_default().to_string();
+        self.current_url = Some(url);
+        self.fragment_offset(&fragment)
+    }
+
+    /// Returns the vertical offset of the element with the given id (or anchor name).
+    ///
+    /// Until there is a real layout this searches the raw HTML, using the same line metrics
+    /// as [`BrowsingContext::rebuild_render_list_if_needed`].
+    pub fn fragment_offset(&self, fragment: &str) -> Option<i32> {
+        if fragment.is_empty() || fragment.eq_ignore_ascii_case("top") {
+            return Some(0);
+        }
+
+        let needles = [
+            format!("id=\"{fragment}\""),
+            format!("id='{fragment}'"),
+            format!("name=\"{fragment}\""),
+            format!("name='{fragment}'"),
+        ];
+        self.raw_html
+            .lines()
+            .position(|line| needles.iter().any(|n| line.contains(n.as_str())))
+            .map(|idx| (idx as f32 * 16.0 * self.zoom) as i32)
+    }
+
+    /// Returns the form controls of the document.
+    pub fn forms(&self) -> &FormState {
+        &self.forms
+    }
+
+    /// Forwards a click at `(x, y)` (viewport coordinates) to the form controls.
+    pub fn form_click(&mut self, x: f32, y: f32) -> FormOutcome {
+        let (x, y) = self.to_page(x, y);
+        let outcome = self.forms.click(x, y);
+        if outcome != FormOutcome::Ignored {
+            self.invalidate_render();
+        }
+        outcome
+    }
+
+    /// Returns the submit button at `(x, y)` (viewport coordinates), if any.
+    pub fn submit_button_at(&self, x: f32, y: f32) -> Option<usize> {
+        let (x, y) = self.to_page(x, y);
+        let idx = self.forms.hit_test(x, y)?;
+        let kind = &self.forms.controls()[idx].kind;
+        matches!(kind, FormControlKind::Button { submit: true }).then_some(idx)
+    }
+
+    /// Maps viewport coordinates onto page coordinates at 100% zoom.
+    fn to_page(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = self.render_request().to_document(x, y);
+        (x / self.zoom, y / self.zoom)
+    }
+
+    /// Forwards a key press to the focused form control.
+    pub fn form_key_down(&mut self, key: &str) -> FormOutcome {
+        let outcome = self.forms.key_down(key);
+        if outcome == FormOutcome::Changed {
+            if let Some(idx) = self.forms.focused() {
+                self.recheck_spelling(idx);
+            }
+            self.invalidate_render();
+        }
+        outcome
+    }
+
+    /// Forwards a typed character to the focused form control.
+    pub fn form_input_char(&mut self, character: char) -> bool {
+        let consumed = self.forms.insert_char(character);
+        if consumed {
+            if let Some(idx) = self.forms.focused() {
+                self.recheck_spelling(idx);
+            }
+            self.invalidate_render();
+        }
+        consumed
+    }
+
+    /// Selects the whole value of the focused form control.
+    pub fn form_select_all(&mut self) -> bool {
+        let selected = self.forms.select_all();
+        if selected {
+            self.invalidate_render();
+        }
+        selected
+    }
+
+    /// Pastes text from the clipboard into the focused form control.
+    pub fn form_paste(&mut self, text: &str) -> bool {
+        let pasted = self.forms.paste(text);
+        if pasted {
+            self.focused_control_changed();
+        }
+        pasted
+    }
+
+    /// Returns the selected text of the focused form control, removing it when `cut` is set.
+    pub fn form_copy(&mut self, cut: bool) -> Option<String> {
+        if !cut {
+            return self.forms.copy_selection();
+        }
+        let text = self.forms.cut_selection()?;
+        self.focused_control_changed();
+        Some(text)
+    }
+
+    /// Returns the text field at `(x, y)` (viewport coordinates), if any.
+    pub fn editable_control_at(&self, x: f32, y: f32) -> Option<usize> {
+        let (x, y) = self.to_page(x, y);
+        let idx = self.forms.hit_test(x, y)?;
+        self.forms.controls()[idx].is_editable().then_some(idx)
+    }
+
+    /// Returns the file input at `(x, y)` (viewport coordinates), if any.
+    pub fn file_input_at(&self, x: f32, y: f32) -> Option<usize> {
+        let (x, y) = self.to_page(x, y);
+        let idx = self.forms.hit_test(x, y)?;
+        matches!(
+            self.forms.controls()[idx].kind,
+            FormControlKind::File { .. }
+        )
+        .then_some(idx)
+    }
+
+    /// Focuses the text field at `(x, y)` (viewport coordinates) and pastes `text` into it.
+    pub fn form_drop_text(&mut self, x: f32, y: f32, text: &str) -> bool {
+        match self.editable_control_at(x, y) {
+            Some(idx) if self.forms.focus_control(idx) => self.form_paste(text),
+            _ => false,
+        }
+    }
+
+    fn focused_control_changed(&mut self) {
+        if let Some(idx) = self.forms.focused() {
+            self.recheck_spelling(idx);
+        }
+        self.invalidate_render();
+    }
+
+    /// Sets the value of a form control, as if the user had typed it.
+    pub fn set_form_value(&mut self, control: usize, value: impl Into<String>) -> bool {
+        let changed = self.forms.set_value(control, value);
+        if changed {
+            self.recheck_spelling(control);
+            self.invalidate_render();
+        }
+        changed
+    }
+
+    /// Stores the files the user agent chose for a file input.
+    pub fn set_form_files(&mut self, control: usize, files: Vec<PathBuf>) -> bool {
+        let changed = self.forms.set_files(control, files);
+        if changed {
+            self.invalidate_render();
+        }
+        changed
+    }
+
+    /// Sets the spellchecker for editable controls and rechecks the current document.
+    pub(crate) fn set_spellcheck_provider(&mut self, provider: Option<SpellcheckHandle>) {
+        self.spellcheck = provider;
+        self.forms.check_all_spelling(self.spellcheck.as_deref());
+        self.invalidate_render();
+    }
+
+    /// Returns the spellchecker's suggestions for the word at `range` in form control
+    /// `control`.
+    pub fn spelling_suggestions(&self, control: usize, range: Range<usize>) -> Vec<String> {
+        let (Some(provider), Some(control)) =
+            (&self.spellcheck, self.forms.controls().get(control))
+        else {
+            return Vec::new();
+        };
+        match control.value.get(range) {
+            Some(word) => provider.suggestions(word),
+            None => Vec::new(),
+        }
+    }
+
+    /// Replaces the text at `range` in form control `control` with `replacement`, typically
+    /// a spelling suggestion. Returns false when the control or range does not exist.
+    pub fn replace_misspelling(
+        &mut self,
+        control: usize,
+        range: Range<usize>,
+        replacement: &str,
+    ) -> bool {
+        let replaced = self.forms.replace_text(control, range, replacement);
+        if replaced {
+            self.recheck_spelling(control);
+            self.invalidate_render();
+        }
+        replaced
+    }
+
+    fn recheck_spelling(&mut self, control: usize) {
+        self.forms
+            .check_spelling(control, self.spellcheck.as_deref());
+    }
+
+    /// Builds the submission for the form of `submitter`, resolved against the current URL.
+    pub fn form_submission(&self, submitter: usize) -> Option<FormSubmission> {
+        self.forms.submission(submitter, self.current_url.as_ref()?)
+    }
+
+    /// Applies the zone's user styles and scripts that match the document being committed.
+    pub(crate) fn set_user_content(&mut self, styles: &[UserStyle], scripts: Vec<UserScript>) {
+        self.page_colors = PageColors::from_styles(styles);
+        self.user_scripts = scripts;
+        self.extension_inbox.clear();
+        self.invalidate_render();
+    }
+
+    /// Returns true when `extension` injected a content script into the current document.
+    pub fn has_content_script(&self, extension: &ExtensionId) -> bool {
+        self.user_scripts
+            .iter()
+            .any(|s| s.extension.as_ref() == Some(extension))
+    }
+
+    /// Queues a message from the user agent for the content scripts of `extension`.
+    pub(crate) fn deliver_extension_message(&mut self, extension: ExtensionId, message: String) {
+        self.extension_inbox.push((extension, message));
+    }
+
+    /// Takes the queued messages for extension content scripts, oldest first. This is where a
+    /// script runtime picks them up.
+    pub fn take_extension_messages(&mut self) -> Vec<(ExtensionId, String)> {
+        std::mem::take(&mut self.extension_inbox)
+    }
+
+    /// User scripts that apply to the current document. There is no script runtime yet, so
+    /// these are not executed.
+    pub fn user_scripts(&self) -> &[UserScript] {
+        &self.user_scripts
+    }
+
+    /// Returns the raw HTML of the current document
+    pub fn raw_html(&self) -> &str {
+        &self.raw_html
+    }
+
     /// Sets the rab HTML for the given tab
     pub fn set_raw_html(&mut self, html: &str) {
         self.raw_html = html.to_string();
+        self.parse_issues.clear();
+        self.forms = FormState::from_html(html);
+        self.forms.check_all_spelling(self.spellcheck.as_deref());
         self.dom_dirty = true; // Mark the DOM as dirty, so it will be rendered
         self.style_dirty = true;
         self.layout_dirty = true;
         self.invalidate_render();
     }
 
+    /// Drops the document and everything derived from it, keeping the URL, viewport and
+    /// settings. Used when the tab is discarded to save memory.
+    pub(crate) fn discard_document(&mut self) {
+        self.raw_html = String::new();
+        self.parse_issues.clear();
+        self.forms = FormState::default();
+        self.user_scripts.clear();
+        self.extension_inbox.clear();
+        self.render_list = RenderList::new();
+        self.previous_render_list = RenderList::new();
+        self.dom_dirty = true;
+        self.style_dirty = true;
+        self.layout_dirty = true;
+        self.invalidate_render();
+    }
+
+    /// Returns a rough estimate in bytes of the memory held by the document and its render
+    /// list.
+    pub fn memory_estimate(&self) -> usize {
+        let items = self.render_list.items.len() + self.previous_render_list.items.len();
+        self.raw_html.len() + items * std::mem::size_of::<DisplayItem>()
+    }
+
     pub fn set_viewport(&mut self, vp: Viewport) {
         if self.viewport != vp {
+            // The render list is in document coordinates, so scrolling alone keeps it valid
+            let resized = self.viewport.as_size() != vp.as_size();
             self.viewport = vp;
-            self.layout_dirty = true;
-            self.invalidate_render();
+            if resized {
+                self.layout_dirty = true;
+                self.invalidate_render();
+            }
         }
     }
 
@@ -132,6 +945,71 @@ impl BrowsingContext {
         &self.viewport
     }
 
+    /// Returns the device pixels per document pixel (1.0 on a regular screen).
+    #[inline]
+    pub fn device_scale(&self) -> f32 {
+        self.device_scale
+    }
+
+    /// Sets the device scale. The viewport is in device pixels, so text wraps at a different
+    /// document width and the render list is rebuilt.
+    pub(crate) fn set_device_scale(&mut self, scale: f32) {
+        if scale.is_finite() && scale > 0.0 && scale != self.device_scale {
+            self.device_scale = scale;
+            self.layout_dirty = true;
+            self.invalidate_render();
+        }
+    }
+
+    /// Returns how backends map the render list onto the surface.
+    pub fn render_request(&self) -> RenderRequest {
+        RenderRequest::new(self.viewport, self.device_scale)
+    }
+
+    /// Returns the page zoom factor (1.0 = 100%).
+    #[inline]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the page zoom factor, clamped to [`MIN_ZOOM`]`..=`[`MAX_ZOOM`].
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        if self.zoom != zoom {
+            self.zoom = zoom;
+            self.layout_dirty = true;
+            self.invalidate_render();
+        }
+    }
+
+    /// Returns the default font family and sizes of the document's text.
+    pub fn font_settings(&self) -> &FontSettings {
+        &self.fonts
+    }
+
+    /// Changes the default fonts and schedules a re-render.
+    pub(crate) fn set_font_settings(&mut self, fonts: FontSettings) {
+        if self.fonts != fonts {
+            self.fonts = fonts;
+            self.layout_dirty = true;
+            self.invalidate_render();
+        }
+    }
+
+    /// Returns how backends should rasterize text.
+    #[inline]
+    pub fn text_options(&self) -> TextOptions {
+        self.text_options
+    }
+
+    /// Changes how text is rasterized and schedules a re-render.
+    pub(crate) fn set_text_options(&mut self, options: TextOptions) {
+        if self.text_options != options {
+            self.text_options = options;
+            self.invalidate_render();
+        }
+    }
+
     #[inline]
     pub fn scene_epoch(&self) -> u64 {
         self.scene_epoch
@@ -152,25 +1030,37 @@ impl BrowsingContext {
 
         // Example scene: clear + show raw HTML as text
         rl.items.push(DisplayItem::Clear {
-            color: Color::new(0.75, 0.75, 0.75, 1.0),
+            color: self
+                .page_colors
+                .background
+                .unwrap_or(Color::new(0.75, 0.75, 0.75, 1.0)),
         });
 
-        // Text color: black
-        let c = Color::new(0.0, 0.0, 0.0, 1.0);
-        let mut y = 24.0;
+        // Text color: black, unless a user style says otherwise
+        let c = self
+            .page_colors
+            .text
+            .unwrap_or(Color::new(0.0, 0.0, 0.0, 1.0));
+        let mut y = 24.0 * self.zoom;
         for line in self.raw_html.lines() {
+            // A killed tab gives up on the layout, and is closed after its tick
+            if self.cancel.load(Ordering::Relaxed) {
+                return;
+            }
             rl.items.push(DisplayItem::TextRun {
-                x: 14.0,
+                x: 14.0 * self.zoom,
                 y,
                 text: line.to_string(),
-                size: 23.0,
+                size: self.fonts.clamp(self.fonts.size) * self.zoom,
+                font_family: self.fonts.family.clone(),
                 color: c,
-                max_width: Some(self.viewport.width as f32),
+                max_width: Some(self.viewport.width as f32 / self.device_scale),
             });
-            y += 16.0;
+            y += 16.0 * self.zoom;
         }
+        self.forms.paint(&mut rl, self.zoom, &self.fonts);
 
-        self.render_list = rl;
+        self.previous_render_list = std::mem::replace(&mut self.render_list, rl);
         self.render_dirty = false;
         self.scene_epoch = self.scene_epoch.wrapping_add(1);
 
@@ -184,6 +1074,23 @@ impl BrowsingContext {
         &self.render_list
     }
 
+    /// Returns what changed between the previous render list and the current one, for hosts
+    /// that paint display items themselves and only want to redraw what changed.
+    pub fn render_list_diff(&self) -> RenderListDiff {
+        self.render_list.diff(&self.previous_render_list)
+    }
+
+    /// Returns the recoverable problems found while parsing the current document, in the order
+    /// they appear in it.
+    pub fn parse_issues(&self) -> &[ParseIssue] {
+        &self.parse_issues
+    }
+
+    /// Takes the messages the document logged since the last call.
+    pub(crate) fn take_console_messages(&mut self) -> Vec<ConsoleMessage> {
+        std::mem::take(&mut self.console)
+    }
+
     /// Returns true when the loading failed
     pub fn has_failed(&self) -> bool {
         self.failed
@@ -194,3 +1101,40 @@ impl BrowsingContext {
         self.current_url.as_ref()
     }
 }
+
+/// A document parsed off the tab's task, waiting to replace the current one.
+#[derive(Default)]
+struct ParsedDocument {
+    html: String,
+    forms: FormState,
+    issues: Vec<ParseIssue>,
+}
+
+/// Request body of a form submission.
+enum Upload {
+    /// `application/x-www-form-urlencoded` form data
+    Form(Vec<u8>),
+    /// Form data with files, streamed from disk
+    Multipart(MultipartForm),
+}
+
+/// A message logged by the document, about the current document unless it says otherwise.
+#[derive(Debug, Clone)]
+pub(crate) struct ConsoleMessage {
+    pub(crate) level: ConsoleLevel,
+    pub(crate) text: String,
+    /// Line in the document, starting at 1
+    pub(crate) line: Option<u32>,
+}
+
+/// Walks the error chain looking for a [`SandboxViolation`] raised by the redirect policy.
+fn find_violation(err: &reqwest::Error) -> Option<SandboxViolation> {
+    let mut source = std::error::Error::source(err);
+    while let Some(e) = source {
+        if let Some(v) = e.downcast_ref::<SandboxViolation>() {
+            return Some(v.clone());
+        }
+        source = e.source();
+    }
+    None
+}
@@ -10,11 +10,16 @@
 //! - [`Cookie`] — Represents a single HTTP cookie (name, value, domain, path, expiry, etc.).
 //! - [`CookieJar`] — In-memory cookie jar with full RFC 6265 handling.
 //! - [`DefaultCookieJar`] — The engine's default [`CookieJar`] implementation.
+//! - [`CookieContext`] — How a request relates to the page that made it, for `SameSite`.
 //! - [`PersistentCookieJar`] — A [`CookieJar`] wrapper that persists its state
 //!   to a [`CookieStore`] backend.
 //! - [`CookieStore`] — Abstract trait for reading/writing cookies to persistent storage.
 //! - [`JsonCookieStore`] — Simple JSON-based cookie store (human-readable, easy to debug).
 //! - [`SqliteCookieStore`] — SQLite-based cookie store (efficient for large sets).
+//! - [`CookieEvent`] — Describes a cookie being added, removed or expired in a zone.
+//! - [`CookiePolicy`] — Decides which cookies a zone stores, see [`BasicCookiePolicy`].
+//! - [`parse_netscape_cookies`], `read_chromium_cookies`, `read_firefox_cookies` — importers
+//!   for cookies exported by other browsers (see the `import` module docs).
 //!
 //! Internally, `CookieJarHandle` and `CookieStoreHandle` are reference-counted handles
 //! used to share jars and stores safely between threads/zones.
@@ -64,17 +69,36 @@
 //!
 mod cookie_jar;
 mod cookies;
+pub(crate) mod date;
+mod event;
+mod import;
+pub(crate) mod notifying_cookie_jar;
 mod persistent_cookie_jar;
+mod policy;
+mod set_cookie;
 mod store;
 
 pub use cookies::Cookie;
 pub use cookies::CookieJarHandle;
 pub(crate) use cookies::CookieStoreHandle;
 
+pub use cookie_jar::CookieContext;
 pub use cookie_jar::CookieJar;
 pub use cookie_jar::DefaultCookieJar;
 pub use persistent_cookie_jar::PersistentCookieJar;
+pub(crate) use policy::allowed_cookies;
+pub use policy::{BasicCookiePolicy, CookiePolicy, CookiePolicyHandle, CookieRequest};
+#[cfg(feature = "fuzzing")]
+pub(crate) use set_cookie::parse_set_cookie;
+
+pub use event::{CookieChange, CookieEvent};
+pub use notifying_cookie_jar::CookieSubscription;
+
+pub use import::parse_netscape_cookies;
+#[cfg(feature = "sqlite_cookie_store")]
+pub use import::{read_chromium_cookies, read_firefox_cookies};
 
 pub use store::CookieStore;
 pub use store::JsonCookieStore;
+#[cfg(feature = "sqlite_cookie_store")]
 pub use store::SqliteCookieStore;
@@ -9,23 +9,30 @@
 //! and parses a subset of RFC 6265 `Set-Cookie` semantics.
 //!
 //! ## Notes & limitations
-//! - Parsing is intentionally **minimal**: attributes like `Expires`, `Path`,
-//!   `Domain`, `Secure`, `HttpOnly`, and `SameSite` are handled; `Max-Age`,
-//!   priorities, size limits, eviction policies, and expiration enforcement are
-//!   not (yet) implemented.
+//! - `Set-Cookie` headers are parsed as RFC 6265 §5.2 describes, including `Max-Age`;
+//!   priorities, size limits and eviction policies are not (yet) implemented.
+//! - Expired cookies are only purged when [`CookieJar::remove_expired`] is called
+//!   (zones do this periodically). A `Set-Cookie` with an expiry in the past deletes
+//!   the cookie right away.
 //! - Cookies are bucketed by **origin** (`url.origin().ascii_serialization()`).
-//!   Within a bucket, simple host/subdomain and path prefix checks are applied.
+//!   Within a bucket, simple host/subdomain and path checks are applied.
+//! - `SameSite` is enforced against the [`CookieContext`] of a request. Sites are
+//!   registrable domains from the Public Suffix List; IP addresses only match themselves.
 //! - This module is **not** internally synchronized. Use it via a
 //!   `CookieJarHandle = Arc<RwLock<dyn CookieJar + Send + Sync>>`.
 //!
 //! See also: RFC 6265bis (HTTP State Management Mechanism).
 //!
-use crate::engine::cookies::Cookie;
+use crate::engine::cookies::notifying_cookie_jar::NotifyingCookieJar;
+use crate::engine::cookies::set_cookie::{default_path, parse_set_cookie};
+use crate::engine::cookies::{Cookie, PersistentCookieJar};
+use crate::net::site;
 use http::HeaderMap;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::collections::HashMap;
-use url::Url;
+use std::time::SystemTime;
+use url::{Host, Url};
 
 /// A cookie jar keeps the cookies for one single zone.
 ///
@@ -54,6 +61,15 @@ pub trait CookieJar: Send + Sync {
     /// `None` means no cookies match the request.
     fn get_request_cookies(&self, url: &Url) -> Option<String>;
 
+    /// Returns the `Cookie` request header value to send for a request to `url` made in
+    /// `context`, leaving out `SameSite` cookies that the context does not allow.
+    ///
+    /// The default implementation ignores `SameSite` and returns
+    /// [`get_request_cookies`](Self::get_request_cookies).
+    fn get_request_cookies_in(&self, url: &Url, _context: CookieContext) -> Option<String> {
+        self.get_request_cookies(url)
+    }
+
     /// Removes all cookies from the jar.
     fn clear(&mut self);
 
@@ -67,6 +83,89 @@ pub trait CookieJar: Send + Sync {
 
     /// Removes all cookies associated with `url` (bucketed by its origin).
     fn remove_cookies_for_url(&mut self, url: &Url);
+
+    /// Inserts already-parsed cookies, each bucketed under the origin of its URL.
+    ///
+    /// Used by the importers in [`cookies`](crate::cookies) to bring over cookies from other
+    /// browsers. A cookie replaces an existing one with the same name in the same bucket.
+    fn insert_cookies(&mut self, cookies: Vec<(Url, Cookie)>);
+
+    /// Removes all cookies that have expired at `now` and returns them with their origin.
+    ///
+    /// The default implementation does not track expiry and removes nothing.
+    fn remove_expired(&mut self, _now: SystemTime) -> Vec<(Url, Cookie)> {
+        Vec::new()
+    }
+}
+
+/// How a request relates to the page that made it, which decides which `SameSite` cookies
+/// go along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieContext {
+    /// A request to the site of the page that made it, or a navigation the user started.
+    /// All cookies go along.
+    SameSite,
+    /// A top-level navigation from another site with a safe method, such as following a link.
+    /// `SameSite=Strict` cookies stay behind.
+    CrossSiteNavigation,
+    /// Any other request from another site, such as a form POST or a subresource. Only
+    /// cookies without `SameSite`, or with `SameSite=None`, go along.
+    CrossSite,
+}
+
+impl CookieContext {
+    /// Returns the context of a top-level navigation to `url`, started by the page at
+    /// `initiator` (`None` when the user started it). `safe_method` is true for GET loads.
+    pub fn for_navigation(url: &Url, initiator: Option<&Url>, safe_method: bool) -> Self {
+        match initiator {
+            Some(initiator) if !same_site(url, initiator) => {
+                if safe_method {
+                    CookieContext::CrossSiteNavigation
+                } else {
+                    CookieContext::CrossSite
+                }
+            }
+            _ => CookieContext::SameSite,
+        }
+    }
+
+    /// Returns the context of a request for a resource at `url` of the document at
+    /// `document`.
+    pub fn for_subresource(url: &Url, document: &Url) -> Self {
+        if same_site(url, document) {
+            CookieContext::SameSite
+        } else {
+            CookieContext::CrossSite
+        }
+    }
+
+    fn allows(self, same_site: Option<&str>) -> bool {
+        match (self, same_site) {
+            (CookieContext::SameSite, _) => true,
+            (_, Some("Strict")) => false,
+            (CookieContext::CrossSiteNavigation, Some("Lax")) => true,
+            (CookieContext::CrossSite, Some("Lax")) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Whether `a` and `b` are on the same site: the same scheme and registrable domain.
+fn same_site(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && match (a.host(), b.host()) {
+            (Some(Host::Domain(a)), Some(Host::Domain(b))) => site(a).eq_ignore_ascii_case(site(b)),
+            (Some(a), Some(b)) => a == b,
+            _ => a.origin() == b.origin(),
+        }
+}
+
+/// Whether a request for `path` matches the cookie path `cookie_path` (RFC 6265, 5.1.4).
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    match path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
 }
 
 /// Default cookie jar which holds cookies for a single zone.
@@ -81,7 +180,8 @@ pub trait CookieJar: Send + Sync {
 ///   (stored as raw string), `SameSite` (`Strict`/`Lax`/`None`, case-insensitive),
 ///   `Secure`, `HttpOnly`.
 /// - If `Path` is absent, a default path is derived from the request URL.
-/// - No expiration or eviction is enforced; `expires` is stored but not acted upon.
+/// - A cookie whose `Expires` lies in the past deletes the stored cookie with that name.
+/// - Other expired cookies stay until [`CookieJar::remove_expired`] is called.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefaultCookieJar {
     /// Simple hashmap of cookies, bucketed by **origin**.
@@ -98,6 +198,37 @@ impl DefaultCookieJar {
             entries: HashMap::new(),
         }
     }
+
+    /// Takes a snapshot of the cookies held by `jar`.
+    ///
+    /// Works for a [`DefaultCookieJar`], and for the engine's decorators (such as a
+    /// [`PersistentCookieJar`]) wrapping one. Returns `None` for other jar implementations,
+    /// as their state cannot be inspected.
+    pub fn snapshot_of(jar: &dyn CookieJar) -> Option<DefaultCookieJar> {
+        DefaultCookieJar::inspect(jar, DefaultCookieJar::clone)
+    }
+
+    /// Runs `f` on the [`DefaultCookieJar`] behind `jar`, without copying it. Looks through
+    /// the same decorators as [`DefaultCookieJar::snapshot_of`].
+    pub(crate) fn inspect<R>(
+        jar: &dyn CookieJar,
+        f: impl FnOnce(&DefaultCookieJar) -> R,
+    ) -> Option<R> {
+        if let Some(default) = jar.as_any().downcast_ref::<DefaultCookieJar>() {
+            return Some(f(default));
+        }
+
+        let inner = if let Some(persistent) = jar.as_any().downcast_ref::<PersistentCookieJar>() {
+            persistent.inner.clone()
+        } else {
+            jar.as_any()
+                .downcast_ref::<NotifyingCookieJar>()?
+                .inner
+                .clone()
+        };
+        let inner = inner.read().ok()?;
+        DefaultCookieJar::inspect(&*inner, f)
+    }
 }
 
 impl CookieJar for DefaultCookieJar {
@@ -110,86 +241,39 @@ impl CookieJar for DefaultCookieJar {
 
     fn store_response_cookies(&mut self, url: &Url, headers: &HeaderMap) {
         let origin = url.origin().ascii_serialization();
-        let _host = url.host_str().unwrap_or_default();
-        let default_path =
-            url.path()
-                .rsplit_once('/')
-                .map_or("/", |(a, _)| if a.is_empty() { "/" } else { a });
+        let default_path = default_path(url);
 
         let bucket = self.entries.entry(origin).or_default();
+        let now = SystemTime::now();
 
         for header in headers.get_all("set-cookie") {
-            if let Ok(header_str) = header.to_str() {
-                if let Some((name, rest)) = header_str.split_once('=') {
-                    let mut cookie = Cookie {
-                        name: name.trim().to_string(),
-                        value: String::new(),
-                        path: None,
-                        domain: None,
-                        secure: false,
-                        expires: None,
-                        same_site: None,
-                        http_only: false,
-                    };
-
-                    for part in rest.split(';') {
-                        let part = part.trim();
-                        if cookie.value.is_empty() {
-                            cookie.value = part.to_string();
-                            continue;
-                        }
-
-                        if let Some((k, v)) = part.split_once('=') {
-                            match k.to_ascii_lowercase().as_str() {
-                                "path" => cookie.path = Some(v.to_string()),
-                                "domain" => {
-                                    cookie.domain = Some(v.trim_start_matches('.').to_string())
-                                }
-                                "expires" => cookie.expires = Some(v.to_string()),
-                                "samesite" => {
-                                    // normalize to "Lax" | "Strict" | "None"
-                                    let val = v.trim();
-                                    if val.eq_ignore_ascii_case("lax") {
-                                        cookie.same_site = Some("Lax".to_string());
-                                    } else if val.eq_ignore_ascii_case("strict") {
-                                        cookie.same_site = Some("Strict".to_string());
-                                    } else if val.eq_ignore_ascii_case("none") {
-                                        cookie.same_site = Some("None".to_string());
-                                        // Optional hardening: SameSite=None SHOULD be Secure.
-                                        // If you want to enforce it, uncomment the next line.
-                                        // if !cookie.secure { cookie.secure = true; }
-                                    } else {
-                                        // leave as-is if unknown, or set Some(val.to_string())
-                                        cookie.same_site = Some(val.to_string());
-                                    }
-                                }
-                                _ => {}
-                            }
-                        } else {
-                            if part.eq_ignore_ascii_case("secure") {
-                                cookie.secure = true;
-                            } else if part.eq_ignore_ascii_case("httponly") {
-                                cookie.http_only = true;
-                            }
-                        }
-                    }
-
-                    if cookie.path.is_none() {
-                        cookie.path = Some(default_path.to_string());
-                    }
-
-                    // Replace existing cookie with same name
-                    if let Some(existing) = bucket.iter_mut().find(|c| c.name == cookie.name) {
-                        *existing = cookie;
-                    } else {
-                        bucket.push(cookie);
-                    }
-                }
+            let Some(cookie) = std::str::from_utf8(header.as_bytes())
+                .ok()
+                .and_then(|h| parse_set_cookie(h, default_path, now))
+            else {
+                continue;
+            };
+
+            // An expiry in the past is how servers delete cookies
+            if cookie.is_expired(now) {
+                bucket.retain(|c| c.name != cookie.name);
+                continue;
+            }
+
+            // Replace existing cookie with same name
+            if let Some(existing) = bucket.iter_mut().find(|c| c.name == cookie.name) {
+                *existing = cookie;
+            } else {
+                bucket.push(cookie);
             }
         }
     }
 
     fn get_request_cookies(&self, url: &Url) -> Option<String> {
+        self.get_request_cookies_in(url, CookieContext::SameSite)
+    }
+
+    fn get_request_cookies_in(&self, url: &Url, context: CookieContext) -> Option<String> {
         let origin = url.origin().ascii_serialization();
         let host = url.host_str().unwrap_or_default();
         let path = url.path();
@@ -209,7 +293,7 @@ impl CookieJar for DefaultCookieJar {
             .filter(|cookie| {
                 // Check path match
                 match &cookie.path {
-                    Some(cookie_path) => path.starts_with(cookie_path),
+                    Some(cookie_path) => path_matches(path, cookie_path),
                     None => true,
                 }
             })
@@ -217,6 +301,7 @@ impl CookieJar for DefaultCookieJar {
                 // Check secure
                 !cookie.secure || is_https
             })
+            .filter(|cookie| context.allows(cookie.same_site.as_deref()))
             .map(|c| format!("{}={}", c.name, c.value))
             .collect::<Vec<_>>()
             .join("; ");
@@ -259,4 +344,159 @@ impl CookieJar for DefaultCookieJar {
         let origin = url.origin().ascii_serialization();
         self.entries.remove(&origin);
     }
+
+    fn insert_cookies(&mut self, cookies: Vec<(Url, Cookie)>) {
+        for (url, cookie) in cookies {
+            let bucket = self
+                .entries
+                .entry(url.origin().ascii_serialization())
+                .or_default();
+            if let Some(existing) = bucket.iter_mut().find(|c| c.name == cookie.name) {
+                *existing = cookie;
+            } else {
+                bucket.push(cookie);
+            }
+        }
+    }
+
+    fn remove_expired(&mut self, now: SystemTime) -> Vec<(Url, Cookie)> {
+        let mut expired = Vec::new();
+
+        for (origin, cookies) in self.entries.iter_mut() {
+            let Ok(url) = Url::parse(origin) else {
+                continue;
+            };
+            cookies.retain(|c| {
+                if c.is_expired(now) {
+                    expired.push((url.clone(), c.clone()));
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        self.entries.retain(|_, cookies| !cookies.is_empty());
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{cookie_header, engine_with_tab, wait_for_load};
+    use crate::{EngineCommand, GosubEngine};
+    use std::io::{Read, Write};
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    /// Answers every request with an empty page that sets `set_cookies` (each ending in
+    /// `\r\n`), and sends the `Cookie` header of each page request on the returned channel.
+    /// Favicon requests and connections that send nothing are not reported.
+    fn serve_recording_cookies(set_cookies: &'static str) -> (u16, Receiver<Option<String>>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                if !request.is_empty() && !request.starts_with("GET /favicon.ico ") {
+                    let _ = tx.send(cookie_header(&request));
+                }
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n{set_cookies}Content-Length: 0\r\n\r\n"
+                );
+            }
+        });
+        (port, rx)
+    }
+
+    #[test]
+    fn sites_are_registrable_domains() {
+        let url = |s: &str| Url::parse(s).unwrap();
+        let context = |a: &str, b: &str| CookieContext::for_subresource(&url(a), &url(b));
+
+        assert_eq!(
+            context("https://a.example.co.uk/", "https://b.example.co.uk/"),
+            CookieContext::SameSite
+        );
+        assert_eq!(
+            context("https://a.co.uk/", "https://b.co.uk/"),
+            CookieContext::CrossSite
+        );
+        assert_eq!(
+            context("https://alice.github.io/", "https://bob.github.io/"),
+            CookieContext::CrossSite
+        );
+        assert_eq!(
+            context("http://127.0.0.1/", "http://127.0.0.1:8080/"),
+            CookieContext::SameSite
+        );
+        assert_eq!(
+            context("http://10.0.0.1/", "http://20.0.0.1/"),
+            CookieContext::CrossSite
+        );
+        assert_eq!(
+            context("http://[::1]/", "http://[::2]/"),
+            CookieContext::CrossSite
+        );
+    }
+
+    #[test]
+    fn cookies_are_sent_back_by_same_site_rules() {
+        let (port, requests) = serve_recording_cookies(
+            "Set-Cookie: strict=1; SameSite=Strict\r\n\
+             Set-Cookie: lax=2; SameSite=Lax\r\n\
+             Set-Cookie: plain=3\r\n\
+             Set-Cookie: secure=4; Secure\r\n\
+             Set-Cookie: admin=5; Path=/admin\r\n",
+        );
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/administration")).unwrap();
+
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let tab = engine.get_tab(tab_id).unwrap();
+        let load = |engine: &mut GosubEngine| {
+            wait_for_load(engine, &rx);
+            requests.recv_timeout(Duration::from_secs(1)).unwrap()
+        };
+
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        assert_eq!(load(&mut engine), None);
+
+        // Navigations the user starts are same-site
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        assert_eq!(
+            load(&mut engine).as_deref(),
+            Some("strict=1; lax=2; plain=3")
+        );
+
+        // Forms on another site submit cross-site
+        let submit_from_other_site = |engine: &mut GosubEngine, method: &str| {
+            {
+                let mut tab = tab.lock().unwrap();
+                tab.context.set_raw_html(&format!(
+                    "<form method={method} action={url}><button>Go</button></form>"
+                ));
+                tab.current_url = Some(Url::parse("https://other.test/").unwrap());
+                tab.submit_form(0);
+            }
+            load(engine)
+        };
+        assert_eq!(
+            submit_from_other_site(&mut engine, "get").as_deref(),
+            Some("lax=2; plain=3")
+        );
+        assert_eq!(
+            submit_from_other_site(&mut engine, "post").as_deref(),
+            Some("plain=3")
+        );
+    }
 }
@@ -45,10 +45,12 @@
 //! };
 //! ```
 
+use crate::engine::cookies::date::parse_cookie_date;
 use crate::engine::cookies::store::CookieStore;
 use crate::engine::cookies::CookieJar;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// A handle to a cookie jar trait.
 ///
@@ -82,7 +84,7 @@ pub type CookieStoreHandle = Arc<dyn CookieStore + Send + Sync>;
 ///
 /// This structure captures the essential attributes of an HTTP cookie and
 /// is suitable for persistence (e.g., JSON, SQLite) via `serde`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Cookie {
     /// Cookie name (case-sensitive).
     pub name: String,
@@ -115,3 +117,26 @@ pub struct Cookie {
     /// If `true`, cookie is blocked from access by client-side scripts (`document.cookie`).
     pub http_only: bool,
 }
+
+impl Cookie {
+    /// Returns the expiry as a UNIX timestamp (seconds), if it is set and can be parsed.
+    ///
+    /// Both ISO 8601 and the HTTP date formats found in `Set-Cookie` headers are understood.
+    pub fn expires_at(&self) -> Option<i64> {
+        self.expires.as_deref().and_then(parse_cookie_date)
+    }
+
+    /// Returns `true` when the cookie has an expiry that lies at or before `now`.
+    ///
+    /// Session cookies and cookies with an unparsable expiry never expire.
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        let Some(expires_at) = self.expires_at() else {
+            return false;
+        };
+        let now = match now.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        expires_at <= now
+    }
+}
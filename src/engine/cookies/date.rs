@@ -0,0 +1,191 @@
+//! Date helpers for cookie expiry handling.
+//!
+//! Cookies carry their expiry as a string: either the raw `Expires` attribute from a
+//! `Set-Cookie` header (e.g. `Wed, 21 Oct 2015 07:28:00 GMT`) or an ISO 8601 timestamp
+//! (e.g. `2015-10-21T07:28:00Z`, as written by the importers). These helpers convert
+//! between those strings and UNIX timestamps without pulling in a date/time crate.
+
+/// Parses a cookie expiry string into a UNIX timestamp (seconds).
+///
+/// Accepts ISO 8601 UTC timestamps and the date formats allowed by the cookie date
+/// algorithm of RFC 6265 §5.1.1 (IMF-fixdate, RFC 850 and asctime variants).
+/// Returns `None` when the string cannot be parsed.
+pub(crate) fn parse_cookie_date(s: &str) -> Option<i64> {
+    parse_iso8601(s.trim()).or_else(|| parse_rfc6265(s))
+}
+
+/// Converts a UNIX timestamp (seconds) into an ISO 8601 UTC string (`YYYY-MM-DDThh:mm:ssZ`).
+pub(crate) fn unix_to_iso8601(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant), valid for the proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Number of days since 1970-01-01 for the given civil date (inverse of the above).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn to_unix(year: i64, month: i64, day: i64, h: i64, m: i64, s: i64) -> Option<i64> {
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || h > 23 || m > 59 || s > 59 {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * 86_400 + h * 3600 + m * 60 + s)
+}
+
+/// Parses `YYYY-MM-DDThh:mm:ss` with an optional `Z` or `+00:00` suffix.
+fn parse_iso8601(s: &str) -> Option<i64> {
+    let s = s
+        .strip_suffix('Z')
+        .or_else(|| s.strip_suffix("+00:00"))
+        .unwrap_or(s);
+    let (date, time) = s.split_once('T')?;
+
+    let mut d = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (d.next()??, d.next()??, d.next()??);
+
+    // Ignore fractional seconds
+    let time = time.split('.').next()?;
+    let mut t = time.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (h, m, sec) = (t.next()??, t.next()??, t.next()??);
+
+    to_unix(year, month, day, h, m, sec)
+}
+
+/// RFC 6265 §5.1.1 cookie date parsing.
+fn parse_rfc6265(s: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let is_delimiter = |c: char| matches!(c, '\x09' | '\x20'..='\x2F' | '\x3B'..='\x40' | '\x5B'..='\x60' | '\x7B'..='\x7E');
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+
+    for token in s.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            let parts: Vec<&str> = token.splitn(3, ':').collect();
+            if parts.len() == 3 {
+                let digits = |p: &str| {
+                    let n: String = p.chars().take_while(|c| c.is_ascii_digit()).collect();
+                    (1..=2)
+                        .contains(&n.len())
+                        .then(|| n.parse::<i64>().ok())
+                        .flatten()
+                };
+                if let (Some(h), Some(m), Some(sec)) =
+                    (digits(parts[0]), digits(parts[1]), digits(parts[2]))
+                {
+                    time = Some((h, m, sec));
+                    continue;
+                }
+            }
+        }
+
+        let leading: String = token.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+        if day.is_none() && (1..=2).contains(&leading.len()) {
+            day = leading.parse::<i64>().ok();
+            continue;
+        }
+
+        if month.is_none() && token.len() >= 3 {
+            let prefix = token[..3].to_ascii_lowercase();
+            if let Some(idx) = MONTHS.iter().position(|m| *m == prefix) {
+                month = Some(idx as i64 + 1);
+                continue;
+            }
+        }
+
+        if year.is_none() && (2..=4).contains(&leading.len()) {
+            year = leading.parse::<i64>().ok();
+            continue;
+        }
+    }
+
+    let mut year = year?;
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if (0..=69).contains(&year) {
+        year += 2000;
+    }
+    if year < 1601 {
+        return None;
+    }
+
+    let (h, m, sec) = time?;
+    to_unix(year, month?, day?, h, m, sec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_to_iso8601_known_values() {
+        assert_eq!(unix_to_iso8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(unix_to_iso8601(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(unix_to_iso8601(1_767_225_599), "2025-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn parses_iso8601_and_round_trips() {
+        assert_eq!(
+            parse_cookie_date("2025-12-31T23:59:59Z"),
+            Some(1_767_225_599)
+        );
+        assert_eq!(
+            parse_cookie_date("2000-02-29T00:00:00.250Z"),
+            Some(951_782_400)
+        );
+        assert_eq!(
+            parse_cookie_date(&unix_to_iso8601(1_445_412_480)),
+            Some(1_445_412_480)
+        );
+    }
+
+    #[test]
+    fn parses_http_date_variants() {
+        // 2015-10-21T07:28:00Z
+        let expected = Some(1_445_412_480);
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), expected);
+        assert_eq!(
+            parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"),
+            expected
+        );
+        assert_eq!(parse_cookie_date("Wed Oct 21 07:28:00 2015"), expected);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse_cookie_date("tomorrow"), None);
+        assert_eq!(parse_cookie_date("Wed, 32 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_cookie_date(""), None);
+    }
+}
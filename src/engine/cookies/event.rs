@@ -0,0 +1,41 @@
+//! Cookie change events.
+//!
+//! Zones wrap their cookie jar so every mutation is reported as a [`CookieEvent`].
+//! Subscribe through [`Zone::subscribe_cookie_events`](crate::zone::Zone::subscribe_cookie_events)
+//! to keep a cookie-manager UI in sync with the jar.
+use crate::engine::cookies::Cookie;
+use crate::tab::TabId;
+use crate::zone::ZoneId;
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Kind of change that happened to a cookie.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum CookieChange {
+    /// The cookie was added, or an existing cookie with the same name was overwritten.
+    Added,
+    /// The cookie was removed (explicitly, by clearing the jar, or by the server
+    /// sending an already-expired `Set-Cookie`).
+    Removed,
+    /// The cookie reached its expiry date and was purged from the jar.
+    Expired,
+}
+
+/// Describes a single change to a cookie in a zone's cookie jar.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct CookieEvent {
+    /// The zone whose cookie jar changed.
+    pub zone: ZoneId,
+    /// The tab that caused the change, if any. `None` for changes made through the
+    /// zone's jar directly (e.g. by the embedder) and for expiry sweeps.
+    pub source_tab: Option<TabId>,
+    /// The origin bucket the cookie belongs to.
+    pub origin: Url,
+    /// The cookie after the change (for `Added`) or before it (for `Removed`/`Expired`).
+    pub cookie: Cookie,
+    /// What happened to the cookie.
+    pub change: CookieChange,
+}
@@ -0,0 +1,318 @@
+//! Cookie importers for browser-standard formats.
+//!
+//! These helpers read cookies exported by other browsers so users migrating to a
+//! Gosub-based user agent can carry their sessions over. Every importer returns a list of
+//! `(Url, Cookie)` pairs, where the URL is the origin bucket the cookie belongs to. Feed the
+//! result into [`CookieJar::insert_cookies`](crate::cookies::CookieJar::insert_cookies) or
+//! [`CookieStore::import_cookies`](crate::cookies::CookieStore::import_cookies).
+//!
+//! Supported formats:
+//! - Netscape `cookies.txt` (as written by curl, wget and most "export cookies" extensions)
+//! - Chromium `Cookies` SQLite database (requires the `sqlite_cookie_store` feature)
+//! - Firefox `cookies.sqlite` database (requires the `sqlite_cookie_store` feature)
+//!
+//! ## Notes & limitations
+//! - Cookies are bucketed under the `https://` origin of their host, since the engine jar
+//!   keys cookies by origin.
+//! - Chromium encrypts cookie values on most platforms. Encrypted cookies cannot be
+//!   imported and are skipped.
+//! - Expiry timestamps are converted to ISO 8601 (`YYYY-MM-DDThh:mm:ssZ`). Session cookies
+//!   have no expiry.
+//!
+//! ## Example
+//! ```rust,no_run
+//! use gosub_engine::cookies::{parse_netscape_cookies, CookieStore, SqliteCookieStore};
+//! use gosub_engine::zone::ZoneId;
+//!
+//! let contents = std::fs::read_to_string("cookies.txt").unwrap();
+//! let cookies = parse_netscape_cookies(&contents).unwrap();
+//!
+//! let store = SqliteCookieStore::new("cookies.db".into());
+//! store.import_cookies(ZoneId::new(), cookies);
+//! ```
+
+use crate::engine::cookies::date::unix_to_iso8601;
+use crate::engine::cookies::Cookie;
+use anyhow::{anyhow, bail, Result};
+use url::Url;
+
+/// Parses the contents of a Netscape `cookies.txt` file.
+///
+/// Each non-comment line holds seven tab-separated fields:
+/// `domain`, `include_subdomains`, `path`, `secure`, `expiry`, `name` and `value`.
+/// Lines prefixed with `#HttpOnly_` are HttpOnly cookies; other `#` lines are comments.
+///
+/// # Errors
+/// Returns an error (naming the line number) when a line is malformed.
+pub fn parse_netscape_cookies(input: &str) -> Result<Vec<(Url, Cookie)>> {
+    let mut cookies = Vec::new();
+
+    for (idx, raw) in input.lines().enumerate() {
+        let line_no = idx + 1;
+        let mut line = raw.trim_end_matches('\r');
+
+        let http_only = match line.strip_prefix("#HttpOnly_") {
+            Some(rest) => {
+                line = rest;
+                true
+            }
+            None => false,
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            bail!(
+                "line {line_no}: expected 7 tab-separated fields, got {}",
+                fields.len()
+            );
+        }
+
+        let expiry: i64 = fields[4]
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("line {line_no}: invalid expiry {:?}", fields[4]))?;
+
+        let cookie = Cookie {
+            name: fields[5].to_string(),
+            value: fields[6].to_string(),
+            path: Some(fields[2].to_string()),
+            domain: None,
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            expires: (expiry > 0).then(|| unix_to_iso8601(expiry)),
+            same_site: None,
+            http_only,
+        };
+
+        let include_subdomains = fields[1].eq_ignore_ascii_case("TRUE");
+        cookies.push(bucket_cookie(
+            fields[0],
+            include_subdomains,
+            cookie,
+            line_no,
+        )?);
+    }
+
+    Ok(cookies)
+}
+
+/// Reads all cookies from a Chromium (Chrome, Edge, Brave, ...) `Cookies` SQLite database.
+///
+/// The database should not be in use by the browser while importing. Cookies whose value is
+/// only available in encrypted form (a non-empty `encrypted_value`) are skipped.
+#[cfg(feature = "sqlite_cookie_store")]
+pub fn read_chromium_cookies(path: &std::path::Path) -> Result<Vec<(Url, Cookie)>> {
+    use r2d2_sqlite::rusqlite::{Connection, OpenFlags};
+
+    // Chromium stores expiry as microseconds since 1601-01-01.
+    const WINDOWS_TO_UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(
+        "SELECT host_key, name, value, path, expires_utc, is_secure, is_httponly, samesite,
+                length(encrypted_value)
+         FROM cookies",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)? != 0,
+            row.get::<_, i64>(6)? != 0,
+            row.get::<_, i64>(7)?,
+            row.get::<_, Option<i64>>(8)?.unwrap_or(0) > 0,
+        ))
+    })?;
+
+    let mut cookies = Vec::new();
+    for (idx, row) in rows.enumerate() {
+        let (host, name, value, path, expires_utc, secure, http_only, same_site, encrypted) = row?;
+        if encrypted {
+            // Encrypted cookie (value lives in `encrypted_value`)
+            continue;
+        }
+
+        let expires = (expires_utc > 0)
+            .then(|| unix_to_iso8601(expires_utc / 1_000_000 - WINDOWS_TO_UNIX_EPOCH_SECS));
+        let cookie = Cookie {
+            name,
+            value,
+            path: Some(path),
+            domain: None,
+            secure,
+            expires,
+            same_site: same_site_from_int(same_site),
+            http_only,
+        };
+
+        cookies.push(bucket_cookie(
+            &host,
+            host.starts_with('.'),
+            cookie,
+            idx + 1,
+        )?);
+    }
+
+    Ok(cookies)
+}
+
+/// Reads all cookies from a Firefox `cookies.sqlite` database.
+///
+/// The database should not be in use by the browser while importing.
+#[cfg(feature = "sqlite_cookie_store")]
+pub fn read_firefox_cookies(path: &std::path::Path) -> Result<Vec<(Url, Cookie)>> {
+    use r2d2_sqlite::rusqlite::{Connection, OpenFlags};
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare(
+        "SELECT host, name, value, path, expiry, isSecure, isHttpOnly, sameSite FROM moz_cookies",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)? != 0,
+            row.get::<_, i64>(6)? != 0,
+            row.get::<_, i64>(7)?,
+        ))
+    })?;
+
+    let mut cookies = Vec::new();
+    for (idx, row) in rows.enumerate() {
+        let (host, name, value, path, expiry, secure, http_only, same_site) = row?;
+
+        // Recent Firefox versions store the expiry in milliseconds instead of seconds.
+        let expiry = if expiry > 100_000_000_000 {
+            expiry / 1000
+        } else {
+            expiry
+        };
+        let cookie = Cookie {
+            name,
+            value,
+            path: Some(path),
+            domain: None,
+            secure,
+            expires: (expiry > 0).then(|| unix_to_iso8601(expiry)),
+            same_site: same_site_from_int(same_site),
+            http_only,
+        };
+
+        cookies.push(bucket_cookie(
+            &host,
+            host.starts_with('.'),
+            cookie,
+            idx + 1,
+        )?);
+    }
+
+    Ok(cookies)
+}
+
+/// Maps a numeric SameSite value (0 = None, 1 = Lax, 2 = Strict) to the engine's string form.
+/// Any other value means the attribute was not set.
+#[cfg(feature = "sqlite_cookie_store")]
+fn same_site_from_int(v: i64) -> Option<String> {
+    match v {
+        0 => Some("None".to_string()),
+        1 => Some("Lax".to_string()),
+        2 => Some("Strict".to_string()),
+        _ => None,
+    }
+}
+
+/// Determines the origin bucket for a cookie on `domain`, and sets the domain attribute
+/// when the cookie is shared with subdomains.
+fn bucket_cookie(
+    domain: &str,
+    include_subdomains: bool,
+    mut cookie: Cookie,
+    record: usize,
+) -> Result<(Url, Cookie)> {
+    let host = domain.trim().trim_start_matches('.');
+    if host.is_empty() {
+        bail!("record {record}: empty cookie domain");
+    }
+
+    let url = Url::parse(&format!("https://{host}/"))
+        .map_err(|e| anyhow!("record {record}: invalid cookie domain {host:?}: {e}"))?;
+
+    if include_subdomains {
+        cookie.domain = Some(host.to_string());
+    }
+
+    Ok((url, cookie))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netscape_parses_fields_comments_and_httponly() {
+        let input = "# Netscape HTTP Cookie File\n\
+                     \n\
+                     .example.com\tTRUE\t/\tTRUE\t1767225599\tsid\tabc=123\n\
+                     #HttpOnly_login.test\tFALSE\t/app\tFALSE\t0\ttoken\txyz\n";
+
+        let cookies = parse_netscape_cookies(input).unwrap();
+        assert_eq!(cookies.len(), 2);
+
+        let (url, c) = &cookies[0];
+        assert_eq!(url.as_str(), "https://example.com/");
+        assert_eq!(c.name, "sid");
+        assert_eq!(c.value, "abc=123");
+        assert_eq!(c.domain.as_deref(), Some("example.com"));
+        assert!(c.secure);
+        assert!(!c.http_only);
+        assert_eq!(c.expires.as_deref(), Some("2025-12-31T23:59:59Z"));
+
+        let (url, c) = &cookies[1];
+        assert_eq!(url.as_str(), "https://login.test/");
+        assert_eq!(c.path.as_deref(), Some("/app"));
+        assert!(c.domain.is_none());
+        assert!(c.http_only);
+        assert!(c.expires.is_none());
+    }
+
+    #[test]
+    fn netscape_rejects_malformed_lines() {
+        let err = parse_netscape_cookies("example.com\tTRUE\t/\n").unwrap_err();
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[cfg(feature = "sqlite_cookie_store")]
+    #[test]
+    fn chromium_skips_only_encrypted_cookies() {
+        use r2d2_sqlite::rusqlite::Connection;
+
+        let path = std::env::temp_dir().join(format!("gosub-chromium-{}.db", uuid::Uuid::new_v4()));
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE cookies (host_key TEXT, name TEXT, value TEXT, path TEXT,
+                 expires_utc INTEGER, is_secure INTEGER, is_httponly INTEGER, samesite INTEGER,
+                 encrypted_value BLOB);
+             INSERT INTO cookies VALUES ('.example.com', 'plain', 'abc', '/', 0, 1, 0, 1, x'');
+             INSERT INTO cookies VALUES ('example.com', 'flag', '', '/', 0, 0, 0, -1, x'');
+             INSERT INTO cookies VALUES ('example.com', 'sealed', '', '/', 0, 0, 0, -1, x'763130');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let cookies = read_chromium_cookies(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let names: Vec<_> = cookies.iter().map(|(_, c)| c.name.as_str()).collect();
+        assert_eq!(names, ["plain", "flag"]);
+        assert_eq!(cookies[1].1.value, "");
+    }
+}
@@ -0,0 +1,397 @@
+//! Cookie jar decorator that publishes change events.
+//!
+//! [`NotifyingCookieJar`] wraps the zone's real jar (which may itself be a
+//! [`PersistentCookieJar`](crate::cookies::PersistentCookieJar)) and publishes a
+//! [`CookieEvent`] for every cookie that is added, removed or expired.
+//!
+//! ## How it works
+//! - Mutations are forwarded to the inner jar. Under the same write lock, the cookies the
+//!   call can touch (the names in its `Set-Cookie` headers, the removed cookie or origin)
+//!   are read before and after, and the difference is published. The exact effect of a
+//!   `Set-Cookie` header (insert, overwrite, deletion, or nothing) is reported without
+//!   looking at the rest of the jar; only [`CookieJar::clear`] reads every cookie.
+//! - The zone holds one jar with no source tab, and every tab gets its own view
+//!   (see [`NotifyingCookieJar::for_tab`]) so events can be attributed to the tab
+//!   that caused them.
+//! - Jars whose state cannot be inspected (see [`DefaultCookieJar::snapshot_of`]) still
+//!   work, but do not produce events.
+use crate::engine::cookies::cookie_jar::DefaultCookieJar;
+use crate::engine::cookies::event::{CookieChange, CookieEvent};
+use crate::engine::cookies::set_cookie::{default_path, parse_set_cookie};
+use crate::engine::cookies::{Cookie, CookieContext, CookieJar, CookieJarHandle};
+use crate::tab::TabId;
+use crate::zone::ZoneId;
+use http::HeaderMap;
+use std::any::Any;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::SystemTime;
+use url::Url;
+
+/// A handle for receiving cookie change notifications.
+pub type CookieSubscription = mpsc::Receiver<CookieEvent>;
+
+/// Internal bus that fans out CookieEvent to subscribers.
+#[derive(Default)]
+pub(crate) struct CookieBus {
+    subs: Mutex<Vec<mpsc::Sender<CookieEvent>>>,
+}
+
+impl CookieBus {
+    pub(crate) fn subscribe(&self) -> CookieSubscription {
+        let (tx, rx) = mpsc::channel();
+        self.subs.lock().unwrap().push(tx);
+        rx
+    }
+    fn publish(&self, ev: CookieEvent) {
+        let mut subs = self.subs.lock().unwrap();
+        subs.retain(|tx| tx.send(ev.clone()).is_ok());
+    }
+}
+
+/// A `CookieJar` decorator that publishes a [`CookieEvent`] for every change.
+pub(crate) struct NotifyingCookieJar {
+    /// Jar that holds the actual cookie state
+    pub(crate) inner: CookieJarHandle,
+    zone: ZoneId,
+    source_tab: Option<TabId>,
+    bus: Arc<CookieBus>,
+}
+
+impl NotifyingCookieJar {
+    /// Wraps `inner` so that changes are published on `bus` on behalf of `zone`.
+    pub(crate) fn new(zone: ZoneId, inner: CookieJarHandle, bus: Arc<CookieBus>) -> Self {
+        Self {
+            inner,
+            zone,
+            source_tab: None,
+            bus,
+        }
+    }
+
+    /// Returns a view on the same jar whose events are attributed to `tab`.
+    pub(crate) fn for_tab(&self, tab: TabId) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            zone: self.zone,
+            source_tab: Some(tab),
+            bus: self.bus.clone(),
+        }
+    }
+
+    /// Runs `f` against the inner jar and publishes the changes it made to the cookies in
+    /// `touched`.
+    fn mutate(&self, touched: Touched, f: impl FnOnce(&mut dyn CookieJar)) {
+        let (before, after) = {
+            let mut inner = self
+                .inner
+                .write()
+                .expect("Failed to acquire write lock on cookie jar");
+            let before = DefaultCookieJar::inspect(&*inner, |jar| touched.cookies_in(jar));
+            f(&mut *inner);
+            let after = DefaultCookieJar::inspect(&*inner, |jar| touched.cookies_in(jar));
+            (before, after)
+        };
+        let (Some(before), Some(after)) = (before, after) else {
+            return;
+        };
+
+        for (origin, cookie) in &after {
+            let unchanged = before.iter().any(|(o, c)| o == origin && c == cookie);
+            if !unchanged {
+                self.publish(origin, cookie.clone(), CookieChange::Added);
+            }
+        }
+        for (origin, cookie) in before {
+            let still_present = after
+                .iter()
+                .any(|(o, c)| *o == origin && c.name == cookie.name);
+            if !still_present {
+                self.publish(&origin, cookie, CookieChange::Removed);
+            }
+        }
+    }
+
+    fn publish(&self, origin: &str, cookie: Cookie, change: CookieChange) {
+        let Ok(origin) = Url::parse(origin) else {
+            return;
+        };
+        self.bus.publish(CookieEvent {
+            zone: self.zone,
+            source_tab: self.source_tab,
+            origin,
+            cookie,
+            change,
+        });
+    }
+}
+
+/// The cookies a mutation of the jar can change.
+enum Touched {
+    /// Cookies with these names, by origin
+    Named(Vec<(String, String)>),
+    /// All cookies of one origin
+    Origin(String),
+    /// Every cookie in the jar
+    All,
+}
+
+impl Touched {
+    /// The cookies named in the `Set-Cookie` headers of a response from `url`.
+    fn set_cookies(url: &Url, headers: &HeaderMap) -> Self {
+        let origin = url.origin().ascii_serialization();
+        let default_path = default_path(url);
+        let now = SystemTime::now();
+        Touched::Named(
+            headers
+                .get_all("set-cookie")
+                .iter()
+                .filter_map(|h| std::str::from_utf8(h.as_bytes()).ok())
+                .filter_map(|h| parse_set_cookie(h, default_path, now))
+                .map(|cookie| (origin.clone(), cookie.name))
+                .collect(),
+        )
+    }
+
+    /// Returns the cookies of `jar` that fall under `self`, with their origin.
+    fn cookies_in(&self, jar: &DefaultCookieJar) -> Vec<(String, Cookie)> {
+        let of_origin = |origin: &String| -> Vec<(String, Cookie)> {
+            jar.entries
+                .get(origin)
+                .into_iter()
+                .flatten()
+                .map(|c| (origin.clone(), c.clone()))
+                .collect()
+        };
+        match self {
+            Touched::Named(names) => {
+                let mut cookies: Vec<(String, Cookie)> = Vec::new();
+                for (origin, name) in names {
+                    let seen = cookies.iter().any(|(o, c)| o == origin && c.name == *name);
+                    let found = jar
+                        .entries
+                        .get(origin)
+                        .and_then(|bucket| bucket.iter().find(|c| c.name == *name));
+                    if let (false, Some(cookie)) = (seen, found) {
+                        cookies.push((origin.clone(), cookie.clone()));
+                    }
+                }
+                cookies
+            }
+            Touched::Origin(origin) => of_origin(origin),
+            Touched::All => jar.entries.keys().flat_map(of_origin).collect(),
+        }
+    }
+}
+
+impl CookieJar for NotifyingCookieJar {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn store_response_cookies(&mut self, url: &Url, headers: &HeaderMap) {
+        self.mutate(Touched::set_cookies(url, headers), |jar| {
+            jar.store_response_cookies(url, headers)
+        });
+    }
+
+    fn get_request_cookies(&self, url: &Url) -> Option<String> {
+        let inner = self
+            .inner
+            .read()
+            .expect("Failed to acquire read lock on cookie jar");
+        inner.get_request_cookies(url)
+    }
+
+    fn get_request_cookies_in(&self, url: &Url, context: CookieContext) -> Option<String> {
+        let inner = self
+            .inner
+            .read()
+            .expect("Failed to acquire read lock on cookie jar");
+        inner.get_request_cookies_in(url, context)
+    }
+
+    fn clear(&mut self) {
+        self.mutate(Touched::All, |jar| jar.clear());
+    }
+
+    fn get_all_cookies(&self) -> Vec<(Url, String)> {
+        let inner = self
+            .inner
+            .read()
+            .expect("Failed to acquire read lock on cookie jar");
+        inner.get_all_cookies()
+    }
+
+    fn remove_cookie(&mut self, url: &Url, cookie_name: &str) {
+        let origin = url.origin().ascii_serialization();
+        self.mutate(
+            Touched::Named(vec![(origin, cookie_name.to_string())]),
+            |jar| jar.remove_cookie(url, cookie_name),
+        );
+    }
+
+    fn remove_cookies_for_url(&mut self, url: &Url) {
+        let origin = url.origin().ascii_serialization();
+        self.mutate(Touched::Origin(origin), |jar| {
+            jar.remove_cookies_for_url(url)
+        });
+    }
+
+    fn insert_cookies(&mut self, cookies: Vec<(Url, Cookie)>) {
+        let touched = Touched::Named(
+            cookies
+                .iter()
+                .map(|(url, c)| (url.origin().ascii_serialization(), c.name.clone()))
+                .collect(),
+        );
+        self.mutate(touched, |jar| jar.insert_cookies(cookies));
+    }
+
+    /// Purges expired cookies and publishes a `CookieExpired` event for each of them.
+    fn remove_expired(&mut self, now: SystemTime) -> Vec<(Url, Cookie)> {
+        let expired = {
+            let mut inner = self
+                .inner
+                .write()
+                .expect("Failed to acquire write lock on cookie jar");
+            inner.remove_expired(now)
+        };
+
+        for (url, cookie) in &expired {
+            self.bus.publish(CookieEvent {
+                zone: self.zone,
+                source_tab: self.source_tab,
+                origin: url.clone(),
+                cookie: cookie.clone(),
+                change: CookieChange::Expired,
+            });
+        }
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::SET_COOKIE;
+    use std::sync::RwLock;
+    use std::time::Duration;
+
+    fn jar() -> (NotifyingCookieJar, CookieSubscription) {
+        let bus = Arc::new(CookieBus::default());
+        let rx = bus.subscribe();
+        let inner: CookieJarHandle = Arc::new(RwLock::new(DefaultCookieJar::new()));
+        (NotifyingCookieJar::new(ZoneId::new(), inner, bus), rx)
+    }
+
+    fn set_cookie(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, value.parse().unwrap());
+        headers
+    }
+
+    fn recv_ok(rx: &CookieSubscription) -> CookieEvent {
+        rx.recv_timeout(Duration::from_millis(200))
+            .expect("expected a CookieEvent")
+    }
+
+    fn recv_none(rx: &CookieSubscription) {
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn set_cookie_publishes_added_with_tab_attribution() {
+        let (zone_jar, rx) = jar();
+        let tab = TabId::new();
+        let mut tab_jar = zone_jar.for_tab(tab);
+        let url = Url::parse("https://example.com/").unwrap();
+
+        tab_jar.store_response_cookies(&url, &set_cookie("sid=1; Path=/"));
+        let ev = recv_ok(&rx);
+        assert_eq!(ev.change, CookieChange::Added);
+        assert_eq!(ev.source_tab, Some(tab));
+        assert_eq!(ev.origin.as_str(), "https://example.com/");
+        assert_eq!(ev.cookie.value, "1");
+
+        // Storing the exact same cookie again is not a change
+        tab_jar.store_response_cookies(&url, &set_cookie("sid=1; Path=/"));
+        recv_none(&rx);
+    }
+
+    #[test]
+    fn a_cookie_set_twice_in_one_response_is_one_change() {
+        let (mut jar, rx) = jar();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        let mut headers = set_cookie("sid=1");
+        headers.append(SET_COOKIE, "sid=2".parse().unwrap());
+        jar.store_response_cookies(&url, &headers);
+        let ev = recv_ok(&rx);
+        assert_eq!(ev.change, CookieChange::Added);
+        assert_eq!(ev.cookie.value, "2");
+        recv_none(&rx);
+
+        // Deleting a cookie that was never stored is not a change either
+        jar.store_response_cookies(
+            &url,
+            &set_cookie("other=1; Expires=Thu, 01 Jan 1970 00:00:00 GMT"),
+        );
+        recv_none(&rx);
+    }
+
+    #[test]
+    fn removals_and_clear_publish_removed() {
+        let (mut jar, rx) = jar();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        jar.store_response_cookies(&url, &set_cookie("a=1"));
+        jar.store_response_cookies(&url, &set_cookie("b=2"));
+        recv_ok(&rx);
+        recv_ok(&rx);
+
+        jar.remove_cookie(&url, "a");
+        let ev = recv_ok(&rx);
+        assert_eq!(ev.change, CookieChange::Removed);
+        assert_eq!(ev.cookie.name, "a");
+        assert!(ev.source_tab.is_none());
+
+        jar.clear();
+        let ev = recv_ok(&rx);
+        assert_eq!(ev.change, CookieChange::Removed);
+        assert_eq!(ev.cookie.name, "b");
+        recv_none(&rx);
+    }
+
+    #[test]
+    fn expired_set_cookie_deletes_and_sweep_publishes_expired() {
+        let (mut jar, rx) = jar();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        jar.store_response_cookies(&url, &set_cookie("a=1"));
+        recv_ok(&rx);
+        jar.store_response_cookies(
+            &url,
+            &set_cookie("a=1; Expires=Thu, 01 Jan 1970 00:00:00 GMT"),
+        );
+        assert_eq!(recv_ok(&rx).change, CookieChange::Removed);
+
+        jar.store_response_cookies(
+            &url,
+            &set_cookie("b=2; Expires=Fri, 01 Jan 2100 00:00:00 GMT"),
+        );
+        recv_ok(&rx);
+
+        let later = SystemTime::UNIX_EPOCH + Duration::from_secs(4_200_000_000);
+        let expired = jar.remove_expired(later);
+        assert_eq!(expired.len(), 1);
+        let ev = recv_ok(&rx);
+        assert_eq!(ev.change, CookieChange::Expired);
+        assert_eq!(ev.cookie.name, "b");
+        assert!(jar.get_request_cookies(&url).is_none());
+    }
+}
@@ -9,7 +9,7 @@
 //!   - the zone identifier ([`ZoneId`]),
 //!   - an inner jar ([`CookieJarHandle`]) where cookies actually live, and
 //!   - a store handle ([`CookieStoreHandle`]) used to persist state.
-//! - On mutating calls (`store_response_cookies`, `clear`, `remove_*`), it first
+//! - On mutating calls (`store_response_cookies`, `insert_cookies`, `clear`, `remove_*`), it first
 //!   writes to the inner jar, then calls [`persist`](#method.persist) to snapshot
 //!   and flush the state to the store.
 //! - Non-mutating calls (`get_request_cookies`, `get_all_cookies`) simply proxy
@@ -24,9 +24,12 @@
 //!   snapshot is created by downcasting the inner jar to [`DefaultCookieJar`]
 //!   and cloning it.
 use crate::engine::cookies::cookie_jar::DefaultCookieJar;
-use crate::engine::cookies::{CookieJar, CookieJarHandle, CookieStoreHandle};
+use crate::engine::cookies::{
+    Cookie, CookieContext, CookieJar, CookieJarHandle, CookieStoreHandle,
+};
 use crate::engine::zone::ZoneId;
 use http::HeaderMap;
+use std::time::SystemTime;
 use url::Url;
 
 /// A `CookieJar` decorator that persists changes after each mutation.
@@ -107,6 +110,14 @@ impl CookieJar for PersistentCookieJar {
         inner.get_request_cookies(url)
     }
 
+    fn get_request_cookies_in(&self, url: &Url, context: CookieContext) -> Option<String> {
+        let inner = self
+            .inner
+            .read()
+            .expect("Failed to acquire read lock on cookie jar");
+        inner.get_request_cookies_in(url, context)
+    }
+
     /// Clears all cookies in the jar, then persists the updated state.
     fn clear(&mut self) {
         {
@@ -151,4 +162,31 @@ impl CookieJar for PersistentCookieJar {
         }
         self.persist();
     }
+
+    /// Inserts a batch of cookies, then persists the updated state once.
+    fn insert_cookies(&mut self, cookies: Vec<(Url, Cookie)>) {
+        {
+            let mut inner = self
+                .inner
+                .write()
+                .expect("Failed to acquire write lock on cookie jar");
+            inner.insert_cookies(cookies);
+        }
+        self.persist();
+    }
+
+    /// Removes expired cookies, and persists the updated state when anything was removed.
+    fn remove_expired(&mut self, now: SystemTime) -> Vec<(Url, Cookie)> {
+        let expired = {
+            let mut inner = self
+                .inner
+                .write()
+                .expect("Failed to acquire write lock on cookie jar");
+            inner.remove_expired(now)
+        };
+        if !expired.is_empty() {
+            self.persist();
+        }
+        expired
+    }
 }
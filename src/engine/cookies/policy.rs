@@ -0,0 +1,198 @@
+//! Which cookies a zone accepts.
+//!
+//! Before a cookie from a `Set-Cookie` header goes into the jar, the zone asks its
+//! [`CookiePolicy`] whether to keep it. [`BasicCookiePolicy`] covers the usual privacy settings
+//! of a browser; user agents with finer rules, such as per-site exceptions, implement the
+//! trait themselves. Zones without a policy accept every cookie.
+//!
+//! ```
+//! use gosub_engine::cookies::{BasicCookiePolicy, Cookie, CookiePolicy, CookieRequest};
+//! use gosub_engine::render::backends::null::NullBackend;
+//! use gosub_engine::GosubEngine;
+//! use std::sync::Arc;
+//!
+//! /// Blocks third-party cookies, except those of the company's login service
+//! struct KeepLogins;
+//!
+//! impl CookiePolicy for KeepLogins {
+//!     fn allow(&self, cookie: &Cookie, request: &CookieRequest<'_>) -> bool {
+//!         request.url.host_str() == Some("login.example.com")
+//!             || BasicCookiePolicy::BlockThirdParty.allow(cookie, request)
+//!     }
+//! }
+//!
+//! let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+//! let zone_id = engine.zone_builder().cookie_policy(Arc::new(KeepLogins)).create().unwrap();
+//! ```
+use crate::engine::cookies::set_cookie::{default_path, parse_set_cookie};
+use crate::engine::cookies::Cookie;
+use http::header::SET_COOKIE;
+use http::HeaderMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use url::Url;
+
+/// The response a cookie came with.
+#[derive(Debug, Clone, Copy)]
+pub struct CookieRequest<'a> {
+    /// URL of the response that sets the cookie
+    pub url: &'a Url,
+    /// Page the request was made for: the document of a subresource, or the URL itself for a
+    /// navigation. `None` when it is not known.
+    pub top_level: Option<&'a Url>,
+    /// Whether the response is from another site than the page. Sites in the same
+    /// [`FirstPartySets`](crate::storage::FirstPartySets) entry count as one site.
+    pub third_party: bool,
+}
+
+/// Decides which cookies a zone stores. Implemented by the user agent, or use
+/// [`BasicCookiePolicy`].
+pub trait CookiePolicy: Send + Sync {
+    /// Returns whether to store `cookie`, which `request` set. Cookies that are refused are
+    /// dropped; cookies already in the jar are left alone.
+    fn allow(&self, cookie: &Cookie, request: &CookieRequest<'_>) -> bool;
+}
+
+/// Shared handle to a cookie policy.
+pub type CookiePolicyHandle = Arc<dyn CookiePolicy>;
+
+/// The cookie settings found in most browsers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BasicCookiePolicy {
+    /// Stores every cookie, like a zone without a policy
+    #[default]
+    AcceptAll,
+    /// Only stores cookies of the site of the page
+    BlockThirdParty,
+    /// Stores no cookies at all
+    BlockAll,
+}
+
+impl CookiePolicy for BasicCookiePolicy {
+    fn allow(&self, _cookie: &Cookie, request: &CookieRequest<'_>) -> bool {
+        match self {
+            BasicCookiePolicy::AcceptAll => true,
+            BasicCookiePolicy::BlockThirdParty => !request.third_party,
+            BasicCookiePolicy::BlockAll => false,
+        }
+    }
+}
+
+/// Returns `headers` without the `Set-Cookie` headers whose cookie `policy` refuses.
+pub(crate) fn allowed_cookies(
+    policy: &dyn CookiePolicy,
+    request: &CookieRequest<'_>,
+    headers: &HeaderMap,
+) -> HeaderMap {
+    let now = SystemTime::now();
+    let path = default_path(request.url);
+    let allowed = |value: &http::HeaderValue| {
+        std::str::from_utf8(value.as_bytes())
+            .ok()
+            .and_then(|header| parse_set_cookie(header, path, now))
+            .is_some_and(|cookie| policy.allow(&cookie, request))
+    };
+
+    let mut filtered = headers.clone();
+    filtered.remove(SET_COOKIE);
+    for value in headers.get_all(SET_COOKIE).iter().filter(|v| allowed(v)) {
+        filtered.append(SET_COOKIE, value.clone());
+    }
+    filtered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab_in, serve_redirect_with_cookie, tick_until};
+    use crate::{EngineCommand, EngineNotification};
+    use std::time::Duration;
+
+    #[test]
+    fn refused_cookies_are_removed_from_the_headers() {
+        struct NoTrackers;
+        impl CookiePolicy for NoTrackers {
+            fn allow(&self, cookie: &Cookie, _request: &CookieRequest<'_>) -> bool {
+                !cookie.name.starts_with("_track")
+            }
+        }
+
+        let url = Url::parse("https://shop.test/cart").unwrap();
+        let mut headers = HeaderMap::new();
+        for cookie in ["sid=1", "_tracker=2", "lang=nl"] {
+            headers.append(SET_COOKIE, cookie.parse().unwrap());
+        }
+        headers.insert("x-other", "kept".parse().unwrap());
+        let request = CookieRequest {
+            url: &url,
+            top_level: Some(&url),
+            third_party: false,
+        };
+
+        let kept = allowed_cookies(&NoTrackers, &request, &headers);
+        let names: Vec<_> = kept.get_all(SET_COOKIE).iter().collect();
+        assert_eq!(names, ["sid=1", "lang=nl"]);
+        assert_eq!(kept["x-other"], "kept");
+
+        let third_party = CookieRequest {
+            third_party: true,
+            ..request
+        };
+        let policy = BasicCookiePolicy::BlockThirdParty;
+        assert_eq!(
+            allowed_cookies(&policy, &request, &headers)
+                .get_all(SET_COOKIE)
+                .iter()
+                .count(),
+            3
+        );
+        assert!(allowed_cookies(&policy, &third_party, &headers)
+            .get(SET_COOKIE)
+            .is_none());
+        assert!(
+            allowed_cookies(&BasicCookiePolicy::BlockAll, &request, &headers)
+                .get(SET_COOKIE)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn cookie_policies_decide_which_cookies_are_stored() {
+        struct NoHops;
+        impl CookiePolicy for NoHops {
+            fn allow(&self, cookie: &Cookie, request: &CookieRequest<'_>) -> bool {
+                assert!(!request.third_party);
+                cookie.name != "hop"
+            }
+        }
+
+        let (port, requests) = serve_redirect_with_cookie();
+        let start = Url::parse(&format!("http://127.0.0.1:{port}/start")).unwrap();
+        let (mut engine, zone_id, tab_id) =
+            engine_with_tab_in(None, |zone| zone.cookie_policy(Arc::new(NoHops)));
+        let rx = engine.subscribe_notifications();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(start.clone()))
+            .unwrap();
+
+        let stored = tick_until(&mut engine, &rx, |n| match n {
+            EngineNotification::Redirected { cookies, .. } => Some(cookies),
+            _ => None,
+        });
+        assert_eq!(stored, Some(Vec::new()));
+        let (_, cookie) = requests.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert_eq!(cookie, None);
+
+        // Switching the policy applies to open tabs
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        zone.lock()
+            .unwrap()
+            .set_cookie_policy(Some(Arc::new(BasicCookiePolicy::BlockAll)));
+        let tab = engine.get_tab(tab_id).unwrap();
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::SET_COOKIE, "sid=1".parse().unwrap());
+        let tab = tab.lock().unwrap();
+        let allowed = tab.context.allowed_navigation_cookies(&start, &headers);
+        assert!(allowed.get(http::header::SET_COOKIE).is_none());
+    }
+}
@@ -0,0 +1,262 @@
+//! `Set-Cookie` header parsing.
+//!
+//! Follows the parsing algorithm of RFC 6265 §5.2 (with the `SameSite` attribute of
+//! RFC 6265bis): the value is everything between the first `=` and the first `;`, so it may
+//! contain `=` itself; attributes with an invalid value are ignored rather than dropping the
+//! cookie; the last occurrence of an attribute wins; and `Max-Age` takes precedence over
+//! `Expires`. Expiry times are stored on the [`Cookie`] as ISO 8601 timestamps.
+use crate::engine::cookies::date::{parse_cookie_date, unix_to_iso8601};
+use crate::engine::cookies::Cookie;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Latest expiry a cookie can have, the end of the year 9999. Later ones are clamped, so that
+/// every expiry can be written as an ISO 8601 timestamp and read back.
+const MAX_EXPIRY: i64 = 253_402_300_799;
+
+/// Returns the default path of cookies set by a response from `url` (RFC 6265 §5.1.4): the
+/// path up to, but not including, its last `/`.
+pub(crate) fn default_path(url: &Url) -> &str {
+    match url.path().rsplit_once('/') {
+        Some((dir, _)) if !dir.is_empty() => dir,
+        _ => "/",
+    }
+}
+
+/// Parses one `Set-Cookie` header received at `now`. Cookies without a valid `Path` attribute
+/// get `default_path`. Returns `None` when the header has no `=` or an empty name, which
+/// means the cookie is ignored.
+pub(crate) fn parse_set_cookie(
+    header: &str,
+    default_path: &str,
+    now: SystemTime,
+) -> Option<Cookie> {
+    let (pair, attributes) = header.split_once(';').unwrap_or((header, ""));
+    let (name, value) = pair.split_once('=')?;
+    let name = trim_wsp(name);
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: trim_wsp(value).to_string(),
+        path: None,
+        domain: None,
+        secure: false,
+        expires: None,
+        same_site: None,
+        http_only: false,
+    };
+    let mut max_age = None;
+    let mut expires = None;
+
+    for attribute in attributes.split(';') {
+        let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+        let value = trim_wsp(value);
+        match trim_wsp(key).to_ascii_lowercase().as_str() {
+            "expires" => {
+                if let Some(at) = parse_cookie_date(value) {
+                    expires = Some(at);
+                }
+            }
+            "max-age" => {
+                if let Some(delta) = parse_max_age(value) {
+                    max_age = Some(delta);
+                }
+            }
+            "domain" if !value.is_empty() => {
+                cookie.domain = Some(value.trim_start_matches('.').to_ascii_lowercase());
+            }
+            "path" => {
+                cookie.path = value.starts_with('/').then(|| value.to_string());
+            }
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => {
+                cookie.same_site = ["Strict", "Lax", "None"]
+                    .into_iter()
+                    .find(|policy| value.eq_ignore_ascii_case(policy))
+                    .map(str::to_string);
+            }
+            _ => {}
+        }
+    }
+
+    let expiry = match max_age {
+        // A non-positive Max-Age expires the cookie right away
+        Some(delta) if delta <= 0 => Some(0),
+        Some(delta) => Some(unix_seconds(now).saturating_add(delta)),
+        None => expires,
+    };
+    cookie.expires = expiry.map(|at| unix_to_iso8601(at.min(MAX_EXPIRY)));
+    cookie.path = cookie.path.or_else(|| Some(default_path.to_string()));
+    Some(cookie)
+}
+
+/// Parses a `Max-Age` value: an optional `-` followed by digits. Values too large for an `i64`
+/// are clamped.
+fn parse_max_age(value: &str) -> Option<i64> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let negative = digits.len() != value.len();
+    Some(match digits.parse::<i64>() {
+        Ok(n) if negative => -n,
+        Ok(n) => n,
+        Err(_) if negative => i64::MIN,
+        Err(_) => i64::MAX,
+    })
+}
+
+/// Trims spaces and tabs, the only whitespace RFC 6265 strips.
+fn trim_wsp(s: &str) -> &str {
+    s.trim_matches([' ', '\t'])
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::cookies::{CookieJar, DefaultCookieJar};
+    use http::header::SET_COOKIE;
+    use http::HeaderMap;
+    use std::time::Duration;
+
+    /// Adapted from the parser tests of the http-state suite (github.com/abarth/http-state):
+    /// the `Set-Cookie` headers sent by `/cookie-parser`, and the `Cookie` header expected on
+    /// the next request. Cases that depend on cookie ordering by path or on storing cookies of
+    /// the same name under different paths are left out.
+    const HTTP_STATE: &[(&str, &[&str], &str)] = &[
+        ("plain", &["foo=bar"], "foo=bar"),
+        (
+            "past and future expires",
+            &[
+                "foo=bar; Expires=Fri, 07 Aug 2007 08:04:19 GMT",
+                "foo2=bar2; Expires=Fri, 07 Aug 2037 08:04:19 GMT",
+            ],
+            "foo2=bar2",
+        ),
+        ("max-age", &["foo=bar; max-age=10000;"], "foo=bar"),
+        ("zero max-age", &["foo=bar; max-age=0;"], ""),
+        ("version", &["foo=bar; version=1;"], "foo=bar"),
+        ("large version", &["foo=bar; version=1000;"], "foo=bar"),
+        (
+            "unknown attribute",
+            &["foo=bar; customvalue=1000;"],
+            "foo=bar",
+        ),
+        ("secure over http", &["foo=bar; secure;"], ""),
+        (
+            "quoted attribute",
+            &["foo=bar; customvalue=\"1000 or more\";"],
+            "foo=bar",
+        ),
+        (
+            "no trailing semicolon",
+            &["foo=bar; customvalue=\"no trailing semicolon\""],
+            "foo=bar",
+        ),
+        ("replaced", &["foo=bar", "foo=qux"], "foo=qux"),
+        (
+            "replaced with a path",
+            &["foo=bar", "foo=qux; path=/"],
+            "foo=qux",
+        ),
+        ("domain", &["foo=bar; domain=home.example.org"], "foo=bar"),
+        (
+            "domain with dot",
+            &["foo=bar; domain=.home.example.org"],
+            "foo=bar",
+        ),
+        (
+            "uppercase domain",
+            &["foo=bar; domain=HOME.EXAMPLE.ORG"],
+            "foo=bar",
+        ),
+        (
+            "last path wins",
+            &["foo=bar; path=/cookie-parser-result; path=/qux"],
+            "",
+        ),
+        (
+            "last path wins again",
+            &["foo=bar; path=/qux; path=/cookie-parser-result"],
+            "foo=bar",
+        ),
+        ("relative path", &["foo=bar; path=a"], "foo=bar"),
+        ("empty path", &["foo=bar; path"], "foo=bar"),
+        ("mixed case secure", &["foo=bar; seCURe"], ""),
+        ("secure with value", &["foo=bar; secure=aaaa"], ""),
+        ("httponly", &["foo=bar; httponly"], "foo=bar"),
+        ("empty attribute", &["foo=bar; ; secure"], ""),
+        ("repeated secure", &["foo=bar; Secure; Secure"], ""),
+        ("negative max-age", &["foo=bar; max-age=-1"], ""),
+        ("invalid max-age", &["foo=bar; max-age=bad"], "foo=bar"),
+        (
+            "max-age over expires",
+            &["foo=bar; max-age=0; expires=Fri, 07 Aug 2037 08:04:19 GMT"],
+            "",
+        ),
+        ("short name", &["a=bc"], "a=bc"),
+        ("tabs", &["\ta\t=\tb\t"], "a=b"),
+        ("second pair", &["a=b; c=d"], "a=b"),
+        ("empty name", &["=b"], ""),
+        ("equals in value", &["a=b=c"], "a=b=c"),
+        ("no equals", &["a"], ""),
+        ("quote in name", &["a\"b=c"], "a\"b=c"),
+        ("empty name with equals", &["=a=b"], ""),
+        ("quoted value", &["foo=\"bar\""], "foo=\"bar\""),
+        (
+            "equals in value and secure",
+            &["foo=bar=baz; Secure=qux"],
+            "",
+        ),
+        ("empty value", &["foo=; bar"], "foo="),
+        ("space before semicolon", &["foo=bar ; secure"], ""),
+    ];
+
+    #[test]
+    fn http_state_parser_tests() {
+        let set = Url::parse("http://home.example.org:8888/cookie-parser?0001").unwrap();
+        let result = Url::parse("http://home.example.org:8888/cookie-parser-result?0001").unwrap();
+
+        for (name, headers, expected) in HTTP_STATE {
+            let mut map = HeaderMap::new();
+            for header in *headers {
+                map.append(SET_COOKIE, header.parse().unwrap());
+            }
+            let mut jar = DefaultCookieJar::new();
+            jar.store_response_cookies(&set, &map);
+            let sent = jar.get_request_cookies(&result).unwrap_or_default();
+            assert_eq!(sent, *expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn max_age_wins_over_expires() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let cookie = parse_set_cookie(
+            "sid=a=b; Max-Age=60; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Path=/app; SameSite=lax",
+            "/",
+            now,
+        )
+        .unwrap();
+        assert_eq!(cookie.value, "a=b");
+        assert_eq!(cookie.expires.as_deref(), Some("2023-11-14T22:14:20Z"));
+        assert_eq!(cookie.path.as_deref(), Some("/app"));
+        assert_eq!(cookie.same_site.as_deref(), Some("Lax"));
+
+        let clamped = parse_set_cookie("a=b; Max-Age=99999999999999999999", "/", now).unwrap();
+        assert_eq!(clamped.expires.as_deref(), Some("9999-12-31T23:59:59Z"));
+        let unknown = parse_set_cookie("a=b; SameSite=sometimes", "/", now).unwrap();
+        assert_eq!(unknown.same_site, None);
+    }
+}
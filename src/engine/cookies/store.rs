@@ -36,15 +36,19 @@
 //! let private_zone_id = engine.zone_builder().cookie_store(cookie_store).create().unwrap();
 //! ```
 mod json;
+#[cfg(feature = "sqlite_cookie_store")]
 mod sqlite;
 
 use crate::engine::cookies::cookie_jar::DefaultCookieJar;
 use crate::engine::cookies::cookies::CookieJarHandle;
+use crate::engine::cookies::Cookie;
 use crate::engine::zone::ZoneId;
+use url::Url;
 
 /// File-backed JSON cookie store (one file for all zones).
 pub use json::JsonCookieStore;
 /// SQLite-backed cookie store (one database for all zones).
+#[cfg(feature = "sqlite_cookie_store")]
 pub use sqlite::SqliteCookieStore;
 
 /// A cookie **store** mints per-zone cookie **jars** and (optionally) persists them.
@@ -92,4 +96,19 @@ pub trait CookieStore: Send + Sync {
     /// Called during graceful shutdown or at explicit flush points. Implementations
     /// should make a **best-effort** to write all dirty state and avoid panicking.
     fn persist_all(&self);
+
+    /// Imports `cookies` into the jar for `zone_id`.
+    ///
+    /// The cookies are inserted as a single batch, so persistent jars write to durable
+    /// storage once. Returns `false` if the store has no jar for the zone.
+    fn import_cookies(&self, zone_id: ZoneId, cookies: Vec<(Url, Cookie)>) -> bool {
+        let Some(jar) = self.jar_for(zone_id) else {
+            return false;
+        };
+        let Ok(mut jar) = jar.write() else {
+            return false;
+        };
+        jar.insert_cookies(cookies);
+        true
+    }
 }
@@ -20,6 +20,8 @@
 //! - `persist_zone_from_snapshot` and `remove_zone` **read then rewrite** the entire
 //!   JSON file. For large datasets, consider an SQLite-backed store.
 //! - File writes are not atomic.
+//! - The file is locked (see [`ProfileLock`]) for the lifetime of the store, so a second
+//!   engine instance cannot open it concurrently.
 //! - Several helpers use `expect(...)` and will **panic** on I/O/serialization errors.
 //!
 //! ### Example
@@ -39,7 +41,9 @@ use crate::engine::cookies::cookie_jar::DefaultCookieJar;
 use crate::engine::cookies::persistent_cookie_jar::PersistentCookieJar;
 use crate::engine::cookies::store::CookieStore;
 use crate::engine::cookies::{CookieJarHandle, CookieStoreHandle};
+use crate::engine::storage::ProfileLock;
 use crate::engine::zone::ZoneId;
+use crate::EngineError;
 use serde::{Deserialize, Serialize};
 
 /// On-disk representation of all zones' cookie jars.
@@ -66,6 +70,9 @@ pub struct JsonCookieStore {
     ///
     /// This is initialized in [`new`](Self::new) and then read-only thereafter.
     store_self: RwLock<Option<CookieStoreHandle>>,
+
+    /// Keeps other engine instances from using the same file
+    _lock: ProfileLock,
 }
 
 impl JsonCookieStore {
@@ -74,8 +81,23 @@ impl JsonCookieStore {
     /// If the file does not exist, an empty structure is written to disk.
     ///
     /// # Panics
-    /// Panics if the initial write of an empty file fails.
+    /// Panics if the file is in use by another engine instance. See
+    /// [`try_new`](Self::try_new) for a fallible variant.
     pub fn new(path: PathBuf) -> Arc<Self> {
+        Self::try_new(path).expect("Failed to open JSON cookie store")
+    }
+
+    /// Creates (or opens) a JSON cookie store at `path`.
+    ///
+    /// The store locks the file for as long as it is alive, so other engine instances
+    /// cannot attach to it at the same time.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another instance already uses the file.
+    /// - [`EngineError::StorageError`] when the lock file cannot be created.
+    pub fn try_new(path: PathBuf) -> Result<Arc<Self>, EngineError> {
+        let lock = ProfileLock::acquire(&path)?;
+
         // Try to create empty file if it doesn't exist
         if !path.exists() {
             let _ = fs::write(
@@ -91,6 +113,7 @@ impl JsonCookieStore {
             path,
             jars: RwLock::new(HashMap::new()),
             store_self: RwLock::new(None),
+            _lock: lock,
         });
 
         {
@@ -98,7 +121,20 @@ impl JsonCookieStore {
             *self_ref = Some(store.clone() as CookieStoreHandle);
         }
 
-        store
+        Ok(store)
+    }
+
+    /// Serializes `zones` into the JSON format used by this store's file.
+    ///
+    /// Writing the result to disk produces a file that [`JsonCookieStore::new`] can open,
+    /// which makes this useful for exporting cookies out of any other store.
+    pub fn export(zones: HashMap<ZoneId, DefaultCookieJar>) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&CookieStoreFile { zones })
+    }
+
+    /// Parses a JSON export (or a store file) back into per-zone cookie jars.
+    pub fn import(json: &str) -> serde_json::Result<HashMap<ZoneId, DefaultCookieJar>> {
+        serde_json::from_str::<CookieStoreFile>(json).map(|file| file.zones)
     }
 
     /// Loads and deserializes the full cookie store file.
@@ -10,6 +10,9 @@
 //! - In-memory cache: `jars: RwLock<HashMap<ZoneId, CookieJarHandle>>` for quick reuse.
 //! - The store keeps a self handle (`store_self`) so persistent jars can call
 //!   back into `persist_zone_from_snapshot`.
+//! - The last persisted state of every zone is remembered, so a snapshot only
+//!   writes the cookies that changed (upserts on the `(zone_id, origin, name)` key)
+//!   and deletes the ones that disappeared, instead of rewriting the whole zone.
 //! - Database access is via an `r2d2` pool for safe multi-threaded use.
 //!
 //! ## Concurrency
@@ -19,7 +22,11 @@
 //!   across threads.
 //!
 //! ## I/O characteristics & caveats
-//! - `save_zone` **rewrites** the set of cookies for a zone (DELETE + INSERT).
+//! - `save_zone` writes only the difference with the previously persisted state, in a
+//!   single transaction. A zone is **rewritten** (DELETE + INSERT) only when its previous
+//!   state is unknown.
+//! - The database is locked (see [`ProfileLock`]) for the lifetime of the store, so a second
+//!   engine instance cannot open it concurrently.
 //! - Several helpers use `expect(...)` and will **panic** on DB errors. Consider
 //!   replacing with fallible variants for production.
 //!
@@ -33,17 +40,38 @@
 
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use r2d2::{Pool, PooledConnection};
-use r2d2_sqlite::rusqlite::params;
+use r2d2_sqlite::rusqlite::{params, Transaction};
 use r2d2_sqlite::SqliteConnectionManager;
 
 use crate::engine::cookies::cookie_jar::DefaultCookieJar;
 use crate::engine::cookies::persistent_cookie_jar::PersistentCookieJar;
 use crate::engine::cookies::store::CookieStore;
 use crate::engine::cookies::{Cookie, CookieJarHandle, CookieStoreHandle};
+use crate::engine::storage::migrations::{migrate, Migration};
+use crate::engine::storage::ProfileLock;
 use crate::engine::zone::ZoneId;
+use crate::EngineError;
+
+/// Schema of the `cookies` table
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    sql: "CREATE TABLE IF NOT EXISTS cookies (
+        zone_id TEXT NOT NULL,
+        origin TEXT NOT NULL,
+        name TEXT NOT NULL,
+        value TEXT NOT NULL,
+        path TEXT,
+        domain TEXT,
+        secure INTEGER NOT NULL,
+        expires TEXT,
+        same_site TEXT,
+        http_only INTEGER NOT NULL,
+        PRIMARY KEY (zone_id, origin, name)
+    );",
+}];
 
 /// A SQLite-based cookie store that persists cookies across sessions.
 ///
@@ -56,6 +84,11 @@ pub struct SqliteCookieStore {
     jars: RwLock<HashMap<ZoneId, CookieJarHandle>>,
     /// Self handle provided to persistent jars for callback persistence.
     store_self: RwLock<Option<CookieStoreHandle>>,
+    /// Last state written to (or loaded from) the database per zone, used to compute
+    /// incremental updates.
+    persisted: Mutex<HashMap<ZoneId, DefaultCookieJar>>,
+    /// Keeps other engine instances from using the same database
+    _lock: ProfileLock,
 }
 
 impl SqliteCookieStore {
@@ -64,35 +97,40 @@ impl SqliteCookieStore {
     /// Returns an `Arc<Self>` ready to be used as a `CookieStoreHandle`.
     ///
     /// # Panics
-    /// Panics if the pool cannot be created or if the `cookies` table cannot be created.
+    /// Panics if the database is in use by another engine instance, if the pool cannot be
+    /// created or if the `cookies` table cannot be created. See [`try_new`](Self::try_new)
+    /// for a fallible variant.
     pub fn new(path: PathBuf) -> Arc<Self> {
-        let manager = SqliteConnectionManager::file(path);
-        let pool = Pool::new(manager).expect("Failed to create SQLite pool");
+        Self::try_new(path).expect("Failed to open SQLite cookie store")
+    }
+
+    /// Opens (or creates) a SQLite database at `path` and migrates it to the current schema.
+    ///
+    /// The store locks the database for as long as it is alive, so other engine instances
+    /// cannot attach to it at the same time.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another instance already uses the database.
+    /// - [`EngineError::StorageError`] when the database cannot be opened or initialized.
+    pub fn try_new(path: PathBuf) -> Result<Arc<Self>, EngineError> {
+        let lock = ProfileLock::acquire(&path)?;
+
+        let manager = SqliteConnectionManager::file(&path);
+        let pool = Pool::new(manager).map_err(|e| EngineError::StorageError(e.to_string()))?;
 
         {
-            let conn = pool.get().expect("DB connection");
-            conn.execute_batch(
-                "CREATE TABLE IF NOT EXISTS cookies (
-                    zone_id TEXT NOT NULL,
-                    origin TEXT NOT NULL,
-                    name TEXT NOT NULL,
-                    value TEXT NOT NULL,
-                    path TEXT,
-                    domain TEXT,
-                    secure INTEGER NOT NULL,
-                    expires TEXT,
-                    same_site TEXT,
-                    http_only INTEGER NOT NULL,
-                    PRIMARY KEY (zone_id, origin, name)
-                );",
-            )
-            .expect("Failed to create cookies table");
+            let conn = pool
+                .get()
+                .map_err(|e| EngineError::StorageError(e.to_string()))?;
+            migrate(&conn, Some(&path), MIGRATIONS)?;
         }
 
         let store = Arc::new(Self {
             pool,
             jars: RwLock::new(HashMap::new()),
             store_self: RwLock::new(None),
+            persisted: Mutex::new(HashMap::new()),
+            _lock: lock,
         });
 
         {
@@ -100,7 +138,7 @@ impl SqliteCookieStore {
             *self_ref = Some(store.clone() as CookieStoreHandle);
         }
 
-        store
+        Ok(store)
     }
 
     /// Borrows a pooled SQLite connection.
@@ -149,51 +187,102 @@ impl SqliteCookieStore {
             }
         }
 
+        self.persisted.lock().unwrap().insert(zone_id, jar.clone());
+
         jar
     }
 
-    /// Replaces all cookies for `zone_id` with the contents of `jar` in a transaction.
+    /// Writes `jar` as the new state of `zone_id` in a single transaction.
     ///
-    /// DELETEs the existing rows for the zone and INSERTs the new set.
+    /// When the previously persisted state of the zone is known, only changed cookies are
+    /// upserted and removed cookies are deleted. Otherwise, all rows of the zone are replaced.
     ///
     /// # Panics
     /// Panics if the transaction, statement preparation, or execution fails.
     fn save_zone(&self, zone_id: ZoneId, jar: &DefaultCookieJar) {
+        let mut persisted = self.persisted.lock().unwrap();
+
         let mut conn = self.conn();
         let tx = conn.transaction().expect("Transaction failed");
 
-        tx.execute(
-            "DELETE FROM cookies WHERE zone_id = ?1",
-            [zone_id.to_string()],
-        )
-        .expect("Failed to delete cookies");
+        match persisted.get(&zone_id) {
+            Some(previous) => {
+                let mut stmt = tx
+                    .prepare("DELETE FROM cookies WHERE zone_id = ?1 AND origin = ?2 AND name = ?3")
+                    .expect("Prepare failed");
+                for (origin, cookies) in &previous.entries {
+                    let current = jar.entries.get(origin);
+                    for cookie in cookies {
+                        let kept = current.is_some_and(|c| c.iter().any(|c| c.name == cookie.name));
+                        if !kept {
+                            stmt.execute(params![zone_id.to_string(), origin, cookie.name])
+                                .expect("Failed to delete cookie");
+                        }
+                    }
+                }
+                drop(stmt);
+
+                let changed = jar.entries.iter().flat_map(|(origin, cookies)| {
+                    let before = previous.entries.get(origin);
+                    cookies
+                        .iter()
+                        .filter(move |c| !before.is_some_and(|b| b.contains(c)))
+                        .map(move |c| (origin, c))
+                });
+                Self::upsert_cookies(&tx, zone_id, changed);
+            }
+            None => {
+                tx.execute(
+                    "DELETE FROM cookies WHERE zone_id = ?1",
+                    [zone_id.to_string()],
+                )
+                .expect("Failed to delete cookies");
+
+                let all = jar
+                    .entries
+                    .iter()
+                    .flat_map(|(origin, cookies)| cookies.iter().map(move |c| (origin, c)));
+                Self::upsert_cookies(&tx, zone_id, all);
+            }
+        }
+
+        tx.commit().expect("Commit failed");
+        persisted.insert(zone_id, jar.clone());
+    }
 
+    /// Inserts or updates the given `(origin, cookie)` rows of `zone_id` within `tx`.
+    ///
+    /// # Panics
+    /// Panics if statement preparation or execution fails.
+    fn upsert_cookies<'a>(
+        tx: &Transaction,
+        zone_id: ZoneId,
+        cookies: impl Iterator<Item = (&'a String, &'a Cookie)>,
+    ) {
         let mut stmt = tx.prepare(
             "INSERT INTO cookies (zone_id, origin, name, value, path, domain, secure, expires, same_site, http_only)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT (zone_id, origin, name) DO UPDATE SET
+                value = excluded.value, path = excluded.path, domain = excluded.domain,
+                secure = excluded.secure, expires = excluded.expires,
+                same_site = excluded.same_site, http_only = excluded.http_only"
         ).expect("Prepare failed");
 
-        for (origin, cookies) in &jar.entries {
-            for cookie in cookies {
-                stmt.execute(params![
-                    zone_id.to_string(),
-                    origin,
-                    cookie.name,
-                    cookie.value,
-                    cookie.path,
-                    cookie.domain,
-                    cookie.secure as i64,
-                    cookie.expires,
-                    cookie.same_site,
-                    cookie.http_only as i64
-                ])
-                .expect("Failed to insert cookie");
-            }
+        for (origin, cookie) in cookies {
+            stmt.execute(params![
+                zone_id.to_string(),
+                origin,
+                cookie.name,
+                cookie.value,
+                cookie.path,
+                cookie.domain,
+                cookie.secure as i64,
+                cookie.expires,
+                cookie.same_site,
+                cookie.http_only as i64
+            ])
+            .expect("Failed to upsert cookie");
         }
-
-        drop(stmt);
-
-        tx.commit().expect("Commit failed");
     }
 
     /// Deletes all cookies for `zone_id` from the database.
@@ -201,6 +290,8 @@ impl SqliteCookieStore {
     /// # Panics
     /// Panics on SQL execution error.
     fn remove_zone_from_db(&self, zone_id: ZoneId) {
+        self.persisted.lock().unwrap().remove(&zone_id);
+
         let conn = self.conn();
         conn.execute(
             "DELETE FROM cookies WHERE zone_id = ?1",
@@ -283,3 +374,96 @@ impl CookieStore for SqliteCookieStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    fn temp_db() -> PathBuf {
+        std::env::temp_dir().join(format!("gosub-cookies-{}.db", uuid::Uuid::new_v4()))
+    }
+
+    fn cookie(name: &str, value: &str) -> Cookie {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            path: Some("/".into()),
+            domain: None,
+            secure: false,
+            expires: None,
+            same_site: None,
+            http_only: false,
+        }
+    }
+
+    fn jar_with(cookies: &[(&str, &str, &str)]) -> DefaultCookieJar {
+        let mut jar = DefaultCookieJar::new();
+        for (origin, name, value) in cookies {
+            jar.entries
+                .entry(origin.to_string())
+                .or_default()
+                .push(cookie(name, value));
+        }
+        jar
+    }
+
+    fn sorted(jar: &DefaultCookieJar) -> Vec<(String, String, String)> {
+        let mut rows: Vec<_> = jar
+            .entries
+            .iter()
+            .flat_map(|(o, cs)| {
+                cs.iter()
+                    .map(move |c| (o.clone(), c.name.clone(), c.value.clone()))
+            })
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    #[test]
+    fn incremental_snapshots_upsert_and_delete() {
+        let path = temp_db();
+        let store = SqliteCookieStore::new(path.clone());
+        let zone = ZoneId::new();
+        let other = ZoneId::new();
+
+        store.save_zone(other, &jar_with(&[("https://b.test", "keep", "1")]));
+        store.save_zone(
+            zone,
+            &jar_with(&[("https://a.test", "x", "1"), ("https://a.test", "y", "2")]),
+        );
+
+        // Change x, drop y, add z
+        let next = jar_with(&[("https://a.test", "x", "10"), ("https://c.test", "z", "3")]);
+        store.save_zone(zone, &next);
+
+        // The database must hold exactly the latest state, and leave other zones alone
+        assert_eq!(sorted(&store.load_zone(zone)), sorted(&next));
+        assert_eq!(store.load_zone(other).entries.len(), 1);
+
+        let jar = store.jar_for(zone).unwrap();
+        let url = Url::parse("https://a.test/").unwrap();
+        assert_eq!(
+            jar.read().unwrap().get_request_cookies(&url).as_deref(),
+            Some("x=10")
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db.lock"));
+    }
+
+    #[test]
+    fn second_store_on_same_database_is_rejected() {
+        let path = temp_db();
+        let _store = SqliteCookieStore::new(path.clone());
+
+        assert!(matches!(
+            SqliteCookieStore::try_new(path.clone()),
+            Err(EngineError::ProfileInUse(_))
+        ));
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db.lock"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Problems found while parsing documents.
+//!
+//! There is no HTML parser yet, so the checks are limited to what the text pipeline can see:
+//! the encoding of the document and markup that is cut off at its end. Every problem becomes a
+//! [`ParseIssue`] with the position where it was found; the document loads regardless.
+//!
+//! The issues of a document are published as
+//! [`EngineNotification::ParseIssues`](crate::EngineNotification::ParseIssues) when it commits,
+//! counted in [`TabPublicState::parse_issues`](crate::tab::TabPublicState::parse_issues) and
+//! logged to the console of tabs that capture it.
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Only the start of a document is searched for a character encoding declaration, like
+/// browsers do before they start parsing.
+const CHARSET_PRESCAN_BYTES: usize = 1024;
+
+/// What is wrong in a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum ParseIssueKind {
+    /// Bytes that are not valid UTF-8 were replaced with U+FFFD
+    InvalidUtf8,
+    /// The document declares a character encoding other than UTF-8. Only UTF-8 is supported,
+    /// so the document was decoded as UTF-8 anyway.
+    UnsupportedEncoding(String),
+    /// A comment is still open at the end of the document
+    UnterminatedComment,
+    /// A tag is still open at the end of the document
+    UnterminatedTag,
+}
+
+impl fmt::Display for ParseIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseIssueKind::InvalidUtf8 => {
+                write!(
+                    f,
+                    "the document is not valid UTF-8; invalid bytes were replaced"
+                )
+            }
+            ParseIssueKind::UnsupportedEncoding(label) => {
+                write!(f, "encoding '{label}' is not supported; decoded as UTF-8")
+            }
+            ParseIssueKind::UnterminatedComment => write!(f, "comment is not closed"),
+            ParseIssueKind::UnterminatedTag => write!(f, "tag is not closed"),
+        }
+    }
+}
+
+/// A recoverable problem in a document and where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct ParseIssue {
+    /// What is wrong
+    pub kind: ParseIssueKind,
+    /// Line of the problem, starting at 1
+    pub line: u32,
+    /// Column of the problem in characters, starting at 1
+    pub column: u32,
+}
+
+impl fmt::Display for ParseIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+/// Decodes the body of a document and checks it, returning the text and the issues in the
+/// order they appear.
+pub(crate) fn decode_document(body: Vec<u8>) -> (String, Vec<ParseIssue>) {
+    let mut issues = Vec::new();
+    let html = match String::from_utf8(body) {
+        Ok(html) => html,
+        Err(e) => {
+            let valid = e.utf8_error().valid_up_to();
+            let html = String::from_utf8_lossy(e.as_bytes()).into_owned();
+            // The text before the first invalid byte is unchanged by the replacement
+            issues.push(issue_at(&html, valid, ParseIssueKind::InvalidUtf8));
+            html
+        }
+    };
+
+    if let Some((offset, label)) = declared_charset(&html) {
+        if !matches!(label.as_str(), "utf-8" | "utf8" | "unicode-1-1-utf-8") {
+            issues.push(issue_at(
+                &html,
+                offset,
+                ParseIssueKind::UnsupportedEncoding(label),
+            ));
+        }
+    }
+
+    if let Some(offset) = unterminated_comment(&html) {
+        issues.push(issue_at(&html, offset, ParseIssueKind::UnterminatedComment));
+    } else if let Some(offset) = unterminated_tag(&html) {
+        issues.push(issue_at(&html, offset, ParseIssueKind::UnterminatedTag));
+    }
+
+    issues.sort_by_key(|issue| (issue.line, issue.column));
+    (html, issues)
+}
+
+fn issue_at(text: &str, offset: usize, kind: ParseIssueKind) -> ParseIssue {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    ParseIssue {
+        kind,
+        line: before.matches('\n').count() as u32 + 1,
+        column: before[line_start..].chars().count() as u32 + 1,
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Returns the offset and lowercased label of the `charset` of the first `<meta>` tag that
+/// has one.
+fn declared_charset(html: &str) -> Option<(usize, String)> {
+    let head = html.as_bytes();
+    let head = head[..head.len().min(CHARSET_PRESCAN_BYTES)].to_ascii_lowercase();
+
+    let mut from = 0;
+    while let Some(start) = find(&head[from..], b"<meta").map(|i| from + i) {
+        let end = head[start..]
+            .iter()
+            .position(|b| *b == b'>')
+            .map_or(head.len(), |i| start + i);
+        if let Some(i) = find(&head[start..end], b"charset=") {
+            let value_start = start + i + "charset=".len();
+            let value: String = head[value_start..end]
+                .iter()
+                .skip_while(|b| matches!(b, b'"' | b'\''))
+                .take_while(|b| !matches!(b, b'"' | b'\'' | b';' | b'/' | b' '))
+                .map(|b| *b as char)
+                .collect();
+            if !value.is_empty() {
+                return Some((value_start, value));
+            }
+        }
+        from = end;
+    }
+    None
+}
+
+/// Returns the offset of a `<!--` that is never closed.
+fn unterminated_comment(html: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(start) = html[from..].find("<!--").map(|i| from + i) {
+        match html[start + 4..].find("-->") {
+            Some(i) => from = start + 4 + i + 3,
+            None => return Some(start),
+        }
+    }
+    None
+}
+
+/// Returns the offset of a tag that is cut off by the end of the document.
+fn unterminated_tag(html: &str) -> Option<usize> {
+    let start = html.rfind('<')?;
+    if html[start..].contains('>') {
+        return None;
+    }
+    let next = html[start + 1..].chars().next()?;
+    (next.is_ascii_alphabetic() || next == '/' || next == '!').then_some(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_problems_and_cut_off_markup_are_reported_with_positions() {
+        let (html, issues) = decode_document(b"<p>ok</p>\n<p>caf\xe9</p>".to_vec());
+        assert_eq!(html, "<p>ok</p>\n<p>caf\u{fffd}</p>");
+        assert_eq!(
+            issues,
+            vec![ParseIssue {
+                kind: ParseIssueKind::InvalidUtf8,
+                line: 2,
+                column: 7,
+            }]
+        );
+
+        let (_, issues) =
+            decode_document(b"<meta charset=\"ISO-8859-1\">\n<p>a</p><!-- x".to_vec());
+        let kinds: Vec<_> = issues.iter().map(|i| (i.kind.clone(), i.line)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    ParseIssueKind::UnsupportedEncoding("iso-8859-1".to_string()),
+                    1
+                ),
+                (ParseIssueKind::UnterminatedComment, 2),
+            ]
+        );
+
+        let (_, issues) = decode_document(b"<p>a < b</p>\n<div class=".to_vec());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, ParseIssueKind::UnterminatedTag);
+        assert_eq!((issues[0].line, issues[0].column), (2, 1));
+
+        let (_, issues) = decode_document(b"<meta charset=utf-8><!-- a --><p>fine</p>".to_vec());
+        assert!(issues.is_empty());
+    }
+}
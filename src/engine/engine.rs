@@ -1,16 +1,37 @@
+use crate::config::{FrameSharing, RuntimeConfig};
 use crate::cookies::CookieJarHandle;
+use crate::engine::activity::{Activity, ActivityEntry, ActivityLog};
+use crate::engine::logging::{self, engine_log, LogSink};
+#[cfg(feature = "serde_events")]
+use crate::engine::recording::{RecordedInput, Recorder, Recording};
 use crate::engine::storage::StorageService;
-use crate::engine::tab::{Tab, TabId};
-use crate::engine::tick::TickResult;
+use crate::engine::tab::{PopupRequestId, Tab, TabId, TabStateHandle};
+use crate::engine::tick::{EngineFrameStats, FrameCoordinator, FrameStats, JankStats, TickResult};
+use crate::engine::watchdog::Watchdog;
 use crate::engine::zone::ZoneManager;
 use crate::render::backend::{CompositorSink, RenderBackend};
-use crate::render::Viewport;
-use crate::zone::ZoneConfig;
-use crate::zone::{Zone, ZoneId};
-use crate::{EngineCommand, EngineConfig, EngineError, EngineEvent};
+use crate::render::{Frame, FrameCollector, Viewport};
+use crate::zone::{Zone, ZoneId, ZoneMetadata, ZoneServicesFactory};
+use crate::zone::{ZoneCloneOptions, ZoneConfig};
+use crate::{
+    EngineCommand, EngineConfig, EngineError, EngineEvent, EngineNotification,
+    NotificationCategories, NotificationSubscription, SequencedSubscription, TabKiller,
+    ZoneCommand,
+};
+use std::any::Any;
 use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
+use url::Url;
+
+/// How often [`GosubEngine::tick`] checks the memory used by tabs
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the engine waits before it tries again to recover a lost device, after the first
+/// failed attempt. The wait doubles with every further failure, up to [`MAX_RECOVERY_RETRY`].
+const RECOVERY_RETRY: Duration = Duration::from_millis(500);
+const MAX_RECOVERY_RETRY: Duration = Duration::from_secs(30);
 
 /// Entry point to the Gosub engine.
 ///
@@ -19,44 +40,267 @@ use tokio::runtime::Runtime;
 /// See [`Viewport`], [`ZoneId`], [`TabId`], [`EngineEvent`], [`EngineCommand`].
 pub struct GosubEngine {
     /// Configuration for the whole engine
-    _config: EngineConfig,
+    config: EngineConfig,
     /// Manages zones
     zone_manager: ZoneManager,
     /// Tokio runtime for async operations
     pub runtime: Arc<Runtime>,
     // Render backend for the engine
     backend: Box<dyn RenderBackend>,
+    /// Subscription drained by `poll_event`, created on first use
+    poll_rx: Option<NotificationSubscription>,
+    /// When the memory used by tabs was last checked against the limit
+    last_memory_check: Instant,
+    /// Creates the services of new zones, see [`EngineBuilder::zone_services`](crate::EngineBuilder::zone_services)
+    zone_services: Option<ZoneServicesFactory>,
+    /// Input log, while recording
+    #[cfg(feature = "serde_events")]
+    recorder: Option<Recorder>,
+    /// Warning about the backend that waits for a zone to be published in
+    backend_warning: Option<String>,
+    /// Recent activity, when [`EngineConfig::activity_log_size`] is set
+    activity: Option<ActivityLog>,
+    /// Shares the frames of a tick between tabs, see [`EngineConfig::frame_sharing`]
+    frames: FrameCoordinator,
+    /// After a failed recovery of a lost device: when to try again, and how long the last wait was
+    recovery_retry: Option<(Instant, Duration)>,
 }
 
 impl GosubEngine {
     pub fn update_backend_renderer(&mut self, new_backend: Box<dyn RenderBackend>) {
         self.backend = new_backend;
+        self.configure_backend();
+    }
+
+    /// Moves the backend to a new device after it lost its own. The tabs of every zone get
+    /// new surfaces, and every zone publishes [`EngineNotification::BackendReset`]. When the
+    /// backend cannot recover, the engine waits longer after every attempt before it tries again.
+    fn recover_backend(&mut self) {
+        let now = Instant::now();
+        if self
+            .recovery_retry
+            .is_some_and(|(retry_at, _)| now < retry_at)
+        {
+            return;
+        }
+        if let Err(e) = self.backend.recover() {
+            let wait = self.recovery_retry.map_or(RECOVERY_RETRY, |(_, wait)| {
+                (wait * 2).min(MAX_RECOVERY_RETRY)
+            });
+            engine_log!(
+                Error,
+                "cannot recover the render backend, trying again in {:?}: {}",
+                wait,
+                e
+            );
+            self.recovery_retry = Some((now + wait, wait));
+            return;
+        }
+        self.recovery_retry = None;
+        for zone_id in self.zone_manager.iter() {
+            if let Some(zone) = self.zone_manager.get_zone(zone_id) {
+                if let Ok(mut zone) = zone.lock() {
+                    zone.reset_surfaces();
+                }
+            }
+        }
+        self.configure_backend();
+    }
+
+    /// Applies the [`GpuOptions`](crate::config::GpuOptions) to the backend. When the device
+    /// cannot use the anti-aliasing they ask for, the backend falls back to another mode and
+    /// every zone receives a warning on the next tick.
+    fn configure_backend(&mut self) {
+        let wanted = self.config.gpu.antialiasing();
+        let used = self.backend.set_antialiasing(wanted);
+        if used != wanted {
+            let message = format!(
+                "the GPU does not support {wanted:?} anti-aliasing, using {used:?} instead"
+            );
+            engine_log!(Warn, "{}", message);
+            self.backend_warning = Some(message);
+        }
     }
 
     /// Create a new engine.
     ///
-    /// If `config` is `None`, defaults are used.
+    /// If `config` is `None`, defaults are used. See [`GosubEngine::builder`] for more options.
     ///
     /// ```
     /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
     /// let engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
     /// ```
     pub fn new(config: Option<EngineConfig>, backend: Box<dyn RenderBackend>) -> Self {
-        let runtime = Arc::new(
-            tokio::runtime::Builder::new_multi_thread()
-                .enable_all()
-                .build()
-                .expect("Failed to create Tokio runtime"),
-        );
+        Self::from_parts(config.unwrap_or_default(), backend, None, None, None)
+    }
 
-        // I don't like that we have to clone the config but we need it in the "engine" and the zone manager as well.
-        let resolved_config = config.unwrap_or_else(EngineConfig::default);
+    /// Creates an engine, and a runtime for it unless one is given.
+    pub(crate) fn from_parts(
+        config: EngineConfig,
+        backend: Box<dyn RenderBackend>,
+        runtime: Option<Arc<Runtime>>,
+        zone_services: Option<ZoneServicesFactory>,
+        notification_capacity: Option<usize>,
+    ) -> Self {
+        let runtime = runtime.unwrap_or_else(|| {
+            // Parsing runs on the blocking pool, so it gets the same number of threads as the
+            // async work
+            let threads = config.worker_threads.max(1);
+            Arc::new(
+                tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(threads)
+                    .max_blocking_threads(threads)
+                    .enable_all()
+                    .build()
+                    .expect("Failed to create Tokio runtime"),
+            )
+        });
+
+        logging::set_max_level(config.log_level);
 
-        Self {
-            _config: resolved_config.clone(),
-            zone_manager: ZoneManager::new(resolved_config),
+        // I don't like that we have to clone the config but we need it in the "engine" and the zone manager as well.
+        let mut engine = Self {
+            config: config.clone(),
+            activity: ActivityLog::new(config.activity_log_size),
+            zone_manager: ZoneManager::new(config)
+                .with_notification_capacity(notification_capacity)
+                .with_watchdog(),
             runtime,
             backend,
+            poll_rx: None,
+            last_memory_check: Instant::now(),
+            zone_services,
+            #[cfg(feature = "serde_events")]
+            recorder: None,
+            backend_warning: None,
+            frames: FrameCoordinator::default(),
+            recovery_retry: None,
+        };
+        engine.configure_backend();
+        engine
+    }
+
+    /// Returns the factory for the services of new zones, if the engine was built with one.
+    pub(crate) fn zone_services_factory(&self) -> Option<ZoneServicesFactory> {
+        self.zone_services.clone()
+    }
+
+    /// Subscribe to [`EngineNotification`]s published by any zone or tab, in the
+    /// [default categories](NotificationCategories::DEFAULT).
+    ///
+    /// Notifications are produced while ticking; drain the receiver with `try_recv` after
+    /// each [`GosubEngine::tick`].
+    pub fn subscribe_notifications(&self) -> NotificationSubscription {
+        self.zone_manager.subscribe_notifications()
+    }
+
+    /// Returns a handle that kills unresponsive tabs from any thread, or `None` when the
+    /// engine runs no watchdog (see [`EngineConfig::tab_watchdog_timeout`]). Take it before
+    /// handing the engine to the thread that ticks it: a stuck tab holds that thread, so the
+    /// handle is the only way to reach it.
+    pub fn tab_killer(&self) -> Option<TabKiller> {
+        self.zone_manager.watchdog().map(Watchdog::killer)
+    }
+
+    /// Subscribe to the [`EngineNotification`]s of one zone and its tabs, for instance to
+    /// drive a window that shows only that zone. Notifications of other zones are not
+    /// delivered to (or copied for) the receiver.
+    ///
+    /// ```
+    /// # let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+    /// # let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// let work = engine.zone_builder().create().unwrap();
+    /// let home = engine.zone_builder().create().unwrap();
+    /// let rx = engine.subscribe_zone_notifications(work).unwrap();
+    ///
+    /// let zone = engine.get_zone_mut(home).unwrap();
+    /// zone.lock().unwrap().set_javascript_enabled(false).unwrap();
+    /// assert!(rx.try_recv().is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`EngineError::ZoneNotFound`] when there is no such zone.
+    pub fn subscribe_zone_notifications(
+        &self,
+        zone_id: ZoneId,
+    ) -> Result<NotificationSubscription, EngineError> {
+        self.zone_manager.subscribe_zone_notifications(zone_id)
+    }
+
+    /// Like [`GosubEngine::subscribe_notifications`], but only for the notifications in
+    /// `categories`, for instance to follow frames as well:
+    ///
+    /// ```
+    /// use gosub_engine::NotificationCategories;
+    ///
+    /// # let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+    /// # let engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// let rx = engine.subscribe_categories(
+    ///     NotificationCategories::DEFAULT | NotificationCategories::RENDER,
+    /// );
+    /// ```
+    ///
+    /// Notifications of other categories are not delivered to (or copied for) the receiver.
+    pub fn subscribe_categories(
+        &self,
+        categories: NotificationCategories,
+    ) -> NotificationSubscription {
+        self.zone_manager.subscribe_categories(categories)
+    }
+
+    /// Like [`GosubEngine::subscribe_zone_notifications`], but only for the notifications in
+    /// `categories`.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::ZoneNotFound`] when there is no such zone.
+    pub fn subscribe_zone_categories(
+        &self,
+        zone_id: ZoneId,
+        categories: NotificationCategories,
+    ) -> Result<NotificationSubscription, EngineError> {
+        self.zone_manager
+            .subscribe_zone_categories(zone_id, categories)
+    }
+
+    /// Like [`GosubEngine::subscribe_notifications`], but for every category, and every
+    /// notification comes with an engine-wide sequence number, a per-tab sequence number and
+    /// a timestamp (see [`SequencedNotification`](crate::SequencedNotification)).
+    ///
+    /// Test harnesses and user agents that merge notifications into their own event stream can
+    /// use these to detect drops and to restore the publication order.
+    pub fn subscribe_sequenced(&self) -> SequencedSubscription {
+        self.zone_manager.subscribe_sequenced()
+    }
+
+    /// Returns the next pending [`EngineNotification`], waiting up
+    /// to `timeout` for one to arrive.
+    ///
+    /// This is a pull-based alternative to [`GosubEngine::subscribe_notifications`] for
+    /// embedders that drive the engine from their own loop, such as language bindings without
+    /// an async runtime. Pass [`Duration::ZERO`] to return immediately. Notifications are
+    /// queued from the first call onward; anything published before that is not seen here.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
+    /// let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// let compositor = &mut gosub_engine::render::DefaultCompositor::new(|| {});
+    ///
+    /// engine.tick(compositor);
+    /// while let Some(notification) = engine.poll_event(Duration::ZERO) {
+    ///     println!("{notification:?}");
+    /// }
+    /// ```
+    pub fn poll_event(&mut self, timeout: Duration) -> Option<EngineNotification> {
+        let rx = self
+            .poll_rx
+            .get_or_insert_with(|| self.zone_manager.subscribe_notifications());
+
+        if timeout.is_zero() {
+            rx.try_recv().ok()
+        } else {
+            rx.recv_timeout(timeout).ok()
         }
     }
 
@@ -72,6 +316,58 @@ impl GosubEngine {
             .create_zone(zone_id, config, storage_service, cookie_jar)
     }
 
+    /// Create a new zone from an existing one and return its [`ZoneId`].
+    ///
+    /// The new zone gets the same configuration as `source_zone`, and depending on `options`
+    /// a snapshot of its cookies and localStorage. Useful for test fixtures or for opening a
+    /// temporary copy of a profile.
+    ///
+    /// ```
+    /// use gosub_engine::zone::ZoneCloneOptions;
+    ///
+    /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
+    /// let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    ///
+    /// let zone_id = engine.zone_builder().create().unwrap();
+    /// let copy_id = engine.clone_zone(zone_id, ZoneCloneOptions {
+    ///     copy_cookies: true,
+    ///     copy_local_storage: true,
+    ///     ..Default::default()
+    /// }).unwrap();
+    /// assert_ne!(zone_id, copy_id);
+    /// ```
+    pub fn clone_zone(
+        &mut self,
+        source_zone: ZoneId,
+        options: ZoneCloneOptions,
+    ) -> Result<ZoneId, EngineError> {
+        self.zone_manager.clone_zone(source_zone, options)
+    }
+
+    /// Reads the cookies zone `owner` would send to `url`, on behalf of zone `reader`, and
+    /// returns them as a `Cookie` header value. The owner has to set
+    /// [`SharedFlags::share_cookiejar`](crate::zone::SharedFlags::share_cookiejar) and
+    /// designate the reader with [`Zone::allow_cookie_reader`]; the reader only gets a copy
+    /// and cannot change the owner's cookies. Each attempt, allowed or not, is published as
+    /// [`EngineNotification::SharedCookiesRead`](crate::EngineNotification::SharedCookiesRead).
+    ///
+    /// This is the only way to reach shared cookies: they are not added to the requests of
+    /// the reader's tabs, which keep using their own jar. What the reader does with the
+    /// returned header is up to the user agent.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::ZoneNotFound`] when either zone does not exist, and
+    /// [`EngineError::CookieJarNotShared`] when the owner does not share its cookies with the
+    /// reader.
+    pub fn read_shared_cookies(
+        &self,
+        reader: ZoneId,
+        owner: ZoneId,
+        url: &Url,
+    ) -> Result<Option<String>, EngineError> {
+        self.zone_manager.read_shared_cookies(reader, owner, url)
+    }
+
     /// Get a mutable handle to a zone.
     ///
     /// This returns an [`Arc<Mutex<Zone>>`]; lock it before use.
@@ -79,6 +375,25 @@ impl GosubEngine {
         self.zone_manager.get_zone_mut(&zone_id)
     }
 
+    /// Returns the title, icon, description and color of a zone, or `None` when the zone does
+    /// not exist. Changes are published as
+    /// [`EngineNotification::ZoneMetadataChanged`](crate::EngineNotification::ZoneMetadataChanged).
+    pub fn zone_metadata(&self, zone_id: ZoneId) -> Option<ZoneMetadata> {
+        let zone_arc = self.zone_manager.get_zone(zone_id)?;
+        let zone = zone_arc.lock().ok()?;
+        Some(zone.metadata())
+    }
+
+    /// Returns every zone with its metadata, so that a window opened later can draw the same
+    /// zone list as the others.
+    pub fn zones(&self) -> Vec<(ZoneId, ZoneMetadata)> {
+        self.zone_manager
+            .iter()
+            .into_iter()
+            .filter_map(|zone_id| Some((zone_id, self.zone_metadata(zone_id)?)))
+            .collect()
+    }
+
     /// Retrieves a reference to a tab regardless of its zone
     pub fn get_tab(&self, tab_id: TabId) -> Option<Arc<Mutex<Tab>>> {
         for zone_id in self.zone_manager.iter() {
@@ -93,8 +408,86 @@ impl GosubEngine {
         None
     }
 
+    /// Returns a handle for reading a tab's URL, title and loading state.
+    ///
+    /// Get the handle once and keep it: reading from it is cheap and does not lock the engine,
+    /// so the UI can poll it every frame.
+    ///
+    /// ```
+    /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
+    /// let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// let zone_id = engine.zone_builder().create().unwrap();
+    /// let viewport = gosub_engine::render::Viewport::new(0, 0, 800, 600);
+    /// let tab_id = engine.open_tab_in_zone(zone_id, viewport).unwrap();
+    ///
+    /// let state = engine.tab_state(tab_id).unwrap();
+    /// assert_eq!(state.url(), None);
+    /// assert_eq!(state.title(), "New Tab");
+    /// ```
+    pub fn tab_state(&self, tab_id: TabId) -> Option<TabStateHandle> {
+        let tab_arc = self.get_tab(tab_id)?;
+        let tab = tab_arc.lock().ok()?;
+        Some(tab.state_handle())
+    }
+
+    /// Attaches data of the user agent to a tab, so that it can be found by [`TabId`] without a
+    /// map of its own. See [`Tab::set_user_data`].
+    ///
+    /// ```
+    /// # let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+    /// # let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// # let zone_id = engine.zone_builder().create().unwrap();
+    /// # let viewport = gosub_engine::render::Viewport::new(0, 0, 800, 600);
+    /// struct WidgetId(u32);
+    ///
+    /// let tab_id = engine.open_tab_in_zone(zone_id, viewport).unwrap();
+    /// engine.set_tab_user_data(tab_id, Box::new(WidgetId(7))).unwrap();
+    ///
+    /// assert_eq!(engine.tab_user_data::<WidgetId>(tab_id).unwrap().0, 7);
+    /// assert!(engine.tab_user_data::<String>(tab_id).is_none());
+    /// ```
+    pub fn set_tab_user_data(
+        &mut self,
+        tab_id: TabId,
+        data: Box<dyn Any + Send + Sync>,
+    ) -> Result<(), EngineError> {
+        let tab_arc = self.get_tab(tab_id).ok_or(EngineError::InvalidTabId)?;
+        let mut tab = tab_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
+        tab.set_user_data(data);
+        Ok(())
+    }
+
+    /// Returns the data attached to a tab with [`GosubEngine::set_tab_user_data`], or `None`
+    /// when the tab does not exist, has no data or has data of another type.
+    pub fn tab_user_data<T: Any + Send + Sync>(&self, tab_id: TabId) -> Option<Arc<T>> {
+        let tab_arc = self.get_tab(tab_id)?;
+        let tab = tab_arc.lock().ok()?;
+        tab.user_data()
+    }
+
+    /// Returns the frame timing of a tab: how many frames it rendered, how many went over the
+    /// frame budget set by [`EngineConfig::target_fps`] and how much work was deferred because
+    /// of that.
+    pub fn jank_stats(&self, tab_id: TabId) -> Option<JankStats> {
+        let tab_arc = self.get_tab(tab_id)?;
+        let tab = tab_arc.lock().ok()?;
+        Some(tab.jank_stats())
+    }
+
+    /// Returns the timing of the last frame a tab rendered, the same that came with its last
+    /// [`EngineNotification::FrameRendered`].
+    pub fn frame_stats(&self, tab_id: TabId) -> Option<FrameStats> {
+        let tab_arc = self.get_tab(tab_id)?;
+        let tab = tab_arc.lock().ok()?;
+        Some(tab.frame_stats())
+    }
+
     /// Open a new tab in a zone and return its [`TabId`].
     ///
+    /// Tabs are set up completely before this returns, so events and commands can be sent to
+    /// the tab right away. Subscribers are told with
+    /// [`EngineNotification::TabOpened`](crate::EngineNotification::TabOpened).
+    ///
     /// ```
     /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
     /// let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
@@ -115,13 +508,84 @@ impl GosubEngine {
             .ok_or(EngineError::ZoneNotFound)?;
         let mut zone = zone_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
 
-        zone.open_tab(self.runtime.clone(), viewport)
+        let tab_id = zone.open_tab(self.runtime.clone(), viewport)?;
+        self.log_activity(|| Activity::OpenTab {
+            zone_id,
+            tab_id,
+            viewport,
+        });
+        #[cfg(feature = "serde_events")]
+        self.record(RecordedInput::OpenTab { tab_id, viewport });
+        Ok(tab_id)
+    }
+
+    /// Returns the configuration of the engine.
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// Changes the settings that may change while the engine runs, see [`RuntimeConfig`]. The
+    /// other fields of the [`EngineConfig`] are fixed when the engine is created.
+    ///
+    /// ```
+    /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
+    /// let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    ///
+    /// engine.update_config(|c| {
+    ///     c.target_fps = Some(30);
+    ///     c.memory_cache_bytes = 16 * 1024 * 1024;
+    /// }).unwrap();
+    /// assert_eq!(engine.config().target_fps, Some(30));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidConfiguration`] when the result does not validate; nothing
+    /// is changed then.
+    pub fn update_config(&mut self, f: impl FnOnce(&mut RuntimeConfig)) -> Result<(), EngineError> {
+        let mut runtime = self.config.runtime();
+        f(&mut runtime);
+        let config = self
+            .config
+            .with_runtime(runtime)
+            .map_err(|e| EngineError::InvalidConfiguration(e.to_string()))?;
+
+        logging::set_max_level(config.log_level);
+        self.zone_manager.update_engine_config(config.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    /// Returns whether the host is online, as last reported with
+    /// [`EngineCommand::SetNetworkState`] or [`GosubEngine::set_network_state`].
+    pub fn is_online(&self) -> bool {
+        self.zone_manager.is_online()
+    }
+
+    /// Tells the engine whether the host is online, see [`EngineCommand::SetNetworkState`].
+    pub fn set_network_state(&mut self, online: bool) {
+        self.zone_manager.set_network_state(online);
+    }
+
+    /// Sends the engine's log messages to `sink` instead of the [`log`] logger, or with `None`
+    /// back to the logger. See the [`logging`](crate::logging) module.
+    pub fn set_log_sink(&self, sink: Option<LogSink>) {
+        logging::set_sink(sink);
     }
 
     /// Do an engine tick, processing all zones and tabs
     pub fn tick(&mut self, host: &mut impl CompositorSink) -> BTreeMap<TabId, TickResult> {
         let mut results = BTreeMap::new();
 
+        if self.backend.device_lost() {
+            self.recover_backend();
+        }
+        if let Some(message) = &self.backend_warning {
+            if self.zone_manager.warn_all(message) {
+                self.backend_warning = None;
+            }
+        }
+        self.share_frames();
+
         for zone_id in self.zone_manager.iter() {
             let Some(zone_arc) = self.zone_manager.get_zone(zone_id) else {
                 continue;
@@ -140,14 +604,149 @@ impl GosubEngine {
             }
         }
 
+        if self.last_memory_check.elapsed() >= MEMORY_CHECK_INTERVAL {
+            self.last_memory_check = Instant::now();
+            self.enforce_memory_limit();
+        }
+
+        let frames = results.values().filter(|r| r.needs_redraw).count();
+        self.frames.record_frames(frames, Instant::now());
         results
     }
 
+    /// Tells the tabs that have a frame to render whether it is their turn in this tick.
+    fn share_frames(&mut self) {
+        let sharing = self.config.frame_sharing;
+        if sharing == FrameSharing::Unlimited {
+            // Forgets the tabs that were waiting, in case the sharing was just turned off
+            self.frames.grant(sharing, &[]);
+            return;
+        }
+
+        let mut waiting = Vec::new();
+        for zone_id in self.zone_manager.iter() {
+            let Some(zone_arc) = self.zone_manager.get_zone(zone_id) else {
+                continue;
+            };
+            let Ok(zone) = zone_arc.lock() else {
+                continue;
+            };
+            for tab_arc in zone.tabs() {
+                let Ok(tab) = tab_arc.lock() else {
+                    continue;
+                };
+                if tab.wants_frame() {
+                    waiting.push((tab.id, tab_arc.clone()));
+                }
+            }
+        }
+
+        let ids: Vec<TabId> = waiting.iter().map(|(tab_id, _)| *tab_id).collect();
+        let granted = self.frames.grant(sharing, &ids);
+        for (tab_id, tab_arc) in waiting {
+            if let Ok(mut tab) = tab_arc.lock() {
+                tab.hold_frame(!granted.contains(&tab_id));
+            }
+        }
+    }
+
+    /// Sets the tab the user is working in, which renders before the others with
+    /// [`FrameSharing::FocusFirst`]. `None` when no tab has the focus.
+    pub fn set_focused_tab(&mut self, tab_id: Option<TabId>) {
+        self.frames.set_focused(tab_id);
+    }
+
+    /// Returns the frame timing of all tabs together: frames per second, and how often tabs
+    /// waited for their turn because of [`EngineConfig::frame_sharing`].
+    pub fn engine_frame_stats(&self) -> EngineFrameStats {
+        self.frames.stats()
+    }
+
+    /// Discards background tabs, least recently used first, until the estimated memory used by
+    /// all tabs is below [`EngineConfig::tab_memory_limit_bytes`]. Returns the discarded tabs.
+    ///
+    /// [`GosubEngine::tick`] does this about once a second. Call it directly to react to memory
+    /// pressure reported by the OS. Each discarded tab publishes an
+    /// [`EngineNotification::TabDiscarded`]; it is restored when it is made active again with
+    /// [`EngineCommand::SetMode`]. Pinned tabs are left alone, see [`EngineCommand::SetPinned`].
+    pub fn enforce_memory_limit(&mut self) -> Vec<TabId> {
+        let Some(limit) = self.config.tab_memory_limit_bytes else {
+            return Vec::new();
+        };
+
+        let mut used = 0u64;
+        let mut candidates = Vec::new();
+        for zone_id in self.zone_manager.iter() {
+            let Some(zone_arc) = self.zone_manager.get_zone(zone_id) else {
+                continue;
+            };
+            let Ok(zone) = zone_arc.lock() else {
+                continue;
+            };
+            for tab_arc in zone.tabs() {
+                let Ok(tab) = tab_arc.lock() else {
+                    continue;
+                };
+                used += tab.memory_estimate() as u64;
+                if tab.is_discardable() {
+                    candidates.push((tab.last_active(), tab_arc.clone()));
+                }
+            }
+        }
+
+        candidates.sort_by_key(|(last_active, _)| *last_active);
+
+        let mut discarded = Vec::new();
+        for (_, tab_arc) in candidates {
+            if used <= limit {
+                break;
+            }
+            let Ok(mut tab) = tab_arc.lock() else {
+                continue;
+            };
+            let freed = tab.discard(&mut *self.backend);
+            used = used.saturating_sub(freed as u64);
+            discarded.push(tab.id);
+        }
+        discarded
+    }
+
+    /// Do an engine tick and return the frames that tabs produced during it.
+    ///
+    /// This is the pull-based counterpart of [`GosubEngine::tick`], meant for immediate-mode
+    /// UIs (like egui) that repaint every frame: call it once per UI frame and paint whatever
+    /// it returns. Tabs without a new frame are not included, and frames of auxiliary
+    /// viewports are returned as separate entries. Loading progress is reported through
+    /// notifications (see [`GosubEngine::poll_event`]).
+    ///
+    /// ```
+    /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
+    /// let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    ///
+    /// for frame in engine.collect_frames() {
+    ///     println!("new frame for tab {:?}: {:?}", frame.tab_id, frame.handle);
+    /// }
+    /// ```
+    pub fn collect_frames(&mut self) -> Vec<Frame> {
+        let mut collector = FrameCollector::new();
+        self.tick(&mut collector);
+        collector.drain()
+    }
+
     /// Handle an event for a specific tab
     pub fn handle_event(&mut self, tab_id: TabId, event: EngineEvent) -> Result<(), EngineError> {
+        self.log_activity(|| Activity::Event {
+            tab_id,
+            event: event.clone(),
+        });
         let tab_arc = self.get_tab(tab_id).ok_or(EngineError::InvalidTabId)?;
         let mut tab = tab_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
 
+        #[cfg(feature = "serde_events")]
+        self.record(RecordedInput::Event {
+            tab_id,
+            event: event.clone(),
+        });
         tab.handle_event(event);
         Ok(())
     }
@@ -158,10 +757,167 @@ impl GosubEngine {
         tab_id: TabId,
         command: EngineCommand,
     ) -> Result<(), EngineError> {
+        self.log_activity(|| Activity::Command {
+            tab_id,
+            command: command.clone(),
+        });
+        if let EngineCommand::ResolvePopup {
+            request_id,
+            allow,
+            zone_id,
+        } = command
+        {
+            return self.resolve_popup(tab_id, request_id, allow, zone_id);
+        }
+        if let EngineCommand::CloseTab = command {
+            return self.close_tab(tab_id, false);
+        }
+        if let EngineCommand::ForceKill = command {
+            return self.close_tab(tab_id, true);
+        }
+        if let EngineCommand::SetNetworkState { online } = command {
+            self.set_network_state(online);
+            return Ok(());
+        }
+
         let tab_arc = self.get_tab(tab_id).ok_or(EngineError::InvalidTabId)?;
         let mut tab = tab_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
+        let _busy = self
+            .zone_manager
+            .watchdog()
+            .map(|w| w.busy(tab.zone_id, tab_id, tab.cancel_flag()));
 
+        #[cfg(feature = "serde_events")]
+        self.record(RecordedInput::Command {
+            tab_id,
+            command: command.clone(),
+        });
         tab.execute_command(command);
         Ok(())
     }
+
+    /// Closes a tab in whichever zone it is, see [`EngineCommand::CloseTab`] and
+    /// [`EngineCommand::ForceKill`].
+    fn close_tab(&mut self, tab_id: TabId, force: bool) -> Result<(), EngineError> {
+        for zone_id in self.zone_manager.iter() {
+            let Some(zone) = self.zone_manager.get_zone(zone_id) else {
+                continue;
+            };
+            let mut zone = zone.lock().map_err(|_| EngineError::ZoneLocked)?;
+            if zone.get_tab(tab_id).is_some() {
+                return if force {
+                    zone.force_close_tab(tab_id)
+                } else {
+                    zone.close_tab(tab_id)
+                };
+            }
+        }
+        Err(EngineError::InvalidTabId)
+    }
+
+    /// Answers a popup request of `opener`, see [`EngineCommand::ResolvePopup`].
+    fn resolve_popup(
+        &mut self,
+        opener: TabId,
+        request_id: PopupRequestId,
+        allow: bool,
+        zone_id: Option<ZoneId>,
+    ) -> Result<(), EngineError> {
+        let opener_arc = self.get_tab(opener).ok_or(EngineError::InvalidTabId)?;
+        let (submission, opener_zone, viewport, policy) = {
+            let mut tab = opener_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
+            let submission = tab
+                .take_popup_request(request_id)
+                .ok_or(EngineError::UnknownPopupRequest)?;
+            (
+                submission,
+                tab.zone_id,
+                *tab.context.viewport(),
+                tab.partition_policy,
+            )
+        };
+        if !allow {
+            return Ok(());
+        }
+
+        let zone_id = zone_id.unwrap_or(opener_zone);
+        let tab_id = self.open_tab_in_zone(zone_id, viewport)?;
+        // A popup in the opener's zone starts out with the opener's sessionStorage for its origin
+        if zone_id == opener_zone {
+            if let Some(zone) = self.zone_manager.get_zone(zone_id) {
+                let zone = zone.lock().map_err(|_| EngineError::ZoneLocked)?;
+                if let Err(e) =
+                    zone.clone_session_for_popup(opener, policy, tab_id, &submission.url)
+                {
+                    engine_log!(
+                        Warn,
+                        "cannot copy sessionStorage into popup {:?}: {}",
+                        tab_id,
+                        e
+                    );
+                }
+            }
+        }
+        let tab_arc = self.get_tab(tab_id).ok_or(EngineError::InvalidTabId)?;
+        let mut tab = tab_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
+        tab.open_as_popup(opener, request_id, submission);
+        Ok(())
+    }
+
+    /// Executes a command for a whole zone, such as replacing its configuration
+    pub fn execute_zone_command(
+        &mut self,
+        zone_id: ZoneId,
+        command: ZoneCommand,
+    ) -> Result<(), EngineError> {
+        self.log_activity(|| Activity::ZoneCommand {
+            zone_id,
+            command: command.clone(),
+        });
+        let zone_arc = self
+            .zone_manager
+            .get_zone(zone_id)
+            .ok_or(EngineError::ZoneNotFound)?;
+        let mut zone = zone_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
+        zone.execute_command(command)
+    }
+
+    /// Returns the most recent tabs opened, events and commands, oldest first, for attaching
+    /// to a bug report. Empty unless [`EngineConfig::activity_log_size`] is set; see the
+    /// [`activity`](crate::activity) module.
+    pub fn dump_recent_activity(&self) -> Vec<ActivityEntry> {
+        self.activity
+            .as_ref()
+            .map(ActivityLog::entries)
+            .unwrap_or_default()
+    }
+
+    /// Adds to the activity log, if the engine keeps one. `activity` is only called then, so
+    /// that events and commands are not cloned for nothing.
+    fn log_activity(&mut self, activity: impl FnOnce() -> Activity) {
+        if let Some(log) = self.activity.as_mut() {
+            log.record(activity());
+        }
+    }
+
+    /// Starts recording all tabs opened and all events and commands sent to tabs, from now on.
+    /// A recording that is already running is restarted. See the
+    /// [`recording`](crate::recording) module.
+    #[cfg(feature = "serde_events")]
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Recorder::new());
+    }
+
+    /// Stops recording and returns what was recorded, or `None` when not recording.
+    #[cfg(feature = "serde_events")]
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recorder.take().map(Recorder::finish)
+    }
+
+    #[cfg(feature = "serde_events")]
+    fn record(&mut self, input: RecordedInput) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(input);
+        }
+    }
 }
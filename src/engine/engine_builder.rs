@@ -0,0 +1,149 @@
+use crate::render::backend::RenderBackend;
+use crate::zone::{ZoneServices, ZoneServicesFactory};
+use crate::{EngineConfig, EngineError, GosubEngine};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// Builder for a [`GosubEngine`], for when [`GosubEngine::new`] is not enough.
+///
+/// Only the render backend is required. Everything else has the defaults of
+/// [`GosubEngine::new`]:
+///
+/// * [`EngineConfig`] — If not provided, [`EngineConfig::default`] is used.
+/// * Runtime — If not provided, the engine creates a tokio runtime with
+///   [`EngineConfig::worker_threads`] threads. Pass one to share it with the rest of the
+///   application.
+/// * Zone services — If not provided, zones keep their data in memory unless the zone
+///   builder is given stores. See [`ZoneServices`].
+/// * Notification capacity — If not provided, subscribers queue an unlimited number of
+///   notifications. With a capacity, a subscriber that falls behind misses notifications
+///   instead of growing without bounds; sequenced subscribers see the gap in the numbers.
+///
+/// ```
+/// use gosub_engine::render::backends::null::NullBackend;
+/// use gosub_engine::zone::ZoneServices;
+/// use gosub_engine::{EngineConfig, GosubEngine};
+///
+/// let mut engine = GosubEngine::builder()
+///     .backend(Box::new(NullBackend::new().unwrap()))
+///     .config(EngineConfig::builder().max_zones(4).build().unwrap())
+///     .zone_services(|_zone_id| ZoneServices::default())
+///     .notification_capacity(1024)
+///     .build()
+///     .unwrap();
+///
+/// let zone_id = engine.zone_builder().create().unwrap();
+/// ```
+#[derive(Default)]
+pub struct EngineBuilder {
+    /// Render backend of the engine
+    backend: Option<Box<dyn RenderBackend>>,
+    /// Optional configuration of the engine
+    config: Option<EngineConfig>,
+    /// Optional runtime to run async work on
+    runtime: Option<Arc<Runtime>>,
+    /// Optional factory for the services of new zones
+    zone_services: Option<ZoneServicesFactory>,
+    /// Optional limit on queued notifications per subscriber
+    notification_capacity: Option<usize>,
+}
+
+impl GosubEngine {
+    /// Entry point to start building an engine.
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+}
+
+impl EngineBuilder {
+    pub fn backend(mut self, backend: Box<dyn RenderBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    pub fn config(mut self, config: EngineConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn runtime(mut self, runtime: Arc<Runtime>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    pub fn zone_services(
+        mut self,
+        factory: impl Fn(crate::zone::ZoneId) -> ZoneServices + Send + Sync + 'static,
+    ) -> Self {
+        self.zone_services = Some(Arc::new(factory));
+        self
+    }
+
+    pub fn notification_capacity(mut self, capacity: usize) -> Self {
+        self.notification_capacity = Some(capacity);
+        self
+    }
+
+    /// Creates the engine.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidConfiguration`] when no backend was set or the
+    /// notification capacity is zero.
+    pub fn build(self) -> Result<GosubEngine, EngineError> {
+        let backend = self.backend.ok_or_else(|| {
+            EngineError::InvalidConfiguration("a render backend is required".to_string())
+        })?;
+        if self.notification_capacity == Some(0) {
+            return Err(EngineError::InvalidConfiguration(
+                "notification capacity must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(GosubEngine::from_parts(
+            self.config.unwrap_or_default(),
+            backend,
+            self.runtime,
+            self.zone_services,
+            self.notification_capacity,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::backends::null::NullBackend;
+    use crate::zone::SiteSettingsStore;
+
+    #[test]
+    fn zones_get_the_factory_services_unless_given_their_own() {
+        let shared = Arc::new(SiteSettingsStore::in_memory());
+        let factory_store = shared.clone();
+        let mut engine = GosubEngine::builder()
+            .backend(Box::new(NullBackend::new().unwrap()))
+            .zone_services(move |_| ZoneServices {
+                site_settings: Some(factory_store.clone()),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let own = Arc::new(SiteSettingsStore::in_memory());
+        let from_factory = engine.zone_builder().create().unwrap();
+        let explicit = engine
+            .zone_builder()
+            .site_settings(own.clone())
+            .create()
+            .unwrap();
+
+        let store_of = |engine: &mut GosubEngine, zone_id| {
+            let zone = engine.get_zone_mut(zone_id).unwrap();
+            let store = zone.lock().unwrap().site_settings();
+            store
+        };
+        assert!(Arc::ptr_eq(&store_of(&mut engine, from_factory), &shared));
+        assert!(Arc::ptr_eq(&store_of(&mut engine, explicit), &own));
+
+        assert!(GosubEngine::builder().build().is_err());
+    }
+}
@@ -9,6 +9,14 @@ pub enum EngineError {
     #[error("Invalid zone ID")]
     InvalidZoneId,
 
+    /// The zone has no tab group with the provided ID.
+    #[error("Invalid tab group ID")]
+    InvalidTabGroupId,
+
+    /// The tab has no pending popup request with the provided ID; it was already answered
+    #[error("Unknown popup request")]
+    UnknownPopupRequest,
+
     /// The zone manager cannot create any more zones (limit reached)
     #[error("Zone limit exceeded")]
     ZoneLimitExceeded,
@@ -45,7 +53,27 @@ pub enum EngineError {
     #[error("Zone already exists")]
     ZoneAlreadyExists,
 
+    /// A storage operation (local/session storage, cookies) has failed
+    #[error("Storage error: {0}")]
+    StorageError(String),
+
+    /// The profile file at the given path is already used by another engine instance
+    #[error("Profile in use by another instance: {0}")]
+    ProfileInUse(String),
+
     /// An invalid configuration was provided for the engine or zone
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
+
+    /// Typed input could not be turned into a URL
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    /// An extension with the same id is already loaded in the zone
+    #[error("Extension already loaded: {0}")]
+    ExtensionAlreadyLoaded(String),
+
+    /// The zone does not share its cookie jar with the zone that tried to read it
+    #[error("Cookie jar is not shared with this zone")]
+    CookieJarNotShared,
 }
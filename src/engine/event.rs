@@ -1,7 +1,17 @@
+use crate::render::{PrintToPdf, Viewport};
+use crate::tab::{
+    AuxViewportId, FileChooserRequestId, PopupRequestId, TabId, TabMode, TabOverrides,
+};
+use crate::zone::{ExtensionId, TabGroupId, ZoneConfig, ZoneId};
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::PathBuf;
 use url::Url;
 
 /// Represents a mouse button that can be pressed or released
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
 pub enum MouseButton {
     /// Left mouse button pressed (or depressed)
     Left,
@@ -11,8 +21,117 @@ pub enum MouseButton {
     Right,
 }
 
+/// Modifier keys held down, as tracked by a tab from the [`EngineEvent::KeyDown`] and
+/// [`EngineEvent::KeyUp`] events of `Control`, `Shift`, `Alt` and `Meta`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyModifiers {
+    /// A `Control` key is down
+    pub ctrl: bool,
+    /// A `Shift` key is down
+    pub shift: bool,
+    /// An `Alt` key is down
+    pub alt: bool,
+    /// A `Meta` key (Command on macOS, Windows key elsewhere) is down
+    pub meta: bool,
+}
+
+impl KeyModifiers {
+    /// Records a key press or release. Returns false when `key` is not a modifier.
+    pub(crate) fn update(&mut self, key: &str, down: bool) -> bool {
+        let flag = match key {
+            "Control" => &mut self.ctrl,
+            "Shift" => &mut self.shift,
+            "Alt" => &mut self.alt,
+            "Meta" => &mut self.meta,
+            _ => return false,
+        };
+        *flag = down;
+        true
+    }
+}
+
+/// Where the user wants a link or form they activated to load, following the usual
+/// conventions of browsers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum NavigationDisposition {
+    /// Load in the tab itself
+    #[default]
+    CurrentTab,
+    /// Open a new tab without switching to it
+    NewBackgroundTab,
+    /// Open a new tab and switch to it
+    NewForegroundTab,
+    /// Open a new window
+    NewWindow,
+}
+
+impl NavigationDisposition {
+    /// Returns the disposition of a click with `button` while `modifiers` are held.
+    ///
+    /// A middle click, or a click with `Control` or `Meta`, opens a background tab, and with
+    /// `Shift` added a foreground tab. `Shift` alone opens a new window. Both `Control` and
+    /// `Meta` count so user agents need not know the platform convention.
+    ///
+    /// ```
+    /// use gosub_engine::{KeyModifiers, MouseButton, NavigationDisposition};
+    ///
+    /// let none = KeyModifiers::default();
+    /// let ctrl = KeyModifiers { ctrl: true, ..Default::default() };
+    /// assert_eq!(
+    ///     NavigationDisposition::from_click(&MouseButton::Left, none),
+    ///     NavigationDisposition::CurrentTab
+    /// );
+    /// assert_eq!(
+    ///     NavigationDisposition::from_click(&MouseButton::Left, ctrl),
+    ///     NavigationDisposition::NewBackgroundTab
+    /// );
+    /// ```
+    pub fn from_click(button: &MouseButton, modifiers: KeyModifiers) -> Self {
+        let new_tab = matches!(button, MouseButton::Middle) || modifiers.ctrl || modifiers.meta;
+        match (new_tab, modifiers.shift) {
+            (true, false) => NavigationDisposition::NewBackgroundTab,
+            (true, true) => NavigationDisposition::NewForegroundTab,
+            (false, true) => NavigationDisposition::NewWindow,
+            (false, false) => NavigationDisposition::CurrentTab,
+        }
+    }
+}
+
+/// What is being dragged onto a tab from outside the engine.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum DragPayload {
+    /// Links, such as a bookmark or a link dragged from another tab. `file:` links are only
+    /// opened in zones with
+    /// [`ZoneConfig::enable_local_file_access`](crate::zone::ZoneConfig::enable_local_file_access).
+    Urls(Vec<Url>),
+    /// Plain text
+    Text(String),
+    /// Files from the file manager. Only accepted by file inputs, as if the user had chosen
+    /// them.
+    Files(Vec<PathBuf>),
+}
+
+/// What a tab does with a payload when it is dropped, reported while dragging so the user
+/// agent can show a matching cursor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum DropEffect {
+    /// The drop is refused
+    #[default]
+    None,
+    /// The tab navigates to the dropped link
+    Navigate,
+    /// The text is inserted into the form control under the pointer
+    InsertText,
+    /// The files are chosen for the file input under the pointer
+    ChooseFiles,
+}
+
 /// Events that have occurred and must be passed to the engine from the user agent
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
 pub enum EngineEvent {
     /// Move has moved to a new position
     MouseMove {
@@ -68,13 +187,461 @@ pub enum EngineEvent {
         /// The new height of the viewport
         height: u32,
     },
+    /// The tab moved to a screen with another device scale, or the scale of its screen
+    /// changed. Sizes and positions in events stay in device pixels.
+    ScaleFactorChanged {
+        /// Device pixels per document pixel, such as 2.0 on a HiDPI screen
+        scale: f32,
+    },
+    /// A finger touched the screen. `id` identifies the touch point until it ends, so
+    /// several fingers can be tracked at once.
+    TouchStart {
+        /// Identifier of the touch point
+        id: u64,
+        /// The x coordinate of the touch
+        x: f32,
+        /// The y coordinate of the touch
+        y: f32,
+    },
+    /// A touch point moved
+    TouchMove {
+        /// Identifier of the touch point
+        id: u64,
+        /// The new x coordinate of the touch
+        x: f32,
+        /// The new y coordinate of the touch
+        y: f32,
+    },
+    /// A finger was lifted from the screen
+    TouchEnd {
+        /// Identifier of the touch point
+        id: u64,
+        /// The x coordinate where the touch ended
+        x: f32,
+        /// The y coordinate where the touch ended
+        y: f32,
+    },
+    /// The platform cancelled all active touches (e.g. a system gesture took over)
+    TouchCancel,
+    /// Pointer input batched by the user agent over one frame. Equivalent to a `MouseMove`
+    /// to `position` followed by a `Scroll` of the summed deltas, but cheaper to send for
+    /// high-frequency input.
+    PointerFrame {
+        /// The last pointer position in the frame, if the pointer moved
+        position: Option<(f32, f32)>,
+        /// The summed horizontal scroll delta
+        scroll_dx: f32,
+        /// The summed vertical scroll delta
+        scroll_dy: f32,
+    },
+}
+
+/// Appends `event` to a queue of pending input, merging it with the last queued event where
+/// only the end result matters.
+///
+/// Consecutive mouse moves, resizes, scale changes and moves of the same touch point keep only the latest one, consecutive scrolls and
+/// pointer frames are summed. Anything else (button and key events) is queued as-is, so the
+/// relative order of input is preserved.
+pub(crate) fn coalesce_into(queue: &mut Vec<EngineEvent>, event: EngineEvent) {
+    use EngineEvent::*;
+
+    match (queue.last_mut(), event) {
+        (Some(MouseMove { x, y }), MouseMove { x: nx, y: ny }) => {
+            (*x, *y) = (nx, ny);
+        }
+        (
+            Some(Resize { width, height }),
+            Resize {
+                width: nw,
+                height: nh,
+            },
+        ) => {
+            (*width, *height) = (nw, nh);
+        }
+        (Some(ScaleFactorChanged { scale }), ScaleFactorChanged { scale: ns }) => {
+            *scale = ns;
+        }
+        (
+            Some(TouchMove { id, x, y }),
+            TouchMove {
+                id: nid,
+                x: nx,
+                y: ny,
+            },
+        ) if *id == nid => {
+            (*x, *y) = (nx, ny);
+        }
+        (Some(Scroll { dx, dy }), Scroll { dx: ndx, dy: ndy }) => {
+            *dx += ndx;
+            *dy += ndy;
+        }
+        (
+            Some(PointerFrame {
+                position,
+                scroll_dx,
+                scroll_dy,
+            }),
+            PointerFrame {
+                position: np,
+                scroll_dx: ndx,
+                scroll_dy: ndy,
+            },
+        ) => {
+            *position = np.or(*position);
+            *scroll_dx += ndx;
+            *scroll_dy += ndy;
+        }
+        (_, event) => queue.push(event),
+    }
 }
 
 /// Commands that the engine need to execute
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
 pub enum EngineCommand {
     /// An url must be loaded inside the tab
     Navigate(Url),
+    /// Typed input, such as the text of an address bar, must be loaded inside the tab. The
+    /// zone's [`UrlResolver`](crate::zone::UrlResolver) turns it into a URL; input it cannot
+    /// resolve is reported with
+    /// [`EngineNotification::NavigationFailed`](crate::EngineNotification::NavigationFailed).
+    NavigateInput(String),
+    /// Load the given HTML as if it was the response of a navigation to `base_url` (or
+    /// `about:blank`). The network is not used.
+    LoadHtml {
+        /// The document
+        html: String,
+        /// URL of the document, used for relative links and as the history entry
+        base_url: Option<Url>,
+    },
+    /// Load the given content as if it was the response of a navigation to `base_url` (or
+    /// `about:blank`). Only textual types can be displayed; other types fail to load.
+    LoadBytes {
+        /// The content
+        data: Vec<u8>,
+        /// Media type of the content, such as `text/html; charset=utf-8`
+        mime: String,
+        /// URL of the content, used for relative links and as the history entry
+        base_url: Option<Url>,
+    },
     /// Reload the current URL in the tab
     Reload(),
+    /// Go back one entry in the tab's history
+    GoBack(),
+    /// Go forward one entry in the tab's history
+    GoForward(),
+    /// Additionally render the tab into a separate surface of the given size, e.g. for a
+    /// tab preview or picture-in-picture. Frames for it are submitted through
+    /// [`CompositorSink::submit_aux_frame`](crate::render::backend::CompositorSink::submit_aux_frame).
+    /// Adding an existing `id` again replaces its viewport.
+    AddAuxViewport {
+        /// Identifier chosen by the user agent
+        id: AuxViewportId,
+        /// Area and size to render
+        viewport: Viewport,
+    },
+    /// Stop rendering into an auxiliary viewport
+    RemoveAuxViewport {
+        /// Identifier of the viewport
+        id: AuxViewportId,
+    },
+    /// Replace the tab's exceptions to the zone settings, such as Do Not Track
+    SetOverrides(TabOverrides),
+    /// Change the tab's activity mode, for instance when the user switches tabs. Activating a
+    /// discarded tab loads its page again.
+    SetMode(TabMode),
+    /// Tell the tab whether the user agent shows it, following the UA's own layout: a tab in
+    /// the background of a tab strip or in a collapsed pane is not `visible`, a pane behind
+    /// another window is `occluded`. Tabs that cannot be seen either way do not render, and
+    /// tick at most 10 times a second; their frame is rendered when they are shown again.
+    /// Hidden tabs also report `"hidden"` as the
+    /// [`visibility_state`](crate::BrowsingContext::visibility_state) of their document.
+    /// Tabs are visible and not occluded when opened.
+    SetVisibility {
+        /// Whether the tab is laid out on screen
+        visible: bool,
+        /// Whether something covers all of the tab
+        occluded: bool,
+    },
+    /// Start (`true`) or stop sending the messages of the tab's documents as
+    /// [`EngineNotification::ConsoleMessage`](crate::EngineNotification::ConsoleMessage).
+    /// Off by default; messages logged while it is off are dropped.
+    EnableConsoleCapture(bool),
+    /// Pin or unpin the tab. Pinned tabs are never discarded to save memory; the flag is shown
+    /// in [`TabPublicState::pinned`](crate::tab::TabPublicState::pinned). Tabs of a restored
+    /// session are pinned with
+    /// [`Zone::restore_pinned_tabs`](crate::zone::Zone::restore_pinned_tabs).
+    SetPinned {
+        /// Whether the tab is pinned
+        pinned: bool,
+    },
+    /// Stop the navigation in flight. The current document stays, and
+    /// [`EngineNotification::LoadCancelled`](crate::EngineNotification::LoadCancelled) is
+    /// published with the URL that was being loaded. Does nothing when the tab is not loading.
+    StopLoading,
+    /// Export the requests made for the tab's latest navigation as a HAR 1.2 document, for
+    /// performance tools that import HTTP archives. The document is published as
+    /// [`EngineNotification::HarExported`](crate::EngineNotification::HarExported).
+    ExportHar,
+    /// Print the current document to PDF with the page setup of `options`. The document is
+    /// published as [`EngineNotification::PdfPrinted`](crate::EngineNotification::PdfPrinted);
+    /// options that cannot be printed with only raise a warning.
+    PrintToPdf {
+        /// Paper, margins, scale, page ranges, header and footer
+        options: PrintToPdf,
+    },
+    /// Open a connection to `origin` ahead of a navigation the user agent expects, for
+    /// instance when the user hovers a link, so that the navigation starts faster. Documents
+    /// ask for the same with `<link rel="preconnect">`. The connection is shared by the tabs
+    /// of the zone; see [`Zone::connection_stats`](crate::zone::Zone::connection_stats).
+    Preconnect {
+        /// URL of the origin to connect to
+        origin: Url,
+    },
+    /// Close the tab. Its loads are stopped, its session storage is dropped and it leaves its
+    /// group; the engine then publishes
+    /// [`EngineNotification::TabClosed`](crate::EngineNotification::TabClosed) and the tab no
+    /// longer counts toward [`ZoneConfig::max_tabs`](crate::zone::ZoneConfig::max_tabs).
+    CloseTab,
+    /// Close the tab without waiting for it, typically after
+    /// [`EngineNotification::TabUnresponsive`](crate::EngineNotification::TabUnresponsive).
+    /// Like [`EngineCommand::CloseTab`], but the tab is dropped from its zone even when
+    /// another thread still holds it, and it is killed: a holder stuck in its pipeline gives
+    /// up at the next stage, or the next line of its layout. A frame the backend is painting is
+    /// finished first. A tab stuck on the thread that sends commands is killed with
+    /// [`TabKiller`](crate::TabKiller) instead.
+    ForceKill,
+    /// Tell the engine whether the host is online, for instance when the operating system
+    /// reports a change in connectivity. Applies to the whole engine, whichever tab it is sent
+    /// to. While offline, navigations fail right away with an offline error page unless the
+    /// page is cached. A change is announced with
+    /// [`EngineNotification::NetworkStateChanged`](crate::EngineNotification::NetworkStateChanged).
+    SetNetworkState {
+        /// Whether the host is online
+        online: bool,
+    },
+    /// Answer a [`EngineNotification::PopupRequested`](crate::EngineNotification::PopupRequested)
+    /// or [`EngineNotification::OpenUrlRequested`](crate::EngineNotification::OpenUrlRequested)
+    /// of this tab. When allowed, the engine opens a tab with this tab as its opener, loads the
+    /// requested page in it and publishes
+    /// [`EngineNotification::PopupOpened`](crate::EngineNotification::PopupOpened).
+    ResolvePopup {
+        /// The request to answer
+        request_id: PopupRequestId,
+        /// Whether to open the popup; refused requests are dropped
+        allow: bool,
+        /// Zone to open the tab in, the zone of this tab when `None`
+        zone_id: Option<ZoneId>,
+    },
+    /// Replace a word that the spellchecker flagged in a form control, usually with one of the
+    /// [`BrowsingContext::spelling_suggestions`](crate::BrowsingContext::spelling_suggestions)
+    ReplaceMisspelling {
+        /// Index of the control in the document's forms
+        control: usize,
+        /// Byte range of the word in the control's value
+        range: Range<usize>,
+        /// Text to put in its place
+        replacement: String,
+    },
+    /// Deliver a message to the content scripts of an extension in the current document
+    MessageExtension {
+        /// Extension to deliver to
+        extension_id: ExtensionId,
+        /// Message payload, usually JSON
+        message: String,
+    },
+    /// Paste text the user agent read from the clipboard into the focused form control,
+    /// replacing its selection. Ignored when no text field has the focus.
+    Paste {
+        /// Text to paste
+        text: String,
+    },
+    /// Copy the selection of the focused form control. The engine does not touch the
+    /// clipboard itself; the text comes back in an
+    /// [`EngineNotification::ClipboardRequested`](crate::EngineNotification::ClipboardRequested).
+    Copy,
+    /// Like [`EngineCommand::Copy`], and removes the selection from the control
+    Cut,
+    /// Select the whole value of the focused form control
+    SelectAll,
+    /// Answer a
+    /// [`EngineNotification::FileChooserRequested`](crate::EngineNotification::FileChooserRequested)
+    /// of this tab with the files the user chose. An empty list means the user cancelled; an
+    /// input without `multiple` keeps only the first file. The files are read when the form is
+    /// submitted.
+    ResolveFileChooser {
+        /// The request to answer
+        request_id: FileChooserRequestId,
+        /// Chosen files
+        files: Vec<PathBuf>,
+    },
+    /// A drag entered the tab at `(x, y)` (viewport coordinates). The tab answers with an
+    /// [`EngineNotification::DropEffectChanged`](crate::EngineNotification::DropEffectChanged)
+    /// whenever what a drop would do changes.
+    DragEnter {
+        /// The x coordinate of the pointer
+        x: f32,
+        /// The y coordinate of the pointer
+        y: f32,
+        /// What is dragged
+        payload: DragPayload,
+    },
+    /// The drag moved within the tab
+    DragOver {
+        /// The x coordinate of the pointer
+        x: f32,
+        /// The y coordinate of the pointer
+        y: f32,
+    },
+    /// The drag left the tab or was cancelled
+    DragLeave,
+    /// The payload was dropped at `(x, y)`. Text goes into the form control under the
+    /// pointer, files into the file input under it, and links are loaded in the tab.
+    Drop {
+        /// The x coordinate of the pointer
+        x: f32,
+        /// The y coordinate of the pointer
+        y: f32,
+        /// What was dropped
+        payload: DragPayload,
+    },
+}
+
+/// Commands that change a whole zone, see
+/// [`GosubEngine::execute_zone_command`](crate::GosubEngine::execute_zone_command)
+#[derive(Debug, Clone)]
+pub enum ZoneCommand {
+    /// Replace the zone's configuration. How the open tabs pick up the change is described at
+    /// [`Zone::update_config`](crate::zone::Zone::update_config).
+    UpdateConfig(Box<ZoneConfig>),
+    /// Move a tab into a group, see [`Zone::add_tab_to_group`](crate::zone::Zone::add_tab_to_group)
+    AddTabToGroup {
+        /// Tab to move
+        tab_id: TabId,
+        /// Group to move it into
+        group_id: TabGroupId,
+    },
+    /// Take a tab out of its group
+    RemoveTabFromGroup(TabId),
+    /// Exempt an origin from storage eviction, or make it evictable again, see
+    /// [`Zone::set_storage_persistent`](crate::zone::Zone::set_storage_persistent)
+    SetStoragePersistent {
+        /// Origin whose storage to keep
+        origin: url::Origin,
+        /// Whether the storage is kept when the zone runs out of room
+        persistent: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn click_dispositions_follow_buttons_and_modifiers() {
+        let mut modifiers = KeyModifiers::default();
+        let click = |button, modifiers| NavigationDisposition::from_click(&button, modifiers);
+
+        assert_eq!(
+            click(MouseButton::Middle, modifiers),
+            NavigationDisposition::NewBackgroundTab
+        );
+        assert!(modifiers.update("Shift", true));
+        assert!(!modifiers.update("a", true));
+        assert_eq!(
+            click(MouseButton::Left, modifiers),
+            NavigationDisposition::NewWindow
+        );
+        modifiers.update("Meta", true);
+        assert_eq!(
+            click(MouseButton::Left, modifiers),
+            NavigationDisposition::NewForegroundTab
+        );
+        modifiers.update("Shift", false);
+        assert_eq!(
+            click(MouseButton::Left, modifiers),
+            NavigationDisposition::NewBackgroundTab
+        );
+    }
+
+    #[test]
+    fn coalescing_merges_moves_and_scrolls_but_keeps_order() {
+        let mut queue = Vec::new();
+        coalesce_into(&mut queue, EngineEvent::MouseMove { x: 1.0, y: 1.0 });
+        coalesce_into(&mut queue, EngineEvent::MouseMove { x: 5.0, y: 7.0 });
+        coalesce_into(&mut queue, EngineEvent::Scroll { dx: 0.0, dy: 10.0 });
+        coalesce_into(&mut queue, EngineEvent::Scroll { dx: 2.0, dy: 15.0 });
+        coalesce_into(
+            &mut queue,
+            EngineEvent::MouseDown {
+                button: MouseButton::Left,
+                x: 5.0,
+                y: 7.0,
+            },
+        );
+        coalesce_into(&mut queue, EngineEvent::MouseMove { x: 6.0, y: 8.0 });
+
+        assert_eq!(queue.len(), 4);
+        assert!(matches!(
+            queue[0],
+            EngineEvent::MouseMove { x: 5.0, y: 7.0 }
+        ));
+        assert!(matches!(
+            queue[1],
+            EngineEvent::Scroll { dx: 2.0, dy: 25.0 }
+        ));
+        assert!(matches!(queue[2], EngineEvent::MouseDown { .. }));
+        assert!(matches!(
+            queue[3],
+            EngineEvent::MouseMove { x: 6.0, y: 8.0 }
+        ));
+    }
+
+    #[test]
+    fn coalescing_sums_pointer_frames() {
+        let mut queue = Vec::new();
+        let frame = |position, dy| EngineEvent::PointerFrame {
+            position,
+            scroll_dx: 0.0,
+            scroll_dy: dy,
+        };
+        coalesce_into(&mut queue, frame(Some((1.0, 2.0)), 3.0));
+        coalesce_into(&mut queue, frame(None, 4.0));
+
+        assert_eq!(queue.len(), 1);
+        assert!(matches!(
+            queue[0],
+            EngineEvent::PointerFrame {
+                position: Some((1.0, 2.0)),
+                scroll_dy: 7.0,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "serde_events")]
+    #[test]
+    fn events_and_commands_round_trip_through_json() {
+        let ev = EngineEvent::MouseDown {
+            button: MouseButton::Left,
+            x: 10.0,
+            y: 20.5,
+        };
+        let json = serde_json::to_string(&ev).unwrap();
+        match serde_json::from_str::<EngineEvent>(&json).unwrap() {
+            EngineEvent::MouseDown { button, x, y } => {
+                assert!(matches!(button, MouseButton::Left));
+                assert_eq!((x, y), (10.0, 20.5));
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+
+        let cmd = EngineCommand::Navigate(Url::parse("https://example.com/").unwrap());
+        let json = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(json, r#"{"Navigate":"https://example.com/"}"#);
+        assert!(matches!(
+            serde_json::from_str::<EngineCommand>(&json).unwrap(),
+            EngineCommand::Navigate(url) if url.as_str() == "https://example.com/"
+        ));
+    }
 }
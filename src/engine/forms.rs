@@ -0,0 +1,1061 @@
+//! Form controls and form submission.
+//!
+//! Until the engine has a real DOM, forms are picked up from the raw HTML of a page: every
+//! `<form>`, `<input>`, `<textarea>`, `<button>` and `<select>` tag is turned into a
+//! [`FormControl`] with its own value state. Controls are drawn on top of the page as simple
+//! boxes and respond to mouse clicks and keyboard input forwarded by the tab.
+//!
+//! Activating a submit button (or pressing Enter in a text field) produces a
+//! [`FormSubmission`], which the tab then loads as a GET or POST navigation. Forms that target
+//! a new browsing context (`target="_blank"`) ask the user agent for a popup instead.
+//!
+//! File inputs do not pick files themselves: activating one returns
+//! [`FormOutcome::ChooseFiles`], and the user agent's answer is stored with
+//! [`FormState::set_files`]. Forms with `enctype="multipart/form-data"` then upload the files
+//! as a [`MultipartForm`].
+use crate::engine::spellcheck::{word_ranges, SpellcheckProvider};
+use crate::net::MultipartForm;
+use crate::render::{Color, DisplayItem, FontSettings, RenderList};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use url::form_urlencoded;
+use url::Url;
+
+/// Distance between two lines of the raw-HTML layout, in CSS pixels.
+const LINE_HEIGHT: f32 = 16.0;
+/// Left margin of the raw-HTML layout, in CSS pixels.
+const MARGIN_X: f32 = 14.0;
+/// Font size of the text inside controls, in CSS pixels.
+const CONTROL_FONT_SIZE: f32 = 13.0;
+/// Estimated advance of a character inside controls, relative to the font size. There is no
+/// text shaping at this level yet, so squiggles are positioned using this average width.
+const CONTROL_CHAR_WIDTH: f32 = 0.55;
+
+/// HTTP method used to submit a form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormMethod {
+    /// Form data is appended to the action URL as a query string
+    #[default]
+    Get,
+    /// Form data is sent in the request body, encoded as the form's [`FormEnctype`]
+    Post,
+}
+
+/// How a POST form encodes its data, from its `enctype` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormEnctype {
+    /// `application/x-www-form-urlencoded`; file inputs only send the names of their files
+    #[default]
+    UrlEncoded,
+    /// `multipart/form-data`, which uploads the content of chosen files
+    Multipart,
+}
+
+/// A `<form>` element.
+#[derive(Debug, Clone, Default)]
+pub struct Form {
+    /// Value of the `action` attribute (resolved against the page URL on submission)
+    pub action: Option<String>,
+    /// Submission method
+    pub method: FormMethod,
+    /// Encoding of the form data for POST submissions
+    pub enctype: FormEnctype,
+    /// Value of the `target` attribute, naming the browsing context to load the result in
+    pub target: Option<String>,
+}
+
+/// The kind of a form control, with any kind-specific state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormControlKind {
+    /// Single-line text input
+    Text,
+    /// Single-line text input whose value is masked
+    Password,
+    /// Input that is submitted but not shown
+    Hidden,
+    /// Multi-line text input
+    TextArea,
+    /// Button; `submit` buttons submit their form when activated
+    Button {
+        /// True for `type=submit` (the default for `<button>`)
+        submit: bool,
+    },
+    /// Drop-down list of options
+    Select {
+        /// `(value, label)` of every option
+        options: Vec<(String, String)>,
+        /// Index of the selected option
+        selected: usize,
+    },
+    /// File input; files are chosen by the user agent
+    File {
+        /// Media types and extensions from the `accept` attribute, such as `image/*` or `.pdf`
+        accept: Vec<String>,
+        /// True when more than one file may be chosen
+        multiple: bool,
+        /// Chosen files
+        files: Vec<PathBuf>,
+    },
+}
+
+/// A single interactive form control.
+#[derive(Debug, Clone)]
+pub struct FormControl {
+    /// Kind of control
+    pub kind: FormControlKind,
+    /// Name under which the value is submitted. Unnamed controls are not submitted.
+    pub name: Option<String>,
+    /// Current value (for buttons: the label; for selects: the selected option's value; for
+    /// file inputs: the names of the chosen files)
+    pub value: String,
+    /// Index of the form the control belongs to, if any
+    pub form: Option<usize>,
+    /// Position and size in page coordinates at 100% zoom: `(x, y, width, height)`
+    pub rect: (f32, f32, f32, f32),
+    /// Byte ranges of `value` that the spellchecker flagged
+    pub misspellings: Vec<Range<usize>>,
+}
+
+impl FormControl {
+    /// Returns true when the control's text is spellchecked. Passwords never are.
+    pub fn is_spellchecked(&self) -> bool {
+        matches!(self.kind, FormControlKind::Text | FormControlKind::TextArea)
+    }
+
+    /// Returns true when the control accepts text input.
+    pub fn is_editable(&self) -> bool {
+        matches!(
+            self.kind,
+            FormControlKind::Text | FormControlKind::Password | FormControlKind::TextArea
+        )
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let (cx, cy, w, h) = self.rect;
+        x >= cx && x < cx + w && y >= cy && y < cy + h
+    }
+
+    /// Text drawn inside the control.
+    fn display_text(&self) -> String {
+        match &self.kind {
+            FormControlKind::Password => "\u{2022}".repeat(self.value.chars().count()),
+            FormControlKind::Select { options, selected } => options
+                .get(*selected)
+                .map(|(_, label)| format!("{label} \u{25BE}"))
+                .unwrap_or_default(),
+            FormControlKind::File { files, .. } => match files.len() {
+                0 => "Choose file\u{2026}".to_string(),
+                1 => self.value.clone(),
+                n => format!("{n} files"),
+            },
+            _ => self.value.clone(),
+        }
+    }
+}
+
+/// A form that is ready to be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSubmission {
+    /// Method to use
+    pub method: FormMethod,
+    /// URL to load. For GET submissions the form data is already in the query string.
+    pub url: Url,
+    /// `application/x-www-form-urlencoded` body, for POST submissions
+    pub body: Option<Vec<u8>>,
+    /// `multipart/form-data` body, for POST submissions of forms with that `enctype`. `body`
+    /// is `None` then.
+    pub multipart: Option<MultipartForm>,
+    /// Browsing context the form targets, from its `target` attribute
+    pub target: Option<String>,
+}
+
+impl FormSubmission {
+    /// Returns the number of bytes the submission uploads, or 0 for GET submissions. Files that
+    /// cannot be read do not count.
+    pub fn upload_size(&self) -> usize {
+        match (&self.body, &self.multipart) {
+            (Some(body), _) => body.len(),
+            (None, Some(form)) => form.content_length().unwrap_or(0) as usize,
+            (None, None) => 0,
+        }
+    }
+
+    /// Returns true when the submission should load in a new tab rather than in the tab of the
+    /// form. Frames do not exist, so only `_self`, `_parent` and `_top` stay in the tab.
+    pub fn opens_new_tab(&self) -> bool {
+        match self.target.as_deref() {
+            None | Some("") => false,
+            Some(target) => !["_self", "_parent", "_top"]
+                .iter()
+                .any(|t| target.eq_ignore_ascii_case(t)),
+        }
+    }
+}
+
+/// The forms and controls of a document, plus which control has the keyboard focus.
+///
+/// Text is always inserted at the end of the focused control's value, unless part of the
+/// value is selected: then typed and pasted text replaces the selection. Moving the focus
+/// drops the selection.
+#[derive(Debug, Clone, Default)]
+pub struct FormState {
+    forms: Vec<Form>,
+    controls: Vec<FormControl>,
+    focused: Option<usize>,
+    /// Byte range of the focused control's value that is selected
+    selection: Option<Range<usize>>,
+}
+
+impl FormState {
+    /// Extracts the forms and controls from raw HTML.
+    pub fn from_html(html: &str) -> FormState {
+        let mut state = FormState::default();
+        let mut current_form = None;
+        // Select whose `<option>` tags are being collected
+        let mut open_select: Option<usize> = None;
+
+        for (line_idx, line) in html.lines().enumerate() {
+            let y = line_idx as f32 * LINE_HEIGHT + 8.0;
+            let mut x = MARGIN_X;
+
+            for (tag, attrs, rest) in tags(line) {
+                let mut push = |state: &mut FormState, kind, name, value, width: f32| {
+                    state.controls.push(FormControl {
+                        kind,
+                        name,
+                        value,
+                        form: current_form,
+                        rect: (x, y, width, LINE_HEIGHT),
+                        misspellings: Vec::new(),
+                    });
+                    x += width + 10.0;
+                };
+
+                match tag.as_str() {
+                    "form" => {
+                        state.forms.push(Form {
+                            action: attr(attrs, "action"),
+                            method: match attr(attrs, "method") {
+                                Some(m) if m.eq_ignore_ascii_case("post") => FormMethod::Post,
+                                _ => FormMethod::Get,
+                            },
+                            enctype: match attr(attrs, "enctype") {
+                                Some(e) if e.eq_ignore_ascii_case("multipart/form-data") => {
+                                    FormEnctype::Multipart
+                                }
+                                _ => FormEnctype::UrlEncoded,
+                            },
+                            target: attr(attrs, "target"),
+                        });
+                        current_form = Some(state.forms.len() - 1);
+                    }
+                    "/form" => current_form = None,
+                    "input" => {
+                        let value = attr(attrs, "value").unwrap_or_default();
+                        let (kind, width) = match attr(attrs, "type").unwrap_or_default().as_str() {
+                            "password" => (FormControlKind::Password, 200.0),
+                            "hidden" => (FormControlKind::Hidden, 0.0),
+                            "submit" => (FormControlKind::Button { submit: true }, 100.0),
+                            "button" | "reset" => {
+                                (FormControlKind::Button { submit: false }, 100.0)
+                            }
+                            "file" => {
+                                let accept = attr(attrs, "accept")
+                                    .unwrap_or_default()
+                                    .split(',')
+                                    .map(|a| a.trim().to_ascii_lowercase())
+                                    .filter(|a| !a.is_empty())
+                                    .collect();
+                                let kind = FormControlKind::File {
+                                    accept,
+                                    multiple: has_attr(attrs, "multiple"),
+                                    files: Vec::new(),
+                                };
+                                // Pages cannot preset the files, so `value` is ignored
+                                push(&mut state, kind, attr(attrs, "name"), String::new(), 200.0);
+                                continue;
+                            }
+                            _ => (FormControlKind::Text, 200.0),
+                        };
+                        push(&mut state, kind, attr(attrs, "name"), value, width);
+                    }
+                    "textarea" => {
+                        let value = rest.split("</textarea").next().unwrap_or_default();
+                        push(
+                            &mut state,
+                            FormControlKind::TextArea,
+                            attr(attrs, "name"),
+                            value.to_string(),
+                            300.0,
+                        );
+                    }
+                    "button" => {
+                        let label = rest.split('<').next().unwrap_or_default().trim();
+                        let submit = attr(attrs, "type").is_none_or(|t| t == "submit");
+                        push(
+                            &mut state,
+                            FormControlKind::Button { submit },
+                            attr(attrs, "name"),
+                            label.to_string(),
+                            100.0,
+                        );
+                    }
+                    "select" => {
+                        let kind = FormControlKind::Select {
+                            options: Vec::new(),
+                            selected: 0,
+                        };
+                        push(&mut state, kind, attr(attrs, "name"), String::new(), 150.0);
+                        open_select = Some(state.controls.len() - 1);
+                    }
+                    "option" => {
+                        let Some(idx) = open_select else { continue };
+                        let label = rest.split('<').next().unwrap_or_default().trim();
+                        let value = attr(attrs, "value").unwrap_or_else(|| label.to_string());
+                        let control = &mut state.controls[idx];
+                        if let FormControlKind::Select { options, selected } = &mut control.kind {
+                            if options.is_empty() || has_attr(attrs, "selected") {
+                                *selected = options.len();
+                                control.value = value.clone();
+                            }
+                            options.push((value, label.to_string()));
+                        }
+                    }
+                    "/select" => open_select = None,
+                    _ => {}
+                }
+            }
+        }
+
+        state
+    }
+
+    /// All forms of the document, in source order.
+    pub fn forms(&self) -> &[Form] {
+        &self.forms
+    }
+
+    /// All controls of the document, in source order.
+    pub fn controls(&self) -> &[FormControl] {
+        &self.controls
+    }
+
+    /// Index of the focused control, if any.
+    pub fn focused(&self) -> Option<usize> {
+        self.focused
+    }
+
+    /// Returns the index of the control at `(x, y)` (page coordinates at 100% zoom).
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        self.controls
+            .iter()
+            .position(|c| c.kind != FormControlKind::Hidden && c.contains(x, y))
+    }
+
+    /// Handles a click at `(x, y)` (page coordinates at 100% zoom): focuses the control
+    /// under the pointer and activates buttons and selects.
+    pub fn click(&mut self, x: f32, y: f32) -> FormOutcome {
+        let Some(idx) = self.hit_test(x, y) else {
+            let changed = self.focused.is_some();
+            self.focus(None);
+            return FormOutcome::changed(changed);
+        };
+        self.focus(Some(idx));
+
+        let control = &mut self.controls[idx];
+        match &mut control.kind {
+            FormControlKind::Button { submit: true } => FormOutcome::Submit(idx),
+            FormControlKind::File { .. } => FormOutcome::ChooseFiles(idx),
+            FormControlKind::Select { options, selected } if !options.is_empty() => {
+                *selected = (*selected + 1) % options.len();
+                control.value = options[*selected].0.clone();
+                FormOutcome::Changed
+            }
+            _ => FormOutcome::Changed,
+        }
+    }
+
+    /// Inserts a typed character into the focused control. Returns false when there is no
+    /// focused text field to receive it.
+    pub fn insert_char(&mut self, character: char) -> bool {
+        !character.is_control() && self.insert_text(&character.to_string())
+    }
+
+    /// Moves the keyboard focus to the control at `idx`. Returns false when there is no such
+    /// control or it cannot take the focus.
+    pub fn focus_control(&mut self, idx: usize) -> bool {
+        match self.controls.get(idx) {
+            Some(control) if control.kind != FormControlKind::Hidden => {
+                self.focus(Some(idx));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the selected byte range of the focused control's value, if any.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        self.selection.clone()
+    }
+
+    /// Selects the whole value of the focused control. Returns false when there is no focused
+    /// text field.
+    pub fn select_all(&mut self) -> bool {
+        let Some(len) = self.editable_value().map(str::len) else {
+            return false;
+        };
+        self.selection = (len > 0).then_some(0..len);
+        true
+    }
+
+    /// Pastes `text` into the focused control, replacing the selection. Single-line fields
+    /// turn line breaks into spaces, and other control characters are dropped. Returns false
+    /// when there is no focused text field to receive it.
+    pub fn paste(&mut self, text: &str) -> bool {
+        let multiline = matches!(
+            self.focused.map(|idx| &self.controls[idx].kind),
+            Some(FormControlKind::TextArea)
+        );
+        let text: String = text
+            .replace("\r\n", "\n")
+            .chars()
+            .filter_map(|c| match c {
+                '\n' | '\r' if multiline => Some('\n'),
+                '\n' | '\r' => Some(' '),
+                '\t' => Some(c),
+                c if c.is_control() => None,
+                c => Some(c),
+            })
+            .collect();
+        self.insert_text(&text)
+    }
+
+    /// Returns the selected text of the focused control, for copying to the clipboard.
+    /// Passwords are never copied.
+    pub fn copy_selection(&self) -> Option<String> {
+        let idx = self.focused?;
+        if self.controls[idx].kind == FormControlKind::Password {
+            return None;
+        }
+        let range = self.selection.clone()?;
+        self.editable_value()?.get(range).map(str::to_string)
+    }
+
+    /// Removes the selected text of the focused control and returns it, for cutting to the
+    /// clipboard. Passwords are never cut.
+    pub fn cut_selection(&mut self) -> Option<String> {
+        let text = self.copy_selection()?;
+        self.insert_text("");
+        Some(text)
+    }
+
+    /// Replaces the selection of the focused text field with `text`, or appends it when
+    /// nothing is selected.
+    fn insert_text(&mut self, text: &str) -> bool {
+        let selection = self.selection.take();
+        match self.focused_control_mut() {
+            Some(control) if control.is_editable() => {
+                match selection.filter(|r| control.value.get(r.clone()).is_some()) {
+                    Some(range) => control.value.replace_range(range, text),
+                    None => control.value.push_str(text),
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Handles a key press for the focused control.
+    ///
+    /// Keys use the names sent in [`EngineEvent::KeyDown`](crate::EngineEvent::KeyDown):
+    /// `Backspace`, `Enter`, `Tab`, `Escape`, `ArrowUp` and `ArrowDown` are understood.
+    pub fn key_down(&mut self, key: &str) -> FormOutcome {
+        let Some(idx) = self.focused else {
+            return FormOutcome::Ignored;
+        };
+
+        match key {
+            "Tab" => {
+                let next = (idx + 1..self.controls.len())
+                    .find(|i| self.controls[*i].kind != FormControlKind::Hidden);
+                self.focus(next);
+                FormOutcome::Changed
+            }
+            "Escape" => {
+                self.focus(None);
+                FormOutcome::Changed
+            }
+            "Backspace" if self.selection.as_ref().is_some_and(|r| !r.is_empty()) => {
+                FormOutcome::changed(self.insert_text(""))
+            }
+            _ => {
+                let control = &mut self.controls[idx];
+                let editable = control.is_editable();
+                match (&mut control.kind, key) {
+                    (FormControlKind::TextArea, "Enter") => {
+                        control.value.push('\n');
+                        FormOutcome::Changed
+                    }
+                    (FormControlKind::Text | FormControlKind::Password, "Enter")
+                    | (FormControlKind::Button { submit: true }, "Enter") => {
+                        FormOutcome::Submit(idx)
+                    }
+                    (FormControlKind::File { .. }, "Enter") => FormOutcome::ChooseFiles(idx),
+                    (_, "Backspace") if editable => {
+                        FormOutcome::changed(control.value.pop().is_some())
+                    }
+                    (FormControlKind::Select { options, selected }, "ArrowUp" | "ArrowDown") => {
+                        let new = if key == "ArrowUp" {
+                            selected.saturating_sub(1)
+                        } else {
+                            (*selected + 1).min(options.len().saturating_sub(1))
+                        };
+                        let changed = new != *selected;
+                        if let Some((value, _)) = options.get(new) {
+                            *selected = new;
+                            control.value = value.clone();
+                        }
+                        FormOutcome::changed(changed)
+                    }
+                    _ => FormOutcome::Ignored,
+                }
+            }
+        }
+    }
+
+    /// Sets the value of the control at `idx`. Returns false when there is no such control.
+    pub fn set_value(&mut self, idx: usize, value: impl Into<String>) -> bool {
+        if self.focused == Some(idx) {
+            self.selection = None;
+        }
+        match self.controls.get_mut(idx) {
+            Some(control) => {
+                control.value = value.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stores the files chosen for the file input at `idx`; an input without `multiple` keeps
+    /// only the first one. Returns false when there is no such file input.
+    pub fn set_files(&mut self, idx: usize, mut files: Vec<PathBuf>) -> bool {
+        let Some(control) = self.controls.get_mut(idx) else {
+            return false;
+        };
+        let FormControlKind::File {
+            multiple,
+            files: chosen,
+            ..
+        } = &mut control.kind
+        else {
+            return false;
+        };
+        if !*multiple {
+            files.truncate(1);
+        }
+        control.value = files
+            .iter()
+            .map(|f| file_name(f))
+            .collect::<Vec<_>>()
+            .join(", ");
+        *chosen = files;
+        true
+    }
+
+    /// Replaces the `range` (bytes) of the value of control `idx` with `replacement`.
+    /// Returns false when there is no such control or the range is not valid for its value.
+    pub fn replace_text(&mut self, idx: usize, range: Range<usize>, replacement: &str) -> bool {
+        let Some(control) = self.controls.get_mut(idx) else {
+            return false;
+        };
+        if !control.is_editable() || control.value.get(range.clone()).is_none() {
+            return false;
+        }
+        control.value.replace_range(range, replacement);
+        if self.focused == Some(idx) {
+            self.selection = None;
+        }
+        true
+    }
+
+    /// Asks `provider` for the misspelled words of control `idx`. Without a provider, any
+    /// previous result is cleared.
+    pub(crate) fn check_spelling(&mut self, idx: usize, provider: Option<&dyn SpellcheckProvider>) {
+        let Some(control) = self.controls.get_mut(idx) else {
+            return;
+        };
+        control.misspellings = match provider {
+            Some(provider) if control.is_spellchecked() => {
+                let words = word_ranges(&control.value);
+                let value = &control.value;
+                provider
+                    .check(value, &words)
+                    .into_iter()
+                    .filter(|r| value.get(r.clone()).is_some())
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Runs [`FormState::check_spelling`] for every control.
+    pub(crate) fn check_all_spelling(&mut self, provider: Option<&dyn SpellcheckProvider>) {
+        for idx in 0..self.controls.len() {
+            self.check_spelling(idx, provider);
+        }
+    }
+
+    /// Builds the submission of the form that `submitter` (a control index) belongs to.
+    ///
+    /// The submitter's own name/value pair is included when it is a named button. Returns
+    /// `None` when the control does not belong to a form, or the action cannot be resolved
+    /// against `base`.
+    pub fn submission(&self, submitter: usize, base: &Url) -> Option<FormSubmission> {
+        let form_idx = self.controls.get(submitter)?.form?;
+        let form = &self.forms[form_idx];
+
+        let entries: Vec<(&str, &FormControl)> = self
+            .controls
+            .iter()
+            .enumerate()
+            .filter(|(_, control)| control.form == Some(form_idx))
+            .filter(|(idx, control)| {
+                !matches!(control.kind, FormControlKind::Button { .. }) || *idx == submitter
+            })
+            .filter_map(|(_, control)| Some((control.name.as_deref()?, control)))
+            .collect();
+
+        let mut url = match form.action.as_deref() {
+            Some(action) if !action.is_empty() => base.join(action).ok()?,
+            _ => base.clone(),
+        };
+        url.set_fragment(None);
+
+        Some(match (form.method, form.enctype) {
+            (FormMethod::Get, _) => {
+                url.set_query(Some(&url_encoded(&entries)));
+                FormSubmission {
+                    method: FormMethod::Get,
+                    url,
+                    body: None,
+                    multipart: None,
+                    target: form.target.clone(),
+                }
+            }
+            (FormMethod::Post, FormEnctype::UrlEncoded) => FormSubmission {
+                method: FormMethod::Post,
+                url,
+                body: Some(url_encoded(&entries).into_bytes()),
+                multipart: None,
+                target: form.target.clone(),
+            },
+            (FormMethod::Post, FormEnctype::Multipart) => FormSubmission {
+                method: FormMethod::Post,
+                url,
+                body: None,
+                multipart: Some(multipart(&entries)),
+                target: form.target.clone(),
+            },
+        })
+    }
+
+    /// Appends the controls to a render list, scaled by `zoom`. Control text uses the family
+    /// of `fonts` and respects its minimum size.
+    pub(crate) fn paint(&self, rl: &mut RenderList, zoom: f32, fonts: &FontSettings) {
+        let border = Color::new(0.3, 0.3, 0.3, 1.0);
+        let focus = Color::new(0.1, 0.4, 0.9, 1.0);
+        let text = Color::new(0.0, 0.0, 0.0, 1.0);
+        let font_size = fonts.clamp(CONTROL_FONT_SIZE) * zoom;
+        let char_width = font_size * CONTROL_CHAR_WIDTH;
+
+        for (idx, control) in self.controls.iter().enumerate() {
+            if control.kind == FormControlKind::Hidden {
+                continue;
+            }
+            let (x, y, w, h) = control.rect;
+            let (x, y, w, h) = (x * zoom, y * zoom, w * zoom, h * zoom);
+            let fill = match control.kind {
+                FormControlKind::Button { .. } => Color::new(0.88, 0.88, 0.88, 1.0),
+                _ => Color::new(1.0, 1.0, 1.0, 1.0),
+            };
+
+            let outline = if self.focused == Some(idx) {
+                focus
+            } else {
+                border
+            };
+            rl.add_command(DisplayItem::Rect {
+                x: x - zoom,
+                y: y - zoom,
+                w: w + 2.0 * zoom,
+                h: h + 2.0 * zoom,
+                color: outline,
+            });
+            rl.add_command(DisplayItem::Rect {
+                x,
+                y,
+                w,
+                h,
+                color: fill,
+            });
+            rl.add_command(DisplayItem::TextRun {
+                x: x + 3.0 * zoom,
+                y: y + h - 3.0 * zoom,
+                text: control.display_text(),
+                size: font_size,
+                font_family: fonts.family.clone(),
+                color: text,
+                max_width: Some(w - 6.0 * zoom),
+            });
+
+            for range in &control.misspellings {
+                let chars_before = control.value[..range.start].chars().count() as f32;
+                let chars = control.value[range.clone()].chars().count() as f32;
+                let start = x + 3.0 * zoom + chars_before * char_width;
+                let end = (start + chars * char_width).min(x + w);
+                paint_squiggle(rl, start, end, y + h - 2.0 * zoom, zoom);
+            }
+        }
+    }
+
+    fn focused_control_mut(&mut self) -> Option<&mut FormControl> {
+        self.controls.get_mut(self.focused?)
+    }
+
+    fn focus(&mut self, idx: Option<usize>) {
+        if self.focused != idx {
+            self.selection = None;
+        }
+        self.focused = idx;
+    }
+
+    /// Value of the focused control, when it is a text field.
+    fn editable_value(&self) -> Option<&str> {
+        let control = self.controls.get(self.focused?)?;
+        control.is_editable().then_some(control.value.as_str())
+    }
+}
+
+/// Result of forwarding input to the form controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormOutcome {
+    /// The input did not affect any control
+    Ignored,
+    /// Focus or a control value changed; the page needs a repaint
+    Changed,
+    /// The control with this index asked to submit its form
+    Submit(usize),
+    /// The file input with this index was activated; the user agent should choose files
+    ChooseFiles(usize),
+}
+
+impl FormOutcome {
+    fn changed(changed: bool) -> FormOutcome {
+        if changed {
+            FormOutcome::Changed
+        } else {
+            FormOutcome::Ignored
+        }
+    }
+}
+
+/// Encodes form entries as `application/x-www-form-urlencoded`. File inputs send the names of
+/// their files.
+fn url_encoded(entries: &[(&str, &FormControl)]) -> String {
+    let mut data = form_urlencoded::Serializer::new(String::new());
+    for (name, control) in entries {
+        match &control.kind {
+            FormControlKind::File { files, .. } if !files.is_empty() => {
+                for file in files {
+                    data.append_pair(name, &file_name(file));
+                }
+            }
+            FormControlKind::File { .. } => {
+                data.append_pair(name, "");
+            }
+            _ => {
+                data.append_pair(name, &control.value);
+            }
+        }
+    }
+    data.finish()
+}
+
+/// Builds the `multipart/form-data` body of form entries.
+fn multipart(entries: &[(&str, &FormControl)]) -> MultipartForm {
+    let mut form = MultipartForm::new();
+    for (name, control) in entries {
+        match &control.kind {
+            FormControlKind::File { files, .. } if !files.is_empty() => {
+                for file in files {
+                    form.add_file(*name, file);
+                }
+            }
+            FormControlKind::File { .. } => form.add_empty_file(*name),
+            _ => form.add_text(*name, &control.value),
+        }
+    }
+    form
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Draws a zig-zag underline from `start` to `end` at `y`.
+fn paint_squiggle(rl: &mut RenderList, start: f32, end: f32, y: f32, zoom: f32) {
+    let color = Color::new(0.9, 0.1, 0.1, 1.0);
+    let step = 2.0 * zoom;
+    let mut x = start;
+    let mut up = false;
+    while x < end {
+        rl.add_command(DisplayItem::Rect {
+            x,
+            y: if up { y - zoom } else { y },
+            w: step.min(end - x),
+            h: zoom,
+            color,
+        });
+        x += step;
+        up = !up;
+    }
+}
+
+/// Returns every tag on the line as `(lowercase name, attribute source, text after the tag)`.
+pub(crate) fn tags(line: &str) -> Vec<(String, &str, &str)> {
+    let mut out = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('<') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('>') else { break };
+        let inner = after[..end].trim_end_matches('/');
+        let name_len = inner
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inner.len());
+        out.push((
+            inner[..name_len].to_ascii_lowercase(),
+            &inner[name_len..],
+            &after[end + 1..],
+        ));
+        rest = &after[end + 1..];
+    }
+    out
+}
+
+/// Returns the value of attribute `name`, accepting double-quoted, single-quoted and unquoted
+/// values.
+pub(crate) fn attr(attrs: &str, name: &str) -> Option<String> {
+    attributes(attrs)
+        .into_iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.to_string())
+}
+
+fn has_attr(attrs: &str, name: &str) -> bool {
+    attributes(attrs)
+        .iter()
+        .any(|(n, _)| n.eq_ignore_ascii_case(name))
+}
+
+fn attributes(mut s: &str) -> Vec<(&str, &str)> {
+    let mut out = Vec::new();
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return out;
+        }
+        let name_end = s
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(s.len());
+        let name = &s[..name_end];
+        s = s[name_end..].trim_start();
+
+        let Some(value) = s.strip_prefix('=') else {
+            out.push((name, ""));
+            continue;
+        };
+        let value = value.trim_start();
+        let (v, remaining) = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => {
+                let body = &value[1..];
+                let end = body.find(q).unwrap_or(body.len());
+                (&body[..end], body.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..end], &value[end..])
+            }
+        };
+        out.push((name, v));
+        s = remaining;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGIN: &str = "<form action=\"/login\" method=post>\n\
+        <input type=text name=user value='bob'> <input type=password name=pass>\n\
+        <input type=hidden name=csrf value=\"t0k en\">\n\
+        <select name=lang><option value=en>English</option><option value=nl selected>Dutch</option></select>\n\
+        <textarea name=note>hi</textarea>\n\
+        <button name=action value=login>Log in</button>\n\
+        </form>";
+
+    #[test]
+    fn parses_controls_and_initial_values() {
+        let forms = FormState::from_html(LOGIN);
+        assert_eq!(forms.forms().len(), 1);
+        assert_eq!(forms.forms()[0].method, FormMethod::Post);
+
+        let controls = forms.controls();
+        assert_eq!(controls.len(), 6);
+        assert_eq!(controls[0].value, "bob");
+        assert_eq!(controls[1].kind, FormControlKind::Password);
+        assert_eq!(controls[2].value, "t0k en");
+        assert_eq!(controls[3].value, "nl");
+        assert_eq!(controls[4].value, "hi");
+        assert_eq!(controls[5].value, "Log in");
+        assert!(controls.iter().all(|c| c.form == Some(0)));
+    }
+
+    #[test]
+    fn typing_and_post_submission() {
+        let mut forms = FormState::from_html(LOGIN);
+        let (x, y, _, _) = forms.controls()[1].rect;
+        assert_eq!(forms.click(x + 1.0, y + 1.0), FormOutcome::Changed);
+        for c in "s3cr&t".chars() {
+            assert!(forms.insert_char(c));
+        }
+        forms.key_down("Backspace");
+
+        let submitter = match forms.key_down("Enter") {
+            FormOutcome::Submit(idx) => idx,
+            other => panic!("expected a submission, got {other:?}"),
+        };
+        let base = Url::parse("https://example.test/account/").unwrap();
+        let sub = forms.submission(submitter, &base).unwrap();
+        assert_eq!(sub.method, FormMethod::Post);
+        assert_eq!(sub.url.as_str(), "https://example.test/login");
+        assert_eq!(
+            String::from_utf8(sub.body.unwrap()).unwrap(),
+            "user=bob&pass=s3cr%26&csrf=t0k+en&lang=nl&note=hi"
+        );
+    }
+
+    #[test]
+    fn get_submission_includes_the_submit_button() {
+        let html = "<form action=search><input name=q value=rust><input type=submit name=go value=Go></form>";
+        let mut forms = FormState::from_html(html);
+        let (x, y, _, _) = forms.controls()[1].rect;
+
+        assert_eq!(forms.click(x + 1.0, y + 1.0), FormOutcome::Submit(1));
+        let base = Url::parse("https://example.test/index.html#top").unwrap();
+        let sub = forms.submission(1, &base).unwrap();
+        assert_eq!(sub.url.as_str(), "https://example.test/search?q=rust&go=Go");
+        assert!(sub.body.is_none());
+    }
+
+    struct Dictionary(&'static [&'static str]);
+
+    impl SpellcheckProvider for Dictionary {
+        fn check(&self, text: &str, words: &[Range<usize>]) -> Vec<Range<usize>> {
+            words
+                .iter()
+                .filter(|r| !self.0.contains(&&text[(*r).clone()]))
+                .cloned()
+                .collect()
+        }
+    }
+
+    #[test]
+    fn spellcheck_skips_passwords_and_replacements_apply() {
+        let mut forms = FormState::from_html(LOGIN);
+        forms.set_value(0, "helo world");
+        forms.set_value(1, "hunter");
+        forms.check_all_spelling(Some(&Dictionary(&["hello", "world", "hi"])));
+
+        assert_eq!(forms.controls()[0].misspellings, vec![0..4]);
+        assert!(forms.controls()[1].misspellings.is_empty());
+
+        assert!(forms.replace_text(0, 0..4, "hello"));
+        assert!(!forms.replace_text(0, 40..42, "x"));
+        assert_eq!(forms.controls()[0].value, "hello world");
+    }
+
+    #[test]
+    fn file_inputs_upload_multipart_or_send_their_names() {
+        let html = "<form action=/upload method=post enctype=multipart/form-data>\n\
+            <input name=title value=trip> <input type=file name=photos multiple value=x>\n\
+            <input type=file name=notes><input type=submit>\n\
+            </form>";
+        let mut forms = FormState::from_html(html);
+        assert_eq!(forms.forms()[0].enctype, FormEnctype::Multipart);
+        let (x, y, _, _) = forms.controls()[1].rect;
+        assert_eq!(forms.click(x + 1.0, y + 1.0), FormOutcome::ChooseFiles(1));
+        assert_eq!(forms.controls()[1].value, "");
+
+        let files = vec![PathBuf::from("/pics/a.png"), PathBuf::from("/pics/b.jpg")];
+        assert!(forms.set_files(1, files));
+        assert!(!forms.set_files(0, Vec::new()));
+        assert_eq!(forms.controls()[1].value, "a.png, b.jpg");
+        assert_eq!(forms.controls()[1].display_text(), "2 files");
+
+        let base = Url::parse("https://example.test/").unwrap();
+        let sub = forms.submission(3, &base).unwrap();
+        assert!(sub.body.is_none());
+        let mut expected = MultipartForm::new();
+        expected.add_text("title", "trip");
+        expected.add_file("photos", "/pics/a.png");
+        expected.add_file("photos", "/pics/b.jpg");
+        expected.add_empty_file("notes");
+        let multipart = sub.multipart.unwrap();
+        assert_eq!(
+            format!("{multipart:?}").replace(multipart.boundary(), ""),
+            format!("{expected:?}").replace(expected.boundary(), "")
+        );
+
+        // Without the multipart encoding only the file names are sent
+        let mut forms = FormState::from_html(&html.replace(" enctype=multipart/form-data", ""));
+        forms.set_files(1, vec![PathBuf::from("/pics/a.png")]);
+        let sub = forms.submission(3, &base).unwrap();
+        assert_eq!(
+            String::from_utf8(sub.body.unwrap()).unwrap(),
+            "title=trip&photos=a.png&notes="
+        );
+    }
+
+    #[test]
+    fn clipboard_edits_replace_the_selection() {
+        let mut forms = FormState::from_html(LOGIN);
+        assert!(!forms.paste("nobody focused"));
+
+        let (x, y, _, _) = forms.controls()[0].rect;
+        forms.click(x + 1.0, y + 1.0);
+        assert_eq!(forms.copy_selection(), None);
+        assert!(forms.paste("by\r\nline"));
+        assert_eq!(forms.controls()[0].value, "bobby line");
+
+        assert!(forms.select_all());
+        assert_eq!(forms.cut_selection().as_deref(), Some("bobby line"));
+        assert_eq!(forms.controls()[0].value, "");
+        assert!(forms.paste("alice"));
+        forms.select_all();
+        forms.insert_char('e');
+        assert_eq!(forms.controls()[0].value, "e");
+
+        // The text area keeps line breaks, and passwords stay off the clipboard
+        forms.key_down("Tab");
+        forms.paste("x");
+        forms.select_all();
+        assert_eq!(forms.copy_selection(), None);
+        forms.key_down("Tab");
+        forms.key_down("Tab");
+        forms.paste("\nthere");
+        assert_eq!(forms.controls()[4].value, "hi\nthere");
+    }
+}
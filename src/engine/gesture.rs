@@ -0,0 +1,145 @@
+//! Gesture synthesis from raw touch input.
+//!
+//! Touch devices report individual touch points. [`GestureRecognizer`] turns them into the
+//! gestures a tab understands: a quick touch without movement is a tap (handled like a
+//! click), dragging a single finger pans the page and moving two fingers apart or together
+//! changes the page zoom.
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Maximum distance in pixels a touch may move and still count as a tap.
+const TAP_SLOP: f32 = 10.0;
+/// Maximum duration of a tap.
+const TAP_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// A gesture recognized from touch input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Gesture {
+    /// A single short touch at the given position
+    Tap { x: f32, y: f32 },
+    /// A single finger moved by the given delta
+    Pan { dx: f32, dy: f32 },
+    /// The distance between two fingers changed by the given factor
+    Pinch { scale: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TouchPoint {
+    start: (f32, f32),
+    pos: (f32, f32),
+    started: Instant,
+}
+
+/// Tracks active touch points and synthesizes [`Gesture`]s from them.
+#[derive(Debug, Default)]
+pub(crate) struct GestureRecognizer {
+    touches: BTreeMap<u64, TouchPoint>,
+    /// False once the current touch sequence can no longer be a tap
+    tap_candidate: bool,
+}
+
+impl GestureRecognizer {
+    pub(crate) fn touch_start(&mut self, id: u64, x: f32, y: f32) -> Option<Gesture> {
+        self.touches.insert(
+            id,
+            TouchPoint {
+                start: (x, y),
+                pos: (x, y),
+                started: Instant::now(),
+            },
+        );
+        self.tap_candidate = self.touches.len() == 1;
+        None
+    }
+
+    pub(crate) fn touch_move(&mut self, id: u64, x: f32, y: f32) -> Option<Gesture> {
+        let before = self.pinch_distance();
+        let point = self.touches.get_mut(&id)?;
+        let (dx, dy) = (x - point.pos.0, y - point.pos.1);
+        point.pos = (x, y);
+        if distance(point.start, point.pos) > TAP_SLOP {
+            self.tap_candidate = false;
+        }
+
+        match self.touches.len() {
+            1 => Some(Gesture::Pan { dx, dy }),
+            _ => {
+                self.tap_candidate = false;
+                let (before, after) = (before?, self.pinch_distance()?);
+                (before > 0.0).then(|| Gesture::Pinch {
+                    scale: after / before,
+                })
+            }
+        }
+    }
+
+    pub(crate) fn touch_end(&mut self, id: u64, x: f32, y: f32) -> Option<Gesture> {
+        let point = self.touches.remove(&id)?;
+        let is_tap = self.tap_candidate
+            && self.touches.is_empty()
+            && point.started.elapsed() <= TAP_TIMEOUT
+            && distance(point.start, (x, y)) <= TAP_SLOP;
+        if self.touches.is_empty() {
+            self.tap_candidate = false;
+        }
+
+        is_tap.then_some(Gesture::Tap { x, y })
+    }
+
+    /// Forgets all touch points, e.g. when the platform cancels the touch sequence.
+    pub(crate) fn cancel(&mut self) {
+        self.touches.clear();
+        self.tap_candidate = false;
+    }
+
+    /// Distance between the first two touch points, if there are at least two.
+    fn pinch_distance(&self) -> Option<f32> {
+        let mut points = self.touches.values();
+        let (a, b) = (points.next()?, points.next()?);
+        Some(distance(a.pos, b.pos))
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_touch_is_a_tap() {
+        let mut r = GestureRecognizer::default();
+        assert_eq!(r.touch_start(1, 10.0, 10.0), None);
+        assert_eq!(
+            r.touch_move(1, 12.0, 11.0),
+            Some(Gesture::Pan { dx: 2.0, dy: 1.0 })
+        );
+        assert_eq!(
+            r.touch_end(1, 12.0, 11.0),
+            Some(Gesture::Tap { x: 12.0, y: 11.0 })
+        );
+    }
+
+    #[test]
+    fn drag_is_not_a_tap() {
+        let mut r = GestureRecognizer::default();
+        r.touch_start(1, 10.0, 10.0);
+        r.touch_move(1, 10.0, 60.0);
+        assert_eq!(r.touch_end(1, 10.0, 60.0), None);
+    }
+
+    #[test]
+    fn two_fingers_pinch() {
+        let mut r = GestureRecognizer::default();
+        r.touch_start(1, 0.0, 0.0);
+        r.touch_start(2, 100.0, 0.0);
+        assert_eq!(
+            r.touch_move(2, 200.0, 0.0),
+            Some(Gesture::Pinch { scale: 2.0 })
+        );
+        assert_eq!(r.touch_end(2, 200.0, 0.0), None);
+        assert_eq!(r.touch_end(1, 0.0, 0.0), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! HAR export of the requests of a navigation.
+//!
+//! Every tab keeps a [`NetworkLog`] of the requests made for its latest navigation, including
+//! retries and responses served from the cache. [`EngineCommand::ExportHar`] turns it into an
+//! [HTTP Archive](http://www.softwareishard.com/blog/har-12-spec/) (HAR 1.2) document, which
+//! performance tools and browser developer tools can import.
+//!
+//! Documents are not streamed and the network layer does not report its phases, so the timing
+//! of an entry is a single `wait` covering the whole request.
+//!
+//! [`EngineCommand::ExportHar`]: crate::EngineCommand::ExportHar
+use crate::engine::cookies::date::unix_to_iso8601;
+use crate::net::Response;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use serde_json::{json, Value};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Identifier of the single page in an exported archive
+const PAGE_ID: &str = "page_1";
+
+/// Requests made for the latest navigation of a tab.
+#[derive(Default)]
+pub(crate) struct NetworkLog {
+    /// When the navigation started
+    started: Option<SystemTime>,
+    /// Requests that finished or failed
+    entries: Vec<Entry>,
+    /// Request that is still running
+    in_flight: Option<Request>,
+}
+
+/// A request as it was sent.
+struct Request {
+    method: &'static str,
+    url: Url,
+    headers: HeaderMap,
+    body_size: usize,
+    started: SystemTime,
+    clock: Instant,
+    from_cache: bool,
+}
+
+/// A request and what came of it.
+struct Entry {
+    request: Request,
+    /// Milliseconds from sending the request until it finished
+    time: f64,
+    outcome: Result<ResponseSummary, String>,
+}
+
+/// The parts of a response that go into the archive.
+struct ResponseSummary {
+    status: u16,
+    status_text: String,
+    headers: HeaderMap,
+    body_size: usize,
+}
+
+impl NetworkLog {
+    /// Forgets the requests of the previous navigation.
+    pub(crate) fn begin_navigation(&mut self) {
+        *self = Self {
+            started: Some(SystemTime::now()),
+            ..Self::default()
+        };
+    }
+
+    /// Records that a request for `url` was sent. `from_cache` marks responses that are
+    /// served from the HTTP cache without touching the network.
+    pub(crate) fn request_started(
+        &mut self,
+        method: &'static str,
+        url: &Url,
+        headers: &HeaderMap,
+        body_size: usize,
+        from_cache: bool,
+    ) {
+        self.started.get_or_insert_with(SystemTime::now);
+        self.in_flight = Some(Request {
+            method,
+            url: url.clone(),
+            headers: headers.clone(),
+            body_size,
+            started: SystemTime::now(),
+            clock: Instant::now(),
+            from_cache,
+        });
+    }
+
+    /// Records the response to the request in flight.
+    pub(crate) fn request_finished(&mut self, response: &Response) {
+        self.finish(Ok(ResponseSummary {
+            status: response.status,
+            status_text: response.status_text.clone(),
+            headers: response.headers.clone(),
+            body_size: response.body.len(),
+        }));
+    }
+
+    /// Records that the request in flight failed with `error`.
+    pub(crate) fn request_failed(&mut self, error: &str) {
+        self.finish(Err(error.to_string()));
+    }
+
+    fn finish(&mut self, outcome: Result<ResponseSummary, String>) {
+        let Some(request) = self.in_flight.take() else {
+            return;
+        };
+        let time = request.clock.elapsed().as_secs_f64() * 1000.0;
+        self.entries.push(Entry {
+            request,
+            time,
+            outcome,
+        });
+    }
+
+    /// Returns the log as a HAR 1.2 document, with `title` as the title of the page.
+    pub(crate) fn to_har(&self, title: &str) -> Value {
+        let started = self.started.unwrap_or_else(SystemTime::now);
+        let on_load = self
+            .entries
+            .last()
+            .map(|e| millis_between(started, e.request.started) + e.time)
+            .unwrap_or(-1.0);
+
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": "gosub-engine",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "pages": [{
+                    "startedDateTime": iso8601(started),
+                    "id": PAGE_ID,
+                    "title": title,
+                    "pageTimings": { "onContentLoad": -1, "onLoad": on_load },
+                }],
+                "entries": self.entries.iter().map(Entry::to_har).collect::<Vec<_>>(),
+            }
+        })
+    }
+}
+
+impl Entry {
+    fn to_har(&self) -> Value {
+        let request = &self.request;
+        let response = match &self.outcome {
+            Ok(response) => json!({
+                "status": response.status,
+                "statusText": response.status_text,
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": headers(&response.headers),
+                "content": {
+                    "size": response.body_size,
+                    "mimeType": response
+                        .headers
+                        .get(CONTENT_TYPE)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default(),
+                },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": if request.from_cache { 0 } else { response.body_size },
+            }),
+            Err(error) => json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+                "_error": error,
+            }),
+        };
+        let cache = if request.from_cache {
+            let entry = json!({
+                "lastAccess": iso8601(request.started),
+                "eTag": "",
+                "hitCount": 1,
+            });
+            json!({ "beforeRequest": entry })
+        } else {
+            json!({})
+        };
+
+        json!({
+            "pageref": PAGE_ID,
+            "startedDateTime": iso8601(request.started),
+            "time": self.time,
+            "request": {
+                "method": request.method,
+                "url": request.url.as_str(),
+                "httpVersion": "HTTP/1.1",
+                "cookies": [],
+                "headers": headers(&request.headers),
+                "queryString": request
+                    .url
+                    .query_pairs()
+                    .map(|(name, value)| json!({ "name": name, "value": value }))
+                    .collect::<Vec<_>>(),
+                "headersSize": -1,
+                "bodySize": request.body_size,
+            },
+            "response": response,
+            "cache": cache,
+            "timings": { "send": 0, "wait": self.time, "receive": 0 },
+        })
+    }
+}
+
+fn headers(headers: &HeaderMap) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            json!({ "name": name.as_str(), "value": String::from_utf8_lossy(value.as_bytes()) })
+        })
+        .collect()
+}
+
+fn iso8601(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    unix_to_iso8601(secs)
+}
+
+fn millis_between(from: SystemTime, to: SystemTime) -> f64 {
+    to.duration_since(from)
+        .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, serve_html, wait_for_load};
+    use crate::{EngineCommand, EngineNotification};
+
+    #[test]
+    fn requests_become_har_entries() {
+        let mut log = NetworkLog::default();
+        log.begin_navigation();
+        let url = Url::parse("https://example.com/search?q=rust").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("dnt", "1".parse().unwrap());
+
+        log.request_started("GET", &url, &headers, 0, false);
+        log.request_failed("connection reset");
+        log.request_started("GET", &url, &headers, 0, false);
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(CONTENT_TYPE, "text/html".parse().unwrap());
+        log.request_finished(&Response {
+            url: url.clone(),
+            status: 200,
+            status_text: "OK".to_string(),
+            headers: response_headers,
+            body: b"<p>hi</p>".to_vec(),
+            redirects: Vec::new(),
+        });
+
+        let har = log.to_har("Search");
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(har["log"]["pages"][0]["title"], "Search");
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["response"]["status"], 0);
+        assert_eq!(entries[0]["response"]["_error"], "connection reset");
+        let entry = &entries[1];
+        assert_eq!(entry["request"]["url"], url.as_str());
+        assert_eq!(entry["request"]["headers"][0]["name"], "dnt");
+        assert_eq!(entry["request"]["queryString"][0]["value"], "rust");
+        assert_eq!(entry["response"]["content"]["mimeType"], "text/html");
+        assert_eq!(entry["response"]["content"]["size"], 9);
+
+        log.begin_navigation();
+        assert!(log.to_har("")["log"]["entries"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn navigations_export_as_har() {
+        let port = serve_html("<p>hi</p>");
+
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/?q=1")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+
+        engine
+            .execute_command(tab_id, EngineCommand::ExportHar)
+            .unwrap();
+        let har = rx
+            .try_iter()
+            .find_map(|n| match n {
+                EngineNotification::HarExported { har, .. } => Some(har),
+                _ => None,
+            })
+            .unwrap();
+        let har: serde_json::Value = serde_json::from_str(&har).unwrap();
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["request"]["method"], "GET");
+        assert_eq!(entries[0]["request"]["url"], url.as_str());
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert_eq!(entries[0]["response"]["content"]["size"], 9);
+    }
+}
@@ -0,0 +1,188 @@
+//! Session history of a tab (the back/forward list).
+use url::Url;
+
+/// A single entry in the back/forward list.
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    url: Url,
+    /// Entries created by same-document navigations share the document of the entry they
+    /// were created from.
+    document: u64,
+}
+
+/// The back/forward list of a tab.
+///
+/// Entries are added when a navigation commits. A traversal (back, forward or reload) only
+/// marks the target entry as pending; it becomes current once the load commits, so a failed
+/// load leaves the history untouched.
+///
+/// Same-document navigations (fragment changes) add entries that belong to the current
+/// document. Traversing between such entries does not need a load, see
+/// [`SessionHistory::pending_is_same_document`].
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SessionHistory {
+    entries: Vec<HistoryEntry>,
+    /// Index of the current entry (meaningless while `entries` is empty)
+    index: usize,
+    /// Entry that the in-flight load will commit to, for traversals
+    pending: Option<usize>,
+    /// Document id handed to the next full load
+    next_document: u64,
+}
+
+impl SessionHistory {
+    /// Records a committed load of `url`.
+    pub(crate) fn commit(&mut self, url: Url) {
+        let document = self.next_document;
+        self.next_document += 1;
+
+        match self.pending.take() {
+            Some(target) if target < self.entries.len() => {
+                // Redirects may change the final URL of a traversal. A traversal that reloads
+                // the document detaches the entry from its siblings.
+                let entry = &mut self.entries[target];
+                if entry.url != url || target == self.index {
+                    *entry = HistoryEntry { url, document };
+                }
+                self.index = target;
+            }
+            _ => self.push(HistoryEntry { url, document }),
+        }
+    }
+
+    /// Records a same-document navigation to `url`, or commits a pending traversal between
+    /// entries of the current document.
+    pub(crate) fn commit_same_document(&mut self, url: Url) {
+        if let Some(target) = self.pending.take().filter(|t| *t < self.entries.len()) {
+            self.index = target;
+            return;
+        }
+
+        let document = match self.entries.get(self.index) {
+            Some(entry) => entry.document,
+            None => {
+                self.next_document += 1;
+                self.next_document - 1
+            }
+        };
+        self.push(HistoryEntry { url, document });
+    }
+
+    /// Returns true when the pending traversal targets another entry of the current document,
+    /// so it can be completed with [`SessionHistory::commit_same_document`] without a load.
+    pub(crate) fn pending_is_same_document(&self) -> bool {
+        match (self.pending, self.entries.get(self.index)) {
+            (Some(target), Some(current)) if target != self.index => self
+                .entries
+                .get(target)
+                .is_some_and(|entry| entry.document == current.document),
+            _ => false,
+        }
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.index + 1);
+        }
+        self.entries.push(entry);
+        self.index = self.entries.len() - 1;
+    }
+
+    /// Starts a traversal `delta` entries away from the current one, returning the URL to load.
+    pub(crate) fn begin_traversal(&mut self, delta: isize) -> Option<Url> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let target = self.index.checked_add_signed(delta)?;
+        let url = self.entries.get(target)?.url.clone();
+        self.pending = Some(target);
+        Some(url)
+    }
+
+    /// Forgets a pending traversal, e.g. because the load failed or a new navigation started.
+    pub(crate) fn cancel_pending(&mut self) {
+        self.pending = None;
+    }
+
+    pub(crate) fn can_go_back(&self) -> bool {
+        !self.entries.is_empty() && self.index > 0
+    }
+
+    pub(crate) fn can_go_forward(&self) -> bool {
+        self.index + 1 < self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn back_forward_and_new_navigation_truncate() {
+        let mut h = SessionHistory::default();
+        assert!(!h.can_go_back());
+        h.commit(u("https://a.test/"));
+        h.commit(u("https://b.test/"));
+        h.commit(u("https://c.test/"));
+        assert!(h.can_go_back() && !h.can_go_forward());
+
+        assert_eq!(h.begin_traversal(-1), Some(u("https://b.test/")));
+        h.commit(u("https://b.test/"));
+        assert!(h.can_go_back() && h.can_go_forward());
+
+        // New navigation drops the forward entries
+        h.commit(u("https://d.test/"));
+        assert!(!h.can_go_forward());
+        assert_eq!(h.begin_traversal(-1), Some(u("https://b.test/")));
+        assert_eq!(h.begin_traversal(-3), None);
+    }
+
+    #[test]
+    fn reload_and_cancelled_traversal_do_not_add_entries() {
+        let mut h = SessionHistory::default();
+        h.commit(u("https://a.test/"));
+        h.commit(u("https://b.test/"));
+
+        assert!(h.begin_traversal(0).is_some());
+        h.commit(u("https://b.test/"));
+        assert!(!h.can_go_forward());
+
+        h.begin_traversal(-1);
+        h.cancel_pending();
+        h.commit(u("https://c.test/"));
+        assert_eq!(h.begin_traversal(-1), Some(u("https://b.test/")));
+    }
+
+    #[test]
+    fn same_document_entries_traverse_without_a_load() {
+        let mut h = SessionHistory::default();
+        h.commit(u("https://a.test/"));
+        h.commit(u("https://b.test/page"));
+        h.commit_same_document(u("https://b.test/page#one"));
+        h.commit_same_document(u("https://b.test/page#two"));
+        assert!(h.can_go_back());
+
+        assert_eq!(h.begin_traversal(-1), Some(u("https://b.test/page#one")));
+        assert!(h.pending_is_same_document());
+        h.commit_same_document(u("https://b.test/page#one"));
+        assert!(h.can_go_forward());
+
+        // Back to the first page needs a full load
+        assert_eq!(h.begin_traversal(-2), Some(u("https://a.test/")));
+        assert!(!h.pending_is_same_document());
+        h.commit(u("https://a.test/"));
+
+        // Forward lands on an entry of a document that is no longer loaded
+        assert_eq!(h.begin_traversal(1), Some(u("https://b.test/page")));
+        assert!(!h.pending_is_same_document());
+
+        // Reloads are never same-document
+        h.cancel_pending();
+        h.begin_traversal(0);
+        assert!(!h.pending_is_same_document());
+    }
+}
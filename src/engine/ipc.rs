@@ -0,0 +1,748 @@
+//! Running a tab in a process of its own.
+//!
+//! In the default process model every tab runs inside the engine process, so a tab that
+//! crashes takes the user agent down with it. With the `multiprocess` feature, a tab can run in
+//! a child process instead: the child runs [`run_tab_worker`] with an engine of its own, and the
+//! user agent drives it through a [`TabProcess`]. A crash of the child ends only that tab;
+//! [`TabProcess::status`] reports it and the user agent can spawn a new worker.
+//!
+//! The processes talk over the child's stdin and stdout. Every message is a
+//! [`HostMessage`] or [`WorkerMessage`], encoded as JSON with a little-endian `u32` length in
+//! front (see [`write_message`] and [`read_message`]). Frames do not go through the pipe: the
+//! worker paints into a file that both processes map into memory, and only announces each
+//! frame with [`WorkerMessage::Frame`]. The user agent copies the frame out and answers with
+//! [`HostMessage::FrameReleased`] before the worker writes the next one.
+//!
+//! Frames only cross the process boundary as CPU pixels, so the worker has to render with a
+//! backend that produces [`ExternalHandle::CpuPixelsOwned`], such as the
+//! [`SoftwareBackend`](crate::render::backends::software::SoftwareBackend) or the Cairo
+//! backend.
+//!
+//! A worker tab is not part of any zone of the user agent. The worker opens a zone of its own,
+//! so cookies and storage are not shared with the user agent's zones, and the worker's engine
+//! follows the [`EngineConfig`] passed to [`run_tab_worker`], sandbox mode included, not the
+//! configuration of the user agent.
+//!
+//! The frame file is created by the user agent, in a directory that only its user can access,
+//! and opened by the worker.
+//!
+//! Only available with the `multiprocess` feature.
+//!
+//! ```no_run
+//! use gosub_engine::ipc::{run_tab_worker_stdio, TabProcess};
+//! use gosub_engine::render::backends::software::SoftwareBackend;
+//! use gosub_engine::render::{DefaultCompositor, Viewport};
+//! use gosub_engine::EngineCommand;
+//! use std::process::Command;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! // The user agent starts itself again as the worker
+//! if std::env::args().any(|arg| arg == "--tab-worker") {
+//!     return run_tab_worker_stdio(Box::new(SoftwareBackend::new()?), None);
+//! }
+//!
+//! let mut worker = Command::new(std::env::current_exe()?);
+//! worker.arg("--tab-worker");
+//! let mut tab = TabProcess::spawn(worker, Viewport::new(0, 0, 800, 600))?;
+//! tab.execute_command(EngineCommand::Navigate("https://example.com".parse()?))?;
+//!
+//! let mut compositor = DefaultCompositor::new(|| {});
+//! loop {
+//!     for notification in tab.poll(&mut compositor) {
+//!         println!("{notification:?}");
+//!     }
+//!     std::thread::sleep(std::time::Duration::from_millis(16));
+//! }
+//! # }
+//! ```
+use crate::render::backend::{CompositorSink, ExternalHandle, PixelFormat, RenderBackend};
+use crate::render::{DefaultCompositor, Viewport};
+use crate::tab::TabId;
+use crate::{EngineCommand, EngineConfig, EngineEvent, EngineNotification, GosubEngine};
+use anyhow::{anyhow, bail, Context, Result};
+use memmap2::{Mmap, MmapMut};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Largest message accepted from the other process
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// Time between two ticks of the worker's engine
+const WORKER_TICK_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Messages from the user agent to a tab worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HostMessage {
+    /// First message of the connection: open the tab.
+    Open {
+        /// Initial viewport of the tab
+        viewport: Viewport,
+        /// File the worker paints its frames into. The user agent creates it.
+        frame_path: PathBuf,
+    },
+    /// An event for the tab
+    Event(EngineEvent),
+    /// A command for the tab
+    Command(EngineCommand),
+    /// The user agent copied the last frame out of the frame file; the next one may be written.
+    FrameReleased,
+    /// Close the tab and exit.
+    Shutdown,
+}
+
+/// Messages from a tab worker to the user agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WorkerMessage {
+    /// The tab was opened.
+    Ready {
+        /// Id of the tab in the worker's engine
+        tab_id: TabId,
+    },
+    /// A notification of the worker's engine
+    Notification(Box<EngineNotification>),
+    /// A frame was written to the start of the frame file.
+    Frame {
+        /// Width of the frame in pixels
+        width: u32,
+        /// Height of the frame in pixels
+        height: u32,
+        /// Bytes per row of pixels
+        stride: u32,
+        /// Layout of the pixels
+        format: PixelFormat,
+    },
+    /// The tab was closed; the worker exits after this message.
+    Closed,
+}
+
+/// Writes `message` to `writer` as one length-prefixed JSON frame and flushes it.
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> Result<()> {
+    let body = serde_json::to_vec(message).context("encoding IPC message")?;
+    if body.len() > MAX_MESSAGE_LEN {
+        bail!("IPC message of {} bytes is too large", body.len());
+    }
+    writer.write_all(&(body.len() as u32).to_le_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads one message written by [`write_message`]. Returns `None` when the other process closed
+/// the connection between two messages.
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        bail!("IPC message of {len} bytes is too large");
+    }
+    let mut body = vec![0u8; len];
+    reader
+        .read_exact(&mut body)
+        .context("connection closed in the middle of a message")?;
+    Ok(Some(
+        serde_json::from_slice(&body).context("decoding IPC message")?,
+    ))
+}
+
+/// What became of the worker process of a [`TabProcess`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerStatus {
+    /// The worker is running
+    Running,
+    /// The tab was closed and the worker exited normally
+    Closed,
+    /// The connection ended without the tab being closed: the worker crashed or was killed.
+    /// Holds the exit status when it is known.
+    Crashed(Option<ExitStatus>),
+}
+
+/// A tab that runs in a worker process.
+pub struct TabProcess {
+    tab_id: TabId,
+    child: Option<Child>,
+    input: BufWriter<Box<dyn Write + Send>>,
+    messages: Receiver<WorkerMessage>,
+    frames: FrameReader,
+    status: WorkerStatus,
+}
+
+impl TabProcess {
+    /// Spawns `command` as a worker that calls [`run_tab_worker_stdio`], and opens a tab of
+    /// `viewport` in it. Stdin and stdout of the command are taken over for the connection.
+    ///
+    /// Returns once the worker reported the tab as opened.
+    ///
+    /// # Errors
+    /// Fails when the command cannot be started, or exits before opening the tab.
+    pub fn spawn(mut command: Command, viewport: Viewport) -> Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("spawning tab worker")?;
+        let stdin: ChildStdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        match Self::connect(stdout, Box::new(stdin), viewport) {
+            Ok(mut process) => {
+                process.child = Some(child);
+                Ok(process)
+            }
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(e)
+            }
+        }
+    }
+
+    /// Opens a tab over an established connection to a worker that runs [`run_tab_worker`].
+    fn connect(
+        output: impl Read + Send + 'static,
+        input: Box<dyn Write + Send>,
+        viewport: Viewport,
+    ) -> Result<Self> {
+        let frames = FrameReader::create()?;
+        let frame_path = frames.path();
+
+        let mut input = BufWriter::new(input);
+        write_message(
+            &mut input,
+            &HostMessage::Open {
+                viewport,
+                frame_path: frame_path.clone(),
+            },
+        )?;
+
+        let mut output = BufReader::new(output);
+        let tab_id = match read_message(&mut output)? {
+            Some(WorkerMessage::Ready { tab_id }) => tab_id,
+            Some(other) => bail!("tab worker answered {other:?} instead of opening the tab"),
+            None => bail!("tab worker exited before opening the tab"),
+        };
+
+        let (tx, messages) = mpsc::channel();
+        thread::Builder::new()
+            .name("gosub-ipc-reader".to_string())
+            .spawn(move || {
+                while let Ok(Some(message)) = read_message::<WorkerMessage>(&mut output) {
+                    if tx.send(message).is_err() {
+                        break;
+                    }
+                }
+            })
+            .context("starting IPC reader thread")?;
+
+        Ok(Self {
+            tab_id,
+            child: None,
+            input,
+            messages,
+            frames,
+            status: WorkerStatus::Running,
+        })
+    }
+
+    /// Returns the id of the tab inside the worker. Notifications of the worker carry this id.
+    pub fn tab_id(&self) -> TabId {
+        self.tab_id
+    }
+
+    /// Sends an event to the tab.
+    pub fn handle_event(&mut self, event: EngineEvent) -> Result<()> {
+        self.send(&HostMessage::Event(event))
+    }
+
+    /// Sends a command to the tab.
+    pub fn execute_command(&mut self, command: EngineCommand) -> Result<()> {
+        self.send(&HostMessage::Command(command))
+    }
+
+    /// Handles everything the worker sent since the last call: new frames are submitted to
+    /// `sink` under [`TabProcess::tab_id`], and the notifications are returned.
+    pub fn poll(&mut self, sink: &mut impl CompositorSink) -> Vec<EngineNotification> {
+        let mut notifications = Vec::new();
+        loop {
+            match self.messages.try_recv() {
+                Ok(WorkerMessage::Notification(notification)) => notifications.push(*notification),
+                Ok(WorkerMessage::Frame {
+                    width,
+                    height,
+                    stride,
+                    format,
+                }) => match self.frames.read(height as usize * stride as usize) {
+                    Ok(pixels) => {
+                        sink.submit_frame(
+                            self.tab_id,
+                            ExternalHandle::CpuPixelsOwned {
+                                width,
+                                height,
+                                stride,
+                                pixels,
+                                format,
+                            },
+                        );
+                        let _ = self.send(&HostMessage::FrameReleased);
+                    }
+                    Err(e) => log::warn!("cannot read frame of tab worker: {e:#}"),
+                },
+                Ok(WorkerMessage::Closed) => self.status = WorkerStatus::Closed,
+                Ok(WorkerMessage::Ready { .. }) => {}
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if self.status == WorkerStatus::Running {
+                        let exit = self
+                            .child
+                            .as_mut()
+                            .and_then(|c| c.try_wait().ok().flatten());
+                        self.status = WorkerStatus::Crashed(exit);
+                    }
+                    break;
+                }
+            }
+        }
+        notifications
+    }
+
+    /// Returns what became of the worker, as of the last [`TabProcess::poll`].
+    pub fn status(&self) -> WorkerStatus {
+        self.status
+    }
+
+    /// Closes the tab and waits for the worker to exit.
+    pub fn shutdown(mut self) -> Result<()> {
+        let _ = self.send(&HostMessage::Shutdown);
+        if let Some(mut child) = self.child.take() {
+            child.wait().context("waiting for tab worker")?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, message: &HostMessage) -> Result<()> {
+        write_message(&mut self.input, message)
+    }
+}
+
+impl Drop for TabProcess {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Runs a tab worker on the stdin and stdout of the current process, until the tab is closed
+/// or the user agent goes away. This is what the command passed to [`TabProcess::spawn`] calls.
+///
+/// Nothing else may write to stdout while the worker runs.
+pub fn run_tab_worker_stdio(
+    backend: Box<dyn RenderBackend>,
+    config: Option<EngineConfig>,
+) -> Result<()> {
+    run_tab_worker(backend, config, std::io::stdin(), std::io::stdout().lock())
+}
+
+/// Runs a tab worker that reads [`HostMessage`]s from `input` and writes [`WorkerMessage`]s to
+/// `output`, until the tab is closed or `input` ends.
+///
+/// The worker creates an engine with `backend` and `config`, opens one zone with a single tab
+/// in it, and ticks the engine about every 16ms.
+pub fn run_tab_worker(
+    backend: Box<dyn RenderBackend>,
+    config: Option<EngineConfig>,
+    input: impl Read + Send + 'static,
+    output: impl Write,
+) -> Result<()> {
+    let mut input = BufReader::new(input);
+    let mut output = BufWriter::new(output);
+
+    let (viewport, frame_path) = match read_message(&mut input)? {
+        Some(HostMessage::Open {
+            viewport,
+            frame_path,
+        }) => (viewport, frame_path),
+        Some(other) => bail!("expected the tab to be opened, got {other:?}"),
+        None => return Ok(()),
+    };
+
+    let mut engine = GosubEngine::new(config, backend);
+    let zone_id = engine.zone_builder().create()?;
+    let notifications = engine.subscribe_notifications();
+    let tab_id = engine.open_tab_in_zone(zone_id, viewport)?;
+    let mut frames = FrameWriter::open(&frame_path)?;
+    write_message(&mut output, &WorkerMessage::Ready { tab_id })?;
+
+    let (tx, messages) = mpsc::channel();
+    thread::Builder::new()
+        .name("gosub-ipc-reader".to_string())
+        .spawn(move || {
+            while let Ok(Some(message)) = read_message::<HostMessage>(&mut input) {
+                if tx.send(message).is_err() {
+                    break;
+                }
+            }
+        })
+        .context("starting IPC reader thread")?;
+
+    let mut compositor = DefaultCompositor::new(|| {});
+    let mut frame_in_flight = false;
+    let mut pending_frame = None;
+    let mut closed = false;
+
+    while !closed {
+        let mut next = messages.recv_timeout(WORKER_TICK_INTERVAL);
+        loop {
+            match next {
+                Ok(HostMessage::Event(event)) => engine.handle_event(tab_id, event)?,
+                Ok(HostMessage::Command(command)) => engine.execute_command(tab_id, command)?,
+                Ok(HostMessage::FrameReleased) => frame_in_flight = false,
+                Ok(HostMessage::Open { .. }) => log::warn!("tab worker was opened twice"),
+                Ok(HostMessage::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
+                    engine.execute_command(tab_id, EngineCommand::CloseTab)?;
+                    engine.tick(&mut compositor);
+                    closed = true;
+                    break;
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+            next = messages.try_recv().map_err(|e| match e {
+                TryRecvError::Empty => RecvTimeoutError::Timeout,
+                TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+            });
+        }
+
+        if !closed {
+            engine.tick(&mut compositor);
+            if let Some(frame) = compositor.frames.remove(&tab_id) {
+                pending_frame = Some(frame);
+            }
+        }
+
+        while let Ok(notification) = notifications.try_recv() {
+            if let EngineNotification::TabClosed {
+                tab_id: closed_id, ..
+            } = &notification
+            {
+                closed |= *closed_id == tab_id;
+            }
+            write_message(
+                &mut output,
+                &WorkerMessage::Notification(Box::new(notification)),
+            )?;
+        }
+
+        if !frame_in_flight && !closed {
+            if let Some(ExternalHandle::CpuPixelsOwned {
+                width,
+                height,
+                stride,
+                pixels,
+                format,
+            }) = pending_frame.take()
+            {
+                frames.write(&pixels)?;
+                write_message(
+                    &mut output,
+                    &WorkerMessage::Frame {
+                        width,
+                        height,
+                        stride,
+                        format,
+                    },
+                )?;
+                frame_in_flight = true;
+            }
+        }
+    }
+
+    write_message(&mut output, &WorkerMessage::Closed)?;
+    Ok(())
+}
+
+/// Worker side of the frame file.
+struct FrameWriter {
+    file: File,
+    map: Option<MmapMut>,
+}
+
+impl FrameWriter {
+    /// Opens the frame file that the user agent created.
+    fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("opening frame file {}", path.display()))?;
+        Ok(Self { file, map: None })
+    }
+
+    /// Writes `pixels` to the start of the file, growing it when they do not fit.
+    fn write(&mut self, pixels: &[u8]) -> Result<()> {
+        if self.map.as_ref().is_none_or(|map| map.len() < pixels.len()) {
+            self.map = None;
+            self.file.set_len(pixels.len() as u64)?;
+            // SAFETY: the file is only written by this worker, and the user agent only reads
+            // it between a `Frame` message and its `FrameReleased` answer, while the worker
+            // does not touch it.
+            self.map = Some(unsafe { MmapMut::map_mut(&self.file)? });
+        }
+        let map = self.map.as_mut().expect("mapped above");
+        map[..pixels.len()].copy_from_slice(pixels);
+        map.flush_range(0, pixels.len())?;
+        Ok(())
+    }
+}
+
+/// User agent side of the frame file. The file and its directory are removed on drop.
+struct FrameReader {
+    dir: TempDir,
+    map: Option<Mmap>,
+}
+
+impl FrameReader {
+    /// Creates an empty frame file in a new directory that only the current user can access.
+    /// Neither may exist yet, so no other user can slip in a file of their own.
+    fn create() -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("gosub-frames-")
+            .tempdir()
+            .context("creating frame directory")?;
+        let reader = Self { dir, map: None };
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(reader.path())
+            .context("creating frame file")?;
+        Ok(reader)
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.path().join("frames")
+    }
+
+    /// Copies the first `len` bytes of the file, mapping it again when it grew.
+    fn read(&mut self, len: usize) -> Result<Vec<u8>> {
+        if self.map.as_ref().is_none_or(|map| map.len() < len) {
+            let path = self.path();
+            let file = File::open(&path)
+                .with_context(|| format!("opening frame file {}", path.display()))?;
+            // SAFETY: see `FrameWriter::write`; the worker does not write while the frame is
+            // being copied.
+            self.map = Some(unsafe { Mmap::map(&file)? });
+        }
+        let map = self.map.as_ref().expect("mapped above");
+        map.get(..len)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| anyhow!("frame file is smaller than the announced frame"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::backends::software::SoftwareBackend;
+    use std::time::Instant;
+
+    /// Polls `tab` until `done` returns true for the collected notifications, or panics.
+    fn poll_until(
+        tab: &mut TabProcess,
+        compositor: &mut DefaultCompositor,
+        mut done: impl FnMut(&TabProcess, &[EngineNotification], &DefaultCompositor) -> bool,
+    ) -> Vec<EngineNotification> {
+        let started = Instant::now();
+        let mut seen = Vec::new();
+        while started.elapsed() < Duration::from_secs(10) {
+            seen.extend(tab.poll(compositor));
+            if done(tab, &seen, compositor) {
+                return seen;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("tab worker did not get there, saw {seen:?}");
+    }
+
+    #[test]
+    fn messages_survive_the_round_trip() {
+        let mut buf = Vec::new();
+        write_message(
+            &mut buf,
+            &HostMessage::Event(EngineEvent::Scroll { dx: 0.0, dy: 3.0 }),
+        )
+        .unwrap();
+        write_message(&mut buf, &HostMessage::Shutdown).unwrap();
+
+        let mut reader = buf.as_slice();
+        assert!(matches!(
+            read_message(&mut reader).unwrap(),
+            Some(HostMessage::Event(EngineEvent::Scroll { dy, .. })) if dy == 3.0
+        ));
+        assert!(matches!(
+            read_message(&mut reader).unwrap(),
+            Some(HostMessage::Shutdown)
+        ));
+        assert!(read_message::<HostMessage>(&mut reader).unwrap().is_none());
+
+        // A message cut off by a crash is an error, not the end of the connection
+        let mut truncated = &buf[..buf.len() - 2];
+        read_message::<HostMessage>(&mut truncated).unwrap();
+        assert!(read_message::<HostMessage>(&mut truncated).is_err());
+    }
+
+    #[test]
+    fn tabs_in_a_worker_render_through_the_frame_file() {
+        let (host_rx, worker_tx) = std::io::pipe().unwrap();
+        let (worker_rx, host_tx) = std::io::pipe().unwrap();
+        let worker = thread::spawn(move || {
+            run_tab_worker(
+                Box::new(SoftwareBackend::new().unwrap()),
+                None,
+                worker_rx,
+                worker_tx,
+            )
+        });
+
+        let mut tab =
+            TabProcess::connect(host_rx, Box::new(host_tx), Viewport::new(0, 0, 64, 48)).unwrap();
+        tab.execute_command(EngineCommand::LoadHtml {
+            html: "<p>Hello from another process</p>".to_string(),
+            base_url: None,
+        })
+        .unwrap();
+
+        let mut compositor = DefaultCompositor::new(|| {});
+        let tab_id = tab.tab_id();
+        poll_until(&mut tab, &mut compositor, |_, seen, compositor| {
+            compositor.frames.contains_key(&tab_id)
+                && seen
+                    .iter()
+                    .any(|n| matches!(n, EngineNotification::PageLoaded { .. }))
+        });
+        let Some(ExternalHandle::CpuPixelsOwned {
+            width,
+            height,
+            pixels,
+            ..
+        }) = compositor.frames.get(&tab_id)
+        else {
+            panic!("no CPU frame was submitted");
+        };
+        assert_eq!((*width, *height), (64, 48));
+        assert_eq!(pixels.len(), 64 * 48 * 4);
+
+        tab.execute_command(EngineCommand::CloseTab).unwrap();
+        let seen = poll_until(&mut tab, &mut compositor, |tab, _, _| {
+            tab.status() != WorkerStatus::Running
+        });
+        assert!(seen
+            .iter()
+            .any(|n| matches!(n, EngineNotification::TabClosed { .. })));
+        assert_eq!(tab.status(), WorkerStatus::Closed);
+        worker.join().unwrap().unwrap();
+
+        let dir = tab.frames.dir.path().to_path_buf();
+        assert!(tab.frames.path().exists());
+        drop(tab);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn frames_keep_the_pixel_format_of_the_worker() {
+        let (host_rx, worker_tx) = std::io::pipe().unwrap();
+        let (mut worker_rx, host_tx) = std::io::pipe().unwrap();
+        let worker = thread::spawn(move || {
+            let Some(HostMessage::Open { frame_path, .. }) = read_message(&mut worker_rx).unwrap()
+            else {
+                panic!("tab was not opened first");
+            };
+            let mut output = worker_tx;
+            write_message(
+                &mut output,
+                &WorkerMessage::Ready {
+                    tab_id: TabId::new(),
+                },
+            )
+            .unwrap();
+            FrameWriter::open(&frame_path)
+                .unwrap()
+                .write(&[1, 2, 3, 4])
+                .unwrap();
+            write_message(
+                &mut output,
+                &WorkerMessage::Frame {
+                    width: 1,
+                    height: 1,
+                    stride: 4,
+                    format: PixelFormat::PreMulArgb32,
+                },
+            )
+            .unwrap();
+            // Keep the connection open until the frame is released
+            let _: Option<HostMessage> = read_message(&mut worker_rx).unwrap();
+        });
+
+        let mut tab =
+            TabProcess::connect(host_rx, Box::new(host_tx), Viewport::new(0, 0, 1, 1)).unwrap();
+        let mut compositor = DefaultCompositor::new(|| {});
+        let tab_id = tab.tab_id();
+        poll_until(&mut tab, &mut compositor, |_, _, compositor| {
+            compositor.frames.contains_key(&tab_id)
+        });
+        assert!(matches!(
+            compositor.frames.get(&tab_id),
+            Some(ExternalHandle::CpuPixelsOwned {
+                pixels,
+                format: PixelFormat::PreMulArgb32,
+                ..
+            }) if pixels == &[1, 2, 3, 4]
+        ));
+        worker.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn workers_that_die_are_reported_as_crashed() {
+        // Exits before opening the tab
+        let err = TabProcess::spawn(Command::new("true"), Viewport::new(0, 0, 8, 8))
+            .err()
+            .expect("worker did not open a tab");
+        assert!(err.to_string().contains("exited"));
+
+        let (host_rx, worker_tx) = std::io::pipe().unwrap();
+        let (mut worker_rx, host_tx) = std::io::pipe().unwrap();
+        let worker = thread::spawn(move || {
+            // Open the tab, then die without closing it
+            let _: Option<HostMessage> = read_message(&mut worker_rx).unwrap();
+            let mut output = worker_tx;
+            write_message(
+                &mut output,
+                &WorkerMessage::Ready {
+                    tab_id: TabId::new(),
+                },
+            )
+            .unwrap();
+        });
+
+        let mut tab =
+            TabProcess::connect(host_rx, Box::new(host_tx), Viewport::new(0, 0, 8, 8)).unwrap();
+        worker.join().unwrap();
+        let mut compositor = DefaultCompositor::new(|| {});
+        poll_until(&mut tab, &mut compositor, |tab, _, _| {
+            tab.status() != WorkerStatus::Running
+        });
+        assert_eq!(tab.status(), WorkerStatus::Crashed(None));
+    }
+}
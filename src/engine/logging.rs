@@ -0,0 +1,131 @@
+//! Log output of the engine.
+//!
+//! The engine logs through the [`log`] crate, keeping only the records at or above
+//! [`EngineConfig::log_level`](crate::EngineConfig::log_level). Hosts that want the engine's
+//! messages in their own logging UI install a [`LogSink`] with
+//! [`GosubEngine::set_log_sink`](crate::GosubEngine::set_log_sink); records then go to the sink
+//! instead of the `log` logger.
+//!
+//! The level and the sink are global to the process: with several engines in one process, the
+//! one configured last decides.
+//!
+//! ```
+//! use gosub_engine::logging::{LogRecord, LogSink};
+//! use std::sync::{Arc, Mutex};
+//!
+//! let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+//! let engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+//!
+//! let lines = Arc::new(Mutex::new(Vec::new()));
+//! let captured = lines.clone();
+//! let sink: LogSink = Arc::new(move |record: &LogRecord| {
+//!     captured.lock().unwrap().push(format!("{:?}: {}", record.level, record.message));
+//! });
+//! engine.set_log_sink(Some(sink));
+//! ```
+use crate::config::LogLevel;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// A message logged by the engine, as passed to a [`LogSink`].
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// Severity of the message
+    pub level: LogLevel,
+    /// Module that logged the message, such as `gosub_engine::engine::tab`
+    pub target: &'static str,
+    /// The message itself
+    pub message: String,
+}
+
+/// Receives the log records of the engine. It is called on the thread that logged, which can be
+/// a worker thread, so it should return quickly.
+pub type LogSink = Arc<dyn Fn(&LogRecord) + Send + Sync>;
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static SINK: RwLock<Option<LogSink>> = RwLock::new(None);
+
+/// Sets the least severe level that is still logged.
+pub(crate) fn set_max_level(level: LogLevel) {
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Installs (or with `None`, removes) the sink that receives log records.
+pub(crate) fn set_sink(sink: Option<LogSink>) {
+    if let Ok(mut current) = SINK.write() {
+        *current = sink;
+    }
+}
+
+/// Returns true when records of `level` are logged.
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    level as u8 <= MAX_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Passes a record to the sink, or to the `log` logger when there is none. Use `engine_log!`
+/// instead of calling this directly.
+pub(crate) fn dispatch(level: LogLevel, target: &'static str, args: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    // The sink is cloned so it may replace itself without deadlocking
+    let sink = SINK.read().ok().and_then(|sink| sink.clone());
+    match sink {
+        Some(sink) => sink(&LogRecord {
+            level,
+            target,
+            message: args.to_string(),
+        }),
+        None => log::log!(target: target, level.into(), "{}", args),
+    }
+}
+
+/// Logs a message at a [`LogLevel`], like `engine_log!(Warn, "cannot open {}", path)`.
+macro_rules! engine_log {
+    ($level:ident, $($arg:tt)+) => {
+        $crate::engine::logging::dispatch(
+            $crate::config::LogLevel::$level,
+            module_path!(),
+            format_args!($($arg)+),
+        )
+    };
+}
+pub(crate) use engine_log;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn records_below_the_level_are_dropped_and_the_rest_reach_the_sink() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let captured = seen.clone();
+        // Other tests log concurrently, so only the records of this module count
+        set_sink(Some(Arc::new(move |record: &LogRecord| {
+            if record.target == module_path!() {
+                captured
+                    .lock()
+                    .unwrap()
+                    .push((record.level, record.message.clone()));
+            }
+        })));
+        set_max_level(LogLevel::Warn);
+
+        engine_log!(Info, "not shown");
+        engine_log!(Warn, "shown {}", 1);
+        engine_log!(Error, "shown {}", 2);
+
+        set_sink(None);
+        set_max_level(LogLevel::Info);
+        let seen = seen.lock().unwrap();
+        assert_eq!(
+            *seen,
+            vec![
+                (LogLevel::Warn, "shown 1".to_string()),
+                (LogLevel::Error, "shown 2".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,1344 @@
+//! Notifications sent from the engine to the user agent.
+//!
+//! Where [`EngineEvent`](crate::EngineEvent) and [`EngineCommand`](crate::EngineCommand)
+//! flow from the user agent into the engine, an [`EngineNotification`] flows the other way:
+//! it tells the user agent that something happened that it may want to surface.
+//!
+//! Subscribe through [`GosubEngine::subscribe_notifications`](crate::GosubEngine::subscribe_notifications).
+//! Dropping the receiver unsubscribes.
+//!
+//! Every notification belongs to one of the [`NotificationCategories`]. Subscribers receive
+//! the [default](NotificationCategories::DEFAULT) categories unless they subscribe with
+//! [`GosubEngine::subscribe_categories`](crate::GosubEngine::subscribe_categories); the
+//! frequent render and storage notifications are only sent to subscribers that ask for them.
+//!
+//! Notifications of one tab are published in order. Subscribers that need to relate
+//! notifications of different tabs, or to notice dropped ones, use
+//! [`GosubEngine::subscribe_sequenced`](crate::GosubEngine::subscribe_sequenced) to receive
+//! [`SequencedNotification`]s instead.
+//!
+//! Embedders without a runtime of their own (such as language bindings) can instead call
+//! [`GosubEngine::poll_event`](crate::GosubEngine::poll_event) from their event loop. With
+//! the `serde_events` feature, notifications can be serialized to hand them across the
+//! language boundary.
+use crate::engine::diagnostics::ParseIssue;
+use crate::engine::tick::FrameStats;
+use crate::render::{RenderList, RenderRequest};
+use crate::storage::event::StorageScope;
+use crate::tab::{FileChooserRequestId, PopupRequestId, TabId};
+use crate::zone::{ExtensionId, TabGroupId, ZoneId, ZoneMetadata, ZoneMetrics};
+use crate::{DropEffect, NavigationDisposition};
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::BitOr;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Notifications that the engine sends to the user agent.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum EngineNotification {
+    /// Something was blocked or went wrong in a way that does not stop the engine, for
+    /// instance a navigation that was refused by the [`SandboxMode`](crate::config::SandboxMode).
+    Warning {
+        /// Zone in which the warning occurred
+        zone_id: ZoneId,
+        /// Tab in which the warning occurred, if any
+        tab_id: Option<TabId>,
+        /// Human-readable description
+        message: String,
+    },
+    /// A navigation committed: the main document of `url` was received.
+    PageLoaded {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that loaded the page
+        tab_id: TabId,
+        /// Final URL of the page (after redirects)
+        url: Url,
+    },
+    /// The URL of a tab changed without loading a new document, for instance after
+    /// following a `#fragment` link or moving back/forward between such entries.
+    LocationChanged {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab whose location changed
+        tab_id: TabId,
+        /// New URL of the tab
+        url: Url,
+    },
+    /// A load of a tab followed a redirect. Sent for every hop, in order, once the load got
+    /// its final response.
+    Redirected {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that is loading
+        tab_id: TabId,
+        /// URL that answered with the redirect
+        from: Url,
+        /// URL the load moved on to
+        to: Url,
+        /// Status code of the redirect, such as 302
+        status: u16,
+        /// Names of the cookies the redirect stored in the tab's cookie jar
+        cookies: Vec<String>,
+    },
+    /// A content script of an extension sent a message to the user agent.
+    ExtensionMessage {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab running the content script
+        tab_id: TabId,
+        /// Extension that sent the message
+        extension_id: ExtensionId,
+        /// Message payload, usually JSON
+        message: String,
+    },
+    /// A request was refused by the zone's [`ContentBlocker`](crate::net::ContentBlocker).
+    RequestBlocked {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that made the request
+        tab_id: TabId,
+        /// URL that was blocked
+        url: Url,
+        /// Filter rule that blocked the request
+        rule: String,
+        /// Number of requests blocked in the tab since its current page was loaded
+        blocked_count: u32,
+    },
+    /// A navigation failed, either in the network layer or because it was refused.
+    LoadFailed {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that tried to load the page
+        tab_id: TabId,
+        /// URL that was requested
+        url: Url,
+        /// Description of the failure; `"timeout"` when the load ran into
+        /// [`EngineConfig::connect_timeout`](crate::EngineConfig::connect_timeout) or
+        /// [`EngineConfig::request_timeout`](crate::EngineConfig::request_timeout)
+        error: String,
+    },
+    /// Typed input passed to
+    /// [`EngineCommand::NavigateInput`](crate::EngineCommand::NavigateInput) could not be
+    /// turned into a URL, so nothing was loaded.
+    NavigationFailed {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that was asked to navigate
+        tab_id: TabId,
+        /// Input as it was given
+        input: String,
+        /// Description of the failure
+        error: String,
+    },
+    /// A navigation was stopped with
+    /// [`EngineCommand::StopLoading`](crate::EngineCommand::StopLoading) before it committed.
+    LoadCancelled {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that stopped loading
+        tab_id: TabId,
+        /// URL that was being loaded
+        url: Url,
+    },
+    /// A tab was opened and is ready for events and commands. Published once per tab, before
+    /// any other notification about it.
+    TabOpened {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that was opened
+        tab_id: TabId,
+    },
+    /// A tab was closed. Published once per tab; nothing about the tab follows it.
+    TabClosed {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that was closed
+        tab_id: TabId,
+    },
+    /// A tab has been busy with a single tick or command for longer than
+    /// [`EngineConfig::tab_watchdog_timeout`](crate::EngineConfig::tab_watchdog_timeout).
+    /// Sent while the tab is still busy, at most once per tick or command. User agents can
+    /// offer to stop the page with [`TabKiller::kill`](crate::TabKiller::kill), after which the
+    /// tab is closed at the next stage of its pipeline.
+    TabUnresponsive {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that is busy
+        tab_id: TabId,
+        /// How long the tab had been busy when it was reported
+        busy_for: Duration,
+    },
+    /// A tab that was reported with [`EngineNotification::TabUnresponsive`] finished what it
+    /// was busy with.
+    TabResponsive {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that responds again
+        tab_id: TabId,
+    },
+    /// A background tab was discarded to save memory. Its document and surfaces were dropped;
+    /// the URL, history and thumbnail are kept, and the page is loaded again when the tab
+    /// becomes active.
+    TabDiscarded {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that was discarded
+        tab_id: TabId,
+        /// Estimated number of bytes freed
+        freed_bytes: u64,
+    },
+    /// A discarded tab started loading its page again.
+    TabRestored {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that was restored
+        tab_id: TabId,
+    },
+    /// The current document of a tab logged a message, such as a parser warning. Only sent for
+    /// tabs that enabled [`EngineCommand::EnableConsoleCapture`](crate::EngineCommand::EnableConsoleCapture).
+    ConsoleMessage {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab of the document
+        tab_id: TabId,
+        /// Severity of the message
+        level: ConsoleLevel,
+        /// The message
+        text: String,
+        /// Document or script the message is about, if known
+        source: Option<Url>,
+        /// Line in `source` the message is about, starting at 1
+        line: Option<u32>,
+    },
+    /// The document that finished loading in a tab has problems the parser recovered from,
+    /// such as an unsupported encoding. User agents can show these as page issues.
+    ParseIssues {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab of the document
+        tab_id: TabId,
+        /// URL of the document
+        url: Url,
+        /// The problems, in the order they appear in the document
+        issues: Vec<ParseIssue>,
+    },
+    /// A page asked to open a new tab, for instance with a form that targets `_blank`. Answer
+    /// with [`EngineCommand::ResolvePopup`](crate::EngineCommand::ResolvePopup) on the opener.
+    PopupRequested {
+        /// Zone of the opener
+        zone_id: ZoneId,
+        /// Tab of the page that asked
+        opener: TabId,
+        /// ID to answer the request with
+        request_id: PopupRequestId,
+        /// URL the new tab would load
+        url: Url,
+        /// Window features asked for, as in the third argument of `window.open`. Empty when
+        /// the page did not ask for any.
+        features: String,
+    },
+    /// The user activated a link or form with a modifier key or the middle button, asking to
+    /// open it elsewhere than in the tab. The user agent can open it itself, or answer with
+    /// [`EngineCommand::ResolvePopup`](crate::EngineCommand::ResolvePopup) to have the engine
+    /// open a tab, which keeps the body of POST forms. Requests that are not answered before
+    /// the tab navigates away are dropped.
+    OpenUrlRequested {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab the link or form is in
+        tab_id: TabId,
+        /// ID to answer the request with
+        request_id: PopupRequestId,
+        /// URL to open
+        url: Url,
+        /// Where the user wants it opened, never
+        /// [`NavigationDisposition::CurrentTab`](crate::NavigationDisposition::CurrentTab)
+        disposition: NavigationDisposition,
+    },
+    /// A tab was opened for an allowed popup request and started loading the page.
+    PopupOpened {
+        /// Zone the tab was opened in
+        zone_id: ZoneId,
+        /// The new tab
+        tab_id: TabId,
+        /// Tab of the page that asked for it
+        opener: TabId,
+        /// The request that was allowed
+        request_id: PopupRequestId,
+    },
+    /// A tab group of a zone was created, or its name, color or tabs changed.
+    TabGroupChanged {
+        /// Zone of the group
+        zone_id: ZoneId,
+        /// The group, see [`Zone::tab_group`](crate::zone::Zone::tab_group)
+        group_id: TabGroupId,
+    },
+    /// A tab group was removed from its zone. Its tabs are no longer grouped.
+    TabGroupRemoved {
+        /// Zone of the group
+        zone_id: ZoneId,
+        /// The removed group
+        group_id: TabGroupId,
+    },
+    /// A zone was created, by the [zone builder](crate::GosubEngine::zone_builder) or as a
+    /// copy of another zone.
+    ZoneCreated {
+        /// The new zone
+        zone_id: ZoneId,
+        /// Title, icon, description and color the zone starts with
+        metadata: ZoneMetadata,
+    },
+    /// The title, icon, description or color of a zone was changed with one of the setters of
+    /// [`Zone`](crate::zone::Zone). The icon and description are read with
+    /// [`GosubEngine::zone_metadata`](crate::GosubEngine::zone_metadata).
+    ZoneMetadataChanged {
+        /// Zone that changed
+        zone_id: ZoneId,
+        /// Title of the zone
+        title: String,
+        /// Tab color of the zone (RGBA)
+        color: [u8; 4],
+    },
+    /// The counters of a zone, published every ten seconds when
+    /// [`EngineConfig::metrics_enabled`](crate::EngineConfig::metrics_enabled) is set.
+    ZoneMetrics {
+        /// Zone the counters are of
+        zone_id: ZoneId,
+        /// The counters
+        metrics: ZoneMetrics,
+    },
+    /// The configuration of a zone was changed while it was running.
+    ZoneConfigChanged {
+        /// Zone that was reconfigured
+        zone_id: ZoneId,
+    },
+    /// A tab asks the user agent to put text on the clipboard, after an
+    /// [`EngineCommand::Copy`](crate::EngineCommand::Copy) or
+    /// [`EngineCommand::Cut`](crate::EngineCommand::Cut). The engine never writes the system
+    /// clipboard itself, so the user agent decides whether to honor the request.
+    ClipboardRequested {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that asks
+        tab_id: TabId,
+        /// Text to put on the clipboard
+        text: String,
+    },
+    /// A file input of the page was activated. The user agent shows a file chooser and answers
+    /// with [`EngineCommand::ResolveFileChooser`](crate::EngineCommand::ResolveFileChooser).
+    FileChooserRequested {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that asks
+        tab_id: TabId,
+        /// ID to answer the request with
+        request_id: FileChooserRequestId,
+        /// Media types and extensions the input accepts, such as `image/*` or `.pdf`. Empty when
+        /// any file will do.
+        accept: Vec<String>,
+        /// Whether more than one file may be chosen
+        multiple: bool,
+    },
+    /// What dropping the payload of a drag onto a tab would do changed, after an
+    /// [`EngineCommand::DragEnter`](crate::EngineCommand::DragEnter) or as the pointer moves
+    /// over content that accepts other drops. Changes back to [`DropEffect::None`] when the
+    /// drag leaves.
+    DropEffectChanged {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that is dragged over
+        tab_id: TabId,
+        /// What a drop would do
+        effect: DropEffect,
+    },
+    /// A tab has a new frame ready to paint. Sent for every frame, so only to subscribers of
+    /// [`NotificationCategories::RENDER`].
+    FrameRendered {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that rendered
+        tab_id: TabId,
+        /// Timing of the frame
+        stats: FrameStats,
+    },
+    /// A tab built a new render list for the host to paint, in zones that use
+    /// [`RenderMode::DisplayList`](crate::render::RenderMode::DisplayList). Sent for every
+    /// frame, so only to subscribers of [`NotificationCategories::RENDER`].
+    SceneReady {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab the scene belongs to
+        tab_id: TabId,
+        /// What to paint, in document coordinates
+        render_list: RenderList,
+        /// Where the document is scrolled to and how it maps onto device pixels
+        request: RenderRequest,
+        /// Epoch of the scene; it only changes when the render list does
+        epoch: u64,
+    },
+    /// The render backend lost its GPU device and now renders on a new one. The frames of
+    /// the zone's tabs move to new textures, which hosts that registered the old ones register
+    /// again. Published once for every zone.
+    BackendReset {
+        /// Zone the notification is published for
+        zone_id: ZoneId,
+    },
+    /// A page changed local or session storage. Only sent to subscribers of
+    /// [`NotificationCategories::STORAGE`].
+    StorageChanged {
+        /// Zone of the storage
+        zone_id: ZoneId,
+        /// Tab whose page made the change, if known
+        tab_id: Option<TabId>,
+        /// Serialized origin of the changed storage
+        origin: String,
+        /// Serialized top-level origin the storage is partitioned by; `None` when the storage
+        /// is not partitioned
+        partition: Option<String>,
+        /// Whether local or session storage changed
+        scope: StorageScope,
+        /// Key that changed; `None` when the storage was cleared or several keys changed
+        key: Option<String>,
+    },
+    /// The zone came close to its storage quota and the localStorage of these origins was
+    /// removed, least recently used first. User agents can tell the user that the data of
+    /// these sites is gone. Origins marked with
+    /// [`Zone::set_storage_persistent`](crate::zone::Zone::set_storage_persistent) are never
+    /// evicted.
+    StorageEvicted {
+        /// Zone of the storage
+        zone_id: ZoneId,
+        /// Serialized origins whose data was removed
+        origins: Vec<String>,
+        /// Bytes freed
+        bytes: u64,
+    },
+    /// A zone read the cookies of another zone through
+    /// [`GosubEngine::read_shared_cookies`](crate::GosubEngine::read_shared_cookies), or tried
+    /// to. Published for the zone that owns the cookies, as an audit trail.
+    SharedCookiesRead {
+        /// Zone that owns the cookies
+        zone_id: ZoneId,
+        /// Zone that read them
+        reader: ZoneId,
+        /// URL the cookies were read for
+        url: Url,
+        /// Whether the read was allowed; refused reads return no cookies
+        allowed: bool,
+    },
+    /// The requests of a tab's latest navigation, as asked for with
+    /// [`EngineCommand::ExportHar`](crate::EngineCommand::ExportHar).
+    HarExported {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab the requests were made in
+        tab_id: TabId,
+        /// HAR 1.2 document, as JSON
+        har: String,
+    },
+    /// The current document of a tab, printed as asked for with
+    /// [`EngineCommand::PrintToPdf`](crate::EngineCommand::PrintToPdf).
+    PdfPrinted {
+        /// Zone of the tab
+        zone_id: ZoneId,
+        /// Tab that was printed
+        tab_id: TabId,
+        /// The PDF file
+        pdf: Vec<u8>,
+    },
+    /// The user agent reported that the host went offline or came back online, see
+    /// [`EngineCommand::SetNetworkState`](crate::EngineCommand::SetNetworkState). Published
+    /// once for every zone.
+    NetworkStateChanged {
+        /// Zone the notification is published for
+        zone_id: ZoneId,
+        /// Whether the host is online now
+        online: bool,
+    },
+}
+
+impl EngineNotification {
+    /// Returns the zone the notification is about.
+    pub fn zone_id(&self) -> ZoneId {
+        match self {
+            EngineNotification::Warning { zone_id, .. }
+            | EngineNotification::PageLoaded { zone_id, .. }
+            | EngineNotification::LocationChanged { zone_id, .. }
+            | EngineNotification::Redirected { zone_id, .. }
+            | EngineNotification::ExtensionMessage { zone_id, .. }
+            | EngineNotification::RequestBlocked { zone_id, .. }
+            | EngineNotification::LoadFailed { zone_id, .. }
+            | EngineNotification::NavigationFailed { zone_id, .. }
+            | EngineNotification::LoadCancelled { zone_id, .. }
+            | EngineNotification::TabOpened { zone_id, .. }
+            | EngineNotification::TabClosed { zone_id, .. }
+            | EngineNotification::TabUnresponsive { zone_id, .. }
+            | EngineNotification::TabResponsive { zone_id, .. }
+            | EngineNotification::TabDiscarded { zone_id, .. }
+            | EngineNotification::TabRestored { zone_id, .. }
+            | EngineNotification::ConsoleMessage { zone_id, .. }
+            | EngineNotification::ParseIssues { zone_id, .. }
+            | EngineNotification::PopupRequested { zone_id, .. }
+            | EngineNotification::PopupOpened { zone_id, .. }
+            | EngineNotification::OpenUrlRequested { zone_id, .. }
+            | EngineNotification::TabGroupChanged { zone_id, .. }
+            | EngineNotification::TabGroupRemoved { zone_id, .. }
+            | EngineNotification::ZoneCreated { zone_id, .. }
+            | EngineNotification::ZoneMetadataChanged { zone_id, .. }
+            | EngineNotification::ZoneMetrics { zone_id, .. }
+            | EngineNotification::ZoneConfigChanged { zone_id }
+            | EngineNotification::ClipboardRequested { zone_id, .. }
+            | EngineNotification::FileChooserRequested { zone_id, .. }
+            | EngineNotification::DropEffectChanged { zone_id, .. }
+            | EngineNotification::FrameRendered { zone_id, .. }
+            | EngineNotification::BackendReset { zone_id }
+            | EngineNotification::SceneReady { zone_id, .. }
+            | EngineNotification::StorageChanged { zone_id, .. }
+            | EngineNotification::StorageEvicted { zone_id, .. }
+            | EngineNotification::SharedCookiesRead { zone_id, .. }
+            | EngineNotification::HarExported { zone_id, .. }
+            | EngineNotification::PdfPrinted { zone_id, .. }
+            | EngineNotification::NetworkStateChanged { zone_id, .. } => *zone_id,
+        }
+    }
+
+    /// Returns the tab the notification is about, if any.
+    pub fn tab_id(&self) -> Option<TabId> {
+        match self {
+            EngineNotification::Warning { tab_id, .. } => *tab_id,
+            EngineNotification::PageLoaded { tab_id, .. }
+            | EngineNotification::LocationChanged { tab_id, .. }
+            | EngineNotification::Redirected { tab_id, .. }
+            | EngineNotification::ExtensionMessage { tab_id, .. }
+            | EngineNotification::RequestBlocked { tab_id, .. }
+            | EngineNotification::LoadFailed { tab_id, .. }
+            | EngineNotification::NavigationFailed { tab_id, .. }
+            | EngineNotification::LoadCancelled { tab_id, .. }
+            | EngineNotification::TabOpened { tab_id, .. }
+            | EngineNotification::TabClosed { tab_id, .. }
+            | EngineNotification::TabUnresponsive { tab_id, .. }
+            | EngineNotification::TabResponsive { tab_id, .. }
+            | EngineNotification::TabDiscarded { tab_id, .. }
+            | EngineNotification::TabRestored { tab_id, .. }
+            | EngineNotification::ConsoleMessage { tab_id, .. }
+            | EngineNotification::ParseIssues { tab_id, .. }
+            | EngineNotification::PopupOpened { tab_id, .. }
+            | EngineNotification::OpenUrlRequested { tab_id, .. }
+            | EngineNotification::ClipboardRequested { tab_id, .. }
+            | EngineNotification::FileChooserRequested { tab_id, .. }
+            | EngineNotification::DropEffectChanged { tab_id, .. }
+            | EngineNotification::FrameRendered { tab_id, .. }
+            | EngineNotification::SceneReady { tab_id, .. }
+            | EngineNotification::HarExported { tab_id, .. }
+            | EngineNotification::PdfPrinted { tab_id, .. } => Some(*tab_id),
+            EngineNotification::PopupRequested { opener, .. } => Some(*opener),
+            EngineNotification::StorageChanged { tab_id, .. } => *tab_id,
+            EngineNotification::TabGroupChanged { .. }
+            | EngineNotification::TabGroupRemoved { .. }
+            | EngineNotification::ZoneCreated { .. }
+            | EngineNotification::ZoneMetadataChanged { .. }
+            | EngineNotification::ZoneMetrics { .. }
+            | EngineNotification::ZoneConfigChanged { .. }
+            | EngineNotification::StorageEvicted { .. }
+            | EngineNotification::SharedCookiesRead { .. }
+            | EngineNotification::BackendReset { .. }
+            | EngineNotification::NetworkStateChanged { .. } => None,
+        }
+    }
+
+    /// Returns the category the notification belongs to.
+    pub fn category(&self) -> NotificationCategories {
+        match self {
+            EngineNotification::FrameRendered { .. } | EngineNotification::SceneReady { .. } => {
+                NotificationCategories::RENDER
+            }
+            EngineNotification::PageLoaded { .. }
+            | EngineNotification::LocationChanged { .. }
+            | EngineNotification::Redirected { .. }
+            | EngineNotification::LoadFailed { .. }
+            | EngineNotification::NavigationFailed { .. }
+            | EngineNotification::LoadCancelled { .. }
+            | EngineNotification::ParseIssues { .. }
+            | EngineNotification::PopupRequested { .. }
+            | EngineNotification::OpenUrlRequested { .. } => NotificationCategories::NAVIGATION,
+            EngineNotification::Warning { .. }
+            | EngineNotification::ExtensionMessage { .. }
+            | EngineNotification::ConsoleMessage { .. }
+            | EngineNotification::TabOpened { .. }
+            | EngineNotification::TabClosed { .. }
+            | EngineNotification::TabUnresponsive { .. }
+            | EngineNotification::TabResponsive { .. }
+            | EngineNotification::TabDiscarded { .. }
+            | EngineNotification::TabRestored { .. }
+            | EngineNotification::PopupOpened { .. }
+            | EngineNotification::ClipboardRequested { .. }
+            | EngineNotification::PdfPrinted { .. }
+            | EngineNotification::StorageEvicted { .. }
+            | EngineNotification::SharedCookiesRead { .. }
+            | EngineNotification::FileChooserRequested { .. }
+            | EngineNotification::DropEffectChanged { .. }
+            | EngineNotification::TabGroupChanged { .. }
+            | EngineNotification::TabGroupRemoved { .. }
+            | EngineNotification::ZoneCreated { .. }
+            | EngineNotification::ZoneMetadataChanged { .. }
+            | EngineNotification::ZoneMetrics { .. }
+            | EngineNotification::ZoneConfigChanged { .. }
+            | EngineNotification::BackendReset { .. } => NotificationCategories::LIFECYCLE,
+            EngineNotification::StorageChanged { .. } => NotificationCategories::STORAGE,
+            EngineNotification::RequestBlocked { .. }
+            | EngineNotification::HarExported { .. }
+            | EngineNotification::NetworkStateChanged { .. } => NotificationCategories::NETWORK,
+        }
+    }
+}
+
+/// A set of notification categories, for subscribing to only some notifications.
+///
+/// ```
+/// use gosub_engine::NotificationCategories;
+///
+/// let categories = NotificationCategories::DEFAULT | NotificationCategories::STORAGE;
+/// assert!(categories.contains(NotificationCategories::NAVIGATION));
+/// assert!(!categories.contains(NotificationCategories::RENDER));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct NotificationCategories(u8);
+
+impl NotificationCategories {
+    /// No notifications at all
+    pub const NONE: Self = Self(0);
+    /// A new frame of a tab is ready, sent once per frame
+    pub const RENDER: Self = Self(1);
+    /// Loads, failed loads, URL changes and requests to open URLs elsewhere
+    pub const NAVIGATION: Self = Self(1 << 1);
+    /// Tabs, tab groups and zones coming and going or changing, and what pages and the engine
+    /// report about them
+    pub const LIFECYCLE: Self = Self(1 << 2);
+    /// Changes to local and session storage
+    pub const STORAGE: Self = Self(1 << 3);
+    /// Requests that were blocked
+    pub const NETWORK: Self = Self(1 << 4);
+    /// Every category
+    pub const ALL: Self = Self(0b1_1111);
+    /// What plain subscriptions receive: everything except the frequent render and storage
+    /// notifications
+    pub const DEFAULT: Self = Self(Self::NAVIGATION.0 | Self::LIFECYCLE.0 | Self::NETWORK.0);
+
+    /// Returns true when all categories of `other` are in the set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the set without the categories of `other`.
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl Default for NotificationCategories {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl BitOr for NotificationCategories {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Severity of a [`EngineNotification::ConsoleMessage`], following the methods of the
+/// `console` object of scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub enum ConsoleLevel {
+    /// `console.debug`
+    Debug,
+    /// `console.log`
+    Log,
+    /// `console.info`
+    Info,
+    /// `console.warn`, and warnings of the engine about the document
+    Warn,
+    /// `console.error`, and errors of the engine about the document
+    Error,
+}
+
+/// An [`EngineNotification`] together with the metadata needed to detect drops and to
+/// reconstruct the order in which notifications of different tabs were published.
+///
+/// Received through [`GosubEngine::subscribe_sequenced`](crate::GosubEngine::subscribe_sequenced).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct SequencedNotification {
+    /// Engine-wide sequence number. Starts at 0 and increases by one for every notification,
+    /// so a gap means notifications were missed.
+    pub seq: u64,
+    /// Sequence number among the notifications of the same tab, starting at 0. `None` for
+    /// notifications that are not about a tab.
+    pub tab_seq: Option<u64>,
+    /// Time between the creation of the engine and the publication of the notification
+    pub timestamp: Duration,
+    /// The notification itself
+    pub notification: EngineNotification,
+}
+
+/// A handle for receiving engine notifications.
+pub type NotificationSubscription = mpsc::Receiver<EngineNotification>;
+
+/// A handle for receiving engine notifications with their sequence numbers.
+pub type SequencedSubscription = mpsc::Receiver<SequencedNotification>;
+
+/// Internal bus that fans out EngineNotification to subscribers.
+///
+/// Notifications are numbered under the bus lock, so the sequence numbers reflect the order
+/// in which every subscriber receives them.
+pub(crate) struct NotificationBus {
+    /// Reference point for timestamps
+    epoch: Instant,
+    /// Number of notifications a subscriber can have queued, unlimited when `None`
+    capacity: Option<usize>,
+    state: Mutex<BusState>,
+}
+
+#[derive(Default)]
+struct BusState {
+    subs: Vec<Filtered>,
+    /// Subscribers to the notifications of a single zone
+    zone_subs: HashMap<ZoneId, Vec<Filtered>>,
+    sequenced_subs: Vec<Subscriber<SequencedNotification>>,
+    next_seq: u64,
+    next_tab_seq: HashMap<TabId, u64>,
+}
+
+/// Sending half of a subscription.
+enum Subscriber<T> {
+    Unbounded(mpsc::Sender<T>),
+    Bounded(mpsc::SyncSender<T>),
+}
+
+/// A subscriber to some categories of notifications.
+struct Filtered {
+    categories: NotificationCategories,
+    tx: Subscriber<EngineNotification>,
+}
+
+impl Filtered {
+    /// Queues `ev` when the subscriber wants its category. Returns false once the receiver
+    /// is gone.
+    fn deliver(&self, ev: &EngineNotification) -> bool {
+        !self.categories.contains(ev.category()) || self.tx.deliver(ev.clone())
+    }
+}
+
+impl<T> Subscriber<T> {
+    /// Queues `value`, or drops it when a bounded subscriber is full. Returns false once the
+    /// receiver is gone.
+    fn deliver(&self, value: T) -> bool {
+        match self {
+            Subscriber::Unbounded(tx) => tx.send(value).is_ok(),
+            Subscriber::Bounded(tx) => {
+                !matches!(tx.try_send(value), Err(mpsc::TrySendError::Disconnected(_)))
+            }
+        }
+    }
+}
+
+impl Default for NotificationBus {
+    fn default() -> Self {
+        Self::with_capacity(None)
+    }
+}
+
+impl NotificationBus {
+    /// Creates a bus whose subscribers queue at most `capacity` notifications each. Further
+    /// notifications are dropped for that subscriber until it catches up.
+    pub(crate) fn with_capacity(capacity: Option<usize>) -> Self {
+        Self {
+            epoch: Instant::now(),
+            capacity,
+            state: Mutex::new(BusState::default()),
+        }
+    }
+
+    fn channel<T>(&self) -> (Subscriber<T>, mpsc::Receiver<T>) {
+        match self.capacity {
+            Some(capacity) => {
+                let (tx, rx) = mpsc::sync_channel(capacity);
+                (Subscriber::Bounded(tx), rx)
+            }
+            None => {
+                let (tx, rx) = mpsc::channel();
+                (Subscriber::Unbounded(tx), rx)
+            }
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> NotificationSubscription {
+        self.subscribe_categories(NotificationCategories::DEFAULT)
+    }
+
+    /// Subscribes to the notifications in `categories`. Others are never cloned for the
+    /// subscriber.
+    pub(crate) fn subscribe_categories(
+        &self,
+        categories: NotificationCategories,
+    ) -> NotificationSubscription {
+        let (tx, rx) = self.channel();
+        self.state
+            .lock()
+            .unwrap()
+            .subs
+            .push(Filtered { categories, tx });
+        rx
+    }
+
+    /// Subscribes to the notifications of `zone_id` and its tabs only. Notifications of other
+    /// zones are never cloned for the subscriber.
+    pub(crate) fn subscribe_zone(&self, zone_id: ZoneId) -> NotificationSubscription {
+        self.subscribe_zone_categories(zone_id, NotificationCategories::DEFAULT)
+    }
+
+    /// Subscribes to the notifications in `categories` of `zone_id` and its tabs.
+    pub(crate) fn subscribe_zone_categories(
+        &self,
+        zone_id: ZoneId,
+        categories: NotificationCategories,
+    ) -> NotificationSubscription {
+        let (tx, rx) = self.channel();
+        let mut state = self.state.lock().unwrap();
+        state
+            .zone_subs
+            .entry(zone_id)
+            .or_default()
+            .push(Filtered { categories, tx });
+        rx
+    }
+
+    /// Subscribes to every notification, with sequence numbers. Sequenced subscribers are not
+    /// filtered, so a gap in the numbers always means a dropped notification.
+    pub(crate) fn subscribe_sequenced(&self) -> SequencedSubscription {
+        let (tx, rx) = self.channel();
+        self.state.lock().unwrap().sequenced_subs.push(tx);
+        rx
+    }
+
+    pub(crate) fn publish(&self, ev: EngineNotification) {
+        let mut state = self.state.lock().unwrap();
+
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        let tab_seq = ev.tab_id().map(|tab_id| {
+            let next = state.next_tab_seq.entry(tab_id).or_default();
+            *next += 1;
+            *next - 1
+        });
+        // Nothing follows the closing of a tab, so its counter can go
+        if let EngineNotification::TabClosed { tab_id, .. } = &ev {
+            state.next_tab_seq.remove(tab_id);
+        }
+
+        if !state.sequenced_subs.is_empty() {
+            let sequenced = SequencedNotification {
+                seq,
+                tab_seq,
+                timestamp: self.epoch.elapsed(),
+                notification: ev.clone(),
+            };
+            state
+                .sequenced_subs
+                .retain(|tx| tx.deliver(sequenced.clone()));
+        }
+        let zone_id = ev.zone_id();
+        if let Some(zone_subs) = state.zone_subs.get_mut(&zone_id) {
+            zone_subs.retain(|sub| sub.deliver(&ev));
+            if zone_subs.is_empty() {
+                state.zone_subs.remove(&zone_id);
+            }
+        }
+        state.subs.retain(|sub| sub.deliver(&ev));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SandboxMode;
+    use crate::render::backends::null::NullBackend;
+    use crate::render::{DefaultCompositor, Viewport};
+    use crate::scheduler::Schedule;
+    use crate::{EngineCommand, EngineConfig, EngineError, GosubEngine};
+    use std::time::Duration;
+    use url::Url;
+
+    #[test]
+    fn evicted_origins_are_announced() {
+        let config = EngineConfig::builder()
+            .quota_per_zone_bytes(100)
+            .build()
+            .unwrap();
+        let mut engine = GosubEngine::new(Some(config), Box::new(NullBackend::new().unwrap()));
+        let zone_id = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_notifications();
+
+        let origin = Url::parse("https://big.test/").unwrap().origin();
+        {
+            let zone = engine.get_zone_mut(zone_id).unwrap();
+            let zone = zone.lock().unwrap();
+            let area = zone
+                .local_area(&crate::storage::PartitionKey::None, &origin)
+                .unwrap();
+            area.set_item("blob", &"x".repeat(95)).unwrap();
+        }
+        engine.tick(&mut DefaultCompositor::new(|| {}));
+
+        let evicted: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::StorageEvicted { origins, bytes, .. } => Some((origins, bytes)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(evicted, [(vec!["https://big.test".to_string()], 99)]);
+    }
+
+    #[test]
+    fn storage_changes_name_their_partition() {
+        let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+        let zone_id = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_categories(NotificationCategories::STORAGE);
+
+        let top = Url::parse("https://news.test/").unwrap();
+        let widget = Url::parse("https://widget.test/embed").unwrap();
+        {
+            let zone = engine.get_zone_mut(zone_id).unwrap();
+            let zone = zone.lock().unwrap();
+            let explained = zone.explain_partition(&widget, &top);
+            assert!(explained.third_party && !explained.shared_with_first_party);
+            zone.local_area(&explained.partition, &widget.origin())
+                .unwrap()
+                .set_item("seen", "1")
+                .unwrap();
+        }
+        engine.tick(&mut DefaultCompositor::new(|| {}));
+
+        let changes: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::StorageChanged {
+                    origin, partition, ..
+                } => Some((origin, partition)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            [(
+                "https://widget.test".to_string(),
+                Some("https://news.test".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn first_party_sets_share_partitions_across_related_sites() {
+        let overlapping = crate::storage::FirstPartySets::new()
+            .with_set("corp.test", ["corp-sso.test"])
+            .with_set("other.test", ["corp-sso.test"]);
+        let err = EngineConfig::builder()
+            .first_party_sets(overlapping)
+            .build()
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("corp-sso.test is in more than one set"));
+
+        let sets = crate::storage::FirstPartySets::new().with_set("corp.test", ["corp-sso.test"]);
+        let config = EngineConfig::builder()
+            .first_party_sets(sets)
+            .build()
+            .unwrap();
+        let mut engine = GosubEngine::new(Some(config), Box::new(NullBackend::new().unwrap()));
+        let zone_id = engine.zone_builder().create().unwrap();
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let zone = zone.lock().unwrap();
+
+        let top = Url::parse("https://corp.test/").unwrap();
+        let sso = Url::parse("https://login.corp-sso.test/widget").unwrap();
+        let explained = zone.explain_partition(&sso, &top);
+        assert!(explained.third_party && explained.shared_with_first_party);
+        let unrelated = Url::parse("https://ads.test/pixel").unwrap();
+        assert!(
+            !zone
+                .explain_partition(&unrelated, &top)
+                .shared_with_first_party
+        );
+    }
+
+    #[test]
+    fn shared_cookie_jars_are_read_only_by_designated_zones() {
+        let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+        let sso = engine.zone_builder().create().unwrap();
+        let work = engine.zone_builder().create().unwrap();
+        let other = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_notifications();
+
+        let login = Url::parse("https://login.test/").unwrap();
+        let zone = engine.get_zone_mut(sso).unwrap();
+        {
+            let mut zone = zone.lock().unwrap();
+            let mut headers = http::HeaderMap::new();
+            headers.append(http::header::SET_COOKIE, "session=abc".parse().unwrap());
+            zone.cookie_jar
+                .write()
+                .unwrap()
+                .store_response_cookies(&login, &headers);
+            zone.allow_cookie_reader(work);
+        }
+
+        // Designating a reader is not enough while the jar is not shared
+        assert!(matches!(
+            engine.read_shared_cookies(work, sso, &login),
+            Err(EngineError::CookieJarNotShared)
+        ));
+        zone.lock().unwrap().shared_flags.share_cookiejar = true;
+        assert_eq!(
+            engine
+                .read_shared_cookies(work, sso, &login)
+                .unwrap()
+                .as_deref(),
+            Some("session=abc")
+        );
+        assert!(matches!(
+            engine.read_shared_cookies(other, sso, &login),
+            Err(EngineError::CookieJarNotShared)
+        ));
+
+        let audit: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::SharedCookiesRead {
+                    zone_id,
+                    reader,
+                    url,
+                    allowed,
+                } if zone_id == sso && url == login => Some((reader, allowed)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(audit, [(work, false), (work, true), (other, false)]);
+
+        // Only the user agent gets the shared cookies, the reader's own jar stays empty
+        let work = engine.get_zone_mut(work).unwrap();
+        let work = work.lock().unwrap();
+        assert!(work
+            .cookie_jar
+            .read()
+            .unwrap()
+            .get_request_cookies(&login)
+            .is_none());
+    }
+
+    #[test]
+    fn strict_sandbox_blocks_file_urls_with_a_warning() {
+        let config = EngineConfig::builder()
+            .sandbox_mode(SandboxMode::Strict)
+            .build()
+            .unwrap();
+        let mut engine = GosubEngine::new(Some(config), Box::new(NullBackend::new().unwrap()));
+
+        let zone_id = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_notifications();
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 800, 600))
+            .unwrap();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::TabOpened { tab_id: t, .. }) if t == tab_id
+        ));
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::Navigate(Url::parse("file:///etc/passwd").unwrap()),
+            )
+            .unwrap();
+
+        let compositor = &mut DefaultCompositor::new(|| {});
+        engine.tick(compositor);
+
+        match rx.try_recv().expect("expected a warning") {
+            EngineNotification::Warning {
+                zone_id: z,
+                tab_id: t,
+                message,
+            } => {
+                assert_eq!(z, zone_id);
+                assert_eq!(t, Some(tab_id));
+                assert!(message.contains("file:///etc/passwd"));
+            }
+            other => panic!("unexpected notification: {other:?}"),
+        }
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::LoadFailed { tab_id: t, .. }) if t == tab_id
+        ));
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let tab = tab.lock().unwrap();
+        assert!(tab.is_error);
+        assert!(tab.context.has_failed());
+    }
+
+    #[test]
+    fn zone_metadata_changes_are_published_once() {
+        let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+        let rx = engine.subscribe_notifications();
+        let zone_id = engine.zone_builder().create().unwrap();
+        let created = engine.zone_metadata(zone_id).unwrap();
+
+        {
+            let zone = engine.get_zone_mut(zone_id).unwrap();
+            let mut zone = zone.lock().unwrap();
+            zone.set_title("Work");
+            zone.set_title("Work");
+            zone.set_color([0, 120, 215, 255]);
+            zone.set_description("Office accounts");
+        }
+
+        let published: Vec<_> = rx
+            .try_iter()
+            .filter(|n| n.category() == NotificationCategories::LIFECYCLE)
+            .collect();
+        assert!(matches!(
+            &published[0],
+            EngineNotification::ZoneCreated { zone_id: id, metadata } if *id == zone_id && *metadata == created
+        ));
+        let titles: Vec<_> = published[1..]
+            .iter()
+            .filter_map(|n| match n {
+                EngineNotification::ZoneMetadataChanged { title, color, .. } => {
+                    Some((title.as_str(), *color))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(titles.len(), 3);
+        assert_eq!(titles[2], ("Work", [0, 120, 215, 255]));
+
+        let metadata = engine.zone_metadata(zone_id).unwrap();
+        assert_eq!(metadata.description, "Office accounts");
+        assert_eq!(engine.zones(), [(zone_id, metadata)]);
+        assert!(engine.zone_metadata(ZoneId::new()).is_none());
+    }
+
+    #[test]
+    fn zones_publish_metrics_when_enabled() {
+        let config = EngineConfig::builder()
+            .metrics_enabled(true)
+            .build()
+            .unwrap();
+        let mut engine = GosubEngine::new(Some(config), Box::new(NullBackend::new().unwrap()));
+        let rx = engine.subscribe_notifications();
+        let zone_id = engine.zone_builder().create().unwrap();
+        engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 320, 200))
+            .unwrap();
+        {
+            let zone = engine.get_zone_mut(zone_id).unwrap();
+            let mut zone = zone.lock().unwrap();
+            assert!(
+                zone.set_job_schedule(crate::zone::METRICS_JOB, Schedule::every(Duration::ZERO))
+            );
+            assert!(!zone.set_job_schedule("unknown", Schedule::every(Duration::ZERO)));
+        }
+
+        engine.tick(&mut DefaultCompositor::new(|| {}));
+        let metrics = rx
+            .try_iter()
+            .find_map(|n| match n {
+                EngineNotification::ZoneMetrics {
+                    zone_id: z,
+                    metrics,
+                } if z == zone_id => Some(metrics),
+                _ => None,
+            })
+            .expect("no metrics published");
+        assert_eq!(metrics.tabs, 1);
+        assert_eq!(metrics.cache_entries, 0);
+    }
+
+    #[test]
+    fn poll_event_drains_queued_notifications() {
+        let config = EngineConfig::builder()
+            .sandbox_mode(SandboxMode::Strict)
+            .build()
+            .unwrap();
+        let mut engine = GosubEngine::new(Some(config), Box::new(NullBackend::new().unwrap()));
+        assert!(engine.poll_event(Duration::ZERO).is_none());
+
+        let zone_id = engine.zone_builder().create().unwrap();
+        assert!(matches!(
+            engine.poll_event(Duration::ZERO),
+            Some(EngineNotification::ZoneCreated { zone_id: z, .. }) if z == zone_id
+        ));
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 800, 600))
+            .unwrap();
+        assert!(matches!(
+            engine.poll_event(Duration::ZERO),
+            Some(EngineNotification::TabOpened { tab_id: t, .. }) if t == tab_id
+        ));
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::Navigate(Url::parse("myapp://settings").unwrap()),
+            )
+            .unwrap();
+        engine.tick(&mut DefaultCompositor::new(|| {}));
+
+        assert!(matches!(
+            engine.poll_event(Duration::ZERO),
+            Some(EngineNotification::Warning { .. })
+        ));
+        assert!(matches!(
+            engine.poll_event(Duration::from_millis(10)),
+            Some(EngineNotification::LoadFailed { .. })
+        ));
+        assert!(engine.poll_event(Duration::from_millis(10)).is_none());
+    }
+
+    #[test]
+    fn sequence_numbers_are_engine_wide_and_per_tab() {
+        let bus = NotificationBus::default();
+        let plain = bus.subscribe();
+        let rx = bus.subscribe_sequenced();
+
+        let zone_id = ZoneId::new();
+        let (a, b) = (TabId::new(), TabId::new());
+        let url = Url::parse("https://example.test/").unwrap();
+        let loaded = |tab_id| EngineNotification::PageLoaded {
+            zone_id,
+            tab_id,
+            url: url.clone(),
+        };
+
+        bus.publish(loaded(a));
+        bus.publish(loaded(b));
+        bus.publish(EngineNotification::Warning {
+            zone_id,
+            tab_id: None,
+            message: "zone-wide".into(),
+        });
+        bus.publish(loaded(a));
+
+        let received: Vec<_> = rx.try_iter().collect();
+        let seqs: Vec<_> = received.iter().map(|n| (n.seq, n.tab_seq)).collect();
+        assert_eq!(seqs, [(0, Some(0)), (1, Some(0)), (2, None), (3, Some(1))]);
+        assert!(received
+            .windows(2)
+            .all(|w| w[0].timestamp <= w[1].timestamp));
+        assert_eq!(received[3].notification.tab_id(), Some(a));
+
+        // Plain subscribers still get every notification
+        assert_eq!(plain.try_iter().count(), 4);
+    }
+
+    #[test]
+    fn bounded_subscribers_drop_what_does_not_fit() {
+        let bus = NotificationBus::with_capacity(Some(2));
+        let rx = bus.subscribe_sequenced();
+        let zone_id = ZoneId::new();
+
+        for _ in 0..3 {
+            bus.publish(EngineNotification::ZoneConfigChanged { zone_id });
+        }
+        let seqs: Vec<_> = rx.try_iter().map(|n| n.seq).collect();
+        assert_eq!(seqs, [0, 1]);
+
+        // Once drained there is room again, and the gap in the numbers shows the drop
+        bus.publish(EngineNotification::ZoneConfigChanged { zone_id });
+        assert_eq!(rx.try_recv().unwrap().seq, 3);
+    }
+
+    #[test]
+    fn zone_subscribers_only_receive_their_zone() {
+        let bus = NotificationBus::default();
+        let (work, home) = (ZoneId::new(), ZoneId::new());
+        let work_rx = bus.subscribe_zone(work);
+        let home_rx = bus.subscribe_zone(home);
+        let all = bus.subscribe();
+
+        for zone_id in [work, home, work] {
+            bus.publish(EngineNotification::ZoneConfigChanged { zone_id });
+        }
+
+        let zones = |rx: &NotificationSubscription| -> Vec<ZoneId> {
+            rx.try_iter().map(|n| n.zone_id()).collect()
+        };
+        assert_eq!(zones(&work_rx), [work, work]);
+        assert_eq!(zones(&home_rx), [home]);
+        assert_eq!(zones(&all).len(), 3);
+
+        drop(home_rx);
+        bus.publish(EngineNotification::ZoneConfigChanged { zone_id: home });
+        assert!(!bus.state.lock().unwrap().zone_subs.contains_key(&home));
+    }
+
+    #[test]
+    fn noisy_categories_are_opt_in() {
+        let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+        let zone_id = engine.zone_builder().create().unwrap();
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 800, 600))
+            .unwrap();
+        let plain = engine.subscribe_notifications();
+        let frames = engine
+            .subscribe_zone_categories(zone_id, NotificationCategories::RENDER)
+            .unwrap();
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html: "<p>hi</p>".into(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+        let compositor = &mut DefaultCompositor::new(|| {});
+        let mut received = Vec::new();
+        for _ in 0..200 {
+            engine.tick(compositor);
+            received.extend(frames.try_iter());
+            if !received.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(!received.is_empty());
+        assert!(received.iter().all(|n| matches!(
+            n,
+            EngineNotification::FrameRendered { tab_id: t, stats, .. }
+                if *t == tab_id && stats.frame_id >= 1
+        )));
+        assert_eq!(
+            engine.frame_stats(tab_id).map(|s| s.frame_id),
+            Some(engine.jank_stats(tab_id).unwrap().frames)
+        );
+        assert!(plain
+            .try_iter()
+            .all(|n| n.category() != NotificationCategories::RENDER));
+        assert!(NotificationCategories::ALL
+            .without(NotificationCategories::RENDER)
+            .contains(NotificationCategories::DEFAULT | NotificationCategories::STORAGE));
+    }
+}
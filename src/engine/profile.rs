@@ -0,0 +1,232 @@
+//! Profile directories.
+//!
+//! A [`Profile`] is the directory in which a user agent keeps the data of its zones across
+//! restarts. It has a fixed layout:
+//!
+//! ```text
+//! <profile>/
+//!   profile.json          layout version
+//!   cookies.sqlite        cookies of all zones
+//!   localstorage.sqlite   local storage of all zones
+//!   cache/                for the user agent's disk cache
+//!   sessions/             for the user agent's saved sessions
+//! ```
+//!
+//! Directories are created readable by the owner only. A profile written by an older engine
+//! is upgraded to the current layout when it is opened; one written by a newer engine is
+//! refused rather than damaged.
+//!
+//! ```no_run
+//! use gosub_engine::profile::Profile;
+//! use gosub_engine::GosubEngine;
+//!
+//! let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+//! let mut engine = GosubEngine::new(None, Box::new(backend));
+//!
+//! let profile = Profile::open(".gosub-profile").unwrap();
+//! let zone_id = engine.zone_builder().profile(&profile).create().unwrap();
+//! ```
+use crate::cookies::{CookieStoreHandle, SqliteCookieStore};
+use crate::storage::{InMemorySessionStore, PartitionPolicy, SqliteLocalStore, StorageService};
+use crate::zone::ZoneServices;
+use crate::EngineError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Version of the profile layout written by this engine
+pub const PROFILE_VERSION: u32 = 1;
+
+const VERSION_FILE: &str = "profile.json";
+const COOKIES_FILE: &str = "cookies.sqlite";
+const LOCAL_STORAGE_FILE: &str = "localstorage.sqlite";
+const CACHE_DIR: &str = "cache";
+const SESSIONS_DIR: &str = "sessions";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ProfileFile {
+    version: u32,
+}
+
+/// An open profile directory, with the stores that live in it.
+///
+/// The stores lock their files, so a profile can only be opened once at a time. Hand it to
+/// as many zones as needed with `zone_builder().profile(..)` or [`services`](Self::services);
+/// the stores keep the data of each zone apart.
+pub struct Profile {
+    /// Root directory of the profile
+    root: PathBuf,
+    /// Local storage in `localstorage.sqlite`, session storage in memory
+    storage: Arc<StorageService>,
+    /// Cookies in `cookies.sqlite`
+    cookie_store: CookieStoreHandle,
+}
+
+impl Profile {
+    /// Opens the profile in `dir`, creating or upgrading it as needed.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another instance uses the profile.
+    /// - [`EngineError::StorageError`] when the directory cannot be created, or when the
+    ///   profile was written by a newer engine.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, EngineError> {
+        let root = dir.into();
+        create_private_dir(&root)?;
+
+        let version = read_version(&root)?;
+        if version > PROFILE_VERSION {
+            return Err(EngineError::StorageError(format!(
+                "profile {} has version {version}, this engine supports up to {PROFILE_VERSION}",
+                root.display()
+            )));
+        }
+        for from in version..PROFILE_VERSION {
+            upgrade(&root, from)?;
+        }
+        create_private_dir(&root.join(CACHE_DIR))?;
+        create_private_dir(&root.join(SESSIONS_DIR))?;
+
+        let local = root.join(LOCAL_STORAGE_FILE);
+        let local = local.to_str().ok_or_else(|| {
+            EngineError::StorageError(format!("invalid path: {}", local.display()))
+        })?;
+        let local = SqliteLocalStore::new(local).map_err(|e| {
+            e.downcast::<EngineError>()
+                .unwrap_or_else(|e| EngineError::StorageError(e.to_string()))
+        })?;
+        let storage = Arc::new(StorageService::new(
+            Arc::new(local),
+            Arc::new(InMemorySessionStore::new()),
+        ));
+        let cookie_store = SqliteCookieStore::try_new(root.join(COOKIES_FILE))?;
+
+        if version != PROFILE_VERSION {
+            write_version(&root)?;
+        }
+
+        Ok(Self {
+            root,
+            storage,
+            cookie_store,
+        })
+    }
+
+    /// Returns the root directory of the profile.
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory for the user agent's disk cache.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join(CACHE_DIR)
+    }
+
+    /// Returns the directory for the user agent's saved sessions.
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.root.join(SESSIONS_DIR)
+    }
+
+    /// Returns the storage service of the profile.
+    pub fn storage(&self) -> Arc<StorageService> {
+        self.storage.clone()
+    }
+
+    /// Returns the cookie store of the profile.
+    pub fn cookie_store(&self) -> CookieStoreHandle {
+        self.cookie_store.clone()
+    }
+
+    /// Returns the services of a zone that keeps its data in this profile. Storage is
+    /// partitioned by top-level origin.
+    pub fn services(&self) -> ZoneServices {
+        ZoneServices {
+            storage: Some(self.storage()),
+            cookie_store: Some(self.cookie_store()),
+            partition_policy: Some(PartitionPolicy::TopLevelOrigin),
+            ..Default::default()
+        }
+    }
+}
+
+/// Creates `dir` and its parents. New directories are only accessible by the owner.
+fn create_private_dir(dir: &Path) -> Result<(), EngineError> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(dir)
+        .map_err(|e| EngineError::StorageError(format!("cannot create {}: {e}", dir.display())))
+}
+
+/// Returns the layout version of the profile in `root`. Directories without a version file
+/// are version 0.
+fn read_version(root: &Path) -> Result<u32, EngineError> {
+    let path = root.join(VERSION_FILE);
+    if !path.exists() {
+        return Ok(0);
+    }
+    let contents =
+        std::fs::read_to_string(&path).map_err(|e| EngineError::StorageError(e.to_string()))?;
+    serde_json::from_str::<ProfileFile>(&contents)
+        .map(|f| f.version)
+        .map_err(|e| EngineError::StorageError(format!("{}: {e}", path.display())))
+}
+
+fn write_version(root: &Path) -> Result<(), EngineError> {
+    let contents = serde_json::to_string(&ProfileFile {
+        version: PROFILE_VERSION,
+    })
+    .map_err(|e| EngineError::StorageError(e.to_string()))?;
+    std::fs::write(root.join(VERSION_FILE), contents)
+        .map_err(|e| EngineError::StorageError(e.to_string()))
+}
+
+/// Upgrades the layout of the profile in `root` from version `from` to `from + 1`.
+fn upgrade(_root: &Path, from: u32) -> Result<(), EngineError> {
+    match from {
+        // Unversioned directories are either new, or use the version 1 file names already;
+        // only the subdirectories are new.
+        0 => Ok(()),
+        _ => unreachable!("no upgrade from profile version {from}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_are_created_upgraded_and_versioned() {
+        let dir = std::env::temp_dir().join(format!("gosub-profile-{}", uuid::Uuid::new_v4()));
+
+        let profile = Profile::open(&dir).unwrap();
+        assert!(profile.cache_dir().is_dir());
+        assert!(profile.sessions_dir().is_dir());
+        assert_eq!(read_version(&dir).unwrap(), PROFILE_VERSION);
+        assert!(matches!(
+            Profile::open(&dir),
+            Err(EngineError::ProfileInUse(_))
+        ));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(profile.cache_dir())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+
+        let newer = std::env::temp_dir().join(format!("gosub-profile-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&newer).unwrap();
+        std::fs::write(newer.join(VERSION_FILE), r#"{"version":99}"#).unwrap();
+        assert!(matches!(
+            Profile::open(&newer),
+            Err(EngineError::StorageError(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(&newer);
+    }
+}
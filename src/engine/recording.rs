@@ -0,0 +1,307 @@
+//! Recording and replaying the input of an engine.
+//!
+//! Bugs in rendering or layout are often hard to reproduce from a description. With
+//! [`GosubEngine::start_recording`], the engine keeps a timestamped log of every tab it opens
+//! and every [`EngineEvent`] and [`EngineCommand`] it receives. The resulting [`Recording`] can
+//! be saved as JSON lines, attached to a bug report, and fed into a fresh engine with a
+//! [`Replayer`], at the recorded pace or as fast as possible.
+//!
+//! Tabs are identified by the ids they had while recording; the replayer opens a new tab for
+//! each of them and maps the ids. Zones are not recorded: all tabs are replayed into the zone
+//! passed to the replayer.
+//!
+//! Only available with the `serde_events` feature.
+//!
+//! ```
+//! use gosub_engine::recording::Replayer;
+//! use gosub_engine::render::backends::null::NullBackend;
+//! use gosub_engine::render::{DefaultCompositor, Viewport};
+//! use gosub_engine::{EngineEvent, GosubEngine};
+//!
+//! let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+//! let zone_id = engine.zone_builder().create().unwrap();
+//!
+//! engine.start_recording();
+//! let tab_id = engine.open_tab_in_zone(zone_id, Viewport::new(0, 0, 800, 600)).unwrap();
+//! engine.handle_event(tab_id, EngineEvent::Scroll { dx: 0.0, dy: 120.0 }).unwrap();
+//! let recording = engine.stop_recording().unwrap();
+//! assert_eq!(recording.entries.len(), 2);
+//!
+//! // Replay into a fresh engine, without waiting between entries
+//! let mut fresh = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+//! let zone_id = fresh.zone_builder().create().unwrap();
+//! let mut replayer = Replayer::new(recording).paced(false);
+//! replayer.run(&mut fresh, zone_id, &mut DefaultCompositor::new(|| {})).unwrap();
+//! ```
+use crate::render::backend::CompositorSink;
+use crate::render::Viewport;
+use crate::tab::TabId;
+use crate::zone::ZoneId;
+use crate::{EngineCommand, EngineError, EngineEvent, GosubEngine};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// One recorded input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordedInput {
+    /// A tab was opened
+    OpenTab {
+        /// Id of the tab during recording
+        tab_id: TabId,
+        /// Initial viewport of the tab
+        viewport: Viewport,
+    },
+    /// An event was sent to a tab
+    Event {
+        /// Id of the tab during recording
+        tab_id: TabId,
+        /// The event
+        event: EngineEvent,
+    },
+    /// A command was sent to a tab
+    Command {
+        /// Id of the tab during recording
+        tab_id: TabId,
+        /// The command
+        command: EngineCommand,
+    },
+}
+
+/// A recorded input with the time it was received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEntry {
+    /// Time since the recording started
+    pub at: Duration,
+    /// What was received
+    pub input: RecordedInput,
+}
+
+/// A log of engine input, in the order it was received.
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    /// Recorded entries, oldest first
+    pub entries: Vec<RecordedEntry>,
+}
+
+impl Recording {
+    /// Writes the recording as JSON lines, one entry per line.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<()> {
+        for entry in &self.entries {
+            serde_json::to_writer(&mut writer, entry)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a recording written by [`Recording::write_to`].
+    pub fn read_from(reader: impl BufRead) -> Result<Recording> {
+        let mut entries = Vec::new();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line)
+                .with_context(|| format!("invalid recording entry on line {}", idx + 1))?;
+            entries.push(entry);
+        }
+        Ok(Recording { entries })
+    }
+
+    /// Saves the recording to a file.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("creating recording {}", path.display()))?;
+        self.write_to(std::io::BufWriter::new(file))
+    }
+
+    /// Loads a recording from a file.
+    pub fn load(path: &Path) -> Result<Recording> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("opening recording {}", path.display()))?;
+        Recording::read_from(BufReader::new(file))
+    }
+}
+
+/// Collects input while the engine is recording.
+pub(crate) struct Recorder {
+    started: Instant,
+    recording: Recording,
+}
+
+impl Recorder {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            recording: Recording::default(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, input: RecordedInput) {
+        self.recording.entries.push(RecordedEntry {
+            at: self.started.elapsed(),
+            input,
+        });
+    }
+
+    pub(crate) fn finish(self) -> Recording {
+        self.recording
+    }
+}
+
+/// Feeds a [`Recording`] into an engine.
+pub struct Replayer {
+    recording: Recording,
+    /// Index of the next entry to apply
+    next: usize,
+    /// Wait for each entry's timestamp before applying it
+    paced: bool,
+    /// Recorded tab ids mapped to the tabs opened during replay
+    tabs: HashMap<TabId, TabId>,
+}
+
+impl Replayer {
+    /// Creates a replayer that applies entries at the recorded pace.
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            next: 0,
+            paced: true,
+            tabs: HashMap::new(),
+        }
+    }
+
+    /// Sets whether entries wait for their recorded time (`true`, the default) or are applied
+    /// as fast as possible.
+    pub fn paced(mut self, paced: bool) -> Self {
+        self.paced = paced;
+        self
+    }
+
+    /// Returns true when all entries have been applied.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.entries.len()
+    }
+
+    /// Returns the tab opened during replay for a tab id from the recording.
+    pub fn replayed_tab(&self, recorded: TabId) -> Option<TabId> {
+        self.tabs.get(&recorded).copied()
+    }
+
+    /// Applies the entries that are due `elapsed` after the start of the replay (all remaining
+    /// entries when not paced). Tabs are opened in `zone_id`. Call this from the embedder's
+    /// own loop, between ticks.
+    ///
+    /// # Errors
+    /// Returns the engine error of the first entry that could not be applied. Entries for tabs
+    /// that were never opened in the recording fail with [`EngineError::InvalidTabId`].
+    pub fn apply_due(
+        &mut self,
+        engine: &mut GosubEngine,
+        zone_id: ZoneId,
+        elapsed: Duration,
+    ) -> Result<(), EngineError> {
+        while let Some(entry) = self.recording.entries.get(self.next) {
+            if self.paced && entry.at > elapsed {
+                break;
+            }
+            let input = entry.input.clone();
+            self.next += 1;
+
+            match input {
+                RecordedInput::OpenTab { tab_id, viewport } => {
+                    let replayed = engine.open_tab_in_zone(zone_id, viewport)?;
+                    self.tabs.insert(tab_id, replayed);
+                }
+                RecordedInput::Event { tab_id, event } => {
+                    engine.handle_event(self.map_tab(tab_id)?, event)?;
+                }
+                RecordedInput::Command { tab_id, command } => {
+                    engine.execute_command(self.map_tab(tab_id)?, command)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replays the whole recording into `zone_id`, ticking the engine into `host` in between,
+    /// and returns when every entry has been applied.
+    ///
+    /// # Errors
+    /// See [`Replayer::apply_due`].
+    pub fn run(
+        &mut self,
+        engine: &mut GosubEngine,
+        zone_id: ZoneId,
+        host: &mut impl CompositorSink,
+    ) -> Result<(), EngineError> {
+        let started = Instant::now();
+        loop {
+            self.apply_due(engine, zone_id, started.elapsed())?;
+            engine.tick(host);
+            if self.is_finished() {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(4));
+        }
+    }
+
+    fn map_tab(&self, recorded: TabId) -> Result<TabId, EngineError> {
+        self.replayed_tab(recorded).ok_or(EngineError::InvalidTabId)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::backends::null::NullBackend;
+    use crate::MouseButton;
+
+    #[test]
+    fn recordings_round_trip_and_replay_into_new_tabs() {
+        let mut engine = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+        let zone_id = engine.zone_builder().create().unwrap();
+
+        assert!(engine.stop_recording().is_none());
+        engine.start_recording();
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 640, 480))
+            .unwrap();
+        engine
+            .handle_event(
+                tab_id,
+                EngineEvent::MouseDown {
+                    button: MouseButton::Left,
+                    x: 4.0,
+                    y: 2.0,
+                },
+            )
+            .unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Reload())
+            .unwrap();
+        let recording = engine.stop_recording().unwrap();
+
+        let mut json = Vec::new();
+        recording.write_to(&mut json).unwrap();
+        let recording = Recording::read_from(json.as_slice()).unwrap();
+        assert_eq!(recording.entries.len(), 3);
+        assert!(recording.entries.windows(2).all(|w| w[0].at <= w[1].at));
+
+        let mut fresh = GosubEngine::new(None, Box::new(NullBackend::new().unwrap()));
+        let zone_id = fresh.zone_builder().create().unwrap();
+        let mut replayer = Replayer::new(recording);
+        replayer
+            .apply_due(&mut fresh, zone_id, Duration::MAX)
+            .unwrap();
+        assert!(replayer.is_finished());
+
+        let replayed = replayer.replayed_tab(tab_id).unwrap();
+        assert_ne!(replayed, tab_id);
+        assert!(fresh.get_tab(replayed).is_some());
+    }
+}
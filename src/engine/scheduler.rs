@@ -0,0 +1,217 @@
+//! Periodic housekeeping jobs.
+//!
+//! Subsystems that need to do something every so often (purging expired cookies, evicting
+//! cache entries, flushing data to disk) register a job with a [`Scheduler`] instead of
+//! spawning their own timer task. The scheduler does not run anything by itself: its owner
+//! asks it which jobs are [`due`](Scheduler::due) while ticking and runs them in place. Jobs
+//! therefore never outlive their owner and never run concurrently with the engine's own work.
+//!
+//! Every job has a [`Schedule`]: an interval, plus optional random jitter so that jobs of many
+//! zones that were created together do not all fire on the same tick.
+//!
+//! ```
+//! use gosub_engine::scheduler::{Schedule, Scheduler};
+//! use std::time::{Duration, Instant};
+//!
+//! let start = Instant::now();
+//! let mut scheduler = Scheduler::new();
+//! scheduler.add("flush", Schedule::every(Duration::from_secs(30)), start);
+//!
+//! assert!(scheduler.due(start).is_empty());
+//! assert_eq!(scheduler.due(start + Duration::from_secs(30)), ["flush"]);
+//! ```
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// How often a job runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    /// Time between two runs
+    pub interval: Duration,
+    /// Upper bound of a random delay added to each interval
+    pub jitter: Duration,
+}
+
+impl Schedule {
+    /// Runs every `interval`, without jitter.
+    pub fn every(interval: Duration) -> Self {
+        Self {
+            interval,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Adds up to `jitter` of random delay to each interval.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    fn next_after(&self, now: Instant) -> Instant {
+        let jitter = if self.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            rand::rng().random_range(Duration::ZERO..=self.jitter)
+        };
+        now + self.interval + jitter
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    name: &'static str,
+    schedule: Schedule,
+    next_run: Instant,
+    paused: bool,
+}
+
+/// A set of named periodic jobs, driven by its owner's tick.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler without jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a job that first becomes due one interval after `now`. Adding a job with an
+    /// existing name replaces its schedule.
+    pub fn add(&mut self, name: &'static str, schedule: Schedule, now: Instant) {
+        let next_run = schedule.next_after(now);
+        match self.job_mut(name) {
+            Some(job) => {
+                job.schedule = schedule;
+                job.next_run = next_run;
+            }
+            None => self.jobs.push(Job {
+                name,
+                schedule,
+                next_run,
+                paused: false,
+            }),
+        }
+    }
+
+    /// Removes a job. Returns false when there was no job with that name.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|job| job.name != name);
+        self.jobs.len() != before
+    }
+
+    /// Returns the schedule of a job.
+    pub fn schedule(&self, name: &str) -> Option<Schedule> {
+        self.jobs
+            .iter()
+            .find(|j| j.name == name)
+            .map(|j| j.schedule)
+    }
+
+    /// Changes the schedule of a job; its next run is one new interval after `now`. Returns
+    /// false when there is no such job.
+    pub fn set_schedule(&mut self, name: &str, schedule: Schedule, now: Instant) -> bool {
+        let Some(job) = self.job_mut(name) else {
+            return false;
+        };
+        job.schedule = schedule;
+        job.next_run = schedule.next_after(now);
+        true
+    }
+
+    /// Stops a job from becoming due until it is resumed.
+    pub fn pause(&mut self, name: &str) -> bool {
+        self.job_mut(name).map(|job| job.paused = true).is_some()
+    }
+
+    /// Resumes a paused job. Overdue jobs become due right away.
+    pub fn resume(&mut self, name: &str) -> bool {
+        self.job_mut(name).map(|job| job.paused = false).is_some()
+    }
+
+    /// Makes a job due on the next call to [`Scheduler::due`], for instance before shutting
+    /// down.
+    pub fn trigger(&mut self, name: &str, now: Instant) -> bool {
+        self.job_mut(name).map(|job| job.next_run = now).is_some()
+    }
+
+    /// Restarts the interval of a job, for when its work was just done outside the scheduler.
+    pub fn postpone(&mut self, name: &str, now: Instant) -> bool {
+        let Some(job) = self.job_mut(name) else {
+            return false;
+        };
+        job.next_run = job.schedule.next_after(now);
+        true
+    }
+
+    /// Returns when a job is due next, or `None` when it is paused or does not exist.
+    pub fn next_run(&self, name: &str) -> Option<Instant> {
+        self.jobs
+            .iter()
+            .find(|j| j.name == name && !j.paused)
+            .map(|j| j.next_run)
+    }
+
+    /// Returns the names of the jobs that are due at `now`, in the order they were added,
+    /// and schedules their next run. The caller is expected to run them.
+    ///
+    /// A job that missed several intervals (because the owner did not tick) is returned once.
+    pub fn due(&mut self, now: Instant) -> Vec<&'static str> {
+        let mut due = Vec::new();
+        for job in self.jobs.iter_mut() {
+            if !job.paused && job.next_run <= now {
+                job.next_run = job.schedule.next_after(now);
+                due.push(job.name);
+            }
+        }
+        due
+    }
+
+    fn job_mut(&mut self, name: &str) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|j| j.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_become_due_per_interval_and_can_be_paused() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let mut scheduler = Scheduler::new();
+        scheduler.add("fast", Schedule::every(secs(1)), start);
+        scheduler.add("slow", Schedule::every(secs(10)), start);
+
+        assert_eq!(scheduler.due(start + secs(1)), ["fast"]);
+        assert!(scheduler.due(start + secs(1)).is_empty());
+
+        // Missed intervals collapse into one run
+        assert_eq!(scheduler.due(start + secs(10)), ["fast", "slow"]);
+
+        scheduler.pause("fast");
+        assert!(scheduler.due(start + secs(12)).is_empty());
+        assert_eq!(scheduler.next_run("fast"), None);
+        scheduler.resume("fast");
+        assert_eq!(scheduler.due(start + secs(12)), ["fast"]);
+
+        scheduler.trigger("slow", start + secs(12));
+        assert_eq!(scheduler.due(start + secs(12)), ["slow"]);
+        assert!(scheduler.remove("slow"));
+        assert!(!scheduler.set_schedule("slow", Schedule::every(secs(1)), start));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let start = Instant::now();
+        let schedule = Schedule::every(Duration::from_secs(5)).with_jitter(Duration::from_secs(2));
+        for _ in 0..50 {
+            let next = schedule.next_after(start);
+            assert!(next >= start + Duration::from_secs(5));
+            assert!(next <= start + Duration::from_secs(7));
+        }
+    }
+}
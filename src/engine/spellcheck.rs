@@ -0,0 +1,144 @@
+//! Spellchecking of editable form fields.
+//!
+//! The engine does not ship dictionaries. Instead, the user agent registers a
+//! [`SpellcheckProvider`] on a zone (see [`Zone::set_spellcheck_provider`](crate::zone::Zone::set_spellcheck_provider)),
+//! usually backed by the platform spellchecker or hunspell. Whenever the text of an editable
+//! control changes, the engine splits it into words and asks the provider which of them are
+//! misspelled; those words are underlined with a squiggle.
+//!
+//! Corrections are applied with
+//! [`EngineCommand::ReplaceMisspelling`](crate::EngineCommand::ReplaceMisspelling).
+//!
+//! ```
+//! use gosub_engine::spellcheck::SpellcheckProvider;
+//! use std::ops::Range;
+//!
+//! struct TehChecker;
+//!
+//! impl SpellcheckProvider for TehChecker {
+//!     fn check(&self, text: &str, words: &[Range<usize>]) -> Vec<Range<usize>> {
+//!         words
+//!             .iter()
+//!             .filter(|r| text[(*r).clone()].contains("teh"))
+//!             .cloned()
+//!             .collect()
+//!     }
+//! }
+//! ```
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Checks the spelling of words in editable fields. Implemented by the user agent.
+pub trait SpellcheckProvider: Send + Sync {
+    /// Returns the misspelled entries of `words`, which are byte ranges into `text`.
+    ///
+    /// The full text is passed along so providers can take context into account.
+    fn check(&self, text: &str, words: &[Range<usize>]) -> Vec<Range<usize>>;
+
+    /// Returns replacement suggestions for a misspelled word, best first.
+    fn suggestions(&self, _word: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Shared handle to a spellcheck provider.
+pub type SpellcheckHandle = Arc<dyn SpellcheckProvider>;
+
+/// Splits `text` into words and returns their byte ranges.
+///
+/// A word is a run of alphabetic characters, optionally joined by apostrophes (`don't`).
+/// Anything containing digits is skipped, as are URLs-like tokens such as `a.b`.
+pub fn word_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut skip = false;
+
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let joins_word = (c == '\'' || c == '\u{2019}' || c == '.')
+            && start.is_some()
+            && chars.peek().is_some_and(|(_, n)| n.is_alphabetic());
+
+        if c.is_alphabetic() || joins_word {
+            skip |= c == '.';
+            start.get_or_insert(idx);
+        } else if c.is_alphanumeric() || c == '_' {
+            // Identifiers and numbers are not spellchecked
+            skip = true;
+            start.get_or_insert(idx);
+        } else if let Some(s) = start.take() {
+            if !std::mem::take(&mut skip) {
+                words.push(s..idx);
+            }
+        }
+    }
+    if let Some(s) = start {
+        if !skip {
+            words.push(s..text.len());
+        }
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::engine_with_tab_in;
+    use crate::EngineCommand;
+
+    #[test]
+    fn splits_words_and_skips_identifiers() {
+        let text = "Don't spel x2 gosub.io wrong, ok?";
+        let words: Vec<_> = word_ranges(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(words, ["Don't", "spel", "wrong", "ok"]);
+    }
+
+    struct Dictionary;
+
+    impl SpellcheckProvider for Dictionary {
+        fn check(&self, text: &str, words: &[Range<usize>]) -> Vec<Range<usize>> {
+            let known = ["hello", "world"];
+            words
+                .iter()
+                .filter(|r| !known.contains(&&text[(*r).clone()]))
+                .cloned()
+                .collect()
+        }
+
+        fn suggestions(&self, _word: &str) -> Vec<String> {
+            vec!["hello".into()]
+        }
+    }
+
+    #[test]
+    fn zone_spellchecker_flags_and_replaces_misspellings() {
+        let (mut engine, _, tab_id) =
+            engine_with_tab_in(None, |zone| zone.spellcheck(Arc::new(Dictionary)));
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        {
+            let mut tab = tab.lock().unwrap();
+            tab.context
+                .set_raw_html("<input name=q value='helo world'>");
+            assert_eq!(tab.context.forms().controls()[0].misspellings, vec![0..4]);
+            assert_eq!(tab.context.spelling_suggestions(0, 0..4), ["hello"]);
+        }
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::ReplaceMisspelling {
+                    control: 0,
+                    range: 0..4,
+                    replacement: "hello".into(),
+                },
+            )
+            .unwrap();
+
+        let tab = tab.lock().unwrap();
+        let control = &tab.context.forms().controls()[0];
+        assert_eq!(control.value, "hello world");
+        assert!(control.misspellings.is_empty());
+    }
+}
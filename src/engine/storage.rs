@@ -25,13 +25,17 @@
 //! # Available types
 //!
 //! - [`PartitionKey`] — Identifies a storage partition
+//! - [`PartitionExplanation`] — Which partition a resource's storage lives in on a page.
 //! - [`StorageArea`] — Trait for any storage backend.
 //! - [`LocalStore`], [`SessionStore`] — Type aliases for specific store traits.
+//! - [`OriginRecord`] — When a local store's origin was last used, and whether it is persistent.
 //! - [`StorageService`] — High-level handle for a zone's local+session storage.
+//! - [`OriginUsage`] — How much localStorage an origin uses, and when it was last used.
 //! - [`Subscription`] — Used to observe storage change events.
 //! - [`StorageEvent`] — Describes a change in storage (key added, removed, etc.).
 //! - [`SqliteLocalStore`] — SQLite-backed persistent local storage.
 //! - [`InMemorySessionStore`] — In-memory session storage backend.
+//! - [`ProfileLock`] — Cross-process lock held by persistent stores on their files.
 //!
 //! # Choosing a backend
 //!
@@ -39,6 +43,27 @@
 //! - For ephemeral **SessionStorage**, use [`InMemorySessionStore`].
 //! - For testing or incognito modes, you can use in-memory for both.
 //!
+//! # Opened tabs
+//!
+//! A tab opened from another one starts with a copy of the opener's session storage:
+//! [`StorageService::clone_session_tab`] copies all of it into a duplicated tab, and
+//! [`StorageService::clone_session_for_popup`] copies the area of a single origin and
+//! partition into a tab opened with `window.open`.
+//!
+//! # Quota and eviction
+//!
+//! localStorage would otherwise grow without bounds. Zones keep it within
+//! [`EngineConfig::quota_per_zone_bytes`](crate::EngineConfig::quota_per_zone_bytes): once a
+//! zone comes close to its quota, [`StorageService::enforce_quota`] removes whole origins, the
+//! least recently used first. Origins the embedder marked with
+//! [`StorageService::set_persistent`] are never evicted. Local stores that persist their data
+//! keep both in an [`OriginRecord`] per origin, so eviction stays least recently used across
+//! restarts. The service counts the bytes of every origin as they are written, so checking the
+//! quota does not read the whole store.
+//!
+//! The SQLite-backed stores record the version of their schema in the database and upgrade
+//! older databases when they are opened, after copying them to `<database>.v<version>.bak`.
+//!
 //! # Example: Attaching storage to a zone
 //!
 //! ```rust,no_run
@@ -75,6 +100,11 @@ use std::sync::Arc;
 pub mod area;
 /// Event module, providing storage change events.
 pub mod event;
+/// Lock module, preventing several engine instances from sharing profile files.
+pub mod lock;
+/// Migrations module, versioning the schemas of the SQLite-backed stores.
+#[cfg(any(feature = "sqlite_local_store", feature = "sqlite_cookie_store"))]
+pub(crate) mod migrations;
 /// Service module, providing a unified storage service for zones.
 pub mod service;
 /// Storage types
@@ -85,6 +115,7 @@ pub mod local {
     /// In-memory local storage implementation.
     pub mod in_memory;
     /// SQLite-backed local storage implementation.
+    #[cfg(feature = "sqlite_local_store")]
     pub mod sqlite_store;
 }
 
@@ -103,9 +134,11 @@ pub struct StorageHandles {
     pub session: Arc<dyn StorageArea>,
 }
 
-pub use area::{LocalStore, SessionStore, StorageArea};
+pub use area::{LocalStore, OriginRecord, SessionStore, StorageArea};
 pub use event::StorageEvent;
+#[cfg(feature = "sqlite_local_store")]
 pub use local::sqlite_store::SqliteLocalStore;
-pub use service::{StorageService, Subscription};
+pub use lock::ProfileLock;
+pub use service::{OriginUsage, StorageService, Subscription, EVICTION_THRESHOLD};
 pub use session::in_memory::InMemorySessionStore;
-pub use types::PartitionKey;
+pub use types::{FirstPartySets, PartitionExplanation, PartitionKey, PartitionPolicy};
@@ -2,7 +2,9 @@ use super::types::PartitionKey;
 use crate::tab::TabId;
 use crate::zone::ZoneId;
 use anyhow::Result;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Object-safe key/value storage area (DOM’s Storage).
 pub trait StorageArea: Send + Sync {
@@ -12,6 +14,18 @@ pub trait StorageArea: Send + Sync {
     /// Sets the value for the given key, overwriting any existing value.
     fn set_item(&self, key: &str, value: &str) -> Result<()>;
 
+    /// Sets multiple key/value pairs at once.
+    ///
+    /// Persistent stores should apply the whole batch atomically (e.g. in a single
+    /// transaction), so bulk writes don't pay a per-key commit cost. The default
+    /// implementation simply calls [`set_item`](Self::set_item) for each pair.
+    fn set_many(&self, items: &[(&str, &str)]) -> Result<()> {
+        for (key, value) in items {
+            self.set_item(key, value)?;
+        }
+        Ok(())
+    }
+
     /// Removes the item with the given key.
     fn remove_item(&self, key: &str) -> Result<()>;
 
@@ -25,6 +39,16 @@ pub trait StorageArea: Send + Sync {
     fn keys(&self) -> Vec<String>;
 }
 
+/// What a [`LocalStore`] keeps about the localStorage of an origin besides its items, so that
+/// eviction still picks the least recently used origins after a restart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OriginRecord {
+    /// When a page last read or wrote the storage of the origin, if ever
+    pub last_access: Option<SystemTime>,
+    /// Whether the embedder exempted the origin from eviction
+    pub persistent: bool,
+}
+
 /// Store for localStorage-like areas (shared per (zone, partition, origin)).
 pub trait LocalStore: Send + Sync {
     /// Retrieves a storage area for the given zone, partition, and origin.
@@ -34,6 +58,32 @@ pub trait LocalStore: Send + Sync {
         part: &PartitionKey,
         origin: &url::Origin,
     ) -> Result<Arc<dyn StorageArea>>;
+
+    /// Lists the (partition, origin) pairs that currently hold data for the given zone.
+    ///
+    /// Used to snapshot or copy a zone's localStorage. Stores that cannot enumerate
+    /// their contents return an empty list.
+    fn list_areas(&self, _zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        Ok(Vec::new())
+    }
+
+    /// Returns the [`OriginRecord`]s kept for the origins of the given zone.
+    ///
+    /// Stores that do not persist anything return an empty map, and only what happened since
+    /// the [`StorageService`](crate::storage::StorageService) was created is known.
+    fn origin_records(&self, _zone: ZoneId) -> Result<HashMap<url::Origin, OriginRecord>> {
+        Ok(HashMap::new())
+    }
+
+    /// Keeps the record of an origin of the given zone. Storing the default record removes it.
+    fn set_origin_record(
+        &self,
+        _zone: ZoneId,
+        _origin: &url::Origin,
+        _record: OriginRecord,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Store for sessionStorage-like areas (isolated per (zone, tab, partition, origin)).
@@ -47,6 +97,14 @@ pub trait SessionStore: Send + Sync {
         origin: &url::Origin,
     ) -> Arc<dyn StorageArea>;
 
+    /// Lists the (partition, origin) pairs that currently hold data for the given tab.
+    ///
+    /// Used to clone a tab's sessionStorage into a duplicate of the tab. Stores that cannot
+    /// enumerate their contents return an empty list.
+    fn list_areas(&self, _zone: ZoneId, _tab: TabId) -> Vec<(PartitionKey, url::Origin)> {
+        Vec::new()
+    }
+
     /// Drops all session storage for the given tab in the specified zone.
     fn drop_tab(&self, zone: ZoneId, tab: TabId);
 }
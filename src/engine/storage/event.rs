@@ -3,7 +3,8 @@ use crate::tab::TabId;
 use crate::zone::ZoneId;
 
 /// Scope of the store
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
 pub enum StorageScope {
     /// Local storage, typically not tied to a specific tab.
     Local,
@@ -20,7 +21,7 @@ pub struct StorageEvent {
     pub partition: PartitionKey,
     /// The origin of the URL where the storage event occurred.
     pub origin: url::Origin,
-    /// The key that was changed in the storage.
+    /// The key that was changed in the storage. `None` for `clear()` and batched writes.
     pub key: Option<String>,
     /// The old value of the key before the change, if applicable.
     pub old_value: Option<String>,
@@ -30,6 +31,9 @@ pub struct StorageEvent {
     pub source_tab: Option<TabId>,
     /// The scope of the storage event, indicating whether it is local or session storage.
     pub scope: StorageScope,
+    /// All keys written by a batched write (`set_many`), in write order. Empty for
+    /// single-key changes and for `clear()`.
+    pub changed_keys: Vec<String>,
 }
 
 #[cfg(test)]
@@ -58,6 +62,7 @@ mod tests {
             new_value: Some("hello".into()),
             source_tab: None,
             scope: StorageScope::Local,
+            changed_keys: Vec::new(),
         };
 
         assert!(matches!(ev.partition, PartitionKey::None));
@@ -83,6 +88,7 @@ mod tests {
             new_value: Some("2".into()),
             source_tab: Some(tab),
             scope: StorageScope::Session,
+            changed_keys: Vec::new(),
         };
 
         // Basic checks
@@ -120,6 +126,7 @@ mod tests {
             new_value: None,
             source_tab: Some(t()),
             scope: StorageScope::Session,
+            changed_keys: Vec::new(),
         };
 
         let mut ev2 = ev1.clone();
@@ -148,6 +155,7 @@ mod tests {
             new_value: Some("2".into()),
             source_tab: None,
             scope: StorageScope::Local,
+            changed_keys: Vec::new(),
         };
 
         let s = format!("{ev:?}");
@@ -32,6 +32,15 @@ impl LocalStore for InMemoryLocalStore {
             .or_insert_with(|| Arc::new(InMemoryLocalArea::default()) as Arc<dyn StorageArea>)
             .clone())
     }
+
+    fn list_areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        let guard = self.areas.lock().unwrap();
+        Ok(guard
+            .iter()
+            .filter(|((z, _, _), area)| *z == zone && area.len() > 0)
+            .map(|((_, part, origin), _)| (part.clone(), origin.clone()))
+            .collect())
+    }
 }
 
 #[derive(Default)]
@@ -52,6 +61,14 @@ impl StorageArea for InMemoryLocalArea {
         Ok(())
     }
 
+    fn set_many(&self, items: &[(&str, &str)]) -> Result<()> {
+        let mut map = self.map.lock().unwrap();
+        for (key, value) in items {
+            map.insert(key.to_string(), value.to_string());
+        }
+        Ok(())
+    }
+
     fn remove_item(&self, key: &str) -> Result<()> {
         self.map.lock().unwrap().remove(key);
         Ok(())
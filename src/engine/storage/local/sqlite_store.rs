@@ -2,20 +2,66 @@ use anyhow::Result;
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::rusqlite::{params, OpenFlags};
 use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use url::Url;
 
-use crate::engine::storage::area::{LocalStore, StorageArea};
+use crate::engine::storage::area::{LocalStore, OriginRecord, StorageArea};
+use crate::engine::storage::lock::ProfileLock;
+use crate::engine::storage::migrations::{migrate, Migration};
 use crate::engine::storage::types::PartitionKey;
 use crate::zone::ZoneId;
 
+/// Schema of the `local_storage` and `local_storage_origins` tables
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        sql: "CREATE TABLE IF NOT EXISTS local_storage (
+            zone TEXT NOT NULL,
+            partition TEXT NOT NULL,
+            origin TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+            PRIMARY KEY(zone, partition, origin, key)
+        );",
+    },
+    Migration {
+        version: 2,
+        sql: "CREATE TABLE local_storage_origins (
+            zone TEXT NOT NULL,
+            origin TEXT NOT NULL,
+            last_access_ms INTEGER,
+            persistent INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY(zone, origin)
+        );",
+    },
+];
+
 /// SQLite-based local storage implementation
 pub struct SqliteLocalStore {
     pool: Pool<SqliteConnectionManager>,
+    /// Keeps other engine instances from using the same database (`None` for in-memory databases)
+    _lock: Option<ProfileLock>,
 }
 
 impl SqliteLocalStore {
-    /// Creates a new SQLite local store with the specified database file path.
+    /// Creates a new SQLite local store with the specified database file path, migrating an
+    /// existing database to the current schema.
+    ///
+    /// # Errors
+    /// Fails with [`EngineError::ProfileInUse`](crate::EngineError::ProfileInUse) (which can
+    /// be recovered with `err.downcast_ref::<EngineError>()`) when another instance already
+    /// uses the database, or when the database cannot be opened.
     pub fn new(path: &str) -> Result<Self> {
+        let lock = if path == ":memory:" || path.starts_with("file:") {
+            None
+        } else {
+            Some(ProfileLock::acquire(Path::new(path))?)
+        };
+
         let manager = SqliteConnectionManager::file(path)
             .with_flags(
                 OpenFlags::SQLITE_OPEN_READ_WRITE
@@ -26,29 +72,25 @@ impl SqliteLocalStore {
                 c.busy_timeout(std::time::Duration::from_millis(500))?;
                 c.pragma_update(None, "journal_mode", &"WAL")?;
                 c.pragma_update(None, "foreign_keys", &"ON")?;
-                c.execute_batch(
-                    "CREATE TABLE IF NOT EXISTS local_storage (
-                        zone TEXT NOT NULL,
-                        partition TEXT NOT NULL,
-                        origin TEXT NOT NULL,
-                        key TEXT NOT NULL,
-                        value TEXT NOT NULL,
-                        updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
-                        PRIMARY KEY(zone, partition, origin, key)
-                    );",
-                )?;
                 Ok(())
             });
 
+        // Every connection to an in-memory database opens a database of its own
+        let max_size = if lock.is_some() { 16 } else { 1 };
         let pool = Pool::builder()
-            .max_size(16)
+            .max_size(max_size)
             .connection_timeout(std::time::Duration::from_secs(5))
             .build(manager)?;
 
-        Ok(Self { pool })
+        migrate(
+            &*pool.get()?,
+            lock.as_ref().map(|_| Path::new(path)),
+            MIGRATIONS,
+        )?;
+
+        Ok(Self { pool, _lock: lock })
     }
 
-    #[allow(unused)]
     fn conn(&self) -> Result<PooledConnection<SqliteConnectionManager>> {
         Ok(self.pool.get()?)
     }
@@ -64,13 +106,118 @@ impl LocalStore for SqliteLocalStore {
         Ok(Arc::new(SqliteLocalArea {
             pool: self.pool.clone(),
             zone,
-            partition: match part {
-                PartitionKey::None => "".to_string(),
-                PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
-            },
+            partition: partition_to_column(part),
             origin: origin.ascii_serialization(),
         }))
     }
+
+    fn list_areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare("SELECT DISTINCT partition, origin FROM local_storage WHERE zone=?1")?;
+        let rows = stmt.query_map(params![zone.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut areas = Vec::new();
+        for row in rows {
+            let (partition, origin) = row?;
+            let Some(part) = partition_from_column(&partition) else {
+                continue;
+            };
+            let Ok(origin) = Url::parse(&origin) else {
+                continue;
+            };
+            areas.push((part, origin.origin()));
+        }
+
+        Ok(areas)
+    }
+
+    fn origin_records(&self, zone: ZoneId) -> Result<HashMap<url::Origin, OriginRecord>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT origin, last_access_ms, persistent FROM local_storage_origins WHERE zone=?1",
+        )?;
+        let rows = stmt.query_map(params![zone.to_string()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, bool>(2)?,
+            ))
+        })?;
+
+        let mut records = HashMap::new();
+        for row in rows {
+            let (origin, last_access_ms, persistent) = row?;
+            let Ok(origin) = Url::parse(&origin) else {
+                continue;
+            };
+            let last_access =
+                last_access_ms.map(|ms| UNIX_EPOCH + Duration::from_millis(ms.max(0) as u64));
+            records.insert(
+                origin.origin(),
+                OriginRecord {
+                    last_access,
+                    persistent,
+                },
+            );
+        }
+        Ok(records)
+    }
+
+    fn set_origin_record(
+        &self,
+        zone: ZoneId,
+        origin: &url::Origin,
+        record: OriginRecord,
+    ) -> Result<()> {
+        let conn = self.conn()?;
+        if record == OriginRecord::default() {
+            conn.execute(
+                "DELETE FROM local_storage_origins WHERE zone=?1 AND origin=?2",
+                params![zone.to_string(), origin.ascii_serialization()],
+            )?;
+            return Ok(());
+        }
+        let last_access_ms = record.last_access.map(|at| {
+            at.duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as i64
+        });
+        conn.execute(
+            "INSERT INTO local_storage_origins(zone,origin,last_access_ms,persistent)
+             VALUES (?1,?2,?3,?4)
+             ON CONFLICT(zone,origin) DO UPDATE
+             SET last_access_ms=excluded.last_access_ms, persistent=excluded.persistent",
+            params![
+                zone.to_string(),
+                origin.ascii_serialization(),
+                last_access_ms,
+                record.persistent
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// Serializes a partition key into the `partition` column format.
+fn partition_to_column(part: &PartitionKey) -> String {
+    match part {
+        PartitionKey::None => "".to_string(),
+        PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
+    }
+}
+
+/// Parses the `partition` column back into a partition key. Returns `None` for unknown formats.
+fn partition_from_column(s: &str) -> Option<PartitionKey> {
+    if s.is_empty() {
+        return Some(PartitionKey::None);
+    }
+    let origin = s.strip_prefix("top:")?;
+    Url::parse(origin)
+        .ok()
+        .map(|u| PartitionKey::TopLevel(u.origin()))
 }
 
 struct SqliteLocalArea {
@@ -113,6 +260,25 @@ impl StorageArea for SqliteLocalArea {
         Ok(())
     }
 
+    /// Writes all items in a single transaction (one commit for the whole batch).
+    fn set_many(&self, items: &[(&str, &str)]) -> Result<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO local_storage(zone,partition,origin,key,value) VALUES (?1,?2,?3,?4,?5)
+                 ON CONFLICT(zone,partition,origin,key) DO UPDATE
+                 SET value=excluded.value, updated_at=strftime('%s','now')",
+            )?;
+            let zone = self.zone.to_string();
+            for (key, value) in items {
+                stmt.execute(params![zone, self.partition, self.origin, key, value])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
     fn remove_item(&self, key: &str) -> Result<()> {
         let conn = self.conn()?;
         conn.execute(
@@ -164,3 +330,80 @@ impl StorageArea for SqliteLocalArea {
         rows.filter_map(Result::ok).collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_store_on_same_database_is_rejected() {
+        let path = std::env::temp_dir().join(format!("gosub-local-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+
+        let store = SqliteLocalStore::new(path).unwrap();
+        let err = SqliteLocalStore::new(path)
+            .err()
+            .expect("database is in use");
+        assert!(matches!(
+            err.downcast_ref::<crate::EngineError>(),
+            Some(crate::EngineError::ProfileInUse(_))
+        ));
+
+        drop(store);
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(format!("{path}.lock"));
+    }
+
+    #[test]
+    fn set_many_writes_all_items_in_one_transaction() {
+        let path = std::env::temp_dir().join(format!("gosub-local-{}.db", uuid::Uuid::new_v4()));
+        let store = SqliteLocalStore::new(path.to_str().unwrap()).unwrap();
+        let origin = Url::parse("https://bulk.test").unwrap().origin();
+        let area = store
+            .area(ZoneId::new(), &PartitionKey::None, &origin)
+            .unwrap();
+
+        area.set_item("a", "old").unwrap();
+        area.set_many(&[("a", "1"), ("b", "2")]).unwrap();
+
+        assert_eq!(area.keys(), vec!["a", "b"]);
+        assert_eq!(area.get_item("a").as_deref(), Some("1"));
+        assert_eq!(area.get_item("b").as_deref(), Some("2"));
+
+        drop(area);
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db.lock"));
+    }
+    #[test]
+    fn origin_records_survive_reopening_the_database() {
+        let path = std::env::temp_dir().join(format!("gosub-local-{}.db", uuid::Uuid::new_v4()));
+        let zone = ZoneId::new();
+        let origin = Url::parse("https://kept.test").unwrap().origin();
+        let record = OriginRecord {
+            last_access: Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)),
+            persistent: true,
+        };
+
+        let store = SqliteLocalStore::new(path.to_str().unwrap()).unwrap();
+        store.set_origin_record(zone, &origin, record).unwrap();
+        drop(store);
+
+        let store = SqliteLocalStore::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            store.origin_records(zone).unwrap().get(&origin),
+            Some(&record)
+        );
+        assert!(store.origin_records(ZoneId::new()).unwrap().is_empty());
+
+        // The default record is not worth keeping
+        store
+            .set_origin_record(zone, &origin, OriginRecord::default())
+            .unwrap();
+        assert!(store.origin_records(zone).unwrap().is_empty());
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db.lock"));
+    }
+}
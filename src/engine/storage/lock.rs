@@ -0,0 +1,96 @@
+//! Cross-process locking for profile files.
+//!
+//! Two engine instances writing to the same database or JSON file will corrupt each
+//! other's data. Persistent stores therefore take an exclusive advisory lock on a
+//! `<file>.lock` sidecar next to their data file for as long as they are alive. A second
+//! store (in this or another process) attaching to the same file fails with
+//! [`EngineError::ProfileInUse`].
+//!
+//! The lock is advisory: it only protects against other users of this lock, not against
+//! arbitrary processes writing to the files. It is released automatically when the store
+//! is dropped, or when the process exits or crashes.
+use crate::EngineError;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// An exclusive lock on a profile file, held until dropped.
+#[derive(Debug)]
+pub struct ProfileLock {
+    /// Open handle to the lock file; the OS releases the lock when it is closed.
+    _file: File,
+    /// Path of the lock file
+    path: PathBuf,
+}
+
+impl ProfileLock {
+    /// Acquires the lock for the data file at `path`, without blocking.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another store already holds the lock.
+    /// - [`EngineError::StorageError`] when the lock file cannot be created or locked.
+    pub fn acquire(path: &Path) -> Result<Self, EngineError> {
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock_path = PathBuf::from(lock_path);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| {
+                EngineError::StorageError(format!("cannot open {}: {e}", lock_path.display()))
+            })?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(EngineError::ProfileInUse(path.display().to_string()));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(EngineError::StorageError(format!(
+                    "cannot lock {}: {e}",
+                    lock_path.display()
+                )));
+            }
+        }
+
+        // Record the owner for diagnostics; failing to do so is harmless.
+        let _ = file.set_len(0);
+        let _ = write!(file, "{}", std::process::id());
+
+        Ok(Self {
+            _file: file,
+            path: lock_path,
+        })
+    }
+
+    /// Returns the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_acquire_fails_until_first_is_dropped() {
+        let path = std::env::temp_dir().join(format!("gosub-lock-{}.db", uuid::Uuid::new_v4()));
+
+        let first = ProfileLock::acquire(&path).expect("first lock");
+        assert!(matches!(
+            ProfileLock::acquire(&path),
+            Err(EngineError::ProfileInUse(_))
+        ));
+
+        let lock_path = first.path().to_path_buf();
+        drop(first);
+        let again = ProfileLock::acquire(&path).expect("lock after release");
+
+        drop(again);
+        let _ = std::fs::remove_file(lock_path);
+    }
+}
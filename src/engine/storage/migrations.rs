@@ -0,0 +1,169 @@
+//! Schema migrations for the SQLite-backed stores.
+//!
+//! Every store lists its schema as an ordered set of [`Migration`]s, starting at version 1.
+//! [`migrate`] records the applied versions in a `schema_version` table and runs the ones that
+//! are missing, each in its own transaction. Before touching a database that already holds
+//! data, it writes a copy to `<database>.v<version>.bak`, so a failed or unwanted upgrade can
+//! be undone by hand.
+//!
+//! Databases from before versioning have no `schema_version` table and count as version 0.
+//! Migration 1 of each store therefore has to accept tables that already exist.
+use crate::EngineError;
+use r2d2_sqlite::rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+
+/// One step in the schema of a store.
+pub(crate) struct Migration {
+    /// Version the schema has after this migration
+    pub version: u32,
+    /// Statements that bring the schema from the previous version to `version`
+    pub sql: &'static str,
+}
+
+/// Brings the database behind `conn` up to the last of `migrations`, which must be ordered
+/// by version. `path` is the database file, used for the backup; pass `None` for in-memory
+/// databases. Returns the version the database had before.
+///
+/// # Errors
+/// Fails with [`EngineError::StorageError`] when the database was written by a newer engine,
+/// or when the backup or a migration fails. A failed migration leaves the database at the
+/// last version that succeeded.
+pub(crate) fn migrate(
+    conn: &Connection,
+    path: Option<&Path>,
+    migrations: &[Migration],
+) -> Result<u32, EngineError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            applied_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+        );",
+    )
+    .map_err(storage_error)?;
+
+    let current: u32 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(storage_error)?;
+    let latest = migrations.last().map_or(0, |m| m.version);
+    if current > latest {
+        return Err(EngineError::StorageError(format!(
+            "database schema version {current} is newer than the supported version {latest}"
+        )));
+    }
+
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(current);
+    }
+
+    if let Some(path) = path {
+        if has_data(conn)? {
+            backup(conn, &backup_path(path, current))?;
+        }
+    }
+
+    for migration in pending {
+        let tx = conn.unchecked_transaction().map_err(storage_error)?;
+        tx.execute_batch(migration.sql).map_err(|e| {
+            EngineError::StorageError(format!("migration to version {}: {e}", migration.version))
+        })?;
+        tx.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            params![migration.version],
+        )
+        .map_err(storage_error)?;
+        tx.commit().map_err(storage_error)?;
+    }
+
+    Ok(current)
+}
+
+/// Returns where the backup of the database at `path`, taken at `version`, is written.
+pub(crate) fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{version}.bak"));
+    PathBuf::from(backup)
+}
+
+/// Returns true when the database has tables besides `schema_version`.
+fn has_data(conn: &Connection) -> Result<bool, EngineError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name != 'schema_version'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|tables| tables > 0)
+    .map_err(storage_error)
+}
+
+/// Writes a consistent copy of the database to `to`, replacing an older backup.
+fn backup(conn: &Connection, to: &Path) -> Result<(), EngineError> {
+    if to.exists() {
+        std::fs::remove_file(to).map_err(|e| EngineError::StorageError(e.to_string()))?;
+    }
+    let to = to.to_str().ok_or_else(|| {
+        EngineError::StorageError(format!("invalid backup path: {}", to.display()))
+    })?;
+    conn.execute("VACUUM INTO ?1", params![to])
+        .map_err(|e| EngineError::StorageError(format!("backup to {to}: {e}")))?;
+    Ok(())
+}
+
+fn storage_error(e: r2d2_sqlite::rusqlite::Error) -> EngineError {
+    EngineError::StorageError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            sql: "CREATE TABLE IF NOT EXISTS items (key TEXT PRIMARY KEY);",
+        },
+        Migration {
+            version: 2,
+            sql: "ALTER TABLE items ADD COLUMN value TEXT NOT NULL DEFAULT '';",
+        },
+    ];
+
+    #[test]
+    fn unversioned_databases_are_backed_up_and_upgraded() {
+        let path = std::env::temp_dir().join(format!("gosub-migrate-{}.db", uuid::Uuid::new_v4()));
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (key TEXT PRIMARY KEY); INSERT INTO items VALUES ('a');",
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&conn, Some(&path), MIGRATIONS).unwrap(), 0);
+        let value: String = conn
+            .query_row("SELECT value FROM items WHERE key = 'a'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(value, "");
+
+        let backup = Connection::open(backup_path(&path, 0)).unwrap();
+        let keys: i64 = backup
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(keys, 1);
+
+        assert_eq!(migrate(&conn, Some(&path), MIGRATIONS).unwrap(), 2);
+        assert!(matches!(
+            migrate(&conn, Some(&path), &MIGRATIONS[..1]),
+            Err(EngineError::StorageError(_))
+        ));
+
+        drop(backup);
+        drop(conn);
+        let _ = std::fs::remove_file(backup_path(&path, 0));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,14 +1,47 @@
-use super::area::{LocalStore, SessionStore, StorageArea};
+use super::area::{LocalStore, OriginRecord, SessionStore, StorageArea};
 use super::event::{StorageEvent, StorageScope};
-use super::types::PartitionKey;
+use super::types::{
+    compute_partition_key_in, FirstPartySets, PartitionExplanation, PartitionKey, PartitionPolicy,
+};
+use crate::engine::logging::engine_log;
 use crate::tab::TabId;
 use crate::zone::ZoneId;
 use anyhow::Result;
-use std::sync::{mpsc, Arc, Mutex};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
 
 /// A handle for receiving storage change notifications.
 pub type Subscription = mpsc::Receiver<StorageEvent>;
 
+/// Share of the quota above which [`StorageService::enforce_quota`] evicts origins.
+pub const EVICTION_THRESHOLD: f64 = 0.9;
+
+/// Share of the quota that eviction brings the usage back down to, so that a zone close to its
+/// quota does not evict again on every write.
+const EVICTION_TARGET: f64 = 0.8;
+
+/// How often the last access of an origin is written to the local store. Pages read their
+/// storage all the time, so the store lags behind by at most this much.
+const ACCESS_WRITE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How much localStorage an origin uses in a zone, see [`StorageService::usage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginUsage {
+    /// The origin
+    pub origin: url::Origin,
+    /// Size of all keys and values of the origin, over all partitions, in bytes
+    pub bytes: u64,
+    /// When a page last read or wrote the storage of the origin, as kept in its
+    /// [`OriginRecord`]. `None` when no page used it yet, for instance after it was copied
+    /// from another zone.
+    pub last_access: Option<SystemTime>,
+    /// Whether the embedder exempted the origin from eviction, see
+    /// [`StorageService::set_persistent`]
+    pub persistent: bool,
+}
+
 /// Internal bus that fans out StorageEvent to subscribers.
 #[derive(Default)]
 struct StorageBus {
@@ -26,17 +59,152 @@ impl StorageBus {
     }
 }
 
+/// Size and [`OriginRecord`] of the localStorage of every origin, shared with the areas the
+/// service hands out.
+///
+/// A zone is read from the store once, when its usage is first needed. From then on the
+/// areas keep the sizes up to date as they are written, and records are written through to
+/// the store.
+struct OriginLedger {
+    store: Arc<dyn LocalStore>,
+    state: Mutex<LedgerState>,
+}
+
+#[derive(Default)]
+struct LedgerState {
+    /// Zones whose sizes and records were read from the store
+    loaded: HashSet<ZoneId>,
+    origins: HashMap<(ZoneId, url::Origin), OriginEntry>,
+}
+
+#[derive(Default)]
+struct OriginEntry {
+    /// Size of all keys and values of the origin, over all partitions, in bytes. Only kept
+    /// for loaded zones.
+    bytes: u64,
+    record: OriginRecord,
+    /// Last access as written to the store
+    stored_access: Option<SystemTime>,
+}
+
+impl OriginLedger {
+    fn state(&self) -> MutexGuard<'_, LedgerState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Returns the state with `zone` loaded from the store.
+    fn loaded(&self, zone: ZoneId) -> Result<MutexGuard<'_, LedgerState>> {
+        let mut state = self.state();
+        if !state.loaded.contains(&zone) {
+            self.load(&mut state, zone)?;
+        }
+        Ok(state)
+    }
+
+    /// Sizes up the areas of `zone` and reads the records of its origins. Records changed
+    /// since the service was created are newer than the stored ones.
+    fn load(&self, state: &mut LedgerState, zone: ZoneId) -> Result<()> {
+        let mut bytes: HashMap<url::Origin, u64> = HashMap::new();
+        for (part, origin) in self.store.list_areas(zone)? {
+            let area = self.store.area(zone, &part, &origin)?;
+            *bytes.entry(origin).or_default() += area_bytes(area.as_ref());
+        }
+        for (origin, record) in self.store.origin_records(zone)? {
+            match state.origins.entry((zone, origin)) {
+                Entry::Vacant(vacant) => {
+                    vacant.insert(OriginEntry {
+                        bytes: 0,
+                        record,
+                        stored_access: record.last_access,
+                    });
+                }
+                Entry::Occupied(mut occupied) => {
+                    let entry = occupied.get_mut();
+                    entry.record.last_access = entry.record.last_access.max(record.last_access);
+                    entry.stored_access = entry.stored_access.max(record.last_access);
+                }
+            }
+        }
+        for (origin, bytes) in bytes {
+            state.origins.entry((zone, origin)).or_default().bytes = bytes;
+        }
+        state.loaded.insert(zone);
+        Ok(())
+    }
+
+    /// Makes `zone` load again, after its areas were written around the ledger.
+    fn unload(&self, zone: ZoneId) {
+        let mut state = self.state();
+        state.loaded.remove(&zone);
+        for ((z, _), entry) in state.origins.iter_mut() {
+            if *z == zone {
+                entry.bytes = 0;
+            }
+        }
+    }
+
+    /// Records that a page used the storage of `origin`.
+    fn touch(&self, zone: ZoneId, origin: &url::Origin) {
+        let now = SystemTime::now();
+        let mut state = self.state();
+        let entry = state.origins.entry((zone, origin.clone())).or_default();
+        entry.record.last_access = Some(now);
+        let due = entry.stored_access.is_none_or(|at| {
+            now.duration_since(at)
+                .is_ok_and(|d| d >= ACCESS_WRITE_INTERVAL)
+        });
+        if !due {
+            return;
+        }
+        entry.stored_access = Some(now);
+        let record = entry.record;
+        drop(state);
+        self.store_record(zone, origin, record);
+    }
+
+    /// Accounts for a write that added and removed the given numbers of bytes of `origin`.
+    fn resize(&self, zone: ZoneId, origin: &url::Origin, added: u64, removed: u64) {
+        let mut state = self.state();
+        // Zones that are not loaded yet are sized up from the store when they are
+        if !state.loaded.contains(&zone) {
+            return;
+        }
+        let entry = state.origins.entry((zone, origin.clone())).or_default();
+        entry.bytes = (entry.bytes + added).saturating_sub(removed);
+    }
+
+    fn store_record(&self, zone: ZoneId, origin: &url::Origin, record: OriginRecord) {
+        if let Err(e) = self.store.set_origin_record(zone, origin, record) {
+            engine_log!(
+                Warn,
+                "Zone {:?}: cannot store the record of {}: {e}",
+                zone,
+                origin.ascii_serialization()
+            );
+        }
+    }
+}
+
 /// Public service used by the engine/DOM to access storage and receive events.
+///
+/// Besides handing out storage areas, the service keeps track of how much localStorage every
+/// origin uses and when it last used it, so that [`StorageService::enforce_quota`] can evict
+/// the least recently used origins when a zone runs out of room.
 pub struct StorageService {
     local: Arc<dyn LocalStore>,
     session: Arc<dyn SessionStore>,
     bus: Arc<StorageBus>,
+    ledger: Arc<OriginLedger>,
 }
 
 impl StorageService {
     /// Create a new StorageService with the given local and session stores.
     pub fn new(local: Arc<dyn LocalStore>, session: Arc<dyn SessionStore>) -> Self {
         Self {
+            ledger: Arc::new(OriginLedger {
+                store: local.clone(),
+                state: Mutex::new(LedgerState::default()),
+            }),
             local,
             session,
             bus: Arc::new(StorageBus::default()),
@@ -56,6 +224,7 @@ impl StorageService {
         origin: &url::Origin,
     ) -> Result<Arc<dyn StorageArea>> {
         let inner = self.local.area(zone, part, origin)?;
+        self.ledger.touch(zone, origin);
         Ok(self.wrap_notifying(
             inner,
             zone,
@@ -85,11 +254,232 @@ impl StorageService {
         )
     }
 
+    /// Copies all localStorage data of `from_zone` into `to_zone` of the `target` service.
+    ///
+    /// The target may be this same service. Items are written directly into the target
+    /// store, so no storage events are published for the copied keys.
+    pub fn copy_local_zone(
+        &self,
+        from_zone: ZoneId,
+        target: &StorageService,
+        to_zone: ZoneId,
+    ) -> Result<()> {
+        for (part, origin) in self.local.list_areas(from_zone)? {
+            let src = self.local.area(from_zone, &part, &origin)?;
+            let dst = target.local.area(to_zone, &part, &origin)?;
+            for key in src.keys() {
+                if let Some(value) = src.get_item(&key) {
+                    dst.set_item(&key, &value)?;
+                }
+            }
+        }
+        target.ledger.unload(to_zone);
+        Ok(())
+    }
+
+    /// Copies all sessionStorage of tab `from` into tab `to`, as when a tab is duplicated.
+    ///
+    /// Every partition and origin is copied. As with [`copy_local_zone`](Self::copy_local_zone),
+    /// no storage events are published for the copied keys.
+    pub fn clone_session_tab(&self, zone: ZoneId, from: TabId, to: TabId) -> Result<()> {
+        for (part, origin) in self.session.list_areas(zone, from) {
+            self.copy_session_area(zone, from, to, &part, &origin)?;
+        }
+        Ok(())
+    }
+
+    /// Copies the sessionStorage that `opener` holds for `origin` in partition `part` into
+    /// `popup`, as when a page opens a new window with `window.open`.
+    ///
+    /// Only that one area is copied: the opened page starts out with the opener's session
+    /// data for its own origin, not with data of other origins or partitions.
+    pub fn clone_session_for_popup(
+        &self,
+        zone: ZoneId,
+        opener: TabId,
+        popup: TabId,
+        part: &PartitionKey,
+        origin: &url::Origin,
+    ) -> Result<()> {
+        self.copy_session_area(zone, opener, popup, part, origin)
+    }
+
+    /// Explains which partition the storage of `url` lives in when it is loaded on the
+    /// top-level page `top_level`, under `policy`.
+    ///
+    /// This is a debugging aid for verifying isolation: it tells whether a third-party
+    /// resource shares its storage with visits to its own site. Zones fill in their own
+    /// policy with [`Zone::explain_partition`](crate::zone::Zone::explain_partition).
+    ///
+    /// ```
+    /// use gosub_engine::storage::{PartitionKey, PartitionPolicy, StorageService};
+    /// use url::Url;
+    ///
+    /// let ad = Url::parse("https://ads.test/pixel.js").unwrap();
+    /// let news = Url::parse("https://news.test/").unwrap();
+    /// let explained = StorageService::explain_partition(&ad, &news, PartitionPolicy::TopLevelOrigin);
+    /// assert_eq!(explained.partition, PartitionKey::TopLevel(news.origin()));
+    /// assert!(explained.third_party && !explained.shared_with_first_party);
+    /// ```
+    pub fn explain_partition(
+        url: &url::Url,
+        top_level: &url::Url,
+        policy: PartitionPolicy,
+    ) -> PartitionExplanation {
+        Self::explain_partition_in(url, top_level, policy, &FirstPartySets::default())
+    }
+
+    /// Like [`StorageService::explain_partition`], with the related sites of `sets` sharing
+    /// a partition.
+    pub fn explain_partition_in(
+        url: &url::Url,
+        top_level: &url::Url,
+        policy: PartitionPolicy,
+        sets: &FirstPartySets,
+    ) -> PartitionExplanation {
+        let origin = url.origin();
+        let top_level_origin = top_level.origin();
+        let partition = compute_partition_key_in(top_level, policy, sets);
+        PartitionExplanation {
+            third_party: origin != top_level_origin,
+            shared_with_first_party: partition == compute_partition_key_in(url, policy, sets),
+            origin,
+            top_level: top_level_origin,
+            policy,
+            partition,
+        }
+    }
+
+    /// Lists the (partition, origin) pairs that hold localStorage data for `zone`.
+    pub fn local_areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+        self.local.list_areas(zone)
+    }
+
+    /// Exempts an origin from eviction in `zone`, or makes it evictable again. Embedders mark
+    /// origins persistent when the user asked to keep their data. The mark is kept in the
+    /// local store.
+    pub fn set_persistent(&self, zone: ZoneId, origin: &url::Origin, persistent: bool) {
+        let record = {
+            let mut state = self.ledger.state();
+            let entry = state.origins.entry((zone, origin.clone())).or_default();
+            entry.record.persistent = persistent;
+            entry.record
+        };
+        self.ledger.store_record(zone, origin, record);
+    }
+
+    /// Returns true when `origin` is exempt from eviction in `zone`.
+    pub fn is_persistent(&self, zone: ZoneId, origin: &url::Origin) -> bool {
+        self.ledger.loaded(zone).is_ok_and(|state| {
+            state
+                .origins
+                .get(&(zone, origin.clone()))
+                .is_some_and(|entry| entry.record.persistent)
+        })
+    }
+
+    /// Returns the localStorage usage of every origin with data in `zone`, largest first.
+    ///
+    /// Only stores that can [list their areas](LocalStore::list_areas) report anything. The
+    /// store is read the first time a zone is asked for; after that, the sizes follow the
+    /// writes made through the service.
+    pub fn usage(&self, zone: ZoneId) -> Result<Vec<OriginUsage>> {
+        let state = self.ledger.loaded(zone)?;
+        let mut usage: Vec<OriginUsage> = state
+            .origins
+            .iter()
+            .filter(|((z, _), entry)| *z == zone && entry.bytes > 0)
+            .map(|((_, origin), entry)| OriginUsage {
+                origin: origin.clone(),
+                bytes: entry.bytes,
+                last_access: entry.record.last_access,
+                persistent: entry.record.persistent,
+            })
+            .collect();
+        usage.sort_by_key(|u| std::cmp::Reverse(u.bytes));
+        Ok(usage)
+    }
+
+    /// Removes the localStorage of `origin` in all partitions of `zone`, and returns the
+    /// number of bytes freed. No storage events are published for the removed keys.
+    pub fn clear_origin(&self, zone: ZoneId, origin: &url::Origin) -> Result<u64> {
+        let mut freed = 0;
+        for (part, area_origin) in self.local.list_areas(zone)? {
+            if area_origin != *origin {
+                continue;
+            }
+            let area = self.local.area(zone, &part, &area_origin)?;
+            freed += area_bytes(area.as_ref());
+            area.clear()?;
+        }
+        let record = {
+            let mut state = self.ledger.state();
+            let entry = state.origins.entry((zone, origin.clone())).or_default();
+            entry.bytes = 0;
+            entry.record.last_access = None;
+            entry.stored_access = None;
+            entry.record
+        };
+        self.ledger.store_record(zone, origin, record);
+        Ok(freed)
+    }
+
+    /// Keeps the localStorage of `zone` within `quota` bytes.
+    ///
+    /// Nothing happens until the zone uses more than [`EVICTION_THRESHOLD`] of the quota. Then
+    /// whole origins are removed, least recently used first, until the usage is back under 80%
+    /// of the quota. Origins marked [persistent](StorageService::set_persistent) are never
+    /// removed, so a zone may stay over quota. Returns the origins that were removed.
+    pub fn enforce_quota(&self, zone: ZoneId, quota: u64) -> Result<Vec<OriginUsage>> {
+        let mut usage = self.usage(zone)?;
+        let mut total: u64 = usage.iter().map(|u| u.bytes).sum();
+        if total as f64 <= quota as f64 * EVICTION_THRESHOLD {
+            return Ok(Vec::new());
+        }
+
+        // Origins that no page used yet count as the least recently used
+        usage.retain(|u| !u.persistent);
+        usage.sort_by_key(|u| u.last_access);
+        let mut evicted = Vec::new();
+        for candidate in usage {
+            if total as f64 <= quota as f64 * EVICTION_TARGET {
+                break;
+            }
+            total -= self.clear_origin(zone, &candidate.origin)?.min(total);
+            evicted.push(candidate);
+        }
+        Ok(evicted)
+    }
+
     /// Drops a tab from sessionStorage.
     pub fn drop_tab(&self, zone: ZoneId, tab: TabId) {
         self.session.drop_tab(zone, tab);
     }
 
+    fn copy_session_area(
+        &self,
+        zone: ZoneId,
+        from: TabId,
+        to: TabId,
+        part: &PartitionKey,
+        origin: &url::Origin,
+    ) -> Result<()> {
+        let src = self.session.area(zone, from, part, origin);
+        let items: Vec<(String, String)> = src
+            .keys()
+            .into_iter()
+            .filter_map(|key| src.get_item(&key).map(|value| (key, value)))
+            .collect();
+        if items.is_empty() {
+            return Ok(());
+        }
+        let items: Vec<(&str, &str)> = items
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        self.session.area(zone, to, part, origin).set_many(&items)
+    }
+
     fn wrap_notifying(
         &self,
         inner: Arc<dyn StorageArea>,
@@ -106,6 +496,7 @@ impl StorageService {
             origin,
             source_tab,
             bus: self.bus.clone(),
+            ledger: (scope == StorageScope::Local).then(|| self.ledger.clone()),
             scope,
         })
     }
@@ -119,16 +510,51 @@ struct NotifyingArea {
     origin: url::Origin,
     source_tab: Option<TabId>,
     bus: Arc<StorageBus>,
+    /// Sizes and access times to update, for localStorage areas
+    ledger: Option<Arc<OriginLedger>>,
     scope: StorageScope,
 }
 
+impl NotifyingArea {
+    fn touch(&self) {
+        if let Some(ledger) = &self.ledger {
+            ledger.touch(self.zone, &self.origin);
+        }
+    }
+
+    fn resize(&self, added: u64, removed: u64) {
+        if let Some(ledger) = &self.ledger {
+            ledger.resize(self.zone, &self.origin, added, removed);
+        }
+    }
+}
+
+/// Size of an item, in bytes.
+fn item_bytes(key: &str, value: Option<&str>) -> u64 {
+    value.map_or(0, |value| (key.len() + value.len()) as u64)
+}
+
+/// Size of the keys and values of an area, in bytes.
+fn area_bytes(area: &dyn StorageArea) -> u64 {
+    area.keys()
+        .iter()
+        .map(|key| key.len() + area.get_item(key).map_or(0, |v| v.len()))
+        .sum::<usize>() as u64
+}
+
 impl StorageArea for NotifyingArea {
     fn get_item(&self, key: &str) -> Option<String> {
+        self.touch();
         self.inner.get_item(key)
     }
     fn set_item(&self, key: &str, value: &str) -> Result<()> {
+        self.touch();
         let old = self.inner.get_item(key);
         self.inner.set_item(key, value)?;
+        self.resize(
+            item_bytes(key, Some(value)),
+            item_bytes(key, old.as_deref()),
+        );
         self.bus.publish(StorageEvent {
             zone: self.zone,
             partition: self.partition.clone(),
@@ -138,12 +564,51 @@ impl StorageArea for NotifyingArea {
             new_value: Some(value.to_string()),
             source_tab: self.source_tab,
             scope: self.scope,
+            changed_keys: Vec::new(),
+        });
+        Ok(())
+    }
+    /// Writes the batch through to the inner store and publishes a single event
+    /// listing all changed keys.
+    fn set_many(&self, items: &[(&str, &str)]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.touch();
+        // The last value of a key is the one that sticks
+        let sizes = self.ledger.as_ref().map(|_| {
+            let latest: HashMap<&str, &str> = items.iter().copied().collect();
+            latest
+                .into_iter()
+                .fold((0, 0), |(added, removed), (key, value)| {
+                    let old = self.inner.get_item(key);
+                    (
+                        added + item_bytes(key, Some(value)),
+                        removed + item_bytes(key, old.as_deref()),
+                    )
+                })
+        });
+        self.inner.set_many(items)?;
+        if let Some((added, removed)) = sizes {
+            self.resize(added, removed);
+        }
+        self.bus.publish(StorageEvent {
+            zone: self.zone,
+            partition: self.partition.clone(),
+            origin: self.origin.clone(),
+            key: None,
+            old_value: None,
+            new_value: None,
+            source_tab: self.source_tab,
+            scope: self.scope,
+            changed_keys: items.iter().map(|(k, _)| k.to_string()).collect(),
         });
         Ok(())
     }
     fn remove_item(&self, key: &str) -> Result<()> {
         let old = self.inner.get_item(key);
         self.inner.remove_item(key)?;
+        self.resize(0, item_bytes(key, old.as_deref()));
         self.bus.publish(StorageEvent {
             zone: self.zone,
             partition: self.partition.clone(),
@@ -153,11 +618,17 @@ impl StorageArea for NotifyingArea {
             new_value: None,
             source_tab: self.source_tab,
             scope: self.scope,
+            changed_keys: Vec::new(),
         });
         Ok(())
     }
     fn clear(&self) -> Result<()> {
+        let removed = self
+            .ledger
+            .as_ref()
+            .map_or(0, |_| area_bytes(self.inner.as_ref()));
         self.inner.clear()?;
+        self.resize(0, removed);
         self.bus.publish(StorageEvent {
             zone: self.zone,
             partition: self.partition.clone(),
@@ -167,6 +638,7 @@ impl StorageArea for NotifyingArea {
             new_value: None,
             source_tab: self.source_tab,
             scope: self.scope,
+            changed_keys: Vec::new(),
         });
         Ok(())
     }
@@ -181,8 +653,11 @@ impl StorageArea for NotifyingArea {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::test_support::{engine_with_tab, tick};
+    use crate::{EngineCommand, EngineEvent, EngineNotification, MouseButton};
     use std::collections::HashMap;
     use std::time::Duration;
+    use url::Url;
 
     use crate::storage::InMemorySessionStore;
     use crate::tab::TabId;
@@ -301,6 +776,32 @@ mod tests {
         recv_none(&rx);
     }
 
+    #[test]
+    fn local_set_many_emits_single_batched_event() {
+        let local = Arc::new(TestLocalStore::default());
+        let session = Arc::new(InMemorySessionStore::new());
+        let svc = StorageService::new(local, session);
+
+        let zone = z();
+        let part = PartitionKey::None;
+        let origin = o("https://bulk.test");
+
+        let rx = svc.subscribe();
+        let area = svc.local_for(zone, &part, &origin).expect("area");
+
+        area.set_many(&[("a", "1"), ("b", "2"), ("c", "3")])
+            .unwrap();
+        let ev = recv_ok(&rx);
+        assert!(ev.key.is_none());
+        assert_eq!(ev.changed_keys, vec!["a", "b", "c"]);
+        assert_eq!(area.get_item("b").as_deref(), Some("2"));
+        recv_none(&rx);
+
+        // An empty batch is a no-op
+        area.set_many(&[]).unwrap();
+        recv_none(&rx);
+    }
+
     #[test]
     fn local_remove_and_clear_emit_events() {
         let local = Arc::new(TestLocalStore::default());
@@ -407,6 +908,308 @@ mod tests {
         recv_none(&rx2);
     }
 
+    #[test]
+    fn copy_local_zone_copies_all_areas_without_events() {
+        use crate::engine::storage::local::in_memory::InMemoryLocalStore;
+
+        let src = StorageService::new(
+            Arc::new(InMemoryLocalStore::new()),
+            Arc::new(InMemorySessionStore::new()),
+        );
+        let dst = StorageService::new(
+            Arc::new(InMemoryLocalStore::new()),
+            Arc::new(InMemorySessionStore::new()),
+        );
+
+        let from = z();
+        let to = z();
+        let part = PartitionKey::TopLevel(o("https://a.test"));
+        let origin_a = o("https://a.test");
+        let origin_b = o("https://b.test");
+
+        src.local_for(from, &part, &origin_a)
+            .unwrap()
+            .set_item("k", "1")
+            .unwrap();
+        src.local_for(from, &PartitionKey::None, &origin_b)
+            .unwrap()
+            .set_item("x", "2")
+            .unwrap();
+
+        let rx = dst.subscribe();
+        src.copy_local_zone(from, &dst, to).unwrap();
+        recv_none(&rx);
+
+        let a = dst.local_for(to, &part, &origin_a).unwrap();
+        assert_eq!(a.get_item("k").as_deref(), Some("1"));
+        let b = dst.local_for(to, &PartitionKey::None, &origin_b).unwrap();
+        assert_eq!(b.get_item("x").as_deref(), Some("2"));
+
+        // Source zone is left untouched, and the copy is independent.
+        a.set_item("k", "changed").unwrap();
+        let src_a = src.local_for(from, &part, &origin_a).unwrap();
+        assert_eq!(src_a.get_item("k").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn session_storage_is_cloned_into_duplicates_and_popups() {
+        let svc = StorageService::new(
+            Arc::new(TestLocalStore::default()),
+            Arc::new(InMemorySessionStore::new()),
+        );
+        let zone = z();
+        let opener = t();
+        let site = o("https://a.test");
+        let embedded = o("https://b.test");
+        let framed = PartitionKey::TopLevel(site.clone());
+
+        svc.session_for(zone, opener, &PartitionKey::None, &site)
+            .set_item("cart", "3")
+            .unwrap();
+        svc.session_for(zone, opener, &framed, &site)
+            .set_item("frame", "1")
+            .unwrap();
+        svc.session_for(zone, opener, &framed, &embedded)
+            .set_item("widget", "open")
+            .unwrap();
+
+        let rx = svc.subscribe();
+        let duplicate = t();
+        svc.clone_session_tab(zone, opener, duplicate).unwrap();
+        let popup = t();
+        svc.clone_session_for_popup(zone, opener, popup, &PartitionKey::None, &site)
+            .unwrap();
+        recv_none(&rx);
+
+        // A duplicate gets every area, keyed by the same partitions
+        let get = |tab, part: &PartitionKey, origin: &url::Origin, key: &str| {
+            svc.session_for(zone, tab, part, origin).get_item(key)
+        };
+        assert_eq!(
+            get(duplicate, &PartitionKey::None, &site, "cart").as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            get(duplicate, &framed, &site, "frame").as_deref(),
+            Some("1")
+        );
+        assert_eq!(
+            get(duplicate, &framed, &embedded, "widget").as_deref(),
+            Some("open")
+        );
+        assert_eq!(get(duplicate, &PartitionKey::None, &site, "frame"), None);
+
+        // A popup only gets the area of the origin and partition it was opened for
+        assert_eq!(
+            get(popup, &PartitionKey::None, &site, "cart").as_deref(),
+            Some("3")
+        );
+        assert_eq!(get(popup, &framed, &site, "frame"), None);
+        assert_eq!(get(popup, &framed, &embedded, "widget"), None);
+
+        // Copies are independent of the opener from then on
+        svc.session_for(zone, popup, &PartitionKey::None, &site)
+            .set_item("cart", "4")
+            .unwrap();
+        assert_eq!(
+            get(opener, &PartitionKey::None, &site, "cart").as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            get(duplicate, &PartitionKey::None, &site, "cart").as_deref(),
+            Some("3")
+        );
+    }
+
+    #[test]
+    fn explain_partition_reports_isolation() {
+        let top = url::Url::parse("https://news.test/article").unwrap();
+        let own = url::Url::parse("https://news.test/app.js").unwrap();
+        let ad = url::Url::parse("https://ads.test/frame").unwrap();
+
+        let first_party =
+            StorageService::explain_partition(&own, &top, PartitionPolicy::TopLevelOrigin);
+        assert!(!first_party.third_party && first_party.shared_with_first_party);
+
+        let isolated =
+            StorageService::explain_partition(&ad, &top, PartitionPolicy::TopLevelOrigin);
+        assert_eq!(
+            isolated.partition,
+            PartitionKey::TopLevel(o("https://news.test"))
+        );
+        assert!(isolated.third_party && !isolated.shared_with_first_party);
+        assert_eq!(
+            isolated.to_string(),
+            "https://ads.test on https://news.test: partitioned by https://news.test, \
+             isolated from first-party use"
+        );
+
+        // Without partitioning a third party sees the same storage on every site
+        let shared = StorageService::explain_partition(&ad, &top, PartitionPolicy::None);
+        assert_eq!(shared.partition, PartitionKey::None);
+        assert!(shared.third_party && shared.shared_with_first_party);
+    }
+
+    #[test]
+    fn least_recently_used_origins_are_evicted_near_the_quota() {
+        use crate::engine::storage::local::in_memory::InMemoryLocalStore;
+
+        let svc = StorageService::new(
+            Arc::new(InMemoryLocalStore::new()),
+            Arc::new(InMemorySessionStore::new()),
+        );
+        let zone = z();
+        let (old, kept, recent) = (
+            o("https://old.test"),
+            o("https://kept.test"),
+            o("https://recent.test"),
+        );
+        // 20 bytes per origin, the old one spread over two partitions
+        for (origin, part) in [
+            (&old, PartitionKey::None),
+            (&old, PartitionKey::TopLevel(o("https://top.test"))),
+            (&kept, PartitionKey::None),
+            (&recent, PartitionKey::None),
+        ] {
+            let value = if *origin == old {
+                "x".repeat(9)
+            } else {
+                "x".repeat(19)
+            };
+            svc.local_for(zone, &part, origin)
+                .unwrap()
+                .set_item("k", &value)
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        svc.set_persistent(zone, &kept, true);
+        // Reading counts as using the storage
+        std::thread::sleep(Duration::from_millis(2));
+        let _ = svc
+            .local_for(zone, &PartitionKey::None, &kept)
+            .unwrap()
+            .get_item("k");
+
+        let usage = svc.usage(zone).unwrap();
+        assert_eq!(usage.iter().map(|u| u.bytes).sum::<u64>(), 60);
+        assert!(usage.iter().any(|u| u.origin == kept && u.persistent));
+
+        // 60 bytes is within 90% of 70, but not of 60
+        assert!(svc.enforce_quota(zone, 70).unwrap().is_empty());
+        let evicted = svc.enforce_quota(zone, 60).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!((&evicted[0].origin, evicted[0].bytes), (&old, 20));
+
+        // Persistent origins stay, even when that leaves the zone over quota
+        let evicted = svc.enforce_quota(zone, 10).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].origin, recent);
+        let usage = svc.usage(zone).unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].origin, kept);
+    }
+
+    #[test]
+    fn usage_follows_writes_without_reading_the_store_again() {
+        use crate::engine::storage::local::in_memory::InMemoryLocalStore;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Default)]
+        struct CountingStore {
+            inner: InMemoryLocalStore,
+            listed: AtomicUsize,
+        }
+
+        impl LocalStore for CountingStore {
+            fn area(
+                &self,
+                zone: ZoneId,
+                part: &PartitionKey,
+                origin: &url::Origin,
+            ) -> Result<Arc<dyn StorageArea>> {
+                self.inner.area(zone, part, origin)
+            }
+            fn list_areas(&self, zone: ZoneId) -> Result<Vec<(PartitionKey, url::Origin)>> {
+                self.listed.fetch_add(1, Ordering::Relaxed);
+                self.inner.list_areas(zone)
+            }
+        }
+
+        let local = Arc::new(CountingStore::default());
+        let svc = StorageService::new(local.clone(), Arc::new(InMemorySessionStore::new()));
+        let zone = z();
+        let origin = o("https://grow.test");
+        let area = svc.local_for(zone, &PartitionKey::None, &origin).unwrap();
+
+        area.set_item("k", "12345").unwrap();
+        assert_eq!(svc.usage(zone).unwrap()[0].bytes, 6);
+        area.set_item("k", "1").unwrap();
+        area.set_many(&[("a", "1"), ("a", "22"), ("b", "3")])
+            .unwrap();
+        assert_eq!(svc.usage(zone).unwrap()[0].bytes, 2 + 3 + 2);
+        area.remove_item("a").unwrap();
+        assert_eq!(svc.usage(zone).unwrap()[0].bytes, 4);
+        for _ in 0..10 {
+            assert!(svc.enforce_quota(zone, 1_000).unwrap().is_empty());
+        }
+        area.clear().unwrap();
+        assert!(svc.usage(zone).unwrap().is_empty());
+        assert_eq!(local.listed.load(Ordering::Relaxed), 1);
+
+        // Data copied into a zone around its areas is sized up again
+        let copy = z();
+        assert!(svc.usage(copy).unwrap().is_empty());
+        area.set_item("k", "v").unwrap();
+        svc.copy_local_zone(zone, &svc, copy).unwrap();
+        assert_eq!(svc.usage(copy).unwrap()[0].bytes, 2);
+    }
+
+    #[cfg(feature = "sqlite_local_store")]
+    #[test]
+    fn access_times_and_persistent_marks_survive_a_restart() {
+        use crate::storage::SqliteLocalStore;
+
+        let path = std::env::temp_dir().join(format!("gosub-quota-{}.db", uuid::Uuid::new_v4()));
+        let open = || {
+            StorageService::new(
+                Arc::new(SqliteLocalStore::new(path.to_str().unwrap()).unwrap()),
+                Arc::new(InMemorySessionStore::new()),
+            )
+        };
+        let zone = z();
+        let (old, kept, recent) = (
+            o("https://old.test"),
+            o("https://kept.test"),
+            o("https://recent.test"),
+        );
+
+        let svc = open();
+        for origin in [&kept, &old, &recent] {
+            svc.local_for(zone, &PartitionKey::None, origin)
+                .unwrap()
+                .set_item("k", &"x".repeat(19))
+                .unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        svc.set_persistent(zone, &kept, true);
+        drop(svc);
+
+        let svc = open();
+        let usage = svc.usage(zone).unwrap();
+        assert_eq!(usage.len(), 3);
+        assert!(usage.iter().all(|u| u.last_access.is_some()));
+        assert!(svc.is_persistent(zone, &kept));
+
+        // Still least recently used first, and the persistent origin is still kept
+        let evicted = svc.enforce_quota(zone, 30).unwrap();
+        let evicted: Vec<_> = evicted.iter().map(|u| &u.origin).collect();
+        assert_eq!(evicted, vec![&old, &recent]);
+
+        drop(svc);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db.lock"));
+    }
+
     #[test]
     fn dropping_receiver_prunes_subscriber_on_next_publish() {
         // This verifies that sending to a dropped receiver doesn't panic and is pruned.
@@ -431,4 +1234,62 @@ mod tests {
 
         // Nothing to assert directly (subs list is private), but reaching here means no deadlock/panic.
     }
+
+    #[test]
+    fn popups_start_out_with_the_session_storage_of_their_opener() {
+        let (mut engine, zone_id, opener) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let page = Url::parse("http://app.test/index").unwrap();
+
+        let (x, y) = {
+            let tab = engine.get_tab(opener).unwrap();
+            let mut tab = tab.lock().unwrap();
+            zone.lock().unwrap().on_tab_commit(&mut tab, &page).unwrap();
+            let session = tab.context.session_storage().unwrap();
+            session.set_item("cart", "3 items").unwrap();
+            tab.context
+                .set_raw_html("<form action=checkout target=_blank><button>Buy</button></form>");
+            tab.context.navigate_to_fragment(page.clone());
+            let (x, y, _, _) = tab.context.forms().controls()[0].rect;
+            (x + 2.0, y + 2.0)
+        };
+        let button = MouseButton::Left;
+        engine
+            .handle_event(opener, EngineEvent::MouseDown { button, x, y })
+            .unwrap();
+        tick(&mut engine);
+        let (request_id, url) = rx
+            .try_iter()
+            .find_map(|n| match n {
+                EngineNotification::PopupRequested {
+                    request_id, url, ..
+                } => Some((request_id, url)),
+                _ => None,
+            })
+            .expect("no popup requested");
+        engine
+            .execute_command(
+                opener,
+                EngineCommand::ResolvePopup {
+                    request_id,
+                    allow: true,
+                    zone_id: None,
+                },
+            )
+            .unwrap();
+        let popup = rx
+            .try_iter()
+            .find_map(|n| match n {
+                EngineNotification::PopupOpened { tab_id, .. } => Some(tab_id),
+                _ => None,
+            })
+            .expect("no popup opened");
+
+        let tab = engine.get_tab(popup).unwrap();
+        let mut tab = tab.lock().unwrap();
+        zone.lock().unwrap().on_tab_commit(&mut tab, &url).unwrap();
+        let session = tab.context.session_storage().unwrap();
+        assert_eq!(session.get_item("cart").as_deref(), Some("3 items"));
+    }
 }
@@ -7,10 +7,12 @@ use crate::engine::storage::types::PartitionKey;
 use crate::tab::TabId;
 use crate::zone::ZoneId;
 
+type AreaKey = (ZoneId, TabId, PartitionKey, url::Origin);
+
 /// In memory storage
 #[derive(Default)]
 pub struct InMemorySessionStore {
-    data: Arc<RwLock<HashMap<(ZoneId, TabId, String, String), HashMap<String, String>>>>,
+    data: Arc<RwLock<HashMap<AreaKey, HashMap<String, String>>>>,
 }
 
 impl InMemorySessionStore {
@@ -28,15 +30,7 @@ impl SessionStore for InMemorySessionStore {
         part: &PartitionKey,
         origin: &url::Origin,
     ) -> Arc<dyn StorageArea> {
-        let k = (
-            zone,
-            tab,
-            match part {
-                PartitionKey::None => "".to_string(),
-                PartitionKey::TopLevel(o) => format!("top:{}", o.ascii_serialization()),
-            },
-            origin.ascii_serialization(),
-        );
+        let k = (zone, tab, part.clone(), origin.clone());
 
         {
             let mut guard = self.data.write().unwrap();
@@ -49,6 +43,15 @@ impl SessionStore for InMemorySessionStore {
         })
     }
 
+    fn list_areas(&self, zone: ZoneId, tab: TabId) -> Vec<(PartitionKey, url::Origin)> {
+        let guard = self.data.read().unwrap();
+        guard
+            .iter()
+            .filter(|((z, t, _, _), items)| *z == zone && *t == tab && !items.is_empty())
+            .map(|((_, _, part, origin), _)| (part.clone(), origin.clone()))
+            .collect()
+    }
+
     fn drop_tab(&self, zone: ZoneId, tab: TabId) {
         let mut guard = self.data.write().unwrap();
         guard.retain(|(z, t, _, _), _| *z != zone || *t != tab);
@@ -56,8 +59,8 @@ impl SessionStore for InMemorySessionStore {
 }
 
 struct SessionArea {
-    data: Arc<RwLock<HashMap<(ZoneId, TabId, String, String), HashMap<String, String>>>>,
-    key: (ZoneId, TabId, String, String),
+    data: Arc<RwLock<HashMap<AreaKey, HashMap<String, String>>>>,
+    key: AreaKey,
 }
 
 impl StorageArea for SessionArea {
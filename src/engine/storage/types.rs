@@ -1,3 +1,4 @@
+use crate::net::{registrable_domain, site};
 use url::{Origin, Url};
 
 /// Partitioning key (future-proof for state partitioning).
@@ -28,7 +29,7 @@ impl PartitionKey {
 }
 
 /// Partitioning policy for determining how to compute the partition key.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PartitionPolicy {
     /// No partitioning, uses a global state.
     None,
@@ -36,11 +37,166 @@ pub enum PartitionPolicy {
     TopLevelOrigin,
 }
 
+/// Groups of related sites that share a partition, such as the domains a company uses for
+/// single sign-on. Sites are registrable domains like `corp.test`; their subdomains belong to
+/// them.
+///
+/// Under [`PartitionPolicy::TopLevelOrigin`], pages of every site in a set are partitioned as
+/// if they were on the set's primary site, and requests between the sites of a set send their
+/// `SameSite` cookies. Configure them for the whole engine with
+/// [`EngineConfig::first_party_sets`](crate::EngineConfig::first_party_sets).
+///
+/// ```
+/// use gosub_engine::storage::FirstPartySets;
+///
+/// let sets = FirstPartySets::new().with_set("corp.test", ["corp-sso.test", "corp-mail.test"]);
+/// assert_eq!(sets.primary_of("login.corp-sso.test"), Some("corp.test"));
+/// assert!(sets.related("mail.corp-mail.test", "corp.test"));
+/// assert!(!sets.related("corp.test", "other.test"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FirstPartySets {
+    sets: Vec<(String, Vec<String>)>,
+}
+
+impl FirstPartySets {
+    /// Creates an empty list of sets, in which every site stands on its own.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a set of `members` around the site `primary`. Domains are lowercased; whether
+    /// they form valid sets is checked when the engine configuration is built.
+    pub fn with_set<S: Into<String>>(
+        mut self,
+        primary: impl Into<String>,
+        members: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let normalize = |d: String| d.trim_end_matches('.').to_ascii_lowercase();
+        let members = members.into_iter().map(|m| normalize(m.into())).collect();
+        self.sets.push((normalize(primary.into()), members));
+        self
+    }
+
+    /// Returns whether no sets are configured.
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// Returns the primary site of the set that `host` belongs to, if any.
+    pub fn primary_of(&self, host: &str) -> Option<&str> {
+        let site = site(host);
+        self.sets
+            .iter()
+            .find(|(primary, members)| primary == site || members.iter().any(|m| m == site))
+            .map(|(primary, _)| primary.as_str())
+    }
+
+    /// Returns whether the hosts `a` and `b` are on sites of the same set.
+    pub fn related(&self, a: &str, b: &str) -> bool {
+        self.primary_of(a)
+            .is_some_and(|primary| self.primary_of(b) == Some(primary))
+    }
+
+    /// Checks that every entry is a registrable domain, that every set has members, and that
+    /// no site is in more than one set. Returns what is wrong otherwise.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        for (primary, members) in &self.sets {
+            if members.is_empty() {
+                return Err(format!("the set of {primary} has no other sites"));
+            }
+            for domain in std::iter::once(primary).chain(members) {
+                let is_domain = !domain.is_empty()
+                    && domain.parse::<std::net::IpAddr>().is_err()
+                    && domain
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.');
+                if !is_domain {
+                    return Err(format!("{domain:?} is not a domain"));
+                }
+                match registrable_domain(domain) {
+                    Some(site) if site == domain => {}
+                    Some(site) => {
+                        return Err(format!(
+                            "{domain} is not a registrable domain, list {site} instead"
+                        ))
+                    }
+                    None => return Err(format!("{domain} is a public suffix")),
+                }
+                if !seen.insert(domain) {
+                    return Err(format!("{domain} is in more than one set"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How the storage of a (sub)resource is partitioned when it is used on a top-level page.
+///
+/// Returned by [`StorageService::explain_partition`](crate::storage::StorageService::explain_partition)
+/// to verify which data a site can see in which context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionExplanation {
+    /// Origin of the resource that accesses the storage
+    pub origin: Origin,
+    /// Origin of the top-level page
+    pub top_level: Origin,
+    /// Policy the partition was computed with
+    pub policy: PartitionPolicy,
+    /// Partition the resource's storage lives in
+    pub partition: PartitionKey,
+    /// Whether the resource is embedded by a page of another origin
+    pub third_party: bool,
+    /// Whether the resource sees the same storage as when its origin is the top-level page.
+    /// Third-party resources should not, when their storage is isolated.
+    pub shared_with_first_party: bool,
+}
+
+impl std::fmt::Display for PartitionExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} on {}: ",
+            self.origin.ascii_serialization(),
+            self.top_level.ascii_serialization()
+        )?;
+        match &self.partition {
+            PartitionKey::None => write!(f, "unpartitioned")?,
+            PartitionKey::TopLevel(top) => {
+                write!(f, "partitioned by {}", top.ascii_serialization())?
+            }
+        }
+        if self.shared_with_first_party {
+            write!(f, ", shared with first-party use")
+        } else {
+            write!(f, ", isolated from first-party use")
+        }
+    }
+}
+
 /// Computes the partition key based on the URL and the specified partition policy.
 pub fn compute_partition_key(u: &Url, p: PartitionPolicy) -> PartitionKey {
+    compute_partition_key_in(u, p, &FirstPartySets::default())
+}
+
+/// Like [`compute_partition_key`], but URLs on a site of one of `sets` are partitioned under
+/// the set's primary site, with the scheme of the URL.
+pub fn compute_partition_key_in(
+    u: &Url,
+    p: PartitionPolicy,
+    sets: &FirstPartySets,
+) -> PartitionKey {
     match p {
         PartitionPolicy::None => PartitionKey::None,
-        PartitionPolicy::TopLevelOrigin => PartitionKey::TopLevel(u.origin()),
+        PartitionPolicy::TopLevelOrigin => {
+            let related = u
+                .host_str()
+                .and_then(|host| sets.primary_of(host))
+                .and_then(|primary| Url::parse(&format!("{}://{primary}/", u.scheme())).ok());
+            PartitionKey::TopLevel(related.as_ref().unwrap_or(u).origin())
+        }
     }
 }
 
@@ -97,6 +253,55 @@ mod tests {
         assert_eq!(pk, PartitionKey::from_str("http://[2001:db8::1]:8080"));
     }
 
+    #[test]
+    fn related_sites_share_the_primary_partition() {
+        let sets = FirstPartySets::new().with_set("Corp.test", ["corp-sso.test"]);
+        assert!(sets.validate().is_ok());
+
+        let sso = Url::parse("https://login.corp-sso.test/auth").unwrap();
+        let key = compute_partition_key_in(&sso, PartitionPolicy::TopLevelOrigin, &sets);
+        assert_eq!(key, PartitionKey::TopLevel(o("https://corp.test")));
+        let other = Url::parse("https://other.test/").unwrap();
+        assert_eq!(
+            compute_partition_key_in(&other, PartitionPolicy::TopLevelOrigin, &sets),
+            PartitionKey::TopLevel(other.origin())
+        );
+        assert_eq!(
+            compute_partition_key_in(&sso, PartitionPolicy::None, &sets),
+            PartitionKey::None
+        );
+
+        let sets = FirstPartySets::new().with_set("a.co.uk", ["a-sso.co.uk"]);
+        assert_eq!(sets.primary_of("login.a-sso.co.uk"), Some("a.co.uk"));
+        assert_eq!(sets.primary_of("b.co.uk"), None);
+
+        let invalid = |sets: FirstPartySets| sets.validate().unwrap_err();
+        assert!(
+            invalid(FirstPartySets::new().with_set("a.test", Vec::<String>::new()))
+                .contains("no other sites")
+        );
+        assert!(
+            invalid(FirstPartySets::new().with_set("a.test", ["www.b.test"]))
+                .contains("list b.test instead")
+        );
+        assert!(
+            invalid(FirstPartySets::new().with_set("a.test", ["10.0.0.1"]))
+                .contains("not a domain")
+        );
+        assert!(
+            invalid(FirstPartySets::new().with_set("a.test", ["www.b.co.uk"]))
+                .contains("list b.co.uk instead")
+        );
+        assert!(invalid(FirstPartySets::new().with_set("a.test", ["co.uk"]))
+            .contains("co.uk is a public suffix"));
+        assert!(invalid(
+            FirstPartySets::new()
+                .with_set("a.test", ["b.test"])
+                .with_set("c.test", ["b.test"])
+        )
+        .contains("b.test is in more than one set"));
+    }
+
     #[test]
     fn partitionkey_equality_and_hash_semantics() {
         use std::collections::HashSet;
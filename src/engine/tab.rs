@@ -10,8 +10,9 @@
 //!
 //! 1. `Idle` → user action
 //! 2. `PendingLoad(url)` → start network → `Loading`
-//! 3. `Loading` → on success: `Loaded` (and set raw HTML) / on error: `Failed`
-//! 4. `Loaded` → `PendingRendering(viewport)` → `Rendering` → `Rendered` → `Idle`
+//! 3. `Loading` → on success: `Parsing` / on error: `Failed`
+//! 4. `Parsing` → the document is parsed on the blocking pool → `Loaded` (and set raw HTML)
+//! 5. `Loaded` → `PendingRendering(viewport)` → `Rendering` → `Rendered` → `Idle`
 //!
 //! The engine calls `tick()` regularly (e.g., each frame or via a scheduler).
 //! `tick()` returns a [`TickResult`] indicating whether
@@ -45,21 +46,46 @@
 //! }
 //! ```
 
-use crate::engine::cookies::CookieJarHandle;
+use crate::engine::cookies::{CookieJarHandle, DefaultCookieJar};
+use crate::engine::forms::{FormControlKind, FormMethod, FormOutcome, FormSubmission};
+use crate::engine::gesture::{Gesture, GestureRecognizer};
+use crate::engine::har::NetworkLog;
+use crate::engine::history::SessionHistory;
+use crate::engine::logging::engine_log;
 use crate::engine::storage::types::PartitionPolicy;
 use crate::engine::storage::{PartitionKey, StorageEvent, StorageHandles};
-use crate::engine::tick::TickResult;
-use crate::engine::zone::ZoneId;
-use crate::engine::BrowsingContext;
+use crate::engine::tick::{
+    FrameScheduler, FrameStats, JankStats, TickResult, DEFAULT_FRAME_BUDGET,
+};
+use crate::engine::zone::{
+    best_fit, favicon_links, ExtensionId, Favicon, FaviconLink, FaviconStore, SiteSettingsStore,
+    UrlResolver, UserContent, ZoneConfig, ZoneId,
+};
+use crate::engine::{coalesce_into, BrowsingContext, NotificationBus};
+use crate::net::{
+    check_sandbox, decode_data_url, mime, resource_hints, BlockDecision, ContentBlocker, HttpCache,
+    LoadingPolicy, RequestTimeouts, RequestType, ResourceHint, ResourcePriority, ResourceScheduler,
+    Response, RetryPolicy, SandboxViolation, TimeoutKind,
+};
 use crate::render::backend::{
     CompositorSink, ErasedSurface, PresentMode, RenderBackend, RgbaImage, SurfaceSize,
 };
-use crate::render::Viewport;
-use crate::{EngineCommand, EngineEvent};
-use serde::__private::from_utf8_lossy;
-use std::sync::Arc;
-use std::time::Instant;
+use crate::render::{PrintError, PrintToPdf, RenderMode, Viewport};
+use crate::{
+    DragPayload, DropEffect, EngineCommand, EngineConfig, EngineEvent, EngineNotification,
+    KeyModifiers, MouseButton, NavigationDisposition,
+};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE, CONTENT_TYPE};
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use url::Url;
 use uuid::Uuid;
 
@@ -82,6 +108,7 @@ use uuid::Uuid;
 /// and user code to unambiguously reference and operate on a specific tab,
 /// even if tabs are opened or closed dynamically.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
 pub struct TabId(Uuid);
 
 impl TabId {
@@ -91,6 +118,121 @@ impl TabId {
     }
 }
 
+/// Identifies an auxiliary viewport of a tab. Chosen by the user agent, unique per tab.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuxViewportId(pub u32);
+
+/// Identifies a request of a page to open a new tab, see
+/// [`EngineNotification::PopupRequested`]. Unique within the process.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct PopupRequestId(u64);
+
+impl PopupRequestId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Identifies a request of a page for files to upload, see
+/// [`EngineNotification::FileChooserRequested`]. Unique within the process.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileChooserRequestId(u64);
+
+impl FileChooserRequestId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Per-tab exceptions to zone settings, for user agents that let users change them per site.
+///
+/// `None` means the tab follows the zone's [`ZoneConfig`](crate::zone::ZoneConfig). Set them
+/// with [`Tab::set_overrides`] or [`EngineCommand::SetOverrides`]; they apply to requests
+/// started afterward.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct TabOverrides {
+    /// Whether to send `DNT: 1`
+    pub do_not_track: Option<bool>,
+    /// Whether to send `Sec-GPC: 1`
+    pub global_privacy_control: Option<bool>,
+    /// How the tab uses the HTTP cache
+    pub cache_mode: TabCacheMode,
+    /// Which cookie jar the tab's loads read and write
+    pub cookie_jar: TabCookieJar,
+    /// `Accept-Language` header to send
+    pub accept_languages: Option<String>,
+    /// Language tag pages see, such as `fr-CA`
+    pub locale: Option<String>,
+    /// IANA time zone pages see, such as `America/Toronto`
+    pub timezone: Option<String>,
+}
+
+/// How a tab uses the HTTP cache. None of the modes has an effect when the zone config
+/// disables caching.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub enum TabCacheMode {
+    /// Read and write the zone's cache
+    #[default]
+    Default,
+    /// Use a cache of the tab's own, which is dropped with the tab. Nothing the tab loads ends
+    /// up in the zone's cache.
+    Ephemeral,
+    /// Always load from the network, and cache nothing
+    Bypass,
+}
+
+/// Which cookie jar a tab uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub enum TabCookieJar {
+    /// Share the zone's jar with its other tabs
+    #[default]
+    Zone,
+    /// Use a jar of the tab's own, which starts out empty and is dropped with the tab. Cookies
+    /// the tab receives never reach the zone's jar, and no zone cookies are sent.
+    Ephemeral,
+}
+
+/// Largest dimension of the thumbnail taken when a tab is discarded
+const THUMBNAIL_MAX_DIM: u32 = 256;
+
+/// Size in pixels of the favicon a tab shows, which suits tab strips on high-DPI screens
+const TAB_FAVICON_SIZE: u32 = 32;
+
+/// Shortest time between two ticks of a tab that cannot be seen, see
+/// [`EngineCommand::SetVisibility`]
+const OFF_SCREEN_TICK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most popup requests a tab keeps waiting for the user agent; older ones are dropped
+const MAX_PENDING_POPUPS: usize = 8;
+
+/// Most input events a tab queues between two ticks; older ones are dropped, so a tab that
+/// does not tick (frozen, off-screen or hung) does not grow its queue without bound
+const MAX_PENDING_INPUT: usize = 256;
+
+/// A request for a resource of the current document.
+enum Subresource {
+    /// The icon shown for the page, with the URL of the page
+    Favicon(Url, FaviconLink),
+    /// A connection asked for with `<link rel="preconnect">`
+    Preconnect(Url),
+    /// A host name to resolve, asked for with `<link rel="dns-prefetch">`
+    DnsPrefetch(Url),
+}
+
+/// An additional surface the tab renders into (see [`EngineCommand::AddAuxViewport`]).
+struct AuxViewport {
+    viewport: Viewport,
+    surface: Option<Box<dyn ErasedSurface>>,
+}
+
 /// Current state of the tab. This is a state machine that defines what the tab is doing at the moment.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum TabState {
@@ -103,9 +245,14 @@ pub enum TabState {
     PendingLoad(Url),
 
     /// The tab is fetching network resources (main document).
-    /// When done, transitions to [`TabState::Loaded`] on success or [`TabState::Failed`] on error.
+    /// When done, transitions to [`TabState::Parsing`] on success or [`TabState::Failed`] on error.
     Loading,
 
+    /// The main document was received and is being parsed off the tab's task. The tab keeps
+    /// handling input and commands meanwhile; a new navigation cancels the parse.
+    /// When done, transitions to [`TabState::Loaded`].
+    Parsing,
+
     /// Main document has been received and staged into the engine.
     /// The next `tick()` will begin rendering via [`TabState::PendingRendering`].
     Loaded,
@@ -124,8 +271,81 @@ pub enum TabState {
     Failed(String),
 }
 
+/// The user-visible state of a tab, as shown in a tab strip or address bar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TabPublicState {
+    /// URL of the committed page, if any
+    pub url: Option<Url>,
+    /// Title of the page
+    pub title: String,
+    /// True while a navigation is in progress
+    pub is_loading: bool,
+    /// Progress of the current navigation, from 0.0 to 1.0. Documents are not streamed yet,
+    /// so this is 0.0 while loading and 1.0 otherwise.
+    pub progress: f32,
+    /// True when the tab has a previous history entry to go back to
+    pub can_go_back: bool,
+    /// True when the tab has a next history entry to go forward to
+    pub can_go_forward: bool,
+    /// Hash of the favicon data, or `None` when the page has no favicon. Compare it with the
+    /// previous value to find out whether the favicon must be fetched again.
+    pub favicon_hash: Option<u64>,
+    /// Number of requests the zone's content blocker refused since the page was loaded
+    pub blocked_requests: u32,
+    /// Number of problems the parser recovered from in the page, see
+    /// [`BrowsingContext::parse_issues`]
+    pub parse_issues: u32,
+    /// True when the tab is pinned
+    pub pinned: bool,
+}
+
+/// Cheap, cloneable read access to a tab's [`TabPublicState`].
+///
+/// Reading does not lock the engine, the zone or the tab, so a UI thread can call it every
+/// frame. Obtain one with [`GosubEngine::tab_state`](crate::GosubEngine::tab_state). The
+/// state is updated at the end of every tick of the tab.
+#[derive(Debug, Clone)]
+pub struct TabStateHandle {
+    rx: watch::Receiver<TabPublicState>,
+}
+
+impl TabStateHandle {
+    /// Returns a copy of the latest state.
+    pub fn get(&self) -> TabPublicState {
+        self.rx.borrow().clone()
+    }
+
+    /// Returns the URL of the committed page.
+    pub fn url(&self) -> Option<Url> {
+        self.rx.borrow().url.clone()
+    }
+
+    /// Returns the title of the page.
+    pub fn title(&self) -> String {
+        self.rx.borrow().title.clone()
+    }
+
+    /// Returns true while a navigation is in progress.
+    pub fn is_loading(&self) -> bool {
+        self.rx.borrow().is_loading
+    }
+
+    /// Returns a `watch` receiver for the state.
+    ///
+    /// Use it to wait for changes (`changed().await`) instead of polling. Like the handle, it
+    /// always holds the latest state, so a slow reader skips intermediate states rather than
+    /// falling behind.
+    pub fn state_watch(&self) -> watch::Receiver<TabPublicState> {
+        self.rx.clone()
+    }
+}
+
 /// Activity mode for a [`Tab`]. Schedulers can allocate CPU/time by mode.
+///
+/// Only tabs that are not [`TabMode::Active`] can be discarded when the engine runs low on
+/// memory (see [`EngineConfig::tab_memory_limit_bytes`](crate::EngineConfig::tab_memory_limit_bytes)).
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
 pub enum TabMode {
     /// Foreground: fully active (network, layout, paint, animations ~60 Hz).
     Active,
@@ -166,6 +386,10 @@ pub struct Tab {
     pub mode: TabMode,
     /// When was the last tick?
     pub last_tick: Instant,
+    /// When the tab was last active or received input
+    last_active: Instant,
+    /// Set when the document and surfaces were dropped to save memory
+    discarded: bool,
 
     /// Favicon binary data for the current tab
     pub favicon: Vec<u8>,
@@ -181,7 +405,8 @@ pub struct Tab {
     /// Is there an error in the current tab?
     pub is_error: bool,
 
-    /// Cookie jar for this tab. This is shared with the rest of the zone tabs
+    /// Cookie jar for this tab. This is shared with the rest of the zone tabs. Loads use
+    /// a jar of their own instead in [`TabCookieJar::Ephemeral`].
     pub cookie_jar: Option<CookieJarHandle>,
 
     /// Storage partition key
@@ -194,6 +419,8 @@ pub struct Tab {
     surface: Option<Box<dyn ErasedSurface>>, // Surface on which the browsing context can render the tab
     surface_size: SurfaceSize, // Size of the surface (does not have to match viewport)
     present_mode: PresentMode, // Present mode for the surface?
+    /// Whether the backend paints the tab or the host paints its render lists
+    render_mode: RenderMode,
 
     /// The viewport that was committed for the in-flight/last render
     committed_viewport: Viewport,
@@ -201,6 +428,121 @@ pub struct Tab {
     desired_viewport: Viewport,
     /// Set when a resize arrives while rendering. Causes an immediate re-render after finihsing the current rendering.
     dirty_after_inflight: bool,
+    /// Measures frames against the frame budget
+    frame_scheduler: FrameScheduler,
+    /// Set when the thumbnail no longer shows the current document
+    thumbnail_stale: bool,
+    /// Set by the engine when the tab has to wait for a later tick to start its next frame
+    frame_held: bool,
+    /// Set when auxiliary viewports were skipped because a frame ran over budget
+    aux_deferred: bool,
+    /// Viewport and scene epoch of the frame on the main surface, used to serve scrolls from it
+    last_frame: Option<(Viewport, u64)>,
+
+    /// Bus on which the tab reports warnings (e.g. sandbox violations)
+    notifications: Arc<NotificationBus>,
+
+    /// Input received since the last tick, coalesced. At most [`MAX_PENDING_INPUT`].
+    pending_input: Vec<EngineEvent>,
+    /// Payload of the drag over the tab and what dropping it would do
+    drag: Option<(DragPayload, DropEffect)>,
+    /// Whether files can be dropped onto the tab, from the zone's configuration
+    local_file_access: bool,
+    /// Turns touch input into taps, pans and pinches
+    gestures: GestureRecognizer,
+
+    /// Publishes the user-visible state to [`TabStateHandle`]s
+    public_state: watch::Sender<TabPublicState>,
+    /// Back/forward list
+    history: SessionHistory,
+    /// Form submission to POST when the pending load starts
+    pending_submission: Option<FormSubmission>,
+    /// Content provided by the user agent for the pending load
+    pending_content: Option<Response>,
+    /// Final URL of the document being parsed
+    parsing_url: Option<Url>,
+    /// Whether messages of the document are published, see
+    /// [`EngineCommand::EnableConsoleCapture`]
+    console_capture: bool,
+    /// Pinned tabs are never discarded, see [`EngineCommand::SetPinned`]
+    pinned: bool,
+    /// Whether the user agent shows the tab, see [`EngineCommand::SetVisibility`]
+    visible: bool,
+    /// Whether the tab is shown but covered, see [`EngineCommand::SetVisibility`]
+    occluded: bool,
+    /// Tab whose page opened this one, see [`Tab::opener`]
+    opener: Option<TabId>,
+    /// Loads the page asked to open in a new tab, until the user agent resolves them or the
+    /// tab navigates away. At most [`MAX_PENDING_POPUPS`].
+    pending_popups: Vec<(PopupRequestId, FormSubmission)>,
+    /// File inputs of the current document waiting for the user agent to choose files
+    pending_file_choosers: Vec<(FileChooserRequestId, usize)>,
+    /// Modifier keys currently held down, for the disposition of clicks
+    modifiers: KeyModifiers,
+    /// The zone's HTTP cache, unless the zone disables caching
+    zone_cache: Option<Arc<HttpCache>>,
+    /// Cache of the tab in [`TabCacheMode::Ephemeral`], created on first use
+    ephemeral_cache: Option<Arc<HttpCache>>,
+    /// Cookie jar of the tab in [`TabCookieJar::Ephemeral`], created on first use
+    ephemeral_cookie_jar: Option<CookieJarHandle>,
+    /// Page that started the pending load, for `SameSite` cookies; `None` when the user did
+    load_initiator: Option<Url>,
+    /// Whether the response of the load in flight should be cached
+    cache_response: bool,
+    /// Set by a reload, which should not be served from the cache
+    reloading: bool,
+    /// How the zone retries loads that fail on a flaky network, if at all
+    retry_policy: Option<RetryPolicy>,
+    /// Whether the load in flight is a GET navigation, which is safe to retry
+    retryable: bool,
+    /// Number of times the load in flight was retried
+    load_retries: u32,
+    /// When the load in flight is retried, while waiting for its backoff
+    retry_at: Option<Instant>,
+    /// Whether the host is online, shared with the engine
+    online: Arc<AtomicBool>,
+    /// Set when the tab is killed, from any thread. Checked between the stages of the
+    /// pipeline, including the parse on the blocking pool
+    cancel: Arc<AtomicBool>,
+    /// Requests made for the latest navigation, for [`EngineCommand::ExportHar`]
+    network_log: NetworkLog,
+    /// User styles and scripts of the zone, applied to each committed document
+    user_content: Arc<RwLock<UserContent>>,
+    /// Turns typed input into URLs, shared with the zone
+    url_resolver: Arc<UrlResolver>,
+    /// `DNT` and `Sec-GPC` settings of the zone
+    zone_privacy: TabOverrides,
+    /// `User-Agent` and client hints of the zone
+    identity_headers: HeaderMap,
+    /// Languages, locale and time zone of the zone
+    zone_locale: TabOverrides,
+    /// Per-tab exceptions to the zone settings
+    overrides: TabOverrides,
+    /// Per-origin exceptions to the zone config, applied to each committed document
+    site_settings: Arc<SiteSettingsStore>,
+    /// Favicon cache of the zone, filled with the icons of committed documents
+    favicons: Arc<FaviconStore>,
+    /// Page and icon of the favicon fetch in flight
+    pending_favicon: Option<(Url, FaviconLink)>,
+    /// Requests for the resources of the current document, ordered by the zone's loading
+    /// policy
+    subresources: ResourceScheduler<Subresource>,
+    /// Preconnects and DNS prefetches taken from `subresources` that are still running
+    hint_tasks: Vec<JoinHandle<()>>,
+    /// Whether the zone config enables JavaScript and images
+    zone_content_settings: (bool, bool),
+    /// Filter lists of the zone, checked before each request
+    content_blocker: Option<Arc<ContentBlocker>>,
+    /// Requests blocked since the current page was loaded
+    blocked_requests: u32,
+
+    /// Additional viewports rendered together with the main one
+    aux_viewports: BTreeMap<AuxViewportId, AuxViewport>,
+    /// Auxiliary viewports rendered in the in-flight render
+    aux_rendered: Vec<AuxViewportId>,
+
+    /// Data the user agent attached to the tab, see [`Tab::set_user_data`]
+    user_data: Option<Arc<dyn Any + Send + Sync>>,
 }
 
 impl Tab {
@@ -232,6 +574,8 @@ impl Tab {
 
             mode: TabMode::Active, // Default mode is active
             last_tick: Instant::now(),
+            last_active: Instant::now(),
+            discarded: false,
 
             cookie_jar,
             partition_key: PartitionKey::None, // Start with no partition key
@@ -243,32 +587,503 @@ impl Tab {
                 height: 1,
             },
             present_mode: PresentMode::Fifo,
+            render_mode: RenderMode::default(),
             thumbnail: None, // No thumbnail initially
 
             committed_viewport: viewport,
             desired_viewport: viewport,
             dirty_after_inflight: false,
+            frame_scheduler: FrameScheduler::new(DEFAULT_FRAME_BUDGET),
+            thumbnail_stale: false,
+            frame_held: false,
+            aux_deferred: false,
+            last_frame: None,
+            drag: None,
+            local_file_access: false,
+
+            notifications: Arc::new(NotificationBus::default()),
+
+            pending_input: Vec::new(),
+            gestures: GestureRecognizer::default(),
+
+            public_state: watch::Sender::new(TabPublicState::default()),
+            history: SessionHistory::default(),
+            pending_submission: None,
+            pending_content: None,
+            parsing_url: None,
+            console_capture: false,
+            pinned: false,
+            visible: true,
+            occluded: false,
+            opener: None,
+            pending_popups: Vec::new(),
+            pending_file_choosers: Vec::new(),
+            modifiers: KeyModifiers::default(),
+            zone_cache: None,
+            ephemeral_cache: None,
+            ephemeral_cookie_jar: None,
+            load_initiator: None,
+            cache_response: false,
+            reloading: false,
+            retry_policy: None,
+            retryable: false,
+            load_retries: 0,
+            retry_at: None,
+            online: Arc::new(AtomicBool::new(true)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            network_log: NetworkLog::default(),
+            user_content: Arc::new(RwLock::new(UserContent::default())),
+            url_resolver: Arc::new(UrlResolver::new()),
+            zone_privacy: TabOverrides::default(),
+            identity_headers: HeaderMap::new(),
+            zone_locale: TabOverrides::default(),
+            overrides: TabOverrides::default(),
+            site_settings: Arc::new(SiteSettingsStore::in_memory()),
+            favicons: Arc::new(FaviconStore::in_memory()),
+            pending_favicon: None,
+            subresources: ResourceScheduler::new(
+                LoadingPolicy::default(),
+                EngineConfig::default().io_concurrency,
+            ),
+            hint_tasks: Vec::new(),
+            zone_content_settings: (true, true),
+            content_blocker: None,
+            blocked_requests: 0,
+
+            aux_viewports: BTreeMap::new(),
+            aux_rendered: Vec::new(),
+
+            user_data: None,
         };
 
         tab.context.set_viewport(viewport);
+        tab.context.set_cancel_flag(tab.cancel.clone());
+        tab.publish_state();
 
         tab
     }
 
-    /// Navigate to a URL (string is parsed into a `Url`). On success, moves the
-    /// tab to [`TabState::PendingLoad`]. Invalid URLs are ignored and logged.
-    pub fn navigate_to(&mut self, url: impl Into<String>) {
-        let url = match Url::parse(&url.into()) {
+    /// Returns a handle for reading the tab's user-visible state without locking the tab.
+    pub fn state_handle(&self) -> TabStateHandle {
+        TabStateHandle {
+            rx: self.public_state.subscribe(),
+        }
+    }
+
+    /// Publishes the current user-visible state if it changed.
+    fn publish_state(&self) {
+        let favicon_hash = (!self.favicon.is_empty()).then(|| {
+            let mut hasher = DefaultHasher::new();
+            self.favicon.hash(&mut hasher);
+            hasher.finish()
+        });
+        let state = TabPublicState {
+            url: self.current_url.clone(),
+            title: self.title.clone(),
+            is_loading: self.is_loading,
+            progress: if self.is_loading { 0.0 } else { 1.0 },
+            can_go_back: self.history.can_go_back(),
+            can_go_forward: self.history.can_go_forward(),
+            favicon_hash,
+            blocked_requests: self.blocked_requests,
+            parse_issues: self.context.parse_issues().len() as u32,
+            pinned: self.pinned,
+        };
+
+        self.public_state.send_if_modified(|current| {
+            if *current == state {
+                return false;
+            }
+            *current = state;
+            true
+        });
+    }
+
+    /// Navigates to typed input, such as the text of an address bar. The input is resolved
+    /// with the zone's [`UrlResolver`]; see [`EngineCommand::NavigateInput`]. On success,
+    /// moves the tab to [`TabState::PendingLoad`].
+    pub fn navigate_to(&mut self, input: impl Into<String>) {
+        let input = input.into();
+        let url = match self.url_resolver.resolve(&input) {
             Ok(url) => url,
             Err(e) => {
-                // Can't parse string to a URL to load
-                log::error!("Tab[{:?}]: Cannot parse URL: {}", self.id, e);
+                self.notify(EngineNotification::NavigationFailed {
+                    zone_id: self.zone_id,
+                    tab_id: self.id,
+                    input,
+                    error: e.to_string(),
+                });
                 return;
             }
         };
 
-        self.state = TabState::PendingLoad(url.into());
-        self.is_loading = true;
+        self.history.cancel_pending();
+        self.request_load(url);
+    }
+
+    /// Connects the tab to the engine's notification bus.
+    pub(crate) fn bind_notifications(&mut self, bus: Arc<NotificationBus>) {
+        self.notifications = bus;
+    }
+
+    /// Sends a message from a content script of `extension_id` to the user agent, as an
+    /// [`EngineNotification::ExtensionMessage`]. Returns false (and sends nothing) when the
+    /// extension has no content script in the current document.
+    pub fn post_extension_message(&self, extension_id: ExtensionId, message: String) -> bool {
+        if !self.context.has_content_script(&extension_id) {
+            return false;
+        }
+        self.notify(EngineNotification::ExtensionMessage {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            extension_id,
+            message,
+        });
+        true
+    }
+
+    /// Shares the zone's URL resolver with the tab.
+    pub(crate) fn bind_url_resolver(&mut self, resolver: Arc<UrlResolver>) {
+        self.url_resolver = resolver;
+    }
+
+    /// Shares the zone's user styles and scripts with the tab.
+    pub(crate) fn bind_user_content(&mut self, user_content: Arc<RwLock<UserContent>>) {
+        self.user_content = user_content;
+    }
+
+    /// Applies the zone's `DNT` and `Sec-GPC` settings. Tab overrides take precedence.
+    pub(crate) fn bind_privacy(&mut self, do_not_track: bool, global_privacy_control: bool) {
+        self.zone_privacy = TabOverrides {
+            do_not_track: Some(do_not_track),
+            global_privacy_control: Some(global_privacy_control),
+            ..Default::default()
+        };
+    }
+
+    /// Sets the `User-Agent` and client hint headers of the zone.
+    pub(crate) fn bind_identity(&mut self, headers: HeaderMap) {
+        self.identity_headers = headers;
+    }
+
+    /// Applies the zone's `Accept-Language`, locale and time zone. Tab overrides take
+    /// precedence.
+    pub(crate) fn bind_locale(&mut self, config: &ZoneConfig) {
+        self.zone_locale = TabOverrides {
+            accept_languages: config.accept_languages.clone(),
+            locale: config.locale.clone(),
+            timezone: config.timezone.clone(),
+            ..Default::default()
+        };
+    }
+
+    /// Returns the locale and time zone that pages of the tab see, with the tab overrides
+    /// taking precedence over the zone config. `None` means the host's.
+    pub fn locale(&self) -> (Option<&str>, Option<&str>) {
+        let (tab, zone) = (&self.overrides, &self.zone_locale);
+        (
+            tab.locale.as_deref().or(zone.locale.as_deref()),
+            tab.timezone.as_deref().or(zone.timezone.as_deref()),
+        )
+    }
+
+    /// Returns the tab's exceptions to the zone settings.
+    pub fn overrides(&self) -> &TabOverrides {
+        &self.overrides
+    }
+
+    /// Replaces the tab's exceptions to the zone settings. They apply to requests started
+    /// afterward.
+    pub fn set_overrides(&mut self, overrides: TabOverrides) {
+        self.overrides = overrides;
+    }
+
+    /// Headers that the zone settings and tab overrides add to every request.
+    fn request_headers(&self) -> HeaderMap {
+        let (tab, zone) = (&self.overrides, &self.zone_privacy);
+        let dnt = tab.do_not_track.or(zone.do_not_track);
+        let gpc = tab.global_privacy_control.or(zone.global_privacy_control);
+
+        let mut headers = self.identity_headers.clone();
+        if dnt == Some(true) {
+            headers.insert("dnt", HeaderValue::from_static("1"));
+        }
+        if gpc == Some(true) {
+            headers.insert("sec-gpc", HeaderValue::from_static("1"));
+        }
+        let languages = tab
+            .accept_languages
+            .clone()
+            .or_else(|| self.zone_locale.accept_languages.clone())
+            .or_else(|| self.locale().0.map(accept_language_for));
+        if let Some(value) = languages.and_then(|l| HeaderValue::from_str(&l).ok()) {
+            headers.insert(ACCEPT_LANGUAGE, value);
+        }
+        headers
+    }
+
+    /// Shares the zone's site settings with the tab. `config` provides the defaults they
+    /// deviate from.
+    pub(crate) fn bind_site_settings(
+        &mut self,
+        store: Arc<SiteSettingsStore>,
+        config: &ZoneConfig,
+    ) {
+        self.site_settings = store;
+        self.zone_content_settings = (config.javascript_enabled, config.images_enabled);
+    }
+
+    /// Applies the settings of the site of a newly committed document at `url`.
+    fn apply_site_settings(&mut self, url: &Url) {
+        let site = self.site_settings.get(url).unwrap_or_default();
+        let (javascript, images) = self.zone_content_settings;
+        self.context.set_content_settings(
+            site.javascript.unwrap_or(javascript),
+            site.images.unwrap_or(images),
+        );
+        if let Some(zoom) = site.zoom {
+            self.context.set_zoom(zoom);
+        }
+    }
+
+    /// Shares the zone's favicon cache with the tab.
+    pub(crate) fn bind_favicons(&mut self, store: Arc<FaviconStore>) {
+        self.favicons = store;
+    }
+
+    /// Shows the icon of the newly committed document at `url`: the cached copy when the
+    /// zone has one, otherwise it is fetched and picked up by [`Tab::poll_favicon`].
+    fn discover_favicon(&mut self, url: &Url) {
+        self.favicon.clear();
+        self.pending_favicon = None;
+        if !is_network_url(url) {
+            return;
+        }
+        let links = favicon_links(self.context.raw_html(), url);
+        let Some(link) = best_fit(links, TAB_FAVICON_SIZE, |l| l.size) else {
+            return;
+        };
+        if let Some(cached) = self
+            .favicons
+            .icons(url)
+            .into_iter()
+            .find(|i| i.url == link.url)
+        {
+            self.favicon = cached.data;
+        } else if self.online.load(Ordering::Relaxed) {
+            self.subresources.push(
+                ResourcePriority::Image,
+                Subresource::Favicon(url.clone(), link),
+            );
+        }
+    }
+
+    /// Takes the favicon fetched for the current document, if it arrived, and stores it in
+    /// the zone's cache.
+    fn poll_favicon(&mut self) {
+        let Some(done) = self.context.poll_favicon() else {
+            return;
+        };
+        self.subresources.finish();
+        let Some((page, link)) = self.pending_favicon.take() else {
+            return;
+        };
+        let icon = match done {
+            Ok(resp) if (200..300).contains(&resp.status) && !resp.body.is_empty() => {
+                favicon_mime(&resp).map(|mime| Favicon {
+                    url: link.url.clone(),
+                    mime,
+                    size: link.size,
+                    data: resp.body,
+                })
+            }
+            _ => None,
+        };
+        let Some(icon) = icon else {
+            engine_log!(Debug, "Tab[{:?}]: no favicon at {}", self.id, link.url);
+            return;
+        };
+        self.favicon = icon.data.clone();
+        self.favicons.put(&page, icon);
+    }
+
+    /// Opens a connection to the origin of `url` ahead of time, unless the tab is offline or
+    /// the content blocker would refuse requests to it.
+    fn preconnect(&mut self, url: Url) -> Option<JoinHandle<()>> {
+        if !is_network_url(&url)
+            || !self.online.load(Ordering::Relaxed)
+            || self.blocking_rule(&url).is_some()
+        {
+            return None;
+        }
+        self.context.preconnect(url)
+    }
+
+    /// Queues the `<link rel="preconnect">` and `<link rel="dns-prefetch">` hints of the newly
+    /// committed document at `url`.
+    fn apply_resource_hints(&mut self, url: &Url) {
+        for hint in resource_hints(self.context.raw_html(), url) {
+            let request = match hint {
+                ResourceHint::Preconnect(origin) => Subresource::Preconnect(origin),
+                ResourceHint::DnsPrefetch(host) => Subresource::DnsPrefetch(host),
+            };
+            self.subresources.push(ResourcePriority::Prefetch, request);
+        }
+    }
+
+    /// Applies the zone's loading policy. `io_concurrency` limits the requests in flight when
+    /// the policy does not.
+    pub(crate) fn bind_loading_policy(&mut self, policy: LoadingPolicy, io_concurrency: usize) {
+        self.subresources.configure(policy, io_concurrency);
+    }
+
+    /// Starts the queued resource requests that the loading policy allows to go out now.
+    fn load_subresources(&mut self) {
+        let running = self.hint_tasks.len();
+        self.hint_tasks.retain(|task| !task.is_finished());
+        for _ in self.hint_tasks.len()..running {
+            self.subresources.finish();
+        }
+
+        while let Some(request) = self.subresources.pop() {
+            let task = match request {
+                Subresource::Favicon(page, link) => {
+                    self.context.start_favicon_fetch(link.url.clone());
+                    self.pending_favicon = Some((page, link));
+                    continue;
+                }
+                Subresource::Preconnect(origin) => self.preconnect(origin),
+                Subresource::DnsPrefetch(host) => Some(self.context.prefetch_dns(host)),
+            };
+            match task {
+                Some(task) => self.hint_tasks.push(task),
+                None => self.subresources.finish(),
+            }
+        }
+    }
+
+    /// Forgets the resource requests of the previous document. Hints that are already running
+    /// are left to finish, as they may still help the next navigation.
+    fn clear_subresources(&mut self) {
+        self.subresources.clear();
+        self.hint_tasks.clear();
+        self.pending_favicon = None;
+    }
+
+    /// Sets (or with `None`, removes) the content blocker that filters the tab's requests.
+    pub(crate) fn bind_content_blocker(&mut self, blocker: Option<Arc<ContentBlocker>>) {
+        self.content_blocker = blocker;
+    }
+
+    /// Shares the zone's HTTP cache with the tab, or with `None` disables caching.
+    pub(crate) fn bind_cache(&mut self, cache: Option<Arc<HttpCache>>) {
+        self.zone_cache = cache;
+        self.ephemeral_cache = None;
+    }
+
+    /// Returns the cache the tab's loads use under its current cache mode, if any.
+    fn http_cache(&mut self) -> Option<Arc<HttpCache>> {
+        let zone_cache = self.zone_cache.as_ref()?;
+        match self.overrides.cache_mode {
+            TabCacheMode::Default => Some(zone_cache.clone()),
+            TabCacheMode::Ephemeral => {
+                let capacity = zone_cache.capacity();
+                Some(
+                    self.ephemeral_cache
+                        .get_or_insert_with(|| Arc::new(HttpCache::new(capacity)))
+                        .clone(),
+                )
+            }
+            TabCacheMode::Bypass => None,
+        }
+    }
+
+    /// Returns the cookie jar the tab's loads use under its current overrides, if any. Zone
+    /// tabs without a jar keep no cookies.
+    pub(crate) fn effective_cookie_jar(&mut self) -> Option<CookieJarHandle> {
+        match self.overrides.cookie_jar {
+            TabCookieJar::Zone => self.cookie_jar.clone(),
+            TabCookieJar::Ephemeral => Some(
+                self.ephemeral_cookie_jar
+                    .get_or_insert_with(|| Arc::new(RwLock::new(DefaultCookieJar::new())))
+                    .clone(),
+            ),
+        }
+    }
+
+    /// Starts loading `url` from the cache when a fresh copy is available (and `revalidate` is
+    /// not set), and from the network otherwise.
+    fn start_network_load(&mut self, url: Url, revalidate: bool) -> Result<(), SandboxViolation> {
+        let cache = self.http_cache();
+        // URLs the sandbox refuses fall through to the network path, which reports the violation
+        let allowed = check_sandbox(&url, self.context.sandbox_mode()).is_ok();
+        let headers = self.context.request_headers().clone();
+        if !revalidate && allowed {
+            if let Some(cached) = cache.as_ref().and_then(|c| c.get(&url)) {
+                self.network_log
+                    .request_started("GET", &url, &headers, 0, true);
+                self.context.start_with_response(cached);
+                return Ok(());
+            }
+        }
+        self.cache_response = cache.is_some();
+        self.network_log
+            .request_started("GET", &url, &headers, 0, false);
+        self.context.start_loading(url)
+    }
+
+    /// Starts loading `url` while the host is offline: a fresh copy from the cache is shown
+    /// with a warning, anything else fails right away with [`OFFLINE_ERROR`].
+    fn start_offline_load(&mut self, url: &Url) -> Result<(), String> {
+        let cached = self.http_cache().and_then(|cache| cache.get(url));
+        let Some(cached) = cached else {
+            return Err(OFFLINE_ERROR.to_string());
+        };
+        self.warn(format!("Offline, showing a cached copy of {url}"));
+        self.context.start_with_response(cached);
+        Ok(())
+    }
+
+    /// Shares the engine's knowledge of whether the host is online with the tab.
+    pub(crate) fn bind_network_state(&mut self, online: Arc<AtomicBool>) {
+        self.online = online;
+    }
+
+    /// Checks a navigation to `url` against the content blocker. When it is blocked, counts it,
+    /// publishes [`EngineNotification::RequestBlocked`] and returns the matching rule.
+    fn blocking_rule(&mut self, url: &Url) -> Option<String> {
+        let blocker = self.content_blocker.as_ref()?;
+        let BlockDecision::Block { rule } =
+            blocker.check(url, self.current_url.as_ref(), RequestType::Document)
+        else {
+            return None;
+        };
+
+        self.blocked_requests += 1;
+        engine_log!(Info, "Tab[{:?}]: {} blocked by rule {}", self.id, url, rule);
+        self.notify(EngineNotification::RequestBlocked {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            url: url.clone(),
+            rule: rule.clone(),
+            blocked_count: self.blocked_requests,
+        });
+        Some(rule)
+    }
+
+    /// Publishes a notification for the user agent.
+    fn notify(&self, notification: EngineNotification) {
+        self.notifications.publish(notification);
+    }
+
+    /// Logs a warning and publishes it as an [`EngineNotification::Warning`].
+    fn warn(&self, message: String) {
+        engine_log!(Warn, "Tab[{:?}]: {}", self.id, message);
+        self.notify(EngineNotification::Warning {
+            zone_id: self.zone_id,
+            tab_id: Some(self.id),
+            message,
+        });
     }
 
     /// Bind local+session storage handles into the underlying browsing context.
@@ -288,6 +1103,17 @@ impl Tab {
         self.context.set_viewport(viewport);
         self.desired_viewport = viewport;
 
+        self.request_render();
+    }
+
+    /// Set the page zoom factor and schedule a re-render.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.context.set_zoom(zoom);
+        self.request_render();
+    }
+
+    /// Schedule a re-render of the current content.
+    fn request_render(&mut self) {
         if let TabState::Rendering(_) = self.state {
             // Mark the fact that we have triggered a resize during the rendering of the tab
             self.dirty_after_inflight = true;
@@ -308,47 +1134,188 @@ impl Tab {
         host: &mut impl CompositorSink,
     ) -> anyhow::Result<TickResult> {
         let mut result = TickResult::default();
+        if self.is_killed() {
+            return Ok(result);
+        }
+
+        if self.mode == TabMode::Active {
+            self.last_active = Instant::now();
+        }
+
+        for event in std::mem::take(&mut self.pending_input) {
+            self.apply_event(event);
+        }
+        let frame_held = std::mem::take(&mut self.frame_held);
 
         match self.state.clone() {
             TabState::Idle => {
-                // Nothing to do
+                self.run_deferred_work(backend, host, &mut result)?;
             }
 
             // Start loading the URL
             TabState::PendingLoad(url) => {
+                if std::mem::take(&mut self.discarded) {
+                    self.notify(EngineNotification::TabRestored {
+                        zone_id: self.zone_id,
+                        tab_id: self.id,
+                    });
+                }
+                self.context.cancel_pipeline();
+                self.clear_subresources();
+                // The file inputs and popup requests belong to the document that is replaced
+                self.pending_file_choosers.clear();
+                self.pending_popups.clear();
+                self.parsing_url = None;
                 self.state = TabState::Loading;
                 self.is_loading = true;
                 self.pending_url = Some(url.clone());
-                self.context.start_loading(url.clone());
+                self.context.set_request_headers(self.request_headers());
+                let (locale, timezone) = self.locale();
+                self.context
+                    .set_locale(locale.map(str::to_string), timezone.map(str::to_string));
+                let cookie_jar = self.effective_cookie_jar();
+                self.context.set_cookie_jar(cookie_jar);
+                self.context.set_initiator(self.load_initiator.clone());
+                let submission = self.pending_submission.take();
+                self.cache_response = false;
+                self.retryable = false;
+                let revalidate = std::mem::take(&mut self.reloading);
+                let content = self.pending_content.take().filter(|c| c.url == url);
+                let started = if let Some(content) = content {
+                    self.start_with_content(content)
+                } else if url.scheme() == "data" {
+                    self.start_data_url(&url)
+                } else if let Some(rule) = self.blocking_rule(&url) {
+                    Err(format!("Blocked by content filter rule {rule}"))
+                } else if !self.online.load(Ordering::Relaxed) && is_network_url(&url) {
+                    self.start_offline_load(&url)
+                } else {
+                    let started = match submission {
+                        Some(submission) if submission.url == url => {
+                            let method = match submission.method {
+                                FormMethod::Get => "GET",
+                                FormMethod::Post => "POST",
+                            };
+                            let size = submission.upload_size();
+                            let headers = self.context.request_headers().clone();
+                            self.network_log
+                                .request_started(method, &url, &headers, size, false);
+                            self.context.start_submission(submission)
+                        }
+                        _ => {
+                            self.retryable = true;
+                            self.start_network_load(url.clone(), revalidate)
+                        }
+                    };
+                    started.map_err(|violation| {
+                        let message = violation.to_string();
+                        self.warn(message.clone());
+                        message
+                    })
+                };
+                if let Err(message) = started {
+                    let page = if message == OFFLINE_ERROR {
+                        offline_page(&url)
+                    } else {
+                        message.clone()
+                    };
+                    self.notify(EngineNotification::LoadFailed {
+                        zone_id: self.zone_id,
+                        tab_id: self.id,
+                        url,
+                        error: message,
+                    });
+                    self.history.cancel_pending();
+                    self.state = TabState::Failed(page);
+                    self.is_loading = false;
+                    self.is_error = true;
+                    result.needs_redraw = true;
+                }
             }
 
             // Poll the loading task until it's completed (or failed)
             TabState::Loading => {
-                if let Some(done) = self.context.poll_loading() {
+                if let Some(at) = self.retry_at {
+                    if Instant::now() >= at {
+                        self.retry_at = None;
+                        if let Some(url) = self.pending_url.clone() {
+                            self.state = TabState::PendingLoad(url);
+                        }
+                    }
+                } else if let Some(done) = self.context.poll_loading() {
+                    match &done {
+                        Ok(resp) => self.network_log.request_finished(resp),
+                        Err(e) => self.network_log.request_failed(e),
+                    }
                     match done {
-                        Ok(resp) => {
+                        Ok(mut resp) => {
+                            // Cookies of the redirects were stored while following them
+                            for hop in std::mem::take(&mut resp.redirects) {
+                                self.notify(EngineNotification::Redirected {
+                                    zone_id: self.zone_id,
+                                    tab_id: self.id,
+                                    from: hop.from,
+                                    to: hop.to,
+                                    status: hop.status,
+                                    cookies: hop.cookies,
+                                });
+                            }
+
                             // Store cookies from the response in the cookie jar
-                            if let Some(cookie_jar) = &self.cookie_jar {
+                            if let Some(cookie_jar) = self.effective_cookie_jar() {
+                                let headers = self
+                                    .context
+                                    .allowed_navigation_cookies(&resp.url, &resp.headers);
                                 cookie_jar
                                     .write()
                                     .unwrap()
-                                    .store_response_cookies(&resp.url, &resp.headers);
+                                    .store_response_cookies(&resp.url, &headers);
                             }
 
-                            // Set tab state
-                            self.state = TabState::Loaded;
-                            self.is_loading = false;
-                            self.pending_url = None;
-                            self.current_url = Some(resp.url.clone());
-                            self.context
-                                .set_raw_html(from_utf8_lossy(resp.body.as_slice()).as_ref());
-
-                            // Set result
-                            result.page_loaded = true;
-                            result.commited_url = Some(resp.url.clone());
+                            if std::mem::take(&mut self.cache_response) {
+                                if let Some(cache) = self.http_cache() {
+                                    cache.put(&resp);
+                                }
+                            }
+
+                            self.context.start_parsing(std::mem::take(&mut resp.body));
+                            self.parsing_url = Some(resp.url);
+                            self.state = TabState::Parsing;
+                        }
+                        // Stays in loading until the retry starts
+                        Err(e)
+                            if self.context.take_transient_failure() && self.schedule_retry(&e) => {
                         }
                         Err(e) => {
-                            self.state = TabState::Failed(e);
+                            if let Some(violation) = self.context.take_sandbox_violation() {
+                                self.warn(violation.to_string());
+                            }
+                            // Timeouts get a page of their own, and a stable error
+                            let (error, page) =
+                                match (self.context.take_timeout(), self.pending_url.as_ref()) {
+                                    (Some(kind), Some(url)) => {
+                                        engine_log!(
+                                            Info,
+                                            "Tab[{:?}]: {} timed out: {}",
+                                            self.id,
+                                            url,
+                                            e
+                                        );
+                                        let timeouts = self.context.request_timeouts();
+                                        ("timeout".to_string(), timeout_page(url, kind, timeouts))
+                                    }
+                                    _ => (e.clone(), e),
+                                };
+                            if let Some(url) = self.pending_url.clone() {
+                                self.notify(EngineNotification::LoadFailed {
+                                    zone_id: self.zone_id,
+                                    tab_id: self.id,
+                                    url,
+                                    error,
+                                });
+                            }
+                            self.history.cancel_pending();
+                            self.state = TabState::Failed(page);
                             self.is_loading = false;
                             self.is_error = true;
                             result.needs_redraw = true;
@@ -357,11 +1324,24 @@ impl Tab {
                 }
             }
 
+            // Commit the document once it is parsed
+            TabState::Parsing => {
+                if self.context.poll_parsing() {
+                    if let Some(url) = self.parsing_url.take() {
+                        self.commit_document(url, &mut result);
+                    }
+                }
+            }
+
             // Start rendering after we finished loading
             TabState::Loaded => {
                 self.state = TabState::PendingRendering(*self.context.viewport());
             }
 
+            // The frame waits until the tab can be seen, and for its turn among the tabs
+            // that can (see `EngineConfig::frame_sharing`)
+            TabState::PendingRendering(_) if frame_held || !self.is_on_screen() => {}
+
             TabState::PendingRendering(_viewport) => {
                 if self.committed_viewport != self.desired_viewport {
                     self.committed_viewport = self.desired_viewport;
@@ -370,22 +1350,96 @@ impl Tab {
                 self.state = TabState::Rendering(self.committed_viewport);
             }
 
+            // The host paints the scene itself, so it only needs the render list
+            TabState::Rendering(viewport) if self.render_mode == RenderMode::DisplayList => {
+                self.context.rebuild_render_list_if_needed();
+                self.notify(EngineNotification::SceneReady {
+                    zone_id: self.zone_id,
+                    tab_id: self.id,
+                    render_list: self.context.render_list().clone(),
+                    request: self.context.render_request(),
+                    epoch: self.context.scene_epoch(),
+                });
+                self.state = TabState::Rendered(viewport);
+            }
+
             // Normally, rendering will take a while (async). Currently, it doesn't so we move directly
             // to a Rendered state.
             TabState::Rendering(viewport) => {
+                self.frame_scheduler.begin_frame();
+
                 // Make sure we have a surface to render on
                 self.ensure_surface(backend, viewport.as_size())?;
 
                 // Rebuild the render list if needed
+                let started = Instant::now();
                 self.context.rebuild_render_list_if_needed();
+                self.frame_scheduler.record_scene_build(started.elapsed());
+                if self.is_killed() {
+                    return Ok(result);
+                }
 
                 if let Some(ref mut surf) = self.surface {
-                    backend.render(&mut self.context, surf.as_mut())?;
+                    // A scroll over an unchanged scene only needs the newly exposed strips, as
+                    // long as it moves the frame by whole device pixels
+                    let vp = *self.context.viewport();
+                    let scale = self.context.device_scale();
+                    let scroll = self
+                        .last_frame
+                        .filter(|(last, epoch)| {
+                            *epoch == self.context.scene_epoch()
+                                && last.as_size() == vp.as_size()
+                                && (last.x, last.y) != (vp.x, vp.y)
+                        })
+                        .map(|(last, _)| {
+                            (
+                                (vp.x - last.x) as f32 * scale,
+                                (vp.y - last.y) as f32 * scale,
+                            )
+                        })
+                        .filter(|(dx, dy)| dx.fract() == 0.0 && dy.fract() == 0.0)
+                        .map(|(dx, dy)| (dx as i32, dy as i32));
+                    let started = Instant::now();
+                    let reused = match scroll {
+                        Some((dx, dy)) => {
+                            backend.render_scrolled(&mut self.context, surf.as_mut(), dx, dy)?
+                        }
+                        None => {
+                            backend.render(&mut self.context, surf.as_mut())?;
+                            false
+                        }
+                    };
+                    self.frame_scheduler.record_render(started.elapsed());
+                    if reused {
+                        self.frame_scheduler.record_cached_scroll();
+                    }
+                    self.last_frame = Some((vp, self.context.scene_epoch()));
 
                     if let Some(handle) = backend.external_handle(surf.as_mut()) {
                         host.submit_frame(self.id, handle);
                     }
                 }
+                if self.is_killed() {
+                    return Ok(result);
+                }
+
+                // Whatever the user is not looking at can wait for a frame with time to spare
+                if !self.aux_viewports.is_empty() {
+                    if self.frame_scheduler.over_budget() {
+                        self.frame_scheduler.defer();
+                        self.aux_deferred = true;
+                    } else {
+                        self.render_aux_viewports(backend, host)?;
+                    }
+                }
+                if self.thumbnail_stale {
+                    if self.frame_scheduler.over_budget() {
+                        self.frame_scheduler.defer();
+                    } else {
+                        self.capture_thumbnail(backend);
+                    }
+                }
+                self.frame_scheduler.end_frame();
 
                 self.state = TabState::Rendered(viewport);
             }
@@ -394,6 +1448,7 @@ impl Tab {
             TabState::Rendered(_viewport) => {
                 // Tell the world our surface is ready to paint
                 result.needs_redraw = true;
+                result.aux_redraw = std::mem::take(&mut self.aux_rendered);
 
                 if self.dirty_after_inflight || self.committed_viewport != self.desired_viewport {
                     // If we have a dirty viewport, we need to re-render it
@@ -417,77 +1472,1074 @@ impl Tab {
             }
         }
 
+        // A tab killed during the stage stops here, and its zone closes it
+        if self.is_killed() {
+            return Ok(result);
+        }
+        self.poll_favicon();
+        self.load_subresources();
+        self.publish_console_messages();
+        self.publish_state();
         Ok(result)
     }
 
+    /// Publishes the messages the document logged, when console capture is on.
+    fn publish_console_messages(&mut self) {
+        let messages = self.context.take_console_messages();
+        if !self.console_capture {
+            return;
+        }
+        for message in messages {
+            self.notify(EngineNotification::ConsoleMessage {
+                zone_id: self.zone_id,
+                tab_id: self.id,
+                level: message.level,
+                text: message.text,
+                source: self.current_url.clone(),
+                line: message.line,
+            });
+        }
+    }
+
+    /// Makes the parsed document at `url` the tab's current document.
+    fn commit_document(&mut self, url: Url, result: &mut TickResult) {
+        self.state = TabState::Loaded;
+        self.is_loading = false;
+        self.pending_url = None;
+        self.current_url = Some(url.clone());
+        self.history.commit(url.clone());
+        self.blocked_requests = 0;
+        self.thumbnail_stale = true;
+        self.apply_site_settings(&url);
+        self.discover_favicon(&url);
+        self.apply_resource_hints(&url);
+        let (styles, scripts) = self.user_content.read().unwrap().matching(&url);
+        self.context.set_user_content(&styles, scripts);
+
+        result.page_loaded = true;
+        result.commited_url = Some(url.clone());
+
+        self.notify(EngineNotification::PageLoaded {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            url: url.clone(),
+        });
+
+        let issues = self.context.parse_issues();
+        if !issues.is_empty() {
+            self.notify(EngineNotification::ParseIssues {
+                zone_id: self.zone_id,
+                tab_id: self.id,
+                url,
+                issues: issues.to_vec(),
+            });
+        }
+    }
+
     /// Handle an external UI event (scroll, mouse, keyboard, resize).
     /// Typically forwarded from your toolkit.
+    ///
+    /// Events are queued and applied on the next tick. High-frequency input is coalesced
+    /// while queued (see [`EngineEvent::PointerFrame`]), so fast pointer movement costs at
+    /// most one move and one scroll per tick. When more than [`MAX_PENDING_INPUT`] events
+    /// are waiting, the oldest are dropped, and killed tabs drop all input.
     pub(crate) fn handle_event(&mut self, event: EngineEvent) {
+        if self.is_killed() {
+            return;
+        }
+        self.last_active = Instant::now();
+        coalesce_into(&mut self.pending_input, event);
+        if self.pending_input.len() > MAX_PENDING_INPUT {
+            self.pending_input.remove(0);
+        }
+    }
+
+    /// Applies a single UI event to the tab.
+    fn apply_event(&mut self, event: EngineEvent) {
         match event {
             EngineEvent::Scroll { dx, dy } => {
                 let cur_vp = self.context.viewport();
+                // Deltas are in device pixels, the scroll offset in document coordinates
+                let scale = self.context.device_scale();
                 self.set_viewport(Viewport::new(
                     // We should do clamp(), but we don't know the max x/y sizes of the rendered document
-                    (cur_vp.x + dx as i32).max(0),
-                    (cur_vp.y + dy as i32).max(0),
+                    (cur_vp.x + (dx / scale) as i32).max(0),
+                    (cur_vp.y + (dy / scale) as i32).max(0),
                     cur_vp.width,
                     cur_vp.height,
                 ));
             }
             EngineEvent::MouseMove { x, y } => {
-                println!(
+                engine_log!(
+                    Trace,
                     "Mouse moved on tab {:?} to position ({}, {})",
-                    self.id, x, y
+                    self.id,
+                    x,
+                    y
                 );
             }
             EngineEvent::MouseDown { button, x, y } => {
-                println!(
+                engine_log!(
+                    Debug,
                     "Mouse down event on tab {:?} at position ({}, {}) with button {:?}",
-                    self.id, x, y, button
+                    self.id,
+                    x,
+                    y,
+                    button
                 );
+                let disposition = NavigationDisposition::from_click(&button, self.modifiers);
+                match button {
+                    MouseButton::Left => match self.context.form_click(x, y) {
+                        FormOutcome::Submit(submitter) => {
+                            self.submit_form_with(submitter, disposition)
+                        }
+                        outcome => self.apply_form_outcome(outcome),
+                    },
+                    // Middle clicks only activate, without moving the focus
+                    MouseButton::Middle => {
+                        if let Some(submitter) = self.context.submit_button_at(x, y) {
+                            self.submit_form_with(submitter, disposition);
+                        }
+                    }
+                    MouseButton::Right => {}
+                }
             }
             EngineEvent::MouseUp { button, x, y } => {
-                println!(
+                engine_log!(
+                    Debug,
                     "Mouse up event on tab {:?} at position ({}, {}) with button {:?}",
-                    self.id, x, y, button
+                    self.id,
+                    x,
+                    y,
+                    button
                 );
             }
             EngineEvent::KeyDown { key } => {
-                println!("Key down event on tab {:?} for key: {}", self.id, key);
+                engine_log!(
+                    Debug,
+                    "Key down event on tab {:?} for key: {}",
+                    self.id,
+                    key
+                );
+                if !self.modifiers.update(&key, true) {
+                    match self.context.form_key_down(&key) {
+                        // Like a click, Enter with modifiers submits into a new tab or window
+                        FormOutcome::Submit(submitter) => {
+                            let disposition = NavigationDisposition::from_click(
+                                &MouseButton::Left,
+                                self.modifiers,
+                            );
+                            self.submit_form_with(submitter, disposition);
+                        }
+                        outcome => self.apply_form_outcome(outcome),
+                    }
+                }
             }
             EngineEvent::KeyUp { key } => {
-                println!("Key up event on tab {:?} for key: {}", self.id, key);
+                engine_log!(Debug, "Key up event on tab {:?} for key: {}", self.id, key);
+                self.modifiers.update(&key, false);
             }
             EngineEvent::InputChar { character } => {
-                println!(
+                engine_log!(
+                    Debug,
                     "Input character event on tab {:?}: '{}'",
-                    self.id, character
+                    self.id,
+                    character
                 );
+                if self.context.form_input_char(character) {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
             }
             EngineEvent::Resize { width, height } => {
-                println!(
+                engine_log!(
+                    Debug,
                     "Resize event on tab {:?}: new size {}x{}",
-                    self.id, width, height
+                    self.id,
+                    width,
+                    height
                 );
                 let cur_vp = self.context.viewport();
                 self.set_viewport(Viewport::new(cur_vp.x, cur_vp.y, width, height))
             }
+            EngineEvent::ScaleFactorChanged { scale } => {
+                if scale != self.context.device_scale() {
+                    self.context.set_device_scale(scale);
+                    // The previous frame was painted at the old scale, so scrolls cannot reuse it
+                    self.last_frame = None;
+                    self.request_render();
+                }
+            }
+            EngineEvent::TouchStart { id, x, y } => {
+                let gesture = self.gestures.touch_start(id, x, y);
+                self.apply_gesture(gesture);
+            }
+            EngineEvent::TouchMove { id, x, y } => {
+                let gesture = self.gestures.touch_move(id, x, y);
+                self.apply_gesture(gesture);
+            }
+            EngineEvent::TouchEnd { id, x, y } => {
+                let gesture = self.gestures.touch_end(id, x, y);
+                self.apply_gesture(gesture);
+            }
+            EngineEvent::TouchCancel => self.gestures.cancel(),
+            EngineEvent::PointerFrame {
+                position,
+                scroll_dx,
+                scroll_dy,
+            } => {
+                if let Some((x, y)) = position {
+                    self.apply_event(EngineEvent::MouseMove { x, y });
+                }
+                if scroll_dx != 0.0 || scroll_dy != 0.0 {
+                    self.apply_event(EngineEvent::Scroll {
+                        dx: scroll_dx,
+                        dy: scroll_dy,
+                    });
+                }
+            }
         }
     }
 
-    /// Execute a high-level engine command (navigate, reload).
+    /// Execute a high-level engine command (navigate, reload, back/forward).
     pub(crate) fn execute_command(&mut self, command: EngineCommand) {
+        self.last_active = Instant::now();
         match command {
             EngineCommand::Navigate(url) => {
-                self.state = TabState::PendingLoad(url);
+                self.history.cancel_pending();
+                if !self.is_loading && self.context.is_same_document(&url) {
+                    self.navigate_same_document(url);
+                } else {
+                    self.request_load(url);
+                }
             }
+            EngineCommand::NavigateInput(input) => self.navigate_to(input),
+            EngineCommand::LoadHtml { html, base_url } => {
+                self.load_content(html.into_bytes(), "text/html; charset=utf-8", base_url);
+            }
+            EngineCommand::LoadBytes {
+                data,
+                mime,
+                base_url,
+            } => self.load_content(data, &mime, base_url),
             EngineCommand::Reload() => {
-                let Some(url) = self.current_url.clone() else {
+                if self.current_url.is_none() {
                     return;
-                };
-
-                self.state = TabState::PendingLoad(url);
-            }
+                }
+                if let Some(url) = self.history.begin_traversal(0) {
+                    self.reloading = true;
+                    self.request_load(url);
+                }
+            }
+            EngineCommand::GoBack() => {
+                if let Some(url) = self.history.begin_traversal(-1) {
+                    self.traverse_to(url);
+                }
+            }
+            EngineCommand::GoForward() => {
+                if let Some(url) = self.history.begin_traversal(1) {
+                    self.traverse_to(url);
+                }
+            }
+            EngineCommand::AddAuxViewport { id, viewport } => {
+                self.aux_viewports.insert(
+                    id,
+                    AuxViewport {
+                        viewport,
+                        surface: None,
+                    },
+                );
+                self.request_render();
+            }
+            EngineCommand::RemoveAuxViewport { id } => {
+                self.aux_viewports.remove(&id);
+            }
+            EngineCommand::SetOverrides(overrides) => self.set_overrides(overrides),
+            EngineCommand::SetMode(mode) => self.set_mode(mode),
+            EngineCommand::EnableConsoleCapture(enabled) => self.console_capture = enabled,
+            EngineCommand::SetPinned { pinned } => self.set_pinned(pinned),
+            EngineCommand::SetVisibility { visible, occluded } => {
+                self.set_visibility(visible, occluded)
+            }
+            EngineCommand::StopLoading => self.stop_loading(),
+            EngineCommand::ExportHar => self.notify(EngineNotification::HarExported {
+                zone_id: self.zone_id,
+                tab_id: self.id,
+                har: self.export_har(),
+            }),
+            EngineCommand::PrintToPdf { options } => match self.print_to_pdf(&options) {
+                Ok(pdf) => self.notify(EngineNotification::PdfPrinted {
+                    zone_id: self.zone_id,
+                    tab_id: self.id,
+                    pdf,
+                }),
+                Err(e) => self.warn(format!("cannot print: {e}")),
+            },
+            EngineCommand::Preconnect { origin } => {
+                self.preconnect(origin);
+            }
+            // These need the engine, which handles them before the tab sees them
+            EngineCommand::CloseTab
+            | EngineCommand::ForceKill
+            | EngineCommand::SetNetworkState { .. }
+            | EngineCommand::ResolvePopup { .. } => {}
+            EngineCommand::MessageExtension {
+                extension_id,
+                message,
+            } => {
+                if self.context.has_content_script(&extension_id) {
+                    self.context
+                        .deliver_extension_message(extension_id, message);
+                } else {
+                    self.warn(format!(
+                        "extension {extension_id} has no content script in this tab; message dropped"
+                    ));
+                }
+            }
+            EngineCommand::ReplaceMisspelling {
+                control,
+                range,
+                replacement,
+            } => {
+                if self
+                    .context
+                    .replace_misspelling(control, range, &replacement)
+                {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
+            }
+            EngineCommand::Paste { text } => {
+                if self.context.form_paste(&text) {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
+            }
+            EngineCommand::Copy | EngineCommand::Cut => {
+                let cut = matches!(command, EngineCommand::Cut);
+                if let Some(text) = self.context.form_copy(cut) {
+                    if cut {
+                        self.apply_form_outcome(FormOutcome::Changed);
+                    }
+                    self.notify(EngineNotification::ClipboardRequested {
+                        zone_id: self.zone_id,
+                        tab_id: self.id,
+                        text,
+                    });
+                }
+            }
+            EngineCommand::ResolveFileChooser { request_id, files } => {
+                let Some(idx) = self
+                    .pending_file_choosers
+                    .iter()
+                    .position(|(id, _)| *id == request_id)
+                else {
+                    self.warn(format!("unknown file chooser request {request_id:?}"));
+                    return;
+                };
+                let (_, control) = self.pending_file_choosers.remove(idx);
+                // An empty answer means the user cancelled, which keeps the files chosen before
+                if !files.is_empty() && self.context.set_form_files(control, files) {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
+            }
+            EngineCommand::SelectAll => {
+                if self.context.form_select_all() {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
+            }
+            EngineCommand::DragEnter { x, y, payload } => {
+                let effect = self.drop_effect(x, y, &payload);
+                self.drag = Some((payload, DropEffect::None));
+                self.set_drop_effect(effect);
+            }
+            EngineCommand::DragOver { x, y } => {
+                if let Some((payload, _)) = &self.drag {
+                    let effect = self.drop_effect(x, y, payload);
+                    self.set_drop_effect(effect);
+                }
+            }
+            EngineCommand::DragLeave => {
+                self.set_drop_effect(DropEffect::None);
+                self.drag = None;
+            }
+            EngineCommand::Drop { x, y, payload } => {
+                self.drag = None;
+                self.drop_payload(x, y, payload);
+            }
+        }
+    }
+
+    /// Returns what dropping `payload` at `(x, y)` would do.
+    fn drop_effect(&self, x: f32, y: f32, payload: &DragPayload) -> DropEffect {
+        let over_text_field = self.context.editable_control_at(x, y).is_some();
+        match payload {
+            DragPayload::Urls(urls) if urls.is_empty() => DropEffect::None,
+            DragPayload::Urls(_) | DragPayload::Text(_) if over_text_field => {
+                DropEffect::InsertText
+            }
+            // Only the first link is opened, and local files only where the zone allows it
+            DragPayload::Urls(urls) if urls[0].scheme() == "file" && !self.local_file_access => {
+                DropEffect::None
+            }
+            DragPayload::Urls(_) => DropEffect::Navigate,
+            DragPayload::Files(files)
+                if !files.is_empty() && self.context.file_input_at(x, y).is_some() =>
+            {
+                DropEffect::ChooseFiles
+            }
+            DragPayload::Files(_) | DragPayload::Text(_) => DropEffect::None,
+        }
+    }
+
+    /// Records what a drop would do, telling the user agent when that changed. Does nothing
+    /// without a drag in progress.
+    fn set_drop_effect(&mut self, effect: DropEffect) {
+        let previous = match &mut self.drag {
+            Some((_, current)) => std::mem::replace(current, effect),
+            None => return,
+        };
+        if previous != effect {
+            self.notify(EngineNotification::DropEffectChanged {
+                zone_id: self.zone_id,
+                tab_id: self.id,
+                effect,
+            });
+        }
+    }
+
+    /// Handles a drop: text goes into the form control under the pointer, files into the file
+    /// input under it, and links are loaded in the tab.
+    fn drop_payload(&mut self, x: f32, y: f32, payload: DragPayload) {
+        match (self.drop_effect(x, y, &payload), payload) {
+            (DropEffect::None, _) => {}
+            (DropEffect::InsertText, DragPayload::Text(text)) => {
+                if self.context.form_drop_text(x, y, &text) {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
+            }
+            (DropEffect::InsertText, DragPayload::Urls(urls)) => {
+                let text = urls.iter().map(Url::as_str).collect::<Vec<_>>().join(" ");
+                if self.context.form_drop_text(x, y, &text) {
+                    self.apply_form_outcome(FormOutcome::Changed);
+                }
+            }
+            (_, DragPayload::Urls(urls)) => {
+                // Like browsers, only the first of several links is opened
+                self.history.cancel_pending();
+                self.request_load(urls[0].clone());
+            }
+            (_, DragPayload::Files(files)) => {
+                if let Some(control) = self.context.file_input_at(x, y) {
+                    if self.context.set_form_files(control, files) {
+                        self.apply_form_outcome(FormOutcome::Changed);
+                    }
+                }
+            }
+            (_, DragPayload::Text(_)) => {}
+        }
+    }
+
+    /// Submits the form that the control `submitter` belongs to, starting a GET or POST load
+    /// of the form's action. Does nothing when the control is not part of a form.
+    pub fn submit_form(&mut self, submitter: usize) {
+        self.submit_form_with(submitter, NavigationDisposition::CurrentTab);
+    }
+
+    /// Submits the form of `submitter` where the user asked for it. Anything but the current
+    /// tab is left to the user agent with an [`EngineNotification::OpenUrlRequested`].
+    fn submit_form_with(&mut self, submitter: usize, disposition: NavigationDisposition) {
+        let Some(submission) = self.context.form_submission(submitter) else {
+            return;
+        };
+        if disposition != NavigationDisposition::CurrentTab {
+            let url = submission.url.clone();
+            let request_id = self.push_popup_request(submission);
+            self.notify(EngineNotification::OpenUrlRequested {
+                zone_id: self.zone_id,
+                tab_id: self.id,
+                request_id,
+                url,
+                disposition,
+            });
+        } else if submission.opens_new_tab() {
+            self.request_popup(submission);
+        } else {
+            self.load_submission(submission);
+        }
+    }
+
+    /// Asks the user agent to open the result of `submission` in a new tab. The request waits
+    /// for [`EngineCommand::ResolvePopup`].
+    fn request_popup(&mut self, submission: FormSubmission) {
+        let url = submission.url.clone();
+        let request_id = self.push_popup_request(submission);
+        self.notify(EngineNotification::PopupRequested {
+            zone_id: self.zone_id,
+            opener: self.id,
+            request_id,
+            url,
+            features: String::new(),
+        });
+    }
+
+    /// Starts a load of `url` on the next tick. The tab counts as loading from here on, so
+    /// input that arrives before the tick does not treat the old document as current.
+    fn request_load(&mut self, url: Url) {
+        self.state = TabState::PendingLoad(url);
+        self.load_initiator = None;
+        self.is_loading = true;
+        self.load_retries = 0;
+        self.retry_at = None;
+        self.network_log.begin_navigation();
+    }
+
+    /// Returns the requests made for the latest navigation as a HAR 1.2 document, see
+    /// [`EngineCommand::ExportHar`].
+    pub fn export_har(&self) -> String {
+        let har = self.network_log.to_har(&self.title);
+        serde_json::to_string_pretty(&har).unwrap_or_default()
+    }
+
+    /// Prints the document as last painted to PDF, see [`EngineCommand::PrintToPdf`].
+    ///
+    /// # Errors
+    /// Fails when `options` cannot be printed with, see [`PrintError`].
+    pub fn print_to_pdf(&self, options: &PrintToPdf) -> Result<Vec<u8>, PrintError> {
+        let url = self
+            .current_url
+            .as_ref()
+            .map(Url::to_string)
+            .unwrap_or_default();
+        self.context
+            .render_list()
+            .to_pdf(options, &self.title, &url)
+    }
+
+    /// Sets (or with `None`, removes) the policy for retrying loads that fail on a flaky
+    /// network.
+    pub(crate) fn bind_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry_policy = policy;
+    }
+
+    /// Schedules another attempt of the GET navigation in flight, which failed with `error`,
+    /// and warns the user agent about it. Returns false when the load may not be retried
+    /// (anymore).
+    fn schedule_retry(&mut self, error: &str) -> bool {
+        if !self.retryable {
+            return false;
+        }
+        let Some(policy) = self.retry_policy else {
+            return false;
+        };
+        let Some(delay) = policy.backoff(self.load_retries + 1) else {
+            return false;
+        };
+        let Some(url) = self.pending_url.clone() else {
+            return false;
+        };
+
+        self.load_retries += 1;
+        self.retry_at = Some(Instant::now() + delay);
+        self.warn(format!(
+            "Loading {url} failed ({error}), retrying in {delay:?} (attempt {} of {})",
+            self.load_retries, policy.max_retries
+        ));
+        true
+    }
+
+    /// Aborts the navigation in flight, if any, and publishes
+    /// [`EngineNotification::LoadCancelled`] with its URL. The current document stays.
+    fn stop_loading(&mut self) {
+        let url = match std::mem::take(&mut self.state) {
+            TabState::PendingLoad(url) => Some(url),
+            TabState::Loading => self.pending_url.clone(),
+            TabState::Parsing => self.parsing_url.clone(),
+            state => {
+                self.state = state;
+                return;
+            }
+        };
+        self.context.cancel_pipeline();
+        self.clear_subresources();
+        self.history.cancel_pending();
+        self.pending_url = None;
+        self.parsing_url = None;
+        self.pending_submission = None;
+        self.pending_content = None;
+        self.reloading = false;
+        self.cache_response = false;
+        self.retry_at = None;
+        self.is_loading = false;
+        if let Some(url) = url {
+            self.notify(EngineNotification::LoadCancelled {
+                zone_id: self.zone_id,
+                tab_id: self.id,
+                url,
+            });
+        }
+    }
+
+    /// Stops everything the tab has in flight, as it is being closed. The tab can still be
+    /// read through handles the user agent kept, but does no more work.
+    pub(crate) fn close(&mut self) {
+        self.context.cancel_pipeline();
+        self.clear_subresources();
+        self.history.cancel_pending();
+        self.pending_popups.clear();
+        self.pending_file_choosers.clear();
+        self.pending_input.clear();
+        self.parsing_url = None;
+        self.pending_url = None;
+        self.state = TabState::Idle;
+        self.is_loading = false;
+    }
+
+    /// Keeps `submission` until the user agent answers the popup request, dropping the oldest
+    /// request when too many are waiting.
+    fn push_popup_request(&mut self, submission: FormSubmission) -> PopupRequestId {
+        if self.pending_popups.len() >= MAX_PENDING_POPUPS {
+            self.pending_popups.remove(0);
+        }
+        let request_id = PopupRequestId::next();
+        self.pending_popups.push((request_id, submission));
+        request_id
+    }
+
+    /// Removes a popup request of this tab, returning what it wanted to load.
+    pub(crate) fn take_popup_request(
+        &mut self,
+        request_id: PopupRequestId,
+    ) -> Option<FormSubmission> {
+        let idx = self
+            .pending_popups
+            .iter()
+            .position(|(id, _)| *id == request_id)?;
+        Some(self.pending_popups.remove(idx).1)
+    }
+
+    /// Starts loading a popup that `opener` requested, in this newly opened tab.
+    pub(crate) fn open_as_popup(
+        &mut self,
+        opener: TabId,
+        request_id: PopupRequestId,
+        submission: FormSubmission,
+    ) {
+        self.opener = Some(opener);
+        self.notify(EngineNotification::PopupOpened {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            opener,
+            request_id,
+        });
+        self.load_submission(submission);
+    }
+
+    /// Returns the tab whose page opened this tab, if it was opened as a popup.
+    pub fn opener(&self) -> Option<TabId> {
+        self.opener
+    }
+
+    /// Navigates to the result of a form submission.
+    fn load_submission(&mut self, submission: FormSubmission) {
+        self.history.cancel_pending();
+        self.request_load(submission.url.clone());
+        self.load_initiator = self.current_url.clone();
+        if submission.method == FormMethod::Post {
+            self.pending_submission = Some(submission);
+        }
+    }
+
+    /// Repaints or submits after form controls handled some input.
+    fn apply_form_outcome(&mut self, outcome: FormOutcome) {
+        match outcome {
+            FormOutcome::Ignored => {}
+            // Don't disturb a load that is in flight; the new document replaces the forms anyway
+            FormOutcome::Changed if !self.is_loading => self.request_render(),
+            FormOutcome::Changed => {}
+            FormOutcome::Submit(submitter) => self.submit_form(submitter),
+            FormOutcome::ChooseFiles(control) => self.request_file_chooser(control),
+        }
+    }
+
+    /// Asks the user agent to choose files for the file input `control`. The request waits
+    /// for [`EngineCommand::ResolveFileChooser`].
+    fn request_file_chooser(&mut self, control: usize) {
+        let Some(FormControlKind::File {
+            accept, multiple, ..
+        }) = self
+            .context
+            .forms()
+            .controls()
+            .get(control)
+            .map(|c| &c.kind)
+        else {
+            return;
+        };
+        let (accept, multiple) = (accept.clone(), *multiple);
+        if !self.is_loading {
+            self.request_render();
+        }
+        let request_id = FileChooserRequestId::next();
+        self.pending_file_choosers.push((request_id, control));
+        self.notify(EngineNotification::FileChooserRequested {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            request_id,
+            accept,
+            multiple,
+        });
+    }
+
+    /// Completes a pending history traversal, without a load when it stays in the current document.
+    /// Starts a load of content provided by the user agent, which goes through the same states
+    /// as a navigation but never reaches the network.
+    fn load_content(&mut self, data: Vec<u8>, mime: &str, base_url: Option<Url>) {
+        let url = base_url.unwrap_or_else(|| Url::parse("about:blank").expect("valid url"));
+        let mut headers = HeaderMap::new();
+        match HeaderValue::from_str(mime) {
+            Ok(value) => {
+                headers.insert(CONTENT_TYPE, value);
+            }
+            Err(_) => self.warn(format!("ignoring invalid content type {mime:?}")),
+        }
+
+        self.history.cancel_pending();
+        self.pending_content = Some(Response {
+            url: url.clone(),
+            status: 200,
+            status_text: "OK".to_string(),
+            headers,
+            body: data,
+            redirects: Vec::new(),
+        });
+        self.request_load(url);
+    }
+
+    /// Hands provided content to the browsing context, unless it cannot be displayed.
+    fn start_with_content(&mut self, content: Response) -> Result<(), String> {
+        let mime = content
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("text/html");
+        if !mime::is_displayable(mime) {
+            return Err(format!("Cannot display content of type {mime}"));
+        }
+        self.context.start_with_response(content);
+        Ok(())
+    }
+
+    /// Decodes a `data:` URL and loads its content, if the sandbox allows the scheme.
+    fn start_data_url(&mut self, url: &Url) -> Result<(), String> {
+        if let Err(violation) = check_sandbox(url, self.context.sandbox_mode()) {
+            let message = violation.to_string();
+            self.warn(message.clone());
+            return Err(message);
+        }
+        let data = decode_data_url(url).map_err(|e| e.to_string())?;
+        self.start_with_content(data.into_response(url.clone()))
+    }
+
+    fn traverse_to(&mut self, url: Url) {
+        if !self.is_loading && self.history.pending_is_same_document() {
+            self.navigate_same_document(url);
+        } else {
+            self.request_load(url);
+        }
+    }
+
+    /// Moves to another location in the current document: updates the URL and history, scrolls
+    /// to the fragment target and emits [`EngineNotification::LocationChanged`].
+    fn navigate_same_document(&mut self, url: Url) {
+        self.history.commit_same_document(url.clone());
+        self.current_url = Some(url.clone());
+
+        if let Some(y) = self.context.navigate_to_fragment(url.clone()) {
+            let cur_vp = *self.context.viewport();
+            self.set_viewport(Viewport::new(cur_vp.x, y, cur_vp.width, cur_vp.height));
+        }
+
+        self.notify(EngineNotification::LocationChanged {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            url,
+        });
+        self.publish_state();
+    }
+
+    /// Translates a synthesized touch gesture into the equivalent mouse, scroll or zoom input.
+    fn apply_gesture(&mut self, gesture: Option<Gesture>) {
+        match gesture {
+            Some(Gesture::Tap { x, y }) => {
+                let button = MouseButton::Left;
+                self.apply_event(EngineEvent::MouseDown {
+                    button: button.clone(),
+                    x,
+                    y,
+                });
+                self.apply_event(EngineEvent::MouseUp { button, x, y });
+            }
+            // Content follows the finger, so the viewport moves the opposite way
+            Some(Gesture::Pan { dx, dy }) => {
+                self.apply_event(EngineEvent::Scroll { dx: -dx, dy: -dy });
+            }
+            Some(Gesture::Pinch { scale }) => {
+                let zoom = self.context.zoom() * scale;
+                self.set_zoom(zoom);
+            }
+            None => {}
+        }
+    }
+
+    /// Changes the activity mode of the tab. Activating a discarded tab loads its page again.
+    pub fn set_mode(&mut self, mode: TabMode) {
+        self.mode = mode;
+        if mode == TabMode::Active {
+            self.last_active = Instant::now();
+            self.restore();
+        }
+    }
+
+    /// Switches between painting with the backend and handing render lists to the host. The
+    /// surface is dropped when the host takes over, and the next frame goes out in the new
+    /// mode.
+    pub(crate) fn bind_render_mode(&mut self, mode: RenderMode) {
+        if self.render_mode == mode {
+            return;
+        }
+        self.render_mode = mode;
+        if mode == RenderMode::DisplayList {
+            self.surface = None;
+            self.last_frame = None;
+        }
+        self.context.invalidate_render();
+        if self.state == TabState::Idle && self.current_url.is_some() {
+            self.state = TabState::PendingRendering(self.desired_viewport);
+        }
+    }
+
+    /// Allows or refuses files dropped onto the tab, following the zone's
+    /// `enable_local_file_access`.
+    pub(crate) fn bind_local_file_access(&mut self, allowed: bool) {
+        self.local_file_access = allowed;
+    }
+
+    /// Sets the time that rendering a frame may take, derived from the engine's FPS target.
+    pub(crate) fn bind_frame_budget(&mut self, budget: Duration) {
+        self.frame_scheduler.set_budget(budget);
+    }
+
+    /// Returns the frame timing of the tab so far.
+    pub fn jank_stats(&self) -> JankStats {
+        self.frame_scheduler.stats()
+    }
+
+    /// Returns the timing of the last frame the tab rendered.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_scheduler.last_frame()
+    }
+
+    /// Runs work that was deferred because a frame ran over budget.
+    fn run_deferred_work(
+        &mut self,
+        backend: &mut dyn RenderBackend,
+        host: &mut impl CompositorSink,
+        result: &mut TickResult,
+    ) -> anyhow::Result<()> {
+        if std::mem::take(&mut self.aux_deferred) {
+            self.render_aux_viewports(backend, host)?;
+            result.aux_redraw = std::mem::take(&mut self.aux_rendered);
+        }
+        if self.thumbnail_stale {
+            self.capture_thumbnail(backend);
+        }
+        Ok(())
+    }
+
+    /// Replaces the thumbnail with a snapshot of the main surface, if there is one.
+    fn capture_thumbnail(&mut self, backend: &mut dyn RenderBackend) {
+        let Some(surface) = self.surface.as_mut() else {
+            return;
+        };
+        match backend.snapshot(surface.as_mut(), THUMBNAIL_MAX_DIM) {
+            Ok(thumbnail) => self.thumbnail = Some(thumbnail),
+            Err(e) => engine_log!(Warn, "Tab[{:?}]: cannot capture thumbnail: {}", self.id, e),
+        }
+        self.thumbnail_stale = false;
+    }
+
+    /// Returns true once the tab was killed, see [`EngineCommand::ForceKill`] and
+    /// [`TabKiller`](crate::TabKiller). A killed tab does no more work and is closed by its zone.
+    /// It notices the kill between the stages of its pipeline, between the lines of its layout
+    /// and between the viewports it renders; a frame the backend is painting is finished first.
+    pub fn is_killed(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Returns the flag that kills the tab when set.
+    pub(crate) fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancel.clone()
+    }
+
+    /// Returns true when the tab was discarded and has not been restored since.
+    pub fn is_discarded(&self) -> bool {
+        self.discarded
+    }
+
+    /// Returns when the tab was last active or received input or a command.
+    pub fn last_active(&self) -> Instant {
+        self.last_active
+    }
+
+    /// Returns true when the tab is pinned.
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Pins or unpins the tab, see [`EngineCommand::SetPinned`].
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+        self.publish_state();
+    }
+
+    /// Attaches data of the user agent to the tab, such as the widget that shows it, replacing
+    /// what was attached before. The engine does not look at the data; it is dropped when the
+    /// tab closes.
+    pub fn set_user_data(&mut self, data: Box<dyn Any + Send + Sync>) {
+        self.user_data = Some(Arc::from(data));
+    }
+
+    /// Returns the data attached with [`Tab::set_user_data`], or `None` when nothing is
+    /// attached or it is not a `T`.
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.user_data.clone()?.downcast().ok()
+    }
+
+    /// Removes the data attached with [`Tab::set_user_data`].
+    pub fn clear_user_data(&mut self) {
+        self.user_data = None;
+    }
+
+    /// Returns true when the tab has a frame to render and waits to start it.
+    pub(crate) fn wants_frame(&self) -> bool {
+        self.is_on_screen() && matches!(self.state, TabState::PendingRendering(_))
+    }
+
+    /// Tells the tab whether the user agent shows it, see [`EngineCommand::SetVisibility`].
+    pub fn set_visibility(&mut self, visible: bool, occluded: bool) {
+        self.visible = visible;
+        self.occluded = occluded;
+        self.context.set_hidden(!visible);
+    }
+
+    /// Returns true when the user agent shows the tab and nothing covers it.
+    pub fn is_on_screen(&self) -> bool {
+        self.visible && !self.occluded
+    }
+
+    /// Returns how long to wait between two ticks of the tab, or `None` when it is not ticked
+    /// at all.
+    pub(crate) fn tick_interval(&self) -> Option<Duration> {
+        let interval = match self.mode {
+            TabMode::Active => Duration::from_secs(0), // Always run
+            TabMode::BackgroundLive => Duration::from_millis(100), // Run at 10Hz
+            TabMode::BackgroundIdle => Duration::from_secs(1), // Run at 1Hz
+            TabMode::Suspended => return None,
+        };
+        if self.is_on_screen() {
+            Some(interval)
+        } else {
+            // Nothing of the tab can be seen, so it runs like a background tab
+            Some(interval.max(OFF_SCREEN_TICK_INTERVAL))
+        }
+    }
+
+    /// Makes the tab wait with its next frame during the next tick, or lets it go ahead.
+    pub(crate) fn hold_frame(&mut self, held: bool) {
+        self.frame_held = held;
+    }
+
+    /// Returns true when the tab may be discarded: it is in the background, not pinned and not
+    /// busy.
+    pub fn is_discardable(&self) -> bool {
+        !self.discarded
+            && !self.pinned
+            && self.mode != TabMode::Active
+            && !self.is_loading
+            && self.state == TabState::Idle
+    }
+
+    /// Returns a rough estimate in bytes of the memory held by the tab: its surfaces,
+    /// thumbnail and document.
+    pub fn memory_estimate(&self) -> usize {
+        let surface_bytes = |size: SurfaceSize| size.width as usize * size.height as usize * 4;
+        let surfaces: usize = self
+            .surface
+            .iter()
+            .chain(
+                self.aux_viewports
+                    .values()
+                    .filter_map(|aux| aux.surface.as_ref()),
+            )
+            .map(|surface| surface_bytes(surface.size()))
+            .sum();
+        let thumbnail = self.thumbnail.as_ref().map_or(0, |t| t.pixels.len());
+
+        surfaces + thumbnail + self.context.memory_estimate()
+    }
+
+    /// Drops the document and surfaces of the tab, keeping its URL, history and a thumbnail
+    /// of the last frame. The page is loaded again when the tab becomes active. Returns the
+    /// estimated number of bytes freed.
+    pub(crate) fn discard(&mut self, backend: &mut dyn RenderBackend) -> usize {
+        let before = self.memory_estimate();
+
+        self.capture_thumbnail(backend);
+        self.surface = None;
+        self.last_frame = None;
+        for aux in self.aux_viewports.values_mut() {
+            aux.surface = None;
+        }
+        self.aux_deferred = false;
+        self.context.discard_document();
+        self.discarded = true;
+
+        let freed = before.saturating_sub(self.memory_estimate());
+        self.notify(EngineNotification::TabDiscarded {
+            zone_id: self.zone_id,
+            tab_id: self.id,
+            freed_bytes: freed as u64,
+        });
+        freed
+    }
+
+    /// Drops the surfaces of the tab after the backend moved to a new device, and schedules a
+    /// frame on new ones.
+    pub(crate) fn drop_surfaces(&mut self) {
+        self.surface = None;
+        self.last_frame = None;
+        for aux in self.aux_viewports.values_mut() {
+            aux.surface = None;
+        }
+        self.context.invalidate_render();
+        if self.state == TabState::Idle && !self.discarded && self.current_url.is_some() {
+            self.request_render();
+        }
+    }
+
+    /// Loads the page of a discarded tab again, from its current history entry.
+    fn restore(&mut self) {
+        if !self.discarded {
+            return;
+        }
+        match self.history.begin_traversal(0) {
+            Some(url) => self.request_load(url),
+            None => {
+                // Nothing was loaded, so there is nothing to load again
+                self.discarded = false;
+                self.notify(EngineNotification::TabRestored {
+                    zone_id: self.zone_id,
+                    tab_id: self.id,
+                });
+            }
         }
     }
 
@@ -522,6 +2574,46 @@ impl Tab {
         // }
     }
 
+    /// Renders the current content into every auxiliary viewport and submits the frames.
+    ///
+    /// The render list is rebuilt for each viewport size and restored for the main viewport
+    /// afterwards.
+    fn render_aux_viewports(
+        &mut self,
+        backend: &mut dyn RenderBackend,
+        host: &mut impl CompositorSink,
+    ) -> anyhow::Result<()> {
+        if self.aux_viewports.is_empty() {
+            return Ok(());
+        }
+
+        let main_viewport = *self.context.viewport();
+        for (id, aux) in self.aux_viewports.iter_mut() {
+            if self.cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let size = aux.viewport.as_size();
+            if aux.surface.as_ref().map(|s| s.size()) != Some(size) {
+                aux.surface = Some(backend.create_surface(size, self.present_mode)?);
+            }
+
+            self.context.set_viewport(aux.viewport);
+            self.context.rebuild_render_list_if_needed();
+
+            if let Some(surf) = aux.surface.as_mut() {
+                backend.render(&mut self.context, surf.as_mut())?;
+                if let Some(handle) = backend.external_handle(surf.as_mut()) {
+                    host.submit_aux_frame(self.id, *id, handle);
+                }
+            }
+            self.aux_rendered.push(*id);
+        }
+
+        self.context.set_viewport(main_viewport);
+        self.context.rebuild_render_list_if_needed();
+        Ok(())
+    }
+
     /// Ensure the tab has a surface of the given size, creating it if necessary.
     fn ensure_surface(
         &mut self,
@@ -534,6 +2626,2021 @@ impl Tab {
             }
         }
         self.surface = Some(backend.create_surface(size, self.present_mode)?);
+        self.last_frame = None;
         Ok(())
     }
 }
+
+/// Error of [`EngineNotification::LoadFailed`] for navigations made while the host is offline
+const OFFLINE_ERROR: &str = "offline";
+
+/// Returns true when loading `url` needs the network.
+fn is_network_url(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+/// Returns the `Accept-Language` header for pages in `locale`: the tag itself, followed by
+/// its language when the tag has a region or script, such as `nl-NL,nl;q=0.9`.
+fn accept_language_for(locale: &str) -> String {
+    match locale.split_once('-') {
+        Some((language, _)) => format!("{locale},{language};q=0.9"),
+        None => locale.to_string(),
+    }
+}
+
+/// Returns the media type of a fetched favicon, or `None` when the response is not an image,
+/// such as an HTML "not found" page. Icons served without a proper type are accepted when
+/// their URL says they are `.ico` files.
+fn favicon_mime(resp: &Response) -> Option<String> {
+    let declared = resp
+        .headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(mime::essence)
+        .unwrap_or_default();
+    if declared.starts_with("image/") {
+        Some(declared)
+    } else if matches!(declared.as_str(), "" | "application/octet-stream")
+        && resp.url.path().ends_with(".ico")
+    {
+        Some("image/x-icon".to_string())
+    } else {
+        None
+    }
+}
+
+/// Returns the error page shown when `url` is not loaded because the host is offline.
+fn offline_page(url: &Url) -> String {
+    let host = url.host_str().unwrap_or(url.as_str());
+    format!(
+        "<html><head><title>You are offline</title></head><body><h1>You are offline</h1>\
+         <p>{host} cannot be loaded until the network connection is back.</p></body></html>"
+    )
+}
+
+/// Returns the error page shown when loading `url` ran into `kind` of `timeouts`.
+fn timeout_page(url: &Url, kind: TimeoutKind, timeouts: RequestTimeouts) -> String {
+    let host = url.host_str().unwrap_or(url.as_str());
+    let (title, detail) = match kind {
+        TimeoutKind::Connect => (
+            "The connection has timed out",
+            format!(
+                "{host} could not be reached within {} seconds.",
+                timeouts.connect.as_secs_f32()
+            ),
+        ),
+        TimeoutKind::Total => (
+            "The server took too long to respond",
+            format!(
+                "{host} did not finish its response within {} seconds.",
+                timeouts.total.as_secs_f32()
+            ),
+        ),
+    };
+    format!("<html><head><title>{title}</title></head><body><h1>{title}</h1><p>{detail}</p></body></html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{AntialiasingMode, GpuOptions};
+    use crate::cookies::CookieJarHandle;
+    use crate::diagnostics::ParseIssueKind;
+    use crate::engine::test_support::{
+        engine_with_tab, engine_with_tab_in, navigate_offline, new_engine, open_tab,
+        serve_html_with_headers, tick, tick_until, wait_for_load,
+    };
+    use crate::engine::BrowsingContext;
+    use crate::render::backend::{
+        ErasedSurface, ExternalHandle, PresentMode, RenderBackend, RgbaImage, SurfaceSize,
+    };
+    use crate::render::backends::null::NullBackend;
+    use crate::render::{DisplayItem, PrintToPdf, RenderMode, Viewport};
+    use crate::tab::{
+        AuxViewportId, TabCacheMode, TabCookieJar, TabId, TabMode, TabOverrides, TabState,
+        MAX_PENDING_INPUT, MAX_PENDING_POPUPS,
+    };
+    use crate::zone::{Extension, ExtensionId, UrlResolver, ZoneConfig};
+    use crate::{
+        DragPayload, DropEffect, EngineCommand, EngineConfig, EngineError, EngineEvent,
+        EngineNotification, GosubEngine, MouseButton, NavigationDisposition,
+        NotificationCategories, ZoneCommand,
+    };
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use url::Url;
+
+    #[test]
+    fn stopping_a_load_reports_the_aborted_url() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let tab = engine.get_tab(tab_id).unwrap();
+        let cancelled = |rx: &std::sync::mpsc::Receiver<EngineNotification>| -> Vec<Url> {
+            rx.try_iter()
+                .filter_map(|n| match n {
+                    EngineNotification::LoadCancelled { url, .. } => Some(url),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Stopped before the load started
+        let first = Url::parse("https://first.test/").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(first.clone()))
+            .unwrap();
+        assert!(tab.lock().unwrap().is_loading);
+        engine
+            .execute_command(tab_id, EngineCommand::StopLoading)
+            .unwrap();
+        assert_eq!(cancelled(&rx), vec![first]);
+
+        // Stopped while fetching
+        let second = Url::parse("https://second.test/").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(second.clone()))
+            .unwrap();
+        tick(&mut engine);
+        assert_eq!(tab.lock().unwrap().state, TabState::Loading);
+        engine
+            .execute_command(tab_id, EngineCommand::StopLoading)
+            .unwrap();
+        assert_eq!(cancelled(&rx), vec![second]);
+        {
+            let tab = tab.lock().unwrap();
+            assert_eq!(tab.state, TabState::Idle);
+            assert!(!tab.is_loading);
+        }
+
+        engine
+            .execute_command(tab_id, EngineCommand::StopLoading)
+            .unwrap();
+        tick(&mut engine);
+        assert!(rx.try_iter().next().is_none());
+    }
+
+    #[test]
+    fn typed_input_is_resolved_by_the_zone() {
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let tab = engine.get_tab(tab_id).unwrap();
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::NavigateInput("example.test/a".into()),
+            )
+            .unwrap();
+        assert_eq!(
+            tab.lock().unwrap().state,
+            TabState::PendingLoad(Url::parse("https://example.test/a").unwrap())
+        );
+
+        engine
+            .execute_command(tab_id, EngineCommand::NavigateInput("two words".into()))
+            .unwrap();
+        let failures: Vec<_> = rx
+            .try_iter()
+            .filter(|n| matches!(n, EngineNotification::NavigationFailed { .. }))
+            .collect();
+        assert_eq!(failures.len(), 1);
+
+        engine
+            .get_zone_mut(zone_id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .set_url_resolver(UrlResolver::new().with_fallback(|input| {
+                Url::parse(&format!("https://search.test/?q={input}")).ok()
+            }));
+        engine
+            .execute_command(tab_id, EngineCommand::NavigateInput("two words".into()))
+            .unwrap();
+        assert_eq!(
+            tab.lock().unwrap().state,
+            TabState::PendingLoad(Url::parse("https://search.test/?q=two%20words").unwrap())
+        );
+    }
+
+    #[test]
+    fn closed_tabs_stop_and_free_their_slot() {
+        let config = EngineConfig::builder()
+            .default_zone_config(ZoneConfig::builder().max_tabs(1).build().unwrap())
+            .build()
+            .unwrap();
+        let mut engine = new_engine(Some(config));
+        let zone_id = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_notifications();
+        let viewport = Viewport::new(0, 0, 800, 600);
+        let tab_id = engine.open_tab_in_zone(zone_id, viewport).unwrap();
+        assert!(matches!(
+            engine.open_tab_in_zone(zone_id, viewport),
+            Err(EngineError::TabLimitExceeded)
+        ));
+
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let group = zone.lock().unwrap().create_group("Work", [0, 0, 0, 255]);
+        zone.lock()
+            .unwrap()
+            .add_tab_to_group(tab_id, group)
+            .unwrap();
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::Navigate(Url::parse("https://example.test/").unwrap()),
+            )
+            .unwrap();
+        tick(&mut engine);
+        let tab = engine.get_tab(tab_id).unwrap();
+        let _ = rx.try_iter().count();
+
+        engine
+            .execute_command(tab_id, EngineCommand::CloseTab)
+            .unwrap();
+        assert!(matches!(
+            engine.execute_command(tab_id, EngineCommand::CloseTab),
+            Err(EngineError::InvalidTabId)
+        ));
+        assert!(engine.get_tab(tab_id).is_none());
+        assert!(!tab.lock().unwrap().is_loading);
+        assert!(zone
+            .lock()
+            .unwrap()
+            .tab_group(group)
+            .unwrap()
+            .tabs
+            .is_empty());
+
+        let closed: Vec<_> = rx
+            .try_iter()
+            .filter(
+                |n| matches!(n, EngineNotification::TabClosed { tab_id: t, .. } if *t == tab_id),
+            )
+            .collect();
+        assert_eq!(closed.len(), 1);
+
+        tick(&mut engine);
+        assert!(rx.try_iter().all(|n| n.tab_id() != Some(tab_id)));
+        assert!(engine.open_tab_in_zone(zone_id, viewport).is_ok());
+    }
+
+    #[test]
+    fn killed_tabs_are_dropped_even_while_held() {
+        let config = EngineConfig::builder()
+            .tab_watchdog_timeout(Some(Duration::from_secs(5)))
+            .build()
+            .unwrap();
+        let (mut engine, _, tab_id) = engine_with_tab(Some(config));
+        let rx = engine.subscribe_notifications();
+
+        // Another thread is stuck with the tab; the engine does not wait for it
+        let tab = engine.get_tab(tab_id).unwrap();
+        let held = tab.lock().unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::ForceKill)
+            .unwrap();
+        assert!(held.is_killed());
+        drop(held);
+
+        assert!(engine.get_tab(tab_id).is_none());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::TabClosed { tab_id: t, .. }) if t == tab_id
+        ));
+        assert!(matches!(
+            engine.execute_command(tab_id, EngineCommand::ForceKill),
+            Err(EngineError::InvalidTabId)
+        ));
+    }
+
+    #[test]
+    fn tabs_killed_during_a_tick_are_closed_after_it() {
+        let config = EngineConfig::builder()
+            .tab_watchdog_timeout(Some(Duration::from_secs(5)))
+            .build()
+            .unwrap();
+        let (mut engine, _, tab_id) = engine_with_tab(Some(config));
+        assert!(engine.tab_killer().is_some());
+        let rx = engine.subscribe_notifications();
+
+        // What a killer on another thread does to a tab stuck in its tick
+        let tab = engine.get_tab(tab_id).unwrap();
+        tab.lock()
+            .unwrap()
+            .cancel_flag()
+            .store(true, Ordering::Relaxed);
+        tick(&mut engine);
+
+        assert!(engine.get_tab(tab_id).is_none());
+        assert!(rx
+            .try_iter()
+            .any(|n| matches!(n, EngineNotification::TabClosed { tab_id: t, .. } if t == tab_id)));
+        assert!(new_engine(None).tab_killer().is_none());
+    }
+
+    #[test]
+    fn killed_tabs_give_up_their_parse() {
+        let (engine, _, tab_id) = engine_with_tab(None);
+        let tab = engine.get_tab(tab_id).unwrap();
+        let mut tab = tab.lock().unwrap();
+
+        tab.cancel_flag().store(true, Ordering::Relaxed);
+        tab.context.start_parsing(b"<p>never shown</p>".to_vec());
+        let mut parsed = false;
+        for _ in 0..200 {
+            parsed = tab.context.poll_parsing();
+            if parsed {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(parsed);
+        assert_eq!(tab.context.raw_html(), "");
+    }
+
+    #[test]
+    fn killed_tabs_give_up_their_layout() {
+        let (engine, _, tab_id) = engine_with_tab(None);
+        let tab = engine.get_tab(tab_id).unwrap();
+        let mut tab = tab.lock().unwrap();
+
+        tab.context.set_raw_html("<p>never laid out</p>");
+        tab.cancel_flag().store(true, Ordering::Relaxed);
+        tab.context.rebuild_render_list_if_needed();
+        assert!(!tab
+            .context
+            .render_list()
+            .items
+            .iter()
+            .any(|item| matches!(item, DisplayItem::TextRun { .. })));
+    }
+
+    #[test]
+    fn input_for_tabs_that_do_not_tick_is_capped() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let key = |key: &str| EngineEvent::KeyDown { key: key.into() };
+
+        for _ in 0..MAX_PENDING_INPUT {
+            engine.handle_event(tab_id, key("a")).unwrap();
+        }
+        engine.handle_event(tab_id, key("b")).unwrap();
+        let tab = engine.get_tab(tab_id).unwrap();
+        {
+            let tab = tab.lock().unwrap();
+            assert_eq!(tab.pending_input.len(), MAX_PENDING_INPUT);
+            assert!(matches!(
+                tab.pending_input.last(),
+                Some(EngineEvent::KeyDown { key }) if key == "b"
+            ));
+        }
+
+        // Once killed, the tab never ticks again, so its input is dropped right away
+        tab.lock().unwrap().pending_input.clear();
+        tab.lock()
+            .unwrap()
+            .cancel_flag()
+            .store(true, Ordering::Relaxed);
+        engine.handle_event(tab_id, key("c")).unwrap();
+        assert!(tab.lock().unwrap().pending_input.is_empty());
+    }
+
+    #[test]
+    fn aux_viewports_get_their_own_frames() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+
+        let preview = AuxViewportId(1);
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::AddAuxViewport {
+                    id: preview,
+                    viewport: Viewport::new(0, 0, 200, 150),
+                },
+            )
+            .unwrap();
+
+        let mut frames = Vec::new();
+        for _ in 0..3 {
+            frames.extend(engine.collect_frames());
+        }
+        assert!(frames
+            .iter()
+            .any(|f| f.tab_id == tab_id && f.aux_viewport.is_none()));
+        let aux = frames
+            .iter()
+            .find(|f| f.aux_viewport == Some(preview))
+            .expect("no frame for the aux viewport");
+        assert!(matches!(
+            aux.handle,
+            crate::render::backend::ExternalHandle::NullHandle {
+                width: 200,
+                height: 150,
+                ..
+            }
+        ));
+
+        // The main viewport is restored after rendering the preview
+        let tab = engine.get_tab(tab_id).unwrap();
+        assert_eq!(
+            *tab.lock().unwrap().context.viewport(),
+            Viewport::new(0, 0, 800, 600)
+        );
+    }
+
+    #[test]
+    fn fragment_navigation_scrolls_without_a_load() {
+        let mut engine = new_engine(None);
+        let zone_id = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_notifications();
+        let tab_id = open_tab(&mut engine, zone_id);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::TabOpened { tab_id: t, .. }) if t == tab_id
+        ));
+
+        // Pretend the page has been loaded
+        let page = Url::parse("https://example.test/page").unwrap();
+        {
+            let tab = engine.get_tab(tab_id).unwrap();
+            let mut tab = tab.lock().unwrap();
+            tab.context
+                .set_raw_html("<h1>Title</h1>\n<p>Intro</p>\n<h2 id=\"usage\">Usage</h2>\n");
+            tab.context.navigate_to_fragment(page.clone());
+        }
+
+        let usage = page.join("#usage").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(usage.clone()))
+            .unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(page.join("#top").unwrap()))
+            .unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::GoBack())
+            .unwrap();
+
+        let urls: Vec<_> = rx
+            .try_iter()
+            .map(|n| match n {
+                EngineNotification::LocationChanged { tab_id: t, url, .. } if t == tab_id => url,
+                other => panic!("unexpected notification: {other:?}"),
+            })
+            .collect();
+        assert_eq!(urls.len(), 3);
+        assert_eq!(urls[2], usage);
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let tab = tab.lock().unwrap();
+        assert_eq!(tab.current_url.as_ref(), Some(&usage));
+        assert_eq!(tab.context.viewport().y, 32);
+        assert!(!matches!(tab.state, TabState::PendingLoad(_)));
+        assert!(tab.state_handle().get().can_go_forward);
+    }
+
+    #[test]
+    fn forms_targeting_blank_open_a_popup_once_the_user_agent_allows_it() {
+        let (mut engine, _, opener) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let (x, y) = {
+            let tab = engine.get_tab(opener).unwrap();
+            let mut tab = tab.lock().unwrap();
+            tab.context.set_raw_html(
+                "<form action=search target=_blank><input name=q value=ok><button>Go</button></form>",
+            );
+            tab.context
+                .navigate_to_fragment(Url::parse("myapp://docs/index").unwrap());
+            let (x, y, _, _) = tab.context.forms().controls()[1].rect;
+            (x + 2.0, y + 2.0)
+        };
+        let click = |engine: &mut GosubEngine| {
+            let button = MouseButton::Left;
+            engine
+                .handle_event(opener, EngineEvent::MouseDown { button, x, y })
+                .unwrap();
+            tick(engine);
+            rx.try_iter()
+                .find_map(|n| match n {
+                    EngineNotification::PopupRequested {
+                        request_id, url, ..
+                    } => Some((request_id, url)),
+                    _ => None,
+                })
+                .expect("no popup requested")
+        };
+
+        let (refused, _) = click(&mut engine);
+        let resolve = |request_id, allow| EngineCommand::ResolvePopup {
+            request_id,
+            allow,
+            zone_id: None,
+        };
+        engine
+            .execute_command(opener, resolve(refused, false))
+            .unwrap();
+        assert!(matches!(
+            engine.execute_command(opener, resolve(refused, true)),
+            Err(EngineError::UnknownPopupRequest)
+        ));
+
+        let (allowed, url) = click(&mut engine);
+        assert_eq!(url.as_str(), "myapp://docs/search?q=ok");
+        engine
+            .execute_command(opener, resolve(allowed, true))
+            .unwrap();
+        let popup = rx
+            .try_iter()
+            .find_map(|n| match n {
+                EngineNotification::PopupOpened {
+                    tab_id, request_id, ..
+                } if request_id == allowed => Some(tab_id),
+                _ => None,
+            })
+            .expect("no popup opened");
+
+        let tab = engine.get_tab(popup).unwrap();
+        let tab = tab.lock().unwrap();
+        assert_eq!(tab.opener(), Some(opener));
+        assert_eq!(tab.state, TabState::PendingLoad(url));
+    }
+
+    #[test]
+    fn modified_and_middle_clicks_ask_the_user_agent_where_to_open() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let ((x, y), input) = {
+            let tab = engine.get_tab(tab_id).unwrap();
+            let mut tab = tab.lock().unwrap();
+            tab.context.set_raw_html(
+                "<form action=search><input name=q value=ok><button>Go</button></form>",
+            );
+            tab.context
+                .navigate_to_fragment(Url::parse("myapp://docs/index").unwrap());
+            let (x, y, _, _) = tab.context.forms().controls()[1].rect;
+            let (ix, iy, _, _) = tab.context.forms().controls()[0].rect;
+            ((x + 2.0, y + 2.0), (ix + 2.0, iy + 2.0))
+        };
+        let key = |key: &str, down: bool| {
+            let key = key.to_string();
+            if down {
+                EngineEvent::KeyDown { key }
+            } else {
+                EngineEvent::KeyUp { key }
+            }
+        };
+        let click = |engine: &mut GosubEngine, button, keys: &[&str]| {
+            for k in keys {
+                engine.handle_event(tab_id, key(k, true)).unwrap();
+            }
+            engine
+                .handle_event(tab_id, EngineEvent::MouseDown { button, x, y })
+                .unwrap();
+            for k in keys {
+                engine.handle_event(tab_id, key(k, false)).unwrap();
+            }
+            tick(engine);
+            rx.try_iter().find_map(|n| match n {
+                EngineNotification::OpenUrlRequested {
+                    request_id,
+                    disposition,
+                    url,
+                    ..
+                } => Some((request_id, disposition, url)),
+                _ => None,
+            })
+        };
+
+        let (_, disposition, url) =
+            click(&mut engine, MouseButton::Left, &["Control", "Shift"]).unwrap();
+        assert_eq!(disposition, NavigationDisposition::NewForegroundTab);
+        assert_eq!(url.as_str(), "myapp://docs/search?q=ok");
+
+        let (request_id, disposition, _) = click(&mut engine, MouseButton::Middle, &[]).unwrap();
+        assert_eq!(disposition, NavigationDisposition::NewBackgroundTab);
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::ResolvePopup {
+                    request_id,
+                    allow: true,
+                    zone_id: None,
+                },
+            )
+            .unwrap();
+        assert!(rx.try_iter().any(
+            |n| matches!(n, EngineNotification::PopupOpened { opener, .. } if opener == tab_id)
+        ));
+
+        // Enter with modifiers asks the same as a click
+        let events = [
+            EngineEvent::MouseDown {
+                button: MouseButton::Left,
+                x: input.0,
+                y: input.1,
+            },
+            key("Control", true),
+            key("Enter", true),
+            key("Control", false),
+        ];
+        for event in events {
+            engine.handle_event(tab_id, event).unwrap();
+        }
+        tick(&mut engine);
+        assert!(rx.try_iter().any(|n| matches!(
+            n,
+            EngineNotification::OpenUrlRequested {
+                disposition: NavigationDisposition::NewBackgroundTab,
+                ..
+            }
+        )));
+
+        // Requests the user agent leaves unanswered are capped, and dropped once the tab
+        // navigates; the modifiers were released, so a plain click submits in the tab itself
+        for _ in 0..MAX_PENDING_POPUPS + 2 {
+            click(&mut engine, MouseButton::Middle, &[]).unwrap();
+        }
+        let tab = engine.get_tab(tab_id).unwrap();
+        assert_eq!(tab.lock().unwrap().pending_popups.len(), MAX_PENDING_POPUPS);
+        assert!(click(&mut engine, MouseButton::Left, &[]).is_none());
+        assert!(tab.lock().unwrap().pending_popups.is_empty());
+    }
+
+    #[test]
+    fn typing_into_a_form_and_submitting_loads_the_action() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        // The custom scheme makes the sandbox refuse the submission, so nothing hits the network
+        let (x, y) = {
+            let tab = engine.get_tab(tab_id).unwrap();
+            let mut tab = tab.lock().unwrap();
+            tab.context
+                .set_raw_html("<form action=search><input name=q><button>Go</button></form>");
+            tab.context
+                .navigate_to_fragment(Url::parse("myapp://docs/index").unwrap());
+            let (x, y, _, _) = tab.context.forms().controls()[0].rect;
+            (x + 2.0, y + 2.0)
+        };
+
+        let events = [
+            EngineEvent::MouseDown {
+                button: MouseButton::Left,
+                x,
+                y,
+            },
+            EngineEvent::InputChar { character: 'o' },
+            EngineEvent::InputChar { character: 'k' },
+            EngineEvent::KeyDown {
+                key: "Enter".into(),
+            },
+        ];
+        for event in events {
+            engine.handle_event(tab_id, event).unwrap();
+        }
+        tick(&mut engine);
+        tick(&mut engine);
+
+        let failed = rx
+            .try_iter()
+            .find_map(|n| match n {
+                EngineNotification::LoadFailed { url, .. } => Some(url),
+                _ => None,
+            })
+            .expect("the submission was not loaded");
+        assert_eq!(failed.as_str(), "myapp://docs/search?q=ok");
+    }
+
+    #[test]
+    fn drops_insert_text_or_navigate() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let (x, y) = {
+            let mut tab = tab.lock().unwrap();
+            tab.context.set_raw_html("<input name=q value='drop '>");
+            let (x, y, _, _) = tab.context.forms().controls()[0].rect;
+            (x + 1.0, y + 1.0)
+        };
+        let text = DragPayload::Text("here".into());
+        let file = DragPayload::Files(vec![std::env::temp_dir().join("page.html")]);
+        let link = DragPayload::Urls(vec![Url::parse("https://example.test/").unwrap()]);
+        for command in [
+            // Text is only accepted over the text field
+            EngineCommand::DragEnter {
+                x: 700.0,
+                y: 500.0,
+                payload: text.clone(),
+            },
+            EngineCommand::DragOver { x, y },
+            EngineCommand::Drop {
+                x,
+                y,
+                payload: text,
+            },
+            // Files need local file access, which the zone does not allow
+            EngineCommand::DragEnter {
+                x: 700.0,
+                y: 500.0,
+                payload: file.clone(),
+            },
+            EngineCommand::Drop {
+                x: 700.0,
+                y: 500.0,
+                payload: file,
+            },
+            EngineCommand::DragEnter {
+                x: 700.0,
+                y: 500.0,
+                payload: link.clone(),
+            },
+            EngineCommand::DragLeave,
+        ] {
+            engine.execute_command(tab_id, command).unwrap();
+        }
+
+        let effects: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::DropEffectChanged { effect, .. } => Some(effect),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            effects,
+            [
+                DropEffect::InsertText,
+                DropEffect::Navigate,
+                DropEffect::None
+            ]
+        );
+        assert_eq!(
+            tab.lock().unwrap().context.forms().controls()[0].value,
+            "drop here"
+        );
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::Drop {
+                    x: 700.0,
+                    y: 500.0,
+                    payload: link,
+                },
+            )
+            .unwrap();
+        assert!(matches!(
+            tab.lock().unwrap().state,
+            TabState::PendingLoad(ref url) if url.as_str() == "https://example.test/"
+        ));
+    }
+
+    #[test]
+    fn dropped_files_go_into_file_inputs() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let (x, y) = {
+            let mut tab = tab.lock().unwrap();
+            tab.context.set_raw_html("<input type=file name=photo>");
+            let (x, y, _, _) = tab.context.forms().controls()[0].rect;
+            (x + 1.0, y + 1.0)
+        };
+        let file = DragPayload::Files(vec![std::env::temp_dir().join("photo.png")]);
+        let page = std::env::temp_dir().join("page.html");
+        let local = DragPayload::Urls(vec![Url::from_file_path(page).unwrap()]);
+        for command in [
+            // Files are refused outside the file input
+            EngineCommand::DragEnter {
+                x: 700.0,
+                y: 500.0,
+                payload: file.clone(),
+            },
+            EngineCommand::DragOver { x, y },
+            EngineCommand::Drop {
+                x,
+                y,
+                payload: file,
+            },
+            // The zone does not allow local file access
+            EngineCommand::DragEnter {
+                x: 700.0,
+                y: 500.0,
+                payload: local.clone(),
+            },
+            EngineCommand::Drop {
+                x: 700.0,
+                y: 500.0,
+                payload: local,
+            },
+        ] {
+            engine.execute_command(tab_id, command).unwrap();
+        }
+
+        let effects: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::DropEffectChanged { effect, .. } => Some(effect),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(effects, [DropEffect::ChooseFiles]);
+        let tab = tab.lock().unwrap();
+        assert_eq!(tab.context.forms().controls()[0].value, "photo.png");
+        assert!(!matches!(tab.state, TabState::PendingLoad(_)));
+    }
+
+    #[test]
+    fn clipboard_commands_edit_the_focused_control() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        {
+            let mut tab = tab.lock().unwrap();
+            tab.context.set_raw_html("<input name=q value='gosub'>");
+            let (x, y, _, _) = tab.context.forms().controls()[0].rect;
+            tab.context.form_click(x + 1.0, y + 1.0);
+        }
+
+        for command in [
+            EngineCommand::Copy,
+            EngineCommand::Paste {
+                text: " engine".into(),
+            },
+            EngineCommand::SelectAll,
+            EngineCommand::Cut,
+        ] {
+            engine.execute_command(tab_id, command).unwrap();
+        }
+
+        // Nothing was selected for the first copy
+        let copied: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::ClipboardRequested {
+                    tab_id: t, text, ..
+                } if t == tab_id => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(copied, ["gosub engine"]);
+        assert_eq!(tab.lock().unwrap().context.forms().controls()[0].value, "");
+    }
+
+    #[test]
+    fn printing_publishes_the_pdf_or_warns() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        {
+            let tab = engine.get_tab(tab_id).unwrap();
+            let mut tab = tab.lock().unwrap();
+            tab.context.set_raw_html("<p>printed</p>");
+            tab.context.rebuild_render_list_if_needed();
+        }
+
+        let footer = PrintToPdf {
+            footer_template: Some("{title}: {page}/{pages}".into()),
+            ..PrintToPdf::default()
+        };
+        let too_large = PrintToPdf {
+            scale: 4.0,
+            ..PrintToPdf::default()
+        };
+        for options in [footer, too_large] {
+            engine
+                .execute_command(tab_id, EngineCommand::PrintToPdf { options })
+                .unwrap();
+        }
+
+        let notifications: Vec<_> = rx.try_iter().collect();
+        let pdf = notifications
+            .iter()
+            .find_map(|n| match n {
+                EngineNotification::PdfPrinted { pdf, .. } => Some(pdf),
+                _ => None,
+            })
+            .unwrap();
+        let pdf = String::from_utf8_lossy(pdf);
+        assert!(pdf.contains("(<p>printed</p>) Tj"));
+        assert!(pdf.contains("(New Tab: 1/1) Tj"));
+        assert!(notifications.iter().any(|n| matches!(
+            n,
+            EngineNotification::Warning { message, .. } if message.contains("scale 4")
+        )));
+    }
+
+    #[test]
+    fn file_choosers_are_answered_by_the_user_agent() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let click = || {
+            let mut tab = tab.lock().unwrap();
+            let (x, y, _, _) = tab.context.forms().controls()[0].rect;
+            let outcome = tab.context.form_click(x + 1.0, y + 1.0);
+            tab.apply_form_outcome(outcome);
+        };
+        tab.lock()
+            .unwrap()
+            .context
+            .set_raw_html("<input type=file name=photo accept='image/*, .PDF'>");
+        click();
+        click();
+
+        let requests: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::FileChooserRequested {
+                    request_id,
+                    accept,
+                    multiple,
+                    ..
+                } => Some((request_id, accept, multiple)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].1, ["image/*", ".pdf"]);
+        assert!(!requests[0].2);
+
+        let files = vec![PathBuf::from("/tmp/a.png"), PathBuf::from("/tmp/b.png")];
+        for (request_id, files) in [(requests[0].0, files), (requests[1].0, Vec::new())] {
+            engine
+                .execute_command(
+                    tab_id,
+                    EngineCommand::ResolveFileChooser { request_id, files },
+                )
+                .unwrap();
+        }
+
+        // Only one file fits, and cancelling the second chooser kept it
+        let tab = tab.lock().unwrap();
+        assert!(tab.pending_file_choosers.is_empty());
+        assert_eq!(tab.context.forms().controls()[0].value, "a.png");
+    }
+
+    #[test]
+    fn extension_messages_need_a_content_script() {
+        let mut engine = new_engine(None);
+        let zone_id = engine.zone_builder().create().unwrap();
+        let rx = engine.subscribe_notifications();
+        let tab_id = open_tab(&mut engine, zone_id);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::TabOpened { tab_id: t, .. }) if t == tab_id
+        ));
+
+        let manifest = r#"{"name": "Echo", "content_scripts": [{"matches": ["<all_urls>"], "js": ["echo.js"]}]}"#;
+        let id = ExtensionId("echo".into());
+        let ext = Extension::from_manifest(id.clone(), manifest, |_| Ok(String::new())).unwrap();
+        {
+            let zone = engine.get_zone_mut(zone_id).unwrap();
+            let mut zone = zone.lock().unwrap();
+            zone.load_extension(ext.clone()).unwrap();
+            assert!(zone.load_extension(ext).is_err());
+        }
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        {
+            let mut tab = tab.lock().unwrap();
+            assert!(!tab.post_extension_message(id.clone(), "early".into()));
+
+            // Commit a document the way a finished load does
+            let url = Url::parse("https://example.test/").unwrap();
+            let (styles, scripts) = tab.user_content.read().unwrap().matching(&url);
+            tab.context.set_user_content(&styles, scripts);
+            assert!(tab.post_extension_message(id.clone(), "{\"ping\":1}".into()));
+        }
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::ExtensionMessage { extension_id, message, .. })
+                if extension_id == id && message == "{\"ping\":1}"
+        ));
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::MessageExtension {
+                    extension_id: id.clone(),
+                    message: "pong".into(),
+                },
+            )
+            .unwrap();
+        let messages = tab.lock().unwrap().context.take_extension_messages();
+        assert_eq!(messages, vec![(id, "pong".to_string())]);
+    }
+
+    #[test]
+    fn privacy_headers_follow_zone_config_and_tab_overrides() {
+        let config = ZoneConfig::builder().do_not_track(true).build().unwrap();
+        let (mut engine, _, tab_id) = engine_with_tab_in(None, |zone| zone.config(config));
+        let navigate = |engine: &mut GosubEngine| {
+            let tab = navigate_offline(engine, tab_id);
+            let tab = tab.lock().unwrap();
+            let headers = tab.context.request_headers();
+            (headers.contains_key("dnt"), headers.contains_key("sec-gpc"))
+        };
+
+        assert_eq!(navigate(&mut engine), (true, false));
+
+        let overrides = TabOverrides {
+            do_not_track: Some(false),
+            global_privacy_control: Some(true),
+            ..Default::default()
+        };
+        engine
+            .execute_command(tab_id, EngineCommand::SetOverrides(overrides))
+            .unwrap();
+        assert_eq!(navigate(&mut engine), (false, true));
+    }
+
+    #[test]
+    fn locale_and_time_zone_follow_zone_config_and_tab_overrides() {
+        assert!(ZoneConfig::builder().locale("dutch!").build().is_err());
+        assert!(ZoneConfig::builder().timezone("Mars Base").build().is_err());
+
+        let config = ZoneConfig::builder()
+            .locale("nl-NL")
+            .timezone("Europe/Amsterdam")
+            .build()
+            .unwrap();
+        let (mut engine, _, tab_id) = engine_with_tab_in(None, |zone| zone.config(config));
+        let navigate = |engine: &mut GosubEngine| {
+            let tab = navigate_offline(engine, tab_id);
+            let tab = tab.lock().unwrap();
+            let context = &tab.context;
+            (
+                context.request_headers()["accept-language"].clone(),
+                context.locale().map(str::to_string),
+                context.timezone().map(str::to_string),
+            )
+        };
+
+        assert_eq!(
+            navigate(&mut engine),
+            (
+                "nl-NL,nl;q=0.9".parse().unwrap(),
+                Some("nl-NL".into()),
+                Some("Europe/Amsterdam".into())
+            )
+        );
+
+        let overrides = TabOverrides {
+            locale: Some("ja-JP".into()),
+            timezone: Some("Asia/Tokyo".into()),
+            ..Default::default()
+        };
+        engine
+            .execute_command(tab_id, EngineCommand::SetOverrides(overrides))
+            .unwrap();
+        assert_eq!(
+            navigate(&mut engine),
+            (
+                "ja-JP,ja;q=0.9".parse().unwrap(),
+                Some("ja-JP".into()),
+                Some("Asia/Tokyo".into())
+            )
+        );
+    }
+
+    #[test]
+    fn automated_engines_disclose_themselves() {
+        let open = |automated: bool| {
+            let config = crate::EngineConfig::builder()
+                .user_agent("Gosub/0.1")
+                .automated(automated)
+                .build()
+                .unwrap();
+            let (mut engine, _, tab_id) = engine_with_tab(Some(config));
+            let tab = navigate_offline(&mut engine, tab_id);
+            let tab = tab.lock().unwrap();
+            let user_agent = tab.context.request_headers()["user-agent"].clone();
+            (user_agent, tab.context.webdriver())
+        };
+
+        assert_eq!(open(false), ("Gosub/0.1".parse().unwrap(), false));
+        assert_eq!(open(true), ("Gosub/0.1 Headless".parse().unwrap(), true));
+    }
+
+    #[test]
+    fn engine_config_updates_reach_open_tabs() {
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+
+        engine
+            .update_config(|c| {
+                c.text_hinting = crate::render::TextHinting::None;
+                c.memory_cache_bytes = 1024;
+            })
+            .unwrap();
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let options = tab.lock().unwrap().context.text_options();
+        assert_eq!(options.hinting, crate::render::TextHinting::None);
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        assert_eq!(zone.lock().unwrap().http_cache().capacity(), 1024);
+    }
+
+    #[test]
+    fn zone_config_updates_reach_open_tabs() {
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+
+        zone.lock()
+            .unwrap()
+            .update_config(|c| c.do_not_track = true)
+            .unwrap();
+        assert!(rx.try_iter().any(|n| matches!(
+            n,
+            EngineNotification::ZoneConfigChanged { zone_id: id } if id == zone_id
+        )));
+
+        let tab = navigate_offline(&mut engine, tab_id);
+        assert!(tab
+            .lock()
+            .unwrap()
+            .context
+            .request_headers()
+            .contains_key("dnt"));
+
+        let err = zone.lock().unwrap().set_font_scale(20.0).unwrap_err();
+        assert!(matches!(err, crate::EngineError::InvalidConfiguration(_)));
+        assert!(!rx
+            .try_iter()
+            .any(|n| matches!(n, EngineNotification::ZoneConfigChanged { .. })));
+    }
+
+    #[test]
+    fn site_settings_apply_when_a_document_commits() {
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+
+        let store = engine
+            .get_zone_mut(zone_id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .site_settings();
+        let url = Url::parse("https://quiet.test/article").unwrap();
+        store.update(&url, |s| {
+            s.javascript = Some(false);
+            s.zoom = Some(1.5);
+        });
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let mut tab = tab.lock().unwrap();
+        assert!(tab.context.javascript_enabled());
+        tab.apply_site_settings(&url);
+        assert!(!tab.context.javascript_enabled());
+        assert!(tab.context.images_enabled());
+        assert_eq!(tab.context.zoom(), 1.5);
+
+        tab.apply_site_settings(&Url::parse("https://other.test/").unwrap());
+        assert!(tab.context.javascript_enabled());
+    }
+
+    #[test]
+    fn provided_content_commits_like_a_navigation() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        // A custom scheme would be refused by the sandbox if it went to the network
+        let base = Url::parse("myapp://generated/report").unwrap();
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html: "<h1>Report</h1>".to_string(),
+                    base_url: Some(base.clone()),
+                },
+            )
+            .unwrap();
+        let loaded = tick_until(&mut engine, &rx, |n| match n {
+            EngineNotification::PageLoaded { url, .. } => Some(url),
+            EngineNotification::LoadFailed { error, .. } => panic!("load failed: {error}"),
+            _ => None,
+        });
+        assert_eq!(loaded, Some(base.clone()));
+        {
+            let tab = engine.get_tab(tab_id).unwrap();
+            let tab = tab.lock().unwrap();
+            assert_eq!(tab.current_url, Some(base));
+            assert_eq!(tab.context.raw_html(), "<h1>Report</h1>");
+        }
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadBytes {
+                    data: vec![0x89, b'P', b'N', b'G'],
+                    mime: "image/png".to_string(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+        tick(&mut engine);
+        let error = rx.try_iter().find_map(|n| match n {
+            EngineNotification::LoadFailed { url, error, .. } => Some((url, error)),
+            _ => None,
+        });
+        let (url, error) = error.expect("binary content was not refused");
+        assert_eq!(url.as_str(), "about:blank");
+        assert!(error.contains("image/png"));
+    }
+
+    #[test]
+    fn console_messages_of_the_document_are_published_when_captured() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let base = Url::parse("https://example.com/latin1.html").unwrap();
+
+        let load = |engine: &mut GosubEngine| {
+            engine
+                .execute_command(
+                    tab_id,
+                    EngineCommand::LoadBytes {
+                        data: b"<p>ok</p>\n<p>caf\xe9</p>".to_vec(),
+                        mime: "text/html".to_string(),
+                        base_url: Some(base.clone()),
+                    },
+                )
+                .unwrap();
+            let mut messages = Vec::new();
+            for _ in 0..200 {
+                tick(engine);
+                let mut loaded = false;
+                for n in rx.try_iter() {
+                    match n {
+                        EngineNotification::ConsoleMessage {
+                            level,
+                            source,
+                            line,
+                            ..
+                        } => messages.push((level, source, line)),
+                        EngineNotification::PageLoaded { .. } => loaded = true,
+                        _ => {}
+                    }
+                }
+                if loaded {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            messages
+        };
+
+        assert!(load(&mut engine).is_empty());
+
+        engine
+            .execute_command(tab_id, EngineCommand::EnableConsoleCapture(true))
+            .unwrap();
+        assert_eq!(
+            load(&mut engine),
+            vec![(crate::ConsoleLevel::Warn, Some(base.clone()), Some(2))]
+        );
+    }
+
+    #[test]
+    fn parse_issues_are_published_and_counted_in_the_tab_state() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let state = engine.tab_state(tab_id).unwrap();
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadBytes {
+                    data: b"<meta charset=\"windows-1252\">\n<p>caf\xe9</p>".to_vec(),
+                    mime: "text/html".to_string(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+        let issues = tick_until(&mut engine, &rx, |n| match n {
+            EngineNotification::ParseIssues { issues, .. } => Some(issues),
+            _ => None,
+        });
+
+        let kinds: Vec<_> = issues
+            .expect("no parse issues published")
+            .into_iter()
+            .map(|issue| (issue.kind, issue.line))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    ParseIssueKind::UnsupportedEncoding("windows-1252".to_string()),
+                    1
+                ),
+                (ParseIssueKind::InvalidUtf8, 2),
+            ]
+        );
+        assert_eq!(state.get().parse_issues, 2);
+    }
+
+    #[test]
+    fn cache_modes_choose_which_cache_a_tab_uses() {
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let zone_cache = engine
+            .get_zone_mut(zone_id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .http_cache();
+        let url = Url::parse("https://cached.test/").unwrap();
+        let mut headers = http::HeaderMap::new();
+        headers.insert("cache-control", "max-age=300".parse().unwrap());
+        zone_cache.put(&crate::net::Response {
+            url: url.clone(),
+            status: 200,
+            status_text: "OK".to_string(),
+            headers,
+            body: b"<p>from cache</p>".to_vec(),
+            redirects: Vec::new(),
+        });
+
+        // Served from the zone cache, so no network request is made
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let mut tab = tab.lock().unwrap();
+        assert_eq!(tab.context.raw_html(), "<p>from cache</p>");
+
+        let mode = |mode| TabOverrides {
+            cache_mode: mode,
+            ..Default::default()
+        };
+        tab.set_overrides(mode(TabCacheMode::Ephemeral));
+        let ephemeral = tab.http_cache().unwrap();
+        assert!(!Arc::ptr_eq(&ephemeral, &zone_cache));
+        assert!(ephemeral.get(&url).is_none());
+
+        tab.set_overrides(mode(TabCacheMode::Bypass));
+        assert!(tab.http_cache().is_none());
+
+        // A zone without caching leaves every mode without a cache
+        tab.bind_cache(None);
+        tab.set_overrides(mode(TabCacheMode::Default));
+        assert!(tab.http_cache().is_none());
+    }
+
+    #[test]
+    fn a_new_navigation_cancels_the_parse_in_flight() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let load = |engine: &mut GosubEngine, html: &str, url: &str| {
+            engine
+                .execute_command(
+                    tab_id,
+                    EngineCommand::LoadHtml {
+                        html: html.to_string(),
+                        base_url: Some(Url::parse(url).unwrap()),
+                    },
+                )
+                .unwrap();
+        };
+        let state = |engine: &GosubEngine| {
+            engine
+                .get_tab(tab_id)
+                .unwrap()
+                .lock()
+                .unwrap()
+                .state
+                .clone()
+        };
+
+        load(&mut engine, "<p>stale</p>", "https://stale.test/");
+        for _ in 0..200 {
+            if state(&engine) == TabState::Parsing {
+                break;
+            }
+            tick(&mut engine);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(state(&engine), TabState::Parsing);
+
+        load(&mut engine, "<p>fresh</p>", "https://fresh.test/");
+        let mut committed = Vec::new();
+        for _ in 0..200 {
+            tick(&mut engine);
+            committed.extend(rx.try_iter().filter_map(|n| match n {
+                EngineNotification::PageLoaded { url, .. } => Some(url),
+                _ => None,
+            }));
+            if !committed.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(committed, vec![Url::parse("https://fresh.test/").unwrap()]);
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        assert_eq!(tab.lock().unwrap().context.raw_html(), "<p>fresh</p>");
+    }
+
+    #[test]
+    fn scrolling_reuses_the_previous_frame() {
+        let backend = crate::render::backends::software::SoftwareBackend::new().unwrap();
+        let mut engine = GosubEngine::new(None, Box::new(backend));
+        let rx = engine.subscribe_notifications();
+        let zone_id = engine.zone_builder().create().unwrap();
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 200, 100))
+            .unwrap();
+
+        let html: String = (0..50).map(|i| format!("<p>line {i}</p>\n")).collect();
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html,
+                    base_url: None,
+                },
+            )
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+        for _ in 0..8 {
+            tick(&mut engine);
+        }
+        assert_eq!(engine.jank_stats(tab_id).unwrap().cached_scroll_frames, 0);
+
+        engine
+            .handle_event(tab_id, EngineEvent::Scroll { dx: 0.0, dy: 30.0 })
+            .unwrap();
+        for _ in 0..8 {
+            tick(&mut engine);
+        }
+        let stats = engine.jank_stats(tab_id).unwrap();
+        assert_eq!(stats.cached_scroll_frames, 1);
+        assert!(stats.frames >= 2);
+
+        // On a HiDPI screen, scroll deltas are device pixels and the frame moves by as many
+        engine
+            .handle_event(tab_id, EngineEvent::ScaleFactorChanged { scale: 2.0 })
+            .unwrap();
+        for _ in 0..8 {
+            tick(&mut engine);
+        }
+        engine
+            .handle_event(tab_id, EngineEvent::Scroll { dx: 0.0, dy: 30.0 })
+            .unwrap();
+        for _ in 0..8 {
+            tick(&mut engine);
+        }
+        assert_eq!(engine.jank_stats(tab_id).unwrap().cached_scroll_frames, 2);
+        let tab = engine.get_tab(tab_id).unwrap();
+        let request = tab.lock().unwrap().context.render_request();
+        assert_eq!((request.scroll_y, request.device_scale), (45.0, 2.0));
+
+        // Text wraps at the width of the viewport in document pixels
+        let tab = tab.lock().unwrap();
+        let width = tab.context.viewport().width as f32 / 2.0;
+        assert!(tab.context.render_list().items.iter().any(|item| matches!(
+            item,
+            DisplayItem::TextRun { max_width, .. } if *max_width == Some(width)
+        )));
+    }
+
+    #[test]
+    fn zone_font_settings_apply_to_open_tabs() {
+        let backend = NullBackend::new().unwrap();
+        let mut engine = GosubEngine::new(None, Box::new(backend));
+        let rx = engine.subscribe_notifications();
+        let zone_id = engine.zone_builder().create().unwrap();
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 200, 100))
+            .unwrap();
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html: "<p>hello</p>".to_string(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+
+        let first_text_run = |engine: &GosubEngine| {
+            let tab = engine.get_tab(tab_id).unwrap();
+            let mut tab = tab.lock().unwrap();
+            tab.context.rebuild_render_list_if_needed();
+            tab.context
+                .render_list()
+                .items
+                .iter()
+                .find_map(|item| match item {
+                    DisplayItem::TextRun {
+                        size, font_family, ..
+                    } => Some((*size, font_family.clone())),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        assert_eq!(first_text_run(&engine), (16.0, None));
+
+        let config = ZoneConfig::builder()
+            .default_font_family("Serif")
+            .default_font_size(20)
+            .minimum_font_size(18)
+            .build()
+            .unwrap();
+        engine
+            .execute_zone_command(zone_id, ZoneCommand::UpdateConfig(Box::new(config.clone())))
+            .unwrap();
+        assert_eq!(first_text_run(&engine), (20.0, Some("Serif".to_string())));
+
+        let mut invalid = config;
+        invalid.minimum_font_size = 30;
+        assert!(engine
+            .execute_zone_command(zone_id, ZoneCommand::UpdateConfig(Box::new(invalid)))
+            .is_err());
+        assert_eq!(first_text_run(&engine), (20.0, Some("Serif".to_string())));
+    }
+
+    #[test]
+    fn hung_servers_fail_the_load_with_a_timeout() {
+        // Accepts connections but never answers them
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let _held: Vec<_> = listener.incoming().collect();
+        });
+
+        let config = EngineConfig::builder()
+            .request_timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let (mut engine, _, tab_id) = engine_with_tab(Some(config));
+        let rx = engine.subscribe_notifications();
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+
+        let error = tick_until(&mut engine, &rx, |n| match n {
+            EngineNotification::LoadFailed { error, .. } => Some(error),
+            _ => None,
+        });
+        assert_eq!(error.as_deref(), Some("timeout"));
+
+        tick(&mut engine);
+        let tab = engine.get_tab(tab_id).unwrap();
+        let tab = tab.lock().unwrap();
+        assert!(tab.is_error);
+        assert!(tab.context.raw_html().contains("took too long to respond"));
+    }
+
+    #[test]
+    fn offline_navigations_fail_right_away() {
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let _ = rx.try_iter().count();
+
+        for _ in 0..2 {
+            engine
+                .execute_command(tab_id, EngineCommand::SetNetworkState { online: false })
+                .unwrap();
+        }
+        assert!(!engine.is_online());
+        let changes: Vec<_> = rx.try_iter().collect();
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(
+            changes[0],
+            EngineNotification::NetworkStateChanged { zone_id: z, online: false } if z == zone_id
+        ));
+
+        let url = Url::parse("https://example.test/").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+        tick(&mut engine);
+        assert!(rx.try_iter().any(|n| matches!(
+            n,
+            EngineNotification::LoadFailed { ref error, .. } if error == "offline"
+        )));
+        tick(&mut engine);
+        let tab = engine.get_tab(tab_id).unwrap();
+        assert!(tab
+            .lock()
+            .unwrap()
+            .context
+            .raw_html()
+            .contains("You are offline"));
+
+        // Pages that need no network still load
+        let url = Url::parse("data:text/html,<p>local</p>").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+
+        engine.set_network_state(true);
+        assert!(rx.try_iter().any(|n| matches!(
+            n,
+            EngineNotification::NetworkStateChanged { online: true, .. }
+        )));
+    }
+
+    #[test]
+    fn ephemeral_cookie_jars_are_isolated_from_the_zone() {
+        let port = serve_html_with_headers("Set-Cookie: visit=1\r\n", "<p>hi</p>");
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+
+        let mut engine = new_engine(None);
+        let rx = engine.subscribe_notifications();
+        let zone_id = engine.zone_builder().create().unwrap();
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let zone_jar = zone.lock().unwrap().cookie_jar.clone();
+        let mut headers = http::HeaderMap::new();
+        headers.append(http::header::SET_COOKIE, "zone=1".parse().unwrap());
+        zone_jar
+            .write()
+            .unwrap()
+            .store_response_cookies(&url, &headers);
+
+        let load = |engine: &mut GosubEngine, cookie_jar| {
+            let tab_id = open_tab(engine, zone_id);
+            let overrides = TabOverrides {
+                cookie_jar,
+                ..Default::default()
+            };
+            engine
+                .execute_command(tab_id, EngineCommand::SetOverrides(overrides))
+                .unwrap();
+            engine
+                .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+                .unwrap();
+            let loaded = tick_until(engine, &rx, |n| {
+                matches!(n, EngineNotification::PageLoaded { tab_id: t, .. } if t == tab_id)
+                    .then_some(())
+            });
+            assert!(loaded.is_some());
+            engine.get_tab(tab_id).unwrap()
+        };
+        let cookies = |jar: &CookieJarHandle| jar.read().unwrap().get_request_cookies(&url);
+
+        let private = load(&mut engine, TabCookieJar::Ephemeral);
+        let private_jar = private.lock().unwrap().effective_cookie_jar().unwrap();
+        assert_eq!(cookies(&private_jar).as_deref(), Some("visit=1"));
+        assert_eq!(cookies(&zone_jar).as_deref(), Some("zone=1"));
+
+        let shared = load(&mut engine, TabCookieJar::Zone);
+        let shared_jar = shared.lock().unwrap().effective_cookie_jar().unwrap();
+        assert!(cookies(&shared_jar).unwrap().contains("visit=1"));
+        assert!(cookies(&zone_jar).unwrap().contains("visit=1"));
+        assert_eq!(cookies(&private_jar).as_deref(), Some("visit=1"));
+    }
+
+    #[test]
+    fn pinned_tabs_are_restored_with_the_session() {
+        let mut engine = new_engine(None);
+        let zone_id = engine.zone_builder().create().unwrap();
+        let pinned = open_tab(&mut engine, zone_id);
+        let other = open_tab(&mut engine, zone_id);
+
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let mut zone = zone.lock().unwrap();
+        assert!(zone.pinned_tabs().is_empty());
+        zone.restore_pinned_tabs(&[pinned, TabId::new()]);
+        assert_eq!(zone.pinned_tabs(), vec![pinned]);
+        drop(zone);
+
+        assert!(engine.tab_state(pinned).unwrap().get().pinned);
+        assert!(!engine.tab_state(other).unwrap().get().pinned);
+    }
+
+    #[test]
+    fn background_tabs_are_discarded_and_restored_on_activation() {
+        let config = EngineConfig {
+            tab_memory_limit_bytes: Some(0),
+            ..Default::default()
+        };
+        let mut engine = new_engine(Some(config));
+        let rx = engine.subscribe_notifications();
+        let zone_id = engine.zone_builder().create().unwrap();
+        let background = open_tab(&mut engine, zone_id);
+        let foreground = open_tab(&mut engine, zone_id);
+
+        let url = Url::parse("data:text/html,<p>kept</p>").unwrap();
+        for tab_id in [background, foreground] {
+            engine
+                .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+                .unwrap();
+        }
+        let mut loaded = 0;
+        for _ in 0..200 {
+            tick(&mut engine);
+            loaded += rx
+                .try_iter()
+                .filter(|n| matches!(n, EngineNotification::PageLoaded { .. }))
+                .count();
+            if loaded == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(loaded, 2);
+        // Let both tabs render and settle
+        for _ in 0..8 {
+            tick(&mut engine);
+        }
+
+        engine
+            .execute_command(background, EngineCommand::SetMode(TabMode::BackgroundIdle))
+            .unwrap();
+        engine
+            .execute_command(background, EngineCommand::SetPinned { pinned: true })
+            .unwrap();
+        assert!(engine.enforce_memory_limit().is_empty());
+        engine
+            .execute_command(background, EngineCommand::SetPinned { pinned: false })
+            .unwrap();
+        assert_eq!(engine.enforce_memory_limit(), vec![background]);
+        assert!(rx.try_iter().any(|n| matches!(
+            n,
+            EngineNotification::TabDiscarded { tab_id, .. } if tab_id == background
+        )));
+        {
+            let tab = engine.get_tab(background).unwrap();
+            let tab = tab.lock().unwrap();
+            assert!(tab.is_discarded());
+            assert_eq!(tab.context.raw_html(), "");
+            assert_eq!(tab.current_url.as_ref(), Some(&url));
+        }
+
+        engine
+            .execute_command(background, EngineCommand::SetMode(TabMode::Active))
+            .unwrap();
+        let mut restored = false;
+        let mut reloaded = false;
+        for _ in 0..200 {
+            tick(&mut engine);
+            for n in rx.try_iter() {
+                match n {
+                    EngineNotification::TabRestored { tab_id, .. } => {
+                        restored |= tab_id == background
+                    }
+                    EngineNotification::PageLoaded { tab_id, .. } => {
+                        reloaded |= tab_id == background
+                    }
+                    _ => {}
+                }
+            }
+            if reloaded {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(restored && reloaded);
+
+        let tab = engine.get_tab(background).unwrap();
+        let tab = tab.lock().unwrap();
+        assert!(!tab.is_discarded());
+        assert_eq!(tab.context.raw_html(), "<p>kept</p>");
+    }
+
+    #[test]
+    fn display_list_zones_hand_scenes_to_the_host() {
+        let config = ZoneConfig::builder()
+            .render_mode(RenderMode::DisplayList)
+            .build()
+            .unwrap();
+        let (mut engine, zone_id, tab_id) = engine_with_tab_in(None, |zone| zone.config(config));
+        let rx = engine.subscribe_categories(NotificationCategories::RENDER);
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html: "<p>painted by the host</p>".to_string(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+
+        let scene = tick_until(&mut engine, &rx, |n| match n {
+            EngineNotification::SceneReady {
+                tab_id: t,
+                render_list,
+                epoch,
+                ..
+            } if t == tab_id => Some((render_list, epoch)),
+            _ => None,
+        });
+        let (render_list, epoch) = scene.unwrap();
+        assert!(render_list.items.iter().any(|item| matches!(
+            item,
+            DisplayItem::TextRun { text, .. } if text.contains("painted by the host")
+        )));
+
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let zone = zone.lock().unwrap();
+        assert_eq!(zone.scene(tab_id), Some((render_list, epoch)));
+        let tab = zone.get_tab(tab_id).unwrap();
+        assert!(tab.lock().unwrap().surface.is_none());
+    }
+
+    /// Null backend on a device that can only do area anti-aliasing
+    struct AreaOnlyBackend(NullBackend);
+
+    impl RenderBackend for AreaOnlyBackend {
+        fn create_surface(
+            &self,
+            size: SurfaceSize,
+            present: PresentMode,
+        ) -> anyhow::Result<Box<dyn ErasedSurface>> {
+            self.0.create_surface(size, present)
+        }
+
+        fn render(
+            &mut self,
+            context: &mut BrowsingContext,
+            surface: &mut dyn ErasedSurface,
+        ) -> anyhow::Result<()> {
+            self.0.render(context, surface)
+        }
+
+        fn snapshot(
+            &mut self,
+            surface: &mut dyn ErasedSurface,
+            max_dim: u32,
+        ) -> anyhow::Result<RgbaImage> {
+            self.0.snapshot(surface, max_dim)
+        }
+
+        fn external_handle(&mut self, surface: &mut dyn ErasedSurface) -> Option<ExternalHandle> {
+            self.0.external_handle(surface)
+        }
+
+        fn set_antialiasing(&mut self, _mode: AntialiasingMode) -> AntialiasingMode {
+            AntialiasingMode::Area
+        }
+    }
+
+    #[test]
+    fn unsupported_antialiasing_falls_back_with_a_warning() {
+        let gpu = GpuOptions {
+            msaa_samples: 16,
+            ..EngineConfig::default().gpu
+        };
+        assert_eq!(gpu.antialiasing(), AntialiasingMode::Msaa16);
+        let config = EngineConfig::builder().gpu(gpu).build().unwrap();
+        let mut engine = GosubEngine::new(
+            Some(config),
+            Box::new(AreaOnlyBackend(NullBackend::new().unwrap())),
+        );
+        let rx = engine.subscribe_notifications();
+
+        // The warning waits for a zone to be published in
+        tick(&mut engine);
+        let zone_id = engine.zone_builder().create().unwrap();
+        tick(&mut engine);
+        let warnings: Vec<_> = rx
+            .try_iter()
+            .filter_map(|n| match n {
+                EngineNotification::Warning {
+                    zone_id: z,
+                    message,
+                    ..
+                } if z == zone_id => Some(message),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Msaa16"));
+
+        tick(&mut engine);
+        assert_eq!(rx.try_iter().count(), 0);
+    }
+
+    /// Null backend whose device is lost when the flag is set, until it recovers
+    struct LosableBackend {
+        inner: NullBackend,
+        lost: Arc<AtomicBool>,
+        surfaces: Arc<AtomicUsize>,
+        /// Whether `recover` succeeds
+        recoverable: bool,
+        recoveries: Arc<AtomicUsize>,
+    }
+
+    impl RenderBackend for LosableBackend {
+        fn create_surface(
+            &self,
+            size: SurfaceSize,
+            present: PresentMode,
+        ) -> anyhow::Result<Box<dyn ErasedSurface>> {
+            self.surfaces.fetch_add(1, Ordering::Relaxed);
+            self.inner.create_surface(size, present)
+        }
+
+        fn render(
+            &mut self,
+            context: &mut BrowsingContext,
+            surface: &mut dyn ErasedSurface,
+        ) -> anyhow::Result<()> {
+            anyhow::ensure!(!self.device_lost(), "device lost");
+            self.inner.render(context, surface)
+        }
+
+        fn snapshot(
+            &mut self,
+            surface: &mut dyn ErasedSurface,
+            max_dim: u32,
+        ) -> anyhow::Result<RgbaImage> {
+            self.inner.snapshot(surface, max_dim)
+        }
+
+        fn external_handle(&mut self, surface: &mut dyn ErasedSurface) -> Option<ExternalHandle> {
+            self.inner.external_handle(surface)
+        }
+
+        fn device_lost(&self) -> bool {
+            self.lost.load(Ordering::Relaxed)
+        }
+
+        fn recover(&mut self) -> anyhow::Result<()> {
+            self.recoveries.fetch_add(1, Ordering::Relaxed);
+            anyhow::ensure!(self.recoverable, "no device");
+            self.lost.store(false, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tabs_render_again_after_the_device_is_lost() {
+        let lost = Arc::new(AtomicBool::new(false));
+        let surfaces = Arc::new(AtomicUsize::new(0));
+        let backend = LosableBackend {
+            inner: NullBackend::new().unwrap(),
+            lost: lost.clone(),
+            surfaces: surfaces.clone(),
+            recoverable: true,
+            recoveries: Arc::default(),
+        };
+        let mut engine = GosubEngine::new(None, Box::new(backend));
+        let rx = engine.subscribe_categories(NotificationCategories::ALL);
+        let zone_id = engine.zone_builder().create().unwrap();
+        let tab_id = engine
+            .open_tab_in_zone(zone_id, Viewport::new(0, 0, 320, 200))
+            .unwrap();
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html: "<p>hi</p>".to_string(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+        let rendered = |engine: &mut GosubEngine| {
+            for _ in 0..400 {
+                if tick(engine).get(&tab_id).is_some_and(|r| r.needs_redraw) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            false
+        };
+        assert!(rendered(&mut engine));
+        assert_eq!(surfaces.load(Ordering::Relaxed), 1);
+        rx.try_iter().count();
+
+        lost.store(true, Ordering::Relaxed);
+        assert!(rendered(&mut engine));
+        assert!(!lost.load(Ordering::Relaxed));
+        assert!(rx
+            .try_iter()
+            .any(|n| matches!(n, EngineNotification::BackendReset { zone_id: z } if z == zone_id)));
+        // The frame went to a new surface rather than the one of the lost device
+        assert_eq!(surfaces.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn failed_recoveries_are_not_retried_every_tick() {
+        let recoveries = Arc::new(AtomicUsize::new(0));
+        let backend = LosableBackend {
+            inner: NullBackend::new().unwrap(),
+            lost: Arc::new(AtomicBool::new(true)),
+            surfaces: Arc::default(),
+            recoverable: false,
+            recoveries: recoveries.clone(),
+        };
+        let mut engine = GosubEngine::new(None, Box::new(backend));
+        for _ in 0..20 {
+            tick(&mut engine);
+        }
+        assert_eq!(recoveries.load(Ordering::Relaxed), 1);
+    }
+}
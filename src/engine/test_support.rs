@@ -0,0 +1,151 @@
+//! Setup shared by the tests that drive a whole engine.
+use crate::engine::tick::TickResult;
+use crate::engine::zone_builder::ZoneBuilder;
+use crate::render::backends::null::NullBackend;
+use crate::render::{DefaultCompositor, Viewport};
+use crate::tab::{Tab, TabId};
+use crate::zone::ZoneId;
+use crate::{EngineCommand, EngineConfig, EngineNotification, GosubEngine};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// How long [`tick_until`] waits for a notification.
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Returns an engine on the null backend, with `config` or the defaults.
+pub(crate) fn new_engine(config: Option<EngineConfig>) -> GosubEngine {
+    GosubEngine::new(config, Box::new(NullBackend::new().unwrap()))
+}
+
+/// Opens an 800x600 tab in `zone_id`.
+pub(crate) fn open_tab(engine: &mut GosubEngine, zone_id: ZoneId) -> TabId {
+    engine
+        .open_tab_in_zone(zone_id, Viewport::new(0, 0, 800, 600))
+        .unwrap()
+}
+
+/// Returns an engine with `config`, and a tab in a new zone with the default settings.
+pub(crate) fn engine_with_tab(config: Option<EngineConfig>) -> (GosubEngine, ZoneId, TabId) {
+    engine_with_tab_in(config, |zone| zone)
+}
+
+/// Returns an engine with `config`, and a tab in a new zone that `zone` sets up.
+pub(crate) fn engine_with_tab_in(
+    config: Option<EngineConfig>,
+    zone: impl FnOnce(ZoneBuilder<'_>) -> ZoneBuilder<'_>,
+) -> (GosubEngine, ZoneId, TabId) {
+    let mut engine = new_engine(config);
+    let zone_id = zone(engine.zone_builder()).create().unwrap();
+    let tab_id = open_tab(&mut engine, zone_id);
+    (engine, zone_id, tab_id)
+}
+
+/// Ticks the engine once, without a host to present frames to.
+pub(crate) fn tick(engine: &mut GosubEngine) -> BTreeMap<TabId, TickResult> {
+    engine.tick(&mut DefaultCompositor::new(|| {}))
+}
+
+/// Ticks the engine until `find` picks a notification from `rx`, and returns what it picked.
+/// Gives up with `None` after a few seconds.
+pub(crate) fn tick_until<T>(
+    engine: &mut GosubEngine,
+    rx: &Receiver<EngineNotification>,
+    mut find: impl FnMut(EngineNotification) -> Option<T>,
+) -> Option<T> {
+    let deadline = Instant::now() + WAIT;
+    while Instant::now() < deadline {
+        tick(engine);
+        if let Some(found) = rx.try_iter().find_map(&mut find) {
+            return Some(found);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    None
+}
+
+/// Ticks the engine until a page has loaded, and returns whether one did.
+pub(crate) fn wait_for_load(engine: &mut GosubEngine, rx: &Receiver<EngineNotification>) -> bool {
+    tick_until(engine, rx, |n| {
+        matches!(n, EngineNotification::PageLoaded { .. }).then_some(())
+    })
+    .is_some()
+}
+
+/// Starts a navigation in the tab and ticks once, which sets up the request headers and
+/// locale the navigation goes out with. The custom scheme is refused by the sandbox, so
+/// nothing actually goes out.
+pub(crate) fn navigate_offline(engine: &mut GosubEngine, tab_id: TabId) -> Arc<Mutex<Tab>> {
+    let url = Url::parse("myapp://settings/").unwrap();
+    engine
+        .execute_command(tab_id, EngineCommand::Navigate(url))
+        .unwrap();
+    tick(engine);
+    engine.get_tab(tab_id).unwrap()
+}
+
+/// Serves `html` to every request on a local port, and returns the port.
+pub(crate) fn serve_html(html: &'static str) -> u16 {
+    serve_html_with_headers("", html)
+}
+
+/// Serves `html` with the extra `headers` (each ending in `\r\n`) to every request on a
+/// local port, and returns the port.
+pub(crate) fn serve_html_with_headers(headers: &'static str, html: &'static str) -> u16 {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n{headers}Content-Length: {}\r\n\r\n{html}",
+                html.len()
+            );
+        }
+    });
+    port
+}
+
+/// Serves a redirect with a cookie from `/start` (302) and `/form` (303) to `/next`, and
+/// sends the request line and `Cookie` header of each request to `/next` on the channel.
+pub(crate) fn serve_redirect_with_cookie() -> (u16, Receiver<(String, Option<String>)>) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0; 4096];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]);
+            let line = request.lines().next().unwrap_or_default().to_string();
+            let response = if line.contains(" /next ") {
+                let _ = tx.send((line, cookie_header(&request)));
+                "200 OK\r\nContent-Type: text/html"
+            } else if line.contains(" /start ") {
+                "302 Found\r\nLocation: /next\r\nSet-Cookie: hop=1"
+            } else if line.contains(" /form ") {
+                "303 See Other\r\nLocation: /next\r\nSet-Cookie: posted=1"
+            } else {
+                "404 Not Found"
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {response}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+        }
+    });
+    (port, rx)
+}
+
+/// Returns the `Cookie` header of a raw HTTP request.
+pub(crate) fn cookie_header(request: &str) -> Option<String> {
+    request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.eq_ignore_ascii_case("cookie")
+            .then(|| value.trim().to_string())
+    })
+}
@@ -10,6 +10,15 @@
 //! The render pipeline can also use [`DirtyFlags`] to track which stages need
 //! rebuilding or repainting.
 //!
+//! Every frame a tab renders is measured against a frame budget derived from
+//! [`EngineConfig::target_fps`](crate::EngineConfig::target_fps). When the main surface
+//! alone uses up the budget, non-critical work (auxiliary viewports, thumbnail capture) is
+//! deferred to the next tick. The outcome is reported as [`JankStats`].
+//!
+//! Across tabs, [`EngineConfig::frame_sharing`](crate::EngineConfig::frame_sharing) limits how
+//! many of them start a frame in the same engine tick, so that a window full of visible panes
+//! does not render all of them at once. [`EngineFrameStats`] sums up the frames of all tabs.
+//!
 //! # Typical flow
 //!
 //! ```no_run
@@ -35,7 +44,12 @@
 //!     }
 //! }
 //! ```
-use crate::engine::tab::TabState;
+use crate::config::FrameSharing;
+use crate::engine::tab::{TabId, TabState};
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 
 /// Result of processing a single [`Tab`](crate::tab::Tab) tick.
 ///
@@ -61,6 +75,9 @@ pub struct TickResult {
 
     /// URL that was just committed by this tick, if any.
     pub commited_url: Option<url::Url>,
+
+    /// Auxiliary viewports that have a fresh surface ready to paint.
+    pub aux_redraw: Vec<crate::tab::AuxViewportId>,
 }
 
 /// “Dirty” flags for the render pipeline.
@@ -91,3 +108,478 @@ pub struct DirtyFlags {
     /// Viewport size or position has changed.
     pub viewport: bool,
 }
+
+/// Frame budget used when [`EngineConfig::target_fps`](crate::EngineConfig::target_fps) is
+/// uncapped: one frame at 60 Hz.
+pub(crate) const DEFAULT_FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+/// Frames the focused tab may take in a row with [`FrameSharing::FocusFirst`] while other tabs
+/// wait, before the tab that waited longest goes first once.
+const MAX_FOCUSED_FRAMES_IN_A_ROW: u32 = 3;
+
+/// Returns the time one frame may take at `target_fps`.
+pub(crate) fn frame_budget(target_fps: Option<u16>) -> Duration {
+    match target_fps {
+        Some(fps) if fps > 0 => Duration::from_secs(1) / u32::from(fps),
+        _ => DEFAULT_FRAME_BUDGET,
+    }
+}
+
+/// Frame timing of a tab, measured against its frame budget.
+///
+/// Retrieved with [`GosubEngine::jank_stats`](crate::GosubEngine::jank_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JankStats {
+    /// Frames rendered
+    pub frames: u64,
+    /// Frames that took longer than the budget
+    pub janky_frames: u64,
+    /// Pieces of non-critical work that were moved to a later tick
+    pub deferred_work: u64,
+    /// Frames after a scroll that reused the previous frame and only painted the strips that
+    /// scrolled into view
+    pub cached_scroll_frames: u64,
+    /// Render time of the slowest frame
+    pub longest_frame: Duration,
+    /// Render time of all frames together
+    pub total_render_time: Duration,
+    /// Frame intervals missed because frames ran over budget; a frame that took two and a
+    /// half budgets dropped two
+    pub dropped_frames: u64,
+}
+
+impl JankStats {
+    /// Returns the average render time of a frame, or zero before the first frame.
+    pub fn average_frame_time(&self) -> Duration {
+        match u32::try_from(self.frames) {
+            Ok(0) => Duration::ZERO,
+            Ok(frames) => self.total_render_time / frames,
+            Err(_) => {
+                Duration::from_secs_f64(self.total_render_time.as_secs_f64() / self.frames as f64)
+            }
+        }
+    }
+
+    /// Returns the fraction of frames that were over budget, between 0 and 1.
+    pub fn jank_ratio(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.janky_frames as f32 / self.frames as f32
+    }
+}
+
+/// Timing of a single frame of a tab, sent along with
+/// [`EngineNotification::FrameRendered`](crate::EngineNotification::FrameRendered) for FPS
+/// overlays and performance tracking.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct FrameStats {
+    /// Number of the frame in its tab, counting from 1
+    pub frame_id: u64,
+    /// Time spent building the render list, zero when the scene did not change
+    pub scene_build_time: Duration,
+    /// Time the backend spent painting the surface
+    pub render_time: Duration,
+    /// Time the whole frame took, including work such as auxiliary viewports
+    pub frame_time: Duration,
+    /// Frame intervals the tab has dropped so far, see [`JankStats::dropped_frames`]
+    pub dropped_frames: u64,
+}
+
+/// Times the frames of a tab and decides when non-critical work has to wait.
+#[derive(Debug)]
+pub(crate) struct FrameScheduler {
+    budget: Duration,
+    frame_start: Option<Instant>,
+    current: FrameStats,
+    last: FrameStats,
+    stats: JankStats,
+}
+
+impl FrameScheduler {
+    pub(crate) fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            frame_start: None,
+            current: FrameStats::default(),
+            last: FrameStats::default(),
+            stats: JankStats::default(),
+        }
+    }
+
+    pub(crate) fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    /// Starts timing a frame.
+    pub(crate) fn begin_frame(&mut self) {
+        self.frame_start = Some(Instant::now());
+        self.current = FrameStats::default();
+    }
+
+    /// Records the time the frame being timed spent building its render list.
+    pub(crate) fn record_scene_build(&mut self, elapsed: Duration) {
+        self.current.scene_build_time += elapsed;
+    }
+
+    /// Records the time the backend spent painting the frame being timed.
+    pub(crate) fn record_render(&mut self, elapsed: Duration) {
+        self.current.render_time += elapsed;
+    }
+
+    /// Returns true when the frame being timed has used up its budget, so that anything that
+    /// can wait should.
+    pub(crate) fn over_budget(&self) -> bool {
+        self.frame_start
+            .is_some_and(|start| start.elapsed() >= self.budget)
+    }
+
+    /// Records that a piece of work was deferred to a later tick.
+    pub(crate) fn defer(&mut self) {
+        self.stats.deferred_work += 1;
+    }
+
+    /// Records that the frame being timed was served from the previous one after a scroll.
+    pub(crate) fn record_cached_scroll(&mut self) {
+        self.stats.cached_scroll_frames += 1;
+    }
+
+    /// Stops timing the current frame and adds it to the statistics.
+    pub(crate) fn end_frame(&mut self) {
+        let Some(start) = self.frame_start.take() else {
+            return;
+        };
+        let elapsed = start.elapsed();
+        self.stats.frames += 1;
+        self.stats.total_render_time += elapsed;
+        self.stats.longest_frame = self.stats.longest_frame.max(elapsed);
+        if elapsed > self.budget {
+            self.stats.janky_frames += 1;
+            if !self.budget.is_zero() {
+                self.stats.dropped_frames += (elapsed.as_nanos() / self.budget.as_nanos()) as u64;
+            }
+        }
+
+        self.last = FrameStats {
+            frame_id: self.stats.frames,
+            frame_time: elapsed,
+            dropped_frames: self.stats.dropped_frames,
+            ..self.current
+        };
+    }
+
+    pub(crate) fn stats(&self) -> JankStats {
+        self.stats
+    }
+
+    /// Returns the timing of the last finished frame.
+    pub(crate) fn last_frame(&self) -> FrameStats {
+        self.last
+    }
+}
+
+/// Frame timing of all tabs of an engine together.
+///
+/// Retrieved with [`GosubEngine::engine_frame_stats`](crate::GosubEngine::engine_frame_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EngineFrameStats {
+    /// Frames rendered by all tabs
+    pub frames: u64,
+    /// Frames rendered by all tabs during the last second
+    pub fps: u32,
+    /// Ticks in which a tab had a frame to render but had to wait for its turn, see
+    /// [`FrameSharing`]
+    pub throttled_frames: u64,
+    /// Tabs waiting for their turn after the last tick
+    pub waiting_tabs: usize,
+}
+
+/// Decides which tabs start a frame in an engine tick, and counts the frames of all tabs.
+#[derive(Debug, Default)]
+pub(crate) struct FrameCoordinator {
+    /// Number of the current engine tick
+    tick: u64,
+    /// Tick since which each tab that wants a frame has been waiting
+    waiting_since: HashMap<TabId, u64>,
+    /// Tab that goes first with [`FrameSharing::FocusFirst`]
+    focused: Option<TabId>,
+    /// Frames the focused tab took in a row while other tabs waited
+    focused_streak: u32,
+    /// When the frames of the last second were rendered, and how many
+    recent: VecDeque<(Instant, usize)>,
+    stats: EngineFrameStats,
+}
+
+impl FrameCoordinator {
+    pub(crate) fn set_focused(&mut self, tab_id: Option<TabId>) {
+        self.focused = tab_id;
+    }
+
+    /// Returns the tabs of `waiting` that may start a frame this tick under `sharing`. Those
+    /// that have waited longest go first; ties keep the order of `waiting`.
+    pub(crate) fn grant(&mut self, sharing: FrameSharing, waiting: &[TabId]) -> Vec<TabId> {
+        self.tick += 1;
+        let Some(limit) = sharing.frames_per_tick() else {
+            self.waiting_since.clear();
+            self.stats.waiting_tabs = 0;
+            return waiting.to_vec();
+        };
+
+        self.waiting_since
+            .retain(|tab_id, _| waiting.contains(tab_id));
+        for tab_id in waiting {
+            self.waiting_since.entry(*tab_id).or_insert(self.tick);
+        }
+        let focused = match sharing {
+            FrameSharing::FocusFirst(_) => self.focused,
+            _ => None,
+        };
+        // Don't let a focused tab that always has a new frame starve the others
+        let first = focused.filter(|_| self.focused_streak < MAX_FOCUSED_FRAMES_IN_A_ROW);
+
+        let mut granted = waiting.to_vec();
+        granted.sort_by_key(|tab_id| (Some(*tab_id) != first, self.waiting_since[tab_id]));
+        granted.truncate(limit);
+        for tab_id in &granted {
+            self.waiting_since.remove(tab_id);
+        }
+        match focused {
+            Some(tab_id) if granted.contains(&tab_id) && granted.len() < waiting.len() => {
+                self.focused_streak += 1;
+            }
+            _ => self.focused_streak = 0,
+        }
+
+        self.stats.waiting_tabs = waiting.len() - granted.len();
+        self.stats.throttled_frames += self.stats.waiting_tabs as u64;
+        granted
+    }
+
+    /// Records that tabs rendered `frames` frames in the tick that ended at `now`.
+    pub(crate) fn record_frames(&mut self, frames: usize, now: Instant) {
+        self.stats.frames += frames as u64;
+        if frames > 0 {
+            self.recent.push_back((now, frames));
+        }
+        while self
+            .recent
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) >= Duration::from_secs(1))
+        {
+            self.recent.pop_front();
+        }
+        self.stats.fps = self.recent.iter().map(|(_, n)| *n as u32).sum();
+    }
+
+    pub(crate) fn stats(&self) -> EngineFrameStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FrameSharing;
+    use crate::engine::test_support::{engine_with_tab, new_engine, open_tab, tick};
+    use crate::tab::{TabId, TabState};
+    use crate::{EngineCommand, EngineConfig, GosubEngine};
+    use std::time::Duration;
+
+    #[test]
+    fn frames_over_budget_count_as_jank() {
+        assert_eq!(frame_budget(Some(50)), Duration::from_millis(20));
+        assert_eq!(frame_budget(None), DEFAULT_FRAME_BUDGET);
+
+        let mut scheduler = FrameScheduler::new(Duration::from_secs(60));
+        scheduler.begin_frame();
+        assert!(!scheduler.over_budget());
+        scheduler.end_frame();
+
+        scheduler.set_budget(Duration::ZERO);
+        scheduler.begin_frame();
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(scheduler.over_budget());
+        scheduler.defer();
+        scheduler.end_frame();
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.frames, stats.janky_frames), (2, 1));
+        assert_eq!(scheduler.last_frame().frame_id, 2);
+
+        scheduler.set_budget(Duration::from_millis(1));
+        scheduler.begin_frame();
+        scheduler.record_render(Duration::from_millis(2));
+        std::thread::sleep(Duration::from_millis(3));
+        scheduler.end_frame();
+        let last = scheduler.last_frame();
+        assert_eq!(last.frame_id, 3);
+        assert_eq!(last.render_time, Duration::from_millis(2));
+        assert!(last.dropped_frames >= 2);
+        assert_eq!(last.dropped_frames, scheduler.stats().dropped_frames);
+        assert_eq!(stats.deferred_work, 1);
+        assert!(stats.longest_frame >= Duration::from_millis(1));
+        assert_eq!(stats.jank_ratio(), 0.5);
+    }
+
+    #[test]
+    fn tabs_take_turns_and_the_focused_tab_goes_first() {
+        let [a, b, c] = [TabId::new(), TabId::new(), TabId::new()];
+        let mut frames = FrameCoordinator::default();
+
+        assert_eq!(frames.grant(FrameSharing::Unlimited, &[a, b, c]), [a, b, c]);
+        let sharing = FrameSharing::RoundRobin(1);
+        assert_eq!(frames.grant(sharing, &[a, b]), [a]);
+        // `c` just started waiting, `b` has waited longer
+        assert_eq!(frames.grant(sharing, &[a, b, c]), [b]);
+        assert_eq!(frames.grant(sharing, &[a, b, c]), [a]);
+        assert_eq!(frames.grant(sharing, &[a, b, c]), [c]);
+        assert_eq!(frames.stats().throttled_frames, 1 + 2 + 2 + 2);
+
+        frames.set_focused(Some(c));
+        let sharing = FrameSharing::FocusFirst(2);
+        assert_eq!(frames.grant(sharing, &[a, b, c]), [c, b]);
+        assert_eq!(frames.grant(sharing, &[a, b, c]), [c, a]);
+        assert_eq!(frames.stats().waiting_tabs, 1);
+
+        let start = Instant::now();
+        frames.record_frames(2, start);
+        frames.record_frames(0, start + Duration::from_millis(500));
+        assert_eq!(frames.stats().fps, 2);
+        frames.record_frames(1, start + Duration::from_millis(1200));
+        assert_eq!(frames.stats().fps, 1);
+        assert_eq!(frames.stats().frames, 3);
+    }
+
+    #[test]
+    fn a_focused_tab_that_always_wants_a_frame_does_not_starve_the_others() {
+        let [a, b, focused] = [TabId::new(), TabId::new(), TabId::new()];
+        let mut frames = FrameCoordinator::default();
+        frames.set_focused(Some(focused));
+
+        let sharing = FrameSharing::FocusFirst(1);
+        let granted: Vec<_> = (0..8)
+            .flat_map(|_| frames.grant(sharing, &[a, b, focused]))
+            .collect();
+        assert_eq!(
+            granted,
+            [focused, focused, focused, a, focused, focused, focused, b]
+        );
+    }
+
+    #[test]
+    fn visible_tabs_share_the_frames_of_a_tick() {
+        let config = EngineConfig::builder()
+            .frame_sharing(FrameSharing::RoundRobin(1))
+            .build()
+            .unwrap();
+        let mut engine = new_engine(Some(config));
+        let zone_id = engine.zone_builder().create().unwrap();
+        let mut tabs = Vec::new();
+        for _ in 0..3 {
+            let tab_id = open_tab(&mut engine, zone_id);
+            engine
+                .execute_command(
+                    tab_id,
+                    EngineCommand::LoadHtml {
+                        html: "<p>pane</p>".to_string(),
+                        base_url: None,
+                    },
+                )
+                .unwrap();
+            tabs.push(tab_id);
+        }
+
+        let mut rendered: Vec<TabId> = Vec::new();
+        for _ in 0..400 {
+            let results = tick(&mut engine);
+            let frames: Vec<_> = tabs
+                .iter()
+                .filter(|tab_id| results.get(tab_id).is_some_and(|r| r.needs_redraw))
+                .copied()
+                .collect();
+            assert!(frames.len() <= 1, "{frames:?} rendered in the same tick");
+            rendered.extend(frames);
+            if rendered.len() == tabs.len() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        rendered.sort();
+        tabs.sort();
+        assert_eq!(rendered, tabs);
+        let stats = engine.engine_frame_stats();
+        assert_eq!(stats.frames, 3);
+        assert!(stats.throttled_frames > 0);
+    }
+
+    #[test]
+    fn hidden_tabs_wait_to_render_until_they_are_shown() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let tab = engine.get_tab(tab_id).unwrap();
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::SetVisibility {
+                    visible: false,
+                    occluded: false,
+                },
+            )
+            .unwrap();
+        {
+            let tab = tab.lock().unwrap();
+            assert_eq!(tab.context.visibility_state(), "hidden");
+            assert_eq!(tab.tick_interval(), Some(Duration::from_millis(100)));
+        }
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::LoadHtml {
+                    html: "<p>behind</p>".to_string(),
+                    base_url: None,
+                },
+            )
+            .unwrap();
+
+        let renders = |engine: &mut GosubEngine| {
+            for _ in 0..100 {
+                if tick(engine).get(&tab_id).is_some_and(|r| r.needs_redraw) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            false
+        };
+        assert!(!renders(&mut engine));
+        assert!(matches!(
+            tab.lock().unwrap().state,
+            TabState::PendingRendering(_)
+        ));
+
+        // Covered by another window: the page is visible, but still not painted
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::SetVisibility {
+                    visible: true,
+                    occluded: true,
+                },
+            )
+            .unwrap();
+        assert_eq!(tab.lock().unwrap().context.visibility_state(), "visible");
+        assert!(!renders(&mut engine));
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::SetVisibility {
+                    visible: true,
+                    occluded: false,
+                },
+            )
+            .unwrap();
+        assert_eq!(tab.lock().unwrap().tick_interval(), Some(Duration::ZERO));
+        assert!(renders(&mut engine));
+    }
+}
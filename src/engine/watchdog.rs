@@ -0,0 +1,289 @@
+//! Detection of tabs that stopped responding.
+//!
+//! Tabs run on the thread that ticks the engine, so a tab stuck in a pathological layout
+//! holds up the user agent as well. The [`Watchdog`] runs on a thread of its own: every tick
+//! and command of a tab is marked as busy, and a tab that stays busy for longer than
+//! [`EngineConfig::tab_watchdog_timeout`](crate::EngineConfig::tab_watchdog_timeout) is
+//! reported with [`EngineNotification::TabUnresponsive`]. Subscribers that drain
+//! notifications on another thread see it while the tab is still stuck.
+//!
+//! Once the tab returns, [`EngineNotification::TabResponsive`] follows. A stuck tab holds the
+//! thread that ticks the engine, so it cannot be reached with a command. Instead, every busy
+//! tab carries a cancel flag that a [`TabKiller`] sets from any thread (or the watchdog itself,
+//! with [`EngineConfig::tab_watchdog_kill`](crate::EngineConfig::tab_watchdog_kill)). The tab
+//! checks it between the stages of its pipeline, gives up at the next one and is closed.
+use crate::engine::{EngineNotification, NotificationBus};
+use crate::tab::TabId;
+use crate::zone::ZoneId;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Watches the ticks and commands of all tabs of an engine.
+pub(crate) struct Watchdog {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    /// Time a tab may be busy before it is reported
+    timeout: Duration,
+    /// Whether reported tabs are killed as well
+    kill: bool,
+    notifications: Arc<NotificationBus>,
+    state: Mutex<State>,
+    /// Wakes the thread up to stop
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    busy: HashMap<TabId, Busy>,
+    stopped: bool,
+}
+
+/// A tab in the middle of a tick or command.
+struct Busy {
+    zone_id: ZoneId,
+    since: Instant,
+    /// Whether the tab was reported as unresponsive
+    reported: bool,
+    /// Cancel flag of the tab, see [`Tab::is_killed`](crate::tab::Tab::is_killed)
+    cancel: Arc<AtomicBool>,
+}
+
+/// Kills busy tabs from any thread, typically after
+/// [`EngineNotification::TabUnresponsive`]. Obtained with
+/// [`GosubEngine::tab_killer`](crate::GosubEngine::tab_killer).
+#[derive(Clone)]
+pub struct TabKiller {
+    shared: Arc<Shared>,
+}
+
+impl TabKiller {
+    /// Asks a busy tab to stop. The tab gives up at the next stage of its pipeline, or the next
+    /// line of its layout, after which it is closed and [`EngineNotification::TabClosed`] is
+    /// published. A frame the backend is painting is finished first, so a backend that hangs
+    /// cannot be killed. Returns false when the
+    /// tab is not busy; a tab that is not stuck is killed with
+    /// [`EngineCommand::ForceKill`](crate::EngineCommand::ForceKill) instead.
+    pub fn kill(&self, tab_id: TabId) -> bool {
+        self.shared.kill(tab_id)
+    }
+}
+
+impl std::fmt::Debug for TabKiller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TabKiller").finish_non_exhaustive()
+    }
+}
+
+/// Marks a tab as busy until dropped.
+pub(crate) struct BusyGuard {
+    shared: Arc<Shared>,
+    tab_id: TabId,
+}
+
+impl Watchdog {
+    /// Starts a watchdog that reports tabs busy for longer than `timeout` on `notifications`,
+    /// and kills them too when `kill` is set.
+    pub(crate) fn start(
+        timeout: Duration,
+        kill: bool,
+        notifications: Arc<NotificationBus>,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            timeout,
+            kill,
+            notifications,
+            state: Mutex::new(State::default()),
+            wake: Condvar::new(),
+        });
+        let thread = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name("gosub-watchdog".to_string())
+                .spawn(move || shared.run())
+                .expect("failed to start the watchdog thread")
+        };
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Marks `tab_id` as busy until the returned guard is dropped. Killing the tab meanwhile
+    /// sets `cancel`.
+    pub(crate) fn busy(
+        &self,
+        zone_id: ZoneId,
+        tab_id: TabId,
+        cancel: Arc<AtomicBool>,
+    ) -> BusyGuard {
+        self.shared.state.lock().unwrap().busy.insert(
+            tab_id,
+            Busy {
+                zone_id,
+                since: Instant::now(),
+                reported: false,
+                cancel,
+            },
+        );
+        BusyGuard {
+            shared: self.shared.clone(),
+            tab_id,
+        }
+    }
+
+    /// Stops watching a tab that was killed, so nothing more is reported about it.
+    pub(crate) fn forget(&self, tab_id: TabId) {
+        self.shared.state.lock().unwrap().busy.remove(&tab_id);
+    }
+
+    /// Returns a handle that kills busy tabs from any thread.
+    pub(crate) fn killer(&self) -> TabKiller {
+        TabKiller {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Shared {
+    fn kill(&self, tab_id: TabId) -> bool {
+        let state = self.state.lock().unwrap();
+        let Some(busy) = state.busy.get(&tab_id) else {
+            return false;
+        };
+        busy.cancel.store(true, Ordering::Relaxed);
+        true
+    }
+
+    fn run(&self) {
+        // Checking a few times per timeout keeps reports at most a quarter late
+        let interval = (self.timeout / 4).max(Duration::from_millis(1));
+        let mut state = self.state.lock().unwrap();
+        while !state.stopped {
+            let now = Instant::now();
+            for (tab_id, busy) in state.busy.iter_mut() {
+                let busy_for = now.duration_since(busy.since);
+                if busy.reported || busy_for < self.timeout {
+                    continue;
+                }
+                busy.reported = true;
+                if self.kill {
+                    busy.cancel.store(true, Ordering::Relaxed);
+                }
+                self.notifications
+                    .publish(EngineNotification::TabUnresponsive {
+                        zone_id: busy.zone_id,
+                        tab_id: *tab_id,
+                        busy_for,
+                    });
+            }
+            state = self.wake.wait_timeout(state, interval).unwrap().0;
+        }
+    }
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        let Ok(mut state) = self.shared.state.lock() else {
+            return;
+        };
+        if let Some(busy) = state.busy.remove(&self.tab_id) {
+            if busy.reported {
+                self.shared
+                    .notifications
+                    .publish(EngineNotification::TabResponsive {
+                        zone_id: busy.zone_id,
+                        tab_id: self.tab_id,
+                    });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_busy_for_too_long_are_reported_once() {
+        let bus = Arc::new(NotificationBus::default());
+        let rx = bus.subscribe();
+        let watchdog = Watchdog::start(Duration::from_millis(20), false, bus);
+        let (zone_id, tab_id) = (ZoneId::new(), TabId::new());
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        drop(watchdog.busy(zone_id, tab_id, cancel.clone()));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(rx.try_recv().is_err());
+
+        let guard = watchdog.busy(zone_id, tab_id, cancel.clone());
+        std::thread::sleep(Duration::from_millis(100));
+        drop(guard);
+        let received: Vec<_> = rx.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert!(matches!(
+            received[0],
+            EngineNotification::TabUnresponsive { tab_id: t, busy_for, .. }
+                if t == tab_id && busy_for >= Duration::from_millis(20)
+        ));
+        assert!(matches!(
+            received[1],
+            EngineNotification::TabResponsive { tab_id: t, .. } if t == tab_id
+        ));
+        assert!(!cancel.load(Ordering::Relaxed));
+
+        // A killed tab is forgotten, so its return is not announced
+        let guard = watchdog.busy(zone_id, tab_id, cancel.clone());
+        std::thread::sleep(Duration::from_millis(60));
+        watchdog.forget(tab_id);
+        drop(guard);
+        let received: Vec<_> = rx.try_iter().collect();
+        assert_eq!(received.len(), 1);
+    }
+
+    #[test]
+    fn busy_tabs_are_killed_from_another_thread() {
+        let bus = Arc::new(NotificationBus::default());
+        let watchdog = Watchdog::start(Duration::from_secs(5), false, bus);
+        let (zone_id, tab_id) = (ZoneId::new(), TabId::new());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let killer = watchdog.killer();
+
+        // Only a busy tab can be reached
+        assert!(!killer.kill(tab_id));
+        let guard = watchdog.busy(zone_id, tab_id, cancel.clone());
+        let killed = std::thread::spawn(move || killer.kill(tab_id))
+            .join()
+            .unwrap();
+        assert!(killed);
+        assert!(cancel.load(Ordering::Relaxed));
+        drop(guard);
+    }
+
+    #[test]
+    fn the_watchdog_kills_what_it_reports_when_asked_to() {
+        let bus = Arc::new(NotificationBus::default());
+        let watchdog = Watchdog::start(Duration::from_millis(20), true, bus);
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let guard = watchdog.busy(ZoneId::new(), TabId::new(), cancel.clone());
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(cancel.load(Ordering::Relaxed));
+        drop(guard);
+    }
+}
@@ -11,14 +11,16 @@
 //! Zones are the Gosub equivalent of browser profiles. They can be:
 //!
 //! - **Private** — only the tabs within that zone can access its data.
-//! - **Shared** — marked with flags in [`SharedFlags`](crate::engine::zone::Zone) so
-//!   other zones can read or write certain datasets (cookies, passwords, etc.).
+//! - **Shared** — marked with flags in [`SharedFlags`] so other zones can read certain
+//!   datasets (cookies, passwords, etc.).
 //!
 //! # Key Types
 //!
 //! - [`Zone`] — The struct representing one zone instance.
 //! - [`ZoneId`] — Opaque, globally unique identifier for a zone.
 //! - [`ZoneConfig`] — Per-zone configuration settings.
+//! - [`TabGroup`] — A named set of tabs within a zone.
+//! - [`ZoneArchive`] — The exported data of a zone.
 //!
 //! # Example
 //!
@@ -70,12 +72,32 @@
 //!
 //! See [`Zone`] docs for field-level details.
 
+mod archive;
 mod config;
+mod extensions;
+mod favicons;
+mod groups;
 mod manager;
 mod password_store;
+mod services;
+mod site_settings;
+mod url_resolver;
+mod user_content;
 mod zone;
 
+pub use archive::{LocalStorageArchive, ZoneArchive, ARCHIVE_VERSION};
 pub use config::ZoneConfig;
-pub use manager::ZoneManager;
+pub use extensions::{ContentScriptManifest, Extension, ExtensionId, ExtensionManifest};
+pub use favicons::{Favicon, FaviconStore};
+pub(crate) use favicons::{best_fit, favicon_links, FaviconLink};
+pub use groups::{TabGroup, TabGroupId};
+pub use manager::{ZoneCloneOptions, ZoneManager};
+pub use services::{ZoneServices, ZoneServicesBuilder, ZoneServicesFactory};
+pub use site_settings::{Permission, SiteSettings, SiteSettingsStore};
+pub(crate) use user_content::PageColors;
+pub use url_resolver::{ResolvedNavigation, SearchProvider, UrlFallback, UrlResolver};
+pub use user_content::{ScriptRunAt, UrlFilter, UrlPattern, UserContent, UserScript, UserStyle};
+pub use zone::{SharedFlags, ZoneMetadata, ZoneMetrics};
 pub use zone::Zone;
 pub use zone::ZoneId;
+pub use zone::{CACHE_EVICTION_JOB, COOKIE_EXPIRY_JOB, METRICS_JOB, PERSISTENCE_FLUSH_JOB};
@@ -0,0 +1,177 @@
+//! Zone archives.
+//!
+//! A [`ZoneArchive`] is a snapshot of the data of a zone in one JSON file: its metadata,
+//! cookies, localStorage and site settings. User agents use it to back up a zone, or to move
+//! it to another machine, with [`Zone::export_data`](crate::zone::Zone::export_data) and
+//! [`Zone::import_data`](crate::zone::Zone::import_data).
+//!
+//! Session storage, open tabs and their history are not part of an archive; they belong to a
+//! session rather than to the zone.
+use crate::cookies::Cookie;
+use crate::storage::PartitionKey;
+use crate::zone::SiteSettings;
+use crate::EngineError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use url::{Origin, Url};
+
+/// Version of the archive format written by this engine
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// The data of a zone, as written by [`Zone::export_data`](crate::zone::Zone::export_data).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZoneArchive {
+    /// Version of the archive format
+    pub version: u32,
+    /// Title of the zone
+    pub title: String,
+    /// Description of the zone
+    pub description: String,
+    /// Icon of the zone
+    pub icon: Vec<u8>,
+    /// Color of the zone (RGBA)
+    pub color: [u8; 4],
+    /// Cookies, keyed by origin
+    pub cookies: BTreeMap<String, Vec<Cookie>>,
+    /// localStorage areas
+    pub local_storage: Vec<LocalStorageArchive>,
+    /// Site settings, keyed by origin
+    pub site_settings: Vec<(String, SiteSettings)>,
+}
+
+/// The localStorage items of one origin within one partition.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocalStorageArchive {
+    /// Top-level origin of the partition, or `None` for unpartitioned storage
+    pub partition: Option<String>,
+    /// Origin that owns the items
+    pub origin: String,
+    /// Key/value pairs
+    pub items: Vec<(String, String)>,
+}
+
+impl LocalStorageArchive {
+    /// Returns the partition key and origin of the area, or `None` when either does not parse.
+    pub(crate) fn location(&self) -> Option<(PartitionKey, Origin)> {
+        let partition = match &self.partition {
+            None => PartitionKey::None,
+            Some(top) => PartitionKey::TopLevel(Url::parse(top).ok()?.origin()),
+        };
+        Some((partition, Url::parse(&self.origin).ok()?.origin()))
+    }
+}
+
+impl ZoneArchive {
+    /// Reads an archive from `path`.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::StorageError`] when the file cannot be read or parsed, or was
+    /// written by a newer engine.
+    pub fn read(path: &Path) -> Result<Self, EngineError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| EngineError::StorageError(e.to_string()))?;
+        let archive: ZoneArchive = serde_json::from_str(&contents)
+            .map_err(|e| EngineError::StorageError(format!("{}: {e}", path.display())))?;
+        if archive.version > ARCHIVE_VERSION {
+            return Err(EngineError::StorageError(format!(
+                "{} has archive version {}, this engine supports up to {ARCHIVE_VERSION}",
+                path.display(),
+                archive.version
+            )));
+        }
+        Ok(archive)
+    }
+
+    /// Writes the archive to `path`. The file is replaced in one step, so an interrupted
+    /// export never leaves a half-written archive behind.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::StorageError`] when the file cannot be written.
+    pub fn write(&self, path: &Path) -> Result<(), EngineError> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| EngineError::StorageError(e.to_string()))?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, contents).map_err(|e| EngineError::StorageError(e.to_string()))?;
+        std::fs::rename(&tmp, path).map_err(|e| EngineError::StorageError(e.to_string()))
+    }
+}
+
+/// Returns how `partition` is stored in an archive.
+pub(crate) fn partition_to_archive(partition: &PartitionKey) -> Option<String> {
+    match partition {
+        PartitionKey::None => None,
+        PartitionKey::TopLevel(origin) => Some(origin.ascii_serialization()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::local::in_memory::InMemoryLocalStore;
+    use crate::storage::{InMemorySessionStore, StorageService};
+    use crate::zone::{Zone, ZoneConfig};
+    use std::sync::Arc;
+
+    fn zone() -> Zone {
+        let storage = Arc::new(StorageService::new(
+            Arc::new(InMemoryLocalStore::new()),
+            Arc::new(InMemorySessionStore::new()),
+        ));
+        Zone::new(ZoneConfig::default(), storage, None)
+    }
+
+    #[test]
+    fn archives_carry_cookies_storage_and_site_settings() {
+        let url = Url::parse("https://archive.test/page").unwrap();
+        let mut source = zone();
+        source.set_title("Work");
+        source.cookie_jar.write().unwrap().insert_cookies(vec![(
+            url.clone(),
+            Cookie {
+                name: "sid".into(),
+                value: "42".into(),
+                path: Some("/".into()),
+                domain: None,
+                secure: true,
+                expires: None,
+                same_site: None,
+                http_only: true,
+            },
+        )]);
+        let partition = PartitionKey::TopLevel(url.origin());
+        source
+            .storage
+            .local_for(source.id, &partition, &url.origin())
+            .unwrap()
+            .set_item("theme", "dark")
+            .unwrap();
+        let settings = SiteSettings {
+            zoom: Some(1.5),
+            ..Default::default()
+        };
+        source.site_settings().set(&url, settings.clone());
+
+        let path = std::env::temp_dir().join(format!("gosub-zone-{}.json", source.id));
+        source.export_data(&path).unwrap();
+
+        let mut target = zone();
+        target.import_data(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(target.title, "Work");
+        let header = target.cookie_jar.read().unwrap().get_request_cookies(&url);
+        assert_eq!(header.as_deref(), Some("sid=42"));
+        let area = target
+            .storage
+            .local_for(target.id, &partition, &url.origin())
+            .unwrap();
+        assert_eq!(area.get_item("theme").as_deref(), Some("dark"));
+        assert_eq!(target.site_settings().get(&url), Some(settings));
+        assert_eq!(
+            target.export_archive().unwrap(),
+            source.export_archive().unwrap()
+        );
+    }
+}
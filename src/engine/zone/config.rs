@@ -35,9 +35,16 @@
 //!
 //! # Fields (summary)
 //! - `max_tabs`: Maximum number of tabs allowed in the zone (default: 16).
-//! - `user_agent`: Optional UA string to send with requests.
-//! - `accept_languages`: Optional `Accept-Language` header value.
+//! - `user_agent`: Optional UA string to send with requests, instead of the engine's.
+//! - `client_hints`: Send the `Sec-CH-UA` client hints derived from the user agent (default:
+//!   `true`); see [`ClientHints`](crate::net::ClientHints).
+//! - `accept_languages`: Optional `Accept-Language` header value; derived from `locale` when
+//!   not set.
+//! - `locale`: Optional BCP 47 language tag, such as `nl-NL`, that pages see as their language.
+//! - `timezone`: Optional IANA time zone, such as `Europe/Amsterdam`, that pages see instead of
+//!   the host's.
 //! - `do_not_track`: Send `DNT: 1` header if `true`.
+//! - `global_privacy_control`: Send `Sec-GPC: 1` header if `true`.
 //! - `javascript_enabled`: Execute JavaScript if `true`.
 //! - `images_enabled`: Load images if `true`.
 //! - `plugins_enabled`: Enable plugins if `true`.
@@ -45,7 +52,19 @@
 //! - `default_font_family`: Optional default font family name.
 //! - `default_font_size`: Default font size in CSS px (default: 16).
 //! - `minimum_font_size`: Minimum allowed font size in CSS px (must be ≤ `default_font_size`).
-//! - `enable_local_file_access`: Allow `file://` (sandboxing concerns).
+//! - `enable_local_file_access`: Allow `file://` (sandboxing concerns), including files
+//!   dropped onto tabs.
+//! - `cache_enabled`: Reuse cached responses in the zone's tabs (default: `true`); see
+//!   [`TabCacheMode`](crate::tab::TabCacheMode).
+//! - `user_styles` / `user_scripts`: CSS and JavaScript applied to every matching document
+//!   in the zone (see [`UserStyle`](crate::zone::UserStyle)).
+//! - `retry_policy`: Retry GET navigations that fail on a flaky network (default: off); see
+//!   [`RetryPolicy`](crate::net::RetryPolicy).
+//! - `loading_policy`: Order and concurrency of the requests for document resources; see
+//!   [`LoadingPolicy`](crate::net::LoadingPolicy).
+//! - `render_mode`: Whether the render backend paints the zone's tabs or the host paints
+//!   their render lists itself (default: the backend); see
+//!   [`RenderMode`](crate::render::RenderMode).
 //!
 //! # Notes
 //!
@@ -56,16 +75,25 @@
 //!
 //! Builder validation can return [`ZoneConfigError`] if values are invalid
 //! (e.g. `font_scale` outside `0.25..=10.0`, `minimum_font_size > default_font_size`,
-//! or `max_tabs == 0`).
+//! `max_tabs == 0`, a retry policy without backoff, a loading policy that allows no
+//! requests in flight or lists a priority class twice, or a malformed locale or time zone).
 
+use crate::engine::zone::{UserScript, UserStyle};
+use crate::net::{LoadingPolicy, ResourcePriority, RetryPolicy};
+use crate::render::RenderMode;
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct ZoneConfig {
     pub max_tabs: usize,
     pub user_agent: Option<String>,
+    pub client_hints: bool,
     pub accept_languages: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
     pub do_not_track: bool,
+    pub global_privacy_control: bool,
     pub javascript_enabled: bool,
     pub images_enabled: bool,
     pub plugins_enabled: bool,
@@ -74,6 +102,12 @@ pub struct ZoneConfig {
     pub default_font_size: u32,
     pub minimum_font_size: u32,
     pub enable_local_file_access: bool,
+    pub cache_enabled: bool,
+    pub user_styles: Vec<UserStyle>,
+    pub user_scripts: Vec<UserScript>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub loading_policy: LoadingPolicy,
+    pub render_mode: RenderMode,
 }
 
 impl Default for ZoneConfig {
@@ -81,8 +115,12 @@ impl Default for ZoneConfig {
         Self {
             max_tabs: 16,
             user_agent: None,
+            client_hints: true,
             accept_languages: None,
+            locale: None,
+            timezone: None,
             do_not_track: false,
+            global_privacy_control: false,
             javascript_enabled: true,
             images_enabled: true,
             plugins_enabled: false,
@@ -91,6 +129,12 @@ impl Default for ZoneConfig {
             default_font_size: 16,
             minimum_font_size: 0,
             enable_local_file_access: false,
+            cache_enabled: true,
+            user_styles: Vec::new(),
+            user_scripts: Vec::new(),
+            retry_policy: None,
+            loading_policy: LoadingPolicy::default(),
+            render_mode: RenderMode::default(),
         }
     }
 }
@@ -122,8 +166,12 @@ impl ZoneConfigBuilder {
 
     pub fn max_tabs(self, n: usize) -> Self { self.map(|c| c.max_tabs = n) }
     pub fn user_agent<S: Into<String>>(self, ua: S) -> Self { self.map(|c| c.user_agent = Some(ua.into())) }
+    pub fn client_hints(self, on: bool) -> Self { self.map(|c| c.client_hints = on) }
     pub fn accept_languages<S: Into<String>>(self, langs: S) -> Self { self.map(|c| c.accept_languages = Some(langs.into())) }
+    pub fn locale<S: Into<String>>(self, tag: S) -> Self { self.map(|c| c.locale = Some(tag.into())) }
+    pub fn timezone<S: Into<String>>(self, tz: S) -> Self { self.map(|c| c.timezone = Some(tz.into())) }
     pub fn do_not_track(self, dnt: bool) -> Self { self.map(|c| c.do_not_track = dnt) }
+    pub fn global_privacy_control(self, gpc: bool) -> Self { self.map(|c| c.global_privacy_control = gpc) }
     pub fn javascript_enabled(self, on: bool) -> Self { self.map(|c| c.javascript_enabled = on) }
     pub fn images_enabled(self, on: bool) -> Self { self.map(|c| c.images_enabled = on) }
     pub fn plugins_enabled(self, on: bool) -> Self { self.map(|c| c.plugins_enabled = on) }
@@ -132,6 +180,12 @@ impl ZoneConfigBuilder {
     pub fn default_font_size(self, px: u32) -> Self { self.map(|c| c.default_font_size = px) }
     pub fn minimum_font_size(self, px: u32) -> Self { self.map(|c| c.minimum_font_size = px) }
     pub fn enable_local_file_access(self, on: bool) -> Self { self.map(|c| c.enable_local_file_access = on) }
+    pub fn cache_enabled(self, on: bool) -> Self { self.map(|c| c.cache_enabled = on) }
+    pub fn user_style(self, style: UserStyle) -> Self { self.map(|c| c.user_styles.push(style)) }
+    pub fn user_script(self, script: UserScript) -> Self { self.map(|c| c.user_scripts.push(script)) }
+    pub fn retry_policy(self, policy: Option<RetryPolicy>) -> Self { self.map(|c| c.retry_policy = policy) }
+    pub fn loading_policy(self, policy: LoadingPolicy) -> Self { self.map(|c| c.loading_policy = policy) }
+    pub fn render_mode(self, mode: RenderMode) -> Self { self.map(|c| c.render_mode = mode) }
 
     /// Apply multiple changes in one go.
     pub fn with(self, f: impl FnOnce(&mut ZoneConfig)) -> Self { self.map(f) }
@@ -150,6 +204,11 @@ pub enum ZoneConfigError {
     InvalidFontScale(f32),
     MinFontLarger { min: u32, default: u32 },
     ZeroTabs,
+    InvalidRetryBackoff { initial: Duration, max: Duration },
+    ZeroRequestsInFlight,
+    DuplicatePriority(ResourcePriority),
+    InvalidLocale(String),
+    InvalidTimeZone(String),
 }
 
 impl fmt::Display for ZoneConfigError {
@@ -161,12 +220,22 @@ impl fmt::Display for ZoneConfigError {
                 write!(f, "minimum_font_size ({min}) > default_font_size ({default})"),
             ZoneConfigError::ZeroTabs =>
                 write!(f, "max_tabs must be at least 1"),
+            ZoneConfigError::InvalidRetryBackoff { initial, max } =>
+                write!(f, "retry backoff must be non-zero and at most max_backoff (got {initial:?} and {max:?})"),
+            ZoneConfigError::ZeroRequestsInFlight =>
+                write!(f, "loading_policy.max_in_flight must be at least 1"),
+            ZoneConfigError::DuplicatePriority(class) =>
+                write!(f, "loading_policy lists {class:?} more than once"),
+            ZoneConfigError::InvalidLocale(tag) =>
+                write!(f, "locale {tag:?} is not a BCP 47 language tag"),
+            ZoneConfigError::InvalidTimeZone(tz) =>
+                write!(f, "timezone {tz:?} is not an IANA time zone name"),
         }
     }
 }
 impl std::error::Error for ZoneConfigError {}
 
-fn validate(c: &ZoneConfig) -> Result<(), ZoneConfigError> {
+pub(crate) fn validate(c: &ZoneConfig) -> Result<(), ZoneConfigError> {
     if !(0.25..=10.0).contains(&c.font_scale) {
         return Err(ZoneConfigError::InvalidFontScale(c.font_scale));
     }
@@ -179,5 +248,47 @@ fn validate(c: &ZoneConfig) -> Result<(), ZoneConfigError> {
     if c.max_tabs == 0 {
         return Err(ZoneConfigError::ZeroTabs);
     }
+    if let Some(policy) = &c.retry_policy {
+        if policy.initial_backoff.is_zero() || policy.initial_backoff > policy.max_backoff {
+            return Err(ZoneConfigError::InvalidRetryBackoff {
+                initial: policy.initial_backoff,
+                max: policy.max_backoff,
+            });
+        }
+    }
+    let policy = &c.loading_policy;
+    if policy.max_in_flight == Some(0) {
+        return Err(ZoneConfigError::ZeroRequestsInFlight);
+    }
+    for (i, class) in policy.order.iter().enumerate() {
+        if policy.order[..i].contains(class) {
+            return Err(ZoneConfigError::DuplicatePriority(*class));
+        }
+    }
+    if let Some(tag) = c.locale.as_ref().filter(|t| !is_language_tag(t)) {
+        return Err(ZoneConfigError::InvalidLocale(tag.clone()));
+    }
+    if let Some(tz) = c.timezone.as_ref().filter(|t| !is_time_zone(t)) {
+        return Err(ZoneConfigError::InvalidTimeZone(tz.clone()));
+    }
     Ok(())
+}
+
+/// Whether `tag` looks like a BCP 47 language tag: a language of 2 to 3 letters, followed by
+/// subtags of 1 to 8 letters and digits, such as `en`, `nl-NL` or `zh-Hant-TW`.
+pub(crate) fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=3).contains(&language.len())
+        && language.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|s| (1..=8).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric()))
+}
+
+/// Whether `tz` looks like an IANA time zone name, such as `UTC` or `America/New_York`.
+pub(crate) fn is_time_zone(tz: &str) -> bool {
+    !tz.is_empty()
+        && tz.split('/').all(|part| {
+            part.starts_with(|c: char| c.is_ascii_alphabetic())
+                && part.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'+'))
+        })
 }
\ No newline at end of file
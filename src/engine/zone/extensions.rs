@@ -0,0 +1,227 @@
+//! Minimal extension support: content scripts and messaging.
+//!
+//! An [`Extension`] is a bundle of content scripts and stylesheets, described by a manifest in
+//! the WebExtension `manifest.json` format. Only the parts needed for content scripts are
+//! understood:
+//!
+//! ```json
+//! {
+//!   "name": "Reader tweaks",
+//!   "version": "1.0",
+//!   "content_scripts": [{
+//!     "matches": ["https://*/*"],
+//!     "exclude_matches": ["https://bank.example/*"],
+//!     "js": ["content.js"],
+//!     "css": ["content.css"],
+//!     "run_at": "document_start"
+//!   }]
+//! }
+//! ```
+//!
+//! Extensions are loaded per zone with [`Zone::load_extension`](crate::zone::Zone::load_extension).
+//! Their content scripts and stylesheets are applied like [user content](super::UserContent),
+//! tagged with the extension id. Content scripts talk to the user agent through
+//! [`EngineNotification::ExtensionMessage`](crate::EngineNotification::ExtensionMessage), and
+//! the user agent answers with
+//! [`EngineCommand::MessageExtension`](crate::EngineCommand::MessageExtension). There is no
+//! background page, no `chrome.*`/`browser.*` API surface and no permission model yet.
+use crate::engine::zone::{ScriptRunAt, UrlFilter, UrlPattern, UserScript, UserStyle};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::Path;
+
+/// Identifier of an extension within a zone.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ExtensionId(pub String);
+
+impl Display for ExtensionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The subset of a WebExtension manifest that the engine understands.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExtensionManifest {
+    /// Human-readable name
+    pub name: String,
+    /// Version string, as given by the extension
+    #[serde(default)]
+    pub version: String,
+    /// Content scripts and the documents they are injected into
+    #[serde(default)]
+    pub content_scripts: Vec<ContentScriptManifest>,
+}
+
+/// A `content_scripts` entry of a manifest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentScriptManifest {
+    /// Match patterns of the documents to inject into
+    pub matches: Vec<String>,
+    /// Match patterns of documents to skip
+    #[serde(default)]
+    pub exclude_matches: Vec<String>,
+    /// Script files, relative to the extension root
+    #[serde(default)]
+    pub js: Vec<String>,
+    /// Stylesheet files, relative to the extension root
+    #[serde(default)]
+    pub css: Vec<String>,
+    /// `document_start`, `document_end` (default) or `document_idle`
+    #[serde(default)]
+    pub run_at: Option<String>,
+}
+
+/// A loaded extension: its manifest plus the contents of the files it references.
+#[derive(Debug, Clone)]
+pub struct Extension {
+    /// Identifier of the extension
+    pub id: ExtensionId,
+    /// Parsed manifest
+    pub manifest: ExtensionManifest,
+    styles: Vec<UserStyle>,
+    scripts: Vec<UserScript>,
+}
+
+impl Extension {
+    /// Loads an unpacked extension from a directory containing `manifest.json`. The directory
+    /// name is used as extension id.
+    pub fn load_dir(dir: &Path) -> Result<Extension> {
+        let id = dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("invalid extension directory {}", dir.display()))?;
+        let manifest = std::fs::read_to_string(dir.join("manifest.json"))
+            .with_context(|| format!("reading manifest of extension {id}"))?;
+
+        Extension::from_manifest(ExtensionId(id.to_string()), &manifest, |file| {
+            std::fs::read_to_string(dir.join(file))
+                .with_context(|| format!("reading {file} of extension {id}"))
+        })
+    }
+
+    /// Creates an extension from the text of its manifest. `read_file` returns the contents of
+    /// a file referenced by the manifest, given its path relative to the extension root.
+    pub fn from_manifest(
+        id: ExtensionId,
+        manifest: &str,
+        read_file: impl Fn(&str) -> Result<String>,
+    ) -> Result<Extension> {
+        let manifest: ExtensionManifest =
+            serde_json::from_str(manifest).with_context(|| format!("invalid manifest for {id}"))?;
+
+        let mut styles = Vec::new();
+        let mut scripts = Vec::new();
+        for cs in &manifest.content_scripts {
+            if cs.matches.is_empty() {
+                bail!("{id}: content script without match patterns");
+            }
+            let filter = UrlFilter {
+                include: cs
+                    .matches
+                    .iter()
+                    .map(|m| UrlPattern::new(m.as_str()))
+                    .collect(),
+                exclude: cs
+                    .exclude_matches
+                    .iter()
+                    .map(|m| UrlPattern::new(m.as_str()))
+                    .collect(),
+            };
+            let run_at = match cs.run_at.as_deref() {
+                Some("document_start") => ScriptRunAt::DocumentStart,
+                None | Some("document_end") | Some("document_idle") => ScriptRunAt::DocumentEnd,
+                Some(other) => bail!("{id}: unknown run_at value {other:?}"),
+            };
+
+            for file in &cs.css {
+                styles.push(UserStyle {
+                    css: read_file(file)?,
+                    filter: filter.clone(),
+                });
+            }
+            for file in &cs.js {
+                scripts.push(UserScript {
+                    source: read_file(file)?,
+                    filter: filter.clone(),
+                    run_at,
+                    extension: Some(id.clone()),
+                });
+            }
+        }
+
+        Ok(Extension {
+            id,
+            manifest,
+            styles,
+            scripts,
+        })
+    }
+
+    /// Stylesheets injected by the extension's content scripts.
+    pub fn styles(&self) -> &[UserStyle] {
+        &self.styles
+    }
+
+    /// Scripts injected by the extension, tagged with its id.
+    pub fn scripts(&self) -> &[UserScript] {
+        &self.scripts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn content_scripts_from_manifest() {
+        let manifest = r#"{
+            "manifest_version": 3,
+            "name": "Reader tweaks",
+            "content_scripts": [{
+                "matches": ["*://*.example.com/*"],
+                "exclude_matches": ["*://login.example.com/*"],
+                "js": ["a.js", "b.js"],
+                "css": ["a.css"],
+                "run_at": "document_start"
+            }]
+        }"#;
+        let ext = Extension::from_manifest(ExtensionId("reader".into()), manifest, |f| {
+            Ok(format!("/* {f} */"))
+        })
+        .unwrap();
+
+        assert_eq!(ext.manifest.name, "Reader tweaks");
+        assert_eq!(ext.scripts().len(), 2);
+        assert_eq!(ext.scripts()[1].source, "/* b.js */");
+        assert_eq!(ext.scripts()[0].run_at, ScriptRunAt::DocumentStart);
+        assert_eq!(
+            ext.scripts()[0].extension,
+            Some(ExtensionId("reader".into()))
+        );
+
+        let filter = &ext.styles()[0].filter;
+        assert!(filter.matches(&Url::parse("https://www.example.com/a").unwrap()));
+        assert!(!filter.matches(&Url::parse("https://login.example.com/").unwrap()));
+    }
+
+    #[test]
+    fn missing_files_and_bad_run_at_are_errors() {
+        let manifest =
+            r#"{"name": "x", "content_scripts": [{"matches": ["<all_urls>"], "js": ["gone.js"]}]}"#;
+        let err = Extension::from_manifest(ExtensionId("x".into()), manifest, |f| {
+            bail!("no such file: {f}")
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("gone.js"));
+
+        let manifest =
+            r#"{"name": "x", "content_scripts": [{"matches": ["<all_urls>"], "run_at": "later"}]}"#;
+        assert!(
+            Extension::from_manifest(ExtensionId("x".into()), manifest, |_| Ok(String::new()))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Per-zone favicon cache.
+//!
+//! History and bookmark panels show the icon of every site they list, and fetching those
+//! again each time is slow and wasteful. A [`FaviconStore`] keeps the icons a zone's tabs
+//! discovered, keyed by the origin of the page that used them, in memory or in a SQLite
+//! database.
+//!
+//! Tabs fill the store themselves: when a document commits, the tab looks for
+//! `<link rel="icon">` elements (falling back to `/favicon.ico`), uses a cached copy when
+//! there is one, and fetches and stores the icon otherwise. [`Zone::favicon_for`] then looks
+//! up the icon that fits a given size best.
+//!
+//! ```
+//! use gosub_engine::zone::{Favicon, FaviconStore};
+//! use url::Url;
+//!
+//! let store = FaviconStore::in_memory();
+//! let page = Url::parse("https://news.example/today").unwrap();
+//! for size in [16, 64] {
+//!     store.put(&page, Favicon {
+//!         url: Url::parse(&format!("https://news.example/icon-{size}.png")).unwrap(),
+//!         mime: "image/png".to_string(),
+//!         size: Some(size),
+//!         data: vec![0; 8],
+//!     });
+//! }
+//!
+//! let icon = store.favicon_for(&Url::parse("https://news.example/").unwrap(), 32).unwrap();
+//! assert_eq!(icon.size, Some(64));
+//! ```
+//!
+//! [`Zone::favicon_for`]: crate::zone::Zone::favicon_for
+use crate::engine::forms::{attr, tags};
+use crate::engine::storage::ProfileLock;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use url::Url;
+
+#[cfg(feature = "sqlite_local_store")]
+use crate::engine::logging::engine_log;
+#[cfg(feature = "sqlite_local_store")]
+use crate::engine::storage::migrations::{migrate, Migration};
+#[cfg(feature = "sqlite_local_store")]
+use crate::EngineError;
+#[cfg(feature = "sqlite_local_store")]
+use r2d2::Pool;
+#[cfg(feature = "sqlite_local_store")]
+use r2d2_sqlite::rusqlite::params;
+#[cfg(feature = "sqlite_local_store")]
+use r2d2_sqlite::SqliteConnectionManager;
+#[cfg(feature = "sqlite_local_store")]
+use std::path::PathBuf;
+
+/// Schema of the `favicons` table
+#[cfg(feature = "sqlite_local_store")]
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    sql: "CREATE TABLE IF NOT EXISTS favicons (
+        origin TEXT NOT NULL,
+        url TEXT NOT NULL,
+        mime TEXT NOT NULL,
+        size INTEGER,
+        data BLOB NOT NULL,
+        updated_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+        PRIMARY KEY(origin, url)
+    );",
+}];
+
+/// An icon a site uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Favicon {
+    /// Where the icon was fetched from
+    pub url: Url,
+    /// Media type of the image, such as `image/png`
+    pub mime: String,
+    /// Width (and height) in pixels, as declared by the page, or `None` when unknown or
+    /// scalable
+    pub size: Option<u32>,
+    /// Image data
+    pub data: Vec<u8>,
+}
+
+/// Where a store keeps its icons.
+enum Backend {
+    Memory(RwLock<BTreeMap<String, Vec<Favicon>>>),
+    #[cfg(feature = "sqlite_local_store")]
+    Sqlite(Pool<SqliteConnectionManager>),
+}
+
+/// Favicons of a zone, keyed by the origin of the pages that use them.
+pub struct FaviconStore {
+    backend: Backend,
+    /// Keeps other engine instances from using the same database
+    _lock: Option<ProfileLock>,
+}
+
+impl FaviconStore {
+    /// Creates a store that only lives as long as the engine.
+    pub fn in_memory() -> Self {
+        Self {
+            backend: Backend::Memory(RwLock::new(BTreeMap::new())),
+            _lock: None,
+        }
+    }
+
+    /// Opens (or creates) a store backed by the SQLite database at `path`.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another instance already uses the database.
+    /// - [`EngineError::StorageError`] when the database cannot be opened or migrated.
+    #[cfg(feature = "sqlite_local_store")]
+    pub fn open(path: PathBuf) -> Result<Self, EngineError> {
+        let lock = ProfileLock::acquire(&path)?;
+        let manager = SqliteConnectionManager::file(&path).with_init(|c| {
+            c.busy_timeout(std::time::Duration::from_millis(500))?;
+            c.pragma_update(None, "journal_mode", "WAL")?;
+            Ok(())
+        });
+        let pool = Pool::builder()
+            .max_size(4)
+            .build(manager)
+            .map_err(|e| EngineError::StorageError(e.to_string()))?;
+        let conn = pool
+            .get()
+            .map_err(|e| EngineError::StorageError(e.to_string()))?;
+        migrate(&conn, Some(&path), MIGRATIONS)?;
+
+        Ok(Self {
+            backend: Backend::Sqlite(pool),
+            _lock: Some(lock),
+        })
+    }
+
+    /// Stores `icon` for the origin of `page`, replacing an earlier copy of the same icon.
+    /// Returns false for pages without a proper origin, such as `data:` URLs, or when the
+    /// icon could not be written.
+    pub fn put(&self, page: &Url, icon: Favicon) -> bool {
+        let Some(origin) = origin_key(page) else {
+            return false;
+        };
+        match &self.backend {
+            Backend::Memory(sites) => {
+                let mut sites = sites.write().unwrap();
+                let icons = sites.entry(origin).or_default();
+                icons.retain(|i| i.url != icon.url);
+                icons.push(icon);
+                true
+            }
+            #[cfg(feature = "sqlite_local_store")]
+            Backend::Sqlite(pool) => {
+                let result = pool.get().map_err(|e| e.to_string()).and_then(|conn| {
+                    conn.execute(
+                        "INSERT INTO favicons(origin,url,mime,size,data) VALUES (?1,?2,?3,?4,?5)
+                         ON CONFLICT(origin,url) DO UPDATE
+                         SET mime=excluded.mime, size=excluded.size, data=excluded.data,
+                             updated_at=strftime('%s','now')",
+                        params![origin, icon.url.as_str(), icon.mime, icon.size, icon.data],
+                    )
+                    .map_err(|e| e.to_string())
+                });
+                if let Err(e) = &result {
+                    engine_log!(Error, "Failed to store favicon {}: {}", icon.url, e);
+                }
+                result.is_ok()
+            }
+        }
+    }
+
+    /// Returns all icons stored for the origin of `page`.
+    pub fn icons(&self, page: &Url) -> Vec<Favicon> {
+        let Some(origin) = origin_key(page) else {
+            return Vec::new();
+        };
+        match &self.backend {
+            Backend::Memory(sites) => sites
+                .read()
+                .unwrap()
+                .get(&origin)
+                .cloned()
+                .unwrap_or_default(),
+            #[cfg(feature = "sqlite_local_store")]
+            Backend::Sqlite(pool) => {
+                let result = pool.get().map_err(|e| e.to_string()).and_then(|conn| {
+                    let mut stmt = conn
+                        .prepare("SELECT url, mime, size, data FROM favicons WHERE origin=?1")
+                        .map_err(|e| e.to_string())?;
+                    let rows = stmt
+                        .query_map(params![origin], |row| {
+                            Ok((
+                                row.get::<_, String>(0)?,
+                                row.get::<_, String>(1)?,
+                                row.get::<_, Option<u32>>(2)?,
+                                row.get::<_, Vec<u8>>(3)?,
+                            ))
+                        })
+                        .map_err(|e| e.to_string())?;
+                    Ok(rows
+                        .filter_map(Result::ok)
+                        .filter_map(|(url, mime, size, data)| {
+                            Some(Favicon {
+                                url: Url::parse(&url).ok()?,
+                                mime,
+                                size,
+                                data,
+                            })
+                        })
+                        .collect())
+                });
+                result.unwrap_or_else(|e| {
+                    engine_log!(Error, "Failed to read favicons of {}: {}", origin, e);
+                    Vec::new()
+                })
+            }
+        }
+    }
+
+    /// Returns the icon of the origin of `page` that suits `size` pixels best: the smallest
+    /// one that is at least that large, else one of unknown size, else the largest one.
+    pub fn favicon_for(&self, page: &Url, size: u32) -> Option<Favicon> {
+        best_fit(self.icons(page), size, |icon| icon.size)
+    }
+
+    /// Removes the icons of the origin of `page`. Returns false when there were none.
+    pub fn remove(&self, page: &Url) -> bool {
+        let Some(origin) = origin_key(page) else {
+            return false;
+        };
+        match &self.backend {
+            Backend::Memory(sites) => sites.write().unwrap().remove(&origin).is_some(),
+            #[cfg(feature = "sqlite_local_store")]
+            Backend::Sqlite(pool) => pool.get().is_ok_and(|conn| {
+                conn.execute("DELETE FROM favicons WHERE origin=?1", params![origin])
+                    .is_ok_and(|n| n > 0)
+            }),
+        }
+    }
+
+    /// Removes all icons.
+    pub fn clear(&self) {
+        match &self.backend {
+            Backend::Memory(sites) => sites.write().unwrap().clear(),
+            #[cfg(feature = "sqlite_local_store")]
+            Backend::Sqlite(pool) => {
+                if let Ok(conn) = pool.get() {
+                    let _ = conn.execute("DELETE FROM favicons", []);
+                }
+            }
+        }
+    }
+}
+
+impl Default for FaviconStore {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+/// An icon a document links to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FaviconLink {
+    pub url: Url,
+    pub size: Option<u32>,
+}
+
+/// Returns the icons the document at `base` links to with `<link rel="icon">` (or the older
+/// `rel="shortcut icon"`), or `/favicon.ico` when it links to none.
+pub(crate) fn favicon_links(html: &str, base: &Url) -> Vec<FaviconLink> {
+    let mut links = Vec::new();
+    for (name, attrs, _) in tags(html) {
+        if name != "link" {
+            continue;
+        }
+        let is_icon = attr(attrs, "rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|r| r.eq_ignore_ascii_case("icon"))
+        });
+        let href = attr(attrs, "href");
+        let Some(url) = href.filter(|_| is_icon).and_then(|h| base.join(&h).ok()) else {
+            continue;
+        };
+        let size = attr(attrs, "sizes").and_then(|s| largest_size(&s));
+        links.push(FaviconLink { url, size });
+    }
+    if links.is_empty() {
+        if let Ok(url) = base.join("/favicon.ico") {
+            links.push(FaviconLink { url, size: None });
+        }
+    }
+    links
+}
+
+/// Returns the largest size in a `sizes` attribute such as `16x16 32x32`. `any` counts as
+/// unknown.
+fn largest_size(sizes: &str) -> Option<u32> {
+    sizes
+        .split_ascii_whitespace()
+        .filter_map(|s| {
+            let (w, h) = s
+                .to_ascii_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>(), h.parse::<u32>()))?;
+            Some(w.ok()?.max(h.ok()?))
+        })
+        .max()
+}
+
+/// Picks the item that suits `size` pixels best, by the rules of
+/// [`FaviconStore::favicon_for`]. `size_of` returns the size of an item.
+pub(crate) fn best_fit<T>(
+    items: impl IntoIterator<Item = T>,
+    size: u32,
+    size_of: impl Fn(&T) -> Option<u32>,
+) -> Option<T> {
+    items.into_iter().min_by_key(|item| match size_of(item) {
+        Some(s) if s >= size => (0, s),
+        None => (1, 0),
+        Some(s) => (2, u32::MAX - s),
+    })
+}
+
+fn origin_key(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{new_engine, open_tab, tick};
+    use crate::EngineCommand;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn u(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn icon(url: &str, size: Option<u32>) -> Favicon {
+        Favicon {
+            url: u(url),
+            mime: "image/png".to_string(),
+            size,
+            data: vec![1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn lookups_pick_the_best_size_per_origin() {
+        let store = FaviconStore::in_memory();
+        let page = u("https://site.test/a/b");
+        assert!(store.put(&page, icon("https://site.test/16.png", Some(16))));
+        assert!(store.put(&page, icon("https://cdn.test/any.svg", None)));
+        assert!(store.put(&page, icon("https://site.test/64.png", Some(64))));
+        assert!(!store.put(
+            &u("data:text/html,x"),
+            icon("https://site.test/x.png", None)
+        ));
+
+        let pick = |size| {
+            store
+                .favicon_for(&u("https://site.test/"), size)
+                .unwrap()
+                .size
+        };
+        assert_eq!(pick(16), Some(16));
+        assert_eq!(pick(32), Some(64));
+        assert_eq!(pick(128), None);
+        assert!(store.favicon_for(&u("http://site.test/"), 16).is_none());
+
+        // Storing an icon again replaces it
+        store.put(&page, icon("https://site.test/16.png", Some(16)));
+        assert_eq!(store.icons(&page).len(), 3);
+        assert!(store.remove(&page));
+        assert!(store.icons(&page).is_empty());
+    }
+
+    #[test]
+    fn documents_link_their_icons() {
+        let base = u("https://site.test/docs/page.html");
+        let links = favicon_links(
+            r#"<head><link rel="stylesheet" href="a.css"><link rel="shortcut icon" href="/fav.ico">
+            <link rel=icon sizes="16x16 48x48" href="icons/i.png"></head>"#,
+            &base,
+        );
+        assert_eq!(
+            links,
+            vec![
+                FaviconLink {
+                    url: u("https://site.test/fav.ico"),
+                    size: None
+                },
+                FaviconLink {
+                    url: u("https://site.test/docs/icons/i.png"),
+                    size: Some(48)
+                },
+            ]
+        );
+        assert_eq!(
+            favicon_links("<p>no icons</p>", &base)[0].url,
+            u("https://site.test/favicon.ico")
+        );
+    }
+
+    #[cfg(feature = "sqlite_local_store")]
+    #[test]
+    fn icons_survive_reopening_the_database() {
+        let dir = std::env::temp_dir().join(format!("gosub-favicons-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("favicons.sqlite");
+        let page = u("https://site.test/");
+
+        {
+            let store = FaviconStore::open(path.clone()).unwrap();
+            assert!(store.put(&page, icon("https://site.test/32.png", Some(32))));
+        }
+        let store = FaviconStore::open(path).unwrap();
+        assert_eq!(
+            store.favicon_for(&page, 16),
+            Some(icon("https://site.test/32.png", Some(32)))
+        );
+        drop(store);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn favicons_are_fetched_once_and_cached_per_zone() {
+        use std::io::{Read, Write};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let icon_requests = Arc::new(AtomicUsize::new(0));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let counter = icon_requests.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                let (content_type, body): (_, &[u8]) = if buf[..n].starts_with(b"GET /icon.png") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    ("image/png", b"\x89PNG")
+                } else {
+                    (
+                        "text/html",
+                        br#"<link rel="icon" sizes="32x32" href="/icon.png">"#,
+                    )
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(body);
+            }
+        });
+
+        let mut engine = new_engine(None);
+        let zone_id = engine.zone_builder().create().unwrap();
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/page")).unwrap();
+        for _ in 0..2 {
+            let tab_id = open_tab(&mut engine, zone_id);
+            engine
+                .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+                .unwrap();
+            let tab = engine.get_tab(tab_id).unwrap();
+            for _ in 0..400 {
+                tick(&mut engine);
+                if !tab.lock().unwrap().favicon.is_empty() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            assert_eq!(tab.lock().unwrap().favicon, b"\x89PNG");
+        }
+        assert_eq!(icon_requests.load(Ordering::SeqCst), 1);
+
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let icon = zone.lock().unwrap().favicon_for(&url, 16).unwrap();
+        assert_eq!((icon.mime.as_str(), icon.size), ("image/png", Some(32)));
+    }
+}
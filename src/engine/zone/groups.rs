@@ -0,0 +1,189 @@
+//! Tab groups.
+//!
+//! A zone can sort its tabs into named, colored [`TabGroup`]s, as shown by tab strips that
+//! fold related tabs together. A tab is in at most one group. The engine does not treat
+//! grouped tabs differently; groups are metadata the user agent reads back with
+//! [`Zone::tab_groups`](crate::zone::Zone::tab_groups), and every change is published as
+//! [`EngineNotification::TabGroupChanged`](crate::EngineNotification::TabGroupChanged) or
+//! [`EngineNotification::TabGroupRemoved`](crate::EngineNotification::TabGroupRemoved).
+//!
+//! To keep groups across restarts, the user agent stores the groups with the rest of its
+//! session (with the `serde_events` feature they serialize) and hands them to
+//! [`Zone::restore_tab_groups`](crate::zone::Zone::restore_tab_groups) once it has reopened
+//! the tabs. Pinned tabs are saved and restored the same way, with
+//! [`Zone::pinned_tabs`](crate::zone::Zone::pinned_tabs) and
+//! [`Zone::restore_pinned_tabs`](crate::zone::Zone::restore_pinned_tabs).
+use crate::tab::TabId;
+use std::fmt::Display;
+use uuid::Uuid;
+
+/// Identifies a [`TabGroup`] within its zone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct TabGroupId(Uuid);
+
+impl TabGroupId {
+    /// Creates a new, unique group id.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for TabGroupId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for TabGroupId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A named set of tabs in a zone.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct TabGroup {
+    /// ID of the group
+    pub id: TabGroupId,
+    /// Name shown on the group, can be empty
+    pub name: String,
+    /// Color of the group (RGBA)
+    pub color: [u8; 4],
+    /// Tabs in the group, in the order they were added
+    pub tabs: Vec<TabId>,
+}
+
+/// The groups of a zone, in the order they were created.
+#[derive(Debug, Default)]
+pub(crate) struct TabGroups {
+    groups: Vec<TabGroup>,
+}
+
+impl TabGroups {
+    pub(crate) fn all(&self) -> &[TabGroup] {
+        &self.groups
+    }
+
+    pub(crate) fn get(&self, id: TabGroupId) -> Option<&TabGroup> {
+        self.groups.iter().find(|g| g.id == id)
+    }
+
+    pub(crate) fn create(&mut self, name: &str, color: [u8; 4]) -> TabGroupId {
+        let id = TabGroupId::new();
+        self.groups.push(TabGroup {
+            id,
+            name: name.to_string(),
+            color,
+            tabs: Vec::new(),
+        });
+        id
+    }
+
+    /// Changes the name and color of a group. Returns false when there is no such group.
+    pub(crate) fn update(&mut self, id: TabGroupId, name: &str, color: [u8; 4]) -> bool {
+        let Some(group) = self.groups.iter_mut().find(|g| g.id == id) else {
+            return false;
+        };
+        group.name = name.to_string();
+        group.color = color;
+        true
+    }
+
+    /// Removes a group; its tabs become ungrouped. Returns false when there is no such group.
+    pub(crate) fn remove(&mut self, id: TabGroupId) -> bool {
+        let len = self.groups.len();
+        self.groups.retain(|g| g.id != id);
+        self.groups.len() != len
+    }
+
+    /// Returns the group `tab` is in.
+    pub(crate) fn group_of(&self, tab: TabId) -> Option<TabGroupId> {
+        self.groups
+            .iter()
+            .find(|g| g.tabs.contains(&tab))
+            .map(|g| g.id)
+    }
+
+    /// Moves `tab` into group `id`, out of the group it was in before, which is returned.
+    /// Does nothing when there is no group `id`.
+    pub(crate) fn assign(&mut self, tab: TabId, id: TabGroupId) -> Option<TabGroupId> {
+        self.get(id)?;
+        let previous = self.unassign(tab);
+        if let Some(group) = self.groups.iter_mut().find(|g| g.id == id) {
+            group.tabs.push(tab);
+        }
+        previous
+    }
+
+    /// Takes `tab` out of its group, returning the group.
+    pub(crate) fn unassign(&mut self, tab: TabId) -> Option<TabGroupId> {
+        let group = self.groups.iter_mut().find(|g| g.tabs.contains(&tab))?;
+        group.tabs.retain(|t| *t != tab);
+        Some(group.id)
+    }
+
+    /// Replaces all groups. Tabs for which `is_open` returns false are left out, and a tab that
+    /// is listed in several groups only stays in the first.
+    pub(crate) fn restore(&mut self, groups: Vec<TabGroup>, is_open: impl Fn(TabId) -> bool) {
+        self.groups.clear();
+        for mut group in groups {
+            group
+                .tabs
+                .retain(|tab| is_open(*tab) && self.group_of(*tab).is_none());
+            let mut seen = Vec::with_capacity(group.tabs.len());
+            group.tabs.retain(|tab| {
+                let first = !seen.contains(tab);
+                seen.push(*tab);
+                first
+            });
+            self.groups.push(group);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabs_are_in_at_most_one_group() {
+        let (a, b, closed) = (TabId::new(), TabId::new(), TabId::new());
+        let mut groups = TabGroups::default();
+        let work = groups.create("Work", [255, 0, 0, 255]);
+        let news = groups.create("News", [0, 0, 255, 255]);
+
+        assert_eq!(groups.assign(a, work), None);
+        assert_eq!(groups.assign(b, work), None);
+        assert_eq!(groups.assign(a, news), Some(work));
+        assert_eq!(groups.get(work).unwrap().tabs, vec![b]);
+        assert_eq!(groups.group_of(a), Some(news));
+        assert_eq!(groups.assign(a, TabGroupId::new()), None);
+        assert_eq!(groups.group_of(a), Some(news));
+
+        assert!(groups.remove(news));
+        assert_eq!(groups.group_of(a), None);
+        assert_eq!(groups.unassign(b), Some(work));
+        assert_eq!(groups.unassign(b), None);
+
+        let saved = vec![
+            TabGroup {
+                id: work,
+                name: "Work".to_string(),
+                color: [255, 0, 0, 255],
+                tabs: vec![a, closed, a],
+            },
+            TabGroup {
+                id: news,
+                name: "News".to_string(),
+                color: [0, 0, 255, 255],
+                tabs: vec![a, b],
+            },
+        ];
+        groups.restore(saved, |tab| tab != closed);
+        assert_eq!(groups.all().len(), 2);
+        assert_eq!(groups.get(work).unwrap().tabs, vec![a]);
+        assert_eq!(groups.get(news).unwrap().tabs, vec![b]);
+    }
+}
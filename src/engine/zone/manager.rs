@@ -35,14 +35,40 @@
 //! let zone = manager.get_zone(zone_id).unwrap();
 //! ```
 
-use crate::cookies::CookieJarHandle;
+use crate::cookies::{CookieJarHandle, DefaultCookieJar};
 use crate::engine::storage::local::in_memory::InMemoryLocalStore;
 use crate::engine::storage::StorageService;
+use crate::engine::watchdog::Watchdog;
 use crate::engine::zone::{Zone, ZoneConfig, ZoneId};
+use crate::engine::{
+    EngineNotification, NotificationBus, NotificationCategories, NotificationSubscription,
+    SequencedSubscription,
+};
 use crate::storage::InMemorySessionStore;
 use crate::{EngineConfig, EngineError};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use url::Url;
+
+/// Options for [`ZoneManager::clone_zone`].
+///
+/// By default, the clone gets the same configuration and metadata as the source zone, a fresh
+/// in-memory storage service and an empty cookie jar. Set `copy_cookies` and/or
+/// `copy_local_storage` to take a snapshot of the source zone's data. The snapshot is a copy:
+/// changes made in the clone never reach the source zone (and vice versa).
+#[derive(Default, Clone)]
+pub struct ZoneCloneOptions {
+    /// ID for the cloned zone. If not provided, a new one is generated.
+    pub zone_id: Option<ZoneId>,
+    /// Copy the cookies of the source zone into the (in-memory) cookie jar of the clone.
+    pub copy_cookies: bool,
+    /// Copy the localStorage data of the source zone into the storage of the clone.
+    pub copy_local_storage: bool,
+    /// Storage service for the clone. Defaults to in-memory storage, which makes the clone a
+    /// temporary copy that is never persisted.
+    pub storage: Option<Arc<StorageService>>,
+}
 
 /// Manages all zones within the engine.
 ///
@@ -54,6 +80,12 @@ pub struct ZoneManager {
     config: EngineConfig,
     /// Thread-safe map of all active zones, keyed by their IDs.
     zones: Arc<Mutex<HashMap<ZoneId, Arc<Mutex<Zone>>>>>,
+    /// Bus shared by all zones for engine notifications.
+    notifications: Arc<NotificationBus>,
+    /// Watchdog shared by all zones, if the engine runs one.
+    watchdog: Option<Arc<Watchdog>>,
+    /// Whether the host is online, as last reported by the user agent. Shared with all tabs.
+    online: Arc<AtomicBool>,
 }
 
 impl ZoneManager {
@@ -62,9 +94,68 @@ impl ZoneManager {
         Self {
             config,
             zones: Arc::new(Mutex::new(HashMap::new())),
+            notifications: Arc::new(NotificationBus::default()),
+            watchdog: None,
+            online: Arc::new(AtomicBool::new(true)),
         }
     }
 
+    /// Limits the notifications each subscriber can have queued. Must be called before zones
+    /// are created, as they keep the bus they were bound to.
+    pub(crate) fn with_notification_capacity(mut self, capacity: Option<usize>) -> Self {
+        self.notifications = Arc::new(NotificationBus::with_capacity(capacity));
+        self
+    }
+
+    /// Starts the watchdog when the configuration asks for one. Must be called after the
+    /// notification bus is set up and before zones are created.
+    pub(crate) fn with_watchdog(mut self) -> Self {
+        let kill = self.config.tab_watchdog_kill;
+        self.watchdog = self
+            .config
+            .tab_watchdog_timeout
+            .map(|timeout| Arc::new(Watchdog::start(timeout, kill, self.notifications.clone())));
+        self
+    }
+
+    /// Returns the watchdog of the engine, if it runs one.
+    pub(crate) fn watchdog(&self) -> Option<&Watchdog> {
+        self.watchdog.as_deref()
+    }
+
+    /// Returns whether the host is online, as last reported with
+    /// [`ZoneManager::set_network_state`].
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    /// Records whether the host is online. When this changes, every zone publishes
+    /// [`EngineNotification::NetworkStateChanged`](crate::EngineNotification::NetworkStateChanged).
+    pub fn set_network_state(&self, online: bool) {
+        if self.online.swap(online, Ordering::Relaxed) == online {
+            return;
+        }
+        for zone_id in self.iter() {
+            self.notifications
+                .publish(EngineNotification::NetworkStateChanged { zone_id, online });
+        }
+    }
+
+    /// Publishes an [`EngineNotification::Warning`](crate::EngineNotification::Warning) that
+    /// concerns the whole engine in every zone. Returns false when there is no zone to
+    /// publish it in.
+    pub(crate) fn warn_all(&self, message: &str) -> bool {
+        let zones = self.iter();
+        for zone_id in &zones {
+            self.notifications.publish(EngineNotification::Warning {
+                zone_id: *zone_id,
+                tab_id: None,
+                message: message.to_string(),
+            });
+        }
+        !zones.is_empty()
+    }
+
     /// Creates a new zone with the given configuration and optional services.
     ///
     /// # Arguments
@@ -82,6 +173,19 @@ impl ZoneManager {
         config: Option<ZoneConfig>,
         storage_service: Option<Arc<StorageService>>,
         cookie_jar: Option<CookieJarHandle>,
+    ) -> Result<ZoneId, EngineError> {
+        self.create_zone_with(zone_id, config, storage_service, cookie_jar, |_| {})
+    }
+
+    /// Like [`ZoneManager::create_zone`], but lets `init` set up the zone before it is
+    /// announced with [`EngineNotification::ZoneCreated`].
+    fn create_zone_with(
+        &self,
+        zone_id: Option<ZoneId>,
+        config: Option<ZoneConfig>,
+        storage_service: Option<Arc<StorageService>>,
+        cookie_jar: Option<CookieJarHandle>,
+        init: impl FnOnce(&mut Zone),
     ) -> Result<ZoneId, EngineError> {
         let mut zones = self.zones.lock().unwrap();
 
@@ -99,7 +203,7 @@ impl ZoneManager {
         });
 
         let resolved_config = config.unwrap_or_else(|| self.config.default_zone_config.clone());
-        let zone = match zone_id {
+        let mut zone = match zone_id {
             Some(id) => {
                 if zones.contains_key(&id) {
                     return Err(EngineError::ZoneAlreadyExists);
@@ -108,12 +212,139 @@ impl ZoneManager {
             }
             None => Zone::new(resolved_config, storage, cookie_jar),
         };
+        // Before the zone is bound, its own notifications go nowhere
+        init(&mut zone);
+        zone.bind_engine(
+            self.notifications.clone(),
+            self.watchdog.clone(),
+            self.online.clone(),
+            &self.config,
+        );
         let zone_id = zone.id;
+        self.notifications.publish(EngineNotification::ZoneCreated {
+            zone_id,
+            metadata: zone.metadata(),
+        });
 
         zones.insert(zone_id, Arc::new(Mutex::new(zone)));
         Ok(zone_id)
     }
 
+    /// Subscribe to notifications from all zones and their tabs.
+    pub(crate) fn subscribe_notifications(&self) -> NotificationSubscription {
+        self.notifications.subscribe()
+    }
+
+    /// Subscribe to some categories of notifications from all zones and their tabs.
+    pub(crate) fn subscribe_categories(
+        &self,
+        categories: NotificationCategories,
+    ) -> NotificationSubscription {
+        self.notifications.subscribe_categories(categories)
+    }
+
+    /// Subscribe to notifications from one zone and its tabs.
+    pub(crate) fn subscribe_zone_notifications(
+        &self,
+        zone_id: ZoneId,
+    ) -> Result<NotificationSubscription, EngineError> {
+        self.subscribe_zone_categories(zone_id, NotificationCategories::DEFAULT)
+    }
+
+    /// Subscribe to some categories of notifications from one zone and its tabs.
+    pub(crate) fn subscribe_zone_categories(
+        &self,
+        zone_id: ZoneId,
+        categories: NotificationCategories,
+    ) -> Result<NotificationSubscription, EngineError> {
+        if self.get_zone(zone_id).is_none() {
+            return Err(EngineError::ZoneNotFound);
+        }
+        Ok(self
+            .notifications
+            .subscribe_zone_categories(zone_id, categories))
+    }
+
+    /// Subscribe to notifications from all zones and their tabs, with sequence numbers.
+    pub(crate) fn subscribe_sequenced(&self) -> SequencedSubscription {
+        self.notifications.subscribe_sequenced()
+    }
+
+    /// Creates a new zone based on the configuration (and optionally the data) of `source_id`.
+    ///
+    /// See [`ZoneCloneOptions`] for what is copied. Session storage is never copied, as it
+    /// belongs to individual tabs.
+    ///
+    /// # Errors
+    /// - Returns [`EngineError::ZoneNotFound`] if the source zone does not exist.
+    /// - Returns [`EngineError::StorageError`] if the localStorage data could not be copied.
+    /// - Any error returned by [`ZoneManager::create_zone`].
+    pub fn clone_zone(
+        &self,
+        source_id: ZoneId,
+        options: ZoneCloneOptions,
+    ) -> Result<ZoneId, EngineError> {
+        let source_arc = self.get_zone(source_id).ok_or(EngineError::ZoneNotFound)?;
+        let source = source_arc.lock().map_err(|_| EngineError::ZoneLocked)?;
+
+        let storage = options.storage.unwrap_or_else(|| {
+            Arc::new(StorageService::new(
+                Arc::new(InMemoryLocalStore::new()),
+                Arc::new(InMemorySessionStore::new()),
+            ))
+        });
+
+        let jar = if options.copy_cookies {
+            let guard = source
+                .cookie_jar
+                .read()
+                .map_err(|_| EngineError::Internal)?;
+            DefaultCookieJar::snapshot_of(&*guard).ok_or_else(|| {
+                EngineError::StorageError("cookie jar of the source zone cannot be copied".into())
+            })?
+        } else {
+            DefaultCookieJar::new()
+        };
+        let jar: CookieJarHandle = Arc::new(RwLock::new(jar));
+
+        let zone_id = self.create_zone_with(
+            options.zone_id,
+            Some(source.config().clone()),
+            Some(storage.clone()),
+            Some(jar),
+            |zone| {
+                zone.set_title(&source.title);
+                zone.set_description(&source.description);
+                zone.set_icon(source.icon.clone());
+            },
+        )?;
+
+        if options.copy_local_storage {
+            if let Err(e) = source.storage.copy_local_zone(source_id, &storage, zone_id) {
+                let _ = self.remove_zone(zone_id);
+                return Err(EngineError::StorageError(e.to_string()));
+            }
+        }
+
+        Ok(zone_id)
+    }
+
+    /// Returns the `Cookie` header zone `owner` would send to `url`, read on behalf of zone
+    /// `reader`. See [`GosubEngine::read_shared_cookies`](crate::GosubEngine::read_shared_cookies).
+    pub fn read_shared_cookies(
+        &self,
+        reader: ZoneId,
+        owner: ZoneId,
+        url: &Url,
+    ) -> Result<Option<String>, EngineError> {
+        if self.get_zone(reader).is_none() {
+            return Err(EngineError::ZoneNotFound);
+        }
+        let owner = self.get_zone(owner).ok_or(EngineError::ZoneNotFound)?;
+        let owner = owner.lock().map_err(|_| EngineError::ZoneLocked)?;
+        owner.read_cookies_for(reader, url)
+    }
+
     /// Retrieves a zone by its [`ZoneId`], if it exists.
     pub fn get_zone(&self, id: ZoneId) -> Option<Arc<Mutex<Zone>>> {
         let zones = self.zones.lock().ok()?;
@@ -152,4 +383,51 @@ impl ZoneManager {
             .map(|z| z.keys().copied().collect())
             .unwrap_or_default()
     }
+
+    /// Replaces the engine configuration and applies it to all existing zones.
+    pub(crate) fn update_engine_config(&mut self, config: EngineConfig) {
+        self.config = config;
+        let Ok(zones) = self.zones.lock() else {
+            return;
+        };
+        for zone in zones.values() {
+            if let Ok(mut zone) = zone.lock() {
+                zone.apply_engine_config(&self.config);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::new_engine;
+
+    #[test]
+    fn clones_are_announced_with_the_metadata_of_their_source() {
+        let mut engine = new_engine(None);
+        let source = engine.zone_builder().create().unwrap();
+        {
+            let zone = engine.get_zone_mut(source).unwrap();
+            let mut zone = zone.lock().unwrap();
+            zone.set_title("Work");
+            zone.set_description("Office accounts");
+            zone.set_icon(vec![1, 2, 3]);
+        }
+        let rx = engine.subscribe_notifications();
+
+        let copy = engine
+            .clone_zone(source, ZoneCloneOptions::default())
+            .unwrap();
+        match rx.try_recv() {
+            Ok(EngineNotification::ZoneCreated { zone_id, metadata }) => {
+                assert_eq!(zone_id, copy);
+                assert_eq!(metadata.title, "Work");
+                assert_eq!(metadata.description, "Office accounts");
+                assert_eq!(metadata.icon, [1, 2, 3]);
+            }
+            other => panic!("expected ZoneCreated, got {other:?}"),
+        }
+        assert!(rx.try_recv().is_err());
+    }
 }
@@ -0,0 +1,223 @@
+//! Services a zone is created with.
+//!
+//! [`ZoneServices`] bundles the stores and helpers that the setters of
+//! [`GosubEngine::zone_builder`](crate::GosubEngine::zone_builder) take one by one. An engine
+//! built with [`EngineBuilder::zone_services`](crate::EngineBuilder::zone_services) calls a
+//! [`ZoneServicesFactory`] for every new zone, and uses what it returns for everything the
+//! zone builder was not given explicitly.
+//!
+//! [`ZoneServices::builder`] has two presets that cover most user agents:
+//!
+//! - [`ephemeral`](ZoneServicesBuilder::ephemeral) keeps everything in memory, for private
+//!   windows and tests.
+//! - [`persistent`](ZoneServicesBuilder::persistent) keeps cookies and local storage in SQLite
+//!   databases in a [`Profile`](crate::profile::Profile) directory.
+//!
+//! ```no_run
+//! use gosub_engine::render::backends::null::NullBackend;
+//! use gosub_engine::zone::ZoneServices;
+//! use gosub_engine::GosubEngine;
+//!
+//! let services = ZoneServices::builder().persistent("profile").build().unwrap();
+//! let mut engine = GosubEngine::builder()
+//!     .backend(Box::new(NullBackend::new().unwrap()))
+//!     .zone_services(move |_| services.clone())
+//!     .build()
+//!     .unwrap();
+//! ```
+use crate::cookies::{CookieJarHandle, CookiePolicyHandle, CookieStoreHandle};
+use crate::net::ContentBlocker;
+use crate::spellcheck::SpellcheckHandle;
+use crate::storage::local::in_memory::InMemoryLocalStore;
+use crate::storage::{InMemorySessionStore, PartitionPolicy, StorageService};
+use crate::zone::{FaviconStore, SiteSettingsStore, ZoneId};
+use crate::EngineError;
+#[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Stores and helpers of a zone. `None` fields get the engine defaults, which keep everything
+/// in memory.
+#[derive(Clone, Default)]
+pub struct ZoneServices {
+    /// Local and session storage
+    pub storage: Option<Arc<StorageService>>,
+    /// Store the zone's cookie jar is taken from. Ignored when `cookie_jar` is set.
+    pub cookie_store: Option<CookieStoreHandle>,
+    /// Cookie jar of the zone
+    pub cookie_jar: Option<CookieJarHandle>,
+    /// Spellchecker for editable fields
+    pub spellcheck: Option<SpellcheckHandle>,
+    /// Filter lists applied to requests
+    pub content_blocker: Option<Arc<ContentBlocker>>,
+    /// Which cookies the zone stores
+    pub cookie_policy: Option<CookiePolicyHandle>,
+    /// Per-site settings
+    pub site_settings: Option<Arc<SiteSettingsStore>>,
+    /// Cache of the icons of visited sites
+    pub favicons: Option<Arc<FaviconStore>>,
+    /// How the zone's tabs partition their storage
+    pub partition_policy: Option<PartitionPolicy>,
+}
+
+impl ZoneServices {
+    /// Entry point to start building services.
+    pub fn builder() -> ZoneServicesBuilder {
+        ZoneServicesBuilder::default()
+    }
+}
+
+/// Creates the services of a new zone, given its id.
+pub type ZoneServicesFactory = Arc<dyn Fn(ZoneId) -> ZoneServices + Send + Sync>;
+
+/// Where a [`ZoneServicesBuilder`] keeps its data.
+#[derive(Clone, Debug, Default)]
+enum Preset {
+    /// Nothing is set up beyond what was given explicitly
+    #[default]
+    Custom,
+    /// In-memory stores
+    Ephemeral,
+    /// SQLite stores in the given directory
+    #[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+    Persistent(PathBuf),
+}
+
+/// Builder for [`ZoneServices`].
+///
+/// Start from a preset and override what the user agent provides itself; explicitly set
+/// services always win over the ones of the preset. The result can be handed to several
+/// zones: the stores keep the data of each zone apart. Cookie jars, site settings and
+/// favicons are per zone, so the presets leave those to the zone builder.
+#[derive(Default)]
+pub struct ZoneServicesBuilder {
+    /// Stores to set up
+    preset: Preset,
+    /// Services set explicitly
+    services: ZoneServices,
+}
+
+impl ZoneServicesBuilder {
+    /// Keeps storage and cookies in memory only. Storage is not partitioned, as nothing of
+    /// it outlives the engine.
+    pub fn ephemeral(mut self) -> Self {
+        self.preset = Preset::Ephemeral;
+        self
+    }
+
+    /// Keeps cookies and local storage in the [`Profile`](crate::profile::Profile) in `dir`,
+    /// which is created when it does not exist. Storage is partitioned by top-level origin, so third parties cannot
+    /// use what they stored to follow the user across sites and sessions.
+    #[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+    pub fn persistent(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.preset = Preset::Persistent(dir.into());
+        self
+    }
+
+    pub fn storage(mut self, storage: Arc<StorageService>) -> Self {
+        self.services.storage = Some(storage);
+        self
+    }
+
+    pub fn cookie_store(mut self, store: CookieStoreHandle) -> Self {
+        self.services.cookie_store = Some(store);
+        self
+    }
+
+    pub fn spellcheck(mut self, provider: SpellcheckHandle) -> Self {
+        self.services.spellcheck = Some(provider);
+        self
+    }
+
+    pub fn content_blocker(mut self, blocker: Arc<ContentBlocker>) -> Self {
+        self.services.content_blocker = Some(blocker);
+        self
+    }
+
+    pub fn cookie_policy(mut self, policy: CookiePolicyHandle) -> Self {
+        self.services.cookie_policy = Some(policy);
+        self
+    }
+
+    pub fn partition_policy(mut self, policy: PartitionPolicy) -> Self {
+        self.services.partition_policy = Some(policy);
+        self
+    }
+
+    /// Opens the stores of the preset.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another instance uses the profile directory.
+    /// - [`EngineError::StorageError`] when the directory or a database cannot be created.
+    pub fn build(self) -> Result<ZoneServices, EngineError> {
+        let mut services = self.services;
+        match self.preset {
+            Preset::Custom => {}
+            Preset::Ephemeral => {
+                if services.storage.is_none() {
+                    services.storage = Some(Arc::new(StorageService::new(
+                        Arc::new(InMemoryLocalStore::new()),
+                        Arc::new(InMemorySessionStore::new()),
+                    )));
+                }
+                services
+                    .partition_policy
+                    .get_or_insert(PartitionPolicy::None);
+            }
+            #[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+            Preset::Persistent(dir) => {
+                let profile = crate::profile::Profile::open(dir)?;
+                let defaults = profile.services();
+                services.storage = services.storage.or(defaults.storage);
+                services.cookie_store = services.cookie_store.or(defaults.cookie_store);
+                services.partition_policy = services.partition_policy.or(defaults.partition_policy);
+            }
+        }
+        Ok(services)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_fill_what_was_not_given() {
+        let services = ZoneServices::builder().ephemeral().build().unwrap();
+        assert!(services.storage.is_some());
+        assert!(services.cookie_store.is_none());
+        assert_eq!(services.partition_policy, Some(PartitionPolicy::None));
+
+        let services = ZoneServices::builder()
+            .partition_policy(PartitionPolicy::TopLevelOrigin)
+            .ephemeral()
+            .build()
+            .unwrap();
+        assert_eq!(
+            services.partition_policy,
+            Some(PartitionPolicy::TopLevelOrigin)
+        );
+
+        let custom = ZoneServices::builder().build().unwrap();
+        assert!(custom.storage.is_none());
+        assert!(custom.partition_policy.is_none());
+    }
+
+    #[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+    #[test]
+    fn persistent_profiles_cannot_be_opened_twice() {
+        let dir = std::env::temp_dir().join(format!("gosub-profile-{}", uuid::Uuid::new_v4()));
+        let services = ZoneServices::builder().persistent(&dir).build().unwrap();
+        assert!(services.cookie_store.is_some());
+        assert!(dir.join("cookies.sqlite").exists());
+        assert!(dir.join("localstorage.sqlite").exists());
+        assert_eq!(
+            services.partition_policy,
+            Some(PartitionPolicy::TopLevelOrigin)
+        );
+
+        let again = ZoneServices::builder().persistent(&dir).build();
+        assert!(matches!(again, Err(EngineError::ProfileInUse(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
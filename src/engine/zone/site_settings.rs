@@ -0,0 +1,273 @@
+//! Per-site settings.
+//!
+//! Users can deviate from the zone defaults for individual sites: turn JavaScript or images
+//! off, remember a zoom level, or grant a permission. A [`SiteSettingsStore`] keeps these
+//! settings per origin, in memory or in a JSON file, and offers the lookups and CRUD
+//! operations a "site settings" panel needs.
+//!
+//! Each zone has a store, in memory unless another one is passed to the `site_settings` setter
+//! of [`GosubEngine::zone_builder`](crate::GosubEngine::zone_builder). Tabs consult it
+//! whenever a document commits: the zoom level is applied to the tab, and the JavaScript and
+//! image settings end up in the [`BrowsingContext`](crate::BrowsingContext).
+//!
+//! ```
+//! use gosub_engine::zone::{Permission, SiteSettingsStore};
+//! use url::Url;
+//!
+//! let store = SiteSettingsStore::in_memory();
+//! let url = Url::parse("https://maps.example/route?from=a").unwrap();
+//! store.update(&url, |s| {
+//!     s.zoom = Some(1.5);
+//!     s.permissions.insert(Permission::Geolocation, true);
+//! });
+//!
+//! let settings = store.get(&Url::parse("https://maps.example/").unwrap()).unwrap();
+//! assert_eq!(settings.permission(Permission::Geolocation), Some(true));
+//! ```
+use crate::engine::logging::engine_log;
+use crate::engine::storage::ProfileLock;
+use crate::EngineError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use url::Url;
+
+/// Capabilities a site has to ask the user for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Permission {
+    /// Access to the user's location
+    Geolocation,
+    /// Showing system notifications
+    Notifications,
+    /// Access to cameras
+    Camera,
+    /// Access to microphones
+    Microphone,
+    /// Reading the clipboard
+    ClipboardRead,
+    /// Opening pop-up windows
+    Popups,
+}
+
+/// Settings for one origin. `None` fields follow the zone defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SiteSettings {
+    /// Whether scripts may run
+    #[serde(default)]
+    pub javascript: Option<bool>,
+    /// Whether images are loaded
+    #[serde(default)]
+    pub images: Option<bool>,
+    /// Page zoom factor
+    #[serde(default)]
+    pub zoom: Option<f32>,
+    /// Permissions the user granted (`true`) or denied (`false`)
+    #[serde(default)]
+    pub permissions: BTreeMap<Permission, bool>,
+}
+
+impl SiteSettings {
+    /// Returns whether `permission` was granted (`Some(true)`), denied (`Some(false)`), or
+    /// has not been decided yet (`None`).
+    pub fn permission(&self, permission: Permission) -> Option<bool> {
+        self.permissions.get(&permission).copied()
+    }
+
+    /// Returns true when nothing deviates from the zone defaults.
+    pub fn is_empty(&self) -> bool {
+        self == &SiteSettings::default()
+    }
+}
+
+/// On-disk representation of a store.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SiteSettingsFile {
+    sites: BTreeMap<String, SiteSettings>,
+}
+
+/// Site settings of a zone, keyed by origin.
+pub struct SiteSettingsStore {
+    sites: RwLock<BTreeMap<String, SiteSettings>>,
+    /// File the settings are written to after each change, if any
+    path: Option<PathBuf>,
+    /// Keeps other engine instances from using the same file
+    _lock: Option<ProfileLock>,
+}
+
+impl SiteSettingsStore {
+    /// Creates a store that only lives as long as the engine.
+    pub fn in_memory() -> Self {
+        Self {
+            sites: RwLock::new(BTreeMap::new()),
+            path: None,
+            _lock: None,
+        }
+    }
+
+    /// Opens (or creates) a store backed by the JSON file at `path`.
+    ///
+    /// # Errors
+    /// - [`EngineError::ProfileInUse`] when another instance already uses the file.
+    /// - [`EngineError::StorageError`] when the file exists but cannot be read or parsed.
+    pub fn open(path: PathBuf) -> Result<Self, EngineError> {
+        let lock = ProfileLock::acquire(&path)?;
+
+        let sites = if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| EngineError::StorageError(e.to_string()))?;
+            serde_json::from_str::<SiteSettingsFile>(&contents)
+                .map_err(|e| EngineError::StorageError(e.to_string()))?
+                .sites
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            sites: RwLock::new(sites),
+            path: Some(path),
+            _lock: Some(lock),
+        })
+    }
+
+    /// Returns the settings of the origin of `url`, if any were stored.
+    pub fn get(&self, url: &Url) -> Option<SiteSettings> {
+        let origin = origin_key(url)?;
+        self.sites.read().unwrap().get(&origin).cloned()
+    }
+
+    /// Replaces the settings of the origin of `url`. Storing empty settings removes the entry.
+    /// Returns false for URLs without a proper origin, such as `data:` URLs.
+    pub fn set(&self, url: &Url, settings: SiteSettings) -> bool {
+        let Some(origin) = origin_key(url) else {
+            return false;
+        };
+        {
+            let mut sites = self.sites.write().unwrap();
+            if settings.is_empty() {
+                sites.remove(&origin);
+            } else {
+                sites.insert(origin, settings);
+            }
+        }
+        self.persist();
+        true
+    }
+
+    /// Changes the settings of the origin of `url` in place, starting from the stored settings
+    /// (or the defaults). Returns false for URLs without a proper origin.
+    pub fn update(&self, url: &Url, f: impl FnOnce(&mut SiteSettings)) -> bool {
+        let mut settings = self.get(url).unwrap_or_default();
+        f(&mut settings);
+        self.set(url, settings)
+    }
+
+    /// Removes the settings of the origin of `url`. Returns false when there were none.
+    pub fn remove(&self, url: &Url) -> bool {
+        let Some(origin) = origin_key(url) else {
+            return false;
+        };
+        let removed = self.sites.write().unwrap().remove(&origin).is_some();
+        if removed {
+            self.persist();
+        }
+        removed
+    }
+
+    /// Returns all stored settings with their origin, such as `https://example.com`, sorted by
+    /// origin.
+    pub fn list(&self) -> Vec<(String, SiteSettings)> {
+        let sites = self.sites.read().unwrap();
+        sites.iter().map(|(o, s)| (o.clone(), s.clone())).collect()
+    }
+
+    /// Removes the settings of all sites.
+    pub fn clear(&self) {
+        self.sites.write().unwrap().clear();
+        self.persist();
+    }
+
+    /// Writes the settings to the backing file, if any. Failures are logged.
+    fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let file = SiteSettingsFile {
+            sites: self.sites.read().unwrap().clone(),
+        };
+        let result = serde_json::to_vec_pretty(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            engine_log!(
+                Error,
+                "Failed to write site settings to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+impl Default for SiteSettingsStore {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+fn origin_key(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn settings_are_kept_per_origin() {
+        let store = SiteSettingsStore::in_memory();
+        assert!(store.update(&u("https://news.test/a"), |s| s.javascript = Some(false)));
+        assert!(store.update(&u("https://news.test/b"), |s| s.zoom = Some(2.0)));
+        assert!(!store.update(&u("data:text/plain,hi"), |s| s.images = Some(false)));
+
+        let settings = store.get(&u("https://news.test/")).unwrap();
+        assert_eq!(
+            (settings.javascript, settings.zoom),
+            (Some(false), Some(2.0))
+        );
+        assert!(store.get(&u("http://news.test/")).is_none());
+        assert_eq!(store.list().len(), 1);
+
+        // Resetting everything drops the entry
+        store.set(&u("https://news.test/"), SiteSettings::default());
+        assert!(store.list().is_empty());
+        assert!(!store.remove(&u("https://news.test/")));
+    }
+
+    #[test]
+    fn settings_survive_reopening_the_file() {
+        let dir = std::env::temp_dir().join(format!("gosub-site-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("site_settings.json");
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let store = SiteSettingsStore::open(path.clone()).unwrap();
+            store.update(&u("https://maps.test/"), |s| {
+                s.permissions.insert(Permission::Geolocation, true);
+            });
+        }
+
+        let store = SiteSettingsStore::open(path).unwrap();
+        let settings = store.get(&u("https://maps.test/x")).unwrap();
+        assert_eq!(settings.permission(Permission::Geolocation), Some(true));
+        assert_eq!(settings.permission(Permission::Camera), None);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,341 @@
+//! Turning typed input into URLs.
+//!
+//! What a user types into an address bar is rarely a valid URL: `example.com` has no scheme,
+//! `localhost:8080` parses as a URL with the scheme `localhost`, and `rust lifetimes` is no
+//! URL at all. A [`UrlResolver`] applies the usual fixups once, so the layers below only deal
+//! with [`Url`]s. Each zone has its own resolver; see
+//! [`Zone::set_url_resolver`](crate::zone::Zone::set_url_resolver).
+//!
+//! Input that is not a URL can be searched for with a [`SearchProvider`]. To show the user
+//! what the input will do before loading anything, classify it with
+//! [`UrlResolver::resolve_input`] or [`Zone::resolve_input`](crate::zone::Zone::resolve_input).
+//!
+//! ```
+//! use gosub_engine::zone::{ResolvedNavigation, SearchProvider, UrlResolver};
+//!
+//! let resolver = UrlResolver::new();
+//! assert_eq!(resolver.resolve("example.com").unwrap().as_str(), "https://example.com/");
+//! assert_eq!(resolver.resolve("localhost:8080").unwrap().as_str(), "http://localhost:8080/");
+//! assert!(resolver.resolve("rust lifetimes").is_err());
+//!
+//! let search = SearchProvider::new("Example", "https://search.example/?q=%s").unwrap();
+//! let resolver = resolver.with_search_provider(search);
+//! assert!(matches!(
+//!     resolver.resolve_input("rust lifetimes").unwrap(),
+//!     ResolvedNavigation::Search { url, .. } if url.as_str() == "https://search.example/?q=rust+lifetimes"
+//! ));
+//! ```
+use crate::EngineError;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use url::Url;
+
+/// Schemes that are written without `//`, and so are recognized without one.
+const OPAQUE_SCHEMES: &[&str] = &[
+    "about",
+    "blob",
+    "data",
+    "javascript",
+    "mailto",
+    "view-source",
+];
+
+/// Returns the URL to load for input that does not look like a URL, if any.
+pub type UrlFallback = Arc<dyn Fn(&str) -> Option<Url> + Send + Sync>;
+
+/// Placeholder for the query in a [`SearchProvider`] template
+const QUERY_PLACEHOLDER: &str = "%s";
+
+/// A search engine that address-bar input which is not a URL is sent to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchProvider {
+    /// Name to show to the user, such as "DuckDuckGo"
+    name: String,
+    /// URL of a search, with `%s` where the query goes
+    template: String,
+}
+
+impl SearchProvider {
+    /// Creates a provider that searches by loading `template` with `%s` replaced by the
+    /// query, for instance `https://duckduckgo.com/?q=%s`.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidConfiguration`] when the template has no `%s`, or is
+    /// not a valid URL once the placeholder is filled in.
+    pub fn new(name: impl Into<String>, template: impl Into<String>) -> Result<Self, EngineError> {
+        let provider = Self {
+            name: name.into(),
+            template: template.into(),
+        };
+        if !provider.template.contains(QUERY_PLACEHOLDER) {
+            return Err(EngineError::InvalidConfiguration(format!(
+                "search template {} has no {QUERY_PLACEHOLDER}",
+                provider.template
+            )));
+        }
+        if provider.url_for("test").is_none() {
+            return Err(EngineError::InvalidConfiguration(format!(
+                "search template {} is not a valid URL",
+                provider.template
+            )));
+        }
+        Ok(provider)
+    }
+
+    /// Returns the name of the provider.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the template URL of the provider.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Returns the URL that searches for `query`.
+    pub fn url_for(&self, query: &str) -> Option<Url> {
+        let query: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        Url::parse(&self.template.replace(QUERY_PLACEHOLDER, &query)).ok()
+    }
+}
+
+/// What typed input resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedNavigation {
+    /// The input is (or was fixed up into) a URL
+    Url(Url),
+    /// The input is a query for the zone's search provider
+    Search {
+        /// Input as it was typed, without surrounding whitespace
+        query: String,
+        /// URL of the search
+        url: Url,
+    },
+}
+
+impl ResolvedNavigation {
+    /// Returns the URL to load.
+    pub fn url(&self) -> &Url {
+        match self {
+            ResolvedNavigation::Url(url) | ResolvedNavigation::Search { url, .. } => url,
+        }
+    }
+
+    /// Returns the URL to load, consuming the navigation.
+    pub fn into_url(self) -> Url {
+        match self {
+            ResolvedNavigation::Url(url) | ResolvedNavigation::Search { url, .. } => url,
+        }
+    }
+}
+
+/// Resolves typed input into a URL.
+///
+/// Input is taken as-is when it has a scheme followed by `//`, or a scheme that is written
+/// without `//`, such as `about:` or `data:`. Input that looks like a host, optionally with a
+/// port and path, gets the default scheme (`https` unless changed); loopback hosts get
+/// `http`, as local servers rarely have certificates. Anything else goes to the fallback,
+/// and then to the search provider.
+#[derive(Clone)]
+pub struct UrlResolver {
+    /// Scheme added to input without one
+    default_scheme: String,
+    /// What to load for input that is not a URL
+    fallback: Option<UrlFallback>,
+    /// Where to search for input that is not a URL
+    search_provider: Option<SearchProvider>,
+}
+
+impl Default for UrlResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for UrlResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UrlResolver")
+            .field("default_scheme", &self.default_scheme)
+            .field("fallback", &self.fallback.is_some())
+            .field("search_provider", &self.search_provider)
+            .finish()
+    }
+}
+
+impl UrlResolver {
+    /// Creates a resolver that adds `https://` and has no fallback or search provider.
+    pub fn new() -> Self {
+        Self {
+            default_scheme: "https".to_string(),
+            fallback: None,
+            search_provider: None,
+        }
+    }
+
+    /// Sets the scheme added to input without one.
+    pub fn with_default_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.default_scheme = scheme.into();
+        self
+    }
+
+    /// Sets what to load for input that is not a URL.
+    pub fn with_fallback(
+        mut self,
+        fallback: impl Fn(&str) -> Option<Url> + Send + Sync + 'static,
+    ) -> Self {
+        self.fallback = Some(Arc::new(fallback));
+        self
+    }
+
+    /// Sets where to search for input that is not a URL, or stops searching with `None`.
+    pub fn with_search_provider(mut self, provider: impl Into<Option<SearchProvider>>) -> Self {
+        self.search_provider = provider.into();
+        self
+    }
+
+    /// Returns where input that is not a URL is searched for.
+    pub fn search_provider(&self) -> Option<&SearchProvider> {
+        self.search_provider.as_ref()
+    }
+
+    /// Resolves `input` into the URL to load.
+    ///
+    /// # Errors
+    /// See [`resolve_input`](Self::resolve_input).
+    pub fn resolve(&self, input: &str) -> Result<Url, EngineError> {
+        self.resolve_input(input).map(ResolvedNavigation::into_url)
+    }
+
+    /// Resolves `input` and tells whether it is a URL or a search.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidUrl`] when the input is empty, or is not a URL and there
+    /// is neither a fallback that accepts it nor a search provider.
+    pub fn resolve_input(&self, input: &str) -> Result<ResolvedNavigation, EngineError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(EngineError::InvalidUrl("empty input".to_string()));
+        }
+
+        if let Some(url) = self.as_url(input) {
+            return Ok(ResolvedNavigation::Url(url));
+        }
+        if let Some(url) = self.fallback.as_ref().and_then(|fallback| fallback(input)) {
+            return Ok(ResolvedNavigation::Url(url));
+        }
+        if let Some(url) = self.search_provider.as_ref().and_then(|p| p.url_for(input)) {
+            return Ok(ResolvedNavigation::Search {
+                query: input.to_string(),
+                url,
+            });
+        }
+        Err(EngineError::InvalidUrl(input.to_string()))
+    }
+
+    /// Returns `input` as a URL when it is one, possibly after adding a scheme.
+    fn as_url(&self, input: &str) -> Option<Url> {
+        if input.chars().any(char::is_whitespace) {
+            return None;
+        }
+
+        if let Some((scheme, _)) = input.split_once(':') {
+            let scheme = scheme.to_ascii_lowercase();
+            let explicit = input[scheme.len() + 1..].starts_with("//")
+                || OPAQUE_SCHEMES.contains(&scheme.as_str());
+            if explicit {
+                return Url::parse(input).ok();
+            }
+        }
+
+        let scheme = if is_loopback(input) {
+            "http"
+        } else {
+            &self.default_scheme
+        };
+        let url = Url::parse(&format!("{scheme}://{input}")).ok()?;
+        let host = url.host_str()?;
+        let host_like = host.contains('.')
+            || host.eq_ignore_ascii_case("localhost")
+            || url
+                .host()
+                .is_some_and(|h| !matches!(h, url::Host::Domain(_)));
+        host_like.then_some(url)
+    }
+}
+
+/// Returns true when the host of scheme-less `input` is `localhost` or a loopback address.
+fn is_loopback(input: &str) -> bool {
+    let authority = input.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = if let Some(rest) = authority.strip_prefix('[') {
+        rest.split(']').next().unwrap_or_default()
+    } else {
+        authority
+            .rsplit_once(':')
+            .map_or(authority, |(host, _)| host)
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_input_is_fixed_up_once() {
+        let resolver = UrlResolver::new();
+        let resolve = |input: &str| resolver.resolve(input).map(|url| url.to_string());
+
+        assert_eq!(
+            resolve("  example.com/a?b ").unwrap(),
+            "https://example.com/a?b"
+        );
+        assert_eq!(
+            resolve("http://example.com").unwrap(),
+            "http://example.com/"
+        );
+        assert_eq!(resolve("myapp://settings").unwrap(), "myapp://settings");
+        assert_eq!(resolve("about:blank").unwrap(), "about:blank");
+        assert_eq!(resolve("127.0.0.1:3000").unwrap(), "http://127.0.0.1:3000/");
+        assert_eq!(resolve("[::1]/x").unwrap(), "http://[::1]/x");
+        assert_eq!(resolve("10.0.0.1").unwrap(), "https://10.0.0.1/");
+        assert!(resolve("intranet").is_err());
+        assert!(resolve("").is_err());
+
+        let searching = UrlResolver::new().with_fallback(|input| {
+            let mut url = Url::parse("https://search.test/").unwrap();
+            url.query_pairs_mut().append_pair("q", input);
+            Some(url)
+        });
+        assert_eq!(
+            searching.resolve("rust lifetimes").unwrap().as_str(),
+            "https://search.test/?q=rust+lifetimes"
+        );
+        assert_eq!(
+            searching.resolve("example.com").unwrap().as_str(),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn searches_only_take_what_is_not_a_url() {
+        assert!(SearchProvider::new("None", "https://search.test/").is_err());
+        assert!(SearchProvider::new("Relative", "search?q=%s").is_err());
+
+        let provider = SearchProvider::new("Test", "https://search.test/?q=%s").unwrap();
+        let resolver = UrlResolver::new().with_search_provider(provider);
+        assert_eq!(
+            resolver.resolve_input(" c++ & rust ").unwrap(),
+            ResolvedNavigation::Search {
+                query: "c++ & rust".to_string(),
+                url: Url::parse("https://search.test/?q=c%2B%2B+%26+rust").unwrap(),
+            }
+        );
+        assert_eq!(
+            resolver.resolve_input("example.com").unwrap(),
+            ResolvedNavigation::Url(Url::parse("https://example.com/").unwrap())
+        );
+        assert!(resolver.resolve_input("   ").is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! User stylesheets and user scripts.
+//!
+//! A zone can carry CSS and JavaScript that the user (or the user agent on their behalf)
+//! wants applied to every document loaded in the zone, such as a dark-mode override or a
+//! small site fix-up. Each entry has include and exclude [`UrlPattern`]s that decide which
+//! documents it applies to.
+//!
+//! Register entries up front through the `user_style` and `user_script` setters of
+//! [`ZoneConfig::builder`](crate::zone::ZoneConfig::builder), or at runtime with [`Zone::add_user_style`](crate::zone::Zone::add_user_style) and
+//! [`Zone::add_user_script`](crate::zone::Zone::add_user_script). Runtime changes apply to
+//! documents loaded afterward.
+//!
+//! ```
+//! use gosub_engine::zone::{UrlPattern, UserStyle, ZoneConfig};
+//!
+//! let dark = UserStyle::new("html, body { background: #111; color: #ddd }")
+//!     .exclude(UrlPattern::new("https://*.example.org/*"));
+//! let config = ZoneConfig::builder().user_style(dark).build().unwrap();
+//! assert_eq!(config.user_styles.len(), 1);
+//! ```
+//!
+//! The engine has no style or script engine yet. Until it does, user styles are only used for
+//! the page background and text color (`background`, `background-color` and `color` on `html`,
+//! `body`, `:root` or `*`), and user scripts are handed to the browsing context to be run once a
+//! script runtime exists.
+use crate::engine::zone::{Extension, ExtensionId};
+use crate::render::Color;
+use url::Url;
+
+/// A URL match pattern with `*` wildcards, such as `https://*.example.com/*`.
+///
+/// The pattern is matched against the full serialized URL. `*` matches any run of characters
+/// (including none); every other character matches itself. The special pattern `<all_urls>`
+/// matches everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlPattern(String);
+
+impl UrlPattern {
+    /// Creates a pattern from its textual form.
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into())
+    }
+
+    /// A pattern that matches every URL.
+    pub fn all_urls() -> Self {
+        Self("<all_urls>".to_string())
+    }
+
+    /// Returns the textual form of the pattern.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true when `url` matches the pattern.
+    pub fn matches(&self, url: &Url) -> bool {
+        self.0 == "<all_urls>" || wildcard_match(self.0.as_bytes(), url.as_str().as_bytes())
+    }
+}
+
+impl From<&str> for UrlPattern {
+    fn from(pattern: &str) -> Self {
+        UrlPattern::new(pattern)
+    }
+}
+
+/// Include/exclude patterns shared by user styles and scripts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UrlFilter {
+    /// Documents must match at least one of these. Empty means all documents.
+    pub include: Vec<UrlPattern>,
+    /// Documents matching any of these are skipped, even when included.
+    pub exclude: Vec<UrlPattern>,
+}
+
+impl UrlFilter {
+    /// Returns true when the filter selects `url`.
+    pub fn matches(&self, url: &Url) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.matches(url));
+        included && !self.exclude.iter().any(|p| p.matches(url))
+    }
+}
+
+/// A user stylesheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserStyle {
+    /// CSS source
+    pub css: String,
+    /// Documents the stylesheet applies to
+    pub filter: UrlFilter,
+}
+
+impl UserStyle {
+    /// Creates a stylesheet that applies to every document.
+    pub fn new(css: impl Into<String>) -> Self {
+        Self {
+            css: css.into(),
+            filter: UrlFilter::default(),
+        }
+    }
+
+    /// Restricts the stylesheet to documents matching `pattern` (in addition to earlier includes).
+    pub fn include(mut self, pattern: impl Into<UrlPattern>) -> Self {
+        self.filter.include.push(pattern.into());
+        self
+    }
+
+    /// Skips documents matching `pattern`.
+    pub fn exclude(mut self, pattern: impl Into<UrlPattern>) -> Self {
+        self.filter.exclude.push(pattern.into());
+        self
+    }
+}
+
+/// When a user script runs relative to the document load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScriptRunAt {
+    /// Before any of the page's own scripts
+    DocumentStart,
+    /// After the document has been parsed
+    #[default]
+    DocumentEnd,
+}
+
+/// A user script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserScript {
+    /// JavaScript source
+    pub source: String,
+    /// Documents the script runs in
+    pub filter: UrlFilter,
+    /// When the script runs
+    pub run_at: ScriptRunAt,
+    /// Extension that injected the script, if any. Such scripts can message the user agent.
+    pub extension: Option<ExtensionId>,
+}
+
+impl UserScript {
+    /// Creates a script that runs at document end in every document.
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            filter: UrlFilter::default(),
+            run_at: ScriptRunAt::default(),
+            extension: None,
+        }
+    }
+
+    /// Restricts the script to documents matching `pattern` (in addition to earlier includes).
+    pub fn include(mut self, pattern: impl Into<UrlPattern>) -> Self {
+        self.filter.include.push(pattern.into());
+        self
+    }
+
+    /// Skips documents matching `pattern`.
+    pub fn exclude(mut self, pattern: impl Into<UrlPattern>) -> Self {
+        self.filter.exclude.push(pattern.into());
+        self
+    }
+
+    /// Sets when the script runs.
+    pub fn run_at(mut self, run_at: ScriptRunAt) -> Self {
+        self.run_at = run_at;
+        self
+    }
+}
+
+/// The user styles and scripts of a zone.
+#[derive(Debug, Clone, Default)]
+pub struct UserContent {
+    /// Registered stylesheets, in order of registration
+    pub styles: Vec<UserStyle>,
+    /// Registered scripts, in order of registration
+    pub scripts: Vec<UserScript>,
+    /// Loaded extensions, whose content scripts apply after the user's own
+    pub extensions: Vec<Extension>,
+}
+
+impl UserContent {
+    /// Returns the stylesheets and scripts (including those of extensions) that apply to a
+    /// document at `url`.
+    pub fn matching(&self, url: &Url) -> (Vec<UserStyle>, Vec<UserScript>) {
+        let styles = self
+            .styles
+            .iter()
+            .chain(self.extensions.iter().flat_map(|e| e.styles()))
+            .filter(|s| s.filter.matches(url))
+            .cloned()
+            .collect();
+        let scripts = self
+            .scripts
+            .iter()
+            .chain(self.extensions.iter().flat_map(|e| e.scripts()))
+            .filter(|s| s.filter.matches(url))
+            .cloned()
+            .collect();
+        (styles, scripts)
+    }
+}
+
+/// Page-level colors picked from user stylesheets.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PageColors {
+    pub(crate) background: Option<Color>,
+    pub(crate) text: Option<Color>,
+}
+
+impl PageColors {
+    /// Collects the root background and text color from `styles`. Later declarations win.
+    pub(crate) fn from_styles(styles: &[UserStyle]) -> PageColors {
+        let mut colors = PageColors::default();
+
+        for style in styles {
+            let css = strip_comments(&style.css);
+            for rule in css.split('}') {
+                let Some((selectors, body)) = rule.split_once('{') else {
+                    continue;
+                };
+                let targets_root = selectors
+                    .split(',')
+                    .any(|s| matches!(s.trim(), "html" | "body" | ":root" | "*"));
+                if !targets_root {
+                    continue;
+                }
+
+                for decl in body.split(';') {
+                    let Some((prop, value)) = decl.split_once(':') else {
+                        continue;
+                    };
+                    let value = value.trim().trim_end_matches("!important").trim();
+                    match prop.trim().to_ascii_lowercase().as_str() {
+                        "background" | "background-color" => {
+                            colors.background = parse_color(value).or(colors.background)
+                        }
+                        "color" => colors.text = parse_color(value).or(colors.text),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        colors
+    }
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses `#rgb`, `#rrggbb`, `#rrggbbaa`, `rgb()`/`rgba()` and a handful of named colors.
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().to_ascii_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u8> = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()?;
+        return match digits.as_slice() {
+            [r, g, b] => Some(Color::from_u8(r * 17, g * 17, b * 17, 255)),
+            [r1, r2, g1, g2, b1, b2] => Some(Color::from_u8(
+                r1 * 16 + r2,
+                g1 * 16 + g2,
+                b1 * 16 + b2,
+                255,
+            )),
+            [r1, r2, g1, g2, b1, b2, a1, a2] => Some(Color::from_u8(
+                r1 * 16 + r2,
+                g1 * 16 + g2,
+                b1 * 16 + b2,
+                a1 * 16 + a2,
+            )),
+            _ => None,
+        };
+    }
+
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|v| v.strip_suffix(')'))
+    {
+        let parts: Vec<f32> = args
+            .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.parse().ok())
+            .collect::<Option<_>>()?;
+        return match parts.as_slice() {
+            [r, g, b] => Some(Color::new(r / 255.0, g / 255.0, b / 255.0, 1.0)),
+            [r, g, b, a] => Some(Color::new(r / 255.0, g / 255.0, b / 255.0, *a)),
+            _ => None,
+        };
+    }
+
+    match value.as_str() {
+        "black" => Some(Color::from_u8(0, 0, 0, 255)),
+        "white" => Some(Color::from_u8(255, 255, 255, 255)),
+        "gray" | "grey" => Some(Color::from_u8(128, 128, 128, 255)),
+        "transparent" => Some(Color::from_u8(0, 0, 0, 0)),
+        _ => None,
+    }
+}
+
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    // Classic greedy matching with backtracking to the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn patterns_and_filters() {
+        let p = UrlPattern::new("https://*.example.com/*");
+        assert!(p.matches(&u("https://www.example.com/")));
+        assert!(p.matches(&u("https://a.b.example.com/x?y")));
+        assert!(!p.matches(&u("https://example.org/")));
+        assert!(UrlPattern::all_urls().matches(&u("about:blank")));
+
+        let style = UserStyle::new("")
+            .include("https://*")
+            .exclude("*://bank.test/*");
+        assert!(style.filter.matches(&u("https://news.test/")));
+        assert!(!style.filter.matches(&u("https://bank.test/login")));
+        assert!(!style.filter.matches(&u("http://news.test/")));
+    }
+
+    #[test]
+    fn page_colors_from_root_rules() {
+        let styles = [
+            UserStyle::new("/* dark */ html, body { background: #111; color: rgb(221, 221, 221) }"),
+            UserStyle::new("p { color: red } :root { color: #ffffff !important; }"),
+        ];
+        let colors = PageColors::from_styles(&styles);
+
+        let bg: [u8; 4] = colors.background.unwrap().into();
+        assert_eq!(bg, [0x11, 0x11, 0x11, 255]);
+        let text: [u8; 4] = colors.text.unwrap().into();
+        assert_eq!(text, [255, 255, 255, 255]);
+    }
+}
@@ -1,26 +1,50 @@
-use crate::engine::cookies::CookieJarHandle;
-use crate::engine::cookies::DefaultCookieJar;
+use crate::config::SandboxMode;
+use crate::engine::cookies::notifying_cookie_jar::{CookieBus, NotifyingCookieJar};
+use crate::engine::cookies::{
+    CookieJarHandle, CookiePolicyHandle, CookieStoreHandle, CookieSubscription, DefaultCookieJar,
+};
+use crate::engine::logging::engine_log;
+use crate::engine::scheduler::{Schedule, Scheduler};
+use crate::engine::spellcheck::SpellcheckHandle;
 use crate::engine::storage::event::StorageScope;
-use crate::engine::storage::types::compute_partition_key;
+use crate::engine::storage::types::{
+    compute_partition_key_in, FirstPartySets, PartitionExplanation, PartitionPolicy,
+};
 use crate::engine::storage::{
     PartitionKey, StorageArea, StorageEvent, StorageHandles, StorageService, Subscription,
 };
-use crate::engine::tab::{Tab, TabId, TabMode};
-use crate::engine::tick::TickResult;
+use crate::engine::tab::{Tab, TabId, TabState};
+use crate::engine::tick::{frame_budget, TickResult, DEFAULT_FRAME_BUDGET};
+use crate::engine::watchdog::Watchdog;
+use crate::engine::zone::archive::{partition_to_archive, ARCHIVE_VERSION};
+use crate::engine::zone::groups::TabGroups;
 use crate::engine::zone::password_store::PasswordStore;
+use crate::engine::{EngineNotification, NotificationBus, NotificationSubscription};
+use crate::net::{
+    identity_headers, ConnectionPool, ContentBlocker, FilterList, HttpCache, NetworkConditions,
+    PoolStats, RequestTimeouts,
+};
 use crate::render::backend::CompositorSink;
 use crate::render::backend::RenderBackend;
-use crate::render::Viewport;
-use crate::zone::ZoneConfig;
-use crate::EngineError;
+use crate::render::{FontSettings, RenderList, TextOptions, Viewport};
+use crate::zone::{
+    Extension, ExtensionId, Favicon, FaviconStore, LocalStorageArchive, ResolvedNavigation,
+    SearchProvider, SiteSettingsStore, TabGroup, TabGroupId, UrlResolver, UserContent, UserScript,
+    UserStyle, ZoneArchive, ZoneConfig,
+};
+use crate::{EngineConfig, EngineError, ZoneCommand};
+use http::HeaderMap;
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Display;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::runtime::Runtime;
+use url::Url;
 use uuid::Uuid;
 
 /// A unique identifier for a [`Zone`] within a [`GosubEngine`](crate::GosubEngine).
@@ -101,6 +125,20 @@ impl Display for ZoneId {
     }
 }
 
+/// Name of the zone job that purges expired cookies from the zone's jar.
+pub const COOKIE_EXPIRY_JOB: &str = "cookie-expiry";
+/// Name of the zone job that drops stale responses from the zone's HTTP cache.
+pub const CACHE_EVICTION_JOB: &str = "cache-eviction";
+/// Name of the zone job that writes the zone's cookies to the cookie store the zone was
+/// created from, if any.
+pub const PERSISTENCE_FLUSH_JOB: &str = "persistence-flush";
+/// Name of the zone job that publishes [`EngineNotification::ZoneMetrics`]. Only scheduled when
+/// [`EngineConfig::metrics_enabled`] is set.
+pub const METRICS_JOB: &str = "metrics";
+
+/// Cache size of zones until the engine sets the configured `memory_cache_bytes`
+const DEFAULT_CACHE_BYTES: usize = 16 * 1024 * 1024;
+
 /// A `Zone` is a self-contained browsing context within a [`GosubEngine`](crate::engine::GosubEngine).
 ///
 /// All tabs opened in the same zone share the zone's **session storage**,
@@ -165,7 +203,8 @@ impl Display for ZoneId {
 /// - `tabs`: The set of [`Tab`]s currently open in the zone.
 /// - `storage`: The [`StorageService`] used for local/session storage.
 /// - `storage_rx`: Subscription for observing session storage changes.
-/// - `cookie_jar`: Where cookies are stored/loaded for this zone.
+/// - `cookie_jar`: Where cookies are stored/loaded for this zone. Changes made through
+///   it are published as [`CookieEvent`](crate::cookies::CookieEvent)s.
 /// - `password_store`: Per-zone password storage.
 /// - `shared_flags`: Flags that define which data is shared with other zones.
 ///
@@ -196,14 +235,111 @@ pub struct Zone {
 
     /// Where to load/store cookies within this zone
     pub cookie_jar: CookieJarHandle,
+    /// The jar wrapped by `cookie_jar`, used to give each tab its own notifying view
+    cookie_jar_inner: CookieJarHandle,
+    /// Bus on which cookie changes are published
+    cookie_bus: Arc<CookieBus>,
+    /// Store the cookie jar was taken from, flushed by [`PERSISTENCE_FLUSH_JOB`]
+    cookie_store: Option<CookieStoreHandle>,
 
     /// Per-zone password storage
     pub password_store: PasswordStore,
 
     /// Flags controlling which data is shared with other zones.
     pub shared_flags: SharedFlags,
+    /// Zones that may read the cookie jar while `shared_flags.share_cookiejar` is set
+    cookie_readers: HashSet<ZoneId>,
+
+    /// Sandbox mode applied to tabs opened in this zone
+    sandbox_mode: SandboxMode,
+    /// Bus on which the zone's tabs publish engine notifications
+    notifications: Arc<NotificationBus>,
+    /// Spellchecker for editable fields in the zone's tabs
+    spellcheck: Option<SpellcheckHandle>,
+    /// Filter lists applied to requests of the zone's tabs
+    content_blocker: Option<Arc<ContentBlocker>>,
+    /// `blocked_domains` of the engine config, added to the content blocker
+    blocked_domains: Vec<String>,
+    /// `allowlist_domains` of the engine config, added to the content blocker
+    allowlist_domains: Vec<String>,
+    /// Decides which cookies the zone's tabs store
+    cookie_policy: Option<CookiePolicyHandle>,
+    /// User styles and scripts, shared with the zone's tabs
+    user_content: Arc<RwLock<UserContent>>,
+    /// Per-origin exceptions to the zone config, shared with the zone's tabs
+    site_settings: Arc<SiteSettingsStore>,
+    /// Icons of the sites visited in the zone, filled by its tabs
+    favicons: Arc<FaviconStore>,
+    /// Housekeeping jobs, run while ticking
+    scheduler: Scheduler,
+    /// Responses shared by the zone's tabs
+    http_cache: Arc<HttpCache>,
+    /// Connections shared by the zone's tabs
+    connection_pool: Arc<ConnectionPool>,
+    /// Time that rendering a frame of a tab may take
+    frame_budget: Duration,
+    /// How the zone's tabs rasterize text
+    text_options: TextOptions,
+    /// Time limits for the requests of the zone's tabs
+    request_timeouts: RequestTimeouts,
+    /// Most requests in flight per tab when the loading policy sets no limit
+    io_concurrency: usize,
+    /// Bytes of localStorage the zone may use before origins are evicted
+    storage_quota: u64,
+    /// Network conditions the requests of the zone's tabs are slowed down to
+    network_conditions: NetworkConditions,
+    /// Groups the zone's tabs are sorted into
+    tab_groups: TabGroups,
+    /// How tabs opened in the zone partition their storage
+    partition_policy: PartitionPolicy,
+    /// Related sites that share a partition, from the engine configuration
+    first_party_sets: Arc<FirstPartySets>,
+    /// User agent of the engine, sent unless the zone config has its own
+    engine_user_agent: String,
+    /// Whether the engine discloses that it is automated
+    automated: bool,
+    /// Turns typed input into URLs for the zone's tabs
+    url_resolver: Arc<UrlResolver>,
+    /// Watches the zone's tabs for ticks that take too long, if the engine runs one
+    watchdog: Option<Arc<Watchdog>>,
+    /// Kill flags of the zone's tabs, reachable while another thread holds the tab
+    cancel_flags: HashMap<TabId, Arc<AtomicBool>>,
+    /// Whether the host is online, shared with the engine and the zone's tabs
+    online: Arc<AtomicBool>,
+}
+
+/// How a zone presents itself in the user agent, see [`Zone::metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct ZoneMetadata {
+    /// Title of the zone (ie: Home, Work)
+    pub title: String,
+    /// Icon of the zone (could be a base64 encoded image)
+    pub icon: Vec<u8>,
+    /// Description of the zone
+    pub description: String,
+    /// Tab color (RGBA)
+    pub color: [u8; 4],
 }
 
+/// Counters of a zone, published every so often with [`EngineNotification::ZoneMetrics`] when
+/// [`EngineConfig::metrics_enabled`] is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct ZoneMetrics {
+    /// Open tabs
+    pub tabs: usize,
+    /// Responses in the zone's HTTP cache
+    pub cache_entries: usize,
+    /// Size of the cached responses in bytes
+    pub cache_bytes: usize,
+    /// Requests sent by the zone's tabs
+    pub requests: u64,
+    /// Requests that likely went over an open connection
+    pub reused_connections: u64,
+}
+
+/// Which data of a zone other zones may read. Everything is private by default.
 pub struct SharedFlags {
     /// Other zones are allowed to read this autocomplete elements
     pub share_autocomplete: bool,
@@ -211,7 +347,8 @@ pub struct SharedFlags {
     pub share_bookmarks: bool,
     /// Other zones are allowed to read password entries
     pub share_passwords: bool,
-    /// Other zones are allowed to read cookies
+    /// Zones designated with [`Zone::allow_cookie_reader`] are allowed to read cookies, through
+    /// the user agent only
     pub share_cookiejar: bool,
 }
 
@@ -234,8 +371,30 @@ impl Zone {
 
         let storage_rx = storage.subscribe();
 
-        let cookie_jar =
+        let cookie_jar_inner =
             cookie_jar.unwrap_or_else(|| Arc::new(RwLock::new(DefaultCookieJar::new())));
+        let cookie_bus = Arc::new(CookieBus::default());
+        let cookie_jar: CookieJarHandle = Arc::new(RwLock::new(NotifyingCookieJar::new(
+            zone_id,
+            cookie_jar_inner.clone(),
+            cookie_bus.clone(),
+        )));
+
+        let mut scheduler = Scheduler::new();
+        let cookie_expiry =
+            Schedule::every(Duration::from_secs(1)).with_jitter(Duration::from_millis(250));
+        scheduler.add(COOKIE_EXPIRY_JOB, cookie_expiry, Instant::now());
+        let jitter = Duration::from_secs(5);
+        let cache_eviction = Schedule::every(Duration::from_secs(60)).with_jitter(jitter);
+        scheduler.add(CACHE_EVICTION_JOB, cache_eviction, Instant::now());
+        let flush = Schedule::every(Duration::from_secs(30)).with_jitter(jitter);
+        scheduler.add(PERSISTENCE_FLUSH_JOB, flush, Instant::now());
+
+        let user_content = UserContent {
+            styles: config.user_styles.clone(),
+            scripts: config.user_scripts.clone(),
+            extensions: Vec::new(),
+        };
 
         Self {
             id: zone_id,
@@ -250,6 +409,9 @@ impl Zone {
             storage_rx,
 
             cookie_jar,
+            cookie_jar_inner,
+            cookie_bus,
+            cookie_store: None,
             password_store: PasswordStore::new(),
             shared_flags: SharedFlags {
                 share_autocomplete: false,
@@ -257,6 +419,36 @@ impl Zone {
                 share_passwords: false,
                 share_cookiejar: false,
             },
+            cookie_readers: HashSet::new(),
+
+            sandbox_mode: SandboxMode::Balanced,
+            notifications: Arc::new(NotificationBus::default()),
+            spellcheck: None,
+            content_blocker: None,
+            blocked_domains: Vec::new(),
+            allowlist_domains: Vec::new(),
+            cookie_policy: None,
+            user_content: Arc::new(RwLock::new(user_content)),
+            site_settings: Arc::new(SiteSettingsStore::in_memory()),
+            favicons: Arc::new(FaviconStore::in_memory()),
+            scheduler,
+            http_cache: Arc::new(HttpCache::new(DEFAULT_CACHE_BYTES)),
+            connection_pool: Arc::new(ConnectionPool::new()),
+            frame_budget: DEFAULT_FRAME_BUDGET,
+            text_options: TextOptions::default(),
+            request_timeouts: RequestTimeouts::default(),
+            io_concurrency: EngineConfig::default().io_concurrency,
+            storage_quota: EngineConfig::default().quota_per_zone_bytes,
+            network_conditions: NetworkConditions::NONE,
+            tab_groups: TabGroups::default(),
+            partition_policy: PartitionPolicy::TopLevelOrigin,
+            first_party_sets: Arc::default(),
+            engine_user_agent: EngineConfig::default().user_agent,
+            automated: false,
+            url_resolver: Arc::new(UrlResolver::new()),
+            watchdog: None,
+            cancel_flags: HashMap::new(),
+            online: Arc::new(AtomicBool::new(true)),
         }
     }
 
@@ -270,32 +462,690 @@ impl Zone {
         Zone::new_with_id(zone_id, config, storage, cookie_jar)
     }
 
+    /// Returns the configuration of the zone
+    pub fn config(&self) -> &ZoneConfig {
+        &self.config
+    }
+
+    /// Changes the configuration of the running zone and publishes
+    /// [`EngineNotification::ZoneConfigChanged`].
+    ///
+    /// The open tabs pick up the change as far as they can:
+    /// - fonts apply right away and the tabs re-render;
+    /// - the user agent, client hints, languages, locale, time zone, Do Not Track, Global
+    ///   Privacy Control and the cache switch apply to their next request;
+    /// - JavaScript and images apply from their next document on.
+    ///
+    /// `max_tabs` only limits tabs opened afterward, and `user_styles` / `user_scripts` are only
+    /// read when the zone is created; use [`Zone::add_user_style`] and friends instead.
+    ///
+    /// ```
+    /// # let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+    /// # let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// let zone_id = engine.zone_builder().create().unwrap();
+    /// let zone = engine.get_zone_mut(zone_id).unwrap();
+    /// let mut zone = zone.lock().unwrap();
+    ///
+    /// zone.update_config(|c| c.javascript_enabled = false).unwrap();
+    /// assert!(!zone.config().javascript_enabled);
+    /// assert!(zone.update_config(|c| c.font_scale = 0.0).is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidConfiguration`] when the changed configuration does not
+    /// validate; the zone is left unchanged.
+    pub fn update_config(&mut self, f: impl FnOnce(&mut ZoneConfig)) -> Result<(), EngineError> {
+        let mut config = self.config.clone();
+        f(&mut config);
+        super::config::validate(&config)
+            .map_err(|e| EngineError::InvalidConfiguration(e.to_string()))?;
+
+        let fonts = FontSettings::from_zone_config(&config);
+        let cache_changed = config.cache_enabled != self.config.cache_enabled;
+        for tab in self.tabs.values() {
+            let Ok(mut tab) = tab.lock() else {
+                continue;
+            };
+            tab.context.set_font_settings(fonts.clone());
+            tab.bind_privacy(config.do_not_track, config.global_privacy_control);
+            tab.bind_locale(&config);
+            tab.bind_identity(self.identity_headers(&config));
+            tab.bind_site_settings(self.site_settings.clone(), &config);
+            tab.bind_retry_policy(config.retry_policy);
+            tab.bind_loading_policy(config.loading_policy.clone(), self.io_concurrency);
+            tab.bind_render_mode(config.render_mode);
+            tab.bind_local_file_access(config.enable_local_file_access);
+            if cache_changed {
+                tab.bind_cache(config.cache_enabled.then(|| self.http_cache.clone()));
+            }
+        }
+        self.config = config;
+
+        self.notifications
+            .publish(EngineNotification::ZoneConfigChanged { zone_id: self.id });
+        Ok(())
+    }
+
+    /// Returns the `User-Agent` header that tabs send under `config`, and the client hints
+    /// derived from it unless `config` turns them off.
+    fn identity_headers(&self, config: &ZoneConfig) -> HeaderMap {
+        let user_agent = config
+            .user_agent
+            .as_deref()
+            .unwrap_or(&self.engine_user_agent);
+        identity_headers(user_agent, config.client_hints, self.automated)
+    }
+
+    /// Turns JavaScript on or off for the zone. See [`Zone::update_config`].
+    pub fn set_javascript_enabled(&mut self, enabled: bool) -> Result<(), EngineError> {
+        self.update_config(|c| c.javascript_enabled = enabled)
+    }
+
+    /// Turns image loading on or off for the zone. See [`Zone::update_config`].
+    pub fn set_images_enabled(&mut self, enabled: bool) -> Result<(), EngineError> {
+        self.update_config(|c| c.images_enabled = enabled)
+    }
+
+    /// Changes the zone's font scale, which must be within `0.25..=10.0`. See
+    /// [`Zone::update_config`].
+    pub fn set_font_scale(&mut self, scale: f32) -> Result<(), EngineError> {
+        self.update_config(|c| c.font_scale = scale)
+    }
+
+    /// Executes a command that affects the whole zone.
+    ///
+    /// # Errors
+    /// See [`Zone::update_config`] and [`Zone::add_tab_to_group`].
+    pub fn execute_command(&mut self, command: ZoneCommand) -> Result<(), EngineError> {
+        match command {
+            ZoneCommand::UpdateConfig(config) => self.update_config(|c| *c = *config),
+            ZoneCommand::AddTabToGroup { tab_id, group_id } => {
+                self.add_tab_to_group(tab_id, group_id)
+            }
+            ZoneCommand::RemoveTabFromGroup(tab_id) => {
+                self.remove_tab_from_group(tab_id);
+                Ok(())
+            }
+            ZoneCommand::SetStoragePersistent { origin, persistent } => {
+                self.set_storage_persistent(&origin, persistent);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the title, icon, description and color of the zone.
+    pub fn metadata(&self) -> ZoneMetadata {
+        ZoneMetadata {
+            title: self.title.clone(),
+            icon: self.icon.clone(),
+            description: self.description.clone(),
+            color: self.color,
+        }
+    }
+
     /// Sets the title of the zone
     pub fn set_title(&mut self, title: &str) {
-        self.title = title.to_string();
+        if self.title != title {
+            self.title = title.to_string();
+            self.publish_metadata();
+        }
     }
 
     /// Sets the icon of the zone
     pub fn set_icon(&mut self, icon: Vec<u8>) {
-        self.icon = icon;
+        if self.icon != icon {
+            self.icon = icon;
+            self.publish_metadata();
+        }
     }
 
     /// Sets the description of the zone
     pub fn set_description(&mut self, description: &str) {
-        self.description = description.to_string();
+        if self.description != description {
+            self.description = description.to_string();
+            self.publish_metadata();
+        }
     }
 
     /// Sets the color of the zone (RGBA)
     pub fn set_color(&mut self, color: [u8; 4]) {
-        self.color = color;
+        if self.color != color {
+            self.color = color;
+            self.publish_metadata();
+        }
+    }
+
+    /// Tells subscribers that the metadata of the zone changed. Only the setters publish;
+    /// assigning the public fields directly does not.
+    fn publish_metadata(&self) {
+        self.notifications
+            .publish(EngineNotification::ZoneMetadataChanged {
+                zone_id: self.id,
+                title: self.title.clone(),
+                color: self.color,
+            });
+    }
+
+    /// Takes a snapshot of the zone's metadata, cookies, localStorage and site settings.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::StorageError`] when the cookie jar cannot be inspected or the
+    /// storage cannot be read.
+    pub fn export_archive(&self) -> Result<ZoneArchive, EngineError> {
+        let jar = self.cookie_jar.read().map_err(|_| EngineError::Internal)?;
+        let cookies = DefaultCookieJar::snapshot_of(&*jar).ok_or_else(|| {
+            EngineError::StorageError("cookie jar of the zone cannot be exported".into())
+        })?;
+        drop(jar);
+
+        let storage_error = |e: anyhow::Error| EngineError::StorageError(e.to_string());
+        let mut local_storage = Vec::new();
+        for (partition, origin) in self.storage.local_areas(self.id).map_err(storage_error)? {
+            let area = self
+                .storage
+                .local_for(self.id, &partition, &origin)
+                .map_err(storage_error)?;
+            let items = area
+                .keys()
+                .into_iter()
+                .filter_map(|key| area.get_item(&key).map(|value| (key, value)))
+                .collect();
+            local_storage.push(LocalStorageArchive {
+                partition: partition_to_archive(&partition),
+                origin: origin.ascii_serialization(),
+                items,
+            });
+        }
+
+        Ok(ZoneArchive {
+            version: ARCHIVE_VERSION,
+            title: self.title.clone(),
+            description: self.description.clone(),
+            icon: self.icon.clone(),
+            color: self.color,
+            cookies: cookies.entries.into_iter().collect(),
+            local_storage,
+            site_settings: self.site_settings.list(),
+        })
     }
 
-    /// Sets the cookie jar for the zone
+    /// Adds the data of `archive` to the zone, and takes over its metadata. Cookies, items
+    /// and site settings that exist in both are replaced by the ones of the archive; other
+    /// data of the zone is kept. Entries with an invalid origin are skipped.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::StorageError`] when the storage cannot be written.
+    pub fn import_archive(&mut self, archive: ZoneArchive) -> Result<(), EngineError> {
+        self.title = archive.title;
+        self.description = archive.description;
+        self.icon = archive.icon;
+        self.color = archive.color;
+        self.publish_metadata();
+
+        let cookies = archive
+            .cookies
+            .into_iter()
+            .filter_map(|(origin, cookies)| Some((Url::parse(&origin).ok()?, cookies)))
+            .flat_map(|(url, cookies)| cookies.into_iter().map(move |c| (url.clone(), c)))
+            .collect();
+        self.cookie_jar
+            .write()
+            .map_err(|_| EngineError::Internal)?
+            .insert_cookies(cookies);
+
+        for area in &archive.local_storage {
+            let Some((partition, origin)) = area.location() else {
+                continue;
+            };
+            let target = self
+                .storage
+                .local_for(self.id, &partition, &origin)
+                .map_err(|e| EngineError::StorageError(e.to_string()))?;
+            let items: Vec<(&str, &str)> = area
+                .items
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect();
+            target
+                .set_many(&items)
+                .map_err(|e| EngineError::StorageError(e.to_string()))?;
+        }
+
+        for (origin, settings) in archive.site_settings {
+            if let Ok(url) = Url::parse(&origin) {
+                self.site_settings.set(&url, settings);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the zone's data to a [`ZoneArchive`] file at `path`. See
+    /// [`export_archive`](Self::export_archive).
+    ///
+    /// # Errors
+    /// Returns [`EngineError::StorageError`] when the data cannot be read or the file cannot
+    /// be written.
+    pub fn export_data(&self, path: impl AsRef<Path>) -> Result<(), EngineError> {
+        self.export_archive()?.write(path.as_ref())
+    }
+
+    /// Reads the [`ZoneArchive`] file at `path` into the zone. See
+    /// [`import_archive`](Self::import_archive).
+    ///
+    /// ```
+    /// use gosub_engine::GosubEngine;
+    ///
+    /// let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+    /// let mut engine = GosubEngine::new(None, Box::new(backend));
+    /// let home = engine.zone_builder().create().unwrap();
+    /// let copy = engine.zone_builder().create().unwrap();
+    ///
+    /// let path = std::env::temp_dir().join(format!("zone-{home}.json"));
+    /// let home = engine.get_zone_mut(home).unwrap();
+    /// home.lock().unwrap().set_title("Home");
+    /// home.lock().unwrap().export_data(&path).unwrap();
+    ///
+    /// let copy = engine.get_zone_mut(copy).unwrap();
+    /// copy.lock().unwrap().import_data(&path).unwrap();
+    /// assert_eq!(copy.lock().unwrap().title, "Home");
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`EngineError::StorageError`] when the file cannot be read or parsed, or the
+    /// data cannot be stored.
+    pub fn import_data(&mut self, path: impl AsRef<Path>) -> Result<(), EngineError> {
+        self.import_archive(ZoneArchive::read(path.as_ref())?)
+    }
+
+    /// Sets the cookie jar for the zone. Tabs opened afterward will use the new jar.
     pub fn set_cookie_jar(&mut self, cookie_jar: CookieJarHandle) {
-        self.cookie_jar = cookie_jar;
+        self.cookie_jar = Arc::new(RwLock::new(NotifyingCookieJar::new(
+            self.id,
+            cookie_jar.clone(),
+            self.cookie_bus.clone(),
+        )));
+        self.cookie_jar_inner = cookie_jar;
+    }
+
+    /// Designates `reader` as a zone that may read this zone's cookies, for instance a work
+    /// zone that needs the single sign-on cookies of another. Reading only works while
+    /// [`SharedFlags::share_cookiejar`] is set, and goes through
+    /// [`GosubEngine::read_shared_cookies`](crate::GosubEngine::read_shared_cookies). Readers
+    /// can never change the cookies.
+    ///
+    /// Sharing is host-mediated only: the tabs of the reader never send these cookies with
+    /// their own requests. The user agent reads them and decides what to do with them.
+    pub fn allow_cookie_reader(&mut self, reader: ZoneId) {
+        self.cookie_readers.insert(reader);
+    }
+
+    /// Takes back what [`allow_cookie_reader`](Self::allow_cookie_reader) granted.
+    pub fn revoke_cookie_reader(&mut self, reader: ZoneId) {
+        self.cookie_readers.remove(&reader);
+    }
+
+    /// Returns whether `reader` may read this zone's cookies right now.
+    pub fn shares_cookies_with(&self, reader: ZoneId) -> bool {
+        self.shared_flags.share_cookiejar && self.cookie_readers.contains(&reader)
+    }
+
+    /// Returns the `Cookie` header this zone would send to `url`, read on behalf of `reader`.
+    /// Every attempt is published as [`EngineNotification::SharedCookiesRead`], also the
+    /// refused ones.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::CookieJarNotShared`] when `reader` may not read the cookies.
+    pub(crate) fn read_cookies_for(
+        &self,
+        reader: ZoneId,
+        url: &Url,
+    ) -> Result<Option<String>, EngineError> {
+        let allowed = self.shares_cookies_with(reader);
+        self.notifications
+            .publish(EngineNotification::SharedCookiesRead {
+                zone_id: self.id,
+                reader,
+                url: url.clone(),
+                allowed,
+            });
+        if !allowed {
+            return Err(EngineError::CookieJarNotShared);
+        }
+        let jar = self.cookie_jar.read().map_err(|_| EngineError::Internal)?;
+        Ok(jar.get_request_cookies(url))
+    }
+
+    /// Connects the zone to the engine: its tabs publish on `notifications`, know whether
+    /// the host is `online`, and follow the engine's sandbox mode, memory cache size, frame
+    /// rate, text settings, request timeouts, first-party sets, user agent, automation
+    /// disclosure and blocked domains. Tabs opened afterward are affected.
+    pub(crate) fn bind_engine(
+        &mut self,
+        notifications: Arc<NotificationBus>,
+        watchdog: Option<Arc<Watchdog>>,
+        online: Arc<AtomicBool>,
+        config: &EngineConfig,
+    ) {
+        self.notifications = notifications;
+        self.watchdog = watchdog;
+        self.online = online;
+        self.sandbox_mode = config.sandbox_mode;
+        self.http_cache = Arc::new(HttpCache::new(config.memory_cache_bytes as usize));
+        self.frame_budget = frame_budget(config.target_fps);
+        self.text_options = TextOptions::from_config(config);
+        self.request_timeouts = RequestTimeouts::from_config(config);
+        self.io_concurrency = config.io_concurrency;
+        self.storage_quota = config.quota_per_zone_bytes;
+        self.first_party_sets = Arc::new(config.first_party_sets.clone());
+        self.engine_user_agent = config.user_agent.clone();
+        self.automated = config.automated;
+        self.set_blocked_domains(config);
+        if config.metrics_enabled {
+            let metrics = Schedule::every(Duration::from_secs(10));
+            self.scheduler.add(METRICS_JOB, metrics, Instant::now());
+        }
+    }
+
+    /// Applies a changed engine configuration to the zone and its open tabs: the memory cache
+    /// size, frame budget, text settings, request timeouts, I/O concurrency, storage quota and
+    /// blocked domains.
+    pub(crate) fn apply_engine_config(&mut self, config: &EngineConfig) {
+        self.http_cache
+            .set_capacity(config.memory_cache_bytes as usize);
+        self.frame_budget = frame_budget(config.target_fps);
+        self.text_options = TextOptions::from_config(config);
+        self.request_timeouts = RequestTimeouts::from_config(config);
+        self.io_concurrency = config.io_concurrency;
+        self.storage_quota = config.quota_per_zone_bytes;
+        self.enforce_storage_quota();
+        self.set_blocked_domains(config);
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.bind_frame_budget(self.frame_budget);
+                tab.context.set_text_options(self.text_options);
+                tab.context.set_request_timeouts(self.request_timeouts);
+                tab.bind_loading_policy(self.config.loading_policy.clone(), self.io_concurrency);
+            }
+        }
+    }
+
+    /// Sets (or with `None`, removes) the spellchecker for editable fields. Applies to all
+    /// tabs of the zone, including the ones that are already open.
+    pub fn set_spellcheck_provider(&mut self, provider: Option<SpellcheckHandle>) {
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.context.set_spellcheck_provider(provider.clone());
+            }
+        }
+        self.spellcheck = provider;
+    }
+
+    /// Sets (or with `None`, removes) the content blocker that filters requests made in the
+    /// zone. Applies to all tabs of the zone, including the ones that are already open.
+    ///
+    /// The engine's `blocked_domains` and `allowlist_domains` are added to the blocker (see
+    /// [`ContentBlocker::set_domain_list`]); when domains are blocked, the zone keeps a
+    /// blocker for them even after `None`.
+    pub fn set_content_blocker(&mut self, blocker: Option<Arc<ContentBlocker>>) {
+        let blocker = if blocker.is_none() && self.blocked_domains.is_empty() {
+            None
+        } else {
+            let blocker = blocker.unwrap_or_default();
+            blocker.set_domain_list(FilterList::from_domains(
+                &self.blocked_domains,
+                &self.allowlist_domains,
+            ));
+            Some(blocker)
+        };
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.bind_content_blocker(blocker.clone());
+            }
+        }
+        self.content_blocker = blocker;
+    }
+
+    /// Takes the blocked and allowlisted domains of `config` and rebuilds the content blocker
+    /// with them.
+    fn set_blocked_domains(&mut self, config: &EngineConfig) {
+        self.blocked_domains = config.blocked_domains.clone();
+        self.allowlist_domains = config.allowlist_domains.clone();
+        self.set_content_blocker(self.content_blocker.clone());
+    }
+
+    /// Remembers the store the zone's cookie jar was taken from, so that
+    /// [`PERSISTENCE_FLUSH_JOB`] writes the jar back to it.
+    pub(crate) fn set_cookie_store(&mut self, store: CookieStoreHandle) {
+        self.cookie_store = Some(store);
+    }
+
+    /// Sets (or with `None`, removes) the policy that decides which cookies the zone stores.
+    /// Applies to all tabs of the zone, including the ones that are already open; cookies
+    /// stored before are kept.
+    pub fn set_cookie_policy(&mut self, policy: Option<CookiePolicyHandle>) {
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.context.set_cookie_policy(policy.clone());
+            }
+        }
+        self.cookie_policy = policy;
+    }
+
+    /// Returns the zone's content blocker, for instance to read its [`stats`](ContentBlocker::stats).
+    pub fn content_blocker(&self) -> Option<Arc<ContentBlocker>> {
+        self.content_blocker.clone()
+    }
+
+    /// Returns the zone's HTTP cache, for instance to clear it. It exists even when the
+    /// zone config disables caching, but then stays empty.
+    pub fn http_cache(&self) -> Arc<HttpCache> {
+        self.http_cache.clone()
+    }
+
+    /// Returns the counters of the connections the zone's tabs share, such as how many
+    /// requests found a connection that was opened ahead of time.
+    pub fn connection_stats(&self) -> PoolStats {
+        self.connection_pool.stats()
+    }
+
+    /// Returns the zone's per-site settings.
+    pub fn site_settings(&self) -> Arc<SiteSettingsStore> {
+        self.site_settings.clone()
+    }
+
+    /// Replaces the zone's per-site settings store, for instance with a persistent one. Applies
+    /// to documents loaded afterward in all tabs of the zone.
+    pub fn set_site_settings(&mut self, store: Arc<SiteSettingsStore>) {
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.bind_site_settings(store.clone(), &self.config);
+            }
+        }
+        self.site_settings = store;
+    }
+
+    /// Returns the zone's favicon cache.
+    pub fn favicons(&self) -> Arc<FaviconStore> {
+        self.favicons.clone()
+    }
+
+    /// Replaces the zone's favicon cache, for instance with a persistent one. Tabs store the
+    /// icons they discover from now on in `store`.
+    pub fn set_favicons(&mut self, store: Arc<FaviconStore>) {
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.bind_favicons(store.clone());
+            }
+        }
+        self.favicons = store;
+    }
+
+    /// Returns the cached icon of the site of `url` that suits `size` pixels best, for
+    /// history and bookmark lists. See [`FaviconStore::favicon_for`].
+    pub fn favicon_for(&self, url: &Url, size: u32) -> Option<Favicon> {
+        self.favicons.favicon_for(url, size)
+    }
+
+    /// Sets how tabs opened in the zone from now on partition their storage. Tabs that are
+    /// already open keep their policy. Defaults to [`PartitionPolicy::TopLevelOrigin`].
+    pub fn set_partition_policy(&mut self, policy: PartitionPolicy) {
+        self.partition_policy = policy;
+    }
+
+    /// Returns the partition policy of tabs opened in the zone.
+    pub fn partition_policy(&self) -> PartitionPolicy {
+        self.partition_policy
+    }
+
+    /// Explains which storage partition `url` uses on the top-level page `top_level` in this
+    /// zone, taking the engine's first-party sets into account. See
+    /// [`StorageService::explain_partition`].
+    pub fn explain_partition(&self, url: &Url, top_level: &Url) -> PartitionExplanation {
+        StorageService::explain_partition_in(
+            url,
+            top_level,
+            self.partition_policy,
+            &self.first_party_sets,
+        )
+    }
+
+    /// Slows the requests of the zone's tabs down to `conditions`, for instance
+    /// [`NetworkConditions::SLOW_3G`], to test how pages load on a poor connection. Applies to
+    /// requests started afterward, including those of tabs that are already open.
+    /// [`NetworkConditions::NONE`] turns emulation off.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidConfiguration`] when the conditions cannot be emulated;
+    /// see [`NetworkConditions::validate`].
+    pub fn set_network_conditions(
+        &mut self,
+        conditions: NetworkConditions,
+    ) -> Result<(), EngineError> {
+        conditions.validate()?;
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.context.set_network_conditions(conditions);
+            }
+        }
+        self.network_conditions = conditions;
+        Ok(())
+    }
+
+    /// Returns the network conditions the zone emulates.
+    pub fn network_conditions(&self) -> NetworkConditions {
+        self.network_conditions
+    }
+
+    /// Replaces how typed input is turned into URLs in the zone's tabs, for instance to fall
+    /// back to a search engine. See [`EngineCommand::NavigateInput`](crate::EngineCommand::NavigateInput).
+    pub fn set_url_resolver(&mut self, resolver: UrlResolver) {
+        let resolver = Arc::new(resolver);
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.bind_url_resolver(resolver.clone());
+            }
+        }
+        self.url_resolver = resolver;
+    }
+
+    /// Returns how typed input is turned into URLs in the zone's tabs.
+    pub fn url_resolver(&self) -> &UrlResolver {
+        &self.url_resolver
+    }
+
+    /// Sets where the zone's tabs search for typed input that is not a URL. `None` makes
+    /// such input fail with [`EngineNotification::NavigationFailed`].
+    pub fn set_search_provider(&mut self, provider: Option<SearchProvider>) {
+        let resolver = (*self.url_resolver).clone().with_search_provider(provider);
+        self.set_url_resolver(resolver);
+    }
+
+    /// Tells whether address-bar input is a URL or a search, and what it would load, the
+    /// same way [`EngineCommand::NavigateInput`](crate::EngineCommand::NavigateInput) does.
+    /// Nothing is loaded.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidUrl`] when the input is neither a URL nor searchable.
+    pub fn resolve_input(&self, text: &str) -> Result<ResolvedNavigation, EngineError> {
+        self.url_resolver.resolve_input(text)
+    }
+
+    /// Adds a user stylesheet. It applies to documents loaded afterward.
+    pub fn add_user_style(&mut self, style: UserStyle) {
+        self.user_content.write().unwrap().styles.push(style);
+    }
+
+    /// Adds a user script. It runs in documents loaded afterward.
+    pub fn add_user_script(&mut self, script: UserScript) {
+        self.user_content.write().unwrap().scripts.push(script);
+    }
+
+    /// Removes all user stylesheets and scripts, including the ones from the zone config.
+    /// Extensions stay loaded.
+    pub fn clear_user_content(&mut self) {
+        let mut content = self.user_content.write().unwrap();
+        content.styles.clear();
+        content.scripts.clear();
+    }
+
+    /// Loads an extension into the zone. Its content scripts apply to documents loaded
+    /// afterward.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::ExtensionAlreadyLoaded`] when an extension with the same id is
+    /// already loaded.
+    pub fn load_extension(&mut self, extension: Extension) -> Result<ExtensionId, EngineError> {
+        let mut content = self.user_content.write().unwrap();
+        if content.extensions.iter().any(|e| e.id == extension.id) {
+            return Err(EngineError::ExtensionAlreadyLoaded(extension.id.0));
+        }
+        let id = extension.id.clone();
+        content.extensions.push(extension);
+        Ok(id)
+    }
+
+    /// Unloads an extension. Returns false when it was not loaded.
+    pub fn unload_extension(&mut self, id: &ExtensionId) -> bool {
+        let mut content = self.user_content.write().unwrap();
+        let before = content.extensions.len();
+        content.extensions.retain(|e| &e.id != id);
+        content.extensions.len() != before
+    }
+
+    /// Returns the ids of the loaded extensions.
+    pub fn extensions(&self) -> Vec<ExtensionId> {
+        let content = self.user_content.read().unwrap();
+        content.extensions.iter().map(|e| e.id.clone()).collect()
+    }
+
+    /// Returns a copy of the zone's user stylesheets and scripts.
+    pub fn user_content(&self) -> UserContent {
+        self.user_content.read().unwrap().clone()
+    }
+
+    /// Subscribe to the notifications of this zone and its tabs. See
+    /// [`GosubEngine::subscribe_zone_notifications`](crate::GosubEngine::subscribe_zone_notifications).
+    pub fn subscribe_notifications(&self) -> NotificationSubscription {
+        self.notifications.subscribe_zone(self.id)
     }
 
-    /// Opens a new tab into the zone
+    /// Subscribe to changes in the zone's cookie jar (added, removed and expired cookies).
+    pub fn subscribe_cookie_events(&self) -> CookieSubscription {
+        self.cookie_bus.subscribe()
+    }
+
+    /// Purges expired cookies from the zone's cookie jar, publishing an event for each.
+    ///
+    /// Zones already do this periodically while ticking.
+    pub fn expire_cookies(&mut self) {
+        self.scheduler.postpone(COOKIE_EXPIRY_JOB, Instant::now());
+        if let Ok(mut jar) = self.cookie_jar.write() {
+            jar.remove_expired(SystemTime::now());
+        }
+    }
+
+    /// Opens a new tab into the zone and publishes [`EngineNotification::TabOpened`].
     pub(crate) fn open_tab(
         &mut self,
         runtime: Arc<Runtime>,
@@ -305,13 +1155,107 @@ impl Zone {
             return Err(EngineError::TabLimitExceeded);
         }
 
-        let tab = Tab::new(self.id, runtime, viewport, Some(self.cookie_jar.clone()));
+        let mut tab = Tab::new(self.id, runtime, viewport, None);
         let tab_id = tab.id;
+        tab.partition_policy = self.partition_policy;
+
+        // Each tab gets its own view on the zone jar, so cookie events carry the tab id
+        let tab_jar = NotifyingCookieJar::new(
+            self.id,
+            self.cookie_jar_inner.clone(),
+            self.cookie_bus.clone(),
+        )
+        .for_tab(tab_id);
+        tab.cookie_jar = Some(Arc::new(RwLock::new(tab_jar)));
+        tab.context.set_sandbox_mode(self.sandbox_mode);
+        tab.context
+            .set_connection_pool(self.connection_pool.clone());
+        tab.context.set_spellcheck_provider(self.spellcheck.clone());
+        tab.context.set_cookie_policy(self.cookie_policy.clone());
+        tab.bind_privacy(self.config.do_not_track, self.config.global_privacy_control);
+        tab.bind_identity(self.identity_headers(&self.config));
+        tab.bind_locale(&self.config);
+        tab.context.set_webdriver(self.automated);
+        tab.bind_content_blocker(self.content_blocker.clone());
+        tab.bind_cache(self.config.cache_enabled.then(|| self.http_cache.clone()));
+        tab.bind_site_settings(self.site_settings.clone(), &self.config);
+        tab.bind_favicons(self.favicons.clone());
+        tab.bind_retry_policy(self.config.retry_policy);
+        tab.bind_loading_policy(self.config.loading_policy.clone(), self.io_concurrency);
+        tab.bind_render_mode(self.config.render_mode);
+        tab.bind_local_file_access(self.config.enable_local_file_access);
+        tab.bind_user_content(self.user_content.clone());
+        tab.bind_url_resolver(self.url_resolver.clone());
+        tab.bind_notifications(self.notifications.clone());
+        tab.bind_network_state(self.online.clone());
+        tab.bind_frame_budget(self.frame_budget);
+        tab.context.set_text_options(self.text_options);
+        tab.context.set_request_timeouts(self.request_timeouts);
+        tab.context.set_network_conditions(self.network_conditions);
+        tab.context
+            .set_first_party_sets(self.first_party_sets.clone());
+        tab.context
+            .set_font_settings(FontSettings::from_zone_config(&self.config));
 
+        // Only announce the tab once it is fully set up, so the user agent never drives a
+        // tab that is still missing its services
+        self.cancel_flags.insert(tab_id, tab.cancel_flag());
         self.tabs.insert(tab_id, Arc::new(Mutex::new(tab)));
+        self.notifications.publish(EngineNotification::TabOpened {
+            zone_id: self.id,
+            tab_id,
+        });
         Ok(tab_id)
     }
 
+    /// Closes a tab of the zone and publishes [`EngineNotification::TabClosed`]. See
+    /// [`EngineCommand::CloseTab`](crate::EngineCommand::CloseTab).
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidTabId`] when the tab is not (or no longer) in the zone.
+    pub(crate) fn close_tab(&mut self, tab_id: TabId) -> Result<(), EngineError> {
+        let tab = self.tabs.remove(&tab_id).ok_or(EngineError::InvalidTabId)?;
+        if let Ok(mut tab) = tab.lock() {
+            tab.close();
+        }
+        self.forget_tab(tab_id);
+        Ok(())
+    }
+
+    /// Closes a tab of the zone without waiting for a thread that still holds it, see
+    /// [`EngineCommand::ForceKill`](crate::EngineCommand::ForceKill). The tab is killed, so a
+    /// holder stuck in its pipeline gives up at the next stage. Publishes
+    /// [`EngineNotification::TabClosed`].
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidTabId`] when the tab is not (or no longer) in the zone.
+    pub(crate) fn force_close_tab(&mut self, tab_id: TabId) -> Result<(), EngineError> {
+        let tab = self.tabs.remove(&tab_id).ok_or(EngineError::InvalidTabId)?;
+        if let Some(cancel) = self.cancel_flags.get(&tab_id) {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.forget(tab_id);
+        }
+        // Whoever holds the tab drops it when done; its loads are stopped if it is free
+        if let Ok(mut tab) = tab.try_lock() {
+            tab.close();
+        }
+        self.forget_tab(tab_id);
+        Ok(())
+    }
+
+    /// Drops what the zone keeps for a removed tab and announces that it is gone.
+    fn forget_tab(&mut self, tab_id: TabId) {
+        self.cancel_flags.remove(&tab_id);
+        self.storage.drop_tab(self.id, tab_id);
+        self.remove_tab_from_group(tab_id);
+        self.notifications.publish(EngineNotification::TabClosed {
+            zone_id: self.id,
+            tab_id,
+        });
+    }
+
     /// Returns the given tab by its ID, or `None` if it doesn't exist.
     pub fn get_tab(&self, tab_id: TabId) -> Option<Arc<Mutex<Tab>>> {
         self.tabs.get(&tab_id).cloned()
@@ -322,6 +1266,217 @@ impl Zone {
         self.tabs.get_mut(&tab_id).cloned()
     }
 
+    /// Returns the current render list of a tab and its epoch, for hosts that paint display
+    /// items themselves and pull scenes instead of waiting for
+    /// [`EngineNotification::SceneReady`]; see
+    /// [`RenderMode::DisplayList`](crate::render::RenderMode::DisplayList). Returns `None` for
+    /// unknown tabs.
+    pub fn scene(&self, tab_id: TabId) -> Option<(RenderList, u64)> {
+        let tab = self.tabs.get(&tab_id)?.lock().ok()?;
+        Some((tab.context.render_list().clone(), tab.context.scene_epoch()))
+    }
+
+    /// Returns all tabs of the zone, in no particular order.
+    pub fn tabs(&self) -> Vec<Arc<Mutex<Tab>>> {
+        self.tabs.values().cloned().collect()
+    }
+
+    /// Creates an empty tab group and returns its id.
+    ///
+    /// ```
+    /// # let backend = gosub_engine::render::backends::null::NullBackend::new().unwrap();
+    /// # let mut engine = gosub_engine::GosubEngine::new(None, Box::new(backend));
+    /// # let zone_id = engine.zone_builder().create().unwrap();
+    /// # let viewport = gosub_engine::render::Viewport::new(0, 0, 800, 600);
+    /// let tab_id = engine.open_tab_in_zone(zone_id, viewport).unwrap();
+    /// let zone = engine.get_zone_mut(zone_id).unwrap();
+    /// let mut zone = zone.lock().unwrap();
+    ///
+    /// let group_id = zone.create_group("Research", [0x1a, 0x73, 0xe8, 0xff]);
+    /// zone.add_tab_to_group(tab_id, group_id).unwrap();
+    /// assert_eq!(zone.tab_group_of(tab_id), Some(group_id));
+    /// assert_eq!(zone.tab_group(group_id).unwrap().tabs, vec![tab_id]);
+    /// ```
+    pub fn create_group(&mut self, name: &str, color: [u8; 4]) -> TabGroupId {
+        let group_id = self.tab_groups.create(name, color);
+        self.publish_group_changed(group_id);
+        group_id
+    }
+
+    /// Changes the name and color of a tab group.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidTabGroupId`] when the zone has no such group.
+    pub fn update_group(
+        &mut self,
+        group_id: TabGroupId,
+        name: &str,
+        color: [u8; 4],
+    ) -> Result<(), EngineError> {
+        if !self.tab_groups.update(group_id, name, color) {
+            return Err(EngineError::InvalidTabGroupId);
+        }
+        self.publish_group_changed(group_id);
+        Ok(())
+    }
+
+    /// Removes a tab group. Its tabs stay open but are no longer grouped. Returns false when
+    /// the zone has no such group.
+    pub fn remove_group(&mut self, group_id: TabGroupId) -> bool {
+        if !self.tab_groups.remove(group_id) {
+            return false;
+        }
+        self.notifications
+            .publish(EngineNotification::TabGroupRemoved {
+                zone_id: self.id,
+                group_id,
+            });
+        true
+    }
+
+    /// Moves a tab into a group, taking it out of the group it was in.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidTabId`] when the tab is not in this zone, and
+    /// [`EngineError::InvalidTabGroupId`] when the group is not.
+    pub fn add_tab_to_group(
+        &mut self,
+        tab_id: TabId,
+        group_id: TabGroupId,
+    ) -> Result<(), EngineError> {
+        if !self.tabs.contains_key(&tab_id) {
+            return Err(EngineError::InvalidTabId);
+        }
+        if self.tab_groups.get(group_id).is_none() {
+            return Err(EngineError::InvalidTabGroupId);
+        }
+        if self.tab_groups.group_of(tab_id) == Some(group_id) {
+            return Ok(());
+        }
+        if let Some(previous) = self.tab_groups.assign(tab_id, group_id) {
+            self.publish_group_changed(previous);
+        }
+        self.publish_group_changed(group_id);
+        Ok(())
+    }
+
+    /// Takes a tab out of its group. Returns the group it was in.
+    pub fn remove_tab_from_group(&mut self, tab_id: TabId) -> Option<TabGroupId> {
+        let group_id = self.tab_groups.unassign(tab_id)?;
+        self.publish_group_changed(group_id);
+        Some(group_id)
+    }
+
+    /// Returns the tab groups of the zone, in the order they were created.
+    pub fn tab_groups(&self) -> &[TabGroup] {
+        self.tab_groups.all()
+    }
+
+    /// Returns a tab group by its ID.
+    pub fn tab_group(&self, group_id: TabGroupId) -> Option<&TabGroup> {
+        self.tab_groups.get(group_id)
+    }
+
+    /// Returns the group a tab is in.
+    pub fn tab_group_of(&self, tab_id: TabId) -> Option<TabGroupId> {
+        self.tab_groups.group_of(tab_id)
+    }
+
+    /// Replaces the zone's tab groups with groups saved from an earlier session, for instance
+    /// by serializing [`Zone::tab_groups`]. Tabs get new ids when they are reopened, so the
+    /// user agent maps the saved tab ids to the reopened tabs first; tabs that are not open in
+    /// the zone are left out. Publishes a change for every restored group.
+    pub fn restore_tab_groups(&mut self, groups: Vec<TabGroup>) {
+        let tabs = &self.tabs;
+        self.tab_groups
+            .restore(groups, |tab_id| tabs.contains_key(&tab_id));
+        let ids: Vec<_> = self.tab_groups.all().iter().map(|g| g.id).collect();
+        for group_id in ids {
+            self.publish_group_changed(group_id);
+        }
+    }
+
+    /// Returns the zone's pinned tabs, for the user agent to save with its session next to
+    /// [`Zone::tab_groups`].
+    pub fn pinned_tabs(&self) -> Vec<TabId> {
+        self.tabs
+            .iter()
+            .filter(|(_, tab)| tab.lock().is_ok_and(|tab| tab.is_pinned()))
+            .map(|(tab_id, _)| *tab_id)
+            .collect()
+    }
+
+    /// Pins the tabs saved with [`Zone::pinned_tabs`] in an earlier session. As with
+    /// [`Zone::restore_tab_groups`], the user agent maps the saved tab ids to the reopened
+    /// tabs first; tabs that are not open in the zone are left out. The tabs are pinned before
+    /// this returns, so nothing that runs later can discard them.
+    pub fn restore_pinned_tabs(&mut self, tabs: &[TabId]) {
+        for tab_id in tabs {
+            if let Some(Ok(mut tab)) = self.tabs.get(tab_id).map(|tab| tab.lock()) {
+                tab.set_pinned(true);
+            }
+        }
+    }
+
+    fn publish_group_changed(&self, group_id: TabGroupId) {
+        self.notifications
+            .publish(EngineNotification::TabGroupChanged {
+                zone_id: self.id,
+                group_id,
+            });
+    }
+
+    /// Changes how often one of the zone's housekeeping jobs runs, such as
+    /// [`COOKIE_EXPIRY_JOB`]. Returns false when the zone has no such job.
+    pub fn set_job_schedule(&mut self, job: &str, schedule: Schedule) -> bool {
+        self.scheduler.set_schedule(job, schedule, Instant::now())
+    }
+
+    /// Runs the housekeeping jobs that are due at `now`.
+    fn run_due_jobs(&mut self, now: Instant) {
+        for job in self.scheduler.due(now) {
+            match job {
+                COOKIE_EXPIRY_JOB => self.expire_cookies(),
+                CACHE_EVICTION_JOB => {
+                    self.http_cache.purge_expired(now);
+                }
+                PERSISTENCE_FLUSH_JOB => self.flush_cookies(),
+                METRICS_JOB => self.publish_metrics(),
+                other => engine_log!(Warn, "Zone[{}]: no handler for job {}", self.id, other),
+            }
+        }
+    }
+
+    /// Writes the zone's cookies to the store the jar was taken from, if any.
+    fn flush_cookies(&self) {
+        let Some(store) = &self.cookie_store else {
+            return;
+        };
+        let snapshot = match self.cookie_jar_inner.read() {
+            Ok(jar) => DefaultCookieJar::snapshot_of(&*jar),
+            Err(_) => None,
+        };
+        if let Some(snapshot) = snapshot {
+            store.persist_zone_from_snapshot(self.id, &snapshot);
+        }
+    }
+
+    /// Publishes the zone's [`ZoneMetrics`].
+    fn publish_metrics(&self) {
+        let connections = self.connection_pool.stats();
+        let metrics = ZoneMetrics {
+            tabs: self.tabs.len(),
+            cache_entries: self.http_cache.len(),
+            cache_bytes: self.http_cache.size_bytes(),
+            requests: connections.requests,
+            reused_connections: connections.reused,
+        };
+        self.notifications.publish(EngineNotification::ZoneMetrics {
+            zone_id: self.id,
+            metrics,
+        });
+    }
+
     /// Ticks all tabs in the zone, returning a map of TabId to TickResult
     pub fn tick_all_tabs(
         &mut self,
@@ -331,14 +1486,20 @@ impl Zone {
         let now = Instant::now();
         let mut results = BTreeMap::new();
 
+        self.run_due_jobs(now);
+
+        let mut killed = Vec::new();
         for (tab_id, tab_arc) in self.tabs.iter_mut() {
             let mut tab = tab_arc.lock().unwrap();
 
-            let interval = match tab.mode {
-                TabMode::Active => Duration::from_secs(0), // Always run
-                TabMode::BackgroundLive => Duration::from_millis(100), // Run at 10Hz
-                TabMode::BackgroundIdle => Duration::from_secs(1), // Run at 1Hz
-                TabMode::Suspended => continue,            // Skip suspended tabs
+            // Discarded tabs wait for a navigation or activation before doing anything
+            if tab.is_discarded() && tab.state == TabState::Idle {
+                continue;
+            }
+
+            // Suspended tabs are skipped
+            let Some(interval) = tab.tick_interval() else {
+                continue;
             };
 
             // Check if enough time has passed since the last tick
@@ -347,22 +1508,57 @@ impl Zone {
             }
             tab.last_tick = now;
 
-            match tab.tick(backend, host) {
+            let busy = self
+                .watchdog
+                .as_ref()
+                .map(|w| w.busy(self.id, *tab_id, tab.cancel_flag()));
+            let ticked = tab.tick(backend, host);
+            drop(busy);
+            if tab.is_killed() {
+                killed.push(*tab_id);
+                continue;
+            }
+            match ticked {
                 Ok(result) => {
                     // If tick was successful, update the tab's last successful tick time
                     tab.last_tick = now;
+                    if result.needs_redraw {
+                        self.notifications
+                            .publish(EngineNotification::FrameRendered {
+                                zone_id: self.id,
+                                tab_id: *tab_id,
+                                stats: tab.frame_stats(),
+                            });
+                    }
                     results.insert(*tab_id, result);
                 }
                 Err(e) => {
                     // Log or handle the error as needed
-                    log::error!("Error ticking tab {:?}: {}", tab_id, e);
+                    engine_log!(Error, "Error ticking tab {:?}: {}", tab_id, e);
                 }
             }
         }
 
+        // Tabs killed while they were busy are closed once they gave up
+        for tab_id in killed {
+            let _ = self.close_tab(tab_id);
+        }
+
         results
     }
 
+    /// Drops the surfaces of all tabs after the render backend moved to a new device, and
+    /// publishes [`EngineNotification::BackendReset`].
+    pub(crate) fn reset_surfaces(&mut self) {
+        for tab in self.tabs.values() {
+            if let Ok(mut tab) = tab.lock() {
+                tab.drop_surfaces();
+            }
+        }
+        self.notifications
+            .publish(EngineNotification::BackendReset { zone_id: self.id });
+    }
+
     /// Get the shared localStorage area for this (zone × partition × origin).
     pub fn local_area(
         &self,
@@ -382,6 +1578,54 @@ impl Zone {
         self.storage.session_for(self.id, tab, pk, origin)
     }
 
+    /// Copies the sessionStorage that `opener` holds for the origin of `url` into `popup`, a
+    /// tab the opener's page opened to load `url`. See
+    /// [`StorageService::clone_session_for_popup`].
+    pub(crate) fn clone_session_for_popup(
+        &self,
+        opener: TabId,
+        policy: PartitionPolicy,
+        popup: TabId,
+        url: &url::Url,
+    ) -> anyhow::Result<()> {
+        let pk = compute_partition_key_in(url, policy, &self.first_party_sets);
+        self.storage
+            .clone_session_for_popup(self.id, opener, popup, &pk, &url.origin())
+    }
+
+    /// Exempts an origin from storage eviction in this zone, or makes it evictable again, for
+    /// instance when the user asks to keep the data of a site.
+    pub fn set_storage_persistent(&self, origin: &url::Origin, persistent: bool) {
+        self.storage.set_persistent(self.id, origin, persistent);
+    }
+
+    /// Evicts the least recently used origins from localStorage when the zone comes close to
+    /// its quota, see [`StorageService::enforce_quota`], and tells the user agent which ones
+    /// with an [`EngineNotification::StorageEvicted`].
+    fn enforce_storage_quota(&mut self) {
+        let evicted = match self.storage.enforce_quota(self.id, self.storage_quota) {
+            Ok(evicted) if !evicted.is_empty() => evicted,
+            Ok(_) => return,
+            Err(e) => {
+                engine_log!(
+                    Warn,
+                    "Zone {:?}: cannot enforce the storage quota: {e}",
+                    self.id
+                );
+                return;
+            }
+        };
+        self.notifications
+            .publish(EngineNotification::StorageEvicted {
+                zone_id: self.id,
+                origins: evicted
+                    .iter()
+                    .map(|u| u.origin.ascii_serialization())
+                    .collect(),
+                bytes: evicted.iter().map(|u| u.bytes).sum(),
+            });
+    }
+
     /// Tell the storage layer a tab is gone (cleans its sessionStorage).
     pub fn on_tab_closed(&self, tab: TabId) {
         self.storage.drop_tab(self.id, tab);
@@ -390,13 +1634,35 @@ impl Zone {
     /// Read the storage channel and process storage events
     pub fn pump_storage_events(&mut self) {
         // Drain the queue without blocking.
+        let mut local_changed = false;
         while let Ok(ev) = self.storage_rx.try_recv() {
+            local_changed |= ev.zone == self.id && ev.scope == StorageScope::Local;
             self.dispatch_storage_event(ev);
         }
+        if local_changed {
+            self.enforce_storage_quota();
+        }
     }
 
     /// Dispatches the storage event to the correct tabs based on the event's scope.
     fn dispatch_storage_event(&mut self, ev: StorageEvent) {
+        // A storage service can be shared between zones (ie: cloned zones), so skip events of other zones
+        if ev.zone != self.id {
+            return;
+        }
+        self.notifications
+            .publish(EngineNotification::StorageChanged {
+                zone_id: self.id,
+                tab_id: ev.source_tab,
+                origin: ev.origin.ascii_serialization(),
+                partition: match &ev.partition {
+                    PartitionKey::None => None,
+                    PartitionKey::TopLevel(top) => Some(top.ascii_serialization()),
+                },
+                scope: ev.scope,
+                key: ev.key.clone(),
+            });
+
         match ev.scope {
             StorageScope::Local => {
                 // Deliver to *other* same-origin documents in the same zone/partition.
@@ -429,7 +1695,8 @@ impl Zone {
 
     /// Called when a tab commits a navigation to a new URL.
     pub fn on_tab_commit(&self, tab: &mut Tab, final_url: &url::Url) -> anyhow::Result<()> {
-        tab.partition_key = compute_partition_key(final_url, tab.partition_policy);
+        tab.partition_key =
+            compute_partition_key_in(final_url, tab.partition_policy, &self.first_party_sets);
 
         // 2) bind storage
         let origin = final_url.origin().clone();
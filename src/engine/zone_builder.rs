@@ -1,6 +1,8 @@
-use crate::cookies::{CookieJarHandle, CookieStoreHandle};
-use crate::storage::StorageService;
-use crate::zone::{ZoneConfig, ZoneId};
+use crate::cookies::{CookieJarHandle, CookiePolicyHandle, CookieStoreHandle};
+use crate::net::ContentBlocker;
+use crate::spellcheck::SpellcheckHandle;
+use crate::storage::{PartitionPolicy, StorageService};
+use crate::zone::{FaviconStore, SiteSettingsStore, UrlResolver, ZoneConfig, ZoneId, ZoneServices};
 use crate::{EngineError, GosubEngine};
 use std::sync::Arc;
 
@@ -19,6 +21,9 @@ use std::sync::Arc;
 /// * [`ZoneConfig`] — If not provided, the engine will use its default config.
 /// * [`StorageService`] — If not provided, the zone will not have persistence.
 ///
+/// Services that are not set come from the engine's
+/// [`ZoneServicesFactory`](crate::zone::ZoneServicesFactory), when it was built with one.
+///
 /// # Fields
 ///
 /// - `zone_id`: Assign a fixed ID to the zone (useful for restoring state).
@@ -31,7 +36,8 @@ use std::sync::Arc;
 /// ```
 /// use std::sync::Arc;
 /// use gosub_engine::GosubEngine;
-/// use gosub_engine::storage::{InMemorySessionStore, SqliteLocalStore, StorageService};
+/// use gosub_engine::storage::{InMemorySessionStore, StorageService};
+/// use gosub_engine::storage::local::in_memory::InMemoryLocalStore;
 ///
 /// // Create the engine
 /// let backend = gosub_engine::render::backends::null::NullBackend::new().expect("null renderer cannot be created (!?)");
@@ -39,7 +45,7 @@ use std::sync::Arc;
 ///
 /// // Create an in-memory storage service
 /// let storage = Arc::new(StorageService::new(
-///     Arc::new(SqliteLocalStore::new("local.db").unwrap()),
+///     Arc::new(InMemoryLocalStore::new()),
 ///     Arc::new(InMemorySessionStore::new()),
 /// ));
 ///
@@ -70,12 +76,25 @@ pub struct ZoneBuilder<'e> {
     config: Option<ZoneConfig>,
     /// Optional storage service for the Zone.
     storage: Option<Arc<StorageService>>,
-    // partition_policy: Option<PartitionPolicy>,
+    /// Optional partition policy for the tabs of the Zone.
+    partition_policy: Option<PartitionPolicy>,
     // quota_bytes: Option<u64>,
     /// Optional cookie store handle for the Zone.
     cookie_store: Option<CookieStoreHandle>,
     /// Optional cookie jar handle for the Zone. Will override the store
     cookie_jar: Option<CookieJarHandle>,
+    /// Optional spellchecker for editable fields in the Zone.
+    spellcheck: Option<SpellcheckHandle>,
+    /// Optional content blocker for requests made in the Zone.
+    content_blocker: Option<Arc<ContentBlocker>>,
+    /// Optional policy for the cookies stored in the Zone.
+    cookie_policy: Option<CookiePolicyHandle>,
+    /// Optional per-site settings store for the Zone.
+    site_settings: Option<Arc<SiteSettingsStore>>,
+    /// Optional favicon cache for the Zone.
+    favicons: Option<Arc<FaviconStore>>,
+    /// Optional resolver for typed input in the Zone.
+    url_resolver: Option<UrlResolver>,
 }
 
 impl GosubEngine {
@@ -88,7 +107,13 @@ impl GosubEngine {
             storage: None,
             cookie_store: None,
             cookie_jar: None,
-            // partition_policy: None,
+            spellcheck: None,
+            content_blocker: None,
+            cookie_policy: None,
+            site_settings: None,
+            favicons: None,
+            partition_policy: None,
+            url_resolver: None,
             // quota_bytes: None,
         }
     }
@@ -120,6 +145,73 @@ impl<'e> ZoneBuilder<'e> {
         self
     }
 
+    pub fn spellcheck(mut self, provider: SpellcheckHandle) -> Self {
+        self.spellcheck = Some(provider);
+        self
+    }
+
+    pub fn content_blocker(mut self, blocker: Arc<ContentBlocker>) -> Self {
+        self.content_blocker = Some(blocker);
+        self
+    }
+
+    pub fn cookie_policy(mut self, policy: CookiePolicyHandle) -> Self {
+        self.cookie_policy = Some(policy);
+        self
+    }
+
+    pub fn site_settings(mut self, store: Arc<SiteSettingsStore>) -> Self {
+        self.site_settings = Some(store);
+        self
+    }
+
+    pub fn favicons(mut self, store: Arc<FaviconStore>) -> Self {
+        self.favicons = Some(store);
+        self
+    }
+
+    pub fn partition_policy(mut self, policy: PartitionPolicy) -> Self {
+        self.partition_policy = Some(policy);
+        self
+    }
+
+    pub fn url_resolver(mut self, resolver: UrlResolver) -> Self {
+        self.url_resolver = Some(resolver);
+        self
+    }
+
+    /// Keeps the zone's cookies and local storage in `profile`. Like
+    /// [`services`](Self::services), this leaves services that were already set alone.
+    #[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+    pub fn profile(mut self, profile: &crate::profile::Profile) -> Self {
+        self.fill_services(profile.services());
+        self
+    }
+
+    /// Sets all services at once. `None` fields of `services` leave the current value alone.
+    pub fn services(mut self, services: ZoneServices) -> Self {
+        self.fill_services(services);
+        self
+    }
+
+    /// Takes the services that are not set yet from `services`.
+    fn fill_services(&mut self, services: ZoneServices) {
+        // A jar that was set explicitly wins over any store
+        if self.cookie_jar.is_none() && self.cookie_store.is_none() {
+            self.cookie_jar = services.cookie_jar;
+            if self.cookie_jar.is_none() {
+                self.cookie_store = services.cookie_store;
+            }
+        }
+        self.storage = self.storage.take().or(services.storage);
+        self.spellcheck = self.spellcheck.take().or(services.spellcheck);
+        self.content_blocker = self.content_blocker.take().or(services.content_blocker);
+        self.cookie_policy = self.cookie_policy.take().or(services.cookie_policy);
+        self.site_settings = self.site_settings.take().or(services.site_settings);
+        self.favicons = self.favicons.take().or(services.favicons);
+        self.partition_policy = self.partition_policy.take().or(services.partition_policy);
+    }
+
     pub fn create(&mut self) -> Result<ZoneId, EngineError> {
         // Either we have a cookie store from which we can take a jar, or we have provided a cookie jar, but not both.
         if self.cookie_store.is_some() && self.cookie_jar.is_some() {
@@ -133,8 +225,12 @@ impl<'e> ZoneBuilder<'e> {
             self.zone_id = Some(ZoneId::new());
         }
 
+        if let Some(factory) = self.engine.zone_services_factory() {
+            self.fill_services(factory(self.zone_id.unwrap()));
+        }
+
         // If we have a cookie store but not a cookie jar, we let the store create the jar for the zone_id
-        if self.cookie_jar.is_none() && self.cookie_jar.is_some() {
+        if self.cookie_jar.is_none() && self.cookie_store.is_some() {
             let jar = self
                 .cookie_store
                 .clone()
@@ -143,11 +239,41 @@ impl<'e> ZoneBuilder<'e> {
             self.cookie_jar = jar
         }
 
-        self.engine.create_zone(
+        let zone_id = self.engine.create_zone(
             self.zone_id,
             self.config.take(),
             self.storage.take(),
             self.cookie_jar.take(),
-        )
+        )?;
+
+        if let Some(zone) = self.engine.get_zone_mut(zone_id) {
+            let mut zone = zone.lock().unwrap();
+            if let Some(provider) = self.spellcheck.take() {
+                zone.set_spellcheck_provider(Some(provider));
+            }
+            if let Some(blocker) = self.content_blocker.take() {
+                zone.set_content_blocker(Some(blocker));
+            }
+            if let Some(policy) = self.cookie_policy.take() {
+                zone.set_cookie_policy(Some(policy));
+            }
+            if let Some(store) = self.site_settings.take() {
+                zone.set_site_settings(store);
+            }
+            if let Some(store) = self.favicons.take() {
+                zone.set_favicons(store);
+            }
+            if let Some(policy) = self.partition_policy.take() {
+                zone.set_partition_policy(policy);
+            }
+            if let Some(resolver) = self.url_resolver.take() {
+                zone.set_url_resolver(resolver);
+            }
+            if let Some(store) = self.cookie_store.take() {
+                zone.set_cookie_store(store);
+            }
+        }
+
+        Ok(zone_id)
     }
 }
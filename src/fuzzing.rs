@@ -0,0 +1,107 @@
+//! Entry points for fuzzing the parsers that read bytes from the network.
+//!
+//! The engine decodes documents, `Set-Cookie` headers, cookie dates and `data:` URLs with its
+//! own parsers, which see whatever a server sends. The functions here take raw bytes, so that a
+//! fuzzer can feed them anything, and go through the same checks the engine applies before it
+//! reaches each parser (a header that is not a valid header value never gets parsed, for
+//! instance). Their signatures do not change when the parsers behind them do.
+//!
+//! The `fuzz` directory of the crate holds a [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz)
+//! target for each function:
+//!
+//! ```text
+//! cargo +nightly fuzz run set_cookie
+//! ```
+//!
+//! There is no HTML parser yet; [`decode_document`] covers the decoding and checks that run
+//! before parsing.
+//!
+//! Only available with the `fuzzing` feature.
+use crate::cookies::Cookie;
+use crate::diagnostics::ParseIssue;
+use crate::engine::cookies::date;
+use crate::engine::cookies::parse_set_cookie as parse_set_cookie_header;
+use crate::engine::diagnostics;
+use crate::net::{DataUrl, DataUrlError, FilterList};
+use http::HeaderValue;
+use std::time::{Duration, UNIX_EPOCH};
+use url::Url;
+
+/// Path that cookies without a `Path` attribute get in [`parse_set_cookie`]
+pub const DEFAULT_COOKIE_PATH: &str = "/fuzz";
+
+/// Time at which [`parse_set_cookie`] receives its cookies, fixed so that runs can be repeated
+pub const COOKIE_RECEIVED_AT: Duration = Duration::from_secs(1_700_000_000);
+
+/// Decodes the body of a document and checks it, like a tab does when a document commits.
+pub fn decode_document(data: &[u8]) -> (String, Vec<ParseIssue>) {
+    diagnostics::decode_document(data.to_vec())
+}
+
+/// Parses a `Set-Cookie` header value, received at [`COOKIE_RECEIVED_AT`] after the UNIX
+/// epoch. Returns `None` when `data` is not a valid header value or holds no cookie.
+pub fn parse_set_cookie(data: &[u8]) -> Option<Cookie> {
+    let header = HeaderValue::from_bytes(data).ok()?;
+    parse_set_cookie_header(
+        std::str::from_utf8(header.as_bytes()).ok()?,
+        DEFAULT_COOKIE_PATH,
+        UNIX_EPOCH + COOKIE_RECEIVED_AT,
+    )
+}
+
+/// Parses the `Expires` attribute of a cookie into a UNIX timestamp.
+pub fn parse_cookie_date(data: &[u8]) -> Option<i64> {
+    date::parse_cookie_date(std::str::from_utf8(data).ok()?)
+}
+
+/// Decodes a `data:` URL. `data` is the whole URL, including the `data:` scheme.
+pub fn decode_data_url(data: &[u8]) -> Option<Result<DataUrl, DataUrlError>> {
+    let url = Url::parse(std::str::from_utf8(data).ok()?).ok()?;
+    Some(crate::net::decode_data_url(&url))
+}
+
+/// Parses a content blocking filter list.
+pub fn parse_filter_list(data: &[u8]) -> FilterList {
+    FilterList::parse(&String::from_utf8_lossy(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_input_is_rejected_without_panicking() {
+        let cookie = parse_set_cookie(b"sid=1; Secure; SameSite=lax").unwrap();
+        assert_eq!(
+            (cookie.value.as_str(), cookie.path.as_deref(), cookie.secure),
+            ("1", Some(DEFAULT_COOKIE_PATH), true)
+        );
+        assert_eq!(cookie.same_site.as_deref(), Some("Lax"));
+        assert!(parse_set_cookie(b"sid=1\n").is_none());
+        assert_eq!(
+            parse_set_cookie(b"sid=1; Max-Age=60")
+                .unwrap()
+                .expires
+                .as_deref(),
+            Some("2023-11-14T22:14:20Z")
+        );
+        assert!(parse_set_cookie(b"no pair").is_none());
+
+        assert_eq!(
+            parse_cookie_date(b"Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(1_445_412_480)
+        );
+        assert_eq!(parse_cookie_date(&[0xff, 0x00]), None);
+
+        let (text, issues) = decode_document(b"<p>\xff</p><!--");
+        assert!(text.starts_with("<p>\u{fffd}"));
+        assert_eq!(issues.len(), 2);
+
+        assert!(matches!(
+            decode_data_url(b"data:text/plain;base64,aGk="),
+            Some(Ok(DataUrl { body, .. })) if body == b"hi"
+        ));
+        assert!(decode_data_url(b"not a url").is_none());
+        assert_eq!(parse_filter_list(b"||ads.test^\n! \xff").len(), 1);
+    }
+}
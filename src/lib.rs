@@ -58,6 +58,7 @@
 //! - [`Tab`](crate::tab::Tab) — a single tab with a dedicated browsing context
 //! - [`Viewport`](crate::render::Viewport) — target surface size/information
 //! - [`EngineEvent`], [`EngineCommand`] — how you drive tabs
+//! - [`EngineNotification`] — what the engine reports back
 //! - `BrowsingContext` — per-tab state (history, active URL, etc.)
 //!
 //! ## Modules
@@ -75,7 +76,18 @@ pub mod net;
 
 pub mod render;
 
-pub use engine::{EngineCommand, EngineError, EngineEvent, GosubEngine, MouseButton};
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+
+pub use engine::{
+    BlockingEngineHandle, ConsoleLevel, DragPayload, DropEffect, EngineBuilder, EngineCommand,
+    EngineError, EngineEvent, EngineNotification, GosubEngine, KeyModifiers, MouseButton,
+    NavigationDisposition, NotificationCategories, NotificationSubscription,
+    SequencedNotification, SequencedSubscription, TabInput, TabKiller, ZoneCommand,
+};
 
 #[doc(inline)]
 pub use engine::tab;
@@ -83,6 +95,9 @@ pub use engine::tab;
 #[doc(inline)]
 pub use engine::zone;
 
+#[doc(inline)]
+pub use engine::activity;
+
 #[doc(inline)]
 pub use engine::cookies;
 
@@ -90,7 +105,34 @@ pub use engine::cookies;
 pub use engine::storage;
 
 #[doc(inline)]
-pub use engine::tick::TickResult;
+pub use engine::diagnostics;
+
+#[doc(inline)]
+pub use engine::forms;
+
+#[doc(inline)]
+pub use engine::logging;
+
+#[cfg(all(feature = "sqlite_cookie_store", feature = "sqlite_local_store"))]
+#[doc(inline)]
+pub use engine::profile;
+
+#[doc(inline)]
+pub use engine::spellcheck;
+
+#[doc(inline)]
+pub use engine::scheduler;
+
+#[cfg(feature = "serde_events")]
+#[doc(inline)]
+pub use engine::recording;
+
+#[cfg(feature = "multiprocess")]
+#[doc(inline)]
+pub use engine::ipc;
+
+#[doc(inline)]
+pub use engine::tick::{EngineFrameStats, FrameStats, JankStats, TickResult};
 
 // EngineConfig at crate root:
 #[doc(inline)]
@@ -102,11 +144,14 @@ pub use crate::engine::config::EngineConfig;
 pub mod config {
     pub use crate::engine::config::{
         CookiePartitioning,
+        FrameSharing,
         RedirectPolicy,
         ProxyConfig,
         TlsConfig,
         GpuOptions,
+        AntialiasingMode,
         LogLevel,
+        RuntimeConfig,
         SandboxMode,
     };
 }
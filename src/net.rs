@@ -10,6 +10,29 @@
 //! - Downloads the full response body into memory (no streaming yet).
 //! - Returns status code, status text, headers, final URL, and body bytes.
 //!
+//! [`fetch_sandboxed`] additionally refuses URLs (and redirects) whose scheme is not allowed by
+//! a [`SandboxMode`](crate::config::SandboxMode); see [`check_sandbox`]. Form submissions
+//! that use POST are sent with [`post_form_sandboxed`], or with [`post_multipart_sandboxed`]
+//! when they upload files; the files of a [`MultipartForm`] are streamed from disk. All of
+//! them give up after the [`RequestTimeouts`] they are given.
+//!
+//! Zones send their requests through a [`ConnectionPool`], which keeps connections open
+//! between requests and can open them ahead of time. Requests for the resources of a
+//! document are ordered by a [`LoadingPolicy`].
+//!
+//! Loads that fail on a flaky network can be retried with a [`RetryPolicy`], and a poor
+//! network can be emulated with [`NetworkConditions`].
+//!
+//! Responses that may be reused are kept in an in-memory [`HttpCache`].
+//!
+//! Requests carry the user agent string and the [`ClientHints`] derived from it.
+//!
+//! `data:` URLs are decoded locally with [`decode_data_url`]; the [`mime`] module has the
+//! media type checks that all loaders share.
+//!
+//! [`ContentBlocker`] decides which requests to refuse based on Adblock Plus / EasyList filter
+//! lists; see the [`blocklist`] module.
+//!
 //! # Example
 //!
 //! ```rust,no_run
@@ -29,8 +52,41 @@
 //! }
 //! ```
 //!
+pub mod blocklist;
+mod cache;
+mod client_hints;
+mod data_url;
+mod emulation;
 mod fetch;
+pub mod mime;
+mod multipart;
+mod pool;
+mod priority;
+mod redirect;
 mod response;
+mod retry;
+mod sandbox;
+mod site;
 
-pub use fetch::fetch;
+pub use blocklist::{BlockDecision, BlockStats, ContentBlocker, FilterList, RequestType};
+pub use cache::HttpCache;
+pub(crate) use client_hints::identity_headers;
+pub use client_hints::{ClientHints, AUTOMATION_TOKEN};
+pub use data_url::{decode_data_url, DataUrl, DataUrlError, DEFAULT_DATA_MIME};
+pub(crate) use emulation::emulate;
+pub use emulation::NetworkConditions;
+pub use fetch::{
+    fetch, fetch_sandboxed, post_form_sandboxed, post_multipart_sandboxed, RequestTimeouts,
+    TimeoutKind,
+};
+pub use multipart::MultipartForm;
+pub(crate) use pool::{resource_hints, ResourceHint};
+pub use pool::{ConnectionPool, PoolStats};
+pub(crate) use priority::ResourceScheduler;
+pub use priority::{LoadingPolicy, ResourcePriority};
+pub use redirect::Redirect;
+pub(crate) use redirect::RequestCookies;
 pub use response::Response;
+pub use retry::{is_transient, RetryPolicy};
+pub use sandbox::{check_sandbox, SandboxViolation};
+pub(crate) use site::{registrable_domain, site};
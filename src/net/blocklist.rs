@@ -0,0 +1,923 @@
+//! Ad and tracker blocking with Adblock Plus / EasyList filter syntax.
+//!
+//! A [`ContentBlocker`] holds the network rules of one or more [`FilterList`]s, indexed for
+//! quick lookups, and decides per request whether it may go out. Zones use one through
+//! [`Zone::set_content_blocker`](crate::zone::Zone::set_content_blocker); blocked loads are
+//! reported as [`EngineNotification::RequestBlocked`](crate::EngineNotification::RequestBlocked).
+//!
+//! Supported syntax:
+//! - `||example.com^` domain anchors, `|` start/end anchors, `*` wildcards and `^` separators
+//! - `@@` exception rules
+//! - the `third-party`, `domain=`, `match-case` and resource type options (`script`, `image`,
+//!   `stylesheet`, `xmlhttprequest`, `subdocument`, `document`, `other`), including their `~`
+//!   negations
+//!
+//! Element hiding rules (`##`), regular expression rules and rules with unsupported options are
+//! skipped; [`FilterList::skipped`] tells how many.
+//!
+//! ```
+//! use gosub_engine::net::{BlockDecision, ContentBlocker, FilterList, RequestType};
+//! use url::Url;
+//!
+//! let list = FilterList::parse("||ads.example^\n@@||ads.example/allowed.js");
+//! let blocker = ContentBlocker::new();
+//! blocker.add_list(list);
+//!
+//! let page = Url::parse("https://news.test/").unwrap();
+//! let ad = Url::parse("https://ads.example/banner.js").unwrap();
+//! assert!(matches!(
+//!     blocker.check(&ad, Some(&page), RequestType::Script),
+//!     BlockDecision::Block { .. }
+//! ));
+//! assert_eq!(blocker.stats().blocked, 1);
+//! ```
+use crate::net::site;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use url::{Position, Url};
+
+/// The kind of resource a request is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestType {
+    /// A top-level document (navigation)
+    Document,
+    /// A document loaded in a frame
+    Subdocument,
+    /// A script
+    Script,
+    /// An image
+    Image,
+    /// A stylesheet
+    Stylesheet,
+    /// A `fetch()` or XMLHttpRequest
+    XmlHttpRequest,
+    /// Anything else
+    Other,
+}
+
+/// Every [`RequestType`], for rules that apply to all requests
+const ALL_REQUEST_TYPES: [RequestType; 7] = [
+    RequestType::Document,
+    RequestType::Subdocument,
+    RequestType::Script,
+    RequestType::Image,
+    RequestType::Stylesheet,
+    RequestType::XmlHttpRequest,
+    RequestType::Other,
+];
+
+impl RequestType {
+    fn from_option(name: &str) -> Option<RequestType> {
+        Some(match name {
+            "document" => RequestType::Document,
+            "subdocument" => RequestType::Subdocument,
+            "script" => RequestType::Script,
+            "image" => RequestType::Image,
+            "stylesheet" => RequestType::Stylesheet,
+            "xmlhttprequest" => RequestType::XmlHttpRequest,
+            "other" => RequestType::Other,
+            _ => return None,
+        })
+    }
+}
+
+/// Outcome of [`ContentBlocker::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockDecision {
+    /// The request may proceed
+    Allow,
+    /// The request must not be made
+    Block {
+        /// Text of the filter rule that matched
+        rule: String,
+    },
+}
+
+/// Counters kept by a [`ContentBlocker`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockStats {
+    /// Requests checked
+    pub checked: u64,
+    /// Requests blocked
+    pub blocked: u64,
+    /// Requests that matched a blocking rule, but were allowed by an exception
+    pub excepted: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Anchor {
+    /// Matches anywhere in the URL
+    None,
+    /// `|`: matches at the start of the URL
+    Start,
+    /// `||`: matches at the start of the host or one of its parent domains
+    Domain,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Original rule text, reported when the rule blocks a request
+    text: String,
+    anchor: Anchor,
+    /// Pattern body, lowercased unless `match_case`
+    pattern: String,
+    end_anchor: bool,
+    match_case: bool,
+    third_party: Option<bool>,
+    include_domains: Vec<String>,
+    exclude_domains: Vec<String>,
+    /// Types the rule applies to; `None` means every type except documents
+    types: Option<Vec<RequestType>>,
+    exclude_types: Vec<RequestType>,
+}
+
+/// A parsed filter list.
+#[derive(Debug, Clone, Default)]
+pub struct FilterList {
+    block: Vec<Rule>,
+    allow: Vec<Rule>,
+    skipped: usize,
+}
+
+impl FilterList {
+    /// Parses a list in Adblock Plus / EasyList format.
+    pub fn parse(text: &str) -> FilterList {
+        let mut list = FilterList::default();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('!') || line.starts_with('[') {
+                continue;
+            }
+            if line.contains("##") || line.contains("#@#") || line.contains("#?#") {
+                // Element hiding needs a style engine
+                list.skipped += 1;
+                continue;
+            }
+
+            let (exception, rule) = match line.strip_prefix("@@") {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            match parse_rule(rule, line) {
+                Some(rule) if exception => list.allow.push(rule),
+                Some(rule) => list.block.push(rule),
+                None => list.skipped += 1,
+            }
+        }
+
+        list
+    }
+
+    /// Builds a list that blocks every request to the `blocked` domains and their
+    /// subdomains, documents included, except for requests to the `allowed` ones. The rules
+    /// read `||domain^` and `@@||domain^`.
+    pub fn from_domains(blocked: &[String], allowed: &[String]) -> FilterList {
+        let rule = |domain: &String| {
+            let text = format!("||{}^", domain.trim().to_ascii_lowercase());
+            let mut rule = parse_rule(&text, &text).expect("domain rules always parse");
+            rule.types = Some(ALL_REQUEST_TYPES.to_vec());
+            rule
+        };
+        FilterList {
+            block: blocked.iter().map(rule).collect(),
+            allow: allowed.iter().map(rule).collect(),
+            skipped: 0,
+        }
+    }
+
+    /// Number of network rules (blocking and exceptions) in the list.
+    pub fn len(&self) -> usize {
+        self.block.len() + self.allow.len()
+    }
+
+    /// Returns true when the list holds no network rules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of rules that were not understood or are not supported.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+fn parse_rule(rule: &str, text: &str) -> Option<Rule> {
+    if rule.len() > 1 && rule.starts_with('/') && rule.ends_with('/') {
+        // Regular expression rules are not supported
+        return None;
+    }
+
+    let (pattern, options) = match rule.rfind('$') {
+        Some(idx) => (&rule[..idx], Some(&rule[idx + 1..])),
+        None => (rule, None),
+    };
+
+    let mut parsed = Rule {
+        text: text.to_string(),
+        anchor: Anchor::None,
+        pattern: String::new(),
+        end_anchor: false,
+        match_case: false,
+        third_party: None,
+        include_domains: Vec::new(),
+        exclude_domains: Vec::new(),
+        types: None,
+        exclude_types: Vec::new(),
+    };
+
+    for option in options.into_iter().flat_map(|o| o.split(',')) {
+        let option = option.trim().to_ascii_lowercase();
+        let (negated, name) = match option.strip_prefix('~') {
+            Some(name) => (true, name),
+            None => (false, option.as_str()),
+        };
+        match name {
+            "third-party" => parsed.third_party = Some(!negated),
+            "match-case" => parsed.match_case = true,
+            _ if name.starts_with("domain=") => {
+                for domain in name["domain=".len()..].split('|') {
+                    match domain.strip_prefix('~') {
+                        Some(d) => parsed.exclude_domains.push(d.to_string()),
+                        None => parsed.include_domains.push(domain.to_string()),
+                    }
+                }
+            }
+            _ => {
+                let ty = RequestType::from_option(name)?;
+                if negated {
+                    parsed.exclude_types.push(ty);
+                } else {
+                    parsed.types.get_or_insert_with(Vec::new).push(ty);
+                }
+            }
+        }
+    }
+
+    let mut pattern = pattern;
+    if let Some(rest) = pattern.strip_prefix("||") {
+        parsed.anchor = Anchor::Domain;
+        pattern = rest;
+    } else if let Some(rest) = pattern.strip_prefix('|') {
+        parsed.anchor = Anchor::Start;
+        pattern = rest;
+    }
+    if let Some(rest) = pattern.strip_suffix('|') {
+        parsed.end_anchor = true;
+        pattern = rest;
+    }
+    parsed.pattern = if parsed.match_case {
+        pattern.to_string()
+    } else {
+        pattern.to_ascii_lowercase()
+    };
+
+    Some(parsed)
+}
+
+impl Rule {
+    fn applies_to(&self, ty: RequestType, third_party: bool, source_host: Option<&str>) -> bool {
+        let type_ok = match &self.types {
+            Some(types) => types.contains(&ty),
+            None => ty != RequestType::Document,
+        };
+        if !type_ok || self.exclude_types.contains(&ty) {
+            return false;
+        }
+        if self.third_party.is_some_and(|tp| tp != third_party) {
+            return false;
+        }
+
+        if !self.include_domains.is_empty() || !self.exclude_domains.is_empty() {
+            let Some(host) = source_host else {
+                return self.include_domains.is_empty();
+            };
+            if self.exclude_domains.iter().any(|d| is_subdomain(host, d)) {
+                return false;
+            }
+            if !self.include_domains.is_empty()
+                && !self.include_domains.iter().any(|d| is_subdomain(host, d))
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn matches_url(&self, url: &str, lower_url: &str, host_range: (usize, usize)) -> bool {
+        let text = if self.match_case { url } else { lower_url };
+        let (text, pattern) = (text.as_bytes(), self.pattern.as_bytes());
+
+        match self.anchor {
+            Anchor::Start => match_pattern(pattern, text, true, self.end_anchor),
+            Anchor::Domain => {
+                let (start, end) = host_range;
+                std::iter::once(start)
+                    .chain((start..end).filter(|i| text[*i] == b'.').map(|i| i + 1))
+                    .any(|pos| match_pattern(pattern, &text[pos..], true, self.end_anchor))
+            }
+            Anchor::None => match_pattern(pattern, text, false, self.end_anchor),
+        }
+    }
+
+    /// Returns the longest run of token characters in the pattern that every URL the rule
+    /// matches contains as a whole token, lowercased. Runs next to a `*`, or at an unanchored
+    /// end of the pattern, may be part of a longer token in the URL and don't qualify.
+    fn token(&self) -> Option<String> {
+        let pattern = self.pattern.as_bytes();
+        let mut best: Option<&[u8]> = None;
+        let mut end = 0;
+        while end < pattern.len() {
+            let start = end;
+            while end < pattern.len() && is_token_char(pattern[end]) {
+                end += 1;
+            }
+            if start == end {
+                end += 1;
+                continue;
+            }
+            let bounded_before = match start.checked_sub(1) {
+                Some(i) => pattern[i] != b'*',
+                None => !matches!(self.anchor, Anchor::None),
+            };
+            let bounded_after = match pattern.get(end) {
+                Some(c) => *c != b'*',
+                None => self.end_anchor,
+            };
+            if bounded_before && bounded_after && best.is_none_or(|b| b.len() < end - start) {
+                best = Some(&pattern[start..end]);
+            }
+        }
+        best.map(|token| String::from_utf8_lossy(token).to_ascii_lowercase())
+    }
+}
+
+/// Matches `pattern` (with `*` and `^`) against `text`, at its start if `anchored` and up to
+/// its end if `end_anchor`.
+///
+/// The parts between the `*` wildcards are looked for one after the other, each at the
+/// earliest position after the previous one. Every part matches a fixed number of bytes (a
+/// `^` only matches nothing at the very end of the text), so an earlier match never leaves
+/// fewer options for the parts after it and nothing has to be undone. A match takes at most
+/// `pattern.len() * text.len()` steps.
+fn match_pattern(pattern: &[u8], text: &[u8], anchored: bool, end_anchor: bool) -> bool {
+    let mut parts = pattern.split(|c| *c == b'*').peekable();
+    let mut pos = 0;
+    let mut first = true;
+    while let Some(part) = parts.next() {
+        let mut starts = if first && anchored {
+            0..=0
+        } else {
+            pos..=text.len()
+        };
+        first = false;
+        if parts.peek().is_none() && end_anchor {
+            return starts.any(|start| match_part(part, text, start) == Some(text.len()));
+        }
+        match starts.find_map(|start| match_part(part, text, start)) {
+            Some(end) => pos = end,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Matches a part of a pattern without wildcards against `text` at `start`, and returns
+/// where the match ends.
+fn match_part(part: &[u8], text: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start;
+    for &c in part {
+        match text.get(pos) {
+            Some(&t) if c == b'^' && is_separator(t) => pos += 1,
+            Some(&t) if c == t => pos += 1,
+            // `^` also matches the end of the address
+            None if c == b'^' => {}
+            _ => return None,
+        }
+    }
+    Some(pos)
+}
+
+/// Characters that tokens are made of: letters, digits and `%`. Every other character
+/// separates two tokens.
+fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'%'
+}
+
+/// Separator characters as defined by Adblock Plus: anything but a letter, digit or `_-.%`.
+fn is_separator(c: u8) -> bool {
+    !(c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.' | b'%'))
+}
+
+fn is_subdomain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+#[derive(Default)]
+struct RuleSet {
+    /// `||host...` rules, keyed by the literal host at the start of the pattern
+    by_domain: HashMap<String, Vec<Rule>>,
+    /// Other rules, keyed by a token that every URL they match contains (see [`Rule::token`])
+    by_token: HashMap<String, Vec<Rule>>,
+    /// Rules without such a token
+    generic: Vec<Rule>,
+}
+
+impl RuleSet {
+    fn insert(&mut self, rule: Rule) {
+        if let Anchor::Domain = rule.anchor {
+            let host_len = rule
+                .pattern
+                .find(['^', '/', '*', '|', ':'])
+                .unwrap_or(rule.pattern.len());
+            let host = &rule.pattern[..host_len];
+            if !host.is_empty() && !rule.pattern[host_len..].starts_with('*') {
+                self.by_domain
+                    .entry(host.to_ascii_lowercase())
+                    .or_default()
+                    .push(rule);
+                return;
+            }
+        }
+        match rule.token() {
+            Some(token) => self.by_token.entry(token).or_default().push(rule),
+            None => self.generic.push(rule),
+        }
+    }
+
+    fn find(
+        &self,
+        request: &RequestInfo<'_>,
+        ty: RequestType,
+        source_host: Option<&str>,
+    ) -> Option<&Rule> {
+        // Candidate keys: the host and each of its parent domains, and the tokens of the URL
+        let host = request.host;
+        let suffixes =
+            std::iter::once(host).chain(host.match_indices('.').map(|(i, _)| &host[i + 1..]));
+        let tokens = request
+            .lower_url
+            .split(|c: char| !c.is_ascii() || !is_token_char(c as u8))
+            .filter(|token| !token.is_empty());
+        suffixes
+            .filter_map(|h| self.by_domain.get(h))
+            .chain(tokens.filter_map(|token| self.by_token.get(token)))
+            .flatten()
+            .chain(self.generic.iter())
+            .find(|rule| {
+                rule.applies_to(ty, request.third_party, source_host)
+                    && rule.matches_url(request.url, &request.lower_url, request.host_range)
+            })
+    }
+}
+
+struct RequestInfo<'a> {
+    url: &'a str,
+    lower_url: String,
+    host: &'a str,
+    host_range: (usize, usize),
+    third_party: bool,
+}
+
+/// Matches requests against the rules of the loaded filter lists.
+///
+/// Lists can be added while the blocker is in use; it is meant to be shared between tabs
+/// behind an `Arc`. Besides the added lists, the blocker holds one list that is replaced as a
+/// whole (see [`ContentBlocker::set_domain_list`]), which zones fill from the engine's
+/// `blocked_domains` and `allowlist_domains`.
+#[derive(Default)]
+pub struct ContentBlocker {
+    block: RwLock<RuleSet>,
+    allow: RwLock<RuleSet>,
+    domain_block: RwLock<RuleSet>,
+    domain_allow: RwLock<RuleSet>,
+    checked: AtomicU64,
+    blocked: AtomicU64,
+    excepted: AtomicU64,
+}
+
+impl ContentBlocker {
+    /// Creates a blocker without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the rules of `list`.
+    pub fn add_list(&self, list: FilterList) {
+        let mut block = self.block.write().unwrap();
+        for rule in list.block {
+            block.insert(rule);
+        }
+        let mut allow = self.allow.write().unwrap();
+        for rule in list.allow {
+            allow.insert(rule);
+        }
+    }
+
+    /// Replaces the rules of the previous call with the rules of `list`. The rules of
+    /// [`ContentBlocker::add_list`] are kept.
+    pub fn set_domain_list(&self, list: FilterList) {
+        let mut block = RuleSet::default();
+        for rule in list.block {
+            block.insert(rule);
+        }
+        let mut allow = RuleSet::default();
+        for rule in list.allow {
+            allow.insert(rule);
+        }
+        *self.domain_block.write().unwrap() = block;
+        *self.domain_allow.write().unwrap() = allow;
+    }
+
+    /// Decides whether a request for `url` of type `ty`, made by a document at `source`
+    /// (`None` for navigations typed in by the user), may go out.
+    pub fn check(&self, url: &Url, source: Option<&Url>, ty: RequestType) -> BlockDecision {
+        self.checked.fetch_add(1, Ordering::Relaxed);
+
+        let Some(host) = url.host_str() else {
+            return BlockDecision::Allow;
+        };
+        let source_host = source.and_then(|s| s.host_str());
+        let request = RequestInfo {
+            url: url.as_str(),
+            lower_url: url.as_str().to_ascii_lowercase(),
+            host,
+            host_range: (
+                url[..Position::BeforeHost].len(),
+                url[..Position::AfterHost].len(),
+            ),
+            third_party: source_host.is_some_and(|s| site(s) != site(host)),
+        };
+
+        let rule = [&self.block, &self.domain_block]
+            .into_iter()
+            .find_map(|rules| {
+                let rules = rules.read().unwrap();
+                rules
+                    .find(&request, ty, source_host)
+                    .map(|rule| rule.text.clone())
+            });
+        let Some(rule) = rule else {
+            return BlockDecision::Allow;
+        };
+        let excepted = [&self.allow, &self.domain_allow].into_iter().any(|rules| {
+            rules
+                .read()
+                .unwrap()
+                .find(&request, ty, source_host)
+                .is_some()
+        });
+        if excepted {
+            self.excepted.fetch_add(1, Ordering::Relaxed);
+            return BlockDecision::Allow;
+        }
+
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        BlockDecision::Block { rule }
+    }
+
+    /// Returns the counters since the blocker was created.
+    pub fn stats(&self) -> BlockStats {
+        BlockStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            excepted: self.excepted.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, engine_with_tab_in, tick};
+    use crate::tab::TabState;
+    use crate::{EngineCommand, EngineConfig, EngineNotification};
+    use std::sync::Arc;
+
+    fn u(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn blocked(blocker: &ContentBlocker, url: &str, source: &str, ty: RequestType) -> bool {
+        matches!(
+            blocker.check(&u(url), Some(&u(source)), ty),
+            BlockDecision::Block { .. }
+        )
+    }
+
+    #[test]
+    fn parses_easylist_syntax() {
+        let list = FilterList::parse(
+            "[Adblock Plus 2.0]\n\
+             ! Title: test\n\
+             ||ads.example^\n\
+             /banner/*/img^\n\
+             example.com##.ad\n\
+             /ads[0-9]+/\n\
+             ||tracker.test^$third-party,script\n\
+             @@||ads.example/ok^\n\
+             ||x.test^$popup\n",
+        );
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.skipped(), 3);
+    }
+
+    #[test]
+    fn anchors_wildcards_and_exceptions() {
+        let blocker = ContentBlocker::new();
+        blocker.add_list(FilterList::parse(
+            "||ads.example^\n\
+             /banner/*/img^\n\
+             |http://insecure.\n\
+             .swf|\n\
+             @@||ads.example/ok^",
+        ));
+        let page = "https://news.test/";
+        let img = RequestType::Image;
+
+        assert!(blocked(&blocker, "https://ads.example/x.png", page, img));
+        assert!(blocked(
+            &blocker,
+            "https://cdn.ads.example/x.png",
+            page,
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://badads.example/x.png",
+            page,
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://ads.example/ok/x.png",
+            page,
+            img
+        ));
+        assert!(blocked(
+            &blocker,
+            "https://cdn.test/banner/1/img?x",
+            page,
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://cdn.test/banner/1/imgx",
+            page,
+            img
+        ));
+        assert!(blocked(&blocker, "http://insecure.test/", page, img));
+        assert!(blocked(&blocker, "https://cdn.test/movie.swf", page, img));
+        assert!(!blocked(
+            &blocker,
+            "https://cdn.test/movie.swf?x",
+            page,
+            img
+        ));
+
+        // Rules without type options never block documents
+        assert!(!blocked(
+            &blocker,
+            "https://ads.example/",
+            page,
+            RequestType::Document
+        ));
+
+        let stats = blocker.stats();
+        assert_eq!((stats.checked, stats.blocked, stats.excepted), (10, 5, 1));
+    }
+
+    #[test]
+    fn domain_lists_block_documents_and_are_replaced_as_a_whole() {
+        let blocker = ContentBlocker::new();
+        blocker.add_list(FilterList::parse("||ads.example^"));
+        blocker.set_domain_list(FilterList::from_domains(
+            &["Tracker.test".to_string()],
+            &["ok.tracker.test".to_string()],
+        ));
+        let page = "https://news.test/";
+        let doc = RequestType::Document;
+
+        assert_eq!(
+            blocker.check(&u("https://cdn.tracker.test/"), Some(&u(page)), doc),
+            BlockDecision::Block {
+                rule: "||tracker.test^".to_string()
+            }
+        );
+        assert!(blocked(
+            &blocker,
+            "https://tracker.test/t.js",
+            page,
+            RequestType::Script
+        ));
+        assert!(!blocked(&blocker, "https://ok.tracker.test/", page, doc));
+
+        blocker.set_domain_list(FilterList::from_domains(&[], &[]));
+        assert!(!blocked(&blocker, "https://tracker.test/", page, doc));
+        // Lists added with add_list stay
+        assert!(blocked(
+            &blocker,
+            "https://ads.example/x.png",
+            page,
+            RequestType::Image
+        ));
+    }
+
+    #[test]
+    fn party_domain_and_type_options() {
+        let blocker = ContentBlocker::new();
+        blocker.add_list(FilterList::parse(
+            "||tracker.test^$third-party,script\n\
+             /pixel.gif$image,domain=shop.test|~checkout.shop.test\n\
+             ||popups.test^$document",
+        ));
+        let script = RequestType::Script;
+
+        assert!(blocked(
+            &blocker,
+            "https://tracker.test/t.js",
+            "https://news.test/",
+            script
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://tracker.test/t.js",
+            "https://www.tracker.test/",
+            script
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://tracker.test/t.gif",
+            "https://news.test/",
+            RequestType::Image
+        ));
+
+        let img = RequestType::Image;
+        assert!(blocked(
+            &blocker,
+            "https://cdn.test/pixel.gif",
+            "https://www.shop.test/",
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://cdn.test/pixel.gif",
+            "https://checkout.shop.test/",
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://cdn.test/pixel.gif",
+            "https://news.test/",
+            img
+        ));
+
+        assert!(blocked(
+            &blocker,
+            "https://popups.test/",
+            "https://news.test/",
+            RequestType::Document
+        ));
+    }
+
+    #[test]
+    fn third_parties_are_told_apart_by_the_public_suffix_list() {
+        let blocker = ContentBlocker::new();
+        blocker.add_list(FilterList::parse("||tracker.co.uk^$third-party"));
+        let img = RequestType::Image;
+
+        let url = "https://tracker.co.uk/t.gif";
+        assert!(blocked(&blocker, url, "https://news.co.uk/", img));
+        assert!(!blocked(&blocker, url, "https://www.tracker.co.uk/", img));
+
+        blocker.add_list(FilterList::parse("/pixel^$third-party"));
+        let url = "http://10.0.0.1/pixel";
+        assert!(blocked(&blocker, url, "http://10.0.0.2/", img));
+        assert!(!blocked(&blocker, url, "http://10.0.0.1:8080/", img));
+    }
+
+    #[test]
+    fn domain_anchors_start_at_the_host() {
+        let blocker = ContentBlocker::new();
+        blocker.add_list(FilterList::parse("||ads.example^"));
+        let page = "https://news.test/";
+        let img = RequestType::Image;
+
+        assert!(blocked(
+            &blocker,
+            "https://ads.example.user@ads.example/x.png",
+            page,
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://ads.example@cdn.test/x.png",
+            page,
+            img
+        ));
+    }
+
+    #[test]
+    fn tokens_and_wildcards_match_without_backtracking() {
+        let blocker = ContentBlocker::new();
+        blocker.add_list(FilterList::parse(
+            "-ad-\n\
+             *a*a*a*a*a*a*a*a*a*a*a*a*b|",
+        ));
+        let page = "https://news.test/";
+        let img = RequestType::Image;
+
+        assert!(blocked(&blocker, "https://cdn.test/x-ad-1.png", page, img));
+        assert!(!blocked(
+            &blocker,
+            "https://cdn.test/x-bad-1.png",
+            page,
+            img
+        ));
+        assert!(!blocked(
+            &blocker,
+            "https://cdn.test/x-ads-1.png",
+            page,
+            img
+        ));
+
+        let long = format!("https://cdn.test/{}", "a".repeat(2000));
+        let started = std::time::Instant::now();
+        assert!(!blocked(&blocker, &long, page, img));
+        assert!(blocked(&blocker, &format!("{long}b"), page, img));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn content_blocker_refuses_navigations() {
+        let blocker = Arc::new(ContentBlocker::new());
+        blocker.add_list(FilterList::parse("||ads.test^$document"));
+        let (mut engine, _, tab_id) =
+            engine_with_tab_in(None, |zone| zone.content_blocker(blocker.clone()));
+        let rx = engine.subscribe_notifications();
+
+        let url = Url::parse("https://www.ads.test/landing").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        tick(&mut engine);
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::RequestBlocked { url: u, rule, blocked_count: 1, .. })
+                if u == url && rule == "||ads.test^$document"
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(EngineNotification::LoadFailed { .. })
+        ));
+
+        let tab = engine.get_tab(tab_id).unwrap();
+        let tab = tab.lock().unwrap();
+        assert!(matches!(tab.state, TabState::Failed(_)));
+        assert_eq!(tab.state_handle().get().blocked_requests, 1);
+        assert_eq!(blocker.stats().blocked, 1);
+    }
+
+    #[test]
+    fn engine_blocked_domains_refuse_navigations_and_follow_updates() {
+        let config = EngineConfig {
+            blocked_domains: vec!["ads.test".to_string()],
+            ..EngineConfig::default()
+        };
+        let (mut engine, zone_id, tab_id) = engine_with_tab(Some(config));
+        let rx = engine.subscribe_notifications();
+
+        let url = Url::parse("https://www.ads.test/landing").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        tick(&mut engine);
+        assert!(rx.try_iter().any(|n| matches!(
+            n,
+            EngineNotification::RequestBlocked { url: u, rule, .. }
+                if u == url && rule == "||ads.test^"
+        )));
+
+        engine
+            .update_config(|c| c.allowlist_domains = vec!["www.ads.test".to_string()])
+            .unwrap();
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let blocker = zone.lock().unwrap().content_blocker().unwrap();
+        assert_eq!(
+            blocker.check(&url, None, RequestType::Document),
+            BlockDecision::Allow
+        );
+
+        engine.update_config(|c| c.blocked_domains.clear()).unwrap();
+        let other = Url::parse("https://ads.test/").unwrap();
+        assert_eq!(
+            blocker.check(&other, None, RequestType::Document),
+            BlockDecision::Allow
+        );
+    }
+}
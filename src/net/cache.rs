@@ -0,0 +1,319 @@
+//! In-memory HTTP cache.
+//!
+//! [`HttpCache`] keeps successful `GET` responses for as long as their `Cache-Control: max-age`
+//! allows, up to a byte budget; the least recently used entries are evicted first. There is no
+//! revalidation yet, so responses without an explicit lifetime (or marked `no-cache` or
+//! `no-store`) are not stored at all.
+//!
+//! Every zone has one cache, shared by its tabs. Whether a tab reads and writes it depends on
+//! its [`TabCacheMode`](crate::tab::TabCacheMode).
+//!
+//! ```
+//! use gosub_engine::net::{HttpCache, Response};
+//! use http::HeaderMap;
+//! use url::Url;
+//!
+//! let cache = HttpCache::new(1024 * 1024);
+//! let mut headers = HeaderMap::new();
+//! headers.insert("cache-control", "max-age=60".parse().unwrap());
+//! let url = Url::parse("https://example.com/").unwrap();
+//! cache.put(&Response {
+//!     url: url.clone(),
+//!     status: 200,
+//!     status_text: "OK".to_string(),
+//!     headers,
+//!     body: b"<p>hi</p>".to_vec(),
+//!     redirects: Vec::new(),
+//! });
+//!
+//! assert_eq!(cache.get(&url).unwrap().body, b"<p>hi</p>");
+//! ```
+use crate::net::Response;
+use http::header::CACHE_CONTROL;
+use http::HeaderMap;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::{Position, Url};
+
+struct Entry {
+    response: Response,
+    expires: Instant,
+    size: usize,
+    /// Value of the cache's use counter when the entry was last read or written
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<String, Entry>,
+    used: usize,
+    clock: u64,
+}
+
+impl Inner {
+    /// Evicts the least recently used entries until at most `limit` bytes are in use.
+    fn evict_to(&mut self, limit: usize) {
+        while self.used > limit {
+            let Some(lru) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&lru) {
+                self.used -= evicted.size;
+            }
+        }
+    }
+}
+
+/// Responses kept in memory, bounded by size.
+pub struct HttpCache {
+    inner: Mutex<Inner>,
+    capacity: AtomicUsize,
+}
+
+impl HttpCache {
+    /// Creates an empty cache that holds at most `capacity` bytes of responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(Inner::default()),
+            capacity: AtomicUsize::new(capacity),
+        }
+    }
+
+    /// Returns the maximum size of the cache in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    /// Changes the maximum size of the cache, evicting the least recently used entries that no
+    /// longer fit.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        self.capacity.store(capacity, Ordering::Relaxed);
+        inner.evict_to(capacity);
+    }
+
+    /// Returns a copy of the cached response for `url`, if it is still fresh. Stale entries are
+    /// dropped.
+    pub fn get(&self, url: &Url) -> Option<Response> {
+        let key = cache_key(url);
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+
+        let entry = inner.entries.get_mut(key)?;
+        if entry.expires <= Instant::now() {
+            let size = entry.size;
+            inner.entries.remove(key);
+            inner.used -= size;
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.response.clone())
+    }
+
+    /// Stores a response if it may be cached. Returns false when it was not stored.
+    pub fn put(&self, response: &Response) -> bool {
+        if response.status != 200 {
+            return false;
+        }
+        let Some(lifetime) = freshness_lifetime(&response.headers) else {
+            return false;
+        };
+        let size = response.body.len()
+            + response
+                .headers
+                .iter()
+                .map(|(k, v)| k.as_str().len() + v.len())
+                .sum::<usize>();
+        let key = cache_key(&response.url).to_string();
+        let mut inner = self.inner.lock().unwrap();
+        let capacity = self.capacity();
+        if size > capacity {
+            return false;
+        }
+        if let Some(old) = inner.entries.remove(&key) {
+            inner.used -= old.size;
+        }
+        inner.evict_to(capacity - size);
+
+        inner.clock += 1;
+        let last_used = inner.clock;
+        inner.used += size;
+        inner.entries.insert(
+            key,
+            Entry {
+                response: response.clone(),
+                expires: Instant::now() + lifetime,
+                size,
+                last_used,
+            },
+        );
+        true
+    }
+
+    /// Drops the entries that are stale at `now`, which [`HttpCache::get`] would otherwise only
+    /// drop when they are asked for. Returns how many were dropped.
+    pub fn purge_expired(&self, now: Instant) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.entries.len();
+        let mut freed = 0;
+        inner.entries.retain(|_, entry| {
+            let fresh = entry.expires > now;
+            if !fresh {
+                freed += entry.size;
+            }
+            fresh
+        });
+        inner.used -= freed;
+        before - inner.entries.len()
+    }
+
+    /// Removes the entry for `url`. Returns false when there was none.
+    pub fn remove(&self, url: &Url) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.entries.remove(cache_key(url)) {
+            Some(entry) => {
+                inner.used -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.clear();
+        inner.used = 0;
+    }
+
+    /// Returns the number of cached responses, including stale ones that were not dropped yet.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Returns true when nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the size of the cached responses in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.inner.lock().unwrap().used
+    }
+}
+
+/// The fragment is never sent to the server, so it is not part of the key.
+fn cache_key(url: &Url) -> &str {
+    &url[..Position::AfterQuery]
+}
+
+/// Returns how long a response may be served from the cache, or `None` when it must not be
+/// cached.
+fn freshness_lifetime(headers: &HeaderMap) -> Option<Duration> {
+    let mut max_age = None;
+    for value in headers.get_all(CACHE_CONTROL) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for directive in value.split(',') {
+            let directive = directive.trim().to_ascii_lowercase();
+            match directive.split_once('=') {
+                None if directive == "no-store" || directive == "no-cache" => return None,
+                Some(("max-age", secs)) => max_age = secs.trim_matches('"').parse().ok(),
+                _ => {}
+            }
+        }
+    }
+    max_age.filter(|secs| *secs > 0).map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(url: &str, cache_control: &str, body: &[u8]) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, cache_control.parse().unwrap());
+        Response {
+            url: Url::parse(url).unwrap(),
+            status: 200,
+            status_text: "OK".to_string(),
+            headers,
+            body: body.to_vec(),
+            redirects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn only_responses_with_a_lifetime_are_cached() {
+        let cache = HttpCache::new(4096);
+        assert!(cache.put(&response("https://a.test/", "public, max-age=60", b"a")));
+        assert!(!cache.put(&response("https://b.test/", "max-age=60, no-store", b"b")));
+        assert!(!cache.put(&response("https://c.test/", "max-age=0", b"c")));
+        assert!(!cache.put(&response("https://d.test/", "private", b"d")));
+
+        let a = Url::parse("https://a.test/#section").unwrap();
+        assert_eq!(cache.get(&a).unwrap().body, b"a");
+        assert!(cache.get(&Url::parse("https://b.test/").unwrap()).is_none());
+        assert!(cache.remove(&a));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn stale_entries_are_purged() {
+        let cache = HttpCache::new(4096);
+        assert!(cache.put(&response("https://b.test/", "max-age=60", b"b")));
+        let fresh_bytes = cache.size_bytes();
+        assert!(cache.put(&response("https://a.test/", "max-age=1", b"a")));
+
+        assert_eq!(cache.purge_expired(Instant::now()), 0);
+        assert_eq!(
+            cache.purge_expired(Instant::now() + Duration::from_secs(2)),
+            1
+        );
+        assert_eq!((cache.len(), cache.size_bytes()), (1, fresh_bytes));
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let entry = |url: &str| response(url, "max-age=60", &[0; 100]);
+        let size = {
+            let cache = HttpCache::new(1000);
+            cache.put(&entry("https://size.test/"));
+            cache.size_bytes()
+        };
+
+        let cache = HttpCache::new(size * 2);
+        cache.put(&entry("https://one.test/"));
+        cache.put(&entry("https://two.test/"));
+        // Reading "one" makes "two" the least recently used
+        cache
+            .get(&Url::parse("https://one.test/").unwrap())
+            .unwrap();
+        cache.put(&entry("https://three.test/"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache
+            .get(&Url::parse("https://two.test/").unwrap())
+            .is_none());
+        assert!(cache
+            .get(&Url::parse("https://one.test/").unwrap())
+            .is_some());
+        assert!(cache.size_bytes() <= cache.capacity());
+
+        // "one" was read last, so it is the entry that still fits
+        cache.set_capacity(size);
+        assert_eq!(cache.len(), 1);
+        assert!(cache
+            .get(&Url::parse("https://one.test/").unwrap())
+            .is_some());
+    }
+}
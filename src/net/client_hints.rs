@@ -0,0 +1,226 @@
+//! User-Agent Client Hints.
+//!
+//! Browsers with a modern user agent string also describe themselves in the low-entropy
+//! `Sec-CH-UA` headers, and some sites serve a different (often degraded) page when they are
+//! missing. [`ClientHints`] derives these headers from a user agent string, so that they
+//! never contradict it. Zones send them with every request unless
+//! [`ZoneConfig::client_hints`](crate::zone::ZoneConfig::client_hints) is turned off.
+use http::header::USER_AGENT;
+use http::{HeaderMap, HeaderValue};
+
+/// Token appended to the user agent string of an engine that discloses it is automated, see
+/// [`EngineConfig::automated`](crate::EngineConfig::automated).
+pub const AUTOMATION_TOKEN: &str = "Headless";
+
+/// Brand that is sent along with the real ones, so that sites do not rely on an exact list
+/// of brands. Chromium calls this GREASE.
+const GREASE_BRAND: (&str, &str) = ("Not_A Brand", "8");
+
+/// The low-entropy client hints of a user agent.
+///
+/// ```
+/// use gosub_engine::net::ClientHints;
+///
+/// let hints = ClientHints::from_user_agent(
+///     "Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 (KHTML, like Gecko) \
+///      Chrome/126.0.0.0 Mobile Safari/537.36",
+/// );
+/// assert_eq!(
+///     hints.brand_list(),
+///     r#""Chromium";v="126", "Google Chrome";v="126", "Not_A Brand";v="8""#
+/// );
+/// assert_eq!(hints.platform, "Android");
+/// assert!(hints.mobile);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHints {
+    /// Brands and their major versions, for `Sec-CH-UA`
+    pub brands: Vec<(String, String)>,
+    /// Operating system, for `Sec-CH-UA-Platform`; `"Unknown"` when the user agent string
+    /// does not tell
+    pub platform: String,
+    /// Whether the user agent presents itself as a mobile browser, for `Sec-CH-UA-Mobile`
+    pub mobile: bool,
+}
+
+impl ClientHints {
+    /// Derives the hints from a user agent string. Chromium-based browsers are recognized by
+    /// their `Chrome/` or `Edg/` token; for any other user agent the first product, such as
+    /// `Gosub/0.1`, is the brand.
+    pub fn from_user_agent(user_agent: &str) -> Self {
+        let products: Vec<(&str, &str)> = user_agent
+            .split_whitespace()
+            .filter_map(|token| token.split_once('/'))
+            .map(|(name, version)| (name, version.split('.').next().unwrap_or(version)))
+            .collect();
+        let version_of = |name: &str| {
+            products
+                .iter()
+                .find(|(product, _)| *product == name)
+                .map(|(_, version)| version.to_string())
+        };
+
+        let mut brands = Vec::new();
+        if let Some(chrome) = version_of("Chrome").or_else(|| version_of("Chromium")) {
+            brands.push(("Chromium".to_string(), chrome.clone()));
+            match version_of("Edg") {
+                Some(edge) => brands.push(("Microsoft Edge".to_string(), edge)),
+                None => brands.push(("Google Chrome".to_string(), chrome)),
+            }
+        } else if let Some((name, version)) = products.iter().find(|(name, _)| *name != "Mozilla") {
+            brands.push((name.to_string(), version.to_string()));
+        }
+        brands.push((GREASE_BRAND.0.to_string(), GREASE_BRAND.1.to_string()));
+
+        let comment = user_agent
+            .split_once('(')
+            .and_then(|(_, rest)| rest.split_once(')'))
+            .map_or("", |(comment, _)| comment);
+        let platform = if comment.contains("Android") {
+            "Android"
+        } else if comment.contains("iPhone") || comment.contains("iPad") {
+            "iOS"
+        } else if comment.contains("Windows") {
+            "Windows"
+        } else if comment.contains("Mac OS X") || comment.contains("Macintosh") {
+            "macOS"
+        } else if comment.contains("CrOS") {
+            "Chrome OS"
+        } else if comment.contains("Linux") || comment.contains("X11") {
+            "Linux"
+        } else {
+            "Unknown"
+        };
+
+        Self {
+            brands,
+            platform: platform.to_string(),
+            mobile: user_agent
+                .split_whitespace()
+                .any(|token| token == "Mobile" || token.starts_with("Mobile/")),
+        }
+    }
+
+    /// Returns the value of the `Sec-CH-UA` header.
+    pub fn brand_list(&self) -> String {
+        self.brands
+            .iter()
+            .map(|(brand, version)| format!("\"{brand}\";v=\"{version}\""))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Returns the `Sec-CH-UA`, `Sec-CH-UA-Mobile` and `Sec-CH-UA-Platform` headers.
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(brands) = HeaderValue::from_str(&self.brand_list()) {
+            headers.insert("sec-ch-ua", brands);
+        }
+        let mobile = if self.mobile { "?1" } else { "?0" };
+        headers.insert("sec-ch-ua-mobile", HeaderValue::from_static(mobile));
+        if let Ok(platform) = HeaderValue::from_str(&format!("\"{}\"", self.platform)) {
+            headers.insert("sec-ch-ua-platform", platform);
+        }
+        headers
+    }
+}
+
+/// Returns the `User-Agent` header for `user_agent`, with the client hints derived from it
+/// when `client_hints` is set. An `automated` engine appends [`AUTOMATION_TOKEN`] to the user
+/// agent. Empty when `user_agent` is not a valid header value.
+pub(crate) fn identity_headers(user_agent: &str, client_hints: bool, automated: bool) -> HeaderMap {
+    let user_agent = if automated {
+        format!("{user_agent} {AUTOMATION_TOKEN}")
+    } else {
+        user_agent.to_string()
+    };
+    let Ok(value) = HeaderValue::from_str(&user_agent) else {
+        return HeaderMap::new();
+    };
+    let mut headers = if client_hints {
+        ClientHints::from_user_agent(&user_agent).headers()
+    } else {
+        HeaderMap::new()
+    };
+    headers.insert(USER_AGENT, value);
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, navigate_offline};
+    use crate::{EngineConfig, GosubEngine};
+
+    #[test]
+    fn hints_follow_the_user_agent_string() {
+        let edge = ClientHints::from_user_agent(
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) \
+             Chrome/125.0.0.0 Safari/537.36 Edg/125.0.2535.51",
+        );
+        assert_eq!(
+            edge.brand_list(),
+            r#""Chromium";v="125", "Microsoft Edge";v="125", "Not_A Brand";v="8""#
+        );
+        assert_eq!((edge.platform.as_str(), edge.mobile), ("Windows", false));
+
+        let gosub = identity_headers("Gosub/0.1 (+https://gosub.dev)", true, false);
+        assert_eq!(gosub["user-agent"], "Gosub/0.1 (+https://gosub.dev)");
+        assert_eq!(gosub["sec-ch-ua"], r#""Gosub";v="0", "Not_A Brand";v="8""#);
+        assert_eq!(gosub["sec-ch-ua-mobile"], "?0");
+        assert_eq!(gosub["sec-ch-ua-platform"], "\"Unknown\"");
+
+        let opted_out = identity_headers("Gosub/0.1", false, false);
+        assert_eq!(opted_out.len(), 1);
+        assert!(identity_headers("bad\nagent", true, false).is_empty());
+
+        let automated = identity_headers("Gosub/0.1", true, true);
+        assert_eq!(automated["user-agent"], "Gosub/0.1 Headless");
+        assert_eq!(
+            automated["sec-ch-ua"],
+            r#""Gosub";v="0", "Not_A Brand";v="8""#
+        );
+    }
+
+    #[test]
+    fn requests_identify_the_user_agent_with_client_hints() {
+        let config = EngineConfig::builder()
+            .user_agent(
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_5) AppleWebKit/537.36 \
+                 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36",
+            )
+            .build()
+            .unwrap();
+        let (mut engine, zone_id, tab_id) = engine_with_tab(Some(config));
+        let navigate = |engine: &mut GosubEngine| {
+            let tab = navigate_offline(engine, tab_id);
+            let tab = tab.lock().unwrap();
+            tab.context.request_headers().clone()
+        };
+
+        let headers = navigate(&mut engine);
+        assert!(headers["user-agent"]
+            .to_str()
+            .unwrap()
+            .contains("Chrome/126"));
+        assert_eq!(
+            headers["sec-ch-ua"],
+            r#""Chromium";v="126", "Google Chrome";v="126", "Not_A Brand";v="8""#
+        );
+        assert_eq!(headers["sec-ch-ua-platform"], "\"macOS\"");
+        assert_eq!(headers["sec-ch-ua-mobile"], "?0");
+
+        // The zone's own user agent wins, and the hints can be turned off
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        zone.lock()
+            .unwrap()
+            .update_config(|c| {
+                c.user_agent = Some("Gosub/0.3".into());
+                c.client_hints = false;
+            })
+            .unwrap();
+        let headers = navigate(&mut engine);
+        assert_eq!(headers["user-agent"], "Gosub/0.3");
+        assert!(!headers.contains_key("sec-ch-ua"));
+    }
+}
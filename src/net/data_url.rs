@@ -0,0 +1,181 @@
+//! `data:` URLs.
+//!
+//! A `data:` URL carries its content inline: `data:[<mime>][;base64],<data>`. The content is
+//! percent-encoded, and additionally base64-encoded when the `;base64` marker is present.
+//! Decoding follows the "data: URL processor" of the Fetch standard, so a missing media type
+//! means `text/plain;charset=US-ASCII`.
+//!
+//! ```
+//! use gosub_engine::net::decode_data_url;
+//! use url::Url;
+//!
+//! let url = Url::parse("data:text/html;base64,PGgxPkhpPC9oMT4=").unwrap();
+//! let data = decode_data_url(&url).unwrap();
+//! assert_eq!(data.mime, "text/html");
+//! assert_eq!(data.body, b"<h1>Hi</h1>");
+//! ```
+use crate::net::{mime, Response};
+use base64::Engine;
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderValue};
+use percent_encoding::percent_decode_str;
+use url::{Position, Url};
+
+/// Media type of `data:` URLs that do not specify one.
+pub const DEFAULT_DATA_MIME: &str = "text/plain;charset=US-ASCII";
+
+/// Why a `data:` URL could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DataUrlError {
+    /// The URL does not use the `data:` scheme
+    #[error("not a data: URL")]
+    NotDataUrl,
+    /// There is no `,` between the media type and the data
+    #[error("data: URL has no ',' before its data")]
+    MissingComma,
+    /// The data is marked as base64 but is not valid base64
+    #[error("data: URL has invalid base64 data")]
+    InvalidBase64,
+}
+
+/// Decoded content of a `data:` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataUrl {
+    /// Media type, with its parameters
+    pub mime: String,
+    /// Decoded bytes
+    pub body: Vec<u8>,
+}
+
+impl DataUrl {
+    /// Wraps the content in a `200 OK` response for `url`, so it can be loaded like a fetched
+    /// document.
+    pub fn into_response(self, url: Url) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Ok(value) = HeaderValue::from_str(&self.mime) {
+            headers.insert(CONTENT_TYPE, value);
+        }
+        Response {
+            url,
+            status: 200,
+            status_text: "OK".to_string(),
+            headers,
+            body: self.body,
+            redirects: Vec::new(),
+        }
+    }
+}
+
+/// Decodes a `data:` URL. The fragment, if any, is not part of the data.
+///
+/// # Errors
+/// See [`DataUrlError`].
+pub fn decode_data_url(url: &Url) -> Result<DataUrl, DataUrlError> {
+    if url.scheme() != "data" {
+        return Err(DataUrlError::NotDataUrl);
+    }
+
+    let input = &url[Position::BeforePath..Position::AfterQuery];
+    let (header, data) = input.split_once(',').ok_or(DataUrlError::MissingComma)?;
+
+    let mut header = header.trim();
+    let is_base64 = match header.rsplit_once(';') {
+        Some((rest, marker)) if marker.trim().eq_ignore_ascii_case("base64") => {
+            header = rest.trim();
+            true
+        }
+        _ => false,
+    };
+
+    let mut body: Vec<u8> = percent_decode_str(data).collect();
+    if is_base64 {
+        body.retain(|b| !b.is_ascii_whitespace());
+        // Padding is optional in data: URLs
+        while body.last() == Some(&b'=') {
+            body.pop();
+        }
+        body = base64::engine::general_purpose::STANDARD_NO_PAD
+            .decode(&body)
+            .map_err(|_| DataUrlError::InvalidBase64)?;
+    }
+
+    let header = percent_decode_str(header).decode_utf8_lossy();
+    let mime = if header.starts_with(';') {
+        format!("text/plain{header}")
+    } else {
+        header.into_owned()
+    };
+    let mime = if mime::is_valid(&mime) {
+        mime
+    } else {
+        DEFAULT_DATA_MIME.to_string()
+    };
+
+    Ok(DataUrl { mime, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, tick, wait_for_load};
+    use crate::{EngineCommand, EngineNotification};
+
+    fn decode(s: &str) -> Result<DataUrl, DataUrlError> {
+        decode_data_url(&Url::parse(s).unwrap())
+    }
+
+    #[test]
+    fn percent_encoded_and_base64_data_are_decoded() {
+        let data = decode("data:,Hello%2C%20World!").unwrap();
+        assert_eq!(data.mime, DEFAULT_DATA_MIME);
+        assert_eq!(data.body, b"Hello, World!");
+
+        let data = decode("data:text/html;charset=utf-8,%3Cp%3Ehi%3C%2Fp%3E#frag").unwrap();
+        assert_eq!(data.mime, "text/html;charset=utf-8");
+        assert_eq!(data.body, b"<p>hi</p>");
+
+        let data = decode("data:;charset=utf-8;BASE64,aGk").unwrap();
+        assert_eq!(data.mime, "text/plain;charset=utf-8");
+        assert_eq!(data.body, b"hi");
+
+        assert_eq!(
+            decode("data:image/png").unwrap_err(),
+            DataUrlError::MissingComma
+        );
+        assert_eq!(
+            decode("data:;base64,a*b").unwrap_err(),
+            DataUrlError::InvalidBase64
+        );
+        assert_eq!(
+            decode("https://example.com/,x").unwrap_err(),
+            DataUrlError::NotDataUrl
+        );
+    }
+
+    #[test]
+    fn data_urls_load_without_the_network() {
+        let (mut engine, _, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+
+        let url = Url::parse("data:text/html;base64,PHA+aW5saW5lPC9wPg==").unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url.clone()))
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+        let tab = engine.get_tab(tab_id).unwrap();
+        assert_eq!(tab.lock().unwrap().context.raw_html(), "<p>inline</p>");
+        drop(tab);
+
+        // Binary data is refused by the same check as other provided content
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::Navigate(Url::parse("data:image/gif;base64,R0lGOA==").unwrap()),
+            )
+            .unwrap();
+        tick(&mut engine);
+        assert!(rx
+            .try_iter()
+            .any(|n| matches!(n, EngineNotification::LoadFailed { error, .. } if error.contains("image/gif"))));
+    }
+}
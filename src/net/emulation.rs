@@ -0,0 +1,191 @@
+//! Emulation of slow and lossy networks.
+//!
+//! To see how pages behave on a poor connection, a zone can load through
+//! [`NetworkConditions`], much like the throttling profiles of browser developer tools. They
+//! are applied around every request of the zone's tabs: the latency passes before the request
+//! is sent, and request and response bodies take as long as the throughput caps allow. A lost
+//! packet is sent again after another round trip, so loss slows a load down but never fails
+//! it. Responses served from the cache are not affected.
+use crate::net::Response;
+use crate::EngineError;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// Payload of a single packet, used to count the packets that may be lost
+const PACKET_BYTES: usize = 1460;
+
+/// Network conditions to emulate. The default emulates nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NetworkConditions {
+    /// Round-trip time added to every request
+    pub latency: Duration,
+    /// Download throughput in bytes per second, unlimited when `None`
+    pub download_throughput: Option<u64>,
+    /// Upload throughput in bytes per second, unlimited when `None`
+    pub upload_throughput: Option<u64>,
+    /// Share of packets that are lost and sent again, from 0.0 up to (but not including) 1.0
+    pub packet_loss: f32,
+}
+
+impl NetworkConditions {
+    /// No emulation: requests go out as fast as the network allows.
+    pub const NONE: Self = Self {
+        latency: Duration::ZERO,
+        download_throughput: None,
+        upload_throughput: None,
+        packet_loss: 0.0,
+    };
+
+    /// A slow mobile connection, like the "Slow 3G" profile of developer tools.
+    pub const SLOW_3G: Self = Self {
+        latency: Duration::from_millis(2000),
+        download_throughput: Some(50_000),
+        upload_throughput: Some(50_000),
+        packet_loss: 0.0,
+    };
+
+    /// A reasonable mobile connection, like the "Fast 3G" profile of developer tools.
+    pub const FAST_3G: Self = Self {
+        latency: Duration::from_micros(562_500),
+        download_throughput: Some(180_000),
+        upload_throughput: Some(84_375),
+        packet_loss: 0.0,
+    };
+
+    /// Returns true when nothing is emulated.
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Checks that the conditions can be emulated.
+    ///
+    /// # Errors
+    /// Returns [`EngineError::InvalidConfiguration`] when a throughput is zero, or the packet
+    /// loss is not within `0.0..1.0`.
+    pub fn validate(&self) -> Result<(), EngineError> {
+        if self.download_throughput == Some(0) || self.upload_throughput == Some(0) {
+            return Err(EngineError::InvalidConfiguration(
+                "emulated throughput must be at least 1 byte per second".to_string(),
+            ));
+        }
+        if !(0.0..1.0).contains(&self.packet_loss) {
+            return Err(EngineError::InvalidConfiguration(format!(
+                "emulated packet loss {} is out of range (expected 0.0..1.0)",
+                self.packet_loss
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the time it takes to send `bytes` at `throughput`, including the round trips
+    /// for lost packets.
+    fn transfer_time(&self, bytes: usize, throughput: Option<u64>) -> Duration {
+        let mut time = throughput.map_or(Duration::ZERO, |rate| {
+            Duration::from_secs_f64(bytes as f64 / rate as f64)
+        });
+        if self.packet_loss > 0.0 {
+            let packets = bytes.div_ceil(PACKET_BYTES).max(1);
+            let mut rng = rand::rng();
+            let lost = (0..packets)
+                .filter(|_| rng.random_bool(f64::from(self.packet_loss)))
+                .count();
+            time += self.latency * lost as u32;
+        }
+        time
+    }
+}
+
+/// Runs `request`, which uploads `upload` bytes, as if it went over a network with
+/// `conditions`.
+pub(crate) async fn emulate(
+    conditions: NetworkConditions,
+    upload: usize,
+    request: impl Future<Output = Result<Response, reqwest::Error>>,
+) -> Result<Response, reqwest::Error> {
+    if conditions.is_none() {
+        return request.await;
+    }
+    let before =
+        conditions.latency + conditions.transfer_time(upload, conditions.upload_throughput);
+    tokio::time::sleep(before).await;
+    let response = request.await?;
+    let download = conditions.transfer_time(response.body.len(), conditions.download_throughput);
+    tokio::time::sleep(download).await;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, serve_html, wait_for_load};
+    use crate::EngineCommand;
+    use url::Url;
+
+    #[test]
+    fn throughput_caps_stretch_transfers() {
+        let conditions = NetworkConditions {
+            download_throughput: Some(1000),
+            ..NetworkConditions::NONE
+        };
+        assert!(!conditions.is_none());
+        assert_eq!(
+            conditions.transfer_time(500, conditions.download_throughput),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            conditions.transfer_time(500, conditions.upload_throughput),
+            Duration::ZERO
+        );
+
+        // About half of the 100 packets are lost, each costing another round trip
+        let lossy = NetworkConditions {
+            latency: Duration::from_millis(10),
+            packet_loss: 0.5,
+            ..NetworkConditions::NONE
+        };
+        let time = lossy.transfer_time(PACKET_BYTES * 100, None);
+        assert!(time > Duration::from_millis(200) && time < Duration::from_millis(800));
+
+        assert!(NetworkConditions::SLOW_3G.validate().is_ok());
+        assert!(NetworkConditions {
+            packet_loss: 1.0,
+            ..NetworkConditions::NONE
+        }
+        .validate()
+        .is_err());
+        assert!(NetworkConditions {
+            upload_throughput: Some(0),
+            ..NetworkConditions::NONE
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn emulated_latency_slows_loads_down() {
+        let port = serve_html("<p>hi</p>");
+
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let lossy = NetworkConditions {
+            packet_loss: 1.0,
+            ..NetworkConditions::NONE
+        };
+        assert!(zone.lock().unwrap().set_network_conditions(lossy).is_err());
+        let slow = NetworkConditions {
+            latency: Duration::from_millis(150),
+            ..NetworkConditions::NONE
+        };
+        zone.lock().unwrap().set_network_conditions(slow).unwrap();
+
+        let started = std::time::Instant::now();
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+        assert!(wait_for_load(&mut engine, &rx));
+        assert!(started.elapsed() >= slow.latency);
+    }
+}
@@ -1,6 +1,65 @@
-use crate::net::Response;
+use crate::config::SandboxMode;
+use crate::net::pool::POOL_IDLE_TIMEOUT;
+use crate::net::redirect::MAX_REDIRECTS;
+use crate::net::{check_sandbox, MultipartForm, Response};
+use crate::EngineConfig;
+use reqwest::header::HeaderMap;
+use std::time::Duration;
 use url::Url;
 
+/// Time limits for a request, from
+/// [`EngineConfig::connect_timeout`](crate::EngineConfig::connect_timeout) and
+/// [`EngineConfig::request_timeout`](crate::EngineConfig::request_timeout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestTimeouts {
+    /// Time to establish the connection, including DNS and TLS
+    pub connect: Duration,
+    /// Time for the whole request, from connecting until the body is read
+    pub total: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            total: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Returns the request timeouts of an engine configuration.
+    pub fn from_config(config: &EngineConfig) -> Self {
+        Self {
+            connect: config.connect_timeout,
+            total: config.request_timeout,
+        }
+    }
+}
+
+/// Which of the [`RequestTimeouts`] a failed request ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// No connection could be established in time
+    Connect,
+    /// The server accepted the connection, but did not finish its response in time
+    Total,
+}
+
+impl TimeoutKind {
+    /// Returns the timeout that made `err` fail, or `None` when it failed for another reason.
+    pub fn of(err: &reqwest::Error) -> Option<Self> {
+        if !err.is_timeout() {
+            return None;
+        }
+        Some(if err.is_connect() {
+            TimeoutKind::Connect
+        } else {
+            TimeoutKind::Total
+        })
+    }
+}
+
 /// Loads a URL using an HTTP GET request and returns the response.
 ///
 /// This is a convenience wrapper around [`reqwest::Client`].
@@ -29,10 +88,120 @@ use url::Url;
 ///
 /// - This function does **not** yet support streaming bodies; the
 ///   entire response is buffered in memory.
-/// - Only HTTP GET is supported. Other methods may be added later.
+/// - Only HTTP GET is supported here; form posts go through [`post_form_sandboxed`].
 pub async fn fetch(url: Url) -> Result<Response, reqwest::Error> {
-    let client = reqwest::Client::new();
-    let res = client.get(url).send().await?;
+    send(reqwest::Client::new().get(url)).await
+}
+
+/// Like [`fetch`], but follows redirects only to URLs that are allowed by `sandbox`, sends
+/// `headers` with the request and every redirect, and gives up after `timeouts`.
+///
+/// A redirect to a disallowed scheme fails the request; the returned error has the
+/// [`SandboxViolation`](crate::net::SandboxViolation) as its source. Callers are expected to
+/// check the initial URL with [`check_sandbox`] themselves. A request that times out fails with
+/// an error that [`TimeoutKind::of`] recognizes.
+pub async fn fetch_sandboxed(
+    url: Url,
+    sandbox: SandboxMode,
+    headers: HeaderMap,
+    timeouts: RequestTimeouts,
+) -> Result<Response, reqwest::Error> {
+    let client = sandboxed_client(sandbox, timeouts)?;
+    send(client.get(url).headers(headers)).await
+}
+
+/// Sends `body` as an `application/x-www-form-urlencoded` POST request to `url`, following
+/// redirects only to URLs that are allowed by `sandbox` and giving up after `timeouts` (see
+/// [`fetch_sandboxed`]).
+///
+/// This is what form submissions with `method=post` use.
+pub async fn post_form_sandboxed(
+    url: Url,
+    body: Vec<u8>,
+    sandbox: SandboxMode,
+    headers: HeaderMap,
+    timeouts: RequestTimeouts,
+) -> Result<Response, reqwest::Error> {
+    let client = sandboxed_client(sandbox, timeouts)?;
+    let request = client
+        .post(url)
+        .headers(headers)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(body);
+    send(request).await
+}
+
+/// Sends `form` as a `multipart/form-data` POST request to `url`, with the same sandbox and
+/// timeouts as [`post_form_sandboxed`]. Files are read from disk while the request is sent.
+///
+/// This is what form submissions with `enctype=multipart/form-data` use.
+pub async fn post_multipart_sandboxed(
+    url: Url,
+    form: MultipartForm,
+    sandbox: SandboxMode,
+    headers: HeaderMap,
+    timeouts: RequestTimeouts,
+) -> Result<Response, reqwest::Error> {
+    let client = sandboxed_client(sandbox, timeouts)?;
+    send(form.attach(client.post(url).headers(headers))).await
+}
+
+/// Returns a client that follows redirects only to URLs allowed by `sandbox` and gives up
+/// after `timeouts`.
+pub(crate) fn sandboxed_client(
+    sandbox: SandboxMode,
+    timeouts: RequestTimeouts,
+) -> Result<reqwest::Client, reqwest::Error> {
+    let policy = reqwest::redirect::Policy::custom(move |attempt| {
+        if let Err(violation) = check_sandbox(attempt.url(), sandbox) {
+            return attempt.error(violation);
+        }
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+        attempt.follow()
+    });
+    build_client(policy, timeouts)
+}
+
+/// Returns a client that hands redirects back to the caller instead of following them, so
+/// that the engine can follow them itself (see [`Redirect`](crate::net::Redirect)).
+/// Redirects to URLs not allowed by `sandbox` still fail the request, as do all redirects
+/// once `exhausted` is set.
+pub(crate) fn hop_client(
+    sandbox: SandboxMode,
+    timeouts: RequestTimeouts,
+    exhausted: bool,
+) -> Result<reqwest::Client, reqwest::Error> {
+    let policy = reqwest::redirect::Policy::custom(move |attempt| {
+        if let Err(violation) = check_sandbox(attempt.url(), sandbox) {
+            return attempt.error(violation);
+        }
+        if exhausted {
+            return attempt.error("too many redirects");
+        }
+        attempt.stop()
+    });
+    build_client(policy, timeouts)
+}
+
+fn build_client(
+    policy: reqwest::redirect::Policy,
+    timeouts: RequestTimeouts,
+) -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .redirect(policy)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .connect_timeout(timeouts.connect)
+        .timeout(timeouts.total)
+        .build()
+}
+
+pub(crate) async fn send(request: reqwest::RequestBuilder) -> Result<Response, reqwest::Error> {
+    let res = request.send().await?;
 
     // Fetch results
     let final_url = res.url().clone();
@@ -53,5 +222,6 @@ pub async fn fetch(url: Url) -> Result<Response, reqwest::Error> {
         status_text,
         headers,
         body,
+        redirects: Vec::new(),
     })
 }
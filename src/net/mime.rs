@@ -0,0 +1,74 @@
+//! Media type helpers shared by the loaders.
+//!
+//! The engine does not sniff content: every loader (network responses, `data:` URLs, content
+//! provided by the user agent) ends up with a `Content-Type`, and these helpers decide what to do
+//! with it.
+
+use std::path::Path;
+
+/// Media type of binary content whose type is not known.
+pub const DEFAULT_BINARY: &str = "application/octet-stream";
+
+/// Returns the essence of a media type: type and subtype in lowercase, without parameters.
+///
+/// ```
+/// use gosub_engine::net::mime::essence;
+///
+/// assert_eq!(essence("Text/HTML; charset=utf-8"), "text/html");
+/// ```
+pub fn essence(mime: &str) -> String {
+    mime.split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Returns true when `mime` looks like `type/subtype`.
+pub fn is_valid(mime: &str) -> bool {
+    let essence = essence(mime);
+    matches!(essence.split_once('/'), Some((ty, sub)) if !ty.is_empty() && !sub.is_empty())
+}
+
+/// Returns true for media types the browsing context can show, which for now are the textual
+/// ones.
+pub fn is_displayable(mime: &str) -> bool {
+    let essence = essence(mime);
+    essence.starts_with("text/")
+        || essence.ends_with("+xml")
+        || matches!(essence.as_str(), "application/xml" | "application/json")
+}
+
+/// Guesses the media type of a local file from its extension, falling back to
+/// [`DEFAULT_BINARY`].
+///
+/// ```
+/// use gosub_engine::net::mime::from_path;
+/// use std::path::Path;
+///
+/// assert_eq!(from_path(Path::new("photo.JPG")), "image/jpeg");
+/// assert_eq!(from_path(Path::new("archive")), "application/octet-stream");
+/// ```
+pub fn from_path(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => DEFAULT_BINARY,
+    }
+}
@@ -0,0 +1,248 @@
+//! `multipart/form-data` request bodies, as sent by forms with file inputs.
+//!
+//! A [`MultipartForm`] only records names, values and file paths. Files are read when the
+//! body is sent, in chunks of [`CHUNK_SIZE`] bytes, so uploads never have to fit in memory.
+use crate::net::mime;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
+
+/// Number of bytes read from a file at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// One entry of a multipart form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text {
+        name: String,
+        value: String,
+    },
+    /// A file input; `path` is `None` when no file was chosen
+    File {
+        name: String,
+        path: Option<PathBuf>,
+    },
+}
+
+/// A `multipart/form-data` body that is built from form entries and streamed from disk.
+///
+/// ```
+/// use gosub_engine::net::MultipartForm;
+///
+/// let mut form = MultipartForm::new();
+/// form.add_text("title", "holiday");
+/// assert!(form.content_type().starts_with("multipart/form-data; boundary="));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultipartForm {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Default for MultipartForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartForm {
+    /// Creates an empty form with a random boundary.
+    pub fn new() -> Self {
+        Self {
+            boundary: format!("----GosubFormBoundary{:016x}", rand::random::<u64>()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Adds a text entry.
+    pub fn add_text(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.parts.push(Part::Text {
+            name: name.into(),
+            value: value.into(),
+        });
+    }
+
+    /// Adds the file at `path`. Its content is read when the body is sent.
+    pub fn add_file(&mut self, name: impl Into<String>, path: impl Into<PathBuf>) {
+        self.parts.push(Part::File {
+            name: name.into(),
+            path: Some(path.into()),
+        });
+    }
+
+    /// Adds a file input without a chosen file, which is sent as an empty file without a name.
+    pub fn add_empty_file(&mut self, name: impl Into<String>) {
+        self.parts.push(Part::File {
+            name: name.into(),
+            path: None,
+        });
+    }
+
+    /// Returns the boundary that separates the entries.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Returns the `Content-Type` header to send the body with.
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Returns the size of the body in bytes, looking up the size of every file.
+    ///
+    /// # Errors
+    /// Fails when the metadata of one of the files cannot be read.
+    pub fn content_length(&self) -> io::Result<u64> {
+        let mut len = self.closing().len() as u64;
+        for part in &self.parts {
+            len += self.part_header(part).len() as u64 + 2;
+            len += match part {
+                Part::Text { value, .. } => value.len() as u64,
+                Part::File {
+                    path: Some(path), ..
+                } => std::fs::metadata(path)?.len(),
+                Part::File { path: None, .. } => 0,
+            };
+        }
+        Ok(len)
+    }
+
+    /// Returns the body as a stream of chunks. Files are opened as the stream reaches them.
+    pub(crate) fn into_stream(self) -> BoxStream<'static, io::Result<Vec<u8>>> {
+        let closing = self.closing();
+        let parts: Vec<_> = self
+            .parts
+            .iter()
+            .map(|part| (self.part_header(part), part.clone()))
+            .collect();
+
+        stream::iter(parts)
+            .flat_map(|(header, part)| {
+                let content = match part {
+                    Part::Text { value, .. } => {
+                        stream::once(async { Ok(value.into_bytes()) }).boxed()
+                    }
+                    Part::File {
+                        path: Some(path), ..
+                    } => file_chunks(path).boxed(),
+                    Part::File { path: None, .. } => stream::empty().boxed(),
+                };
+                stream::once(async { Ok(header.into_bytes()) })
+                    .chain(content)
+                    .chain(stream::once(async { Ok(b"\r\n".to_vec()) }))
+            })
+            .chain(stream::once(async { Ok(closing.into_bytes()) }))
+            .boxed()
+    }
+
+    /// Sets the content type, and the length when it is known, and attaches the body.
+    pub(crate) fn attach(self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut request = request.header(reqwest::header::CONTENT_TYPE, self.content_type());
+        if let Ok(len) = self.content_length() {
+            request = request.header(reqwest::header::CONTENT_LENGTH, len);
+        }
+        request.body(reqwest::Body::wrap_stream(self.into_stream()))
+    }
+
+    fn part_header(&self, part: &Part) -> String {
+        let disposition = match part {
+            Part::Text { name, .. } => format!("form-data; name=\"{}\"", escape(name)),
+            Part::File { name, path } => {
+                let filename = path
+                    .as_deref()
+                    .and_then(Path::file_name)
+                    .map(|f| f.to_string_lossy())
+                    .unwrap_or_default();
+                format!(
+                    "form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}",
+                    escape(name),
+                    escape(&filename),
+                    path.as_deref()
+                        .map_or(mime::DEFAULT_BINARY, mime::from_path)
+                )
+            }
+        };
+        format!(
+            "--{}\r\nContent-Disposition: {disposition}\r\n\r\n",
+            self.boundary
+        )
+    }
+
+    fn closing(&self) -> String {
+        format!("--{}--\r\n", self.boundary)
+    }
+}
+
+/// Escapes a name or file name for a `Content-Disposition` header, the way browsers do.
+fn escape(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Reads the file at `path` in chunks of [`CHUNK_SIZE`] bytes.
+fn file_chunks(path: PathBuf) -> impl futures::Stream<Item = io::Result<Vec<u8>>> + Send {
+    stream::try_unfold(None, move |file: Option<tokio::fs::File>| {
+        let path = path.clone();
+        async move {
+            let mut file = match file {
+                Some(file) => file,
+                None => tokio::fs::File::open(&path).await?,
+            };
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let read = file.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(None);
+            }
+            chunk.truncate(read);
+            Ok(Some((chunk, Some(file))))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+
+    #[test]
+    fn files_are_streamed_between_the_boundaries() {
+        let dir = std::env::temp_dir().join(format!("gosub-multipart-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        let content = "x".repeat(CHUNK_SIZE + 10);
+        std::fs::write(&path, &content).unwrap();
+
+        let mut form = MultipartForm::new();
+        form.add_text("say\"hi", "hello");
+        form.add_file("upload", &path);
+        form.add_empty_file("other");
+        let len = form.content_length().unwrap();
+        let boundary = form.boundary().to_string();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let chunks: Vec<Vec<u8>> = runtime.block_on(form.into_stream().try_collect()).unwrap();
+        let body = String::from_utf8(chunks.concat()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(body.len() as u64, len);
+        assert!(body.starts_with(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"say%22hi\"\r\n\r\nhello\r\n"
+        )));
+        assert!(body.contains(&format!(
+            "name=\"upload\"; filename=\"notes.txt\"\r\nContent-Type: text/plain\r\n\r\n{content}\r\n"
+        )));
+        assert!(body.contains(
+            "name=\"other\"; filename=\"\"\r\nContent-Type: application/octet-stream\r\n\r\n\r\n"
+        ));
+        assert!(body.ends_with(&format!("--{boundary}--\r\n")));
+    }
+
+    #[test]
+    fn missing_files_fail_the_length() {
+        let mut form = MultipartForm::new();
+        form.add_file("upload", "/nonexistent/gosub/file.bin");
+        assert!(form.content_length().is_err());
+    }
+}
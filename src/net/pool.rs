@@ -0,0 +1,398 @@
+//! Connection reuse and warming.
+//!
+//! Every zone sends its requests through a [`ConnectionPool`], which keeps an HTTP client per
+//! sandbox mode and set of timeouts, so that connections stay open between requests to the
+//! same origin. Redirects are followed by the pool itself rather than by the HTTP client, so
+//! that the cookies set along the way are kept (see [`Redirect`]).
+//!
+//! Documents can ask for connections to be opened before they are needed with
+//! `<link rel="preconnect">`, and for host names to be resolved early with
+//! `<link rel="dns-prefetch">`; the user agent can do the same with
+//! [`EngineCommand::Preconnect`](crate::EngineCommand::Preconnect), for instance when the user
+//! hovers a link.
+//!
+//! [`PoolStats`] shows whether this pays off. The HTTP client does not report which requests
+//! actually found an open connection, so the pool counts a request as reused when its origin
+//! was used within the idle timeout of the connections.
+use crate::config::SandboxMode;
+use crate::engine::forms::{attr, tags};
+use crate::net::fetch::{hop_client, send};
+use crate::net::redirect::{redirect_target, PooledRequest, MAX_REDIRECTS};
+use crate::net::{
+    check_sandbox, MultipartForm, Redirect, RequestCookies, RequestTimeouts, Response,
+};
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::net::ToSocketAddrs;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Time an unused connection is kept open
+pub(crate) const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Most resource hints of a single document that are acted upon
+const MAX_HINTS: usize = 8;
+
+/// Counters of a [`ConnectionPool`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Requests sent through the pool
+    pub requests: u64,
+    /// Requests to an origin that likely still had an open connection
+    pub reused: u64,
+    /// Connections opened ahead of time
+    pub preconnects: u64,
+    /// Requests that found a connection opened ahead of time
+    pub preconnect_hits: u64,
+    /// Host names resolved ahead of time
+    pub dns_prefetches: u64,
+}
+
+/// When an origin was last used, and whether that was a preconnect.
+#[derive(Clone, Copy)]
+struct Warm {
+    at: Instant,
+    preconnected: bool,
+}
+
+/// HTTP clients of a zone, shared by its tabs so that they reuse connections.
+#[derive(Default)]
+pub struct ConnectionPool {
+    clients: Mutex<HashMap<(SandboxMode, RequestTimeouts, bool), reqwest::Client>>,
+    warm: Mutex<HashMap<String, Warm>>,
+    stats: Mutex<PoolStats>,
+}
+
+impl ConnectionPool {
+    /// Creates a pool without connections.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the counters of the pool.
+    pub fn stats(&self) -> PoolStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Sends a GET request for `url`, like [`fetch_sandboxed`](crate::net::fetch_sandboxed)
+    /// but over the pooled connections. With `cookies`, redirects store and send cookies at
+    /// every hop.
+    pub(crate) async fn get(
+        &self,
+        url: Url,
+        sandbox: SandboxMode,
+        headers: HeaderMap,
+        timeouts: RequestTimeouts,
+        cookies: Option<RequestCookies>,
+    ) -> Result<Response, reqwest::Error> {
+        let request = PooledRequest::Get(url);
+        self.send_following(request, sandbox, headers, timeouts, cookies)
+            .await
+    }
+
+    /// Sends a form as a POST request to `url`, like
+    /// [`post_form_sandboxed`](crate::net::post_form_sandboxed) but over the pooled
+    /// connections.
+    pub(crate) async fn post_form(
+        &self,
+        url: Url,
+        body: Vec<u8>,
+        sandbox: SandboxMode,
+        headers: HeaderMap,
+        timeouts: RequestTimeouts,
+        cookies: Option<RequestCookies>,
+    ) -> Result<Response, reqwest::Error> {
+        let request = PooledRequest::Form(url, body);
+        self.send_following(request, sandbox, headers, timeouts, cookies)
+            .await
+    }
+
+    /// Sends a multipart form as a POST request to `url`, like
+    /// [`post_multipart_sandboxed`](crate::net::post_multipart_sandboxed) but over the pooled
+    /// connections.
+    pub(crate) async fn post_multipart(
+        &self,
+        url: Url,
+        form: MultipartForm,
+        sandbox: SandboxMode,
+        headers: HeaderMap,
+        timeouts: RequestTimeouts,
+        cookies: Option<RequestCookies>,
+    ) -> Result<Response, reqwest::Error> {
+        let request = PooledRequest::Multipart(url, form);
+        self.send_following(request, sandbox, headers, timeouts, cookies)
+            .await
+    }
+
+    /// Sends `request` and follows its redirects one hop at a time, up to [`MAX_REDIRECTS`].
+    /// The cookies set by every redirect are stored before the next hop, which sends the
+    /// cookies that apply to its own URL. The cookies of the final response are left to the
+    /// caller.
+    async fn send_following(
+        &self,
+        mut request: PooledRequest,
+        sandbox: SandboxMode,
+        headers: HeaderMap,
+        timeouts: RequestTimeouts,
+        cookies: Option<RequestCookies>,
+    ) -> Result<Response, reqwest::Error> {
+        let mut redirects = Vec::new();
+        loop {
+            let exhausted = redirects.len() >= MAX_REDIRECTS;
+            let client = self.client(sandbox, timeouts, exhausted)?;
+            self.record_request(request.url());
+            let builder = request.build(&client, headers.clone(), cookies.as_ref());
+            let mut response = send(builder).await?;
+
+            let Some(to) = redirect_target(&response.url, response.status, &response.headers)
+            else {
+                response.redirects = redirects;
+                return Ok(response);
+            };
+            let set = cookies
+                .as_ref()
+                .map(|c| c.store(&response.url, &response.headers))
+                .unwrap_or_default();
+            redirects.push(Redirect {
+                from: response.url,
+                to: to.clone(),
+                status: response.status,
+                cookies: set,
+            });
+            request = request.redirect(response.status, to);
+        }
+    }
+
+    /// Opens a connection to the origin of `url` and keeps it in the pool. The connection is
+    /// set up with a `HEAD` request for the root of the origin, as the HTTP client cannot
+    /// connect without sending a request. Returns false when the origin cannot be reached.
+    pub(crate) async fn preconnect(
+        &self,
+        url: Url,
+        sandbox: SandboxMode,
+        headers: HeaderMap,
+        timeouts: RequestTimeouts,
+    ) -> bool {
+        let Some(origin) = origin_key(&url) else {
+            return false;
+        };
+        if self.is_warm(&origin) || check_sandbox(&url, sandbox).is_err() {
+            return false;
+        }
+        let Ok(root) = url.join("/") else {
+            return false;
+        };
+        let Ok(client) = self.client(sandbox, timeouts, false) else {
+            return false;
+        };
+        if client.head(root).headers(headers).send().await.is_err() {
+            return false;
+        }
+        self.warm.lock().unwrap().insert(
+            origin,
+            Warm {
+                at: Instant::now(),
+                preconnected: true,
+            },
+        );
+        self.stats.lock().unwrap().preconnects += 1;
+        true
+    }
+
+    /// Resolves the host name of `url`, so that the system resolver has it cached when a
+    /// connection is made. Returns false when the name does not resolve.
+    pub(crate) async fn prefetch_dns(&self, url: Url) -> bool {
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            return false;
+        };
+        let host = host.to_string();
+        let resolved = tokio::task::spawn_blocking(move || (host.as_str(), port).to_socket_addrs())
+            .await
+            .is_ok_and(|addrs| addrs.is_ok());
+        if resolved {
+            self.stats.lock().unwrap().dns_prefetches += 1;
+        }
+        resolved
+    }
+
+    /// Returns the client for `sandbox` and `timeouts`, creating it on first use. Once a
+    /// request is `exhausted`, its client fails on any further redirect.
+    fn client(
+        &self,
+        sandbox: SandboxMode,
+        timeouts: RequestTimeouts,
+        exhausted: bool,
+    ) -> Result<reqwest::Client, reqwest::Error> {
+        let mut clients = self.clients.lock().unwrap();
+        let key = (sandbox, timeouts, exhausted);
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = hop_client(sandbox, timeouts, exhausted)?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+
+    fn is_warm(&self, origin: &str) -> bool {
+        self.warm
+            .lock()
+            .unwrap()
+            .get(origin)
+            .is_some_and(|w| w.at.elapsed() < POOL_IDLE_TIMEOUT)
+    }
+
+    /// Counts a request to `url`, and whether it likely reuses a connection.
+    fn record_request(&self, url: &Url) {
+        let Some(origin) = origin_key(url) else {
+            return;
+        };
+        let previous = self.warm.lock().unwrap().insert(
+            origin,
+            Warm {
+                at: Instant::now(),
+                preconnected: false,
+            },
+        );
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        if let Some(previous) = previous.filter(|w| w.at.elapsed() < POOL_IDLE_TIMEOUT) {
+            stats.reused += 1;
+            if previous.preconnected {
+                stats.preconnect_hits += 1;
+            }
+        }
+    }
+}
+
+/// A `<link rel="preconnect">` or `<link rel="dns-prefetch">` of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ResourceHint {
+    Preconnect(Url),
+    DnsPrefetch(Url),
+}
+
+/// Returns the preconnect and DNS prefetch hints of the document at `base`, at most
+/// [`MAX_HINTS`] of them. Only `http` and `https` origins are hinted.
+pub(crate) fn resource_hints(html: &str, base: &Url) -> Vec<ResourceHint> {
+    let mut hints = Vec::new();
+    for (name, attrs, _) in tags(html) {
+        if name != "link" {
+            continue;
+        }
+        let (Some(rel), Some(href)) = (attr(attrs, "rel"), attr(attrs, "href")) else {
+            continue;
+        };
+        let Some(url) = base
+            .join(&href)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+        else {
+            continue;
+        };
+        for rel in rel.split_ascii_whitespace() {
+            if rel.eq_ignore_ascii_case("preconnect") {
+                hints.push(ResourceHint::Preconnect(url.clone()));
+            } else if rel.eq_ignore_ascii_case("dns-prefetch") {
+                hints.push(ResourceHint::DnsPrefetch(url.clone()));
+            }
+        }
+    }
+    hints.dedup();
+    hints.truncate(MAX_HINTS);
+    hints
+}
+
+fn origin_key(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, serve_html, tick};
+    use crate::{EngineCommand, GosubEngine};
+    use std::time::Duration;
+    use url::Url;
+
+    #[test]
+    fn documents_hint_connections_to_open() {
+        let base = Url::parse("https://site.test/a/").unwrap();
+        let hints = resource_hints(
+            r#"<link rel="preconnect" href="https://cdn.test"><link rel=dns-prefetch href="//fonts.test">
+            <link rel="preconnect dns-prefetch" href="https://api.test/v1">
+            <link rel="preconnect" href="ftp://files.test"><link rel="stylesheet" href="x.css">"#,
+            &base,
+        );
+        let u = |s| Url::parse(s).unwrap();
+        assert_eq!(
+            hints,
+            vec![
+                ResourceHint::Preconnect(u("https://cdn.test/")),
+                ResourceHint::DnsPrefetch(u("https://fonts.test/")),
+                ResourceHint::Preconnect(u("https://api.test/v1")),
+                ResourceHint::DnsPrefetch(u("https://api.test/v1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn requests_within_the_idle_timeout_count_as_reused() {
+        let pool = ConnectionPool::new();
+        let url = Url::parse("https://site.test/page").unwrap();
+        pool.record_request(&url);
+        pool.record_request(&url.join("/other").unwrap());
+        pool.record_request(&Url::parse("https://else.test/").unwrap());
+        let stats = pool.stats();
+        assert_eq!((stats.requests, stats.reused), (3, 1));
+        assert_eq!(stats.preconnect_hits, 0);
+    }
+
+    #[test]
+    fn preconnected_origins_speed_up_later_navigations() {
+        let target = serve_html("<p>target</p>");
+        let hinting = serve_html(Box::leak(
+            format!(
+                r#"<link rel="preconnect" href="http://127.0.0.1:{target}">
+                <link rel="dns-prefetch" href="http://localhost:{target}">"#
+            )
+            .into_boxed_str(),
+        ));
+
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let run_until = |engine: &mut GosubEngine, done: &dyn Fn(PoolStats) -> bool| {
+            for _ in 0..400 {
+                tick(engine);
+                if done(zone.lock().unwrap().connection_stats()) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            false
+        };
+
+        let origin = Url::parse(&format!("http://127.0.0.1:{hinting}/")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Preconnect { origin })
+            .unwrap();
+        assert!(run_until(&mut engine, &|s| s.preconnects == 1));
+
+        // The page is loaded over the warm connection, and hints at the next origin
+        let url = Url::parse(&format!("http://127.0.0.1:{hinting}/page")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+        assert!(run_until(&mut engine, &|s| s.preconnects == 2
+            && s.dns_prefetches == 1));
+
+        let url = Url::parse(&format!("http://127.0.0.1:{target}/")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+        assert!(run_until(&mut engine, &|s| s.preconnect_hits == 2));
+        let stats = zone.lock().unwrap().connection_stats();
+        assert!(stats.requests >= 2 && stats.reused >= 2);
+    }
+}
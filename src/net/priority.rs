@@ -0,0 +1,182 @@
+//! Ordering the requests a document makes besides its own.
+//!
+//! Requests for the resources of a document are queued per tab and sent by priority class,
+//! with a limit on how many are in flight at once. A [`LoadingPolicy`] in the
+//! [`ZoneConfig`](crate::zone::ZoneConfig) sets the order of the classes and the limit, which
+//! defaults to [`EngineConfig::io_concurrency`](crate::EngineConfig::io_concurrency). Classes
+//! left out of the order are not loaded at all, which suits kiosks and devices that are short
+//! on memory.
+//!
+//! The engine does not load stylesheets, fonts or images yet. Today the queue holds favicons
+//! and the connections that `<link rel="preconnect">` and `<link rel="dns-prefetch">` ask for.
+
+/// Priority class of a request, from most to least urgent in the default order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourcePriority {
+    /// Stylesheets the first paint waits for
+    CriticalCss,
+    /// Web fonts
+    Font,
+    /// Images, including favicons
+    Image,
+    /// Speculative work for later navigations, such as preconnects and DNS prefetches
+    Prefetch,
+}
+
+/// How a tab orders the requests for the resources of its documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadingPolicy {
+    /// Classes from first to last loaded. Classes that are not listed are not loaded.
+    pub order: Vec<ResourcePriority>,
+    /// Most requests a tab has in flight at once, or `None` to use
+    /// [`EngineConfig::io_concurrency`](crate::EngineConfig::io_concurrency)
+    pub max_in_flight: Option<usize>,
+}
+
+impl Default for LoadingPolicy {
+    fn default() -> Self {
+        Self {
+            order: vec![
+                ResourcePriority::CriticalCss,
+                ResourcePriority::Font,
+                ResourcePriority::Image,
+                ResourcePriority::Prefetch,
+            ],
+            max_in_flight: None,
+        }
+    }
+}
+
+impl LoadingPolicy {
+    /// A policy for devices that are short on memory: two requests at a time, and no
+    /// speculative work.
+    pub fn low_memory() -> Self {
+        Self {
+            order: vec![
+                ResourcePriority::CriticalCss,
+                ResourcePriority::Font,
+                ResourcePriority::Image,
+            ],
+            max_in_flight: Some(2),
+        }
+    }
+}
+
+/// Queue of the resource requests of a tab.
+pub(crate) struct ResourceScheduler<T> {
+    policy: LoadingPolicy,
+    /// Most requests in flight, from the policy or the engine config
+    limit: usize,
+    /// Queued requests with their rank in the policy order and their arrival number
+    queue: Vec<(usize, u64, T)>,
+    next_seq: u64,
+    in_flight: usize,
+}
+
+impl<T> ResourceScheduler<T> {
+    /// Creates an empty queue that has at most `io_concurrency` requests in flight, unless
+    /// `policy` sets a lower limit.
+    pub(crate) fn new(policy: LoadingPolicy, io_concurrency: usize) -> Self {
+        let mut scheduler = Self {
+            policy: LoadingPolicy::default(),
+            limit: 1,
+            queue: Vec::new(),
+            next_seq: 0,
+            in_flight: 0,
+        };
+        scheduler.configure(policy, io_concurrency);
+        scheduler
+    }
+
+    /// Switches to `policy`. Queued requests of classes it leaves out are dropped.
+    pub(crate) fn configure(&mut self, policy: LoadingPolicy, io_concurrency: usize) {
+        self.limit = policy.max_in_flight.unwrap_or(io_concurrency).max(1);
+        let ranks: Vec<_> = self
+            .policy
+            .order
+            .iter()
+            .map(|class| policy.order.iter().position(|c| c == class))
+            .collect();
+        self.queue = std::mem::take(&mut self.queue)
+            .into_iter()
+            .filter_map(|(rank, seq, item)| Some((ranks[rank]?, seq, item)))
+            .collect();
+        self.policy = policy;
+    }
+
+    /// Queues `item` in class `priority`. Returns false, dropping the item, when the policy
+    /// does not load that class.
+    pub(crate) fn push(&mut self, priority: ResourcePriority, item: T) -> bool {
+        let Some(rank) = self.policy.order.iter().position(|c| *c == priority) else {
+            return false;
+        };
+        self.queue.push((rank, self.next_seq, item));
+        self.next_seq += 1;
+        true
+    }
+
+    /// Takes the most urgent queued request, oldest first within a class, unless the limit of
+    /// requests in flight is reached. The caller reports its end with
+    /// [`ResourceScheduler::finish`].
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        if self.in_flight >= self.limit {
+            return None;
+        }
+        let (index, _) = self
+            .queue
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (rank, seq, _))| (*rank, *seq))?;
+        self.in_flight += 1;
+        Some(self.queue.remove(index).2)
+    }
+
+    /// Records that a request taken with [`ResourceScheduler::pop`] finished.
+    pub(crate) fn finish(&mut self) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+    }
+
+    /// Drops the queue and forgets the requests in flight, when the document they were for
+    /// goes away.
+    pub(crate) fn clear(&mut self) {
+        self.queue.clear();
+        self.in_flight = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_go_out_by_class_within_the_limit() {
+        let mut scheduler = ResourceScheduler::new(LoadingPolicy::default(), 2);
+        scheduler.push(ResourcePriority::Prefetch, "hint");
+        scheduler.push(ResourcePriority::Image, "logo");
+        scheduler.push(ResourcePriority::CriticalCss, "main.css");
+        scheduler.push(ResourcePriority::Image, "banner");
+
+        assert_eq!(scheduler.pop(), Some("main.css"));
+        assert_eq!(scheduler.pop(), Some("logo"));
+        assert_eq!(scheduler.pop(), None);
+        scheduler.finish();
+        assert_eq!(scheduler.pop(), Some("banner"));
+
+        // Switching to a policy without speculative work drops the hint
+        scheduler.configure(LoadingPolicy::low_memory(), 64);
+        scheduler.finish();
+        scheduler.finish();
+        assert_eq!(scheduler.pop(), None);
+        assert!(!scheduler.push(ResourcePriority::Prefetch, "later"));
+
+        let reversed = LoadingPolicy {
+            order: vec![ResourcePriority::Image, ResourcePriority::CriticalCss],
+            max_in_flight: Some(1),
+        };
+        scheduler.configure(reversed, 64);
+        scheduler.push(ResourcePriority::CriticalCss, "main.css");
+        scheduler.push(ResourcePriority::Image, "logo");
+        assert_eq!(scheduler.pop(), Some("logo"));
+        assert_eq!(scheduler.pop(), None);
+    }
+}
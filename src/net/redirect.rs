@@ -0,0 +1,305 @@
+//! Redirects followed by the engine rather than by the HTTP client.
+//!
+//! Requests sent through a [`ConnectionPool`](crate::net::ConnectionPool) follow redirects one
+//! hop at a time, so that the `Set-Cookie` headers of every `3xx` response reach the cookie
+//! jar and each next hop carries the cookies that apply to it. The hops are kept in
+//! [`Response::redirects`](crate::net::Response::redirects).
+use crate::engine::cookies::{
+    allowed_cookies, CookieContext, CookieJarHandle, CookiePolicyHandle, CookieRequest,
+};
+use crate::engine::storage::FirstPartySets;
+use crate::net::MultipartForm;
+use http::header::{COOKIE, LOCATION, SET_COOKIE};
+use http::{HeaderMap, HeaderValue};
+use std::sync::Arc;
+use url::Url;
+
+/// Most redirects a request follows, the same limit as the HTTP client's default
+pub(crate) const MAX_REDIRECTS: usize = 10;
+
+/// A redirect that a request followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// URL that answered with the redirect
+    pub from: Url,
+    /// URL the request moved on to
+    pub to: Url,
+    /// Status code of the redirect, such as 302
+    pub status: u16,
+    /// Names of the cookies the redirect response set
+    pub cookies: Vec<String>,
+}
+
+/// The cookie jar a request reads and fills at each hop, and how the request relates to the
+/// page that made it.
+#[derive(Clone)]
+pub(crate) struct RequestCookies {
+    /// Jar the cookies are read from and stored in
+    pub jar: CookieJarHandle,
+    /// Page that made the request; `None` when the user started it
+    pub initiator: Option<Url>,
+    /// Whether the request is a top-level navigation rather than for a subresource
+    pub navigation: bool,
+    /// Related sites, whose requests to each other count as same-site
+    pub related: Arc<FirstPartySets>,
+    /// Decides which of the cookies that responses set are stored
+    pub policy: Option<CookiePolicyHandle>,
+}
+
+impl RequestCookies {
+    /// Returns the `Cookie` header to send to `url`, for a request with a safe method or not.
+    pub fn header(&self, url: &Url, safe_method: bool) -> Option<HeaderValue> {
+        let context = match (&self.initiator, self.navigation) {
+            (Some(other), _) if self.related(url, other) => CookieContext::SameSite,
+            (initiator, true) => {
+                CookieContext::for_navigation(url, initiator.as_ref(), safe_method)
+            }
+            (Some(document), false) => CookieContext::for_subresource(url, document),
+            (None, false) => CookieContext::CrossSite,
+        };
+        let cookies = self.jar.read().ok()?.get_request_cookies_in(url, context)?;
+        HeaderValue::from_str(&cookies).ok()
+    }
+
+    /// Stores the cookies of a response from `url`, and returns the names of the cookies set.
+    pub fn store(&self, url: &Url, headers: &HeaderMap) -> Vec<String> {
+        let allowed;
+        let headers = match &self.policy {
+            Some(policy) => {
+                allowed = allowed_cookies(policy.as_ref(), &self.cookie_request(url), headers);
+                &allowed
+            }
+            None => headers,
+        };
+        let names = headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|h| h.to_str().ok()?.split_once('='))
+            .map(|(name, _)| name.trim().to_string())
+            .collect::<Vec<_>>();
+        if !names.is_empty() {
+            if let Ok(mut jar) = self.jar.write() {
+                jar.store_response_cookies(url, headers);
+            }
+        }
+        names
+    }
+
+    /// Describes the response from `url` to the cookie policy. Subresources of another site
+    /// than their document are third-party; navigations never are.
+    fn cookie_request<'a>(&'a self, url: &'a Url) -> CookieRequest<'a> {
+        let top_level = if self.navigation {
+            Some(url)
+        } else {
+            self.initiator.as_ref()
+        };
+        CookieRequest {
+            url,
+            top_level,
+            third_party: top_level.is_some_and(|page| {
+                CookieContext::for_subresource(url, page) == CookieContext::CrossSite
+                    && !self.related(url, page)
+            }),
+        }
+    }
+
+    /// Whether `a` and `b` are on related sites of the same first-party set
+    fn related(&self, a: &Url, b: &Url) -> bool {
+        match (a.host_str(), b.host_str()) {
+            (Some(a), Some(b)) => self.related.related(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// A request the pool sends again for every redirect it follows.
+pub(crate) enum PooledRequest {
+    Get(Url),
+    /// `application/x-www-form-urlencoded` POST
+    Form(Url, Vec<u8>),
+    /// `multipart/form-data` POST, read from disk again for each hop
+    Multipart(Url, MultipartForm),
+}
+
+impl PooledRequest {
+    pub fn url(&self) -> &Url {
+        match self {
+            PooledRequest::Get(url)
+            | PooledRequest::Form(url, _)
+            | PooledRequest::Multipart(url, _) => url,
+        }
+    }
+
+    /// Whether the request is a GET, which `SameSite=Lax` cookies go along with
+    pub fn is_safe(&self) -> bool {
+        matches!(self, PooledRequest::Get(_))
+    }
+
+    /// Builds the request for `client`, with `headers` and the `Cookie` header of this hop.
+    pub fn build(
+        &self,
+        client: &reqwest::Client,
+        mut headers: HeaderMap,
+        cookies: Option<&RequestCookies>,
+    ) -> reqwest::RequestBuilder {
+        headers.remove(COOKIE);
+        if let Some(value) = cookies.and_then(|c| c.header(self.url(), self.is_safe())) {
+            headers.insert(COOKIE, value);
+        }
+        match self {
+            PooledRequest::Get(url) => client.get(url.clone()).headers(headers),
+            PooledRequest::Form(url, body) => client
+                .post(url.clone())
+                .headers(headers)
+                .header(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(body.clone()),
+            PooledRequest::Multipart(url, form) => form
+                .clone()
+                .attach(client.post(url.clone()).headers(headers)),
+        }
+    }
+
+    /// Returns the request for the next hop after a redirect with `status` to `to`. Only 307
+    /// and 308 keep the method and body; the others continue with a GET.
+    pub fn redirect(self, status: u16, to: Url) -> Self {
+        match (self, status) {
+            (PooledRequest::Form(_, body), 307 | 308) => PooledRequest::Form(to, body),
+            (PooledRequest::Multipart(_, form), 307 | 308) => PooledRequest::Multipart(to, form),
+            _ => PooledRequest::Get(to),
+        }
+    }
+}
+
+/// Returns where a response redirects to, or `None` when it is not a redirect.
+pub(crate) fn redirect_target(url: &Url, status: u16, headers: &HeaderMap) -> Option<Url> {
+    if !matches!(status, 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = headers.get(LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab, serve_redirect_with_cookie, tick_until};
+    use crate::{EngineCommand, EngineNotification, GosubEngine};
+    use std::time::Duration;
+
+    #[test]
+    fn only_307_and_308_keep_the_method() {
+        let from = Url::parse("https://a.test/form").unwrap();
+        let to = Url::parse("https://a.test/done").unwrap();
+        let post = || PooledRequest::Form(from.clone(), b"q=1".to_vec());
+
+        assert!(post().redirect(303, to.clone()).is_safe());
+        assert!(post().redirect(302, to.clone()).is_safe());
+        let kept = post().redirect(307, to.clone());
+        assert!(matches!(&kept, PooledRequest::Form(url, body) if *url == to && body == b"q=1"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, "../other?x=1".parse().unwrap());
+        assert_eq!(
+            redirect_target(&from, 301, &headers).unwrap().as_str(),
+            "https://a.test/other?x=1"
+        );
+        assert_eq!(redirect_target(&from, 200, &headers), None);
+        assert_eq!(redirect_target(&from, 302, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn related_sites_send_strict_cookies() {
+        use crate::engine::cookies::{CookieJar, DefaultCookieJar};
+        use std::sync::RwLock;
+
+        let sso = Url::parse("https://login.corp-sso.test/").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(SET_COOKIE, "sid=1; SameSite=Strict".parse().unwrap());
+        let mut jar = DefaultCookieJar::new();
+        jar.store_response_cookies(&sso, &headers);
+
+        let cookies = |initiator: &str| RequestCookies {
+            jar: Arc::new(RwLock::new(jar.clone())),
+            initiator: Some(Url::parse(initiator).unwrap()),
+            navigation: false,
+            related: Arc::new(FirstPartySets::new().with_set("corp.test", ["corp-sso.test"])),
+            policy: None,
+        };
+        assert_eq!(
+            cookies("https://intranet.corp.test/").header(&sso, true),
+            Some(HeaderValue::from_static("sid=1"))
+        );
+        assert_eq!(cookies("https://other.test/").header(&sso, true), None);
+    }
+
+    #[test]
+    fn cookies_set_by_redirects_are_stored_and_sent_on() {
+        let (port, requests) = serve_redirect_with_cookie();
+        let base = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+
+        let (mut engine, zone_id, tab_id) = engine_with_tab(None);
+        let rx = engine.subscribe_notifications();
+        let load = |engine: &mut GosubEngine| {
+            let mut redirects = Vec::new();
+            let loaded = tick_until(engine, &rx, |n| match n {
+                EngineNotification::Redirected {
+                    from,
+                    to,
+                    status,
+                    cookies,
+                    ..
+                } => {
+                    redirects.push((from.path().to_string(), to, status, cookies));
+                    None
+                }
+                EngineNotification::PageLoaded { .. } => Some(()),
+                _ => None,
+            });
+            assert!(loaded.is_some(), "page did not load");
+            let request = requests.recv_timeout(Duration::from_secs(1)).unwrap();
+            (request, redirects)
+        };
+
+        engine
+            .execute_command(
+                tab_id,
+                EngineCommand::Navigate(base.join("/start").unwrap()),
+            )
+            .unwrap();
+        let ((line, cookie), redirects) = load(&mut engine);
+        assert!(line.starts_with("GET /next "));
+        assert_eq!(cookie.as_deref(), Some("hop=1"));
+        assert_eq!(
+            redirects,
+            vec![(
+                "/start".to_string(),
+                base.join("/next").unwrap(),
+                302,
+                vec!["hop".to_string()]
+            )]
+        );
+        let zone = engine.get_zone_mut(zone_id).unwrap();
+        let jar = zone.lock().unwrap().cookie_jar.clone();
+        let next = base.join("/next").unwrap();
+        assert_eq!(
+            jar.read().unwrap().get_request_cookies(&next).as_deref(),
+            Some("hop=1")
+        );
+
+        // A 303 after a POST continues with a GET
+        let tab = engine.get_tab(tab_id).unwrap();
+        {
+            let mut tab = tab.lock().unwrap();
+            tab.context
+                .set_raw_html("<form method=post action=/form><input name=q value=1></form>");
+            tab.submit_form(0);
+        }
+        let ((line, cookie), redirects) = load(&mut engine);
+        assert!(line.starts_with("GET /next "));
+        assert_eq!(cookie.as_deref(), Some("hop=1; posted=1"));
+        assert_eq!(redirects[0].2, 303);
+    }
+}
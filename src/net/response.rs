@@ -17,7 +17,7 @@ use http::HeaderMap;
 ///
 /// All fields reflect the **received** response as-is; no additional parsing
 /// or transformation is performed by this type.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Response {
     /// Final URL of the response (after redirects, if any).
     pub url: url::Url,
@@ -38,4 +38,10 @@ pub struct Response {
     /// Convert to text with `String::from_utf8_lossy`, or parse as binary/JSON
     /// depending on the `Content-Type`.
     pub body: Vec<u8>,
+
+    /// Redirects followed before this response, in order.
+    ///
+    /// Only filled in for requests that the engine follows hop by hop, such as page loads;
+    /// the free fetch functions leave redirects to the HTTP client.
+    pub redirects: Vec<crate::net::Redirect>,
 }
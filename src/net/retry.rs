@@ -0,0 +1,136 @@
+//! Retrying loads that failed on a flaky network.
+//!
+//! A dropped connection or a failed DNS lookup often succeeds a moment later, especially on
+//! mobile networks. A [`RetryPolicy`] lets a zone retry such failures with exponential
+//! backoff. Only GET navigations are retried, as repeating them has no side effects; form
+//! posts and timeouts fail right away.
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// How often, and how far apart, a failed load is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Time before the first retry; every further retry waits twice as long
+    pub initial_backoff: Duration,
+    /// Longest time to wait before a retry
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the time to wait before retry number `retry`, counting from 1, or `None` when
+    /// the policy allows no more retries.
+    pub fn backoff(&self, retry: u32) -> Option<Duration> {
+        if retry == 0 || retry > self.max_retries {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(retry - 1);
+        Some(
+            self.initial_backoff
+                .saturating_mul(factor)
+                .min(self.max_backoff),
+        )
+    }
+}
+
+/// Returns true when `err` is a failure that may go away on its own, such as a refused or
+/// reset connection or a failed DNS lookup.
+pub fn is_transient(err: &reqwest::Error) -> bool {
+    if err.is_timeout() {
+        return false;
+    }
+    if err.is_connect() {
+        return true;
+    }
+    let mut source = std::error::Error::source(err);
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>() {
+            return matches!(
+                io.kind(),
+                ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+            );
+        }
+        source = e.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_support::{engine_with_tab_in, tick_until};
+    use crate::zone::ZoneConfig;
+    use crate::{EngineCommand, EngineNotification};
+    use url::Url;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        let delays: Vec<_> = (1..=6).map(|retry| policy.backoff(retry)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .map(|ms| Some(Duration::from_millis(ms)))
+                .into_iter()
+                .chain([None])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(policy.backoff(0), None);
+    }
+
+    #[test]
+    fn refused_connections_are_retried_with_backoff() {
+        // Nothing listens on the port once the listener is dropped
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let policy = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let config = ZoneConfig::builder()
+            .retry_policy(Some(policy))
+            .build()
+            .unwrap();
+        let (mut engine, _, tab_id) = engine_with_tab_in(None, |zone| zone.config(config));
+        let rx = engine.subscribe_notifications();
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        engine
+            .execute_command(tab_id, EngineCommand::Navigate(url))
+            .unwrap();
+
+        let mut retries = 0;
+        let failed = tick_until(&mut engine, &rx, |n| match n {
+            EngineNotification::Warning { message, .. } if message.contains("retrying") => {
+                retries += 1;
+                None
+            }
+            EngineNotification::LoadFailed { .. } => Some(()),
+            _ => None,
+        });
+        assert!(failed.is_some());
+        assert_eq!(retries, 2);
+    }
+}
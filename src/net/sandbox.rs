@@ -0,0 +1,66 @@
+use crate::config::SandboxMode;
+use std::fmt;
+use url::Url;
+
+/// A request that was refused because of the engine's [`SandboxMode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxViolation {
+    /// The URL that was refused.
+    pub url: Url,
+    /// The sandbox mode that refused it.
+    pub mode: SandboxMode,
+}
+
+impl fmt::Display for SandboxViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sandbox ({:?}) blocked {}: scheme '{}' is not allowed",
+            self.mode,
+            self.url,
+            self.url.scheme()
+        )
+    }
+}
+
+impl std::error::Error for SandboxViolation {}
+
+/// Checks whether `url` may be loaded under `mode`.
+///
+/// # Errors
+/// Returns a [`SandboxViolation`] when the URL scheme is not allowed.
+pub fn check_sandbox(url: &Url, mode: SandboxMode) -> Result<(), SandboxViolation> {
+    if mode.allows_scheme(url.scheme()) {
+        Ok(())
+    } else {
+        Err(SandboxViolation {
+            url: url.clone(),
+            mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn strict_only_allows_web_schemes() {
+        assert!(check_sandbox(&u("https://example.com/"), SandboxMode::Strict).is_ok());
+        assert!(check_sandbox(&u("http://example.com/"), SandboxMode::Strict).is_ok());
+        assert!(check_sandbox(&u("file:///etc/passwd"), SandboxMode::Strict).is_err());
+        assert!(check_sandbox(&u("data:text/plain,hi"), SandboxMode::Strict).is_err());
+        assert!(check_sandbox(&u("myapp://settings"), SandboxMode::Strict).is_err());
+    }
+
+    #[test]
+    fn balanced_blocks_custom_schemes_and_off_blocks_nothing() {
+        assert!(check_sandbox(&u("file:///tmp/a.html"), SandboxMode::Balanced).is_ok());
+        assert!(check_sandbox(&u("myapp://settings"), SandboxMode::Balanced).is_err());
+        assert!(check_sandbox(&u("myapp://settings"), SandboxMode::Off).is_ok());
+    }
+}
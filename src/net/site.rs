@@ -0,0 +1,55 @@
+//! Sites: the registrable domains that cookies, storage partitions and third-party checks
+//! are scoped to.
+//!
+//! The registrable domain of a host is its public suffix plus one label, such as
+//! `example.co.uk` for `www.example.co.uk`. Public suffixes come from a copy of the
+//! [Public Suffix List](https://publicsuffix.org/) that is built into the engine; suffixes
+//! that are not on the list count as a single label.
+use publicsuffix::{List, Psl};
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+static LIST: LazyLock<List> = LazyLock::new(|| {
+    include_str!("public_suffix_list.dat")
+        .parse()
+        .expect("the built-in public suffix list parses")
+});
+
+/// Returns the registrable domain of `host`. IP addresses, and hosts that are a public suffix
+/// themselves (such as `localhost` or `co.uk`), are their own site.
+pub(crate) fn site(host: &str) -> &str {
+    registrable_domain(host).unwrap_or(host)
+}
+
+/// Returns the registrable domain of `host`, or `None` for IP addresses and public suffixes.
+pub(crate) fn registrable_domain(host: &str) -> Option<&str> {
+    if host.starts_with('[') || host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    let domain = LIST.domain(host.as_bytes())?;
+    Some(&host[host.len() - domain.as_bytes().len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sites_follow_the_public_suffix_list() {
+        assert_eq!(site("www.example.com"), "example.com");
+        assert_eq!(site("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(site("example.co.uk"), "example.co.uk");
+        assert_eq!(site("user.github.io"), "user.github.io");
+        assert_eq!(site("www.ads.test"), "ads.test");
+        assert_eq!(site("co.uk"), "co.uk");
+        assert_eq!(site("localhost"), "localhost");
+        assert_eq!(registrable_domain("co.uk"), None);
+    }
+
+    #[test]
+    fn ip_addresses_are_their_own_site() {
+        assert_eq!(site("127.0.0.1"), "127.0.0.1");
+        assert_eq!(site("10.0.0.1"), "10.0.0.1");
+        assert_eq!(site("[::1]"), "[::1]");
+    }
+}
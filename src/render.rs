@@ -28,7 +28,8 @@
 //!
 //! - `backend_cairo` → CPU raster via Cairo (`render::backends::cairo`)
 //! - `backend_vello` → GPU (wgpu) via Vello (`render::backends::vello`)
-//! - always available: `render::backends::null` (no-op, useful for tests)
+//! - always available: `render::backends::null` (no-op, useful for tests) and
+//!   `render::backends::software` (dependency-free CPU raster for headless use)
 //!
 //! Because these modules are feature-gated, this documentation refers to them
 //! using inline code (not links) to avoid broken intra-doc links when a feature
@@ -36,9 +37,11 @@
 //!
 //! ## Viewport
 //!
-//! A [`Viewport`] specifies `(x, y, width, height)` in pixels to define the
-//! visible area that a backend should paint. Hosts typically update the
-//! viewport on resize or scrolling.
+//! A [`Viewport`] specifies `(x, y, width, height)` to define the visible area that a
+//! backend should paint: the scroll offset in document coordinates and the surface size in
+//! device pixels. Hosts typically update the viewport on resize or scrolling, and report the
+//! device scale of the screen with `EngineEvent::ScaleFactorChanged`. Backends paint with a
+//! [`RenderRequest`], which combines both into one transform.
 //!
 //! ```no_run
 //! use gosub_engine::render::Viewport;
@@ -54,7 +57,12 @@
 //! The engine emits a display list (re-exported from this module) containing
 //! shapes, images, text runs, and state. Backends convert this list into the
 //! target API’s primitives. Hosts don’t usually touch the display list
-//! directly; they drive tabs and submit frames to the compositor.
+//! directly; they drive tabs and submit frames to the compositor. Hosts that composite in
+//! another process can forward it with `RenderList::to_bytes`, and hosts that paint display
+//! items themselves can redraw only what `RenderList::diff` reports as changed.
+//!
+//! A display list can also be printed: `RenderList::to_pdf` cuts it into pages as set up by
+//! [`PrintToPdf`], with margins, scale, page ranges and header and footer templates.
 //!
 //! ## Compositing
 //!
@@ -63,6 +71,9 @@
 //! host can present in its UI. This keeps the engine independent from any
 //! specific windowing toolkit.
 //!
+//! Immediate-mode UIs that redraw every frame can pull frames instead: see
+//! `GosubEngine::collect_frames` and [`FrameCollector`].
+//!
 //! ## Typical flow
 //!
 //! ```no_run
@@ -107,19 +118,36 @@ pub mod backend;
 /// Rendering backends for the Gosub engine.
 pub mod backends {
     pub mod null;
+    /// Dependency-free CPU rendering backend
+    pub mod software;
     /// Cairo rendering backend
     #[cfg(feature = "backend_cairo")]
     pub mod cairo;
     /// Vello rendering backend
     #[cfg(feature = "backend_vello")]
     pub mod vello;
+
+    mod auto;
+    pub use auto::{auto, AutoBackend, BackendKind, SkippedBackend};
 }
 
 mod render_list;
 pub use render_list::*;
 
+mod diff;
+pub use diff::{DamageRect, ItemChange, RenderListDiff};
+
+mod serialize;
+pub use serialize::{RenderListDecodeError, RENDER_LIST_FORMAT_VERSION};
+
 mod viewport;
-pub use viewport::Viewport;
+pub use viewport::{RenderRequest, Viewport};
+
+mod pdf;
+pub use pdf::{parse_page_ranges, PageMargins, PrintError, PrintToPdf};
+
+mod text;
+pub use text::{FontSettings, TextHinting, TextOptions, DEFAULT_FONT_SIZE};
 
 mod compositor;
-pub use compositor::{DefaultCompositor};
+pub use compositor::{DefaultCompositor, Frame, FrameCollector};
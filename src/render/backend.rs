@@ -17,12 +17,14 @@
 //! Backends differ in how they manage memory, synchronization, and ownership.
 //! Some are CPU-bound (Cairo), others GPU-accelerated (Vello, Skia, OpenGL).
 
+use crate::engine::config::AntialiasingMode;
 use crate::engine::BrowsingContext;
 use crate::render::Viewport;
 use std::{any::Any, ptr::NonNull};
 
 /// Size of a rendering surface in pixels.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
 pub struct SurfaceSize {
     /// Width of the surface in pixels.
     pub width: u32,
@@ -54,6 +56,7 @@ pub enum PresentMode {
 
 /// Pixel format for surfaces and snapshots.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
 pub enum PixelFormat {
     /// 32-bit ARGB with premultiplied alpha.
     PreMulArgb32,
@@ -246,6 +249,23 @@ pub trait RenderBackend {
         surface: &mut dyn ErasedSurface,
     ) -> anyhow::Result<()>;
 
+    /// Render the browsing context to a surface that still holds the previous frame of the
+    /// same render list, with the viewport scrolled by `(dx, dy)` device pixels since.
+    ///
+    /// Backends that can move the existing pixels and paint only the newly exposed strips do
+    /// so and return `Ok(true)`. The default renders the whole frame and returns `Ok(false)`.
+    fn render_scrolled(
+        &mut self,
+        context: &mut BrowsingContext,
+        surface: &mut dyn ErasedSurface,
+        dx: i32,
+        dy: i32,
+    ) -> anyhow::Result<bool> {
+        let _ = (dx, dy);
+        self.render(context, surface)?;
+        Ok(false)
+    }
+
     /// Generate a small RGBA8 snapshot of the surface, suitable for thumbnails or previews.
     fn snapshot(
         &mut self,
@@ -255,6 +275,32 @@ pub trait RenderBackend {
 
     /// Returns an external handle for the surface, if supported.
     fn external_handle(&mut self, surface: &mut dyn ErasedSurface) -> Option<ExternalHandle>;
+
+    /// Switches to the anti-aliasing `mode` from the engine's
+    /// [`GpuOptions`](crate::config::GpuOptions) and returns the mode used from now on, which
+    /// differs when the device does not support `mode`.
+    ///
+    /// The default suits backends that do not multisample: it ignores the setting and
+    /// returns `mode`.
+    fn set_antialiasing(&mut self, mode: AntialiasingMode) -> AntialiasingMode {
+        mode
+    }
+
+    /// Returns true when the device the backend renders with was lost, for instance after a
+    /// driver reset or a suspend. The engine then calls [`RenderBackend::recover`] before the
+    /// next frame.
+    ///
+    /// The default suits backends without a device that can be lost and returns false.
+    fn device_lost(&self) -> bool {
+        false
+    }
+
+    /// Sets the backend up on a new device after [`RenderBackend::device_lost`]. Surfaces
+    /// created on the old device cannot be used anymore; the engine drops them and creates
+    /// new ones.
+    fn recover(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 /// Interface for compositors to receive frames from backends.
@@ -265,4 +311,16 @@ pub trait RenderBackend {
 pub trait CompositorSink {
     /// Submit a rendered frame for the given tab.
     fn submit_frame(&mut self, tab: crate::tab::TabId, handle: ExternalHandle);
+
+    /// Submit a rendered frame for one of the tab's auxiliary viewports (see
+    /// [`EngineCommand::AddAuxViewport`](crate::EngineCommand::AddAuxViewport)).
+    ///
+    /// The default implementation drops the frame.
+    fn submit_aux_frame(
+        &mut self,
+        _tab: crate::tab::TabId,
+        _viewport: crate::tab::AuxViewportId,
+        _handle: ExternalHandle,
+    ) {
+    }
 }
@@ -0,0 +1,147 @@
+//! Picking a render backend at runtime.
+//!
+//! [`auto`] tries the backends from the most to the least capable and returns the first one
+//! that works on this machine: Vello on the GPU, Cairo, the software rasterizer and finally
+//! the null backend. Backends that were not compiled in are skipped as well, and the result
+//! tells why each skipped backend was passed over.
+use crate::config::GpuOptions;
+use crate::render::backend::RenderBackend;
+#[cfg(feature = "backend_cairo")]
+use crate::render::backend::{PresentMode, SurfaceSize};
+use crate::render::backends::null::NullBackend;
+use crate::render::backends::software::SoftwareBackend;
+#[cfg(feature = "backend_vello")]
+use crate::render::backends::vello::{StandaloneWgpuContext, VelloBackend};
+#[cfg(feature = "backend_vello")]
+use std::sync::Arc;
+
+/// A backend that [`auto`] can pick, from the most to the least preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BackendKind {
+    /// GPU rendering with Vello
+    Vello,
+    /// CPU rendering with Cairo
+    Cairo,
+    /// The dependency-free CPU rasterizer
+    Software,
+    /// No rendering at all
+    Null,
+}
+
+/// A backend that [`auto`] passed over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedBackend {
+    /// The backend
+    pub kind: BackendKind,
+    /// Why it was not used
+    pub reason: String,
+}
+
+/// The backend picked by [`auto`].
+pub struct AutoBackend {
+    /// The backend, ready to hand to [`GosubEngine::new`](crate::GosubEngine::new)
+    pub backend: Box<dyn RenderBackend>,
+    /// Which backend it is
+    pub kind: BackendKind,
+    /// The more capable backends that were tried first, in order
+    pub skipped: Vec<SkippedBackend>,
+    /// For the Vello backend, the GPU device it renders with; frames are textures of this
+    /// device. `None` for the other backends.
+    #[cfg(feature = "backend_vello")]
+    pub wgpu_context: Option<Arc<StandaloneWgpuContext>>,
+}
+
+/// Returns the most capable backend that works on this machine, using the GPU that
+/// `gpu.prefer_low_power` asks for. The null backend always works, so this cannot fail.
+///
+/// ```
+/// use gosub_engine::config::GpuOptions;
+/// use gosub_engine::render::backends::{auto, BackendKind};
+///
+/// let gpu = GpuOptions { prefer_low_power: true, msaa_samples: 1, vsync: true, use_srgb_framebuffer: true };
+/// let picked = auto(&gpu);
+/// for skipped in &picked.skipped {
+///     println!("not using {:?}: {}", skipped.kind, skipped.reason);
+/// }
+/// assert!(picked.skipped.iter().all(|s| s.kind < picked.kind));
+/// let engine = gosub_engine::GosubEngine::new(None, picked.backend);
+/// ```
+pub fn auto(gpu: &GpuOptions) -> AutoBackend {
+    let mut skipped = Vec::new();
+
+    #[cfg(feature = "backend_vello")]
+    {
+        let vello = StandaloneWgpuContext::new(gpu.prefer_low_power).and_then(|context| {
+            let context = Arc::new(context);
+            Ok((VelloBackend::new(context.clone())?, context))
+        });
+        match vello {
+            Ok((backend, context)) => {
+                let mut choice = picked(Box::new(backend), BackendKind::Vello, skipped);
+                choice.wgpu_context = Some(context);
+                return choice;
+            }
+            Err(e) => skipped.push(skip(BackendKind::Vello, e)),
+        }
+    }
+    #[cfg(not(feature = "backend_vello"))]
+    {
+        let _ = gpu;
+        skipped.push(skip(BackendKind::Vello, not_compiled_in("backend_vello")));
+    }
+
+    #[cfg(feature = "backend_cairo")]
+    {
+        let backend = crate::render::backends::cairo::CairoBackend::new();
+        match probe(&backend) {
+            Ok(()) => return picked(Box::new(backend), BackendKind::Cairo, skipped),
+            Err(e) => skipped.push(skip(BackendKind::Cairo, e)),
+        }
+    }
+    #[cfg(not(feature = "backend_cairo"))]
+    skipped.push(skip(BackendKind::Cairo, not_compiled_in("backend_cairo")));
+
+    match SoftwareBackend::new() {
+        Ok(backend) => return picked(Box::new(backend), BackendKind::Software, skipped),
+        Err(e) => skipped.push(skip(BackendKind::Software, e)),
+    }
+
+    let backend = NullBackend::new().expect("the null backend cannot fail");
+    picked(Box::new(backend), BackendKind::Null, skipped)
+}
+
+fn picked(
+    backend: Box<dyn RenderBackend>,
+    kind: BackendKind,
+    skipped: Vec<SkippedBackend>,
+) -> AutoBackend {
+    AutoBackend {
+        backend,
+        kind,
+        skipped,
+        #[cfg(feature = "backend_vello")]
+        wgpu_context: None,
+    }
+}
+
+fn skip(kind: BackendKind, reason: impl ToString) -> SkippedBackend {
+    SkippedBackend {
+        kind,
+        reason: reason.to_string(),
+    }
+}
+
+/// Checks that a CPU backend can create a surface.
+#[cfg(feature = "backend_cairo")]
+fn probe(backend: &dyn RenderBackend) -> anyhow::Result<()> {
+    let size = SurfaceSize {
+        width: 1,
+        height: 1,
+    };
+    backend.create_surface(size, PresentMode::Fifo).map(drop)
+}
+
+#[cfg(not(all(feature = "backend_vello", feature = "backend_cairo")))]
+fn not_compiled_in(feature: &str) -> String {
+    format!("not compiled in (enable the `{feature}` feature)")
+}
@@ -2,7 +2,7 @@ use crate::engine::BrowsingContext;
 use crate::render::backend::{
     ErasedSurface, ExternalHandle, PixelFormat, PresentMode, RenderBackend, RgbaImage, SurfaceSize,
 };
-use crate::render::DisplayItem;
+use crate::render::{DisplayItem, TextHinting};
 use anyhow::{anyhow, Result};
 use std::any::Any;
 
@@ -35,10 +35,9 @@ impl RenderBackend for CairoBackend {
             .downcast_mut::<CairoSurface>()
             .expect("CairoBackend used with non-Cairo surface");
 
-        // Viewport offset. We must take this into account when rendering items.
-        let vp = ctx.viewport();
-        let offset_x = vp.x as f64;
-        let offset_y = vp.y as f64;
+        // Items are in document coordinates; the request maps them onto the surface
+        let [xx, yx, xy, yy, x0, y0] = ctx.render_request().transform();
+        let text_options = ctx.text_options();
 
         {
             // Get the cairo context (CR) from the surface.
@@ -49,7 +48,21 @@ impl RenderBackend for CairoBackend {
             cr.clip();
 
             let _ = cr.save();
-            cr.translate(-offset_x, -offset_y);
+            cr.transform(cairo::Matrix::new(xx, yx, xy, yy, x0, y0));
+
+            let mut font_options = cairo::FontOptions::new()?;
+            font_options.set_hint_style(match text_options.hinting {
+                TextHinting::None => cairo::HintStyle::None,
+                TextHinting::Slight => cairo::HintStyle::Slight,
+                TextHinting::Full => cairo::HintStyle::Full,
+            });
+            // Hinted metrics round every advance, which rules out subpixel positioning
+            font_options.set_hint_metrics(if text_options.subpixel_positioning {
+                cairo::HintMetrics::Off
+            } else {
+                cairo::HintMetrics::On
+            });
+            cr.set_font_options(&font_options);
 
             for item in ctx.render_list().items.iter() {
                 match item {
@@ -81,7 +94,9 @@ impl RenderBackend for CairoBackend {
                         y,
                         text,
                         size,
+                        font_family,
                         color,
+                        ..
                     } => {
                         // Draw text at the specified position with the specified size and color.
                         cr.set_source_rgba(
@@ -91,12 +106,18 @@ impl RenderBackend for CairoBackend {
                             color.a as f64,
                         );
                         cr.select_font_face(
-                            "Sans",
+                            font_family.as_deref().unwrap_or("Sans"),
                             cairo::FontSlant::Normal,
                             cairo::FontWeight::Normal,
                         );
                         cr.set_font_size(*size as f64);
-                        cr.move_to(*x as f64, *y as f64);
+                        // Snap on the surface, where the pixels are
+                        let (dx, dy) = cr.user_to_device(*x as f64, *y as f64);
+                        let (x, y) = cr.device_to_user(
+                            text_options.snap(dx as f32) as f64,
+                            text_options.snap(dy as f32) as f64,
+                        )?;
+                        cr.move_to(x, y);
                         cr.show_text(text)?;
                     }
                 }
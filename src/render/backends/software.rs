@@ -0,0 +1,379 @@
+//! CPU rasterizer without external dependencies.
+//!
+//! The software backend paints the render list into an RGBA8 buffer and hands frames to the
+//! compositor as [`ExternalHandle::CpuPixelsOwned`]. It exists for headless use: thumbnails on
+//! machines without a GPU, and pixel tests (see the `testing` module) that must give the same
+//! result on every machine.
+//!
+//! Rectangles are filled with source-over blending. Text is not shaped: every non-whitespace
+//! character of a text run is painted as a solid box of roughly the size of a glyph. That keeps
+//! the output independent of installed fonts while still showing where text ends up.
+use crate::engine::BrowsingContext;
+use crate::render::backend::{
+    ErasedSurface, ExternalHandle, PixelFormat, PresentMode, RenderBackend, RgbaImage, SurfaceSize,
+};
+use crate::render::{Color, DisplayItem};
+use anyhow::{anyhow, Result};
+use std::any::Any;
+
+/// Width of a character box, relative to the font size
+const GLYPH_ADVANCE: f32 = 0.5;
+/// Height of a character box above the baseline, relative to the font size
+const GLYPH_ASCENT: f32 = 0.7;
+
+/// Backend that rasterizes on the CPU.
+pub struct SoftwareBackend;
+
+impl SoftwareBackend {
+    /// Creates a new instance of the software backend.
+    pub fn new() -> Result<Self> {
+        Ok(Self)
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn create_surface(
+        &self,
+        size: SurfaceSize,
+        _present: PresentMode,
+    ) -> Result<Box<dyn ErasedSurface>> {
+        Ok(Box::new(SoftwareSurface::new(size)))
+    }
+
+    fn render(&mut self, ctx: &mut BrowsingContext, surface: &mut dyn ErasedSurface) -> Result<()> {
+        let s = downcast(surface)?;
+        let full = s.bounds();
+        paint(ctx, s, full);
+        s.frame_id = s.frame_id.wrapping_add(1);
+        Ok(())
+    }
+
+    fn render_scrolled(
+        &mut self,
+        ctx: &mut BrowsingContext,
+        surface: &mut dyn ErasedSurface,
+        dx: i32,
+        dy: i32,
+    ) -> Result<bool> {
+        let s = downcast(surface)?;
+        let (w, h) = (s.size.width as i32, s.size.height as i32);
+        if dx.abs() >= w || dy.abs() >= h {
+            // Nothing of the previous frame stays visible
+            let full = s.bounds();
+            paint(ctx, s, full);
+            s.frame_id = s.frame_id.wrapping_add(1);
+            return Ok(false);
+        }
+
+        s.shift(dx, dy);
+
+        // Paint the strips that scrolled into view
+        let (w, h) = (w as u32, h as u32);
+        let rows = if dy > 0 {
+            Some((h - dy as u32, h))
+        } else if dy < 0 {
+            Some((0, (-dy) as u32))
+        } else {
+            None
+        };
+        let cols = if dx > 0 {
+            Some((w - dx as u32, w))
+        } else if dx < 0 {
+            Some((0, (-dx) as u32))
+        } else {
+            None
+        };
+        if let Some((y0, y1)) = rows {
+            paint(
+                ctx,
+                s,
+                Clip {
+                    x0: 0,
+                    y0,
+                    x1: w,
+                    y1,
+                },
+            );
+        }
+        if let Some((x0, x1)) = cols {
+            paint(
+                ctx,
+                s,
+                Clip {
+                    x0,
+                    y0: 0,
+                    x1,
+                    y1: h,
+                },
+            );
+        }
+
+        s.frame_id = s.frame_id.wrapping_add(1);
+        Ok(true)
+    }
+
+    fn snapshot(&mut self, surface: &mut dyn ErasedSurface, max_dim: u32) -> Result<RgbaImage> {
+        let s = downcast(surface)?;
+
+        // Nearest-neighbour downscale, keeping the aspect ratio
+        let longest = s.size.width.max(s.size.height).max(1);
+        let scale = (max_dim as f32 / longest as f32).min(1.0);
+        let width = ((s.size.width as f32 * scale) as u32).max(1);
+        let height = ((s.size.height as f32 * scale) as u32).max(1);
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let src_y = (y as f32 / scale) as u32;
+            for x in 0..width {
+                let src_x = (x as f32 / scale) as u32;
+                let idx = ((src_y * s.size.width + src_x) * 4) as usize;
+                pixels.extend_from_slice(s.pixels.get(idx..idx + 4).unwrap_or(&[0; 4]));
+            }
+        }
+
+        Ok(RgbaImage::from_raw(
+            pixels,
+            width,
+            height,
+            width * 4,
+            PixelFormat::Rgba8,
+        ))
+    }
+
+    fn external_handle(&mut self, surface: &mut dyn ErasedSurface) -> Option<ExternalHandle> {
+        let s = surface.as_any_mut().downcast_mut::<SoftwareSurface>()?;
+
+        Some(ExternalHandle::CpuPixelsOwned {
+            width: s.size.width,
+            height: s.size.height,
+            stride: s.size.width * 4,
+            pixels: s.pixels.clone(),
+            format: PixelFormat::Rgba8,
+        })
+    }
+}
+
+/// Paints the render list into the `clip` area of the surface, leaving the rest untouched.
+fn paint(ctx: &BrowsingContext, s: &mut SoftwareSurface, clip: Clip) {
+    let request = ctx.render_request();
+
+    s.fill_clip(clip, [0; 4]);
+    for item in ctx.render_list().items.iter() {
+        match item {
+            DisplayItem::Clear { color } => s.fill_clip(clip, (*color).into()),
+            DisplayItem::Rect { x, y, w, h, color } => {
+                let (x, y, w, h) = request.to_device_rect(*x, *y, *w, *h);
+                s.fill_rect(clip, x, y, w, h, *color);
+            }
+            DisplayItem::TextRun {
+                x,
+                y,
+                text,
+                size,
+                color,
+                max_width,
+                ..
+            } => {
+                let advance = size * GLYPH_ADVANCE;
+                let ascent = size * GLYPH_ASCENT;
+                let right = max_width.map(|w| x + w).unwrap_or(f32::INFINITY);
+                let mut pen = *x;
+                for ch in text.chars() {
+                    if pen + advance > right {
+                        break;
+                    }
+                    if !ch.is_whitespace() {
+                        // Leave a one pixel gap so neighbouring characters stay apart
+                        let (x, y, w, h) =
+                            request.to_device_rect(pen, y - ascent, advance - 1.0, ascent);
+                        s.fill_rect(clip, x, y, w, h, *color);
+                    }
+                    pen += advance;
+                }
+            }
+        }
+    }
+}
+
+/// Area of a surface that may be painted, in pixels. `x1` and `y1` are exclusive.
+#[derive(Debug, Clone, Copy)]
+struct Clip {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+fn downcast(surface: &mut dyn ErasedSurface) -> Result<&mut SoftwareSurface> {
+    surface
+        .as_any_mut()
+        .downcast_mut::<SoftwareSurface>()
+        .ok_or_else(|| anyhow!("SoftwareBackend used with non-software surface"))
+}
+
+/// RGBA8 pixel buffer the software backend paints into.
+pub struct SoftwareSurface {
+    /// Size of the surface in pixels.
+    pub size: SurfaceSize,
+    /// Pixels, row by row without padding
+    pixels: Vec<u8>,
+    /// Frame ID for the surface, used to track rendering frames.
+    frame_id: u64,
+}
+
+impl SoftwareSurface {
+    /// Creates a transparent surface.
+    pub fn new(size: SurfaceSize) -> Self {
+        Self {
+            size,
+            pixels: vec![0; (size.width * size.height * 4) as usize],
+            frame_id: 0,
+        }
+    }
+
+    /// Returns the pixels of the last rendered frame in RGBA8, row by row without padding.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the whole surface as a clip area.
+    fn bounds(&self) -> Clip {
+        Clip {
+            x0: 0,
+            y0: 0,
+            x1: self.size.width,
+            y1: self.size.height,
+        }
+    }
+
+    /// Moves the pixels as if the viewport moved by `(dx, dy)`: content moves the opposite
+    /// way. The uncovered area keeps stale pixels and has to be painted again.
+    fn shift(&mut self, dx: i32, dy: i32) {
+        let (w, h) = (self.size.width as i32, self.size.height as i32);
+        let row_bytes = (w * 4) as usize;
+        let copy_w = (w - dx.abs()) as usize * 4;
+        let (src_x, dst_x) = if dx >= 0 {
+            (dx as usize * 4, 0)
+        } else {
+            (0, (-dx) as usize * 4)
+        };
+
+        // Walk rows in the direction that never overwrites a row before it was copied
+        let rows: Box<dyn Iterator<Item = i32>> = if dy >= 0 {
+            Box::new(0..h - dy)
+        } else {
+            Box::new((-dy..h).rev())
+        };
+        for dst_y in rows {
+            let src_y = dst_y + dy;
+            let src = src_y as usize * row_bytes + src_x;
+            let dst = dst_y as usize * row_bytes + dst_x;
+            self.pixels.copy_within(src..src + copy_w, dst);
+        }
+    }
+
+    /// Overwrites the clip area with one pixel value.
+    fn fill_clip(&mut self, clip: Clip, px: [u8; 4]) {
+        for py in clip.y0..clip.y1 {
+            let row = (py * self.size.width) as usize * 4;
+            for chunk in self.pixels[row + clip.x0 as usize * 4..row + clip.x1 as usize * 4]
+                .chunks_exact_mut(4)
+            {
+                chunk.copy_from_slice(&px);
+            }
+        }
+    }
+
+    /// Fills a rectangle in surface coordinates, clipped to `clip`.
+    fn fill_rect(&mut self, clip: Clip, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        let clamp_x = |v: f32| (v.round().max(0.0) as u32).clamp(clip.x0, clip.x1);
+        let clamp_y = |v: f32| (v.round().max(0.0) as u32).clamp(clip.y0, clip.y1);
+        let (x0, x1) = (clamp_x(x), clamp_x(x + w));
+        let (y0, y1) = (clamp_y(y), clamp_y(y + h));
+
+        let alpha = color.a.clamp(0.0, 1.0);
+        let src = [color.r, color.g, color.b];
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let idx = ((py * self.size.width + px) * 4) as usize;
+                let dst = &mut self.pixels[idx..idx + 4];
+                for c in 0..3 {
+                    let d = dst[c] as f32 / 255.0;
+                    dst[c] = ((src[c] * alpha + d * (1.0 - alpha)) * 255.0).round() as u8;
+                }
+                let da = dst[3] as f32 / 255.0;
+                dst[3] = ((alpha + da * (1.0 - alpha)) * 255.0).round() as u8;
+            }
+        }
+    }
+}
+
+impl ErasedSurface for SoftwareSurface {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn size(&self) -> SurfaceSize {
+        self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Viewport;
+    use std::sync::Arc;
+
+    #[test]
+    fn scrolled_frames_match_full_renders() {
+        let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
+        let mut ctx = BrowsingContext::new(runtime);
+        let html: String = (0..40).map(|i| format!("line {i} of the page\n")).collect();
+        ctx.set_raw_html(&html);
+        ctx.set_viewport(Viewport::new(0, 0, 160, 120));
+        ctx.rebuild_render_list_if_needed();
+
+        let mut backend = SoftwareBackend::new().unwrap();
+        let size = SurfaceSize {
+            width: 160,
+            height: 120,
+        };
+        let mut scrolled = SoftwareSurface::new(size);
+        backend.render(&mut ctx, &mut scrolled).unwrap();
+
+        for (dx, dy) in [(0, 37), (5, -20), (-5, 0)] {
+            let vp = *ctx.viewport();
+            ctx.set_viewport(Viewport::new(vp.x + dx, vp.y + dy, 160, 120));
+            assert!(backend
+                .render_scrolled(&mut ctx, &mut scrolled, dx, dy)
+                .unwrap());
+
+            let mut full = SoftwareSurface::new(size);
+            backend.render(&mut ctx, &mut full).unwrap();
+            assert!(
+                scrolled.pixels() == full.pixels(),
+                "mismatch after {dx},{dy}"
+            );
+        }
+
+        // At twice the device scale, the same scroll moves the frame twice as far
+        ctx.set_device_scale(2.0);
+        backend.render(&mut ctx, &mut scrolled).unwrap();
+        for (dx, dy) in [(0, 11), (3, -7)] {
+            let vp = *ctx.viewport();
+            ctx.set_viewport(Viewport::new(vp.x + dx, vp.y + dy, 160, 120));
+            assert!(backend
+                .render_scrolled(&mut ctx, &mut scrolled, dx * 2, dy * 2)
+                .unwrap());
+
+            let mut full = SoftwareSurface::new(size);
+            backend.render(&mut ctx, &mut full).unwrap();
+            assert!(
+                scrolled.pixels() == full.pixels(),
+                "mismatch after {dx},{dy} at scale 2"
+            );
+        }
+    }
+}
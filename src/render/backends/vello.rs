@@ -1,24 +1,32 @@
+use crate::engine::config::AntialiasingMode;
+use crate::engine::logging::engine_log;
 use crate::engine::BrowsingContext;
 use crate::render::backend::GpuPixelFormat;
 use crate::render::backend::{
     ErasedSurface, ExternalHandle, PresentMode, RenderBackend, RgbaImage, SurfaceSize,
 };
-use crate::render::DisplayItem;
+use crate::render::{DisplayItem, TextHinting};
 use anyhow::{anyhow, Result};
 use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use vello::kurbo::Affine;
 use vello::peniko::{Color, Fill};
 use vello::wgpu;
-use vello::{RenderParams, Renderer, RendererOptions, Scene};
+use vello::{AaConfig, AaSupport, RenderParams, Renderer, RendererOptions, Scene};
 use crate::render::backends::vello::font_cache::FontCache;
 use crate::render::backends::vello::font_manager::FontManager;
 use crate::render::backends::vello::text_renderer::{TextKey, TextRenderer};
 
 mod font_manager;
 mod font_cache;
+mod standalone;
 mod text_renderer;
 
+pub use standalone::StandaloneWgpuContext;
+
+/// Font family of text runs that do not name one.
+const DEFAULT_FONT_FAMILY: &str = "Comic Sans";
 
 /// This trait abstracts over the wgpu context (device, queue, texture management) so we can connect
 /// UI based wgpu contexts (like eframe) to the Vello backend.
@@ -28,6 +36,19 @@ pub trait WgpuContextProvider {
     fn create_texture(&self, width: u32, height: u32, format: wgpu::TextureFormat) -> u64;
     fn get_texture(&self, id: u64) -> Option<(wgpu::Texture, wgpu::TextureView)>;
     fn remove_texture(&self, id: u64);
+
+    /// Replaces a lost device and its queue with new ones, and returns the new device. From
+    /// then on [`device`](Self::device), [`queue`](Self::queue) and the textures the provider
+    /// creates belong to the new device, so the host and the backend keep sharing it. Textures
+    /// of the lost device are gone; the backend creates new ones, and the engine publishes
+    /// [`EngineNotification::BackendReset`](crate::EngineNotification::BackendReset) so the host
+    /// can register them.
+    ///
+    /// The default cannot replace the device and returns an error, which leaves the backend
+    /// unable to render.
+    fn recreate(&self) -> Result<&wgpu::Device> {
+        Err(anyhow!("the wgpu context provider cannot replace a lost device"))
+    }
 }
 
 /// A render backend that uses Vello for rendering.
@@ -36,6 +57,12 @@ pub struct VelloBackend<C: WgpuContextProvider> {
     context: Arc<C>,
     /// The Vello renderer instance.
     renderer: Renderer,
+    /// Anti-aliasing methods the renderer was able to set up on the device
+    aa_support: AaSupport,
+    /// Anti-aliasing method used for rendering
+    antialiasing: AaConfig,
+    /// Set when the device is lost
+    lost: Arc<AtomicBool>,
 
     text_renderer: TextRenderer,
     font_manager: FontManager,
@@ -43,12 +70,20 @@ pub struct VelloBackend<C: WgpuContextProvider> {
 }
 
 impl<C: WgpuContextProvider> VelloBackend<C> {
+    /// Creates a backend that renders with 16 samples per pixel until the engine sets the
+    /// anti-aliasing from its [`GpuOptions`](crate::config::GpuOptions). When the device cannot
+    /// run the multisampling pipelines, only area anti-aliasing is available.
     pub fn new(context: Arc<C>) -> Result<Self> {
-        let renderer = Renderer::new(context.device(), RendererOptions::default())?;
+        let (renderer, aa_support) = create_renderer(context.device())?;
+        let antialiasing = if aa_support.msaa16 { AaConfig::Msaa16 } else { AaConfig::Area };
+        let lost = watch_device_loss(context.device());
 
         Ok(Self {
             context,
             renderer,
+            aa_support,
+            antialiasing,
+            lost,
             text_renderer: TextRenderer::new(),
             font_manager: FontManager::new(),
             font_cache: FontCache::new(),
@@ -72,7 +107,7 @@ impl<C: WgpuContextProvider> VelloBackend<C> {
                 base_color: Color::WHITE,
                 width: surface.size.width,
                 height: surface.size.height,
-                antialiasing_method: vello::AaConfig::Msaa16,
+                antialiasing_method: self.antialiasing,
             },
         )?;
 
@@ -80,10 +115,11 @@ impl<C: WgpuContextProvider> VelloBackend<C> {
     }
 
     fn convert_browsing_context_to_scene(&mut self, ctx: &mut BrowsingContext) -> Result<Scene> {
-        // Build a scene from your DisplayItems
-        let vp = ctx.viewport();
-        let offset_x = vp.x as f32;
-        let offset_y = vp.y as f32;
+        // Build a scene from your DisplayItems. They are in document coordinates, which the
+        // request maps onto the surface.
+        let request = ctx.render_request();
+        let transform = Affine::new(request.transform());
+        let text_options = ctx.text_options();
 
         let mut scene = Scene::new();
         for item in ctx.render_list().items.iter() {
@@ -95,17 +131,19 @@ impl<C: WgpuContextProvider> VelloBackend<C> {
                         Affine::IDENTITY,
                         Color::new([color.r, color.g, color.b, color.a]),
                         None,
-                        &vello::kurbo::Rect::new(0.0, 0.0, vp.width as f64, vp.height as f64),
+                        &vello::kurbo::Rect::new(
+                            0.0,
+                            0.0,
+                            request.size.width as f64,
+                            request.size.height as f64,
+                        ),
                     );
                 }
                 DisplayItem::Rect { x, y, w, h, color } => {
-                    let x = (*x as f32) - offset_x;
-                    let y = (*y as f32) - offset_y;
-                    let w = *w as f32;
-                    let h = *h as f32;
+                    let (x, y, w, h) = (*x, *y, *w, *h);
                     scene.fill(
                         Fill::NonZero,
-                        Affine::IDENTITY,
+                        transform,
                         Color::new([color.r, color.g, color.b, color.a]),
                         None,
                         &vello::kurbo::Rect::new(
@@ -121,19 +159,23 @@ impl<C: WgpuContextProvider> VelloBackend<C> {
                     y,
                     text,
                     size,
+                    font_family,
                     color,
                     max_width,
                 } => {
-                    let x = (*x as f32) - offset_x;
-                    let y = (*y as f32) - offset_y;
+                    // Snap on the surface, where the pixels are
+                    let origin = transform * vello::kurbo::Point::new(*x as f64, *y as f64);
+                    let x = text_options.snap(origin.x as f32);
+                    let y = text_options.snap(origin.y as f32);
 
                     let key = TextKey {
                         text: Arc::from(text.as_str()),
-                        font_name: Arc::from("Comic Sans"),
+                        font_name: Arc::from(font_family.as_deref().unwrap_or(DEFAULT_FONT_FAMILY)),
                         font_size: size.ceil() as u32,
                         wrap: max_width.map(|mw| mw.ceil() as u32),
                         // wrap: Some(600),
                         align: 0,
+                        subpixel: text_options.subpixel_positioning,
                     };
 
                     self.text_renderer.draw(
@@ -142,7 +184,9 @@ impl<C: WgpuContextProvider> VelloBackend<C> {
                         &mut scene,
                         &key,
                         x, y,
+                        request.device_scale,
                         (*color).into(),
+                        text_options.hinting != TextHinting::None,
                     );
                 }
             }
@@ -205,6 +249,65 @@ impl<C: WgpuContextProvider> RenderBackend for VelloBackend<C> {
             frame_id: s.frame_id,
         })
     }
+
+    fn device_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Asks the context provider to replace its device and sets up a renderer on the new one.
+    fn recover(&mut self) -> Result<()> {
+        let device = self.context.recreate()?;
+        let (renderer, aa_support) = create_renderer(device)?;
+        if !aa_support.msaa8 {
+            self.antialiasing = AaConfig::Area;
+        }
+        self.lost = watch_device_loss(device);
+        self.renderer = renderer;
+        self.aa_support = aa_support;
+        Ok(())
+    }
+
+    /// Uses `mode` if the renderer supports it, and otherwise the best supported method with
+    /// fewer samples.
+    fn set_antialiasing(&mut self, mode: AntialiasingMode) -> AntialiasingMode {
+        let support = self.aa_support;
+        let (config, used) = match mode {
+            AntialiasingMode::Msaa16 if support.msaa16 => (AaConfig::Msaa16, mode),
+            AntialiasingMode::Msaa16 | AntialiasingMode::Msaa8 if support.msaa8 => {
+                (AaConfig::Msaa8, AntialiasingMode::Msaa8)
+            }
+            _ => (AaConfig::Area, AntialiasingMode::Area),
+        };
+        self.antialiasing = config;
+        used
+    }
+}
+
+/// Creates a renderer with every anti-aliasing method, or only area anti-aliasing when the
+/// device cannot run the multisampling pipelines.
+fn create_renderer(device: &wgpu::Device) -> Result<(Renderer, AaSupport)> {
+    let options = |aa_support| RendererOptions {
+        antialiasing_support: aa_support,
+        ..RendererOptions::default()
+    };
+    match Renderer::new(device, options(AaSupport::all())) {
+        Ok(renderer) => Ok((renderer, AaSupport::all())),
+        Err(_) => Ok((
+            Renderer::new(device, options(AaSupport::area_only()))?,
+            AaSupport::area_only(),
+        )),
+    }
+}
+
+/// Returns a flag that is set once `device` is lost.
+fn watch_device_loss(device: &wgpu::Device) -> Arc<AtomicBool> {
+    let lost = Arc::new(AtomicBool::new(false));
+    let flag = lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        engine_log!(Error, "wgpu device lost ({:?}): {}", reason, message);
+        flag.store(true, Ordering::Relaxed);
+    });
+    lost
 }
 
 /// A vello surface that wraps a wgpu texture.
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use parley::Font;
+use crate::engine::logging::engine_log;
 
 /// A simple font cache that maps font family names to loaded fonts.
 pub struct FontCache {
@@ -43,7 +44,7 @@ impl FontCache {
     }
 
     pub fn insert(&mut self, name: &str, resolved_name: &str, font: Font) {
-        println!("Caching font {} as {}", name, resolved_name);
+        engine_log!(Debug, "Caching font {} as {}", name, resolved_name);
         self.fonts.insert(name.to_string(), font);
         self.resolved_names.insert(name.to_string(), resolved_name.to_string());
     }
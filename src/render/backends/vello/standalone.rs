@@ -0,0 +1,162 @@
+use crate::render::backends::vello::WgpuContextProvider;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+use vello::wgpu;
+
+/// A wgpu device of its own, for hosts that do not render with wgpu themselves. The textures
+/// that frames are rendered into are looked up with [`WgpuContextProvider::get_texture`].
+pub struct StandaloneWgpuContext {
+    /// Which GPU to ask for, kept to open the same kind again after a device loss
+    power_preference: wgpu::PowerPreference,
+    /// The first device, followed by the ones that replaced it
+    gpus: GpuChain,
+    /// Textures by their ID
+    textures: RwLock<HashMap<u64, (wgpu::Texture, wgpu::TextureView)>>,
+    next_texture_id: AtomicU64,
+}
+
+impl StandaloneWgpuContext {
+    /// Opens a device on the integrated GPU when `prefer_low_power` is set and on the fastest
+    /// GPU otherwise, falling back to whatever GPU there is.
+    ///
+    /// # Errors
+    /// Fails when the machine has no GPU that wgpu can use.
+    pub fn new(prefer_low_power: bool) -> Result<Self> {
+        let power_preference = if prefer_low_power {
+            wgpu::PowerPreference::LowPower
+        } else {
+            wgpu::PowerPreference::HighPerformance
+        };
+        Ok(Self {
+            power_preference,
+            gpus: GpuChain::new(Gpu::open(power_preference)?),
+            textures: RwLock::new(HashMap::new()),
+            next_texture_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Returns the name of the GPU the device runs on.
+    pub fn adapter_name(&self) -> &str {
+        &self.gpu().adapter_name
+    }
+
+    /// Returns the current device, the last one that replaced a lost one.
+    fn gpu(&self) -> &Gpu {
+        &self.gpus.last().gpu
+    }
+}
+
+/// A device and the device that replaced it after it was lost. Lost devices are kept until
+/// the context is dropped, so that the references handed out to them stay valid.
+struct GpuChain {
+    gpu: Gpu,
+    replaced_by: OnceLock<Box<GpuChain>>,
+}
+
+impl GpuChain {
+    fn new(gpu: Gpu) -> Self {
+        Self {
+            gpu,
+            replaced_by: OnceLock::new(),
+        }
+    }
+
+    fn last(&self) -> &GpuChain {
+        let mut link = self;
+        while let Some(next) = link.replaced_by.get() {
+            link = next;
+        }
+        link
+    }
+}
+
+/// A device with its queue.
+struct Gpu {
+    /// Name of the GPU, as reported by the driver
+    adapter_name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl Gpu {
+    fn open(power_preference: wgpu::PowerPreference) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or_else(|| anyhow!("no GPU adapter available"))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("Gosub standalone device"),
+                ..Default::default()
+            },
+            None,
+        ))?;
+
+        Ok(Self {
+            adapter_name: adapter.get_info().name,
+            device,
+            queue,
+        })
+    }
+}
+
+impl WgpuContextProvider for StandaloneWgpuContext {
+    fn device(&self) -> &wgpu::Device {
+        &self.gpu().device
+    }
+
+    fn queue(&self) -> &wgpu::Queue {
+        &self.gpu().queue
+    }
+
+    fn create_texture(&self, width: u32, height: u32, format: wgpu::TextureFormat) -> u64 {
+        let texture = self.device().create_texture(&wgpu::TextureDescriptor {
+            label: Some("Gosub Vello Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let id = self.next_texture_id.fetch_add(1, Ordering::Relaxed);
+        self.textures.write().unwrap().insert(id, (texture, view));
+        id
+    }
+
+    fn get_texture(&self, id: u64) -> Option<(wgpu::Texture, wgpu::TextureView)> {
+        self.textures.read().unwrap().get(&id).cloned()
+    }
+
+    fn remove_texture(&self, id: u64) {
+        self.textures.write().unwrap().remove(&id);
+    }
+
+    /// Opens a new device on the same kind of GPU and drops the textures of the lost one.
+    /// Texture IDs are not reused, so IDs of lost textures are not found anymore.
+    fn recreate(&self) -> Result<&wgpu::Device> {
+        let gpu = Gpu::open(self.power_preference)?;
+        self.gpus
+            .last()
+            .replaced_by
+            .set(Box::new(GpuChain::new(gpu)))
+            .map_err(|_| anyhow!("the device was replaced by another thread"))?;
+        self.textures.write().unwrap().clear();
+        Ok(self.device())
+    }
+}
@@ -24,7 +24,7 @@ use crate::render::backends::vello::font_manager::FontManager;
 /// Cache key for shaped text.
 ///
 /// Two keys are considered equal if they match on
-/// - `px`, `align`, `wrap`, and
+/// - `px`, `align`, `wrap`, `subpixel`, and
 /// - both `text` and `font_name` content (pointer equality *or* string equality).
 ///
 /// Notes:
@@ -42,13 +42,16 @@ pub struct TextKey {
     pub wrap: Option<u32>,
     /// Horizontal alignment: 0=left, 1=center, 2=right (currently unused).
     pub align: u8,
+    /// Keep fractional glyph positions instead of rounding them to whole pixels.
+    pub subpixel: bool,
 }
 
 impl PartialEq for TextKey {
     fn eq(&self, o: &Self) -> bool {
         self.font_size == o.font_size && self.align == o.align && self.wrap == o.wrap
-            && Arc::ptr_eq(&self.text, &o.text) && Arc::ptr_eq(&self.font_name, &o.font_name)
-            || (self.text.as_ref() == o.text.as_ref() && self.font_name.as_ref() == o.font_name.as_ref())
+            && self.subpixel == o.subpixel
+            && (Arc::ptr_eq(&self.text, &o.text) && Arc::ptr_eq(&self.font_name, &o.font_name)
+                || (self.text.as_ref() == o.text.as_ref() && self.font_name.as_ref() == o.font_name.as_ref()))
     }
 }
 
@@ -59,6 +62,7 @@ impl Hash for TextKey {
         self.font_size.hash(s);
         self.align.hash(s);
         self.wrap.hash(s);
+        self.subpixel.hash(s);
         self.text.as_ref().hash(s);
         self.font_name.as_ref().hash(s);
     }
@@ -117,6 +121,9 @@ impl TextRenderer {
     /// Performance:
     /// - Multiple calls with the same `key` reuse shaping work.
     /// - If you animate only the position/color, reuse the same `key`.
+    ///
+    /// `(x, y)` is the origin on the surface and `scale` the device scale the glyphs are
+    /// drawn at. With `hint`, Vello fits the glyph outlines to the pixel grid.
     pub fn draw(
         &mut self,
         fm: &mut FontManager,
@@ -125,7 +132,9 @@ impl TextRenderer {
         key: &TextKey,
         x: f32,
         y: f32,
+        scale: f32,
         rgba: [f32; 4],
+        hint: bool,
     ) {
         let runs = if let Some(r) = self.cache.get(key) {
             r.clone()
@@ -135,15 +144,15 @@ impl TextRenderer {
             shaped
         };
 
-        let scale = 1.0;
-        let transform = Affine::translate((scale * x as f64, scale * y as f64));
+        let transform = Affine::translate((x as f64, y as f64)) * Affine::scale(scale as f64);
         let brush = Brush::Solid(Color::new(rgba));
 
 
         for r in runs.iter() {
             scene
                 .draw_glyphs(&r.vello_font)
-                .font_size(r.font_size * scale as f32)
+                .font_size(r.font_size)
+                .hint(hint)
                 .transform(transform)
                 .brush(&brush)
                 .brush_alpha(1.0)
@@ -207,7 +216,7 @@ impl TextRenderer {
                 }
                 let gid = charmap.map(ch).unwrap_or_default();
                 let advance = glyph_metrics.advance_width(gid).unwrap_or_default();
-                let x = pen_x;
+                let x = if key.subpixel { pen_x } else { pen_x.round() };
                 pen_x += advance;
                 Some(Glyph { id: gid.to_u32(), x, y: pen_y })
             }).collect::<Arc<[_]>>();
@@ -259,7 +268,11 @@ impl TextRenderer {
                         let ro = run.offset();
 
                         let glyphs: Vec<Glyph> = run.positioned_glyphs()
-                            .map(|g| Glyph { id: g.id as u32, x: g.x.round(), y: (pen_y + baseline + ro + g.y).round() })
+                            .map(|g| Glyph {
+                                id: g.id as u32,
+                                x: if key.subpixel { g.x } else { g.x.round() },
+                                y: (pen_y + baseline + ro + g.y).round(),
+                            })
                             .collect();
 
                         out.push(CachedRun {
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use crate::render::backend::{CompositorSink, ExternalHandle};
-use crate::tab::TabId;
+use crate::tab::{AuxViewportId, TabId};
 
 /// A default compositor implementation that manages frames per tab
 /// and requests redraws when new frames are submitted.
@@ -20,6 +20,9 @@ pub struct DefaultCompositor {
     /// render backend.
     pub frames: HashMap<TabId, ExternalHandle>,
 
+    /// Frames of auxiliary viewports, keyed by tab and viewport.
+    pub aux_frames: HashMap<(TabId, AuxViewportId), ExternalHandle>,
+
     /// A callback function invoked when a redraw is requested.
     /// Typically this is connected to a GTK widget’s `queue_draw()`
     /// or similar function.
@@ -36,6 +39,7 @@ impl DefaultCompositor {
     pub fn new<F: Fn() + 'static>(redraw_cb: F) -> Self {
         Self {
             frames: HashMap::new(),
+            aux_frames: HashMap::new(),
             redraw_cb: Box::new(redraw_cb),
         }
     }
@@ -86,4 +90,97 @@ impl CompositorSink for DefaultCompositor {
         self.frames.insert(tab_id, handle);
         self.request_redraw();
     }
+
+    /// Stores the frame of an auxiliary viewport and requests a redraw.
+    fn submit_aux_frame(&mut self, tab_id: TabId, viewport: AuxViewportId, handle: ExternalHandle) {
+        self.aux_frames.insert((tab_id, viewport), handle);
+        self.request_redraw();
+    }
+}
+
+/// A rendered frame, as returned by [`GosubEngine::collect_frames`](crate::GosubEngine::collect_frames).
+#[derive(Clone, Debug)]
+pub struct Frame {
+    /// The tab the frame belongs to.
+    pub tab_id: TabId,
+    /// The auxiliary viewport the frame was rendered for, or `None` for the tab's main
+    /// viewport.
+    pub aux_viewport: Option<AuxViewportId>,
+    /// Handle to the rendered surface.
+    pub handle: ExternalHandle,
+}
+
+/// A compositor that only collects submitted frames, for hosts that pull frames instead of
+/// being called back.
+///
+/// When a tab submits more than one frame before the collector is drained, only the latest
+/// frame is kept.
+#[derive(Default)]
+pub struct FrameCollector {
+    frames: HashMap<(TabId, Option<AuxViewportId>), ExternalHandle>,
+}
+
+impl FrameCollector {
+    /// Creates an empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes and returns all collected frames, ordered by tab (main viewport first).
+    pub fn drain(&mut self) -> Vec<Frame> {
+        let mut frames: Vec<Frame> = self
+            .frames
+            .drain()
+            .map(|((tab_id, aux_viewport), handle)| Frame {
+                tab_id,
+                aux_viewport,
+                handle,
+            })
+            .collect();
+        frames.sort_by_key(|f| (f.tab_id, f.aux_viewport));
+        frames
+    }
+}
+
+impl CompositorSink for FrameCollector {
+    fn submit_frame(&mut self, tab_id: TabId, handle: ExternalHandle) {
+        self.frames.insert((tab_id, None), handle);
+    }
+
+    fn submit_aux_frame(&mut self, tab_id: TabId, viewport: AuxViewportId, handle: ExternalHandle) {
+        self.frames.insert((tab_id, Some(viewport)), handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(frame_id: u64) -> ExternalHandle {
+        ExternalHandle::NullHandle {
+            width: 1,
+            height: 1,
+            frame_id,
+        }
+    }
+
+    #[test]
+    fn collector_keeps_latest_frame_per_tab() {
+        let (a, b) = (TabId::new(), TabId::new());
+        let mut collector = FrameCollector::new();
+        collector.submit_frame(a, handle(1));
+        collector.submit_frame(b, handle(2));
+        collector.submit_frame(a, handle(3));
+
+        let frames = collector.drain();
+        assert_eq!(frames.len(), 2);
+        let frame_a = frames.iter().find(|f| f.tab_id == a).unwrap();
+        assert!(matches!(
+            frame_a.handle,
+            ExternalHandle::NullHandle { frame_id: 3, .. }
+        ));
+        assert!(frames.windows(2).all(|w| w[0].tab_id < w[1].tab_id));
+
+        assert!(collector.drain().is_empty());
+    }
 }
@@ -0,0 +1,251 @@
+//! Differences between two render lists of the same tab.
+use crate::render::{DisplayItem, RenderList};
+
+/// How a display item changed between two render lists.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemChange {
+    /// The item at `new` in the new list is not in the old list
+    Inserted {
+        /// Index in the new list
+        new: usize,
+    },
+    /// The item at `old` in the old list is not in the new list
+    Removed {
+        /// Index in the old list
+        old: usize,
+    },
+    /// The item at `old` in the old list was replaced by the item at `new` in the new list
+    Replaced {
+        /// Index in the old list
+        old: usize,
+        /// Index in the new list
+        new: usize,
+    },
+}
+
+/// Rectangle of a surface that has to be repainted, in pixels of the document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DamageRect {
+    /// Left edge
+    pub x: i32,
+    /// Top edge
+    pub y: i32,
+    /// Width
+    pub width: u32,
+    /// Height
+    pub height: u32,
+}
+
+impl DamageRect {
+    /// Smallest pixel rectangle that covers the given area.
+    fn covering(x: f32, y: f32, w: f32, h: f32) -> Self {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (x1, y1) = ((x + w.max(0.0)).ceil(), (y + h.max(0.0)).ceil());
+        Self {
+            x: x0 as i32,
+            y: y0 as i32,
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+        }
+    }
+
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    /// Whether the rectangles overlap or touch.
+    fn meets(&self, other: &DamageRect) -> bool {
+        (self.x as i64) <= other.right()
+            && (other.x as i64) <= self.right()
+            && (self.y as i64) <= other.bottom()
+            && (other.y as i64) <= self.bottom()
+    }
+
+    fn union(&self, other: &DamageRect) -> DamageRect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        DamageRect {
+            x,
+            y,
+            width: (self.right().max(other.right()) - x as i64) as u32,
+            height: (self.bottom().max(other.bottom()) - y as i64) as u32,
+        }
+    }
+}
+
+/// Differences between two render lists, as returned by [`RenderList::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RenderListDiff {
+    /// Changed items, in list order
+    pub changes: Vec<ItemChange>,
+    /// Areas that have to be repainted, none of which overlap or touch. Empty when `full`
+    /// is set.
+    pub regions: Vec<DamageRect>,
+    /// Whether the whole surface has to be repainted, because a
+    /// [`DisplayItem::Clear`] changed
+    pub full: bool,
+}
+
+impl RenderListDiff {
+    /// Returns true when both lists paint the same.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl RenderList {
+    /// Compares this list with `previous`, the list it replaces, and reports which items
+    /// changed and which areas have to be repainted.
+    ///
+    /// Items are matched by position after skipping the items both lists start and end
+    /// with, so an item inserted in the middle does not mark the items after it as changed
+    /// unless they moved. The area of a text run is an estimate that covers any font of its
+    /// size.
+    ///
+    /// ```
+    /// use gosub_engine::render::{Color, DisplayItem, ItemChange, RenderList};
+    ///
+    /// let rect = |x| DisplayItem::Rect { x, y: 0.0, w: 10.0, h: 10.0, color: Color::new(0.0, 0.0, 0.0, 1.0) };
+    /// let mut before = RenderList::new();
+    /// before.add_command(rect(0.0));
+    /// let mut after = before.clone();
+    /// after.add_command(rect(40.0));
+    ///
+    /// let diff = after.diff(&before);
+    /// assert_eq!(diff.changes, vec![ItemChange::Inserted { new: 1 }]);
+    /// assert_eq!(diff.regions[0].x, 40);
+    /// ```
+    pub fn diff(&self, previous: &RenderList) -> RenderListDiff {
+        let (old, new) = (&previous.items, &self.items);
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_changed = prefix..old.len() - suffix;
+        let new_changed = prefix..new.len() - suffix;
+
+        let mut diff = RenderListDiff::default();
+        for offset in 0..old_changed.len().max(new_changed.len()) {
+            let old_index = Some(prefix + offset).filter(|i| old_changed.contains(i));
+            let new_index = Some(prefix + offset).filter(|i| new_changed.contains(i));
+            let change = match (old_index, new_index) {
+                (Some(o), Some(n)) if old[o] == new[n] => continue,
+                (Some(o), Some(n)) => ItemChange::Replaced { old: o, new: n },
+                (Some(o), None) => ItemChange::Removed { old: o },
+                (None, Some(n)) => ItemChange::Inserted { new: n },
+                (None, None) => unreachable!("offset is within one of the ranges"),
+            };
+            for item in old_index
+                .map(|i| &old[i])
+                .into_iter()
+                .chain(new_index.map(|i| &new[i]))
+            {
+                match bounds(item) {
+                    Some(rect) => add_region(&mut diff.regions, rect),
+                    None => diff.full = true,
+                }
+            }
+            diff.changes.push(change);
+        }
+        if diff.full {
+            diff.regions.clear();
+        }
+        diff
+    }
+}
+
+/// Area an item paints, or `None` when it paints the whole surface.
+fn bounds(item: &DisplayItem) -> Option<DamageRect> {
+    match item {
+        DisplayItem::Clear { .. } => None,
+        DisplayItem::Rect { x, y, w, h, .. } => Some(DamageRect::covering(*x, *y, *w, *h)),
+        DisplayItem::TextRun {
+            x,
+            y,
+            text,
+            size,
+            max_width,
+            ..
+        } => {
+            // `y` is the baseline. No font is wider than its size per character, and
+            // descenders stay within a quarter of it.
+            let width = max_width.unwrap_or(text.chars().count() as f32 * size);
+            Some(DamageRect::covering(*x, y - size, width, size * 1.25))
+        }
+    }
+}
+
+/// Adds `rect` to `regions`, merging it with the regions it meets.
+fn add_region(regions: &mut Vec<DamageRect>, mut rect: DamageRect) {
+    while let Some(i) = regions.iter().position(|r| r.meets(&rect)) {
+        rect = rect.union(&regions.swap_remove(i));
+    }
+    regions.push(rect);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Color;
+
+    fn rect(x: f32, y: f32) -> DisplayItem {
+        DisplayItem::Rect {
+            x,
+            y,
+            w: 10.0,
+            h: 10.0,
+            color: Color::new(0.0, 0.0, 0.0, 1.0),
+        }
+    }
+
+    fn list(items: Vec<DisplayItem>) -> RenderList {
+        RenderList { items }
+    }
+
+    #[test]
+    fn only_changed_items_are_reported() {
+        let old = list(vec![rect(0.0, 0.0), rect(0.0, 20.0), rect(0.0, 40.0)]);
+        assert!(old.diff(&old).is_empty());
+
+        let new = list(vec![rect(0.0, 0.0), rect(5.0, 20.0), rect(0.0, 40.0)]);
+        let diff = new.diff(&old);
+        assert_eq!(diff.changes, vec![ItemChange::Replaced { old: 1, new: 1 }]);
+        // The old and new position overlap, so they are repainted as one area
+        assert_eq!(
+            diff.regions,
+            vec![DamageRect {
+                x: 0,
+                y: 20,
+                width: 15,
+                height: 10
+            }]
+        );
+        assert!(!diff.full);
+
+        let shorter = list(vec![rect(0.0, 0.0), rect(0.0, 40.0)]);
+        let diff = shorter.diff(&old);
+        assert_eq!(diff.changes, vec![ItemChange::Removed { old: 1 }]);
+        assert_eq!(diff.regions.len(), 1);
+        let diff = old.diff(&shorter);
+        assert_eq!(diff.changes, vec![ItemChange::Inserted { new: 1 }]);
+    }
+
+    #[test]
+    fn a_new_background_repaints_everything() {
+        let clear = |v| DisplayItem::Clear {
+            color: Color::new(v, v, v, 1.0),
+        };
+        let old = list(vec![clear(1.0), rect(0.0, 0.0)]);
+        let new = list(vec![clear(0.5), rect(0.0, 0.0)]);
+        let diff = new.diff(&old);
+        assert!(diff.full);
+        assert!(diff.regions.is_empty());
+        assert_eq!(diff.changes.len(), 1);
+    }
+}
@@ -0,0 +1,534 @@
+//! PDF output of render lists, for printing pages.
+//!
+//! [`RenderList::to_pdf`] lays a document out on paper as set up by [`PrintToPdf`]: the
+//! document is scaled, cut into pages that fit between the margins, and every page gets the
+//! optional header and footer. Text is set in the Helvetica font that every PDF viewer has, so
+//! nothing is embedded and characters outside Latin-1 print as `?`.
+use crate::render::{Color, DisplayItem, RenderList};
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+/// CSS pixels per inch
+const PX_PER_INCH: f32 = 96.0;
+/// PDF points per inch
+const PT_PER_INCH: f32 = 72.0;
+/// Font size of headers and footers, in points
+const HEADER_FONT_SIZE: f32 = 9.0;
+
+/// Space between the edges of the paper and the printed content, in inches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct PageMargins {
+    /// Top margin, which holds the header
+    pub top: f32,
+    /// Right margin
+    pub right: f32,
+    /// Bottom margin, which holds the footer
+    pub bottom: f32,
+    /// Left margin
+    pub left: f32,
+}
+
+impl PageMargins {
+    /// The same margin on every side.
+    pub const fn uniform(inches: f32) -> Self {
+        Self {
+            top: inches,
+            right: inches,
+            bottom: inches,
+            left: inches,
+        }
+    }
+}
+
+impl Default for PageMargins {
+    /// The default margins of print dialogs, 0.4 inch (about 1 cm).
+    fn default() -> Self {
+        Self::uniform(0.4)
+    }
+}
+
+/// Page setup for printing a tab to PDF, like the options of a browser's print dialog.
+///
+/// Sizes are in inches. Header and footer templates are plain text in which `{page}`,
+/// `{pages}`, `{title}` and `{url}` are replaced by the page number, the number of pages in
+/// the document, the page title and its URL.
+///
+/// ```
+/// use gosub_engine::render::{parse_page_ranges, PageMargins, PrintToPdf};
+///
+/// let options = PrintToPdf {
+///     landscape: true,
+///     margins: PageMargins::uniform(0.5),
+///     page_ranges: parse_page_ranges("1-3, 5").unwrap(),
+///     footer_template: Some("{title} - page {page} of {pages}".into()),
+///     ..PrintToPdf::default()
+/// };
+/// assert_eq!(options.paper_size(), (11.0, 8.5));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+pub struct PrintToPdf {
+    /// Width of the paper in portrait orientation
+    pub paper_width: f32,
+    /// Height of the paper in portrait orientation
+    pub paper_height: f32,
+    /// Print across the long side of the paper
+    pub landscape: bool,
+    /// Margins around the content
+    pub margins: PageMargins,
+    /// Scale of the content, from 0.1 to 2.0
+    pub scale: f32,
+    /// Pages to print, counting from 1. Empty prints every page; see [`parse_page_ranges`].
+    pub page_ranges: Vec<RangeInclusive<u32>>,
+    /// Text printed in the top margin of every page
+    pub header_template: Option<String>,
+    /// Text printed in the bottom margin of every page
+    pub footer_template: Option<String>,
+    /// Print the page background; without it pages are white, as print dialogs default to
+    pub print_background: bool,
+}
+
+impl Default for PrintToPdf {
+    /// US Letter in portrait, at 100% with the default margins and without header or footer.
+    fn default() -> Self {
+        Self {
+            paper_width: 8.5,
+            paper_height: 11.0,
+            landscape: false,
+            margins: PageMargins::default(),
+            scale: 1.0,
+            page_ranges: Vec::new(),
+            header_template: None,
+            footer_template: None,
+            print_background: false,
+        }
+    }
+}
+
+impl PrintToPdf {
+    /// Returns the width and height of the paper in inches, in the orientation it is printed.
+    pub fn paper_size(&self) -> (f32, f32) {
+        if self.landscape {
+            (self.paper_height, self.paper_width)
+        } else {
+            (self.paper_width, self.paper_height)
+        }
+    }
+
+    fn selects(&self, page: u32) -> bool {
+        self.page_ranges.is_empty() || self.page_ranges.iter().any(|r| r.contains(&page))
+    }
+}
+
+/// Why a document could not be printed.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PrintError {
+    /// A page range could not be parsed
+    #[error("invalid page range {0:?}")]
+    InvalidPageRange(String),
+    /// None of the pages of the document are in the page ranges
+    #[error("the page ranges select none of the {0} pages")]
+    NoPagesSelected(u32),
+    /// The scale is outside the range print dialogs allow
+    #[error("scale {0} is outside 0.1 to 2.0")]
+    InvalidScale(f32),
+    /// The margins leave no room on the paper for content
+    #[error("the margins leave no room for content")]
+    NoPrintableArea,
+}
+
+/// Parses page ranges the way print dialogs accept them: comma-separated page numbers and
+/// ranges, such as `1-3, 5, 8-`. Pages count from 1; `-4` means pages 1 to 4 and `8-` means
+/// page 8 to the end.
+///
+/// # Errors
+/// Returns [`PrintError::InvalidPageRange`] for a part that is not a page or range, or a range
+/// that ends before it starts.
+pub fn parse_page_ranges(ranges: &str) -> Result<Vec<RangeInclusive<u32>>, PrintError> {
+    let invalid = |part: &str| PrintError::InvalidPageRange(part.to_string());
+    let page = |s: &str, default: u32| match s.trim() {
+        "" => Some(default),
+        s => s.parse::<u32>().ok().filter(|p| *p > 0),
+    };
+
+    let mut out = Vec::new();
+    for part in ranges.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let range = match part.split_once('-') {
+            Some((start, end)) => page(start, 1).zip(page(end, u32::MAX)),
+            None => page(part, 0).map(|p| (p, p)),
+        };
+        match range {
+            Some((start, end)) if start > 0 && start <= end => out.push(start..=end),
+            _ => return Err(invalid(part)),
+        }
+    }
+    Ok(out)
+}
+
+impl RenderList {
+    /// Prints the list to a PDF document. `title` and `url` fill in the header and footer
+    /// templates of `options`.
+    ///
+    /// The list is taken to hold the whole document in CSS pixels, as
+    /// [`BrowsingContext::render_list`](crate::BrowsingContext::render_list) does, and is cut
+    /// into pages from the top.
+    ///
+    /// # Errors
+    /// Fails when `options` are invalid or select no page of the document.
+    pub fn to_pdf(
+        &self,
+        options: &PrintToPdf,
+        title: &str,
+        url: &str,
+    ) -> Result<Vec<u8>, PrintError> {
+        if !(0.1..=2.0).contains(&options.scale) {
+            return Err(PrintError::InvalidScale(options.scale));
+        }
+        let (paper_w, paper_h) = options.paper_size();
+        let margins = options.margins;
+        let content_w = paper_w - margins.left - margins.right;
+        let content_h = paper_h - margins.top - margins.bottom;
+        if content_w <= 0.0 || content_h <= 0.0 {
+            return Err(PrintError::NoPrintableArea);
+        }
+
+        // Size of the content area in document pixels
+        let page_w = content_w * PX_PER_INCH / options.scale;
+        let page_h = content_h * PX_PER_INCH / options.scale;
+        let document_h = self.items.iter().map(item_bottom).fold(0.0, f32::max);
+        let pages = ((document_h / page_h).ceil() as u32).max(1);
+        let selected: Vec<u32> = (1..=pages).filter(|p| options.selects(*p)).collect();
+        if selected.is_empty() {
+            return Err(PrintError::NoPagesSelected(pages));
+        }
+
+        let page = PageLayout {
+            width_pt: paper_w * PT_PER_INCH,
+            height_pt: paper_h * PT_PER_INCH,
+            left_pt: margins.left * PT_PER_INCH,
+            top_pt: margins.top * PT_PER_INCH,
+            bottom_pt: margins.bottom * PT_PER_INCH,
+            width_px: page_w,
+            height_px: page_h,
+            scale: options.scale * PT_PER_INCH / PX_PER_INCH,
+        };
+        let contents = selected.iter().map(|number| {
+            let expand = |template: &String| {
+                template
+                    .replace("{page}", &number.to_string())
+                    .replace("{pages}", &pages.to_string())
+                    .replace("{title}", title)
+                    .replace("{url}", url)
+            };
+            let header = options.header_template.as_ref().map(expand);
+            let footer = options.footer_template.as_ref().map(expand);
+            page.content(self, *number - 1, options.print_background, header, footer)
+        });
+        Ok(write_document(&page, contents.collect(), title))
+    }
+}
+
+/// Where the content of a page goes on the paper.
+struct PageLayout {
+    width_pt: f32,
+    height_pt: f32,
+    left_pt: f32,
+    top_pt: f32,
+    bottom_pt: f32,
+    /// Size of the content area in document pixels
+    width_px: f32,
+    height_px: f32,
+    /// Points per document pixel
+    scale: f32,
+}
+
+impl PageLayout {
+    /// Returns the content stream of page `index` (counting from 0).
+    fn content(
+        &self,
+        list: &RenderList,
+        index: u32,
+        background: bool,
+        header: Option<String>,
+        footer: Option<String>,
+    ) -> String {
+        let top = index as f32 * self.height_px;
+        let bottom = top + self.height_px;
+        let mut out = String::new();
+
+        // Map document pixels onto the content area, flipping the y axis of PDF
+        let _ = writeln!(
+            out,
+            "q\n{} 0 0 {} {} {} cm",
+            num(self.scale),
+            num(-self.scale),
+            num(self.left_pt),
+            num(self.height_pt - self.top_pt + top * self.scale)
+        );
+        let clip = format!(
+            "0 {} {} {} re",
+            num(top),
+            num(self.width_px),
+            num(self.height_px)
+        );
+        let _ = writeln!(out, "{clip} W n");
+
+        for item in &list.items {
+            match item {
+                DisplayItem::Clear { color } if background && color.a > 0.0 => {
+                    let _ = writeln!(out, "{} {clip} f", fill(color));
+                }
+                DisplayItem::Clear { .. } => {}
+                DisplayItem::Rect { x, y, w, h, color } => {
+                    if color.a <= 0.0 || *y >= bottom || y + h <= top {
+                        continue;
+                    }
+                    let _ = writeln!(
+                        out,
+                        "{} {} {} {} {} re f",
+                        fill(color),
+                        num(*x),
+                        num(*y),
+                        num(*w),
+                        num(*h)
+                    );
+                }
+                DisplayItem::TextRun {
+                    x,
+                    y,
+                    text,
+                    size,
+                    color,
+                    ..
+                } => {
+                    if color.a <= 0.0 || y - size >= bottom || y + size / 4.0 <= top {
+                        continue;
+                    }
+                    // The text matrix flips glyphs back upright
+                    let _ = writeln!(
+                        out,
+                        "BT {} /F1 {} Tf 1 0 0 -1 {} {} Tm {} Tj ET",
+                        fill(color),
+                        num(*size),
+                        num(*x),
+                        num(*y),
+                        pdf_string(text)
+                    );
+                }
+            }
+        }
+        out.push_str("Q\n");
+
+        let margin_text = [
+            (header, self.height_pt - self.top_pt / 2.0),
+            (footer, self.bottom_pt / 2.0),
+        ];
+        for (text, middle) in margin_text {
+            let Some(text) = text else { continue };
+            let _ = writeln!(
+                out,
+                "BT 0 0 0 rg /F1 {} Tf {} {} Td {} Tj ET",
+                num(HEADER_FONT_SIZE),
+                num(self.left_pt),
+                num(middle - HEADER_FONT_SIZE / 3.0),
+                pdf_string(&text)
+            );
+        }
+        out
+    }
+}
+
+/// Assembles the PDF file: catalog, page tree, font, document info, then a page and its
+/// content stream for every page.
+fn write_document(page: &PageLayout, contents: Vec<String>, title: &str) -> Vec<u8> {
+    let first_page = 5;
+    let kids: Vec<String> = (0..contents.len())
+        .map(|i| format!("{} 0 R", first_page + 2 * i))
+        .collect();
+
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            contents.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        format!("<< /Title {} /Producer (Gosub) >>", pdf_string(title)),
+    ];
+    for (i, content) in contents.into_iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            num(page.width_pt),
+            num(page.height_pt),
+            first_page + 2 * i + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ));
+    }
+
+    // Everything is ASCII, apart from the comment that marks the file as binary
+    let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", i + 1).as_bytes());
+    }
+    let xref = out.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    out.extend_from_slice(trailer.as_bytes());
+    out
+}
+
+/// Lowest document coordinate an item paints at.
+fn item_bottom(item: &DisplayItem) -> f32 {
+    match item {
+        DisplayItem::Clear { .. } => 0.0,
+        DisplayItem::Rect { y, h, .. } => y + h,
+        DisplayItem::TextRun { y, size, .. } => y + size / 4.0,
+    }
+}
+
+fn fill(color: &Color) -> String {
+    format!(
+        "{} {} {} rg",
+        num(color.r.clamp(0.0, 1.0)),
+        num(color.g.clamp(0.0, 1.0)),
+        num(color.b.clamp(0.0, 1.0))
+    )
+}
+
+/// Formats a number with at most two decimals and no trailing zeros.
+fn num(value: f32) -> String {
+    let s = format!("{value:.2}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Encodes text as a PDF string literal in WinAnsi encoding. Bytes outside ASCII are written
+/// as octal escapes so that content streams stay ASCII.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => {
+                let _ = write!(out, "\\{:03o}", c as u32);
+            }
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(lines: usize) -> RenderList {
+        let mut list = RenderList::new();
+        list.add_command(DisplayItem::Clear {
+            color: Color::new(0.75, 0.75, 0.75, 1.0),
+        });
+        for line in 0..lines {
+            list.add_command(DisplayItem::TextRun {
+                x: 14.0,
+                y: 24.0 + line as f32 * 16.0,
+                text: format!("line {line}"),
+                size: 13.0,
+                font_family: None,
+                color: Color::new(0.0, 0.0, 0.0, 1.0),
+                max_width: None,
+            });
+        }
+        list
+    }
+
+    #[test]
+    fn page_ranges_parse_like_print_dialogs() {
+        assert_eq!(
+            parse_page_ranges(" 1-3, 5,8-, -2 ").unwrap(),
+            vec![1..=3, 5..=5, 8..=u32::MAX, 1..=2]
+        );
+        assert_eq!(parse_page_ranges("").unwrap(), vec![]);
+        for bad in ["0", "4-2", "a", "1-2-3"] {
+            assert_eq!(
+                parse_page_ranges(bad),
+                Err(PrintError::InvalidPageRange(bad.into()))
+            );
+        }
+    }
+
+    #[test]
+    fn documents_are_cut_into_the_selected_pages() {
+        // 10 inches of content hold 960 pixels, so 150 lines need three pages
+        let list = document(150);
+        let options = PrintToPdf {
+            margins: PageMargins::uniform(0.5),
+            page_ranges: vec![2..=u32::MAX],
+            header_template: Some("{title} ({url})".into()),
+            footer_template: Some("Page {page} of {pages}".into()),
+            ..PrintToPdf::default()
+        };
+        let pdf = list
+            .to_pdf(&options, "Caf\u{e9} (menu)", "https://x.test/")
+            .unwrap();
+        let pdf = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("/MediaBox [0 0 612 792]"));
+        assert!(pdf.contains("(Page 2 of 3)") && pdf.contains("(Page 3 of 3)"));
+        assert!(!pdf.contains("(Page 1 of 3)"));
+        assert!(pdf.contains("(Caf\\351 \\(menu\\) \\(https://x.test/\\))"));
+        assert!(pdf.contains("(line 60)") && !pdf.contains("(line 10)"));
+        // The gray background is left out unless asked for
+        assert!(!pdf.contains("0.75 0.75 0.75 rg"));
+
+        let scaled = PrintToPdf {
+            scale: 2.0,
+            ..options.clone()
+        };
+        let pdf = list.to_pdf(&scaled, "", "").unwrap();
+        assert!(String::from_utf8_lossy(&pdf).contains("Page 6 of 6"));
+
+        assert_eq!(
+            document(10).to_pdf(&options, "", ""),
+            Err(PrintError::NoPagesSelected(1))
+        );
+        let no_room = PrintToPdf {
+            margins: PageMargins::uniform(5.0),
+            ..PrintToPdf::default()
+        };
+        assert_eq!(
+            list.to_pdf(&no_room, "", ""),
+            Err(PrintError::NoPrintableArea)
+        );
+    }
+}
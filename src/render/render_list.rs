@@ -28,11 +28,14 @@
 //!     color: Color::from_u8(255, 255, 255, 255),
 //! });
 //! ```
+#[cfg(feature = "serde_events")]
+use serde::{Deserialize, Serialize};
 
 /// RGBA color used for drawing commands.
 ///
 /// Channels are represented as `f32` in the range `0.0 ..= 1.0`.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
 pub struct Color {
     /// Red channel
     pub r: f32,
@@ -104,7 +107,17 @@ impl Color {
 /// - [`DisplayItem::Clear`] — clear the entire surface to a color.
 /// - [`DisplayItem::Rect`] — draw a solid rectangle.
 /// - [`DisplayItem::TextRun`] — draw a run of text at a position.
-#[derive(Clone, Debug)]
+///
+/// # Stability
+///
+/// Hosts that paint display items themselves (see [`RenderMode::DisplayList`]) match on this
+/// enum. Existing variants and their fields do not change within a minor release; new kinds
+/// of drawing are added as new variants, so such hosts need a wildcard arm that skips items
+/// they do not know. Any change to the enum also bumps
+/// [`RENDER_LIST_FORMAT_VERSION`](crate::render::RENDER_LIST_FORMAT_VERSION).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum DisplayItem {
     /// Clear the entire surface with the given color.
     Clear {
@@ -136,6 +149,8 @@ pub enum DisplayItem {
         text: String,
         /// The font size to use for the text.
         size: f32,
+        /// The font family to use for the text, or `None` for the backend's default.
+        font_family: Option<String>,
         /// The color to render the text with.
         color: Color,
         /// Optional maximum width for text wrapping (in pixels).
@@ -147,7 +162,8 @@ pub enum DisplayItem {
 ///
 /// Collects commands during layout/painting that will be consumed
 /// by the rendering backend or compositor.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(Serialize, Deserialize))]
 pub struct RenderList {
     /// Sequence of drawing commands to execute.
     pub items: Vec<DisplayItem>,
@@ -169,3 +185,17 @@ impl RenderList {
         self.items.clear();
     }
 }
+
+/// Who paints the render lists of a zone's tabs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The render backend paints each render list onto a surface and hands the frame to the
+    /// compositor.
+    #[default]
+    Backend,
+    /// The engine only builds render lists and publishes each one as an
+    /// [`EngineNotification::SceneReady`](crate::EngineNotification::SceneReady); the host
+    /// paints them itself. The backend is not used, so tabs have no surfaces, auxiliary
+    /// viewports or thumbnails.
+    DisplayList,
+}
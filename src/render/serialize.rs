@@ -0,0 +1,310 @@
+//! Binary encoding of render lists, for hosts that composite in another process or record
+//! scenes for later analysis.
+use crate::render::{Color, DisplayItem, RenderList};
+
+/// Magic bytes at the start of an encoded render list
+const MAGIC: &[u8; 4] = b"GSRL";
+
+/// Version of the encoding written by [`RenderList::to_bytes`]
+pub const RENDER_LIST_FORMAT_VERSION: u16 = 1;
+
+const TAG_CLEAR: u8 = 0;
+const TAG_RECT: u8 = 1;
+const TAG_TEXT_RUN: u8 = 2;
+
+/// Smallest encoded item, a `Clear`; bounds the item count a buffer can hold
+const MIN_ITEM_BYTES: usize = 1 + 16;
+
+/// Why bytes could not be decoded into a [`RenderList`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RenderListDecodeError {
+    /// The data does not start with the magic bytes of an encoded render list
+    #[error("not an encoded render list")]
+    BadMagic,
+    /// The data was written in a format version this engine does not read
+    #[error("unsupported render list format version {0}")]
+    UnsupportedVersion(u16),
+    /// The data ends in the middle of an item
+    #[error("render list data is truncated")]
+    Truncated,
+    /// An item has a tag this version does not know
+    #[error("unknown display item tag {0}")]
+    UnknownItem(u8),
+    /// A string is not valid UTF-8
+    #[error("display item text is not valid UTF-8")]
+    InvalidUtf8,
+    /// A flag byte of an optional field is neither 0 nor 1
+    #[error("invalid flag byte {0}")]
+    InvalidFlag(u8),
+    /// There are bytes after the last item
+    #[error("{0} unexpected bytes after the last display item")]
+    TrailingBytes(usize),
+}
+
+impl RenderList {
+    /// Encodes the list in a compact binary format, so that a host can forward the scene to
+    /// another process or record it for analysis. [`RenderList::from_bytes`] turns it back
+    /// into a list.
+    ///
+    /// All numbers are little-endian. The data starts with the magic bytes `GSRL`, the format
+    /// version ([`RENDER_LIST_FORMAT_VERSION`]) as a `u16` and the item count as a `u32`. Each
+    /// item is a tag byte followed by its fields in declaration order: coordinates and sizes
+    /// are `f32`, colors four `f32` channels, strings a `u32` byte length and UTF-8, and
+    /// optional fields a `0`/`1` byte before the value.
+    ///
+    /// ```
+    /// use gosub_engine::render::{Color, DisplayItem, RenderList};
+    ///
+    /// let mut list = RenderList::new();
+    /// list.add_command(DisplayItem::Clear { color: Color::from_u8(255, 255, 255, 255) });
+    /// let bytes = list.to_bytes();
+    /// assert_eq!(RenderList::from_bytes(&bytes).unwrap(), list);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(10 + self.items.len() * 32);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&RENDER_LIST_FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.items.len() as u32).to_le_bytes());
+        for item in &self.items {
+            match item {
+                DisplayItem::Clear { color } => {
+                    out.push(TAG_CLEAR);
+                    put_color(&mut out, color);
+                }
+                DisplayItem::Rect { x, y, w, h, color } => {
+                    out.push(TAG_RECT);
+                    for v in [x, y, w, h] {
+                        out.extend_from_slice(&v.to_le_bytes());
+                    }
+                    put_color(&mut out, color);
+                }
+                DisplayItem::TextRun {
+                    x,
+                    y,
+                    text,
+                    size,
+                    font_family,
+                    color,
+                    max_width,
+                } => {
+                    out.push(TAG_TEXT_RUN);
+                    out.extend_from_slice(&x.to_le_bytes());
+                    out.extend_from_slice(&y.to_le_bytes());
+                    put_str(&mut out, text);
+                    out.extend_from_slice(&size.to_le_bytes());
+                    match font_family {
+                        Some(family) => {
+                            out.push(1);
+                            put_str(&mut out, family);
+                        }
+                        None => out.push(0),
+                    }
+                    put_color(&mut out, color);
+                    match max_width {
+                        Some(w) => {
+                            out.push(1);
+                            out.extend_from_slice(&w.to_le_bytes());
+                        }
+                        None => out.push(0),
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Decodes a list encoded with [`RenderList::to_bytes`]. The input is not trusted:
+    /// corrupt data fails to decode, but never panics or allocates more than the input
+    /// could hold.
+    ///
+    /// # Errors
+    /// Returns a [`RenderListDecodeError`] when `bytes` is not a complete encoded render list
+    /// of a supported version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<RenderList, RenderListDecodeError> {
+        let mut r = Reader { bytes };
+        if r.take(4).ok() != Some(MAGIC.as_slice()) {
+            return Err(RenderListDecodeError::BadMagic);
+        }
+        let version = u16::from_le_bytes(r.array()?);
+        if version != RENDER_LIST_FORMAT_VERSION {
+            return Err(RenderListDecodeError::UnsupportedVersion(version));
+        }
+        let count = u32::from_le_bytes(r.array()?) as usize;
+        if count > r.bytes.len() / MIN_ITEM_BYTES {
+            return Err(RenderListDecodeError::Truncated);
+        }
+
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            let item = match r.byte()? {
+                TAG_CLEAR => DisplayItem::Clear { color: r.color()? },
+                TAG_RECT => DisplayItem::Rect {
+                    x: r.f32()?,
+                    y: r.f32()?,
+                    w: r.f32()?,
+                    h: r.f32()?,
+                    color: r.color()?,
+                },
+                TAG_TEXT_RUN => DisplayItem::TextRun {
+                    x: r.f32()?,
+                    y: r.f32()?,
+                    text: r.string()?,
+                    size: r.f32()?,
+                    font_family: if r.flag()? { Some(r.string()?) } else { None },
+                    color: r.color()?,
+                    max_width: if r.flag()? { Some(r.f32()?) } else { None },
+                },
+                tag => return Err(RenderListDecodeError::UnknownItem(tag)),
+            };
+            items.push(item);
+        }
+        if !r.bytes.is_empty() {
+            return Err(RenderListDecodeError::TrailingBytes(r.bytes.len()));
+        }
+        Ok(RenderList { items })
+    }
+}
+
+fn put_color(out: &mut Vec<u8>, color: &Color) {
+    for v in [color.r, color.g, color.b, color.a] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Cursor over the bytes that are left to decode.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], RenderListDecodeError> {
+        if self.bytes.len() < n {
+            return Err(RenderListDecodeError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], RenderListDecodeError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn byte(&mut self) -> Result<u8, RenderListDecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn flag(&mut self) -> Result<bool, RenderListDecodeError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(RenderListDecodeError::InvalidFlag(other)),
+        }
+    }
+
+    fn f32(&mut self) -> Result<f32, RenderListDecodeError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn color(&mut self) -> Result<Color, RenderListDecodeError> {
+        Ok(Color::new(
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+            self.f32()?,
+        ))
+    }
+
+    fn string(&mut self) -> Result<String, RenderListDecodeError> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| RenderListDecodeError::InvalidUtf8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    fn sample() -> RenderList {
+        let mut list = RenderList::new();
+        list.add_command(DisplayItem::Clear {
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+        });
+        list.add_command(DisplayItem::Rect {
+            x: 10.0,
+            y: 20.5,
+            w: 100.0,
+            h: 50.0,
+            color: Color::from_u8(0, 128, 255, 255),
+        });
+        list.add_command(DisplayItem::TextRun {
+            x: 4.0,
+            y: 16.0,
+            text: "Grüße".to_string(),
+            size: 16.0,
+            font_family: Some("serif".to_string()),
+            color: Color::new(0.0, 0.0, 0.0, 1.0),
+            max_width: None,
+        });
+        list
+    }
+
+    #[test]
+    fn lists_survive_a_round_trip() {
+        let list = sample();
+        let bytes = list.to_bytes();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(RenderList::from_bytes(&bytes).unwrap(), list);
+        assert_eq!(
+            RenderList::from_bytes(&RenderList::new().to_bytes()).unwrap(),
+            RenderList::new()
+        );
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(
+            RenderList::from_bytes(&newer),
+            Err(RenderListDecodeError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            RenderList::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(RenderListDecodeError::Truncated)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            RenderList::from_bytes(&trailing),
+            Err(RenderListDecodeError::TrailingBytes(1))
+        );
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected_without_panicking() {
+        let bytes = sample().to_bytes();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x6f5b);
+        for _ in 0..5000 {
+            let mut input = bytes.clone();
+            match rng.random_range(0..3) {
+                0 => input.truncate(rng.random_range(0..bytes.len())),
+                1 => {
+                    for _ in 0..rng.random_range(1..4) {
+                        let i = rng.random_range(0..input.len());
+                        input[i] = rng.random();
+                    }
+                }
+                _ => {
+                    input = (0..rng.random_range(0..64)).map(|_| rng.random()).collect();
+                }
+            }
+            // Whatever comes out, it must not panic or allocate without bounds
+            let _ = RenderList::from_bytes(&input);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! How backends rasterize text.
+//!
+//! [`TextOptions`] come from the [`EngineConfig`](crate::EngineConfig) and reach the backends
+//! through the [`BrowsingContext`](crate::engine::BrowsingContext) they render. Backends apply
+//! what their text stack supports and ignore the rest.
+//!
+//! [`FontSettings`] are per zone instead: they pick the family and size of the text runs a
+//! context paints, following the zone's [`ZoneConfig`].
+use crate::zone::ZoneConfig;
+use crate::EngineConfig;
+
+/// How strongly glyph outlines are fitted to the pixel grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextHinting {
+    /// Outlines are drawn as designed. Sharpest at large sizes and high DPI.
+    None,
+    /// Outlines are fitted vertically only, which keeps the shapes of the font.
+    #[default]
+    Slight,
+    /// Outlines are fitted in both directions. Crispest at small sizes on low DPI screens.
+    Full,
+}
+
+/// Text rendering settings for a browsing context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextOptions {
+    /// Place glyphs at fractional pixel positions instead of rounding each one
+    pub subpixel_positioning: bool,
+    /// Hinting mode
+    pub hinting: TextHinting,
+    /// Round the origin of every text run to whole pixels, so text does not shimmer while a
+    /// pane is resized
+    pub pixel_snap: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            subpixel_positioning: true,
+            hinting: TextHinting::default(),
+            pixel_snap: true,
+        }
+    }
+}
+
+impl TextOptions {
+    /// Returns the text settings of an engine configuration.
+    pub fn from_config(config: &EngineConfig) -> Self {
+        Self {
+            subpixel_positioning: config.subpixel_text,
+            hinting: config.text_hinting,
+            pixel_snap: config.pixel_snap,
+        }
+    }
+
+    /// Returns `v` rounded to a whole pixel when pixel snapping is on.
+    ///
+    /// ```
+    /// use gosub_engine::render::TextOptions;
+    ///
+    /// let snapped = TextOptions { pixel_snap: true, ..Default::default() };
+    /// assert_eq!(snapped.snap(10.6), 11.0);
+    /// let free = TextOptions { pixel_snap: false, ..Default::default() };
+    /// assert_eq!(free.snap(10.6), 10.6);
+    /// ```
+    pub fn snap(&self, v: f32) -> f32 {
+        if self.pixel_snap {
+            v.round()
+        } else {
+            v
+        }
+    }
+}
+
+/// Default size of text, in CSS pixels, when the zone does not set one.
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// Fonts a browsing context uses for its text runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSettings {
+    /// Font family of text that does not ask for one. `None` leaves the choice to the backend.
+    pub family: Option<String>,
+    /// Size of text that does not ask for one, in CSS pixels
+    pub size: f32,
+    /// Smallest size text is painted at, in CSS pixels
+    pub minimum_size: f32,
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        Self {
+            family: None,
+            size: DEFAULT_FONT_SIZE,
+            minimum_size: 0.0,
+        }
+    }
+}
+
+impl FontSettings {
+    /// Returns the font settings of a zone configuration.
+    pub fn from_zone_config(config: &ZoneConfig) -> Self {
+        Self {
+            family: config.default_font_family.clone(),
+            size: config.default_font_size as f32,
+            minimum_size: config.minimum_font_size as f32,
+        }
+    }
+
+    /// Returns `size` raised to the minimum font size.
+    ///
+    /// ```
+    /// use gosub_engine::render::FontSettings;
+    ///
+    /// let fonts = FontSettings { minimum_size: 12.0, ..Default::default() };
+    /// assert_eq!(fonts.clamp(9.0), 12.0);
+    /// assert_eq!(fonts.clamp(20.0), 20.0);
+    /// ```
+    pub fn clamp(&self, size: f32) -> f32 {
+        size.max(self.minimum_size)
+    }
+}
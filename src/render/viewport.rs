@@ -3,12 +3,13 @@ use crate::render::backend::SurfaceSize;
 /// Viewport definition for rendering.
 ///
 /// A [`Viewport`] describes the rectangular region of a page that should be
-/// rendered. It is defined by its top-left corner `(x, y)` and its pixel
-/// `width` and `height`. The engine and backends use this to determine what
+/// rendered. `(x, y)` is the scroll offset: the point of the document, in document
+/// coordinates, that shows in the top-left corner. `width` and `height` are the size of
+/// the surface in device pixels. The engine and backends use this to determine what
 /// part of a [`Tab`](crate::tab::Tab) to paint and at what size.
 ///
-/// The coordinate system is engine-defined. Typically `(0, 0)` refers to the
-/// top-left corner of the root surface or window.
+/// Backends do not use the viewport directly. They paint with the [`RenderRequest`] of the
+/// browsing context, which adds the device scale.
 ///
 /// # Examples
 ///
@@ -49,17 +50,18 @@ use crate::render::backend::SurfaceSize;
 /// assert_eq!(size.width, 1280);
 /// ```
 #[derive(Clone, Eq, PartialEq, Copy)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
 pub struct Viewport {
-    /// Horizontal offset in pixels from the origin.
+    /// Horizontal scroll offset in document coordinates.
     pub x: i32,
 
-    /// Vertical offset in pixels from the origin.
+    /// Vertical scroll offset in document coordinates.
     pub y: i32,
 
-    /// Width in pixels.
+    /// Width in device pixels.
     pub width: u32,
 
-    /// Height in pixels.
+    /// Height in device pixels.
     pub height: u32,
 }
 
@@ -101,7 +103,7 @@ impl Viewport {
         self.height = height;
     }
 
-    /// Moves the viewport’s origin to `(x, y)` in pixels.
+    /// Scrolls the viewport so that the document point `(x, y)` shows in its top-left corner.
     pub fn translate(&mut self, x: i32, y: i32) {
         self.x = x;
         self.y = y;
@@ -126,3 +128,78 @@ impl Viewport {
         }
     }
 }
+
+/// What a backend paints: the render list of a browsing context is in document coordinates,
+/// and the request tells how they map onto the surface.
+///
+/// A document point `(x, y)` ends up at `((x - scroll_x) * device_scale, (y - scroll_y) *
+/// device_scale)` on the surface. Backends apply that mapping once, as a transform, instead
+/// of offsetting every item.
+///
+/// ```
+/// use gosub_engine::render::{RenderRequest, Viewport};
+///
+/// let request = RenderRequest::new(Viewport::new(0, 100, 800, 600), 2.0);
+/// assert_eq!(request.to_device(10.0, 110.0), (20.0, 20.0));
+/// assert_eq!(request.to_document(20.0, 20.0), (10.0, 110.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde_events", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderRequest {
+    /// Size of the surface in device pixels
+    pub size: SurfaceSize,
+    /// Horizontal scroll offset in document coordinates
+    pub scroll_x: f32,
+    /// Vertical scroll offset in document coordinates
+    pub scroll_y: f32,
+    /// Device pixels per document pixel, such as 2.0 on a HiDPI screen
+    pub device_scale: f32,
+}
+
+impl RenderRequest {
+    /// Creates the request for painting `viewport` at `device_scale`.
+    pub fn new(viewport: Viewport, device_scale: f32) -> Self {
+        Self {
+            size: viewport.as_size(),
+            scroll_x: viewport.x as f32,
+            scroll_y: viewport.y as f32,
+            device_scale,
+        }
+    }
+
+    /// Maps a document point onto the surface.
+    pub fn to_device(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            (x - self.scroll_x) * self.device_scale,
+            (y - self.scroll_y) * self.device_scale,
+        )
+    }
+
+    /// Maps a document rectangle `(x, y, width, height)` onto the surface.
+    pub fn to_device_rect(&self, x: f32, y: f32, width: f32, height: f32) -> (f32, f32, f32, f32) {
+        let (x, y) = self.to_device(x, y);
+        (x, y, width * self.device_scale, height * self.device_scale)
+    }
+
+    /// Maps a point of the surface, such as the position of a click, onto the document.
+    pub fn to_document(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x / self.device_scale + self.scroll_x,
+            y / self.device_scale + self.scroll_y,
+        )
+    }
+
+    /// Returns the transform from document coordinates to device pixels as the affine matrix
+    /// `[a, b, c, d, e, f]`, the layout that Cairo and kurbo use.
+    pub fn transform(&self) -> [f64; 6] {
+        let scale = self.device_scale as f64;
+        [
+            scale,
+            0.0,
+            0.0,
+            scale,
+            -self.scroll_x as f64 * scale,
+            -self.scroll_y as f64 * scale,
+        ]
+    }
+}
@@ -0,0 +1,431 @@
+//! Helpers for visual regression tests.
+//!
+//! [`HeadlessEngine`] runs an engine with a single tab on the
+//! [`SoftwareBackend`](crate::render::backends::software::SoftwareBackend), so frames come out
+//! the same on every machine and no window or GPU is needed. HTML is loaded from a string or a
+//! fixture file, and the engine only advances when the test calls [`HeadlessEngine::step`] (or
+//! [`HeadlessEngine::render`], which steps until a frame is painted).
+//!
+//! Frames are compared against golden PNG files with [`assert_golden`]. A missing golden is
+//! written on first use; set `GOSUB_UPDATE_GOLDENS=1` to rewrite goldens after an intended
+//! change. When a frame does not match, the actual frame and a diff image are saved next to the
+//! golden as `<name>.actual.png` and `<name>.diff.png`.
+//!
+//! Only available with the `testing` feature.
+//!
+//! ```
+//! use gosub_engine::testing::{assert_golden, HeadlessEngine, Tolerance};
+//!
+//! let mut headless = HeadlessEngine::new(320, 240).unwrap();
+//! headless.load_html("<p>Hello golden world</p>");
+//! let frame = headless.render().unwrap();
+//! assert_eq!((frame.width, frame.height), (320, 240));
+//!
+//! # let dir = std::env::temp_dir().join(format!("gosub-golden-doc-{}", std::process::id()));
+//! # let golden = dir.join("hello.png");
+//! // e.g. "tests/goldens/hello.png"
+//! assert_golden(&frame, &golden, Tolerance::EXACT);
+//! # std::fs::remove_dir_all(dir).unwrap();
+//! ```
+use crate::render::backend::{ExternalHandle, PixelFormat, RgbaImage};
+use crate::render::backends::software::SoftwareBackend;
+use crate::render::{DefaultCompositor, Viewport};
+use crate::tab::{TabId, TabState};
+use crate::zone::ZoneId;
+use crate::{EngineConfig, EngineError, GosubEngine, TickResult};
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes [`compare_golden`] rewrite goldens instead of comparing
+pub const UPDATE_GOLDENS_ENV: &str = "GOSUB_UPDATE_GOLDENS";
+
+/// Maximum number of ticks [`HeadlessEngine::render`] waits for a frame
+const MAX_RENDER_TICKS: usize = 64;
+
+/// An engine with one tab that renders in software and only advances when stepped.
+pub struct HeadlessEngine {
+    engine: GosubEngine,
+    compositor: DefaultCompositor,
+    zone_id: ZoneId,
+    tab_id: TabId,
+}
+
+impl HeadlessEngine {
+    /// Creates an engine with the default configuration and one tab of `width` x `height`.
+    pub fn new(width: u32, height: u32) -> Result<Self, EngineError> {
+        Self::with_config(EngineConfig::default(), width, height)
+    }
+
+    /// Creates an engine with `config` and one tab of `width` x `height`.
+    pub fn with_config(config: EngineConfig, width: u32, height: u32) -> Result<Self, EngineError> {
+        let backend =
+            SoftwareBackend::new().map_err(|e| EngineError::RendererError(e.to_string()))?;
+        let mut engine = GosubEngine::new(Some(config), Box::new(backend));
+        let zone_id = engine.zone_builder().create()?;
+        let tab_id = engine.open_tab_in_zone(zone_id, Viewport::new(0, 0, width, height))?;
+
+        Ok(Self {
+            engine,
+            compositor: DefaultCompositor::new(|| {}),
+            zone_id,
+            tab_id,
+        })
+    }
+
+    /// Returns the engine, for sending events and commands or opening more tabs.
+    pub fn engine(&mut self) -> &mut GosubEngine {
+        &mut self.engine
+    }
+
+    /// Returns the zone of the tab.
+    pub fn zone_id(&self) -> ZoneId {
+        self.zone_id
+    }
+
+    /// Returns the tab that is rendered.
+    pub fn tab_id(&self) -> TabId {
+        self.tab_id
+    }
+
+    /// Replaces the document of the tab with `html`, without going through the network. The
+    /// document is painted on the following steps.
+    pub fn load_html(&mut self, html: &str) {
+        let tab = self
+            .engine
+            .get_tab(self.tab_id)
+            .expect("headless tab is gone");
+        let mut tab = tab.lock().unwrap();
+        tab.context.set_raw_html(html);
+        tab.state = TabState::Loaded;
+    }
+
+    /// Loads the HTML file at `path` like [`HeadlessEngine::load_html`]. Relative paths are
+    /// resolved against the working directory, which is the package root under `cargo test`.
+    pub fn load_fixture(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let html = std::fs::read_to_string(path)
+            .with_context(|| format!("reading fixture {}", path.display()))?;
+        self.load_html(&html);
+        Ok(())
+    }
+
+    /// Advances the engine by one tick and returns the result for the tab.
+    pub fn step(&mut self) -> TickResult {
+        let mut results = self.engine.tick(&mut self.compositor);
+        results.remove(&self.tab_id).unwrap_or_default()
+    }
+
+    /// Advances the engine by `frames` ticks.
+    pub fn step_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Steps until the tab has painted its current content and is idle, and returns the frame.
+    ///
+    /// # Errors
+    /// Fails when no frame was painted within a bounded number of ticks, for instance because
+    /// the tab is still waiting for the network.
+    pub fn render(&mut self) -> Result<RgbaImage> {
+        let mut painted = false;
+        for _ in 0..MAX_RENDER_TICKS {
+            let result = self.step();
+            painted |= result.needs_redraw;
+            if painted && matches!(result.status, TabState::Idle) {
+                return self
+                    .frame()
+                    .ok_or_else(|| anyhow!("tab painted without a frame"));
+            }
+        }
+        bail!("tab did not paint within {MAX_RENDER_TICKS} ticks")
+    }
+
+    /// Returns the last frame the tab painted, if any.
+    pub fn frame(&self) -> Option<RgbaImage> {
+        match self.compositor.frames.get(&self.tab_id)? {
+            ExternalHandle::CpuPixelsOwned {
+                width,
+                height,
+                stride,
+                pixels,
+                format,
+            } => Some(RgbaImage::from_raw(
+                pixels.clone(),
+                *width,
+                *height,
+                *stride,
+                *format,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// How far a frame may deviate from its golden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tolerance {
+    /// Largest difference per color channel that still counts as equal
+    pub channel: u8,
+    /// Number of pixels that may differ by more than `channel`
+    pub pixels: usize,
+}
+
+impl Tolerance {
+    /// Every pixel has to match exactly.
+    pub const EXACT: Tolerance = Tolerance {
+        channel: 0,
+        pixels: 0,
+    };
+
+    /// Allows each channel to be off by `channel`, for instance for blending rounding.
+    pub fn channel(channel: u8) -> Self {
+        Self { channel, pixels: 0 }
+    }
+
+    /// Additionally allows `pixels` pixels to differ by any amount.
+    pub fn with_pixels(mut self, pixels: usize) -> Self {
+        self.pixels = pixels;
+        self
+    }
+}
+
+/// Result of comparing two images of the same size.
+#[derive(Debug)]
+pub struct ImageDiff {
+    /// Pixels where some channel differs by more than the tolerance
+    pub differing_pixels: usize,
+    /// Largest difference in any channel of any pixel
+    pub max_channel_delta: u8,
+    /// Differing pixels in red on a dimmed copy of the expected image
+    pub diff: RgbaImage,
+}
+
+/// Compares `actual` with `expected`. Pixels that differ by at most `tolerance.channel` in
+/// every channel are considered equal.
+///
+/// # Errors
+/// Fails when the sizes differ or an image is not in [`PixelFormat::Rgba8`].
+pub fn diff_images(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    tolerance: Tolerance,
+) -> Result<ImageDiff> {
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        bail!(
+            "image is {}x{}, expected {}x{}",
+            actual.width,
+            actual.height,
+            expected.width,
+            expected.height
+        );
+    }
+    let actual = rows(actual)?;
+    let expected_rows = rows(expected)?;
+
+    let mut differing_pixels = 0;
+    let mut max_channel_delta = 0;
+    let mut diff = Vec::with_capacity((expected.width * expected.height * 4) as usize);
+    for (a_row, e_row) in actual.iter().zip(expected_rows.iter()) {
+        for (a, e) in a_row.chunks_exact(4).zip(e_row.chunks_exact(4)) {
+            let delta = a
+                .iter()
+                .zip(e)
+                .map(|(a, e)| a.abs_diff(*e))
+                .max()
+                .unwrap_or(0);
+            max_channel_delta = max_channel_delta.max(delta);
+            if delta > tolerance.channel {
+                differing_pixels += 1;
+                diff.extend_from_slice(&[255, 0, 0, 255]);
+            } else {
+                diff.extend(e[..3].iter().map(|c| c / 4));
+                diff.push(255);
+            }
+        }
+    }
+
+    Ok(ImageDiff {
+        differing_pixels,
+        max_channel_delta,
+        diff: RgbaImage::from_raw(
+            diff,
+            expected.width,
+            expected.height,
+            expected.width * 4,
+            PixelFormat::Rgba8,
+        ),
+    })
+}
+
+/// Compares `image` with the golden PNG at `golden`.
+///
+/// The golden is written instead when it does not exist yet, or when [`UPDATE_GOLDENS_ENV`] is
+/// set to anything but `0`; the returned diff is then empty. On a mismatch, the image and the
+/// diff are written next to the golden to make the failure easy to inspect.
+///
+/// # Errors
+/// Fails when the image does not match within `tolerance`, or when a file cannot be read or
+/// written.
+pub fn compare_golden(image: &RgbaImage, golden: &Path, tolerance: Tolerance) -> Result<ImageDiff> {
+    let update = std::env::var(UPDATE_GOLDENS_ENV).is_ok_and(|v| v != "0");
+    if update || !golden.exists() {
+        save_png(image, golden)?;
+        return diff_images(image, image, Tolerance::EXACT);
+    }
+
+    let expected = load_png(golden)?;
+    let diff = diff_images(image, &expected, tolerance)
+        .with_context(|| format!("comparing with {}", golden.display()))?;
+    if diff.differing_pixels > tolerance.pixels {
+        let actual_path = sibling(golden, "actual");
+        let diff_path = sibling(golden, "diff");
+        save_png(image, &actual_path)?;
+        save_png(&diff.diff, &diff_path)?;
+        bail!(
+            "{} pixels differ from {} (max channel delta {}, {} allowed); see {} and {}",
+            diff.differing_pixels,
+            golden.display(),
+            diff.max_channel_delta,
+            tolerance.pixels,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+    Ok(diff)
+}
+
+/// Like [`compare_golden`], but panics on a mismatch.
+#[track_caller]
+pub fn assert_golden(image: &RgbaImage, golden: impl AsRef<Path>, tolerance: Tolerance) {
+    if let Err(e) = compare_golden(image, golden.as_ref(), tolerance) {
+        panic!("golden mismatch: {e:#}");
+    }
+}
+
+/// Writes an RGBA8 image as PNG, creating parent directories as needed.
+pub fn save_png(image: &RgbaImage, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+    let file = File::create(path).with_context(|| format!("creating {}", path.display()))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rows(image)?.concat())?;
+    Ok(())
+}
+
+/// Reads an 8-bit RGB or RGBA PNG into an RGBA8 image.
+pub fn load_png(path: &Path) -> Result<RgbaImage> {
+    let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut reader = png::Decoder::new(file).read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+
+    let pixels = match (info.color_type, info.bit_depth) {
+        (png::ColorType::Rgba, png::BitDepth::Eight) => buf,
+        (png::ColorType::Rgb, png::BitDepth::Eight) => buf
+            .chunks_exact(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect(),
+        (color, depth) => bail!(
+            "{}: unsupported PNG format {color:?} {depth:?}",
+            path.display()
+        ),
+    };
+    Ok(RgbaImage::from_raw(
+        pixels,
+        info.width,
+        info.height,
+        info.width * 4,
+        PixelFormat::Rgba8,
+    ))
+}
+
+/// Splits an RGBA8 image into rows without stride padding.
+fn rows(image: &RgbaImage) -> Result<Vec<&[u8]>> {
+    if !matches!(image.format, PixelFormat::Rgba8) {
+        bail!("only RGBA8 images are supported");
+    }
+    let row_len = (image.width * 4) as usize;
+    Ok((0..image.height as usize)
+        .map(|y| {
+            let start = y * image.stride as usize;
+            &image.pixels[start..start + row_len]
+        })
+        .collect())
+}
+
+/// Returns `dir/name.<suffix>.png` for `dir/name.png`.
+fn sibling(golden: &Path, suffix: &str) -> PathBuf {
+    let stem = golden.file_stem().unwrap_or_default().to_string_lossy();
+    golden.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gosub-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn rendering_is_deterministic_and_reflects_content() {
+        let dir = temp_dir("fixtures");
+        std::fs::create_dir_all(&dir).unwrap();
+        let fixture = dir.join("golden.html");
+        std::fs::write(&fixture, "<h1>Golden</h1>").unwrap();
+
+        let mut headless = HeadlessEngine::new(200, 100).unwrap();
+        headless.load_html("<h1>Golden</h1>");
+        let first = headless.render().unwrap();
+
+        let mut again = HeadlessEngine::new(200, 100).unwrap();
+        again.load_fixture(&fixture).unwrap();
+        let second = again.render().unwrap();
+        let diff = diff_images(&first, &second, Tolerance::EXACT).unwrap();
+        assert_eq!(diff.differing_pixels, 0);
+
+        headless.load_html("<h1>Golden</h1>\n<p>and a second line</p>");
+        let changed = headless.render().unwrap();
+        let diff = diff_images(&changed, &first, Tolerance::EXACT).unwrap();
+        assert!(diff.differing_pixels > 0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn goldens_are_written_once_and_then_compared() {
+        let dir = temp_dir("goldens");
+        let golden = dir.join("page.png");
+
+        let mut headless = HeadlessEngine::new(64, 48).unwrap();
+        headless.load_html("abc");
+        let frame = headless.render().unwrap();
+        compare_golden(&frame, &golden, Tolerance::EXACT).unwrap();
+        assert!(golden.exists());
+        assert_eq!(load_png(&golden).unwrap().pixels, frame.pixels);
+
+        headless.load_html("xyz xyz");
+        let other = headless.render().unwrap();
+        let err = compare_golden(&other, &golden, Tolerance::channel(8)).unwrap_err();
+        assert!(err.to_string().contains("pixels differ"));
+        assert!(dir.join("page.actual.png").exists());
+        assert!(dir.join("page.diff.png").exists());
+
+        // Enough slack hides the difference
+        compare_golden(&other, &golden, Tolerance::EXACT.with_pixels(64 * 48)).unwrap();
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}